@@ -26,13 +26,13 @@ fn bench_set_direct(c: &mut Criterion) {
 
         group.bench_function(format!("key_len_{}", key_len), |b| {
             let mut executor = CommandExecutor::new();
-            b.iter(|| executor.set_direct(black_box(&key), black_box(&value)))
+            b.iter(|| executor.set_direct(black_box(key.as_bytes()), black_box(&value)))
         });
     }
 
     // Benchmark with various value sizes
     for value_len in [64, 256, 1024] {
-        let key = "benchmark_key";
+        let key = b"benchmark_key";
         let value = vec![b'x'; value_len];
 
         group.bench_function(format!("value_len_{}", value_len), |b| {
@@ -54,17 +54,17 @@ fn bench_get_direct(c: &mut Criterion) {
     for i in 0..100 {
         let key = format!("key:{}", i);
         let value = format!("value:{}", i);
-        executor.set_direct(&key, value.as_bytes());
+        executor.set_direct(key.as_bytes(), value.as_bytes());
     }
 
     // Benchmark existing key (hit)
     group.bench_function("cache_hit", |b| {
-        b.iter(|| executor.get_direct(black_box("key:50")))
+        b.iter(|| executor.get_direct(black_box(b"key:50")))
     });
 
     // Benchmark missing key (miss)
     group.bench_function("cache_miss", |b| {
-        b.iter(|| executor.get_direct(black_box("nonexistent_key")))
+        b.iter(|| executor.get_direct(black_box(b"nonexistent_key")))
     });
 
     group.finish();
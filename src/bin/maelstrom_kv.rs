@@ -85,7 +85,7 @@ fn main() -> io::Result<()> {
             },
             "read" => {
                 let key = value_to_string(&msg.body.key);
-                let cmd = Command::Get(key);
+                let cmd = Command::Get(key.into());
                 let result = executor.execute(&cmd);
 
                 match result {
@@ -157,7 +157,7 @@ fn main() -> io::Result<()> {
                 let from_value = value_to_string(&msg.body.from);
                 let to_value = value_to_string(&msg.body.to);
 
-                let get_cmd = Command::Get(key.clone());
+                let get_cmd = Command::Get(key.clone().into());
                 let current = executor.execute(&get_cmd);
 
                 match current {
@@ -138,7 +138,7 @@ fn node_id_to_replica_id(node_id: &str) -> u64 {
 
 fn delta_to_json(delta: &ReplicationDelta) -> DeltaJson {
     DeltaJson {
-        key: delta.key.clone(),
+        key: delta.key.to_string(),
         value: delta
             .value
             .get()
@@ -184,7 +184,7 @@ fn json_to_delta(json: &DeltaJson) -> ReplicationDelta {
 
     replicated_value.expiry_ms = json.expiry_ms;
 
-    ReplicationDelta::new(json.key.clone(), replicated_value, replica_id)
+    ReplicationDelta::new(SDS::from(json.key.as_str()), replicated_value, replica_id)
 }
 
 fn main() -> io::Result<()> {
@@ -233,7 +233,7 @@ fn main() -> io::Result<()> {
             "read" => {
                 let state = node_state.as_mut().expect("Node not initialized");
                 let key = value_to_string(&msg.body.key);
-                let cmd = Command::Get(key);
+                let cmd = Command::Get(key.into());
                 let result = state.execute(cmd);
 
                 match result {
@@ -344,7 +344,7 @@ fn main() -> io::Result<()> {
                 let from_value = value_to_string(&msg.body.from);
                 let to_value = value_to_string(&msg.body.to);
 
-                let get_cmd = Command::Get(key.clone());
+                let get_cmd = Command::Get(key.as_str().into());
                 let current = state.execute(get_cmd);
 
                 match current {
@@ -50,7 +50,7 @@ fn main() {
     let start = Instant::now();
     for i in 0..iterations {
         let key = format!("key:{}", i % 10000);
-        executor.execute(&Command::Get(key));
+        executor.execute(&Command::Get(key.into()));
     }
     let get_duration = start.elapsed();
     println!(
@@ -69,7 +69,7 @@ fn main() {
     let start = Instant::now();
     for i in 0..iterations {
         let key = format!("counter:{}", i % 1000);
-        executor.execute(&Command::Incr(key));
+        executor.execute(&Command::Incr(key.into()));
     }
     let incr_duration = start.elapsed();
     println!(
@@ -85,7 +85,7 @@ fn main() {
         let key = format!("hash:{}", i % 1000);
         let field = SDS::from_str(&format!("field:{}", i % 100));
         let value = SDS::from_str("hash_value_data");
-        executor.execute(&Command::HSet(key, vec![(field, value)]));
+        executor.execute(&Command::HSet(key.into(), vec![(field, value)]));
     }
     let hset_duration = start.elapsed();
     println!(
@@ -100,7 +100,7 @@ fn main() {
     for i in 0..iterations {
         let key = format!("hash:{}", i % 1000);
         let field = SDS::from_str(&format!("field:{}", i % 100));
-        executor.execute(&Command::HGet(key, field));
+        executor.execute(&Command::HGet(key.into(), field));
     }
     let hget_duration = start.elapsed();
     println!(
@@ -113,13 +113,13 @@ fn main() {
     // Profile LPUSH operations
     for i in 0..100 {
         let key = format!("list:{}", i);
-        executor.execute(&Command::LPush(key, vec![SDS::from_str("init")]));
+        executor.execute(&Command::LPush(key.into(), vec![SDS::from_str("init")]));
     }
     let start = Instant::now();
     for i in 0..iterations {
         let key = format!("list:{}", i % 100);
         let value = SDS::from_str("list_item_value");
-        executor.execute(&Command::LPush(key, vec![value]));
+        executor.execute(&Command::LPush(key.into(), vec![value]));
     }
     let lpush_duration = start.elapsed();
     println!(
@@ -136,7 +136,7 @@ fn main() {
         let member = SDS::from_str(&format!("member:{}", i % 1000));
         let score = (i % 10000) as f64;
         executor.execute(&Command::ZAdd {
-            key,
+            key: key.into(),
             pairs: vec![(score, member)],
             nx: false,
             xx: false,
@@ -18,6 +18,13 @@
 //! | TLS_CA_PATH | - | Path to CA certificate for client verification (optional) |
 //! | TLS_REQUIRE_CLIENT_CERT | false | Require client certificates (mutual TLS) |
 //!
+//! ## Cluster Mode
+//!
+//! | Variable | Default | Description |
+//! |----------|---------|-------------|
+//! | REDIS_CLUSTER_NODES | - | Comma-separated `host:port` of every node, same order on all nodes |
+//! | REDIS_CLUSTER_MYSELF | - | `host:port` of this node (must appear in REDIS_CLUSTER_NODES) |
+//!
 //! ## ACL Configuration (requires `acl` feature)
 //!
 //! | Variable | Default | Description |
@@ -14,6 +14,7 @@
 //! | REDIS_S3_BUCKET | - | S3 bucket name |
 //! | REDIS_S3_PREFIX | redis-stream | S3 key prefix |
 //! | REDIS_S3_ENDPOINT | - | MinIO endpoint URL |
+//! | REDIS_LATENCY_MONITOR_THRESHOLD | 0 | LATENCY event threshold in ms, 0 = off |
 //! | AWS_ACCESS_KEY_ID | - | S3 credentials |
 //! | AWS_SECRET_ACCESS_KEY | - | S3 credentials |
 //! | AWS_REGION | us-east-1 | S3 region |
@@ -37,7 +38,7 @@ static GLOBAL: Jemalloc = Jemalloc;
 use bytes::{BufMut, BytesMut};
 use redis_sim::observability::{init_tracing, shutdown, DatadogConfig};
 use redis_sim::production::ReplicatedShardedState;
-use redis_sim::redis::{Command, RespCodec, RespValue};
+use redis_sim::redis::{Command, RespCodec, RespParser, RespValue};
 use redis_sim::replication::{ConsistencyLevel, ReplicationConfig};
 use redis_sim::streaming::{
     create_integration, ObjectStoreType, StreamingConfig, StreamingIntegrationTrait, WorkerHandles,
@@ -54,6 +55,7 @@ const DEFAULT_PORT: u16 = 6379;
 const DEFAULT_REPLICA_ID: u64 = 1;
 const DEFAULT_DATA_PATH: &str = "/data";
 const DEFAULT_S3_PREFIX: &str = "redis-stream";
+#[cfg(feature = "s3")]
 const DEFAULT_S3_REGION: &str = "us-east-1";
 
 /// Server configuration from environment variables
@@ -61,6 +63,7 @@ struct Config {
     port: u16,
     store_type: String,
     data_path: PathBuf,
+    latency_monitor_threshold: u64,
     #[cfg(feature = "s3")]
     s3_bucket: Option<String>,
     #[cfg(feature = "s3")]
@@ -84,6 +87,10 @@ impl Config {
             data_path: std::env::var("REDIS_DATA_PATH")
                 .map(PathBuf::from)
                 .unwrap_or_else(|_| PathBuf::from(DEFAULT_DATA_PATH)),
+            latency_monitor_threshold: std::env::var("REDIS_LATENCY_MONITOR_THRESHOLD")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
             #[cfg(feature = "s3")]
             s3_bucket: std::env::var("REDIS_S3_BUCKET").ok(),
             #[cfg(feature = "s3")]
@@ -193,6 +200,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     // Create state
     let mut state = ReplicatedShardedState::new(repl_config);
+    state
+        .latency()
+        .set_threshold(config.latency_monitor_threshold);
 
     // Ensure persistence directory exists for localfs
     if config.store_type == "localfs" {
@@ -200,7 +210,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     }
 
     // Create integration and perform recovery
-    let integration = create_integration(streaming_config, DEFAULT_REPLICA_ID).await?;
+    let mut integration = create_integration(streaming_config, DEFAULT_REPLICA_ID).await?;
+    integration.set_latency_monitor(state.latency().clone());
 
     info!("Checking for existing data to recover...");
     let stats = integration.recover(&state).await?;
@@ -400,6 +411,7 @@ fn encode_resp_into(value: &RespValue, buf: &mut BytesMut) {
                 encode_resp_into(elem, buf);
             }
         }
+        other => buf.extend_from_slice(&RespParser::encode(other)),
     }
 }
 
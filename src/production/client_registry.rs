@@ -0,0 +1,648 @@
+//! Connected clients: the shared registry behind the CLIENT command family
+//!
+//! Every `OptimizedConnectionHandler` registers itself on connect and
+//! publishes what CLIENT LIST reports (name, user, database, subscriptions,
+//! buffer sizes, last command) into its own `ClientHandle`. Only the owning
+//! connection writes a handle, so the per-command cost is an uncontended lock.
+//!
+//! CLIENT KILL marks handles as killed and wakes their connections, which
+//! close once the current command has been answered. CLIENT PAUSE is a
+//! registry-wide deadline that connections wait on before running a command.
+//!
+//! MONITOR connections register their push channel here; every connection
+//! feeds them the commands it runs. Connections check `has_monitors` first,
+//! so nothing is formatted while no monitor is attached.
+
+use super::pubsub::{PushSender, SubscriberId};
+use crate::redis::{ClientKillFilter, ClientPauseMode, ClientType, Command, RespValue};
+use parking_lot::{Mutex, RwLock};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{watch, Notify};
+
+/// Clients share their identity with Pub/Sub subscribers (CLIENT ID)
+pub type ClientId = SubscriberId;
+
+/// What a connection publishes about itself for CLIENT LIST / CLIENT INFO
+#[derive(Debug, Clone)]
+pub struct ClientStats {
+    pub name: Option<String>,
+    /// Authenticated ACL user, if any
+    pub user: Option<String>,
+    pub db: usize,
+    /// Name of the last command run, None before the first one
+    pub last_command: Option<&'static str>,
+    pub last_interaction: Instant,
+    pub sub: usize,
+    pub psub: usize,
+    pub ssub: usize,
+    /// Commands queued since MULTI, None outside a transaction
+    pub multi: Option<usize>,
+    pub tracking: bool,
+    pub bcast: bool,
+    /// CLIENT TRACKING REDIRECT target
+    pub redirect: Option<ClientId>,
+    pub no_evict: bool,
+    /// Receiving the MONITOR feed
+    pub monitor: bool,
+    pub protocol: u32,
+    /// Unparsed bytes in the query buffer and its spare capacity
+    pub query_buffer: usize,
+    pub query_buffer_free: usize,
+    /// Bytes of replies not yet written to the socket
+    pub output_buffer: usize,
+}
+
+impl ClientStats {
+    fn new(now: Instant) -> Self {
+        ClientStats {
+            name: None,
+            user: None,
+            db: 0,
+            last_command: None,
+            last_interaction: now,
+            sub: 0,
+            psub: 0,
+            ssub: 0,
+            multi: None,
+            tracking: false,
+            bcast: false,
+            redirect: None,
+            no_evict: false,
+            monitor: false,
+            protocol: 2,
+            query_buffer: 0,
+            query_buffer_free: 0,
+            output_buffer: 0,
+        }
+    }
+
+    /// RESP2 subscribers are pubsub clients; everything else is normal
+    fn client_type(&self) -> ClientType {
+        if self.sub + self.psub + self.ssub > 0 {
+            ClientType::PubSub
+        } else {
+            ClientType::Normal
+        }
+    }
+
+    /// CLIENT LIST flags (N when none apply)
+    fn flags(&self) -> String {
+        let mut flags = String::new();
+        if self.monitor {
+            flags.push('O');
+        }
+        if self.client_type() == ClientType::PubSub {
+            flags.push('P');
+        }
+        if self.multi.is_some() {
+            flags.push('x');
+        }
+        if self.tracking {
+            flags.push('t');
+        }
+        if self.bcast {
+            flags.push('B');
+        }
+        if self.no_evict {
+            flags.push('e');
+        }
+        if flags.is_empty() {
+            flags.push('N');
+        }
+        flags
+    }
+}
+
+/// A registered connection
+pub struct ClientHandle {
+    id: ClientId,
+    addr: String,
+    connected_at: Instant,
+    stats: Mutex<ClientStats>,
+    killed: AtomicBool,
+    kill_signal: Notify,
+}
+
+impl ClientHandle {
+    #[inline]
+    pub fn id(&self) -> ClientId {
+        self.id
+    }
+
+    /// Update the published stats (called by the owning connection only)
+    #[inline]
+    pub fn update(&self, f: impl FnOnce(&mut ClientStats)) {
+        f(&mut self.stats.lock());
+    }
+
+    /// Record that `command` ran just now
+    #[inline]
+    pub fn touch(&self, command: &'static str) {
+        let mut stats = self.stats.lock();
+        stats.last_command = Some(command);
+        stats.last_interaction = Instant::now();
+    }
+
+    pub fn stats(&self) -> ClientStats {
+        self.stats.lock().clone()
+    }
+
+    /// True once CLIENT KILL matched this client
+    #[inline]
+    pub fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Acquire)
+    }
+
+    /// Resolves when CLIENT KILL matches this client
+    pub async fn killed(&self) {
+        while !self.is_killed() {
+            self.kill_signal.notified().await;
+        }
+    }
+
+    fn kill(&self) {
+        self.killed.store(true, Ordering::Release);
+        // A stored permit wakes the connection even if it is not waiting yet
+        self.kill_signal.notify_one();
+    }
+
+    /// One CLIENT LIST line (without the trailing newline)
+    pub fn info_line(&self) -> String {
+        let stats = self.stats();
+        let now = Instant::now();
+        let mut line = String::with_capacity(256);
+        let _ = write!(
+            line,
+            "id={} addr={} name={} age={} idle={} flags={} db={} sub={} psub={} ssub={} multi={} qbuf={} qbuf-free={} obl={} cmd={} user={} redir={} resp={}",
+            self.id,
+            self.addr,
+            stats.name.as_deref().unwrap_or(""),
+            now.duration_since(self.connected_at).as_secs(),
+            now.duration_since(stats.last_interaction).as_secs(),
+            stats.flags(),
+            stats.db,
+            stats.sub,
+            stats.psub,
+            stats.ssub,
+            stats.multi.map_or(-1, |queued| queued as i64),
+            stats.query_buffer,
+            stats.query_buffer_free,
+            stats.output_buffer,
+            stats
+                .last_command
+                .map_or("NULL".to_string(), str::to_lowercase),
+            stats.user.as_deref().unwrap_or(""),
+            stats.redirect.map_or(-1, |id| id as i64),
+            stats.protocol,
+        );
+        line
+    }
+
+    fn matches(&self, filter: &ClientKillFilter) -> bool {
+        let stats = self.stats.lock();
+        filter.id.is_none_or(|id| id == self.id)
+            && filter.addr.as_ref().is_none_or(|addr| *addr == self.addr)
+            && filter
+                .user
+                .as_ref()
+                .is_none_or(|user| stats.user.as_ref() == Some(user))
+            && filter
+                .client_type
+                .is_none_or(|client_type| client_type == stats.client_type())
+    }
+}
+
+/// CLIENT PAUSE in effect until `until`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientPause {
+    pub until: Instant,
+    pub mode: ClientPauseMode,
+}
+
+/// Every connected client, shared by all connection handlers
+pub struct ClientRegistry {
+    clients: RwLock<BTreeMap<ClientId, Arc<ClientHandle>>>,
+    pause: watch::Sender<Option<ClientPause>>,
+    /// Mirrors `pause.is_some()` so the hot path can skip the channel
+    paused: AtomicBool,
+    /// Push channels of MONITOR connections
+    monitors: RwLock<BTreeMap<ClientId, PushSender>>,
+    /// Mirrors `!monitors.is_empty()` so the hot path can skip the lock
+    monitoring: AtomicBool,
+}
+
+impl Default for ClientRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientRegistry {
+    pub fn new() -> Self {
+        ClientRegistry {
+            clients: RwLock::new(BTreeMap::new()),
+            pause: watch::Sender::new(None),
+            paused: AtomicBool::new(false),
+            monitors: RwLock::new(BTreeMap::new()),
+            monitoring: AtomicBool::new(false),
+        }
+    }
+
+    /// Register a new connection from `addr`
+    pub fn register(&self, id: ClientId, addr: String) -> Arc<ClientHandle> {
+        let now = Instant::now();
+        let handle = Arc::new(ClientHandle {
+            id,
+            addr,
+            connected_at: now,
+            stats: Mutex::new(ClientStats::new(now)),
+            killed: AtomicBool::new(false),
+            kill_signal: Notify::new(),
+        });
+        let previous = self.clients.write().insert(id, handle.clone());
+        debug_assert!(previous.is_none(), "client id {} registered twice", id);
+        handle
+    }
+
+    pub fn unregister(&self, id: ClientId) {
+        self.clients.write().remove(&id);
+        self.remove_monitor(id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.read().is_empty()
+    }
+
+    pub fn get(&self, id: ClientId) -> Option<Arc<ClientHandle>> {
+        self.clients.read().get(&id).cloned()
+    }
+
+    /// CLIENT LIST: one line per matching client, in id order
+    pub fn list(&self, client_type: Option<ClientType>, ids: &[ClientId]) -> String {
+        let clients: Vec<Arc<ClientHandle>> = self.clients.read().values().cloned().collect();
+        let mut out = String::new();
+        for client in clients {
+            if !ids.is_empty() && !ids.contains(&client.id) {
+                continue;
+            }
+            if client_type.is_some_and(|t| t != client.stats.lock().client_type()) {
+                continue;
+            }
+            out.push_str(&client.info_line());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// CLIENT KILL: close every client matching `filter`; returns how many
+    pub fn kill(&self, filter: &ClientKillFilter, caller: ClientId) -> usize {
+        let clients: Vec<Arc<ClientHandle>> = self.clients.read().values().cloned().collect();
+        let mut killed = 0;
+        for client in clients {
+            if filter.skipme && client.id == caller {
+                continue;
+            }
+            if client.matches(filter) {
+                client.kill();
+                killed += 1;
+            }
+        }
+        killed
+    }
+
+    /// CLIENT PAUSE: extends any pause in effect, keeping the stricter mode
+    pub fn pause(&self, timeout: Duration, mode: ClientPauseMode) {
+        let until = Instant::now() + timeout;
+        self.pause.send_modify(|pause| {
+            *pause = Some(match *pause {
+                Some(current) => ClientPause {
+                    until: current.until.max(until),
+                    mode: current.mode.max(mode),
+                },
+                None => ClientPause { until, mode },
+            });
+        });
+        self.paused.store(true, Ordering::Release);
+    }
+
+    /// CLIENT UNPAUSE: wake every waiting client
+    pub fn unpause(&self) {
+        self.paused.store(false, Ordering::Release);
+        self.pause.send_replace(None);
+    }
+
+    /// True while a pause may be in effect (cheap check for the hot path)
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// Wait until a pause in effect no longer holds back a command that
+    /// `writes` (or not). Returns at once when nothing is paused.
+    pub async fn wait_while_paused(&self, writes: bool) {
+        let mut pause_rx = self.pause.subscribe();
+        loop {
+            let Some(pause) = *pause_rx.borrow_and_update() else {
+                return;
+            };
+            if Instant::now() >= pause.until {
+                self.pause.send_if_modified(|current| {
+                    if *current == Some(pause) {
+                        self.paused.store(false, Ordering::Release);
+                        *current = None;
+                        true
+                    } else {
+                        false
+                    }
+                });
+                continue;
+            }
+            if pause.mode == ClientPauseMode::Write && !writes {
+                return;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep_until(pause.until.into()) => {}
+                _ = pause_rx.changed() => {}
+            }
+        }
+    }
+
+    /// MONITOR: send every command from now on to `push`
+    pub fn add_monitor(&self, id: ClientId, push: PushSender) {
+        let mut monitors = self.monitors.write();
+        monitors.insert(id, push);
+        self.monitoring.store(true, Ordering::Release);
+    }
+
+    fn remove_monitor(&self, id: ClientId) {
+        let mut monitors = self.monitors.write();
+        if monitors.remove(&id).is_some() {
+            self.monitoring
+                .store(!monitors.is_empty(), Ordering::Release);
+        }
+    }
+
+    /// True while a MONITOR connection is attached (cheap check for the hot path)
+    #[inline]
+    pub fn has_monitors(&self) -> bool {
+        self.monitoring.load(Ordering::Acquire)
+    }
+
+    /// Send a command run by the client at `addr` in database `db` to every
+    /// monitor, as `+<unix time> [<db> <addr>] "<arg>" ...`
+    pub fn feed_monitors(&self, db: usize, addr: &str, args: &[&[u8]]) {
+        let line = monitor_line(SystemTime::now(), db, addr, args);
+        for push in self.monitors.read().values() {
+            // A closed channel belongs to a connection that is unregistering
+            let _ = push.send(RespValue::SimpleString(line.clone()));
+        }
+    }
+
+    /// Whether MONITOR shows `cmd`: administrative commands are left out, as
+    /// in Redis
+    pub fn feeds_monitors(cmd: &Command) -> bool {
+        !matches!(
+            cmd,
+            Command::Monitor
+                | Command::ConfigGet(_)
+                | Command::ConfigSet(_)
+                | Command::ConfigRewrite
+                | Command::ConfigResetStat
+                | Command::SlowlogGet(_)
+                | Command::SlowlogLen
+                | Command::SlowlogReset
+                | Command::LatencyLatest
+                | Command::LatencyHistory(_)
+                | Command::LatencyReset(_)
+                | Command::ClientList { .. }
+                | Command::ClientKill(_)
+                | Command::ClientPause { .. }
+                | Command::ClientUnpause
+                | Command::ClientNoEvict(_)
+                | Command::AclList
+                | Command::AclUsers
+                | Command::AclGetUser { .. }
+                | Command::AclSetUser { .. }
+                | Command::AclDelUser { .. }
+                | Command::ClusterSetSlot { .. }
+                | Command::Unknown(_)
+        )
+    }
+
+    /// Whether CLIENT PAUSE WRITE holds back `cmd`: anything that may
+    /// modify the keyspace, plus scripts and publishing
+    pub fn pauses_on_write(cmd: &Command) -> bool {
+        match cmd {
+            Command::Eval { .. }
+            | Command::EvalSha { .. }
+            | Command::Publish { .. }
+            | Command::SPublish { .. }
+            | Command::FlushDb
+            | Command::FlushAll
+            | Command::SwapDb(_, _) => true,
+            _ => !cmd.is_read_only() && !cmd.get_keys().is_empty(),
+        }
+    }
+}
+
+/// One MONITOR line, formatted like Redis: seconds with microseconds, the
+/// database and client, then every argument quoted with `\"`, `\\` and
+/// non-printable bytes escaped
+fn monitor_line(now: SystemTime, db: usize, addr: &str, args: &[&[u8]]) -> String {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let mut line =
+        String::with_capacity(32 + addr.len() + args.iter().map(|a| a.len() + 3).sum::<usize>());
+    let _ = write!(
+        line,
+        "{}.{:06} [{} {}]",
+        since_epoch.as_secs(),
+        since_epoch.subsec_micros(),
+        db,
+        addr
+    );
+    for arg in args {
+        line.push_str(" \"");
+        for &byte in arg.iter() {
+            match byte {
+                b'\\' => line.push_str("\\\\"),
+                b'"' => line.push_str("\\\""),
+                b'\n' => line.push_str("\\n"),
+                b'\r' => line.push_str("\\r"),
+                b'\t' => line.push_str("\\t"),
+                0x07 => line.push_str("\\a"),
+                0x08 => line.push_str("\\b"),
+                b' '..=b'~' => line.push(byte as char),
+                _ => {
+                    let _ = write!(line, "\\x{:02x}", byte);
+                }
+            }
+        }
+        line.push('"');
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis::SDS;
+
+    #[test]
+    fn test_list_and_info_line() {
+        let registry = ClientRegistry::new();
+        let a = registry.register(1, "127.0.0.1:1000".to_string());
+        registry.register(2, "127.0.0.1:2000".to_string());
+        a.update(|stats| {
+            stats.name = Some("worker".to_string());
+            stats.user = Some("default".to_string());
+            stats.sub = 1;
+            stats.db = 3;
+        });
+        a.touch("GET");
+
+        let line = a.info_line();
+        assert!(line.starts_with("id=1 addr=127.0.0.1:1000 name=worker age=0 idle=0 flags=P db=3 sub=1 psub=0 ssub=0 multi=-1"));
+        assert!(line.ends_with("cmd=get user=default redir=-1 resp=2"));
+
+        assert_eq!(registry.list(None, &[]).lines().count(), 2);
+        assert_eq!(
+            registry.list(Some(ClientType::PubSub), &[]),
+            format!("{}\n", line)
+        );
+        assert!(registry.list(None, &[2]).starts_with("id=2 "));
+        assert!(registry.list(Some(ClientType::Master), &[]).is_empty());
+
+        registry.unregister(1);
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_kill_filters() {
+        let registry = ClientRegistry::new();
+        let clients: Vec<_> = (1..=3)
+            .map(|id| registry.register(id, format!("10.0.0.{}:6000", id)))
+            .collect();
+        clients[1].update(|stats| stats.user = Some("alice".to_string()));
+
+        let by_user = ClientKillFilter {
+            user: Some("alice".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(registry.kill(&by_user, 1), 1);
+        assert!(clients[1].is_killed());
+
+        // SKIPME yes (the default) spares the caller
+        let everyone = ClientKillFilter::default();
+        assert_eq!(registry.kill(&everyone, 1), 2);
+        assert!(!clients[0].is_killed());
+        assert!(clients[2].is_killed());
+
+        let by_addr = ClientKillFilter {
+            addr: Some("10.0.0.1:6000".to_string()),
+            skipme: false,
+            ..Default::default()
+        };
+        assert_eq!(registry.kill(&by_addr, 1), 1);
+        assert!(clients[0].is_killed());
+    }
+
+    #[tokio::test]
+    async fn test_pause_holds_writes_until_unpause() {
+        let registry = Arc::new(ClientRegistry::new());
+        registry.pause(Duration::from_secs(60), ClientPauseMode::Write);
+        assert!(registry.is_paused());
+
+        // Reads pass a WRITE pause
+        tokio::time::timeout(Duration::from_secs(1), registry.wait_while_paused(false))
+            .await
+            .unwrap();
+
+        let waiter = tokio::spawn({
+            let registry = registry.clone();
+            async move { registry.wait_while_paused(true).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        registry.unpause();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!registry.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_pause_expires() {
+        let registry = ClientRegistry::new();
+        registry.pause(Duration::from_millis(20), ClientPauseMode::All);
+        tokio::time::timeout(Duration::from_secs(1), registry.wait_while_paused(false))
+            .await
+            .unwrap();
+        assert!(!registry.is_paused());
+    }
+
+    #[test]
+    fn test_pauses_on_write() {
+        let key = SDS::from("k");
+        assert!(ClientRegistry::pauses_on_write(&Command::set(
+            key.to_string(),
+            SDS::from("v")
+        )));
+        assert!(ClientRegistry::pauses_on_write(&Command::FlushDb));
+        assert!(!ClientRegistry::pauses_on_write(&Command::Get(key)));
+        assert!(!ClientRegistry::pauses_on_write(&Command::Ping));
+        assert!(!ClientRegistry::pauses_on_write(&Command::ClientUnpause));
+    }
+
+    #[test]
+    fn test_monitor_line_format() {
+        let now = UNIX_EPOCH + Duration::from_micros(1_339_518_083_107_412);
+        let line = monitor_line(now, 0, "127.0.0.1:60866", &[b"keys", b"*"]);
+        assert_eq!(line, r#"1339518083.107412 [0 127.0.0.1:60866] "keys" "*""#);
+
+        let now = UNIX_EPOCH + Duration::from_secs(5);
+        let line = monitor_line(now, 2, "a", &[b"SET", b"k", b"a\"b\\\n\x01\xff"]);
+        assert_eq!(line, r#"5.000000 [2 a] "SET" "k" "a\"b\\\n\x01\xff""#);
+    }
+
+    #[test]
+    fn test_monitors_receive_feed() {
+        let registry = ClientRegistry::new();
+        let monitor = registry.register(1, "127.0.0.1:1000".to_string());
+        assert!(!registry.has_monitors());
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        registry.add_monitor(1, tx);
+        monitor.update(|stats| stats.monitor = true);
+        assert!(registry.has_monitors());
+        assert!(monitor.info_line().contains(" flags=O "));
+
+        registry.feed_monitors(1, "10.0.0.1:5000", &[b"GET", b"k"]);
+        match rx.try_recv().unwrap() {
+            RespValue::SimpleString(line) => {
+                assert!(
+                    line.ends_with(r#" [1 10.0.0.1:5000] "GET" "k""#),
+                    "{}",
+                    line
+                )
+            }
+            other => panic!("unexpected frame {:?}", other),
+        }
+
+        assert!(ClientRegistry::feeds_monitors(&Command::Ping));
+        assert!(!ClientRegistry::feeds_monitors(&Command::Monitor));
+        assert!(!ClientRegistry::feeds_monitors(&Command::SlowlogLen));
+
+        registry.unregister(1);
+        assert!(!registry.has_monitors());
+    }
+}
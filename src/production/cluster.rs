@@ -0,0 +1,654 @@
+//! Redis Cluster protocol mode
+//!
+//! Maps the 16384 CRC16 hash slots onto a static set of nodes so that
+//! cluster-aware clients can route keys themselves. Each node owns a
+//! contiguous slot range (the same split `redis-cli --cluster create` makes)
+//! and answers `-MOVED` for keys in slots it does not own.
+//!
+//! ## Slot migration
+//!
+//! `CLUSTER SETSLOT <slot> MIGRATING|IMPORTING <node-id>` marks a slot as moving
+//! between two nodes. While a slot migrates away, the source keeps serving keys
+//! it still holds and answers `-ASK` for the rest; the target only serves the
+//! slot to clients that sent `ASKING` first. `SETSLOT <slot> NODE <node-id>`
+//! hands the slot over and `STABLE` cancels the migration.
+//!
+//! Shards inside a node are unaffected: keys are still spread over the local
+//! shards by `hash_key`, which honours the same `{hashtag}` rule as the slots.
+
+use super::sharded_actor::{hash_tag, CROSSSLOT_ERROR};
+use crate::redis::{ClusterSetSlotAction, RespValue, SDS};
+use ahash::AHashMap;
+use sha1::{Digest, Sha1};
+
+/// Number of hash slots in a Redis Cluster
+pub const CLUSTER_SLOTS: usize = 16384;
+
+/// CRC16-CCITT (XMODEM), the checksum Redis Cluster uses for key slots
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Hash slot of a key (honours `{hashtag}`)
+#[inline]
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    let slot = (crc16(hash_tag(key)) as usize % CLUSTER_SLOTS) as u16;
+    debug_assert!((slot as usize) < CLUSTER_SLOTS, "Slot out of range");
+    slot
+}
+
+/// Static cluster membership
+///
+/// Loaded from environment variables:
+/// - `REDIS_CLUSTER_NODES`: comma-separated `host:port` of every node, in the
+///   same order on all nodes (the order decides the slot split)
+/// - `REDIS_CLUSTER_MYSELF`: `host:port` of this node, one of the above
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterConfig {
+    /// Client-facing `host:port` of every node
+    pub nodes: Vec<String>,
+    /// Index of this node in `nodes`
+    pub myself: usize,
+}
+
+impl ClusterConfig {
+    pub fn new(nodes: Vec<String>, myself: usize) -> Self {
+        debug_assert!(myself < nodes.len(), "myself must index into nodes");
+        ClusterConfig { nodes, myself }
+    }
+
+    /// Load from environment; None when cluster mode is not configured
+    pub fn from_env() -> Option<Self> {
+        let nodes: Vec<String> = std::env::var("REDIS_CLUSTER_NODES")
+            .ok()?
+            .split(',')
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .collect();
+        let myself = std::env::var("REDIS_CLUSTER_MYSELF").ok()?;
+        let myself = nodes.iter().position(|n| *n == myself.trim())?;
+        Some(ClusterConfig { nodes, myself })
+    }
+
+    /// Check the configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if self.nodes.is_empty() {
+            return Err("cluster needs at least one node".to_string());
+        }
+        if self.myself >= self.nodes.len() {
+            return Err(format!(
+                "myself index {} out of range for {} nodes",
+                self.myself,
+                self.nodes.len()
+            ));
+        }
+        for (i, node) in self.nodes.iter().enumerate() {
+            split_addr(node).ok_or_else(|| format!("invalid cluster node address '{}'", node))?;
+            if self.nodes[..i].contains(node) {
+                return Err(format!("duplicate cluster node address '{}'", node));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split `host:port`
+fn split_addr(addr: &str) -> Option<(String, u16)> {
+    let (host, port) = addr.rsplit_once(':')?;
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+/// A cluster member as reported to clients
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterNode {
+    /// 40 hex characters, derived from the address so every node agrees on it
+    pub id: String,
+    pub host: String,
+    pub port: u16,
+    /// Epoch of the node's last slot ownership change, as in CLUSTER NODES
+    pub config_epoch: u64,
+}
+
+impl ClusterNode {
+    fn new(host: String, port: u16, config_epoch: u64) -> Self {
+        let digest = Sha1::digest(format!("{}:{}", host, port).as_bytes());
+        let id = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        ClusterNode {
+            id,
+            host,
+            port,
+            config_epoch,
+        }
+    }
+
+    fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// `[host, port, id]` as used by CLUSTER SLOTS
+    fn slots_entry(&self) -> RespValue {
+        RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(self.host.as_bytes().to_vec())),
+            RespValue::Integer(self.port as i64),
+            RespValue::BulkString(Some(self.id.as_bytes().to_vec())),
+        ]))
+    }
+}
+
+/// Where a command's keys must be served
+#[derive(Debug, Clone, PartialEq)]
+pub enum SlotRoute {
+    /// This node owns the slot (or imports it for an ASKING client)
+    Local,
+    /// This node owns the slot but is migrating it: serve the keys that still
+    /// exist here, send the client to `target` with `-ASK` otherwise
+    Migrating { slot: u16, target: String },
+    /// Redirect error to send instead of executing (MOVED, CROSSSLOT, CLUSTERDOWN)
+    Redirect(RespValue),
+}
+
+/// Slot ownership of a cluster, as seen by this node
+#[derive(Debug, Clone)]
+pub struct ClusterState {
+    nodes: Vec<ClusterNode>,
+    myself: usize,
+    /// Owning node (index into `nodes`) of every slot
+    slots: Vec<Option<usize>>,
+    /// Slots this node is handing to another node
+    migrating: AHashMap<u16, usize>,
+    /// Slots this node is receiving from another node
+    importing: AHashMap<u16, usize>,
+}
+
+impl ClusterState {
+    /// Build the initial slot map: node `i` of `n` owns the slots from
+    /// `round(i * 16384 / n)` up to the next node's first slot, the same split
+    /// as `redis-cli --cluster create`
+    pub fn new(config: &ClusterConfig) -> Result<Self, String> {
+        config.validate()?;
+
+        let nodes: Vec<ClusterNode> = config
+            .nodes
+            .iter()
+            .filter_map(|addr| split_addr(addr))
+            .enumerate()
+            .map(|(i, (host, port))| ClusterNode::new(host, port, i as u64 + 1))
+            .collect();
+        let n = nodes.len();
+        let first_slot = |i: usize| (2 * i * CLUSTER_SLOTS + n) / (2 * n);
+        let mut slots = vec![None; CLUSTER_SLOTS];
+        for i in 0..n {
+            slots[first_slot(i)..first_slot(i + 1)].fill(Some(i));
+        }
+
+        let state = ClusterState {
+            nodes,
+            myself: config.myself,
+            slots,
+            migrating: AHashMap::new(),
+            importing: AHashMap::new(),
+        };
+        state.verify_invariants();
+        Ok(state)
+    }
+
+    #[inline]
+    pub fn myself(&self) -> &ClusterNode {
+        &self.nodes[self.myself]
+    }
+
+    #[inline]
+    pub fn nodes(&self) -> &[ClusterNode] {
+        &self.nodes
+    }
+
+    /// Greatest config epoch known in the cluster (`cluster_current_epoch`)
+    pub fn current_epoch(&self) -> u64 {
+        self.nodes.iter().map(|n| n.config_epoch).max().unwrap_or(0)
+    }
+
+    /// Node owning `slot`, if the slot is assigned
+    pub fn slot_owner(&self, slot: u16) -> Option<&ClusterNode> {
+        self.slots[slot as usize].map(|idx| &self.nodes[idx])
+    }
+
+    /// Decide where a command touching `keys` must run. `asking` is true when
+    /// the client sent ASKING right before this command.
+    pub fn route(&self, keys: &[SDS], asking: bool) -> SlotRoute {
+        let Some(first) = keys.first() else {
+            return SlotRoute::Local;
+        };
+        let slot = key_hash_slot(first.as_bytes());
+        if keys[1..]
+            .iter()
+            .any(|key| key_hash_slot(key.as_bytes()) != slot)
+        {
+            return SlotRoute::Redirect(RespValue::Error(CROSSSLOT_ERROR.to_string()));
+        }
+
+        match self.slots[slot as usize] {
+            None => SlotRoute::Redirect(RespValue::Error(
+                "CLUSTERDOWN Hash slot not served".to_string(),
+            )),
+            Some(owner) if owner == self.myself => match self.migrating.get(&slot) {
+                Some(&target) => SlotRoute::Migrating {
+                    slot,
+                    target: self.nodes[target].addr(),
+                },
+                None => SlotRoute::Local,
+            },
+            Some(_) if asking && self.importing.contains_key(&slot) => SlotRoute::Local,
+            Some(owner) => SlotRoute::Redirect(RespValue::Error(format!(
+                "MOVED {} {}",
+                slot,
+                self.nodes[owner].addr()
+            ))),
+        }
+    }
+
+    /// Apply CLUSTER SETSLOT
+    pub fn set_slot(&mut self, slot: u16, action: &ClusterSetSlotAction) -> Result<(), String> {
+        if slot as usize >= CLUSTER_SLOTS {
+            return Err("ERR Invalid or out of range slot".to_string());
+        }
+        let node_index = |id: &str| {
+            self.nodes
+                .iter()
+                .position(|n| n.id == id)
+                .ok_or_else(|| format!("ERR I don't know about node {}", id))
+        };
+        let owner = self.slots[slot as usize];
+
+        match action {
+            ClusterSetSlotAction::Migrating(id) => {
+                let target = node_index(id)?;
+                if owner != Some(self.myself) {
+                    return Err(format!("ERR I'm not the owner of hash slot {}", slot));
+                }
+                if target == self.myself {
+                    return Err("ERR Target node is myself".to_string());
+                }
+                self.migrating.insert(slot, target);
+            }
+            ClusterSetSlotAction::Importing(id) => {
+                let source = node_index(id)?;
+                if owner == Some(self.myself) {
+                    return Err(format!("ERR I'm already the owner of hash slot {}", slot));
+                }
+                if source == self.myself {
+                    return Err("ERR Source node is myself".to_string());
+                }
+                self.importing.insert(slot, source);
+            }
+            ClusterSetSlotAction::Node(id) => {
+                let new_owner = node_index(id)?;
+                self.slots[slot as usize] = Some(new_owner);
+                self.migrating.remove(&slot);
+                self.importing.remove(&slot);
+                // The new owner's claim must win over every older one
+                self.nodes[new_owner].config_epoch = self.current_epoch() + 1;
+            }
+            ClusterSetSlotAction::Stable => {
+                self.migrating.remove(&slot);
+                self.importing.remove(&slot);
+            }
+        }
+
+        self.verify_invariants();
+        Ok(())
+    }
+
+    /// Contiguous `(start, end, owner)` slot ranges, in slot order
+    fn slot_ranges(&self) -> Vec<(u16, u16, usize)> {
+        let mut ranges: Vec<(u16, u16, usize)> = Vec::new();
+        for (slot, owner) in self.slots.iter().enumerate() {
+            let Some(owner) = *owner else { continue };
+            match ranges.last_mut() {
+                Some((_, end, last)) if *last == owner && *end as usize + 1 == slot => {
+                    *end = slot as u16;
+                }
+                _ => ranges.push((slot as u16, slot as u16, owner)),
+            }
+        }
+        ranges
+    }
+
+    /// CLUSTER SLOTS: `[[start, end, [host, port, id]], ...]`
+    pub fn slots_reply(&self) -> RespValue {
+        RespValue::Array(Some(
+            self.slot_ranges()
+                .into_iter()
+                .map(|(start, end, owner)| {
+                    RespValue::Array(Some(vec![
+                        RespValue::Integer(start as i64),
+                        RespValue::Integer(end as i64),
+                        self.nodes[owner].slots_entry(),
+                    ]))
+                })
+                .collect(),
+        ))
+    }
+
+    /// CLUSTER SHARDS: one `slots`/`nodes` map per node (flattened for RESP2)
+    pub fn shards_reply(&self) -> RespValue {
+        let ranges = self.slot_ranges();
+        let bulk = |s: &str| RespValue::BulkString(Some(s.as_bytes().to_vec()));
+        RespValue::Array(Some(
+            self.nodes
+                .iter()
+                .enumerate()
+                .map(|(idx, node)| {
+                    let slots: Vec<RespValue> = ranges
+                        .iter()
+                        .filter(|(_, _, owner)| *owner == idx)
+                        .flat_map(|(start, end, _)| {
+                            [
+                                RespValue::Integer(*start as i64),
+                                RespValue::Integer(*end as i64),
+                            ]
+                        })
+                        .collect();
+                    let description = vec![
+                        bulk("id"),
+                        bulk(&node.id),
+                        bulk("port"),
+                        RespValue::Integer(node.port as i64),
+                        bulk("ip"),
+                        bulk(&node.host),
+                        bulk("endpoint"),
+                        bulk(&node.host),
+                        bulk("role"),
+                        bulk("master"),
+                        bulk("replication-offset"),
+                        RespValue::Integer(0),
+                        bulk("health"),
+                        bulk("online"),
+                    ];
+                    RespValue::Array(Some(vec![
+                        bulk("slots"),
+                        RespValue::Array(Some(slots)),
+                        bulk("nodes"),
+                        RespValue::Array(Some(vec![RespValue::Array(Some(description))])),
+                    ]))
+                })
+                .collect(),
+        ))
+    }
+
+    /// CLUSTER NODES: one line per node in the `nodes.conf` format
+    pub fn nodes_reply(&self) -> RespValue {
+        let ranges = self.slot_ranges();
+        let mut out = String::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let flags = if idx == self.myself {
+                "myself,master"
+            } else {
+                "master"
+            };
+            out.push_str(&format!(
+                "{} {}@{} {} - 0 0 {} connected",
+                node.id,
+                node.addr(),
+                node.port as u32 + 10000,
+                flags,
+                node.config_epoch
+            ));
+            for (start, end, _) in ranges.iter().filter(|(_, _, owner)| *owner == idx) {
+                if start == end {
+                    out.push_str(&format!(" {}", start));
+                } else {
+                    out.push_str(&format!(" {}-{}", start, end));
+                }
+            }
+            if idx == self.myself {
+                let mut moving: Vec<String> =
+                    self.migrating
+                        .iter()
+                        .map(|(slot, target)| format!(" [{}->-{}]", slot, self.nodes[*target].id))
+                        .chain(self.importing.iter().map(|(slot, source)| {
+                            format!(" [{}-<-{}]", slot, self.nodes[*source].id)
+                        }))
+                        .collect();
+                moving.sort();
+                out.push_str(&moving.concat());
+            }
+            out.push('\n');
+        }
+        RespValue::BulkString(Some(out.into_bytes()))
+    }
+
+    /// CLUSTER INFO
+    pub fn info_reply(&self) -> RespValue {
+        let assigned = self.slots.iter().filter(|s| s.is_some()).count();
+        let state = if assigned == CLUSTER_SLOTS {
+            "ok"
+        } else {
+            "fail"
+        };
+        let info = format!(
+            "cluster_enabled:1\r\n\
+             cluster_state:{}\r\n\
+             cluster_slots_assigned:{}\r\n\
+             cluster_slots_ok:{}\r\n\
+             cluster_slots_pfail:0\r\n\
+             cluster_slots_fail:0\r\n\
+             cluster_known_nodes:{}\r\n\
+             cluster_size:{}\r\n\
+             cluster_current_epoch:{}\r\n\
+             cluster_my_epoch:{}\r\n",
+            state,
+            assigned,
+            assigned,
+            self.nodes.len(),
+            self.slot_ranges()
+                .iter()
+                .map(|(_, _, owner)| *owner)
+                .collect::<std::collections::BTreeSet<_>>()
+                .len(),
+            self.current_epoch(),
+            self.myself().config_epoch,
+        );
+        RespValue::BulkString(Some(info.into_bytes()))
+    }
+
+    #[cfg(debug_assertions)]
+    fn verify_invariants(&self) {
+        debug_assert_eq!(
+            self.slots.len(),
+            CLUSTER_SLOTS,
+            "Invariant violated: slot table must cover every slot"
+        );
+        debug_assert!(
+            self.slots
+                .iter()
+                .flatten()
+                .all(|&owner| owner < self.nodes.len()),
+            "Invariant violated: slot owned by unknown node"
+        );
+        debug_assert!(
+            self.migrating
+                .keys()
+                .all(|&slot| self.slots[slot as usize] == Some(self.myself)),
+            "Invariant violated: migrating slot not owned by this node"
+        );
+        debug_assert!(
+            self.importing
+                .keys()
+                .all(|&slot| self.slots[slot as usize] != Some(self.myself)),
+            "Invariant violated: importing slot already owned by this node"
+        );
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    fn verify_invariants(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn three_nodes(myself: usize) -> ClusterState {
+        let config = ClusterConfig::new(
+            vec![
+                "127.0.0.1:7000".to_string(),
+                "127.0.0.1:7001".to_string(),
+                "127.0.0.1:7002".to_string(),
+            ],
+            myself,
+        );
+        ClusterState::new(&config).unwrap()
+    }
+
+    #[test]
+    fn test_crc16_and_key_slots() {
+        // Reference values from the Redis Cluster specification
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+        assert_eq!(key_hash_slot(b"foo"), 12182);
+        assert_eq!(key_hash_slot(b"bar"), 5061);
+        assert_eq!(
+            key_hash_slot(b"{user1000}.following"),
+            key_hash_slot(b"user1000")
+        );
+        assert_eq!(key_hash_slot(b"{}foo"), crc16(b"{}foo") % 16384);
+    }
+
+    #[test]
+    fn test_even_slot_split() {
+        let cluster = three_nodes(0);
+        match cluster.slots_reply() {
+            RespValue::Array(Some(ranges)) => {
+                let bounds: Vec<(i64, i64)> = ranges
+                    .iter()
+                    .map(|r| match r {
+                        RespValue::Array(Some(items)) => match (&items[0], &items[1]) {
+                            (RespValue::Integer(s), RespValue::Integer(e)) => (*s, *e),
+                            _ => panic!("bad range {:?}", r),
+                        },
+                        _ => panic!("bad range {:?}", r),
+                    })
+                    .collect();
+                assert_eq!(bounds, vec![(0, 5460), (5461, 10922), (10923, 16383)]);
+            }
+            other => panic!("unexpected CLUSTER SLOTS reply {:?}", other),
+        }
+        assert_eq!(cluster.myself().id.len(), 40);
+        assert_eq!(cluster.myself().id, three_nodes(1).nodes()[0].id);
+    }
+
+    #[test]
+    fn test_moved_and_crossslot() {
+        let cluster = three_nodes(0);
+        // "bar" hashes to slot 5061 (node 0), "foo" to 12182 (node 2)
+        assert_eq!(cluster.route(&["bar".into()], false), SlotRoute::Local);
+        assert_eq!(
+            cluster.route(&["foo".into()], false),
+            SlotRoute::Redirect(RespValue::Error("MOVED 12182 127.0.0.1:7002".to_string()))
+        );
+        assert_eq!(
+            cluster.route(&["foo".into(), "bar".into()], false),
+            SlotRoute::Redirect(RespValue::Error(CROSSSLOT_ERROR.to_string()))
+        );
+        assert_eq!(cluster.route(&[], false), SlotRoute::Local);
+    }
+
+    #[test]
+    fn test_slot_migration() {
+        let mut source = three_nodes(0);
+        let mut target = three_nodes(1);
+        let source_id = source.myself().id.clone();
+        let target_id = target.myself().id.clone();
+        let keys = [SDS::from("bar")];
+
+        source
+            .set_slot(5061, &ClusterSetSlotAction::Migrating(target_id.clone()))
+            .unwrap();
+        target
+            .set_slot(5061, &ClusterSetSlotAction::Importing(source_id))
+            .unwrap();
+        assert_eq!(
+            source.route(&keys, false),
+            SlotRoute::Migrating {
+                slot: 5061,
+                target: "127.0.0.1:7001".to_string()
+            }
+        );
+        // The target only serves the slot after ASKING
+        assert!(matches!(target.route(&keys, false), SlotRoute::Redirect(_)));
+        assert_eq!(target.route(&keys, true), SlotRoute::Local);
+
+        for cluster in [&mut source, &mut target] {
+            cluster
+                .set_slot(5061, &ClusterSetSlotAction::Node(target_id.clone()))
+                .unwrap();
+        }
+        assert_eq!(
+            source.route(&keys, false),
+            SlotRoute::Redirect(RespValue::Error("MOVED 5061 127.0.0.1:7001".to_string()))
+        );
+        assert_eq!(target.route(&keys, false), SlotRoute::Local);
+
+        assert!(source
+            .set_slot(5061, &ClusterSetSlotAction::Migrating(target_id))
+            .is_err());
+        assert!(source
+            .set_slot(1, &ClusterSetSlotAction::Node("unknown".to_string()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_info_epochs_match_cluster_nodes() {
+        let info = |cluster: &ClusterState| match cluster.info_reply() {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected CLUSTER INFO reply {:?}", other),
+        };
+        let mut cluster = three_nodes(0);
+        let text = info(&cluster);
+        assert!(text.contains("cluster_current_epoch:3\r\n"), "{}", text);
+        assert!(text.contains("cluster_my_epoch:1\r\n"), "{}", text);
+
+        // Taking a slot moves the new owner past every known epoch
+        let myself = cluster.myself().id.clone();
+        cluster
+            .set_slot(12182, &ClusterSetSlotAction::Node(myself))
+            .unwrap();
+        let text = info(&cluster);
+        assert!(text.contains("cluster_current_epoch:4\r\n"), "{}", text);
+        assert!(text.contains("cluster_my_epoch:4\r\n"), "{}", text);
+        let RespValue::BulkString(Some(nodes)) = cluster.nodes_reply() else {
+            panic!("CLUSTER NODES replies with a bulk string");
+        };
+        let nodes = String::from_utf8(nodes).unwrap();
+        assert!(nodes.lines().next().unwrap().contains(" - 0 0 4 connected"));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(ClusterConfig::new(vec!["127.0.0.1:7000".to_string()], 0)
+            .validate()
+            .is_ok());
+        assert!(ClusterConfig::new(vec!["nohost".to_string()], 0)
+            .validate()
+            .is_err());
+        assert!(ClusterConfig::new(
+            vec!["127.0.0.1:7000".to_string(), "127.0.0.1:7000".to_string()],
+            0
+        )
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,485 @@
+//! Runtime configuration: the registry behind CONFIG GET/SET/REWRITE
+//!
+//! Settings come from two places at startup: the TOML file read by
+//! `PerformanceConfig` and the environment read by `ServerConfig`. The
+//! registry exposes both under Redis parameter names (`maxmemory`, `hz`,
+//! `slowlog-max-len`, ...). Parameters marked mutable can be changed with
+//! CONFIG SET and take effect immediately:
+//!
+//! - `maxmemory*` is pushed to every shard
+//! - `hz` retunes the TTL manager
+//! - `requirepass` replaces the default user's password
+//! - `slowlog-*` resizes the SLOWLOG, `latency-monitor-threshold` the LATENCY
+//!   monitor's
+//! - `timeout` is read by connections as they run
+//!
+//! CONFIG REWRITE saves the settings changed since startup back into the file
+//! they were loaded from, so live changes survive a restart. It edits the
+//! changed keys in place and leaves the rest of the file alone; a server that
+//! fell back to defaults (no file, or one that failed to parse) refuses it
+//! rather than overwrite the file with defaults. `requirepass` is never saved.
+
+use super::perf_config::{PerformanceConfig, SlowlogConfig};
+use super::server_config::ServerConfig;
+use super::ttl_manager::TtlManagerHandle;
+use super::ShardedActorState;
+use crate::redis::{CommandExecutor, EvictionPolicy, MemoryConfig};
+use crate::security::AclManager;
+use parking_lot::RwLock;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Every parameter CONFIG knows, in CONFIG GET order, and whether CONFIG SET
+/// may change it
+const PARAMETERS: &[(&str, bool)] = &[
+    ("maxmemory", true),
+    ("maxmemory-policy", true),
+    ("maxmemory-samples", true),
+    ("timeout", true),
+    ("hz", true),
+    ("slowlog-log-slower-than", true),
+    ("slowlog-max-len", true),
+    ("latency-monitor-threshold", true),
+    ("requirepass", true),
+    ("databases", false),
+    ("num-shards", false),
+    ("response-pool-capacity", false),
+    ("response-pool-prewarm", false),
+    ("read-buffer-size", false),
+    ("max-buffer-size", false),
+    ("min-pipeline-buffer", false),
+    ("batch-threshold", false),
+    ("aclfile", false),
+    ("tls-cert-file", false),
+    ("tls-key-file", false),
+    ("tls-ca-cert-file", false),
+    ("tls-auth-clients", false),
+];
+
+/// What CONFIG SET changes are applied to; absent in unit tests
+struct LiveTargets {
+    state: ShardedActorState,
+    acl_manager: Arc<RwLock<AclManager>>,
+    ttl: TtlManagerHandle,
+}
+
+/// Server-wide configuration shared by all connections
+pub struct ConfigRegistry {
+    /// File CONFIG REWRITE writes; None unless it loaded cleanly at startup
+    path: Option<PathBuf>,
+    perf: RwLock<PerformanceConfig>,
+    /// Environment settings (TLS, ACL file); read-only
+    server: ServerConfig,
+    /// `clients.timeout`, read on every connection wakeup
+    timeout_secs: AtomicU64,
+    targets: Option<LiveTargets>,
+}
+
+impl ConfigRegistry {
+    /// Registry over `perf` (loaded from `path`, None if it came from
+    /// defaults) and `server`; the server's password, which may come from
+    /// REDIS_REQUIRE_PASS, is the one reported
+    pub fn new(path: Option<PathBuf>, mut perf: PerformanceConfig, server: ServerConfig) -> Self {
+        perf.security.requirepass = server.acl.require_pass.clone();
+        ConfigRegistry {
+            path,
+            timeout_secs: AtomicU64::new(perf.clients.timeout),
+            perf: RwLock::new(perf),
+            server,
+            targets: None,
+        }
+    }
+
+    /// Apply CONFIG SET changes to the running server
+    pub fn with_targets(
+        mut self,
+        state: ShardedActorState,
+        acl_manager: Arc<RwLock<AclManager>>,
+        ttl: TtlManagerHandle,
+    ) -> Self {
+        self.targets = Some(LiveTargets {
+            state,
+            acl_manager,
+            ttl,
+        });
+        self
+    }
+
+    /// Idle time after which connections are closed, None = never
+    #[inline]
+    pub fn timeout(&self) -> Option<Duration> {
+        match self.timeout_secs.load(Ordering::Relaxed) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Current SLOWLOG threshold and length
+    pub fn slowlog(&self) -> SlowlogConfig {
+        self.perf.read().slowlog.clone()
+    }
+
+    /// Current maxmemory settings for the whole server
+    pub fn memory_config(&self) -> MemoryConfig {
+        self.perf.read().memory.memory_config()
+    }
+
+    /// CONFIG GET: `(name, value)` of every parameter matching any of the
+    /// glob `patterns` (case-insensitive), each listed once
+    pub fn get(&self, patterns: &[String]) -> Vec<(&'static str, String)> {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_lowercase()).collect();
+        let perf = self.perf.read();
+        PARAMETERS
+            .iter()
+            .filter(|(name, _)| {
+                patterns.iter().any(|pattern| {
+                    CommandExecutor::matches_glob_pattern(name.as_bytes(), pattern.as_bytes())
+                })
+            })
+            .map(|(name, _)| (*name, self.value(&perf, name)))
+            .collect()
+    }
+
+    /// CONFIG SET: change every parameter or none. Errors are full replies.
+    /// The write lock is held until the change is applied everywhere, so
+    /// concurrent CONFIG SETs cannot lose each other's updates.
+    pub fn set(&self, pairs: &[(String, String)]) -> Result<(), String> {
+        let mut perf = self.perf.write();
+        let mut updated = perf.clone();
+        let mut seen: Vec<String> = Vec::with_capacity(pairs.len());
+        for (name, value) in pairs {
+            let name = name.to_lowercase();
+            let failed = |reason: &str| {
+                format!(
+                    "ERR CONFIG SET failed (possibly related to argument '{}') - {}",
+                    name, reason
+                )
+            };
+            match PARAMETERS.iter().find(|(known, _)| *known == name) {
+                None => {
+                    return Err(format!(
+                        "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                        name
+                    ))
+                }
+                Some((_, false)) => return Err(failed("can't set immutable config")),
+                Some(_) if seen.contains(&name) => return Err(failed("duplicate parameter")),
+                Some(_) => {}
+            }
+            Self::apply(&mut updated, &name, value).map_err(|reason| failed(&reason))?;
+            seen.push(name);
+        }
+
+        let previous = std::mem::replace(&mut *perf, updated);
+        let updated = &*perf;
+        self.timeout_secs
+            .store(updated.clients.timeout, Ordering::Relaxed);
+        if let Some(targets) = &self.targets {
+            let memory = updated.memory.memory_config();
+            if memory != previous.memory.memory_config() {
+                targets.state.set_memory_config(memory);
+            }
+            if updated.ttl.hz != previous.ttl.hz {
+                targets.ttl.set_interval(updated.ttl.interval_ms());
+            }
+            targets.state.slowlog().configure(&updated.slowlog);
+            targets
+                .state
+                .latency()
+                .set_threshold(updated.latency.monitor_threshold);
+            if updated.security.requirepass != previous.security.requirepass {
+                let requirepass = updated.security.requirepass.as_deref();
+                let mut manager = targets.acl_manager.write();
+                manager.set_requirepass(requirepass);
+                manager
+                    .set_require_auth(requirepass.is_some() || self.server.acl.acl_file.is_some());
+            }
+        }
+        Ok(())
+    }
+
+    /// CONFIG REWRITE: save the changed settings to the config file
+    pub fn rewrite(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Err("ERR The server is running without a config file".to_string());
+        };
+        self.perf
+            .read()
+            .rewrite_file(path)
+            .map_err(|e| format!("ERR Rewriting config file: {}", e))
+    }
+
+    fn value(&self, perf: &PerformanceConfig, name: &str) -> String {
+        let path =
+            |path: Option<&PathBuf>| path.map(|p| p.display().to_string()).unwrap_or_default();
+        let tls = self.server.tls.as_ref();
+        match name {
+            "maxmemory" => perf.memory.maxmemory.to_string(),
+            "maxmemory-policy" => perf.memory.maxmemory_policy.clone(),
+            "maxmemory-samples" => perf.memory.maxmemory_samples.to_string(),
+            "timeout" => perf.clients.timeout.to_string(),
+            "hz" => perf.ttl.hz.to_string(),
+            "slowlog-log-slower-than" => perf.slowlog.log_slower_than.to_string(),
+            "slowlog-max-len" => perf.slowlog.max_len.to_string(),
+            "latency-monitor-threshold" => perf.latency.monitor_threshold.to_string(),
+            "requirepass" => perf.security.requirepass.clone().unwrap_or_default(),
+            "databases" => perf.databases.to_string(),
+            "num-shards" => perf.num_shards.to_string(),
+            "response-pool-capacity" => perf.response_pool.capacity.to_string(),
+            "response-pool-prewarm" => perf.response_pool.prewarm.to_string(),
+            "read-buffer-size" => perf.buffers.read_size.to_string(),
+            "max-buffer-size" => perf.buffers.max_size.to_string(),
+            "min-pipeline-buffer" => perf.batching.min_pipeline_buffer.to_string(),
+            "batch-threshold" => perf.batching.batch_threshold.to_string(),
+            "aclfile" => path(self.server.acl.acl_file.as_ref()),
+            "tls-cert-file" => path(tls.map(|t| &t.cert_path)),
+            "tls-key-file" => path(tls.map(|t| &t.key_path)),
+            "tls-ca-cert-file" => path(tls.and_then(|t| t.ca_path.as_ref())),
+            "tls-auth-clients" => {
+                let required = tls.is_some_and(|t| t.require_client_cert);
+                if required { "yes" } else { "no" }.to_string()
+            }
+            _ => {
+                debug_assert!(false, "CONFIG parameter without a value: {}", name);
+                String::new()
+            }
+        }
+    }
+
+    /// Set mutable parameter `name` in `perf`, or say why `value` is invalid
+    fn apply(perf: &mut PerformanceConfig, name: &str, value: &str) -> Result<(), String> {
+        let integer = || {
+            value
+                .parse::<i64>()
+                .map_err(|_| "argument couldn't be parsed into an integer".to_string())
+        };
+        let in_range = |min: i64, max: i64| {
+            let n = integer()?;
+            if (min..=max).contains(&n) {
+                Ok(n)
+            } else {
+                Err(format!(
+                    "argument must be between {} and {} inclusive",
+                    min, max
+                ))
+            }
+        };
+        match name {
+            "maxmemory" => {
+                perf.memory.maxmemory =
+                    parse_memory(value).ok_or("argument must be a memory value")?;
+            }
+            "maxmemory-policy" => {
+                let policy = value.to_lowercase();
+                if EvictionPolicy::from_name(&policy).is_none() {
+                    return Err("argument(s) must be one of the following: volatile-lru, volatile-lfu, volatile-random, volatile-ttl, allkeys-lru, allkeys-lfu, allkeys-random, noeviction".to_string());
+                }
+                perf.memory.maxmemory_policy = policy;
+            }
+            "maxmemory-samples" => perf.memory.maxmemory_samples = in_range(1, 64)? as usize,
+            "timeout" => perf.clients.timeout = in_range(0, i32::MAX as i64)? as u64,
+            "hz" => perf.ttl.hz = in_range(1, 500)? as u32,
+            "slowlog-log-slower-than" => {
+                perf.slowlog.log_slower_than = in_range(-1, i64::MAX)?;
+            }
+            "slowlog-max-len" => perf.slowlog.max_len = in_range(0, i64::MAX)? as usize,
+            "latency-monitor-threshold" => {
+                perf.latency.monitor_threshold = in_range(0, i64::MAX)? as u64;
+            }
+            "requirepass" => {
+                if !cfg!(feature = "acl") {
+                    return Err("requirepass needs the acl feature".to_string());
+                }
+                perf.security.requirepass = (!value.is_empty()).then(|| value.to_string());
+            }
+            _ => {
+                debug_assert!(false, "CONFIG SET of an immutable parameter: {}", name);
+                return Err("can't set immutable config".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bytes in a Redis memory value: `1024`, `100kb`, `1g` (k/m/g are powers
+/// of 1000, kb/mb/gb powers of 1024)
+fn parse_memory(value: &str) -> Option<usize> {
+    let value = value.to_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, unit) = value.split_at(split);
+    let multiplier: usize = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    digits.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> ConfigRegistry {
+        ConfigRegistry::new(None, PerformanceConfig::default(), ServerConfig::default())
+    }
+
+    fn set(registry: &ConfigRegistry, pairs: &[(&str, &str)]) -> Result<(), String> {
+        let pairs: Vec<(String, String)> = pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        registry.set(&pairs)
+    }
+
+    #[test]
+    fn test_get_glob_patterns() {
+        let registry = registry();
+        assert_eq!(
+            registry.get(&["maxmemory".to_string()]),
+            vec![("maxmemory", "0".to_string())]
+        );
+        let names: Vec<&str> = registry
+            .get(&["MAXMEMORY*".to_string(), "maxmemory-policy".to_string()])
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(
+            names,
+            vec!["maxmemory", "maxmemory-policy", "maxmemory-samples"]
+        );
+        assert_eq!(
+            registry.get(&["hz".to_string()]),
+            vec![("hz", "10".to_string())]
+        );
+        assert!(registry.get(&["nosuch*".to_string()]).is_empty());
+        assert_eq!(registry.get(&["*".to_string()]).len(), PARAMETERS.len());
+    }
+
+    #[test]
+    fn test_set_is_all_or_nothing() {
+        let registry = registry();
+        set(&registry, &[("maxmemory", "10mb"), ("timeout", "30")]).unwrap();
+        assert_eq!(registry.memory_config().maxmemory, 10 * 1024 * 1024);
+        assert_eq!(registry.timeout(), Some(Duration::from_secs(30)));
+
+        let err = set(&registry, &[("timeout", "5"), ("hz", "0")]).unwrap_err();
+        assert!(err.contains("'hz'"), "{}", err);
+        assert_eq!(registry.timeout(), Some(Duration::from_secs(30)));
+
+        let err = set(&registry, &[("databases", "4")]).unwrap_err();
+        assert!(err.contains("immutable"), "{}", err);
+        let err = set(&registry, &[("nosuch", "1")]).unwrap_err();
+        assert!(err.starts_with("ERR Unknown option"), "{}", err);
+        let err = set(&registry, &[("hz", "20"), ("HZ", "30")]).unwrap_err();
+        assert!(err.contains("duplicate"), "{}", err);
+        let err = set(&registry, &[("maxmemory-policy", "lru")]).unwrap_err();
+        assert!(err.contains("'maxmemory-policy'"), "{}", err);
+
+        set(
+            &registry,
+            &[("slowlog-log-slower-than", "-1"), ("slowlog-max-len", "16")],
+        )
+        .unwrap();
+        assert_eq!(registry.slowlog().log_slower_than, -1);
+        assert_eq!(registry.slowlog().max_len, 16);
+
+        let err = set(&registry, &[("latency-monitor-threshold", "-1")]).unwrap_err();
+        assert!(err.contains("'latency-monitor-threshold'"), "{}", err);
+    }
+
+    #[test]
+    fn test_concurrent_sets_keep_every_update() {
+        let registry = registry();
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for round in 1..=500 {
+                    set(&registry, &[("timeout", &round.to_string())]).unwrap();
+                }
+            });
+            scope.spawn(|| {
+                for round in 1..=500 {
+                    set(&registry, &[("maxmemory", &(round * 1000).to_string())]).unwrap();
+                }
+            });
+        });
+        // Neither writer's last update is reverted by the other's
+        assert_eq!(registry.timeout(), Some(Duration::from_secs(500)));
+        assert_eq!(registry.memory_config().maxmemory, 500_000);
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("1024"), Some(1024));
+        assert_eq!(parse_memory("1k"), Some(1000));
+        assert_eq!(parse_memory("1KB"), Some(1024));
+        assert_eq!(parse_memory("2gb"), Some(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_memory("10xb"), None);
+        assert_eq!(parse_memory("-1"), None);
+        assert_eq!(parse_memory(""), None);
+    }
+
+    #[test]
+    fn test_rewrite_edits_changed_keys_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("perf_config.toml");
+        let original = r#"# Tuned by hand
+num_shards = 4  # keep small
+future_knob = "kept"
+
+[memory]
+maxmemory = 0  # bytes, 0 = unlimited
+maxmemory_policy = "noeviction"
+
+[security]
+requirepass = "from-file"
+"#;
+        std::fs::write(&path, original).unwrap();
+        let perf = PerformanceConfig::try_from_file(&path).unwrap();
+        let mut server = ServerConfig::default();
+        server.acl.require_pass = Some("from-env".to_string());
+        let registry = ConfigRegistry::new(Some(path.clone()), perf, server);
+
+        set(&registry, &[("maxmemory", "1000"), ("hz", "50")]).unwrap();
+        registry.rewrite().unwrap();
+
+        let rewritten = std::fs::read_to_string(&path).unwrap();
+        let expected = original.replace("maxmemory = 0  #", "maxmemory = 1000  #");
+        assert!(rewritten.starts_with(&expected), "{}", rewritten);
+        assert!(rewritten.contains("[ttl]\nhz = 50\n"), "{}", rewritten);
+        assert!(!rewritten.contains("from-env"), "{}", rewritten);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let reloaded = PerformanceConfig::from_file(&path);
+        assert_eq!(reloaded.memory.maxmemory, 1000);
+        assert_eq!(reloaded.ttl.hz, 50);
+        assert_eq!(reloaded.num_shards, 4);
+    }
+
+    #[test]
+    fn test_rewrite_refused_without_loaded_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("perf_config.toml");
+        std::fs::write(&path, "num_shards = [not toml").unwrap();
+        assert!(PerformanceConfig::try_from_file(&path).is_none());
+        assert!(PerformanceConfig::try_from_file(dir.path().join("missing.toml")).is_none());
+
+        // The server fell back to defaults, so the broken file is left alone
+        let registry = registry();
+        set(&registry, &[("hz", "50")]).unwrap();
+        let err = registry.rewrite().unwrap_err();
+        assert!(err.contains("without a config file"), "{}", err);
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "num_shards = [not toml"
+        );
+    }
+}
@@ -1,14 +1,27 @@
+use super::client_registry::{ClientHandle, ClientRegistry};
+use super::cluster::{key_hash_slot, SlotRoute};
+use super::config_registry::ConfigRegistry;
 use super::connection_pool::BufferPoolAsync;
+use super::latency::{COMMAND, FAST_COMMAND};
 use super::perf_config::{BatchingConfig, BufferConfig};
+use super::pubsub::{PubSubHandle, SubscriberId, SubscriptionKind};
+use super::sharded_actor::time_shard_execution;
+use super::tracking::{InvalidationTarget, TrackedClient};
+use super::transaction::{ExecOutcome, TransactionState};
 use super::ShardedActorState;
 use crate::observability::{spans, Metrics};
-use crate::redis::{Command, RespCodec, RespValue};
+use crate::redis::{
+    into_push, upgrade_exec_reply, upgrade_reply, ClientReplyMode, ClientTrackingOptions, Command,
+    RespCodec, RespParser, RespValue, RespValueZeroCopy, SDS,
+};
 use crate::security::{AclManager, AclUser};
 use bytes::{BufMut, BytesMut};
 use parking_lot::RwLock;
+use std::collections::BTreeSet;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn, Instrument};
 
 // P3 optimization: Use itoa for fast integer encoding
@@ -78,6 +91,41 @@ pub struct OptimizedConnectionHandler<S> {
     acl_manager: Arc<RwLock<AclManager>>,
     /// Currently authenticated user (None = not authenticated yet)
     authenticated_user: Option<Arc<AclUser>>,
+    /// Pub/Sub broker shared by all connections
+    pubsub: PubSubHandle,
+    /// This connection's identity in the broker
+    subscriber_id: SubscriberId,
+    /// Messages pushed by the broker; drained between reads
+    push_tx: mpsc::UnboundedSender<RespValue>,
+    push_rx: mpsc::UnboundedReceiver<RespValue>,
+    /// Active subscriptions (non-empty = subscriber mode)
+    subscribed_channels: BTreeSet<String>,
+    subscribed_patterns: BTreeSet<String>,
+    subscribed_shard_channels: BTreeSet<String>,
+    /// MULTI/EXEC/WATCH state (per connection, never shared with a shard)
+    transaction: TransactionState,
+    /// ASKING was sent: the next command may use a slot this node imports
+    asking: bool,
+    /// RESP protocol version negotiated with HELLO (2 until the client asks for 3)
+    protocol: u32,
+    /// Name set with HELLO SETNAME
+    client_name: Option<String>,
+    /// CLIENT TRACKING options while tracking is on
+    tracking: Option<ClientTrackingOptions>,
+    /// CLIENT CACHING YES|NO, applying to the next command only
+    caching: Option<bool>,
+    /// Every connected client (CLIENT LIST/KILL/PAUSE)
+    clients: Arc<ClientRegistry>,
+    /// This connection's entry in `clients`
+    client: Arc<ClientHandle>,
+    /// CLIENT REPLY ON|OFF|SKIP
+    reply_mode: ClientReplyMode,
+    /// CLIENT NO-EVICT (reported in CLIENT LIST; clients are never evicted)
+    no_evict: bool,
+    /// MONITOR was sent: every command run by any client is pushed here
+    monitor: bool,
+    /// Runtime settings (CONFIG GET/SET, idle timeout)
+    server_config: Arc<ConfigRegistry>,
 }
 
 impl<S> OptimizedConnectionHandler<S>
@@ -94,6 +142,9 @@ where
         config: ConnectionConfig,
         acl_manager: Arc<RwLock<AclManager>>,
         client_cert_cn: Option<String>,
+        pubsub: PubSubHandle,
+        clients: Arc<ClientRegistry>,
+        server_config: Arc<ConfigRegistry>,
     ) -> Self {
         let buffer = buffer_pool.acquire();
         let write_buffer = buffer_pool.acquire();
@@ -147,6 +198,12 @@ where
             }
         };
 
+        let (push_tx, push_rx) = mpsc::unbounded_channel();
+        let subscriber_id = pubsub.next_subscriber_id();
+        let client = clients.register(subscriber_id, client_addr.clone());
+        let user = authenticated_user.as_ref().map(|u| u.name.clone());
+        client.update(|stats| stats.user = user);
+
         OptimizedConnectionHandler {
             stream,
             state,
@@ -158,6 +215,25 @@ where
             config,
             acl_manager,
             authenticated_user,
+            subscriber_id,
+            pubsub,
+            push_tx,
+            push_rx,
+            subscribed_channels: BTreeSet::new(),
+            subscribed_patterns: BTreeSet::new(),
+            subscribed_shard_channels: BTreeSet::new(),
+            transaction: TransactionState::new(),
+            asking: false,
+            protocol: 2,
+            client_name: None,
+            tracking: None,
+            caching: None,
+            clients,
+            client,
+            reply_mode: ClientReplyMode::On,
+            no_evict: false,
+            monitor: false,
+            server_config,
         }
     }
 
@@ -173,9 +249,44 @@ where
 
             // Use config for read buffer size (stack-allocate with max expected size)
             let mut read_buf = vec![0u8; self.config.read_buffer_size];
+            let client = self.client.clone();
 
             loop {
-                match self.stream.read(&mut read_buf).await {
+                // CONFIG SET timeout: idle connections are closed, subscribers
+                // and monitors never
+                let idle_timeout = self
+                    .server_config
+                    .timeout()
+                    .filter(|_| !self.in_subscriber_mode() && !self.monitor);
+                let read_result = tokio::select! {
+                    result = self.stream.read(&mut read_buf) => result,
+                    _ = client.killed() => {
+                        info!("Client killed: {}", self.client_addr);
+                        break;
+                    }
+                    _ = tokio::time::sleep(idle_timeout.unwrap_or_default()), if idle_timeout.is_some() => {
+                        info!("Client idle timeout: {}", self.client_addr);
+                        break;
+                    }
+                    Some(message) = self.push_rx.recv() => {
+                        // Pub/Sub message pushed by the broker while waiting for input
+                        Self::encode_resp_into(&self.push_frame(message), &mut self.write_buffer);
+                        while let Ok(message) = self.push_rx.try_recv() {
+                            Self::encode_resp_into(&self.push_frame(message), &mut self.write_buffer);
+                        }
+                        if let Err(e) = self.stream.write_all(&self.write_buffer).await {
+                            error!("Write failed to {}: {}", self.client_addr, e);
+                            break;
+                        }
+                        if let Err(e) = self.stream.flush().await {
+                            error!("Flush failed to {}: {}", self.client_addr, e);
+                            break;
+                        }
+                        self.write_buffer.clear();
+                        continue;
+                    }
+                };
+                match read_result {
                     Ok(0) => {
                         info!("Client disconnected: {}", self.client_addr);
                         break;
@@ -196,6 +307,7 @@ where
                         // Process ALL available commands (pipelining support)
                         let mut commands_executed = 0;
                         let mut had_parse_error = false;
+                        let mut closed = false;
 
                         // OPTIMIZATION: Only attempt batching when buffer is large enough
                         // to contain multiple commands. A single GET/SET is ~25-50 bytes, so
@@ -204,15 +316,35 @@ where
                         let min_pipeline_buffer = self.config.min_pipeline_buffer;
                         let batch_threshold = self.config.batch_threshold;
 
-                        if self.buffer.len() >= min_pipeline_buffer {
+                        // Subscriber mode only accepts (un)subscribe commands, MULTI
+                        // queues commands and cluster mode checks slots, so skip
+                        // batching in all three. MONITOR is fed one command at a
+                        // time by the fast paths.
+                        if self.buffer.len() >= min_pipeline_buffer
+                            && !self.in_subscriber_mode()
+                            && !self.transaction.in_multi()
+                            && self.state.cluster().is_none()
+                            && self.tracking.is_none()
+                            && self.reply_mode == ClientReplyMode::On
+                            && !self.clients.is_paused()
+                            && !self.clients.has_monitors()
+                        {
                             // Try GET batching first
                             let (get_keys, get_count) = self.collect_get_keys();
 
                             if get_count >= batch_threshold {
                                 // Batch execute multiple GETs concurrently
                                 let start = Instant::now();
-                                let results = self.state.fast_batch_get_pipeline(get_keys).await;
-                                let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+                                let results = self.state.fast_batch_get_pipeline(&get_keys).await;
+                                let elapsed = start.elapsed();
+                                let duration_ms = elapsed.as_secs_f64() * 1000.0;
+                                // Each command is charged an equal share of the batch
+                                let share = elapsed / results.len().max(1) as u32;
+                                for key in &get_keys {
+                                    self.record_command_time(FAST_COMMAND, share, || {
+                                        vec![b"GET".as_slice(), key]
+                                    });
+                                }
 
                                 for response in &results {
                                     let success = !matches!(response, RespValue::Error(_));
@@ -221,7 +353,11 @@ where
                                         duration_ms / results.len() as f64,
                                         success,
                                     );
-                                    Self::encode_resp_into(response, &mut self.write_buffer);
+                                    Self::encode_reply_into(
+                                        self.protocol,
+                                        response,
+                                        &mut self.write_buffer,
+                                    );
                                 }
                                 commands_executed += get_count;
                             }
@@ -234,8 +370,15 @@ where
                                     // Batch execute multiple SETs concurrently
                                     let start = Instant::now();
                                     let results =
-                                        self.state.fast_batch_set_pipeline(set_pairs).await;
-                                    let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+                                        self.state.fast_batch_set_pipeline(&set_pairs).await;
+                                    let elapsed = start.elapsed();
+                                    let duration_ms = elapsed.as_secs_f64() * 1000.0;
+                                    let share = elapsed / results.len().max(1) as u32;
+                                    for (key, value) in &set_pairs {
+                                        self.record_command_time(FAST_COMMAND, share, || {
+                                            vec![b"SET".as_slice(), key, value]
+                                        });
+                                    }
 
                                     for response in &results {
                                         let success = !matches!(response, RespValue::Error(_));
@@ -244,7 +387,11 @@ where
                                             duration_ms / results.len() as f64,
                                             success,
                                         );
-                                        Self::encode_resp_into(response, &mut self.write_buffer);
+                                        Self::encode_reply_into(
+                                            self.protocol,
+                                            response,
+                                            &mut self.write_buffer,
+                                        );
                                     }
                                     commands_executed += set_count;
                                 }
@@ -259,6 +406,10 @@ where
                                     // Don't flush yet - continue processing pipeline
                                 }
                                 CommandResult::NeedMoreData => break,
+                                CommandResult::Closed => {
+                                    closed = true;
+                                    break;
+                                }
                                 CommandResult::ParseError(e) => {
                                     warn!(
                                         "Parse error from {}: {}, draining buffer",
@@ -275,6 +426,10 @@ where
                             }
                         }
 
+                        if closed {
+                            break;
+                        }
+
                         // Flush ALL responses at once (critical for pipelining performance)
                         if !self.write_buffer.is_empty() {
                             if let Err(e) = self.stream.write_all(&self.write_buffer).await {
@@ -293,6 +448,12 @@ where
                             // Continue to next read after parse error
                         }
 
+                        // CLIENT KILL: the reply has gone out, now close
+                        if self.client.is_killed() {
+                            info!("Client killed: {}", self.client_addr);
+                            break;
+                        }
+
                         debug!("Processed {} commands in pipeline batch", commands_executed);
                     }
                     Err(e) => {
@@ -302,6 +463,13 @@ where
                 }
             }
 
+            if self.in_subscriber_mode() {
+                self.pubsub.disconnect(self.subscriber_id);
+            }
+            if self.tracking.is_some() {
+                self.state.disable_tracking(self.subscriber_id);
+            }
+            self.clients.unregister(self.subscriber_id);
             self.metrics.record_connection("closed");
             self.buffer_pool.release(self.buffer);
             self.buffer_pool.release(self.write_buffer);
@@ -314,7 +482,14 @@ where
     async fn try_execute_command(&mut self) -> CommandResult {
         // Try fast path first for GET/SET commands (80%+ of traffic)
         // Fast path skips ACL checks for performance - only use when auth not required
-        if self.authenticated_user.is_some() {
+        if self.authenticated_user.is_some()
+            && !self.in_subscriber_mode()
+            && !self.transaction.in_multi()
+            && self.state.cluster().is_none()
+            && self.tracking.is_none()
+            && self.reply_mode == ClientReplyMode::On
+            && !self.clients.is_paused()
+        {
             match self.try_fast_path().await {
                 FastPathResult::Handled => return CommandResult::Executed,
                 FastPathResult::NeedMoreData => return CommandResult::NeedMoreData,
@@ -326,13 +501,99 @@ where
             Ok(Some(resp_value)) => match Command::from_resp_zero_copy(&resp_value) {
                 Ok(cmd) => {
                     let cmd_name = cmd.name();
+                    if self.clients.is_paused() {
+                        let writes = if matches!(cmd, Command::Exec) {
+                            self.transaction
+                                .queued()
+                                .iter()
+                                .any(ClientRegistry::pauses_on_write)
+                        } else {
+                            ClientRegistry::pauses_on_write(&cmd)
+                        };
+                        self.clients.wait_while_paused(writes).await;
+                    }
+                    self.client.touch(cmd_name);
                     let start = Instant::now();
 
+                    // Cluster mode: keys this node must not serve are redirected
+                    // (after AUTH, as in Redis)
+                    if self.authenticated_user.is_some() {
+                        if let Some(redirect) = self.cluster_redirect(&cmd).await {
+                            self.transaction.mark_dirty();
+                            self.metrics.record_command(cmd_name, 0.0, false);
+                            Self::encode_resp_into(&redirect, &mut self.write_buffer);
+                            return CommandResult::Executed;
+                        }
+                    }
+
                     // Handle AUTH and ACL commands specially
                     let response = match &cmd {
+                        // RESP3 clients get pushes out of band and may run any command
+                        _ if self.protocol < 3
+                            && self.in_subscriber_mode()
+                            && !Self::allowed_in_subscriber_mode(&cmd) =>
+                        {
+                            RespValue::Error(format!(
+                                "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING are allowed in this context",
+                                cmd_name.to_lowercase()
+                            ))
+                        }
+                        Command::Ping if self.protocol < 3 && self.in_subscriber_mode() => {
+                            // PING msg echoes msg as the second element
+                            let message = Self::frame_args(&resp_value)
+                                .get(1)
+                                .map_or_else(Vec::new, |arg| arg.to_vec());
+                            RespValue::Array(Some(vec![
+                                RespValue::BulkString(Some(b"pong".to_vec())),
+                                RespValue::BulkString(Some(message)),
+                            ]))
+                        }
+                        _ if self.transaction.intercepts(&cmd) => {
+                            // ACL is checked at queue time, as in Redis
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                self.transaction.mark_dirty();
+                                RespValue::Error(acl_err)
+                            } else if matches!(cmd, Command::Exec) {
+                                let queued = self.transaction.queued().to_vec();
+                                let outcome = if self.timing_commands() {
+                                    let (outcome, shard_time) =
+                                        time_shard_execution(self.transaction.exec(&self.state))
+                                            .await;
+                                    if let Some(duration) = shard_time {
+                                        self.record_command_time(COMMAND, duration, || {
+                                            Self::frame_args(&resp_value)
+                                        });
+                                    }
+                                    outcome
+                                } else {
+                                    self.transaction.exec(&self.state).await
+                                };
+                                let reply = self.finish_exec(outcome).await;
+                                self.follow_queued_select(&queued, &reply);
+                                if self.protocol >= 3 {
+                                    upgrade_exec_reply(&queued, reply)
+                                } else {
+                                    reply
+                                }
+                            } else {
+                                self.transaction.handle(&self.state, &cmd).await
+                            }
+                        }
                         Command::Auth { username, password } => {
                             self.handle_auth(username.as_deref(), password)
                         }
+                        Command::Hello {
+                            protover,
+                            auth,
+                            setname,
+                        } => self.handle_hello(*protover, auth.as_ref(), setname.as_deref()),
+                        Command::Select(db) => {
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                RespValue::Error(acl_err)
+                            } else {
+                                self.handle_select(*db)
+                            }
+                        }
                         Command::AclWhoami => self.handle_acl_whoami(),
                         Command::AclList => self.handle_acl_list(),
                         Command::AclUsers => self.handle_acl_users(),
@@ -343,26 +604,140 @@ where
                         Command::AclDelUser { usernames } => self.handle_acl_deluser(usernames),
                         Command::AclCat { category } => self.handle_acl_cat(category.as_deref()),
                         Command::AclGenPass { bits } => self.handle_acl_genpass(*bits),
+                        Command::Subscribe(_)
+                        | Command::Unsubscribe(_)
+                        | Command::PSubscribe(_)
+                        | Command::PUnsubscribe(_)
+                        | Command::SSubscribe(_)
+                        | Command::SUnsubscribe(_) => {
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                RespValue::Error(acl_err)
+                            } else {
+                                // One confirmation per channel: all but the last go out first
+                                let mut replies = self.handle_subscription(&cmd);
+                                let last = replies.pop().unwrap_or(RespValue::Array(None));
+                                for reply in replies {
+                                    let reply = self.push_frame(reply);
+                                    Self::encode_resp_into(&reply, &mut self.write_buffer);
+                                }
+                                last
+                            }
+                        }
+                        Command::ClusterSlots
+                        | Command::ClusterShards
+                        | Command::ClusterNodes
+                        | Command::ClusterInfo
+                        | Command::ClusterMyId
+                        | Command::ClusterKeySlot(_)
+                        | Command::ClusterSetSlot { .. }
+                        | Command::Asking
+                        | Command::ReadOnly
+                        | Command::ReadWrite => {
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                RespValue::Error(acl_err)
+                            } else {
+                                self.handle_cluster_command(&cmd)
+                            }
+                        }
+                        Command::ClientId
+                        | Command::ClientTracking { .. }
+                        | Command::ClientCaching(_)
+                        | Command::ClientGetRedir
+                        | Command::ClientList { .. }
+                        | Command::ClientInfo
+                        | Command::ClientSetName(_)
+                        | Command::ClientGetName
+                        | Command::ClientKill(_)
+                        | Command::ClientPause { .. }
+                        | Command::ClientUnpause
+                        | Command::ClientNoEvict(_)
+                        | Command::ClientReply(_) => {
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                RespValue::Error(acl_err)
+                            } else {
+                                self.handle_client_command(&cmd)
+                            }
+                        }
+                        Command::Monitor => {
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                RespValue::Error(acl_err)
+                            } else {
+                                self.handle_monitor()
+                            }
+                        }
+                        Command::ConfigGet(_)
+                        | Command::ConfigSet(_)
+                        | Command::ConfigRewrite
+                        | Command::ConfigResetStat => {
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                RespValue::Error(acl_err)
+                            } else {
+                                self.handle_config_command(&cmd)
+                            }
+                        }
+                        Command::Publish { .. }
+                        | Command::SPublish { .. }
+                        | Command::PubSubChannels(_)
+                        | Command::PubSubNumSub(_)
+                        | Command::PubSubNumPat
+                        | Command::PubSubShardChannels(_)
+                        | Command::PubSubShardNumSub(_) => {
+                            if let Err(acl_err) = self.check_acl_permission(&cmd) {
+                                RespValue::Error(acl_err)
+                            } else {
+                                self.handle_pubsub_query(&cmd).await
+                            }
+                        }
                         _ => {
                             // Check ACL permissions for regular commands
                             if let Err(acl_err) = self.check_acl_permission(&cmd) {
                                 RespValue::Error(acl_err)
+                            } else if cmd.block_timeout_ms().is_some() {
+                                match self.execute_blocking_command(&cmd).await {
+                                    Some(reply) => reply,
+                                    None => return CommandResult::Closed,
+                                }
+                            } else if self.timing_commands() {
+                                let (reply, shard_time) =
+                                    time_shard_execution(self.execute_on_shards(&cmd)).await;
+                                if let Some(duration) = shard_time {
+                                    self.record_command_time(COMMAND, duration, || {
+                                        Self::frame_args(&resp_value)
+                                    });
+                                }
+                                reply
                             } else {
-                                self.state.execute(&cmd).await
+                                self.execute_on_shards(&cmd).await
                             }
                         }
                     };
+                    // Fed after running, so SELECT shows the new database
+                    if self.clients.has_monitors() && ClientRegistry::feeds_monitors(&cmd) {
+                        self.feed_monitors(&cmd, &resp_value);
+                    }
+                    // CLIENT CACHING only covers the command right after it
+                    if !matches!(cmd, Command::ClientCaching(_)) {
+                        self.caching = None;
+                    }
+
+                    let response = self.protocol_reply(&cmd, response);
 
                     let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
                     let success = !matches!(&response, RespValue::Error(_));
                     self.metrics.record_command(cmd_name, duration_ms, success);
 
-                    Self::encode_resp_into(&response, &mut self.write_buffer);
+                    if self.reply_enabled(Some(&cmd)) {
+                        Self::encode_resp_into(&response, &mut self.write_buffer);
+                    }
+                    self.publish_client_stats();
                     CommandResult::Executed
                 }
                 Err(e) => {
                     self.metrics.record_command("PARSE_ERROR", 0.0, false);
-                    Self::encode_error_into(&e, &mut self.write_buffer);
+                    self.transaction.mark_dirty();
+                    if self.reply_enabled(None) {
+                        Self::encode_error_into(&e, &mut self.write_buffer);
+                    }
                     CommandResult::Executed
                 }
             },
@@ -371,6 +746,408 @@ where
         }
     }
 
+    /// Run the connection-level commands an EXEC left over and assemble its reply
+    async fn finish_exec(&mut self, outcome: ExecOutcome) -> RespValue {
+        let (mut replies, deferred) = match outcome {
+            ExecOutcome::Done(reply) => return reply,
+            ExecOutcome::Deferred { replies, deferred } => (replies, deferred),
+        };
+        for (index, cmd) in deferred {
+            replies[index] = match cmd {
+                Command::ConfigGet(_) => self.handle_config_command(&cmd),
+                Command::ClientId => self.handle_client_command(&cmd),
+                _ => self.handle_pubsub_query(&cmd).await,
+            };
+        }
+        RespValue::Array(Some(replies))
+    }
+
+    /// Run a regular command on the shards, tracking its reads for client-side
+    /// caching when enabled
+    async fn execute_on_shards(&self, cmd: &Command) -> RespValue {
+        if self.tracking.is_some() {
+            let track_reads = self.tracks_reads();
+            self.state
+                .execute_tracked(cmd, self.subscriber_id, track_reads)
+                .await
+        } else {
+            self.state.execute(cmd).await
+        }
+    }
+
+    /// Run a blocking command (BLPOP, BLMOVE, XREAD BLOCK, ...) while watching
+    /// the socket and CLIENT KILL. Returns None once the client is gone: the
+    /// reply future is dropped, so the shard discards the waiter instead of
+    /// handing it the next push. Input pipelined behind the command is kept
+    /// in the buffer for after the reply.
+    async fn execute_blocking_command(&mut self, cmd: &Command) -> Option<RespValue> {
+        let state = &self.state;
+        let subscriber_id = self.subscriber_id;
+        let track_reads = self.tracking.is_some().then(|| self.tracks_reads());
+        let reply = async move {
+            match track_reads {
+                Some(track_reads) => {
+                    state
+                        .execute_tracked(cmd, subscriber_id, track_reads)
+                        .await
+                }
+                None => state.execute(cmd).await,
+            }
+        };
+        tokio::pin!(reply);
+
+        let mut read_buf = vec![0u8; self.config.read_buffer_size];
+        loop {
+            tokio::select! {
+                biased;
+                reply = &mut reply => return Some(reply),
+                _ = self.client.killed() => {
+                    info!("Client killed while blocked: {}", self.client_addr);
+                    return None;
+                }
+                read = self.stream.read(&mut read_buf) => match read {
+                    Ok(0) => {
+                        info!("Client disconnected while blocked: {}", self.client_addr);
+                        return None;
+                    }
+                    Ok(n) if self.buffer.len() + n > self.config.max_buffer_size => {
+                        error!("Buffer overflow from {}, closing connection", self.client_addr);
+                        return None;
+                    }
+                    Ok(n) => self.buffer.extend_from_slice(&read_buf[..n]),
+                    Err(e) => {
+                        debug!("Read error from {}: {}", self.client_addr, e);
+                        return None;
+                    }
+                },
+            }
+        }
+    }
+
+    /// Arguments of the command in `frame`, the name included
+    fn frame_args(frame: &RespValueZeroCopy) -> Vec<&[u8]> {
+        let RespValueZeroCopy::Array(Some(items)) = frame else {
+            return Vec::new();
+        };
+        items
+            .iter()
+            .filter_map(|item| match item {
+                RespValueZeroCopy::BulkString(Some(bytes))
+                | RespValueZeroCopy::SimpleString(bytes) => Some(bytes.as_ref()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether SLOWLOG or LATENCY wants to know how long commands take
+    #[inline]
+    fn timing_commands(&self) -> bool {
+        self.state.slowlog().threshold().is_some() || self.state.latency().threshold() > 0
+    }
+
+    /// Report a command that spent `duration` in shards to LATENCY as `event`
+    /// and to the SLOWLOG; `args` is only built when the SLOWLOG keeps it
+    fn record_command_time<'a>(
+        &self,
+        event: &'static str,
+        duration: Duration,
+        args: impl FnOnce() -> Vec<&'a [u8]>,
+    ) {
+        self.state.latency().add_sample(event, duration);
+        let slowlog = self.state.slowlog();
+        if slowlog
+            .threshold()
+            .is_some_and(|threshold| duration >= threshold)
+        {
+            slowlog.record(
+                duration,
+                &args(),
+                &self.client_addr,
+                self.client_name.as_deref(),
+            );
+        }
+    }
+
+    /// CLIENT REPLY: whether the reply to `cmd` (None: a parse error) is sent
+    fn reply_enabled(&mut self, cmd: Option<&Command>) -> bool {
+        if let Some(Command::ClientReply(mode)) = cmd {
+            return *mode == ClientReplyMode::On;
+        }
+        match self.reply_mode {
+            ClientReplyMode::On => true,
+            ClientReplyMode::Off => false,
+            ClientReplyMode::Skip => {
+                self.reply_mode = ClientReplyMode::On;
+                false
+            }
+        }
+    }
+
+    /// Refresh what CLIENT LIST reports about this connection
+    fn publish_client_stats(&self) {
+        let multi = self
+            .transaction
+            .in_multi()
+            .then(|| self.transaction.queued_len());
+        self.client.update(|stats| {
+            stats.db = self.state.selected_db();
+            stats.sub = self.subscribed_channels.len();
+            stats.psub = self.subscribed_patterns.len();
+            stats.ssub = self.subscribed_shard_channels.len();
+            stats.multi = multi;
+            stats.tracking = self.tracking.is_some();
+            stats.bcast = self.tracking.as_ref().is_some_and(|t| t.bcast);
+            stats.redirect = self.tracking.as_ref().and_then(|t| t.redirect);
+            stats.no_evict = self.no_evict;
+            stats.protocol = self.protocol;
+            stats.query_buffer = self.buffer.len();
+            stats.query_buffer_free = self.buffer.capacity() - self.buffer.len();
+            stats.output_buffer = self.write_buffer.len();
+        });
+    }
+
+    /// CLIENT SETNAME / HELLO SETNAME; an empty name clears it
+    fn set_client_name(&mut self, name: &str) -> Result<(), RespValue> {
+        if name.bytes().any(|b| !(b'!'..=b'~').contains(&b)) {
+            return Err(RespValue::Error(
+                "ERR Client names cannot contain spaces, newlines or special characters."
+                    .to_string(),
+            ));
+        }
+        self.client_name = (!name.is_empty()).then(|| name.to_string());
+        let name = self.client_name.clone();
+        self.client.update(|stats| stats.name = name);
+        Ok(())
+    }
+
+    /// Shape the RESP2 `reply` to `cmd` for the negotiated protocol
+    fn protocol_reply(&self, cmd: &Command, reply: RespValue) -> RespValue {
+        if self.protocol < 3 {
+            return reply;
+        }
+        match cmd {
+            Command::Subscribe(_)
+            | Command::Unsubscribe(_)
+            | Command::PSubscribe(_)
+            | Command::PUnsubscribe(_)
+            | Command::SSubscribe(_)
+            | Command::SUnsubscribe(_) => into_push(reply),
+            // Already upgraded per queued command
+            Command::Exec => reply,
+            _ => upgrade_reply(cmd, reply),
+        }
+    }
+
+    /// Pub/Sub frames are arrays in RESP2 and push frames in RESP3
+    #[inline]
+    fn push_frame(&self, frame: RespValue) -> RespValue {
+        if self.protocol >= 3 {
+            into_push(frame)
+        } else {
+            frame.into_resp2()
+        }
+    }
+
+    /// A connection with any active subscription is in subscriber mode
+    #[inline]
+    fn in_subscriber_mode(&self) -> bool {
+        !self.subscribed_channels.is_empty()
+            || !self.subscribed_patterns.is_empty()
+            || !self.subscribed_shard_channels.is_empty()
+    }
+
+    fn allowed_in_subscriber_mode(cmd: &Command) -> bool {
+        matches!(
+            cmd,
+            Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::PSubscribe(_)
+                | Command::PUnsubscribe(_)
+                | Command::SSubscribe(_)
+                | Command::SUnsubscribe(_)
+                | Command::Ping
+        )
+    }
+
+    /// Handle (P|S)SUBSCRIBE and (P|S)UNSUBSCRIBE, returning one confirmation per name
+    ///
+    /// Confirmations are `[kind, name, count]` where count is the number of
+    /// channels plus patterns still subscribed (shard channels count separately).
+    fn handle_subscription(&mut self, cmd: &Command) -> Vec<RespValue> {
+        let (kind, names, subscribe, reply_kind) = match cmd {
+            Command::Subscribe(names) => (SubscriptionKind::Channel, names, true, "subscribe"),
+            Command::Unsubscribe(names) => (SubscriptionKind::Channel, names, false, "unsubscribe"),
+            Command::PSubscribe(names) => (SubscriptionKind::Pattern, names, true, "psubscribe"),
+            Command::PUnsubscribe(names) => {
+                (SubscriptionKind::Pattern, names, false, "punsubscribe")
+            }
+            Command::SSubscribe(names) => (SubscriptionKind::Shard, names, true, "ssubscribe"),
+            Command::SUnsubscribe(names) => (SubscriptionKind::Shard, names, false, "sunsubscribe"),
+            _ => {
+                debug_assert!(false, "handle_subscription called with {}", cmd.name());
+                return Vec::new();
+            }
+        };
+
+        // Unsubscribing with no names drops every subscription of that kind
+        let names: Vec<String> = if !subscribe && names.is_empty() {
+            match kind {
+                SubscriptionKind::Channel => self.subscribed_channels.iter().cloned().collect(),
+                SubscriptionKind::Pattern => self.subscribed_patterns.iter().cloned().collect(),
+                SubscriptionKind::Shard => self.subscribed_shard_channels.iter().cloned().collect(),
+            }
+        } else {
+            names.clone()
+        };
+
+        let mut replies = Vec::with_capacity(names.len().max(1));
+        for name in &names {
+            let set = match kind {
+                SubscriptionKind::Channel => &mut self.subscribed_channels,
+                SubscriptionKind::Pattern => &mut self.subscribed_patterns,
+                SubscriptionKind::Shard => &mut self.subscribed_shard_channels,
+            };
+            if subscribe {
+                set.insert(name.clone());
+            } else {
+                set.remove(name);
+            }
+            let count = match kind {
+                SubscriptionKind::Shard => self.subscribed_shard_channels.len(),
+                _ => self.subscribed_channels.len() + self.subscribed_patterns.len(),
+            };
+            replies.push(RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(reply_kind.as_bytes().to_vec())),
+                RespValue::BulkString(Some(name.as_bytes().to_vec())),
+                RespValue::Integer(count as i64),
+            ])));
+        }
+
+        if subscribe {
+            self.pubsub
+                .subscribe(self.subscriber_id, kind, names, self.push_tx.clone());
+        } else if names.is_empty() {
+            // Nothing to unsubscribe from: a single reply with a null name
+            let count = match kind {
+                SubscriptionKind::Shard => self.subscribed_shard_channels.len(),
+                _ => self.subscribed_channels.len() + self.subscribed_patterns.len(),
+            };
+            replies.push(RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(reply_kind.as_bytes().to_vec())),
+                RespValue::BulkString(None),
+                RespValue::Integer(count as i64),
+            ])));
+        } else {
+            self.pubsub.unsubscribe(self.subscriber_id, kind, names);
+        }
+
+        replies
+    }
+
+    /// Handle PUBLISH/SPUBLISH and PUBSUB introspection via the broker
+    async fn handle_pubsub_query(&self, cmd: &Command) -> RespValue {
+        let bulk = |s: &str| RespValue::BulkString(Some(s.as_bytes().to_vec()));
+        match cmd {
+            Command::Publish { channel, message } => {
+                let delivered = self
+                    .pubsub
+                    .publish(false, channel.clone(), message.as_bytes().to_vec())
+                    .await;
+                RespValue::Integer(delivered as i64)
+            }
+            Command::SPublish { channel, message } => {
+                let delivered = self
+                    .pubsub
+                    .publish(true, channel.clone(), message.as_bytes().to_vec())
+                    .await;
+                RespValue::Integer(delivered as i64)
+            }
+            Command::PubSubChannels(pattern) | Command::PubSubShardChannels(pattern) => {
+                let shard = matches!(cmd, Command::PubSubShardChannels(_));
+                let channels = self.pubsub.channels(shard, pattern.clone()).await;
+                RespValue::Array(Some(channels.iter().map(|c| bulk(c)).collect()))
+            }
+            Command::PubSubNumSub(channels) | Command::PubSubShardNumSub(channels) => {
+                let shard = matches!(cmd, Command::PubSubShardNumSub(_));
+                let counts = self.pubsub.numsub(shard, channels.clone()).await;
+                RespValue::Array(Some(
+                    channels
+                        .iter()
+                        .zip(counts)
+                        .flat_map(|(c, n)| [bulk(c), RespValue::Integer(n as i64)])
+                        .collect(),
+                ))
+            }
+            Command::PubSubNumPat => RespValue::Integer(self.pubsub.numpat().await as i64),
+            _ => {
+                debug_assert!(false, "handle_pubsub_query called with {}", cmd.name());
+                RespValue::Error("ERR unsupported pub/sub command".to_string())
+            }
+        }
+    }
+
+    /// Cluster mode: the MOVED, ASK or CROSSSLOT error to send instead of
+    /// running `cmd`, if its keys are not served here. Consumes the ASKING flag.
+    async fn cluster_redirect(&mut self, cmd: &Command) -> Option<RespValue> {
+        let asking = std::mem::take(&mut self.asking);
+        let cluster = self.state.cluster()?;
+        // KEYS takes a pattern, not a key
+        if matches!(cmd, Command::Keys(_)) {
+            return None;
+        }
+        let keys = cmd.get_keys();
+        let route = cluster.read().route(&keys, asking);
+        match route {
+            SlotRoute::Local => None,
+            SlotRoute::Redirect(error) => Some(error),
+            SlotRoute::Migrating { slot, target } => {
+                // Keys still here are served locally; missing ones may already
+                // have been moved to the target
+                let present = self.state.execute(&Command::Exists(keys.clone())).await;
+                if present == RespValue::Integer(keys.len() as i64) {
+                    None
+                } else {
+                    Some(RespValue::Error(format!("ASK {} {}", slot, target)))
+                }
+            }
+        }
+    }
+
+    /// Handle CLUSTER subcommands, ASKING, READONLY and READWRITE
+    fn handle_cluster_command(&mut self, cmd: &Command) -> RespValue {
+        let Some(cluster) = self.state.cluster().cloned() else {
+            return RespValue::Error("ERR This instance has cluster support disabled".to_string());
+        };
+        match cmd {
+            Command::ClusterSlots => cluster.read().slots_reply(),
+            Command::ClusterShards => cluster.read().shards_reply(),
+            Command::ClusterNodes => cluster.read().nodes_reply(),
+            Command::ClusterInfo => cluster.read().info_reply(),
+            Command::ClusterMyId => {
+                RespValue::BulkString(Some(cluster.read().myself().id.as_bytes().to_vec()))
+            }
+            Command::ClusterKeySlot(key) => {
+                RespValue::Integer(key_hash_slot(key.as_bytes()) as i64)
+            }
+            Command::ClusterSetSlot { slot, action } => {
+                match cluster.write().set_slot(*slot, action) {
+                    Ok(()) => RespValue::SimpleString("OK".to_string()),
+                    Err(e) => RespValue::Error(e),
+                }
+            }
+            Command::Asking => {
+                self.asking = true;
+                RespValue::SimpleString("OK".to_string())
+            }
+            // No replicas: every read is served by the slot owner anyway
+            Command::ReadOnly | Command::ReadWrite => RespValue::SimpleString("OK".to_string()),
+            _ => {
+                debug_assert!(false, "handle_cluster_command called with {}", cmd.name());
+                RespValue::Error("ERR unsupported cluster command".to_string())
+            }
+        }
+    }
+
     /// Check ACL permissions for a command
     fn check_acl_permission(&self, cmd: &Command) -> Result<(), String> {
         let manager = self.acl_manager.read();
@@ -385,7 +1162,7 @@ where
 
         // Get the keys involved in this command
         let owned_keys = cmd.get_keys();
-        let keys: Vec<&str> = owned_keys.iter().map(|s| s.as_str()).collect();
+        let keys: Vec<&[u8]> = owned_keys.iter().map(SDS::as_bytes).collect();
 
         // Check permissions
         manager
@@ -401,6 +1178,8 @@ where
         match manager.authenticate(username, password) {
             Ok(user) => {
                 drop(manager); // Release read lock before mutating self
+                let name = user.name.clone();
+                self.client.update(|stats| stats.user = Some(name));
                 self.authenticated_user = Some(user);
                 info!("Client {} authenticated as '{}'", self.client_addr, username);
                 RespValue::SimpleString("OK".to_string())
@@ -415,6 +1194,284 @@ where
         }
     }
 
+    /// Handle CLIENT subcommands
+    fn handle_client_command(&mut self, cmd: &Command) -> RespValue {
+        let ok = || RespValue::SimpleString("OK".to_string());
+        match cmd {
+            Command::ClientId => RespValue::Integer(self.subscriber_id as i64),
+            Command::ClientList { client_type, ids } => RespValue::BulkString(Some(
+                self.clients.list(*client_type, ids).into_bytes(),
+            )),
+            Command::ClientInfo => {
+                self.publish_client_stats();
+                let mut line = self.client.info_line();
+                line.push('\n');
+                RespValue::BulkString(Some(line.into_bytes()))
+            }
+            Command::ClientSetName(name) => match self.set_client_name(name) {
+                Ok(()) => ok(),
+                Err(e) => e,
+            },
+            Command::ClientGetName => {
+                RespValue::BulkString(self.client_name.as_ref().map(|n| n.as_bytes().to_vec()))
+            }
+            Command::ClientKill(filter) => {
+                let killed = self.clients.kill(filter, self.subscriber_id);
+                match (filter.legacy, killed) {
+                    (true, 0) => RespValue::Error("ERR No such client".to_string()),
+                    (true, _) => ok(),
+                    (false, killed) => RespValue::Integer(killed as i64),
+                }
+            }
+            Command::ClientPause { timeout_ms, mode } => {
+                self.clients
+                    .pause(Duration::from_millis(*timeout_ms), *mode);
+                ok()
+            }
+            Command::ClientUnpause => {
+                self.clients.unpause();
+                ok()
+            }
+            Command::ClientNoEvict(on) => {
+                self.no_evict = *on;
+                ok()
+            }
+            Command::ClientReply(mode) => {
+                self.reply_mode = *mode;
+                ok()
+            }
+            Command::ClientTracking { on: false, .. } => {
+                if self.tracking.take().is_some() {
+                    self.state.disable_tracking(self.subscriber_id);
+                }
+                self.caching = None;
+                RespValue::SimpleString("OK".to_string())
+            }
+            Command::ClientTracking { on: true, options } => {
+                if self
+                    .tracking
+                    .as_ref()
+                    .is_some_and(|current| current.bcast != options.bcast)
+                {
+                    return RespValue::Error(
+                        "ERR You can't switch BCAST mode on/off before disabling tracking for this client, and then re-enabling it with a different mode.".to_string(),
+                    );
+                }
+                let target = match options.redirect {
+                    Some(client) => InvalidationTarget::Redirect {
+                        pubsub: self.pubsub.clone(),
+                        client,
+                    },
+                    // Invalidations travel on this connection as push frames
+                    None if self.protocol >= 3 => InvalidationTarget::Push(self.push_tx.clone()),
+                    None => {
+                        return RespValue::Error(
+                            "ERR CLIENT TRACKING without REDIRECT requires RESP3 (HELLO 3)"
+                                .to_string(),
+                        )
+                    }
+                };
+                let bcast_prefixes = options.bcast.then(|| {
+                    if options.prefixes.is_empty() {
+                        vec![SDS::from("")]
+                    } else {
+                        options.prefixes.clone()
+                    }
+                });
+                self.state.enable_tracking(
+                    self.subscriber_id,
+                    TrackedClient {
+                        target,
+                        bcast_prefixes,
+                        noloop: options.noloop,
+                    },
+                );
+                self.tracking = Some(options.clone());
+                RespValue::SimpleString("OK".to_string())
+            }
+            Command::ClientCaching(yes) => match &self.tracking {
+                Some(options) if options.optin && *yes || options.optout && !*yes => {
+                    self.caching = Some(*yes);
+                    RespValue::SimpleString("OK".to_string())
+                }
+                Some(options) if options.optin => RespValue::Error(
+                    "ERR CLIENT CACHING NO is only valid when tracking is enabled in OPTOUT mode."
+                        .to_string(),
+                ),
+                Some(options) if options.optout => RespValue::Error(
+                    "ERR CLIENT CACHING YES is only valid when tracking is enabled in OPTIN mode."
+                        .to_string(),
+                ),
+                _ => RespValue::Error(
+                    "ERR CLIENT CACHING can be called only when the client is in tracking mode with OPTIN or OPTOUT mode enabled".to_string(),
+                ),
+            },
+            Command::ClientGetRedir => match &self.tracking {
+                None => RespValue::Integer(-1),
+                Some(options) => RespValue::Integer(options.redirect.map_or(0, |id| id as i64)),
+            },
+            _ => {
+                debug_assert!(false, "Not a CLIENT command: {}", cmd.name());
+                RespValue::Error("ERR unknown subcommand".to_string())
+            }
+        }
+    }
+
+    /// Handle CONFIG subcommands via the shared registry
+    fn handle_config_command(&self, cmd: &Command) -> RespValue {
+        let ok = || RespValue::SimpleString("OK".to_string());
+        let bulk = |s: &str| RespValue::BulkString(Some(s.as_bytes().to_vec()));
+        match cmd {
+            Command::ConfigGet(patterns) => RespValue::Array(Some(
+                self.server_config
+                    .get(patterns)
+                    .iter()
+                    .flat_map(|(name, value)| [bulk(name), bulk(value)])
+                    .collect(),
+            )),
+            Command::ConfigSet(pairs) => match self.server_config.set(pairs) {
+                Ok(()) => ok(),
+                Err(e) => RespValue::Error(e),
+            },
+            Command::ConfigRewrite => match self.server_config.rewrite() {
+                Ok(()) => ok(),
+                Err(e) => RespValue::Error(e),
+            },
+            Command::ConfigResetStat => {
+                self.state.reset_stats();
+                ok()
+            }
+            _ => {
+                debug_assert!(false, "Not a CONFIG command: {}", cmd.name());
+                RespValue::Error("ERR unknown subcommand".to_string())
+            }
+        }
+    }
+
+    /// Whether the keys of the next read are remembered for invalidation:
+    /// never in BCAST mode, and per CLIENT CACHING with OPTIN/OPTOUT
+    fn tracks_reads(&self) -> bool {
+        match &self.tracking {
+            None => false,
+            Some(options) if options.bcast => false,
+            Some(options) if options.optin => self.caching == Some(true),
+            Some(options) if options.optout => self.caching != Some(false),
+            Some(_) => true,
+        }
+    }
+
+    /// MONITOR: from now on this connection is fed every command any client
+    /// runs
+    fn handle_monitor(&mut self) -> RespValue {
+        if !self.monitor {
+            self.monitor = true;
+            self.clients
+                .add_monitor(self.subscriber_id, self.push_tx.clone());
+            self.client.update(|stats| stats.monitor = true);
+        }
+        RespValue::SimpleString("OK".to_string())
+    }
+
+    /// Show `cmd`, parsed from `frame`, to the MONITOR connections. Passwords
+    /// given to AUTH and HELLO are redacted, as in Redis.
+    fn feed_monitors(&self, cmd: &Command, frame: &RespValueZeroCopy) {
+        const REDACTED: &[u8] = b"(redacted)";
+        let mut args = Self::frame_args(frame);
+        match cmd {
+            Command::Auth { .. } => args[1..].fill(REDACTED),
+            Command::Hello { auth: Some(_), .. } => {
+                if let Some(at) = args
+                    .iter()
+                    .position(|arg| arg.eq_ignore_ascii_case(b"AUTH"))
+                {
+                    let end = args.len().min(at + 3);
+                    args[at + 1..end].fill(REDACTED);
+                }
+            }
+            _ => {}
+        }
+        self.clients
+            .feed_monitors(self.state.selected_db(), &self.client_addr, &args);
+    }
+
+    /// SELECT: the following commands run in database `db`
+    fn handle_select(&mut self, db: usize) -> RespValue {
+        if db != 0 && self.state.cluster().is_some() {
+            return RespValue::Error("ERR SELECT is not allowed in cluster mode".to_string());
+        }
+        if db >= self.state.databases() {
+            return RespValue::Error("ERR DB index is out of range".to_string());
+        }
+        self.state = self.state.with_db(db);
+        RespValue::SimpleString("OK".to_string())
+    }
+
+    /// A SELECT that EXEC ran successfully stays in effect after the transaction
+    fn follow_queued_select(&mut self, queued: &[Command], reply: &RespValue) {
+        let RespValue::Array(Some(replies)) = reply else {
+            return;
+        };
+        for (cmd, reply) in queued.iter().zip(replies) {
+            if let (Command::Select(db), RespValue::SimpleString(_)) = (cmd, reply) {
+                self.state = self.state.with_db(*db);
+            }
+        }
+    }
+
+    /// Handle HELLO: optionally authenticate, name the client and switch protocol
+    ///
+    /// Replies with a map describing the server, flattened to an array in RESP2.
+    fn handle_hello(
+        &mut self,
+        protover: Option<u32>,
+        auth: Option<&(String, String)>,
+        setname: Option<&str>,
+    ) -> RespValue {
+        if protover.is_some_and(|v| !(2..=3).contains(&v)) {
+            return RespValue::Error("NOPROTO unsupported protocol version".to_string());
+        }
+        if let Some((username, password)) = auth {
+            let reply = self.handle_auth(Some(username), password);
+            if matches!(reply, RespValue::Error(_)) {
+                return reply;
+            }
+        }
+        if self.authenticated_user.is_none() {
+            return RespValue::Error(
+                "NOAUTH HELLO must be called with the client already authenticated, otherwise the HELLO <proto> AUTH <user> <pass> option can be used to authenticate the client and select the RESP protocol version at the same time".to_string(),
+            );
+        }
+        if let Some(name) = setname {
+            if let Err(e) = self.set_client_name(name) {
+                return e;
+            }
+        }
+        if let Some(version) = protover {
+            self.protocol = version;
+        }
+
+        let bulk = |s: &str| RespValue::BulkString(Some(s.as_bytes().to_vec()));
+        let mode = if self.state.cluster().is_some() {
+            "cluster"
+        } else {
+            "standalone"
+        };
+        let reply = RespValue::Map(vec![
+            (bulk("server"), bulk("redis")),
+            (bulk("version"), bulk(env!("CARGO_PKG_VERSION"))),
+            (bulk("proto"), RespValue::Integer(self.protocol as i64)),
+            (bulk("id"), RespValue::Integer(self.subscriber_id as i64)),
+            (bulk("mode"), bulk(mode)),
+            (bulk("role"), bulk("master")),
+            (bulk("modules"), RespValue::Array(Some(vec![]))),
+        ]);
+        if self.protocol >= 3 {
+            reply
+        } else {
+            reply.into_resp2()
+        }
+    }
+
     /// Handle ACL WHOAMI command
     fn handle_acl_whoami(&self) -> RespValue {
         let name = match &self.authenticated_user {
@@ -558,7 +1615,8 @@ where
             // Return basic categories even without ACL feature
             let categories = vec![
                 "read", "write", "admin", "dangerous", "keyspace", "string", "list", "set", "hash",
-                "sortedset", "connection", "server",
+                "sortedset", "stream", "bitmap", "hyperloglog", "geo", "pubsub", "blocking",
+                "connection", "server",
             ];
             RespValue::Array(Some(
                 categories
@@ -582,7 +1640,7 @@ where
             // Simple fallback password generation
             use std::time::{SystemTime, UNIX_EPOCH};
             let bits = bits.unwrap_or(256).min(1024);
-            let bytes = (bits as usize + 7) / 8;
+            let bytes = (bits as usize).div_ceil(8);
             let seed = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
@@ -814,18 +1872,31 @@ where
 
         // Extract key as bytes::Bytes (zero-copy from buffer)
         let key = bytes::Bytes::copy_from_slice(&buf[key_start..key_start + key_len]);
+        if self.clients.has_monitors() {
+            // The name as sent, after "*2\r\n$3\r\n"
+            self.clients.feed_monitors(
+                self.state.selected_db(),
+                &self.client_addr,
+                &[&buf[8..11], &key],
+            );
+        }
 
-        // Consume the parsed bytes from buffer
-        let _ = self.buffer.split_to(total_needed);
+        // Consume the parsed bytes from buffer, keeping them for the SLOWLOG
+        let frame = self.buffer.split_to(total_needed);
 
         // Execute fast GET using pooled response slot (avoids oneshot allocation)
         let start = Instant::now();
         let response = self.state.pooled_fast_get(key).await;
-        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        let elapsed = start.elapsed();
+        let duration_ms = elapsed.as_secs_f64() * 1000.0;
         let success = !matches!(&response, RespValue::Error(_));
         self.metrics.record_command("GET", duration_ms, success);
+        self.record_command_time(FAST_COMMAND, elapsed, || {
+            vec![&frame[8..11], &frame[key_start..key_start + key_len]]
+        });
+        self.client.touch("GET");
 
-        Self::encode_resp_into(&response, &mut self.write_buffer);
+        Self::encode_reply_into(self.protocol, &response, &mut self.write_buffer);
         FastPathResult::Handled
     }
 
@@ -893,18 +1964,35 @@ where
         // Extract key and value as bytes::Bytes
         let key = bytes::Bytes::copy_from_slice(&buf[key_start..key_end]);
         let value = bytes::Bytes::copy_from_slice(&buf[val_start..val_start + val_len]);
+        if self.clients.has_monitors() {
+            // The name as sent, after "*3\r\n$3\r\n"
+            self.clients.feed_monitors(
+                self.state.selected_db(),
+                &self.client_addr,
+                &[&buf[8..11], &key, &value],
+            );
+        }
 
-        // Consume the parsed bytes
-        let _ = self.buffer.split_to(total_needed);
+        // Consume the parsed bytes, keeping them for the SLOWLOG
+        let frame = self.buffer.split_to(total_needed);
 
         // Execute fast SET using pooled response slot (avoids oneshot allocation)
         let start = Instant::now();
         let response = self.state.pooled_fast_set(key, value).await;
-        let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+        let elapsed = start.elapsed();
+        let duration_ms = elapsed.as_secs_f64() * 1000.0;
         let success = !matches!(&response, RespValue::Error(_));
         self.metrics.record_command("SET", duration_ms, success);
-
-        Self::encode_resp_into(&response, &mut self.write_buffer);
+        self.record_command_time(FAST_COMMAND, elapsed, || {
+            vec![
+                &frame[8..11],
+                &frame[key_start..key_end],
+                &frame[val_start..val_start + val_len],
+            ]
+        });
+        self.client.touch("SET");
+
+        Self::encode_reply_into(self.protocol, &response, &mut self.write_buffer);
         FastPathResult::Handled
     }
 
@@ -979,6 +2067,18 @@ where
                     Self::encode_resp_into(elem, buf);
                 }
             }
+            // RESP3 types are only sent to HELLO 3 clients, off the hot path
+            other => buf.extend_from_slice(&RespParser::encode(other)),
+        }
+    }
+
+    /// Encode a fast-path GET/SET reply: in RESP3 only the null differs
+    #[inline]
+    fn encode_reply_into(protocol: u32, value: &RespValue, buf: &mut BytesMut) {
+        if protocol >= 3 && matches!(value, RespValue::BulkString(None)) {
+            buf.extend_from_slice(b"_\r\n");
+        } else {
+            Self::encode_resp_into(value, buf);
         }
     }
 
@@ -995,6 +2095,8 @@ enum CommandResult {
     Executed,
     NeedMoreData,
     ParseError(String),
+    /// The client disconnected or was killed while blocked; close without replying
+    Closed,
 }
 
 /// Result of attempting fast path execution
@@ -1006,3 +2108,1105 @@ enum FastPathResult {
     /// Not a fast-path command, fall back to regular parsing
     NotFastPath,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observability::DatadogConfig;
+    use crate::production::{ClusterConfig, ClusterState, PubSubActor};
+    use tokio::io::DuplexStream;
+    use tokio::time::{timeout, Duration};
+
+    fn spawn_client(state: &ShardedActorState, pubsub: &PubSubHandle) -> DuplexStream {
+        spawn_registered_client(state, pubsub, &Arc::new(ClientRegistry::new()), "test")
+    }
+
+    fn spawn_registered_client(
+        state: &ShardedActorState,
+        pubsub: &PubSubHandle,
+        clients: &Arc<ClientRegistry>,
+        addr: &str,
+    ) -> DuplexStream {
+        spawn_handler(state, pubsub, clients, &test_config(), addr)
+    }
+
+    fn test_config() -> Arc<ConfigRegistry> {
+        Arc::new(ConfigRegistry::new(
+            None,
+            crate::production::PerformanceConfig::default(),
+            crate::production::ServerConfig::default(),
+        ))
+    }
+
+    #[allow(clippy::default_constructed_unit_structs)] // DatadogConfig has fields with `datadog`
+    fn spawn_handler(
+        state: &ShardedActorState,
+        pubsub: &PubSubHandle,
+        clients: &Arc<ClientRegistry>,
+        config: &Arc<ConfigRegistry>,
+        addr: &str,
+    ) -> DuplexStream {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let handler = OptimizedConnectionHandler::new(
+            server,
+            state.clone(),
+            addr.to_string(),
+            Arc::new(BufferPoolAsync::new(4, 4096)),
+            Arc::new(Metrics::new(&DatadogConfig::default())),
+            ConnectionConfig::default(),
+            Arc::new(RwLock::new(AclManager::new())),
+            None,
+            pubsub.clone(),
+            clients.clone(),
+            config.clone(),
+        );
+        tokio::spawn(handler.run());
+        client
+    }
+
+    /// Serve one real TCP connection, for tests that need socket EOF
+    #[allow(clippy::default_constructed_unit_structs)] // DatadogConfig has fields with `datadog`
+    async fn spawn_tcp_client(
+        state: &ShardedActorState,
+        pubsub: &PubSubHandle,
+        clients: &Arc<ClientRegistry>,
+    ) -> tokio::net::TcpStream {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = tokio::net::TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, addr) = listener.accept().await.unwrap();
+        let handler = OptimizedConnectionHandler::new(
+            server,
+            state.clone(),
+            addr.to_string(),
+            Arc::new(BufferPoolAsync::new(4, 4096)),
+            Arc::new(Metrics::new(&DatadogConfig::default())),
+            ConnectionConfig::default(),
+            Arc::new(RwLock::new(AclManager::new())),
+            None,
+            pubsub.clone(),
+            clients.clone(),
+            test_config(),
+        );
+        tokio::spawn(handler.run());
+        client
+    }
+
+    /// Wait until CLIENT LIST shows a client whose last command was `cmd`
+    async fn wait_for_client_cmd(clients: &ClientRegistry, cmd: &str) {
+        let needle = format!(" cmd={} ", cmd);
+        timeout(Duration::from_secs(2), async {
+            while !clients.list(None, &[]).contains(&needle) {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("client never ran the command");
+    }
+
+    fn encode(args: &[&str]) -> Vec<u8> {
+        let mut out = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            out.extend_from_slice(format!("${}\r\n{}\r\n", arg.len(), arg).as_bytes());
+        }
+        out
+    }
+
+    /// Read until `expected` bytes have arrived (or fail after a timeout)
+    async fn read_exact_reply(client: &mut DuplexStream, expected: &str) {
+        let mut buf = vec![0u8; expected.len()];
+        timeout(Duration::from_secs(2), client.read_exact(&mut buf))
+            .await
+            .expect("timed out waiting for reply")
+            .expect("read failed");
+        assert_eq!(String::from_utf8_lossy(&buf), expected);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_publish_roundtrip() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut subscriber = spawn_client(&state, &pubsub);
+        let mut publisher = spawn_client(&state, &pubsub);
+
+        subscriber
+            .write_all(&encode(&["SUBSCRIBE", "news", "alerts"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut subscriber,
+            "*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n*3\r\n$9\r\nsubscribe\r\n$6\r\nalerts\r\n:2\r\n",
+        )
+        .await;
+
+        publisher
+            .write_all(&encode(&["PUBLISH", "news", "hi"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut publisher, ":1\r\n").await;
+        read_exact_reply(
+            &mut subscriber,
+            "*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_mode_rejects_regular_commands() {
+        let state = ShardedActorState::with_shards(1);
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        client
+            .write_all(&encode(&["PSUBSCRIBE", "n*"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "*3\r\n$10\r\npsubscribe\r\n$2\r\nn*\r\n:1\r\n").await;
+
+        let expected = "-ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING are allowed in this context\r\n";
+        client.write_all(&encode(&["GET", "key"])).await.unwrap();
+        read_exact_reply(&mut client, expected).await;
+
+        client.write_all(&encode(&["PING"])).await.unwrap();
+        read_exact_reply(&mut client, "*2\r\n$4\r\npong\r\n$0\r\n\r\n").await;
+        client.write_all(&encode(&["PING", "hello"])).await.unwrap();
+        read_exact_reply(&mut client, "*2\r\n$4\r\npong\r\n$5\r\nhello\r\n").await;
+
+        // Leaving subscriber mode restores normal commands
+        client.write_all(&encode(&["PUNSUBSCRIBE"])).await.unwrap();
+        read_exact_reply(
+            &mut client,
+            "*3\r\n$12\r\npunsubscribe\r\n$2\r\nn*\r\n:0\r\n",
+        )
+        .await;
+        client.write_all(&encode(&["GET", "key"])).await.unwrap();
+        read_exact_reply(&mut client, "$-1\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_multi_exec_is_per_connection() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut a = spawn_client(&state, &pubsub);
+        let mut b = spawn_client(&state, &pubsub);
+
+        a.write_all(&encode(&["MULTI"])).await.unwrap();
+        read_exact_reply(&mut a, "+OK\r\n").await;
+        // SET would normally take the fast path; inside MULTI it must queue
+        a.write_all(&encode(&["SET", "k", "a"])).await.unwrap();
+        read_exact_reply(&mut a, "+QUEUED\r\n").await;
+
+        // b is not in a transaction and executes immediately
+        b.write_all(&encode(&["SET", "k", "b"])).await.unwrap();
+        read_exact_reply(&mut b, "+OK\r\n").await;
+
+        a.write_all(&encode(&["NOSUCHCMD"])).await.unwrap();
+        read_exact_reply(&mut a, "-ERR unknown command 'NOSUCHCMD'\r\n").await;
+        a.write_all(&encode(&["EXEC"])).await.unwrap();
+        read_exact_reply(
+            &mut a,
+            "-EXECABORT Transaction discarded because of previous errors.\r\n",
+        )
+        .await;
+
+        b.write_all(&encode(&["GET", "k"])).await.unwrap();
+        read_exact_reply(&mut b, "$1\r\nb\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_object_unknown_subcommand_reply() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        client
+            .write_all(&encode(&["OBJECT", "size", "k"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut client,
+            "-ERR unknown subcommand 'size'. Try OBJECT HELP.\r\n",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_hash_field_expire_numfields_replies() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        for cmd in ["HEXPIRE", "HPEXPIRE"] {
+            client
+                .write_all(&encode(&[cmd, "h", "10", "FIELDS", "0", "f"]))
+                .await
+                .unwrap();
+            read_exact_reply(
+                &mut client,
+                "-ERR Parameter `numFields` should be greater than 0\r\n",
+            )
+            .await;
+            client
+                .write_all(&encode(&[cmd, "h", "10", "FIELDS", "2", "f"]))
+                .await
+                .unwrap();
+            read_exact_reply(
+                &mut client,
+                "-ERR The `numfields` parameter must match the number of arguments\r\n",
+            )
+            .await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_runs_connection_commands_at_exec() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut subscriber = spawn_client(&state, &pubsub);
+        let mut client = spawn_client(&state, &pubsub);
+
+        subscriber
+            .write_all(&encode(&["SUBSCRIBE", "ch"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut subscriber,
+            "*3\r\n$9\r\nsubscribe\r\n$2\r\nch\r\n:1\r\n",
+        )
+        .await;
+
+        client.write_all(&encode(&["MULTI"])).await.unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        for args in [
+            &["SET", "k", "v"][..],
+            &["PUBLISH", "ch", "hi"],
+            &["CONFIG", "GET", "hz"],
+            &["CLIENT", "ID"],
+        ] {
+            client.write_all(&encode(args)).await.unwrap();
+            read_exact_reply(&mut client, "+QUEUED\r\n").await;
+        }
+        client.write_all(&encode(&["EXEC"])).await.unwrap();
+        let RespValue::Array(Some(replies)) = read_reply(&mut client).await else {
+            panic!("EXEC must reply with an array");
+        };
+        assert_eq!(replies.len(), 4);
+        assert_eq!(replies[0], RespValue::SimpleString("OK".to_string()));
+        assert_eq!(replies[1], RespValue::Integer(1));
+        assert_eq!(
+            replies[2],
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"hz".to_vec())),
+                RespValue::BulkString(Some(b"10".to_vec())),
+            ]))
+        );
+        assert!(matches!(replies[3], RespValue::Integer(_)));
+        read_exact_reply(
+            &mut subscriber,
+            "*3\r\n$7\r\nmessage\r\n$2\r\nch\r\n$2\r\nhi\r\n",
+        )
+        .await;
+
+        // Commands that change the connection or server cannot be queued
+        client.write_all(&encode(&["MULTI"])).await.unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        client
+            .write_all(&encode(&["CONFIG", "SET", "hz", "20"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut client,
+            "-ERR Command not allowed inside a transaction\r\n",
+        )
+        .await;
+        client.write_all(&encode(&["EXEC"])).await.unwrap();
+        read_exact_reply(
+            &mut client,
+            "-EXECABORT Transaction discarded because of previous errors.\r\n",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_select_is_per_connection() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut a = spawn_client(&state, &pubsub);
+        let mut b = spawn_client(&state, &pubsub);
+
+        // SET and GET take the fast path, which must follow SELECT too
+        a.write_all(&encode(&["SELECT", "2"])).await.unwrap();
+        read_exact_reply(&mut a, "+OK\r\n").await;
+        a.write_all(&encode(&["SET", "k", "two"])).await.unwrap();
+        read_exact_reply(&mut a, "+OK\r\n").await;
+        b.write_all(&encode(&["GET", "k"])).await.unwrap();
+        read_exact_reply(&mut b, "$-1\r\n").await;
+        b.write_all(&encode(&["SELECT", "16"])).await.unwrap();
+        read_exact_reply(&mut b, "-ERR DB index is out of range\r\n").await;
+
+        // SELECT queued in MULTI applies to the connection after EXEC
+        b.write_all(&encode(&["MULTI"])).await.unwrap();
+        read_exact_reply(&mut b, "+OK\r\n").await;
+        b.write_all(&encode(&["SELECT", "2"])).await.unwrap();
+        read_exact_reply(&mut b, "+QUEUED\r\n").await;
+        b.write_all(&encode(&["EXEC"])).await.unwrap();
+        read_exact_reply(&mut b, "*1\r\n+OK\r\n").await;
+        b.write_all(&encode(&["GET", "k"])).await.unwrap();
+        read_exact_reply(&mut b, "$3\r\ntwo\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_cluster_mode_redirects() {
+        let config = ClusterConfig::new(
+            vec![
+                "127.0.0.1:7000".to_string(),
+                "127.0.0.1:7001".to_string(),
+                "127.0.0.1:7002".to_string(),
+            ],
+            0,
+        );
+        let cluster = ClusterState::new(&config).unwrap();
+        let target_id = cluster.nodes()[1].id.clone();
+        let state = ShardedActorState::with_shards(2).with_cluster(cluster);
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        client
+            .write_all(&encode(&["CLUSTER", "KEYSLOT", "foo"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, ":12182\r\n").await;
+        // "foo" lives on the third node, "bar" (slot 5061) on this one
+        client.write_all(&encode(&["GET", "foo"])).await.unwrap();
+        read_exact_reply(&mut client, "-MOVED 12182 127.0.0.1:7002\r\n").await;
+        client
+            .write_all(&encode(&["SET", "bar", "v"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        client
+            .write_all(&encode(&["MGET", "foo", "bar"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut client,
+            "-CROSSSLOT Keys in request don't hash to the same slot\r\n",
+        )
+        .await;
+
+        // While slot 5061 migrates, existing keys stay here and missing ones ASK
+        client
+            .write_all(&encode(&[
+                "CLUSTER",
+                "SETSLOT",
+                "5061",
+                "MIGRATING",
+                &target_id,
+            ]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        client.write_all(&encode(&["GET", "bar"])).await.unwrap();
+        read_exact_reply(&mut client, "$1\r\nv\r\n").await;
+        client
+            .write_all(&encode(&["GET", "{bar}:new"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "-ASK 5061 127.0.0.1:7001\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_cluster_commands_without_cluster_mode() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        client
+            .write_all(&encode(&["CLUSTER", "SLOTS"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut client,
+            "-ERR This instance has cluster support disabled\r\n",
+        )
+        .await;
+        client
+            .write_all(&encode(&["SET", "foo", "v"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+    }
+
+    /// Read one complete reply frame (or fail after a timeout)
+    async fn read_reply(client: &mut DuplexStream) -> RespValue {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 1024];
+        loop {
+            if let Ok((value, consumed)) = RespParser::parse(&buf) {
+                assert_eq!(consumed, buf.len(), "trailing bytes after reply");
+                return value;
+            }
+            let n = timeout(Duration::from_secs(2), client.read(&mut chunk))
+                .await
+                .expect("timed out waiting for reply")
+                .expect("read failed");
+            assert!(n > 0, "connection closed");
+            buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hello_negotiates_resp3() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        client.write_all(&encode(&["HELLO", "4"])).await.unwrap();
+        read_exact_reply(&mut client, "-NOPROTO unsupported protocol version\r\n").await;
+
+        client
+            .write_all(&encode(&["HELLO", "3", "SETNAME", "app"]))
+            .await
+            .unwrap();
+        let RespValue::Map(fields) = read_reply(&mut client).await else {
+            panic!("HELLO 3 must reply with a map");
+        };
+        let field = |name: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| *k == RespValue::BulkString(Some(name.as_bytes().to_vec())))
+                .map(|(_, v)| v.clone())
+        };
+        assert_eq!(field("proto"), Some(RespValue::Integer(3)));
+        assert_eq!(
+            field("mode"),
+            Some(RespValue::BulkString(Some(b"standalone".to_vec())))
+        );
+
+        client
+            .write_all(&encode(&["HSET", "h", "f", "v"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, ":1\r\n").await;
+        client.write_all(&encode(&["HGETALL", "h"])).await.unwrap();
+        read_exact_reply(&mut client, "%1\r\n$1\r\nf\r\n$1\r\nv\r\n").await;
+        client
+            .write_all(&encode(&["GET", "missing"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "_\r\n").await;
+
+        client.write_all(&encode(&["MULTI"])).await.unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        client.write_all(&encode(&["HGETALL", "h"])).await.unwrap();
+        read_exact_reply(&mut client, "+QUEUED\r\n").await;
+        client.write_all(&encode(&["EXEC"])).await.unwrap();
+        read_exact_reply(&mut client, "*1\r\n%1\r\n$1\r\nf\r\n$1\r\nv\r\n").await;
+
+        // Back to RESP2: HELLO itself replies with a flat array
+        client.write_all(&encode(&["HELLO", "2"])).await.unwrap();
+        assert!(matches!(
+            read_reply(&mut client).await,
+            RespValue::Array(Some(_))
+        ));
+        client
+            .write_all(&encode(&["GET", "missing"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "$-1\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_resp3_pubsub_uses_push_frames() {
+        let state = ShardedActorState::with_shards(1);
+        let pubsub = PubSubActor::spawn();
+        let mut subscriber = spawn_client(&state, &pubsub);
+        let mut publisher = spawn_client(&state, &pubsub);
+
+        subscriber
+            .write_all(&encode(&["HELLO", "3"]))
+            .await
+            .unwrap();
+        read_reply(&mut subscriber).await;
+        subscriber
+            .write_all(&encode(&["SUBSCRIBE", "news"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut subscriber,
+            ">3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n",
+        )
+        .await;
+
+        // RESP3 subscribers may keep issuing regular commands
+        subscriber.write_all(&encode(&["GET", "k"])).await.unwrap();
+        read_exact_reply(&mut subscriber, "_\r\n").await;
+
+        publisher
+            .write_all(&encode(&["PUBLISH", "news", "hi"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut publisher, ":1\r\n").await;
+        read_exact_reply(
+            &mut subscriber,
+            ">3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$2\r\nhi\r\n",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_client_tracking_invalidates_read_keys() {
+        let state = ShardedActorState::with_shards(4);
+        let pubsub = PubSubActor::spawn();
+        let mut reader = spawn_client(&state, &pubsub);
+        let mut writer = spawn_client(&state, &pubsub);
+
+        // RESP2 has no push frames: invalidations need a REDIRECT
+        reader
+            .write_all(&encode(&["CLIENT", "TRACKING", "ON"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut reader,
+            "-ERR CLIENT TRACKING without REDIRECT requires RESP3 (HELLO 3)\r\n",
+        )
+        .await;
+
+        reader.write_all(&encode(&["HELLO", "3"])).await.unwrap();
+        read_reply(&mut reader).await;
+        reader
+            .write_all(&encode(&["CLIENT", "TRACKING", "ON"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut reader, "+OK\r\n").await;
+        reader.write_all(&encode(&["GET", "k"])).await.unwrap();
+        read_exact_reply(&mut reader, "_\r\n").await;
+
+        writer.write_all(&encode(&["SET", "k", "v"])).await.unwrap();
+        read_exact_reply(&mut writer, "+OK\r\n").await;
+        read_exact_reply(&mut reader, ">2\r\n$10\r\ninvalidate\r\n*1\r\n$1\r\nk\r\n").await;
+
+        // Not read since: the next write is not reported
+        writer.write_all(&encode(&["SET", "k", "w"])).await.unwrap();
+        read_exact_reply(&mut writer, "+OK\r\n").await;
+        reader
+            .write_all(&encode(&["CLIENT", "GETREDIR"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut reader, ":0\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_client_tracking_bcast_redirect() {
+        let state = ShardedActorState::with_shards(4);
+        let pubsub = PubSubActor::spawn();
+        let mut listener = spawn_client(&state, &pubsub);
+        let mut tracker = spawn_client(&state, &pubsub);
+        let mut writer = spawn_client(&state, &pubsub);
+
+        listener
+            .write_all(&encode(&["CLIENT", "ID"]))
+            .await
+            .unwrap();
+        let RespValue::Integer(id) = read_reply(&mut listener).await else {
+            panic!("CLIENT ID must reply with an integer");
+        };
+        listener
+            .write_all(&encode(&["SUBSCRIBE", "__redis__:invalidate"]))
+            .await
+            .unwrap();
+        read_reply(&mut listener).await;
+
+        let id = id.to_string();
+        tracker
+            .write_all(&encode(&[
+                "CLIENT", "TRACKING", "ON", "REDIRECT", &id, "BCAST", "PREFIX", "user:", "NOLOOP",
+            ]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut tracker, "+OK\r\n").await;
+
+        // NOLOOP: the tracker's own write is not reported
+        tracker
+            .write_all(&encode(&["SET", "user:1", "a"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut tracker, "+OK\r\n").await;
+        writer
+            .write_all(&encode(&["SET", "order:1", "a"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut writer, "+OK\r\n").await;
+        writer
+            .write_all(&encode(&["SET", "user:2", "b"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut writer, "+OK\r\n").await;
+        read_exact_reply(
+            &mut listener,
+            "*3\r\n$7\r\nmessage\r\n$20\r\n__redis__:invalidate\r\n*1\r\n$6\r\nuser:2\r\n",
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_client_list_setname_and_kill() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let clients = Arc::new(ClientRegistry::new());
+        let mut worker = spawn_registered_client(&state, &pubsub, &clients, "10.0.0.1:5000");
+        let mut admin = spawn_registered_client(&state, &pubsub, &clients, "10.0.0.2:5000");
+
+        worker
+            .write_all(&encode(&["CLIENT", "SETNAME", "worker"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut worker, "+OK\r\n").await;
+        worker
+            .write_all(&encode(&["CLIENT", "GETNAME"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut worker, "$6\r\nworker\r\n").await;
+        worker
+            .write_all(&encode(&["CLIENT", "SETNAME", "has space"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut worker,
+            "-ERR Client names cannot contain spaces, newlines or special characters.\r\n",
+        )
+        .await;
+
+        admin.write_all(&encode(&["CLIENT", "LIST"])).await.unwrap();
+        let RespValue::BulkString(Some(list)) = read_reply(&mut admin).await else {
+            panic!("CLIENT LIST must reply with a bulk string");
+        };
+        let list = String::from_utf8(list).unwrap();
+        let lines: Vec<&str> = list.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", list);
+        assert!(lines[0].contains(" addr=10.0.0.1:5000 name=worker "));
+        assert!(lines[0].contains(" cmd=client user=default "));
+        assert!(lines[1].contains(" addr=10.0.0.2:5000 name= "));
+
+        admin.write_all(&encode(&["CLIENT", "INFO"])).await.unwrap();
+        let RespValue::BulkString(Some(info)) = read_reply(&mut admin).await else {
+            panic!("CLIENT INFO must reply with a bulk string");
+        };
+        assert!(String::from_utf8(info).unwrap().ends_with(" resp=2\n"));
+
+        admin
+            .write_all(&encode(&["CLIENT", "KILL", "10.0.0.9:1"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut admin, "-ERR No such client\r\n").await;
+        admin
+            .write_all(&encode(&["CLIENT", "KILL", "ADDR", "10.0.0.1:5000"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut admin, ":1\r\n").await;
+
+        let mut buf = [0u8; 16];
+        let n = timeout(Duration::from_secs(2), worker.read(&mut buf))
+            .await
+            .expect("killed client was not closed")
+            .unwrap();
+        assert_eq!(n, 0);
+        timeout(Duration::from_secs(2), async {
+            while clients.len() > 1 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .expect("killed client stayed registered");
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_while_blocked_keeps_next_push() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let clients = Arc::new(ClientRegistry::new());
+        let mut waiter = spawn_tcp_client(&state, &pubsub, &clients).await;
+        let mut pusher = spawn_registered_client(&state, &pubsub, &clients, "pusher");
+
+        waiter
+            .write_all(&encode(&["BLPOP", "q3", "0"]))
+            .await
+            .unwrap();
+        wait_for_client_cmd(&clients, "blpop").await;
+        drop(waiter);
+        timeout(Duration::from_secs(2), async {
+            while clients.len() > 1 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("disconnected client stayed registered");
+
+        // The waiter is gone, so the pushed value stays in the list
+        pusher
+            .write_all(&encode(&["RPUSH", "q3", "v1"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut pusher, ":1\r\n").await;
+        pusher.write_all(&encode(&["LLEN", "q3"])).await.unwrap();
+        read_exact_reply(&mut pusher, ":1\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_client_kill_interrupts_blocked_client() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let clients = Arc::new(ClientRegistry::new());
+        let mut admin = spawn_registered_client(&state, &pubsub, &clients, "admin");
+        let mut waiter = spawn_registered_client(&state, &pubsub, &clients, "10.0.0.1:5000");
+
+        waiter
+            .write_all(&encode(&["BLPOP", "q", "0"]))
+            .await
+            .unwrap();
+        wait_for_client_cmd(&clients, "blpop").await;
+        admin
+            .write_all(&encode(&["CLIENT", "KILL", "ADDR", "10.0.0.1:5000"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut admin, ":1\r\n").await;
+
+        let mut buf = [0u8; 16];
+        let n = timeout(Duration::from_secs(2), waiter.read(&mut buf))
+            .await
+            .expect("killed client was not closed")
+            .unwrap();
+        assert_eq!(n, 0, "killed client must get no reply");
+
+        admin
+            .write_all(&encode(&["RPUSH", "q", "v1"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut admin, ":1\r\n").await;
+        admin.write_all(&encode(&["LLEN", "q"])).await.unwrap();
+        read_exact_reply(&mut admin, ":1\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_client_pause_write_holds_writes() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let clients = Arc::new(ClientRegistry::new());
+        let mut admin = spawn_registered_client(&state, &pubsub, &clients, "admin");
+        let mut app = spawn_registered_client(&state, &pubsub, &clients, "app");
+
+        admin
+            .write_all(&encode(&["CLIENT", "PAUSE", "10000", "WRITE"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut admin, "+OK\r\n").await;
+
+        // Reads are served, writes wait for CLIENT UNPAUSE
+        app.write_all(&encode(&["GET", "k"])).await.unwrap();
+        read_exact_reply(&mut app, "$-1\r\n").await;
+        app.write_all(&encode(&["SET", "k", "v"])).await.unwrap();
+        let mut buf = [0u8; 5];
+        assert!(
+            timeout(Duration::from_millis(100), app.read_exact(&mut buf))
+                .await
+                .is_err()
+        );
+
+        admin
+            .write_all(&encode(&["CLIENT", "UNPAUSE"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut admin, "+OK\r\n").await;
+        read_exact_reply(&mut app, "+OK\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn test_client_reply_off_and_skip() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        // SKIP silences itself and the next command
+        client
+            .write_all(&encode(&["CLIENT", "REPLY", "SKIP"]))
+            .await
+            .unwrap();
+        client.write_all(&encode(&["SET", "a", "1"])).await.unwrap();
+        client.write_all(&encode(&["GET", "a"])).await.unwrap();
+        read_exact_reply(&mut client, "$1\r\n1\r\n").await;
+
+        client
+            .write_all(&encode(&["CLIENT", "REPLY", "OFF"]))
+            .await
+            .unwrap();
+        client.write_all(&encode(&["SET", "a", "2"])).await.unwrap();
+        client.write_all(&encode(&["NOSUCHCMD"])).await.unwrap();
+        client
+            .write_all(&encode(&["CLIENT", "REPLY", "ON"]))
+            .await
+            .unwrap();
+        client.write_all(&encode(&["GET", "a"])).await.unwrap();
+        read_exact_reply(&mut client, "+OK\r\n$1\r\n2\r\n").await;
+    }
+
+    #[tokio::test]
+    #[allow(clippy::default_constructed_unit_structs)] // DatadogConfig has fields with `datadog`
+    async fn test_config_get_set_applies_live() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let acl_manager = Arc::new(RwLock::new(AclManager::new()));
+        let ttl = crate::production::TtlManagerActor::spawn(
+            state.clone(),
+            Arc::new(Metrics::new(&DatadogConfig::default())),
+        );
+        let config = Arc::new(
+            ConfigRegistry::new(
+                None,
+                crate::production::PerformanceConfig::default(),
+                crate::production::ServerConfig::default(),
+            )
+            .with_targets(state.clone(), acl_manager, ttl.clone()),
+        );
+        let clients = Arc::new(ClientRegistry::new());
+        let mut client = spawn_handler(&state, &pubsub, &clients, &config, "test");
+
+        client
+            .write_all(&encode(&["CONFIG", "GET", "maxmemory"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "*2\r\n$9\r\nmaxmemory\r\n$1\r\n0\r\n").await;
+
+        client
+            .write_all(&encode(&["CONFIG", "SET", "maxmemory", "1mb", "hz", "20"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        assert_eq!(state.memory_stats().await.maxmemory, 1024 * 1024);
+
+        client
+            .write_all(&encode(&["CONFIG", "SET", "databases", "4"]))
+            .await
+            .unwrap();
+        read_exact_reply(
+            &mut client,
+            "-ERR CONFIG SET failed (possibly related to argument 'databases') - can't set immutable config\r\n",
+        )
+        .await;
+        ttl.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_config_set_timeout_closes_idle_clients() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let config = test_config();
+        let clients = Arc::new(ClientRegistry::new());
+        let mut client = spawn_handler(&state, &pubsub, &clients, &config, "test");
+
+        client
+            .write_all(&encode(&["CONFIG", "SET", "timeout", "1"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+
+        let mut buf = [0u8; 16];
+        let n = timeout(Duration::from_secs(3), client.read(&mut buf))
+            .await
+            .expect("idle client was not closed")
+            .unwrap();
+        assert_eq!(n, 0);
+        assert!(clients.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slowlog_records_shard_commands() {
+        let state = ShardedActorState::with_shards(2);
+        state
+            .slowlog()
+            .configure(&crate::production::SlowlogConfig {
+                log_slower_than: 0,
+                max_len: 128,
+            });
+        let pubsub = PubSubActor::spawn();
+        let mut client = spawn_client(&state, &pubsub);
+
+        client
+            .write_all(&encode(&["CLIENT", "SETNAME", "app"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        client
+            .write_all(&encode(&["RPUSH", "list", "a", "b"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, ":2\r\n").await;
+
+        // Commands answered by the connection never reach a shard
+        let entries = state.slowlog().get(None);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].args,
+            vec![
+                b"RPUSH".to_vec(),
+                b"list".to_vec(),
+                b"a".to_vec(),
+                b"b".to_vec()
+            ]
+        );
+        assert_eq!(entries[0].client_addr, "test");
+        assert_eq!(entries[0].client_name, "app");
+
+        // GET/SET fast paths, pipelined batches and EXEC are timed too
+        client.write_all(&encode(&["set", "k", "v"])).await.unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        client.write_all(&encode(&["GET", "k"])).await.unwrap();
+        read_exact_reply(&mut client, "$1\r\nv\r\n").await;
+        let entries = state.slowlog().get(Some(2));
+        assert_eq!(entries[0].args, vec![b"GET".to_vec(), b"k".to_vec()]);
+        assert_eq!(
+            entries[1].args,
+            vec![b"set".to_vec(), b"k".to_vec(), b"v".to_vec()]
+        );
+
+        let mut pipeline = Vec::new();
+        for _ in 0..4 {
+            pipeline.extend_from_slice(&encode(&["GET", "k"]));
+        }
+        client.write_all(&pipeline).await.unwrap();
+        read_exact_reply(&mut client, &"$1\r\nv\r\n".repeat(4)).await;
+        assert_eq!(state.slowlog().len(), 7);
+
+        for (command, reply) in [
+            (&["MULTI"][..], "+OK\r\n"),
+            (&["INCR", "n"], "+QUEUED\r\n"),
+            (&["EXEC"], "*1\r\n:1\r\n"),
+        ] {
+            client.write_all(&encode(command)).await.unwrap();
+            read_exact_reply(&mut client, reply).await;
+        }
+        assert_eq!(state.slowlog().get(Some(1))[0].args, vec![b"EXEC".to_vec()]);
+        assert_eq!(state.slowlog().len(), 8);
+
+        client
+            .write_all(&encode(&["SLOWLOG", "LEN"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, ":8\r\n").await;
+        client
+            .write_all(&encode(&["SLOWLOG", "RESET"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "+OK\r\n").await;
+        client
+            .write_all(&encode(&["SLOWLOG", "GET"]))
+            .await
+            .unwrap();
+        read_exact_reply(&mut client, "*0\r\n").await;
+    }
+
+    /// Next MONITOR line without its timestamp
+    async fn read_monitor_line(monitor: &mut DuplexStream) -> String {
+        let mut line = Vec::new();
+        while !line.ends_with(b"\r\n") {
+            let mut byte = [0u8; 1];
+            timeout(Duration::from_secs(2), monitor.read_exact(&mut byte))
+                .await
+                .expect("timed out waiting for monitor line")
+                .expect("read failed");
+            line.push(byte[0]);
+        }
+        let line = String::from_utf8(line).unwrap();
+        let (timestamp, rest) = line
+            .trim_end()
+            .strip_prefix('+')
+            .and_then(|line| line.split_once(' '))
+            .expect("malformed monitor line");
+        assert!(timestamp.parse::<f64>().is_ok(), "{}", line);
+        rest.to_string()
+    }
+
+    #[tokio::test]
+    async fn test_monitor_streams_commands() {
+        let state = ShardedActorState::with_shards(2);
+        let pubsub = PubSubActor::spawn();
+        let clients = Arc::new(ClientRegistry::new());
+        let mut monitor = spawn_registered_client(&state, &pubsub, &clients, "monitor");
+        let mut client = spawn_registered_client(&state, &pubsub, &clients, "10.0.0.1:5000");
+
+        monitor.write_all(&encode(&["MONITOR"])).await.unwrap();
+        read_exact_reply(&mut monitor, "+OK\r\n").await;
+
+        for command in [
+            &["SET", "k", "v"][..],
+            &["GET", "k"],
+            &["CONFIG", "GET", "maxmemory"],
+            &["RPUSH", "list", "a b", "\"c\""],
+            &["AUTH", "default", "secret"],
+            &["SELECT", "2"],
+            &["GET", "k"],
+        ] {
+            client.write_all(&encode(command)).await.unwrap();
+        }
+
+        assert_eq!(
+            read_monitor_line(&mut monitor).await,
+            r#"[0 10.0.0.1:5000] "SET" "k" "v""#
+        );
+        assert_eq!(
+            read_monitor_line(&mut monitor).await,
+            r#"[0 10.0.0.1:5000] "GET" "k""#
+        );
+        // CONFIG is not shown
+        assert_eq!(
+            read_monitor_line(&mut monitor).await,
+            r#"[0 10.0.0.1:5000] "RPUSH" "list" "a b" "\"c\"""#
+        );
+        assert_eq!(
+            read_monitor_line(&mut monitor).await,
+            r#"[0 10.0.0.1:5000] "AUTH" "(redacted)" "(redacted)""#
+        );
+        assert_eq!(
+            read_monitor_line(&mut monitor).await,
+            r#"[2 10.0.0.1:5000] "SELECT" "2""#
+        );
+        assert_eq!(
+            read_monitor_line(&mut monitor).await,
+            r#"[2 10.0.0.1:5000] "GET" "k""#
+        );
+
+        // Pipelined GET/SET are not batched past the monitor
+        let mut pipeline = Vec::new();
+        for i in 0..3 {
+            pipeline.extend_from_slice(&encode(&["SET", &format!("p{}", i), "v"]));
+        }
+        for i in 0..3 {
+            pipeline.extend_from_slice(&encode(&["get", &format!("p{}", i)]));
+        }
+        client.write_all(&pipeline).await.unwrap();
+        for i in 0..3 {
+            assert_eq!(
+                read_monitor_line(&mut monitor).await,
+                format!(r#"[2 10.0.0.1:5000] "SET" "p{}" "v""#, i)
+            );
+        }
+        for i in 0..3 {
+            assert_eq!(
+                read_monitor_line(&mut monitor).await,
+                format!(r#"[2 10.0.0.1:5000] "get" "p{}""#, i)
+            );
+        }
+
+        // The monitor keeps answering its own commands
+        monitor.write_all(&encode(&["PING"])).await.unwrap();
+        read_exact_reply(&mut monitor, "+PONG\r\n").await;
+        drop(monitor);
+        timeout(Duration::from_secs(2), async {
+            while clients.has_monitors() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("monitor never unregistered");
+    }
+}
@@ -275,7 +275,7 @@ mod tests {
         let replica_id = ReplicaId::new(1);
         let clock = LamportClock::new(replica_id);
         let value = ReplicatedValue::with_value(SDS::from_str("test"), clock);
-        let delta = ReplicationDelta::new("key1".to_string(), value, replica_id);
+        let delta = ReplicationDelta::new("key1".into(), value, replica_id);
 
         // Queue deltas
         handle.queue_deltas(vec![delta]);
@@ -1,10 +1,12 @@
 use super::gossip_actor::GossipActorHandle;
+use super::latency::{LatencyMonitor, GOSSIP_ROUND};
 use crate::replication::gossip::{GossipMessage, GossipState, RoutedMessage};
 use crate::replication::state::ReplicationDelta;
 use crate::replication::{ReplicaId, ReplicationConfig};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::mpsc;
@@ -145,6 +147,7 @@ impl GossipManager {
     pub async fn start_gossip_loop(
         config: ReplicationConfig,
         gossip_state: Arc<RwLock<GossipState>>,
+        latency: Arc<LatencyMonitor>,
         collect_deltas: impl Fn() -> Vec<ReplicationDelta> + Send + Sync + 'static,
     ) {
         let gossip_interval = config.gossip_interval();
@@ -175,6 +178,7 @@ impl GossipManager {
 
         loop {
             ticker.tick().await;
+            let round_started = Instant::now();
 
             let deltas = collect_deltas();
 
@@ -219,6 +223,7 @@ impl GossipManager {
                     }
                 }
             }
+            latency.add_sample(GOSSIP_ROUND, round_started.elapsed());
         }
     }
 
@@ -243,6 +248,7 @@ impl GossipManager {
     pub async fn start_gossip_loop_with_actor(
         config: ReplicationConfig,
         gossip_handle: GossipActorHandle,
+        latency: Arc<LatencyMonitor>,
         collect_deltas: impl Fn() -> Vec<ReplicationDelta> + Send + Sync + 'static,
     ) {
         let gossip_interval = config.gossip_interval();
@@ -273,6 +279,7 @@ impl GossipManager {
 
         loop {
             ticker.tick().await;
+            let round_started = Instant::now();
 
             let deltas = collect_deltas();
 
@@ -313,6 +320,7 @@ impl GossipManager {
                     }
                 }
             }
+            latency.add_sample(GOSSIP_ROUND, round_started.elapsed());
         }
     }
 }
@@ -0,0 +1,224 @@
+//! LATENCY: spikes in commands and background work, like the Redis latency
+//! monitor
+//!
+//! Connections time the commands they run and background loops each round,
+//! and report it to their server's monitor (`ShardedActorState::latency`)
+//! with `add_sample(EVENT, elapsed)`.
+//! Rounds at least `latency-monitor-threshold` milliseconds long are kept, up
+//! to `LATENCY_TS_LEN` per event; samples within the same second are merged
+//! keeping the worst. A threshold of 0 (the default) turns recording off.
+
+use super::perf_config::LatencyConfig;
+use crate::redis::RespValue;
+use parking_lot::Mutex;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Samples kept per event
+pub const LATENCY_TS_LEN: usize = 160;
+
+/// A command other than GET/SET
+pub const COMMAND: &str = "command";
+/// A GET or SET, including those run in pipelined batches
+pub const FAST_COMMAND: &str = "fast-command";
+/// One TTL manager expiration/eviction round
+pub const EXPIRE_CYCLE: &str = "expire-cycle";
+/// One streaming persistence segment flush
+pub const PERSISTENCE_FLUSH: &str = "persistence-flush";
+/// One gossip round to cluster peers
+pub const GOSSIP_ROUND: &str = "gossip-round";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LatencySample {
+    /// Unix time, seconds
+    timestamp: u64,
+    millis: u64,
+}
+
+#[derive(Debug, Default)]
+struct EventHistory {
+    /// Oldest first
+    samples: VecDeque<LatencySample>,
+    max: u64,
+}
+
+/// Server-wide latency monitor shared by connections and background loops
+#[derive(Debug, Default)]
+pub struct LatencyMonitor {
+    /// Milliseconds; 0 = disabled
+    threshold: AtomicU64,
+    events: Mutex<BTreeMap<&'static str, EventHistory>>,
+}
+
+impl LatencyMonitor {
+    pub fn new(config: &LatencyConfig) -> Self {
+        LatencyMonitor {
+            threshold: AtomicU64::new(config.monitor_threshold),
+            events: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn set_threshold(&self, millis: u64) {
+        self.threshold.store(millis, Ordering::Relaxed);
+    }
+
+    pub fn threshold(&self) -> u64 {
+        self.threshold.load(Ordering::Relaxed)
+    }
+
+    /// Record that `event` took `elapsed`, if it crossed the threshold
+    pub fn add_sample(&self, event: &'static str, elapsed: Duration) {
+        let threshold = self.threshold();
+        let millis = elapsed.as_millis() as u64;
+        if threshold == 0 || millis < threshold {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.add_sample_at(event, timestamp, millis);
+    }
+
+    fn add_sample_at(&self, event: &'static str, timestamp: u64, millis: u64) {
+        let mut events = self.events.lock();
+        let history = events.entry(event).or_default();
+        history.max = history.max.max(millis);
+        if let Some(last) = history.samples.back_mut() {
+            if last.timestamp == timestamp {
+                last.millis = last.millis.max(millis);
+                return;
+            }
+        }
+        if history.samples.len() == LATENCY_TS_LEN {
+            history.samples.pop_front();
+        }
+        history
+            .samples
+            .push_back(LatencySample { timestamp, millis });
+    }
+
+    /// Forget the given events, or all of them when empty. Returns how many
+    /// had samples.
+    pub fn reset(&self, events: &[String]) -> usize {
+        let mut recorded = self.events.lock();
+        if events.is_empty() {
+            let count = recorded.len();
+            recorded.clear();
+            return count;
+        }
+        let before = recorded.len();
+        recorded.retain(|name, _| !events.iter().any(|e| e.eq_ignore_ascii_case(name)));
+        before - recorded.len()
+    }
+
+    /// LATENCY LATEST reply: `[event, timestamp, latest ms, max ms]` per event
+    pub fn latest_reply(&self) -> RespValue {
+        let events = self.events.lock();
+        RespValue::Array(Some(
+            events
+                .iter()
+                .filter_map(|(name, history)| {
+                    let last = history.samples.back()?;
+                    Some(RespValue::Array(Some(vec![
+                        RespValue::BulkString(Some(name.as_bytes().to_vec())),
+                        RespValue::Integer(last.timestamp as i64),
+                        RespValue::Integer(last.millis as i64),
+                        RespValue::Integer(history.max as i64),
+                    ])))
+                })
+                .collect(),
+        ))
+    }
+
+    /// LATENCY HISTORY reply: `[timestamp, ms]` per sample, oldest first;
+    /// empty for an unknown event
+    pub fn history_reply(&self, event: &str) -> RespValue {
+        let events = self.events.lock();
+        let samples = events
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(event))
+            .map(|(_, history)| {
+                history
+                    .samples
+                    .iter()
+                    .map(|sample| {
+                        RespValue::Array(Some(vec![
+                            RespValue::Integer(sample.timestamp as i64),
+                            RespValue::Integer(sample.millis as i64),
+                        ]))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        RespValue::Array(Some(samples))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history_len(monitor: &LatencyMonitor, event: &str) -> usize {
+        match monitor.history_reply(event) {
+            RespValue::Array(Some(samples)) => samples.len(),
+            other => panic!("unexpected reply {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_threshold_gates_samples() {
+        let monitor = LatencyMonitor::default();
+        monitor.add_sample(EXPIRE_CYCLE, Duration::from_secs(1));
+        assert_eq!(
+            history_len(&monitor, EXPIRE_CYCLE),
+            0,
+            "disabled by default"
+        );
+
+        monitor.set_threshold(10);
+        monitor.add_sample(EXPIRE_CYCLE, Duration::from_millis(9));
+        assert_eq!(history_len(&monitor, EXPIRE_CYCLE), 0);
+        monitor.add_sample(EXPIRE_CYCLE, Duration::from_millis(10));
+        assert_eq!(history_len(&monitor, EXPIRE_CYCLE), 1);
+    }
+
+    #[test]
+    fn test_samples_merge_per_second_and_wrap() {
+        let monitor = LatencyMonitor::default();
+        monitor.add_sample_at(GOSSIP_ROUND, 100, 20);
+        monitor.add_sample_at(GOSSIP_ROUND, 100, 50);
+        monitor.add_sample_at(GOSSIP_ROUND, 100, 30);
+        monitor.add_sample_at(GOSSIP_ROUND, 101, 5);
+
+        match monitor.latest_reply() {
+            RespValue::Array(Some(events)) => {
+                assert_eq!(events.len(), 1);
+                let RespValue::Array(Some(fields)) = &events[0] else {
+                    panic!("expected array");
+                };
+                assert_eq!(fields[1], RespValue::Integer(101));
+                assert_eq!(fields[2], RespValue::Integer(5));
+                assert_eq!(fields[3], RespValue::Integer(50));
+            }
+            other => panic!("unexpected reply {:?}", other),
+        }
+        match monitor.history_reply("GOSSIP-ROUND") {
+            RespValue::Array(Some(samples)) => assert_eq!(
+                samples[0],
+                RespValue::Array(Some(vec![RespValue::Integer(100), RespValue::Integer(50)]))
+            ),
+            other => panic!("unexpected reply {:?}", other),
+        }
+
+        for ts in 0..(LATENCY_TS_LEN as u64 + 10) {
+            monitor.add_sample_at(PERSISTENCE_FLUSH, 1000 + ts, 1);
+        }
+        assert_eq!(history_len(&monitor, PERSISTENCE_FLUSH), LATENCY_TS_LEN);
+
+        assert_eq!(monitor.reset(&["nope".to_string()]), 0);
+        assert_eq!(monitor.reset(&[GOSSIP_ROUND.to_string()]), 1);
+        assert_eq!(monitor.reset(&[]), 1);
+        assert_eq!(history_len(&monitor, PERSISTENCE_FLUSH), 0);
+    }
+}
@@ -1,33 +1,58 @@
 mod adaptive_actor;
 mod adaptive_replication;
+mod client_registry;
+mod cluster;
+mod config_registry;
 mod connection_optimized;
 mod connection_pool;
 mod gossip_actor;
 mod gossip_manager;
 mod hotkey;
+mod latency;
 mod load_balancer;
 mod perf_config;
+mod pubsub;
 mod replicated_shard_actor;
 mod replicated_state;
 mod response_pool;
 mod server_config;
 mod server_optimized;
 mod sharded_actor;
+mod slowlog;
+mod tracking;
+mod transaction;
+mod transaction_dst;
 mod ttl_manager;
 
 pub use adaptive_actor::{
     AdaptiveActor, AdaptiveActorConfig, AdaptiveActorHandle, AdaptiveActorStats, AdaptiveMessage,
 };
 pub use adaptive_replication::{AdaptiveConfig, AdaptiveReplicationManager, AdaptiveStats};
+pub use client_registry::{ClientHandle, ClientId, ClientPause, ClientRegistry, ClientStats};
+pub use cluster::{
+    crc16, key_hash_slot, ClusterConfig, ClusterNode, ClusterState, SlotRoute, CLUSTER_SLOTS,
+};
+pub use config_registry::ConfigRegistry;
 pub use connection_optimized::ConnectionConfig;
 pub use connection_pool::ConnectionPool;
 pub use gossip_actor::{GossipActor, GossipActorHandle, GossipMessage};
 pub use gossip_manager::GossipManager;
 pub use hotkey::{AccessMetrics, HotKeyConfig, HotKeyDetector};
+pub use latency::{
+    LatencyMonitor, COMMAND, EXPIRE_CYCLE, FAST_COMMAND, GOSSIP_ROUND, LATENCY_TS_LEN,
+    PERSISTENCE_FLUSH,
+};
 pub use load_balancer::{
     LoadBalancerConfig, LoadBalancerStats, ScalingDecision, ShardLoadBalancer, ShardMetrics,
 };
-pub use perf_config::{BatchingConfig, BufferConfig, PerformanceConfig, ResponsePoolConfig};
+pub use perf_config::{
+    BatchingConfig, BufferConfig, ClientsConfig, LatencyConfig, PerformanceConfig,
+    ResponsePoolConfig, SecurityConfig, SlowlogConfig, TtlConfig,
+};
+pub use pubsub::{
+    PubSubActor, PubSubBroker, PubSubHandle, PubSubMessage, PushSender, SubscriberId,
+    SubscriptionKind,
+};
 pub use replicated_shard_actor::{
     ReplicatedShardActor, ReplicatedShardHandle, ReplicatedShardMessage,
 };
@@ -35,6 +60,16 @@ pub use replicated_state::{GossipBackend, ReplicatedShardedState};
 pub use server_config::{AclServerConfig, ServerConfig, TlsServerConfig};
 pub use server_optimized::OptimizedRedisServer;
 pub use sharded_actor::{ShardConfig, ShardedActorState};
+pub use slowlog::{SlowLog, SlowlogEntry, SLOWLOG_ENTRY_MAX_ARGC, SLOWLOG_ENTRY_MAX_STRING};
+pub use tracking::{
+    InvalidationTarget, TrackedClient, TrackingClientId, TrackingTable, INVALIDATE_CHANNEL,
+    TRACKING_TABLE_MAX_KEYS,
+};
+pub use transaction::{ExecOutcome, TransactionState};
+pub use transaction_dst::{
+    run_transaction_batch, summarize_transaction_batch, TransactionDSTConfig, TransactionDSTHarness,
+    TransactionDSTResult,
+};
 pub use ttl_manager::{TtlManagerActor, TtlManagerHandle, TtlMessage};
 
 pub use server_optimized::OptimizedRedisServer as ProductionRedisServer;
@@ -4,16 +4,21 @@
 //! to be tuned without recompiling. Used by the RedisEvolve harness to discover
 //! optimal configurations through evolutionary optimization.
 
-use serde::Deserialize;
-use std::path::Path;
+use crate::redis::{EvictionPolicy, MemoryConfig, DEFAULT_DATABASES, DEFAULT_MAXMEMORY_SAMPLES};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 /// Performance tuning parameters for the Redis server
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceConfig {
     /// Number of shards for data partitioning (default: 16)
     #[serde(default = "default_num_shards")]
     pub num_shards: usize,
 
+    /// Logical databases for SELECT, like Redis `databases` (default: 16)
+    #[serde(default = "default_databases")]
+    pub databases: usize,
+
     /// Response pool configuration
     #[serde(default)]
     pub response_pool: ResponsePoolConfig,
@@ -25,10 +30,34 @@ pub struct PerformanceConfig {
     /// Batching configuration
     #[serde(default)]
     pub batching: BatchingConfig,
+
+    /// Memory limit and eviction configuration
+    #[serde(default)]
+    pub memory: MaxMemoryConfig,
+
+    /// Client connection limits
+    #[serde(default)]
+    pub clients: ClientsConfig,
+
+    /// Active expire frequency
+    #[serde(default)]
+    pub ttl: TtlConfig,
+
+    /// SLOWLOG threshold and length
+    #[serde(default)]
+    pub slowlog: SlowlogConfig,
+
+    /// LATENCY monitor threshold
+    #[serde(default)]
+    pub latency: LatencyConfig,
+
+    /// Authentication settings
+    #[serde(default)]
+    pub security: SecurityConfig,
 }
 
 /// Response pool parameters for reducing channel allocation overhead
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResponsePoolConfig {
     /// Maximum number of response slots in pool (default: 256)
     #[serde(default = "default_pool_capacity")]
@@ -40,7 +69,7 @@ pub struct ResponsePoolConfig {
 }
 
 /// Buffer size parameters for connection handling
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BufferConfig {
     /// Size of read buffer in bytes (default: 8192)
     #[serde(default = "default_read_buffer")]
@@ -52,7 +81,7 @@ pub struct BufferConfig {
 }
 
 /// Batching parameters for pipeline optimization
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatchingConfig {
     /// Minimum buffer size to attempt batching (default: 60)
     #[serde(default = "default_min_pipeline_buffer")]
@@ -63,10 +92,73 @@ pub struct BatchingConfig {
     pub batch_threshold: usize,
 }
 
+/// maxmemory parameters, split evenly across shards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaxMemoryConfig {
+    /// Memory limit in bytes for the whole server, 0 = unlimited (default: 0)
+    #[serde(default)]
+    pub maxmemory: usize,
+
+    /// Eviction policy once the limit is reached (default: "noeviction")
+    #[serde(default = "default_maxmemory_policy")]
+    pub maxmemory_policy: String,
+
+    /// Keys sampled per eviction for approximate LRU/LFU/TTL (default: 5)
+    #[serde(default = "default_maxmemory_samples")]
+    pub maxmemory_samples: usize,
+}
+
+/// Client connection limits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClientsConfig {
+    /// Close connections idle for this many seconds, 0 = never (default: 0)
+    #[serde(default)]
+    pub timeout: u64,
+}
+
+/// Active expire frequency, like Redis `hz`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TtlConfig {
+    /// TTL manager ticks per second, 1-500 (default: 10, every 100ms)
+    #[serde(default = "default_hz")]
+    pub hz: u32,
+}
+
+/// SLOWLOG parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowlogConfig {
+    /// Log commands slower than this many microseconds; negative = off (default: 10000)
+    #[serde(default = "default_slowlog_log_slower_than")]
+    pub log_slower_than: i64,
+
+    /// Entries kept before the oldest are dropped (default: 128)
+    #[serde(default = "default_slowlog_max_len")]
+    pub max_len: usize,
+}
+
+/// LATENCY monitor parameters
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyConfig {
+    /// Record events taking at least this many milliseconds, 0 = off (default: 0)
+    #[serde(default)]
+    pub monitor_threshold: u64,
+}
+
+/// Authentication settings (REDIS_REQUIRE_PASS takes precedence)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// Password of the default user, like Redis `requirepass` (default: none)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requirepass: Option<String>,
+}
+
 // Default value functions for serde
 fn default_num_shards() -> usize {
     16
 }
+fn default_databases() -> usize {
+    DEFAULT_DATABASES
+}
 fn default_pool_capacity() -> usize {
     256
 }
@@ -85,14 +177,36 @@ fn default_min_pipeline_buffer() -> usize {
 fn default_batch_threshold() -> usize {
     2
 }
+fn default_maxmemory_policy() -> String {
+    "noeviction".to_string()
+}
+fn default_maxmemory_samples() -> usize {
+    DEFAULT_MAXMEMORY_SAMPLES
+}
+fn default_hz() -> u32 {
+    10
+}
+fn default_slowlog_log_slower_than() -> i64 {
+    10_000
+}
+fn default_slowlog_max_len() -> usize {
+    128
+}
 
 impl Default for PerformanceConfig {
     fn default() -> Self {
         Self {
             num_shards: default_num_shards(),
+            databases: default_databases(),
             response_pool: ResponsePoolConfig::default(),
             buffers: BufferConfig::default(),
             batching: BatchingConfig::default(),
+            memory: MaxMemoryConfig::default(),
+            clients: ClientsConfig::default(),
+            ttl: TtlConfig::default(),
+            slowlog: SlowlogConfig::default(),
+            latency: LatencyConfig::default(),
+            security: SecurityConfig::default(),
         }
     }
 }
@@ -124,32 +238,83 @@ impl Default for BatchingConfig {
     }
 }
 
+impl Default for MaxMemoryConfig {
+    fn default() -> Self {
+        Self {
+            maxmemory: 0,
+            maxmemory_policy: default_maxmemory_policy(),
+            maxmemory_samples: default_maxmemory_samples(),
+        }
+    }
+}
+
+impl Default for TtlConfig {
+    fn default() -> Self {
+        Self { hz: default_hz() }
+    }
+}
+
+impl Default for SlowlogConfig {
+    fn default() -> Self {
+        Self {
+            log_slower_than: default_slowlog_log_slower_than(),
+            max_len: default_slowlog_max_len(),
+        }
+    }
+}
+
+impl TtlConfig {
+    /// Time between TTL manager ticks
+    pub fn interval_ms(&self) -> u64 {
+        debug_assert!(self.hz > 0, "Precondition: hz must be positive");
+        1000 / u64::from(self.hz.max(1))
+    }
+}
+
+impl MaxMemoryConfig {
+    /// Server-wide memory settings (an unknown policy falls back to noeviction;
+    /// `PerformanceConfig::validate` rejects it)
+    pub fn memory_config(&self) -> MemoryConfig {
+        MemoryConfig {
+            maxmemory: self.maxmemory,
+            policy: EvictionPolicy::from_name(&self.maxmemory_policy).unwrap_or_default(),
+            samples: self.maxmemory_samples,
+        }
+    }
+}
+
 impl PerformanceConfig {
     /// Load configuration from a TOML file
     ///
     /// Returns default configuration if file doesn't exist or can't be parsed.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_from_file(path).unwrap_or_default()
+    }
+
+    /// Load configuration from a TOML file, or None (logged) if the file
+    /// doesn't exist or can't be read or parsed
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Option<Self> {
         let path = path.as_ref();
 
         if !path.exists() {
             tracing::info!("No config file at {:?}, using defaults", path);
-            return Self::default();
+            return None;
         }
 
         match std::fs::read_to_string(path) {
             Ok(contents) => match toml::from_str(&contents) {
                 Ok(config) => {
                     tracing::info!("Loaded performance config from {:?}", path);
-                    config
+                    Some(config)
                 }
                 Err(e) => {
                     tracing::warn!("Failed to parse config {:?}: {}, using defaults", path, e);
-                    Self::default()
+                    None
                 }
             },
             Err(e) => {
                 tracing::warn!("Failed to read config {:?}: {}, using defaults", path, e);
-                Self::default()
+                None
             }
         }
     }
@@ -157,9 +322,56 @@ impl PerformanceConfig {
     /// Load configuration from environment variable PERF_CONFIG_PATH
     /// or fall back to default path "perf_config.toml"
     pub fn from_env() -> Self {
-        let path =
-            std::env::var("PERF_CONFIG_PATH").unwrap_or_else(|_| "perf_config.toml".to_string());
-        Self::from_file(&path)
+        Self::from_file(Self::env_path())
+    }
+
+    /// The config file `from_env` reads, and CONFIG REWRITE writes
+    pub fn env_path() -> PathBuf {
+        std::env::var("PERF_CONFIG_PATH")
+            .unwrap_or_else(|_| "perf_config.toml".to_string())
+            .into()
+    }
+
+    /// Save the settings that differ from the TOML file at `path` into it.
+    ///
+    /// Only changed keys are edited, so comments, formatting and keys this
+    /// version doesn't know survive. `[security]` is never written: the
+    /// password may come from REDIS_REQUIRE_PASS and must not land on disk.
+    /// The new file is written next to the old one and renamed over it.
+    pub fn rewrite_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let invalid = |e: String| std::io::Error::new(std::io::ErrorKind::InvalidData, e);
+
+        let contents = std::fs::read_to_string(path)?;
+        let saved: Self = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        let mut document: toml_edit::DocumentMut = contents
+            .parse()
+            .map_err(|e: toml_edit::TomlError| invalid(e.to_string()))?;
+
+        let to_table = |config: &Self| match toml::Value::try_from(config) {
+            Ok(toml::Value::Table(table)) => Ok(table),
+            Ok(_) => Err(invalid("config is not a table".to_string())),
+            Err(e) => Err(invalid(e.to_string())),
+        };
+        let mut live = to_table(self)?;
+        live.remove("security");
+        update_table(document.as_table_mut(), &live, &to_table(&saved)?)?;
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| invalid(format!("{:?} is not a file", path)))?;
+        let temp = path.with_file_name(format!(".{}.rewrite", file_name.to_string_lossy()));
+        let written = (|| {
+            let mut file = std::fs::File::create(&temp)?;
+            file.set_permissions(std::fs::metadata(path)?.permissions())?;
+            std::io::Write::write_all(&mut file, document.to_string().as_bytes())?;
+            file.sync_all()?;
+            std::fs::rename(&temp, path)
+        })();
+        if written.is_err() {
+            let _ = std::fs::remove_file(&temp);
+        }
+        written
     }
 
     /// Validate configuration values are within acceptable bounds
@@ -174,6 +386,9 @@ impl PerformanceConfig {
                 self.num_shards
             ));
         }
+        if self.databases == 0 {
+            return Err("databases must be > 0".to_string());
+        }
         if self.response_pool.capacity == 0 {
             return Err("response_pool.capacity must be > 0".to_string());
         }
@@ -186,10 +401,64 @@ impl PerformanceConfig {
         if self.buffers.max_size < self.buffers.read_size {
             return Err("buffers.max_size must be >= read_size".to_string());
         }
+        if EvictionPolicy::from_name(&self.memory.maxmemory_policy).is_none() {
+            return Err(format!(
+                "memory.maxmemory_policy '{}' is not a valid policy",
+                self.memory.maxmemory_policy
+            ));
+        }
+        if self.memory.maxmemory_samples == 0 {
+            return Err("memory.maxmemory_samples must be > 0".to_string());
+        }
+        if !(1..=500).contains(&self.ttl.hz) {
+            return Err(format!("ttl.hz must be 1-500, got {}", self.ttl.hz));
+        }
         Ok(())
     }
 }
 
+/// Set every key of `live` whose value differs from `saved` (what the file
+/// holds) in `table`, keeping each replaced value's comments
+fn update_table(
+    table: &mut dyn toml_edit::TableLike,
+    live: &toml::Table,
+    saved: &toml::Table,
+) -> std::io::Result<()> {
+    let empty = toml::Table::new();
+    for (key, value) in live {
+        if saved.get(key) == Some(value) {
+            continue;
+        }
+        if let toml::Value::Table(live_section) = value {
+            let saved_section = saved.get(key).and_then(toml::Value::as_table);
+            if table.get(key).is_none() {
+                table.insert(key, toml_edit::table());
+            }
+            if let Some(section) = table.get_mut(key).and_then(|item| item.as_table_like_mut()) {
+                update_table(section, live_section, saved_section.unwrap_or(&empty))?;
+            }
+            continue;
+        }
+        let mut updated: toml_edit::Value =
+            value
+                .to_string()
+                .parse()
+                .map_err(|e: toml_edit::TomlError| {
+                    std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+                })?;
+        match table.get_mut(key).and_then(|item| item.as_value_mut()) {
+            Some(current) => {
+                *updated.decor_mut() = current.decor().clone();
+                *current = updated;
+            }
+            None => {
+                table.insert(key, toml_edit::Item::Value(updated));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -221,12 +490,17 @@ mod tests {
 
         config.num_shards = 15; // Not power of 2
         assert!(config.validate().is_err());
+
+        config.num_shards = 16;
+        config.databases = 0;
+        assert!(config.validate().is_err());
     }
 
     #[test]
     fn test_parse_toml() {
         let toml_str = r#"
             num_shards = 32
+            databases = 4
 
             [response_pool]
             capacity = 512
@@ -243,6 +517,7 @@ mod tests {
 
         let config: PerformanceConfig = toml::from_str(toml_str).unwrap();
         assert_eq!(config.num_shards, 32);
+        assert_eq!(config.databases, 4);
         assert_eq!(config.response_pool.capacity, 512);
         assert_eq!(config.response_pool.prewarm, 128);
         assert_eq!(config.buffers.read_size, 16384);
@@ -259,7 +534,68 @@ mod tests {
 
         let config: PerformanceConfig = toml::from_str(toml_str).unwrap();
         assert_eq!(config.num_shards, 8);
+        assert_eq!(config.databases, 16); // default
         assert_eq!(config.response_pool.capacity, 256); // default
         assert_eq!(config.buffers.read_size, 8192); // default
+        assert_eq!(config.memory.memory_config(), MemoryConfig::default());
+    }
+
+    #[test]
+    fn test_memory_section() {
+        let toml_str = r#"
+            [memory]
+            maxmemory = 104857600
+            maxmemory_policy = "allkeys-lfu"
+        "#;
+
+        let config: PerformanceConfig = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        let memory = config.memory.memory_config();
+        assert_eq!(memory.maxmemory, 104857600);
+        assert_eq!(memory.policy, EvictionPolicy::AllKeysLfu);
+        assert_eq!(memory.samples, 5); // default
+
+        let mut config = config;
+        config.memory.maxmemory_policy = "lru".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_runtime_sections() {
+        let toml_str = r#"
+            [clients]
+            timeout = 300
+
+            [ttl]
+            hz = 50
+
+            [slowlog]
+            log_slower_than = -1
+
+            [latency]
+            monitor_threshold = 100
+
+            [security]
+            requirepass = "secret"
+        "#;
+
+        let config: PerformanceConfig = toml::from_str(toml_str).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.clients.timeout, 300);
+        assert_eq!(config.ttl.interval_ms(), 20);
+        assert_eq!(config.slowlog.log_slower_than, -1);
+        assert_eq!(config.slowlog.max_len, 128); // default
+        assert_eq!(config.latency.monitor_threshold, 100);
+        assert_eq!(config.security.requirepass.as_deref(), Some("secret"));
+
+        // Serializes back to the same settings
+        let reparsed: PerformanceConfig =
+            toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(reparsed.ttl.hz, 50);
+        assert_eq!(reparsed.security.requirepass.as_deref(), Some("secret"));
+
+        let mut config = config;
+        config.ttl.hz = 0;
+        assert!(config.validate().is_err());
     }
 }
@@ -0,0 +1,608 @@
+//! PubSubBroker - Actor for channel and pattern message fan-out
+//!
+//! Pub/Sub messages are not keyed data, so they bypass the per-key shard
+//! routing of `ShardedActorState` entirely. A single broker actor owns every
+//! subscription and pushes messages straight into subscriber connections.
+//!
+//! ## Design
+//!
+//! ```text
+//! ┌──────────────┐  PUBLISH   ┌─────────────────┐  push   ┌──────────────┐
+//! │ Connection A │──────────▶│ PubSubBroker    │───────▶│ Connection B │
+//! └──────────────┘            │ (channels,      │        │ (subscriber  │
+//!                             │  patterns,      │───────▶│  mode)       │
+//!                             │  shard channels)│        └──────────────┘
+//!                             └─────────────────┘
+//! ```
+//!
+//! Subscribe/unsubscribe are fire-and-forget: the broker processes its mailbox
+//! in order, so a subscription is always registered before any later PUBLISH.
+
+use super::tracking::INVALIDATE_CHANNEL;
+use crate::redis::{CommandExecutor, RespValue, SDS};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// Unique identifier of a subscribing connection
+pub type SubscriberId = u64;
+
+/// Channel through which the broker pushes messages to a connection
+pub type PushSender = mpsc::UnboundedSender<RespValue>;
+
+/// Namespace a subscription lives in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubscriptionKind {
+    /// SUBSCRIBE / PUBLISH
+    Channel,
+    /// PSUBSCRIBE (glob patterns matched against PUBLISH channels)
+    Pattern,
+    /// SSUBSCRIBE / SPUBLISH
+    Shard,
+}
+
+/// Messages handled by the PubSubBroker actor
+#[derive(Debug)]
+pub enum PubSubMessage {
+    Subscribe {
+        subscriber: SubscriberId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+        push_tx: PushSender,
+    },
+    Unsubscribe {
+        subscriber: SubscriberId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+    },
+    /// Drop every subscription of a disconnected client
+    Disconnect {
+        subscriber: SubscriberId,
+    },
+    Publish {
+        shard: bool,
+        channel: String,
+        message: Vec<u8>,
+        response: oneshot::Sender<usize>,
+    },
+    Channels {
+        shard: bool,
+        pattern: Option<String>,
+        response: oneshot::Sender<Vec<String>>,
+    },
+    NumSub {
+        shard: bool,
+        channels: Vec<String>,
+        response: oneshot::Sender<Vec<usize>>,
+    },
+    NumPat {
+        response: oneshot::Sender<usize>,
+    },
+    /// Client-side caching invalidation redirected to `subscriber`
+    /// (`None`: every key, after a flush)
+    Invalidate {
+        subscriber: SubscriberId,
+        keys: Option<Vec<SDS>>,
+    },
+}
+
+type Subscribers = BTreeMap<SubscriberId, PushSender>;
+
+/// Subscription registry and fan-out logic (owned by the actor)
+///
+/// BTreeMaps keep delivery order and introspection output deterministic.
+#[derive(Default)]
+pub struct PubSubBroker {
+    channels: BTreeMap<String, Subscribers>,
+    patterns: BTreeMap<String, Subscribers>,
+    shard_channels: BTreeMap<String, Subscribers>,
+}
+
+impl PubSubBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn registry_mut(&mut self, kind: SubscriptionKind) -> &mut BTreeMap<String, Subscribers> {
+        match kind {
+            SubscriptionKind::Channel => &mut self.channels,
+            SubscriptionKind::Pattern => &mut self.patterns,
+            SubscriptionKind::Shard => &mut self.shard_channels,
+        }
+    }
+
+    pub fn subscribe(
+        &mut self,
+        subscriber: SubscriberId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+        push_tx: &PushSender,
+    ) {
+        let registry = self.registry_mut(kind);
+        for name in names {
+            registry
+                .entry(name)
+                .or_default()
+                .insert(subscriber, push_tx.clone());
+        }
+    }
+
+    pub fn unsubscribe(
+        &mut self,
+        subscriber: SubscriberId,
+        kind: SubscriptionKind,
+        names: &[String],
+    ) {
+        let registry = self.registry_mut(kind);
+        for name in names {
+            if let Some(subscribers) = registry.get_mut(name) {
+                subscribers.remove(&subscriber);
+                if subscribers.is_empty() {
+                    registry.remove(name);
+                }
+            }
+        }
+    }
+
+    pub fn disconnect(&mut self, subscriber: SubscriberId) {
+        for registry in [
+            &mut self.channels,
+            &mut self.patterns,
+            &mut self.shard_channels,
+        ] {
+            registry.retain(|_, subscribers| {
+                subscribers.remove(&subscriber);
+                !subscribers.is_empty()
+            });
+        }
+    }
+
+    /// Deliver a message and return the number of clients that received it
+    ///
+    /// A client subscribed both to the channel and to matching patterns
+    /// receives one message per subscription, as in Redis.
+    pub fn publish(&mut self, shard: bool, channel: &str, message: &[u8]) -> usize {
+        let channel_bytes = channel.as_bytes().to_vec();
+        let mut delivered = 0;
+
+        let (registry, kind) = if shard {
+            (&mut self.shard_channels, "smessage")
+        } else {
+            (&mut self.channels, "message")
+        };
+        if let Some(subscribers) = registry.get_mut(channel) {
+            let push = RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(kind.as_bytes().to_vec())),
+                RespValue::BulkString(Some(channel_bytes.clone())),
+                RespValue::BulkString(Some(message.to_vec())),
+            ]));
+            delivered += Self::fan_out(subscribers, &push);
+            if subscribers.is_empty() {
+                registry.remove(channel);
+            }
+        }
+
+        if !shard {
+            let mut emptied = Vec::new();
+            for (pattern, subscribers) in self.patterns.iter_mut() {
+                if !CommandExecutor::matches_glob_pattern(channel.as_bytes(), pattern.as_bytes()) {
+                    continue;
+                }
+                let push = RespValue::Array(Some(vec![
+                    RespValue::BulkString(Some(b"pmessage".to_vec())),
+                    RespValue::BulkString(Some(pattern.as_bytes().to_vec())),
+                    RespValue::BulkString(Some(channel_bytes.clone())),
+                    RespValue::BulkString(Some(message.to_vec())),
+                ]));
+                delivered += Self::fan_out(subscribers, &push);
+                if subscribers.is_empty() {
+                    emptied.push(pattern.clone());
+                }
+            }
+            for pattern in emptied {
+                self.patterns.remove(&pattern);
+            }
+        }
+
+        delivered
+    }
+
+    /// Deliver a redirected invalidation on `__redis__:invalidate`, if
+    /// `subscriber` listens on that channel. Returns whether it was sent.
+    pub fn invalidate(&mut self, subscriber: SubscriberId, keys: Option<&[SDS]>) -> bool {
+        let Some(push_tx) = self
+            .channels
+            .get(INVALIDATE_CHANNEL)
+            .and_then(|subscribers| subscribers.get(&subscriber))
+        else {
+            return false;
+        };
+        let keys = match keys {
+            Some(keys) => RespValue::Array(Some(
+                keys.iter()
+                    .map(|k| RespValue::BulkString(Some(k.as_bytes().to_vec())))
+                    .collect(),
+            )),
+            None => RespValue::Array(None),
+        };
+        let push = RespValue::Array(Some(vec![
+            RespValue::BulkString(Some(b"message".to_vec())),
+            RespValue::BulkString(Some(INVALIDATE_CHANNEL.as_bytes().to_vec())),
+            keys,
+        ]));
+        push_tx.send(push).is_ok()
+    }
+
+    /// Send to every subscriber, pruning connections that have gone away
+    fn fan_out(subscribers: &mut Subscribers, push: &RespValue) -> usize {
+        let before = subscribers.len();
+        subscribers.retain(|_, tx| tx.send(push.clone()).is_ok());
+        let delivered = subscribers.len();
+        if delivered < before {
+            debug!("Pruned {} closed pub/sub subscribers", before - delivered);
+        }
+        delivered
+    }
+
+    /// Active channels (with at least one subscriber), optionally glob-filtered
+    pub fn active_channels(&self, shard: bool, pattern: Option<&str>) -> Vec<String> {
+        let registry = if shard {
+            &self.shard_channels
+        } else {
+            &self.channels
+        };
+        registry
+            .keys()
+            .filter(|c| {
+                pattern.is_none_or(|p| {
+                    CommandExecutor::matches_glob_pattern(c.as_bytes(), p.as_bytes())
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn num_subscribers(&self, shard: bool, channel: &str) -> usize {
+        let registry = if shard {
+            &self.shard_channels
+        } else {
+            &self.channels
+        };
+        registry.get(channel).map_or(0, |s| s.len())
+    }
+
+    /// Number of unique patterns subscribed to (PUBSUB NUMPAT)
+    pub fn num_patterns(&self) -> usize {
+        self.patterns.len()
+    }
+}
+
+/// Handle for communicating with the PubSubBroker actor
+#[derive(Clone, Debug)]
+pub struct PubSubHandle {
+    tx: mpsc::UnboundedSender<PubSubMessage>,
+    next_subscriber_id: Arc<AtomicU64>,
+}
+
+impl PubSubHandle {
+    /// Allocate an identifier for a new connection
+    #[inline]
+    pub fn next_subscriber_id(&self) -> SubscriberId {
+        self.next_subscriber_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn subscribe(
+        &self,
+        subscriber: SubscriberId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+        push_tx: PushSender,
+    ) {
+        let _ = self.tx.send(PubSubMessage::Subscribe {
+            subscriber,
+            kind,
+            names,
+            push_tx,
+        });
+    }
+
+    pub fn unsubscribe(
+        &self,
+        subscriber: SubscriberId,
+        kind: SubscriptionKind,
+        names: Vec<String>,
+    ) {
+        let _ = self.tx.send(PubSubMessage::Unsubscribe {
+            subscriber,
+            kind,
+            names,
+        });
+    }
+
+    pub fn disconnect(&self, subscriber: SubscriberId) {
+        let _ = self.tx.send(PubSubMessage::Disconnect { subscriber });
+    }
+
+    /// Publish a message; returns the number of clients that received it
+    pub async fn publish(&self, shard: bool, channel: String, message: Vec<u8>) -> usize {
+        let (response, rx) = oneshot::channel();
+        let msg = PubSubMessage::Publish {
+            shard,
+            channel,
+            message,
+            response,
+        };
+        if self.tx.send(msg).is_err() {
+            return 0;
+        }
+        rx.await.unwrap_or(0)
+    }
+
+    pub async fn channels(&self, shard: bool, pattern: Option<String>) -> Vec<String> {
+        let (response, rx) = oneshot::channel();
+        let msg = PubSubMessage::Channels {
+            shard,
+            pattern,
+            response,
+        };
+        if self.tx.send(msg).is_err() {
+            return Vec::new();
+        }
+        rx.await.unwrap_or_default()
+    }
+
+    pub async fn numsub(&self, shard: bool, channels: Vec<String>) -> Vec<usize> {
+        let len = channels.len();
+        let (response, rx) = oneshot::channel();
+        let msg = PubSubMessage::NumSub {
+            shard,
+            channels,
+            response,
+        };
+        if self.tx.send(msg).is_err() {
+            return vec![0; len];
+        }
+        rx.await.unwrap_or_else(|_| vec![0; len])
+    }
+
+    /// Redirect a client-side caching invalidation to `subscriber`
+    pub fn invalidate(&self, subscriber: SubscriberId, keys: Option<Vec<SDS>>) {
+        let _ = self.tx.send(PubSubMessage::Invalidate { subscriber, keys });
+    }
+
+    pub async fn numpat(&self) -> usize {
+        let (response, rx) = oneshot::channel();
+        if self.tx.send(PubSubMessage::NumPat { response }).is_err() {
+            return 0;
+        }
+        rx.await.unwrap_or(0)
+    }
+
+    /// Check if the actor is still running
+    pub fn is_running(&self) -> bool {
+        !self.tx.is_closed()
+    }
+}
+
+/// Actor wrapper that owns the broker and serves its mailbox
+pub struct PubSubActor {
+    broker: PubSubBroker,
+    rx: mpsc::UnboundedReceiver<PubSubMessage>,
+}
+
+impl PubSubActor {
+    /// Spawn the actor and return the handle
+    pub fn spawn() -> PubSubHandle {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let actor = PubSubActor {
+            broker: PubSubBroker::new(),
+            rx,
+        };
+        tokio::spawn(actor.run());
+        PubSubHandle {
+            tx,
+            next_subscriber_id: Arc::new(AtomicU64::new(1)),
+        }
+    }
+
+    async fn run(mut self) {
+        while let Some(msg) = self.rx.recv().await {
+            match msg {
+                PubSubMessage::Subscribe {
+                    subscriber,
+                    kind,
+                    names,
+                    push_tx,
+                } => self.broker.subscribe(subscriber, kind, names, &push_tx),
+                PubSubMessage::Unsubscribe {
+                    subscriber,
+                    kind,
+                    names,
+                } => self.broker.unsubscribe(subscriber, kind, &names),
+                PubSubMessage::Disconnect { subscriber } => self.broker.disconnect(subscriber),
+                PubSubMessage::Publish {
+                    shard,
+                    channel,
+                    message,
+                    response,
+                } => {
+                    let delivered = self.broker.publish(shard, &channel, &message);
+                    let _ = response.send(delivered);
+                }
+                PubSubMessage::Channels {
+                    shard,
+                    pattern,
+                    response,
+                } => {
+                    let _ = response.send(self.broker.active_channels(shard, pattern.as_deref()));
+                }
+                PubSubMessage::NumSub {
+                    shard,
+                    channels,
+                    response,
+                } => {
+                    let counts = channels
+                        .iter()
+                        .map(|c| self.broker.num_subscribers(shard, c))
+                        .collect();
+                    let _ = response.send(counts);
+                }
+                PubSubMessage::NumPat { response } => {
+                    let _ = response.send(self.broker.num_patterns());
+                }
+                PubSubMessage::Invalidate { subscriber, keys } => {
+                    self.broker.invalidate(subscriber, keys.as_deref());
+                }
+            }
+        }
+        debug!("PubSub broker channel closed, shutting down");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_broker_channel_and_pattern_delivery() {
+        let mut broker = PubSubBroker::new();
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, mut rx2) = mpsc::unbounded_channel();
+
+        broker.subscribe(1, SubscriptionKind::Channel, names(&["news"]), &tx1);
+        broker.subscribe(2, SubscriptionKind::Pattern, names(&["n*"]), &tx2);
+
+        assert_eq!(broker.publish(false, "news", b"hello"), 2);
+        assert_eq!(
+            rx1.try_recv().unwrap(),
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"message".to_vec())),
+                RespValue::BulkString(Some(b"news".to_vec())),
+                RespValue::BulkString(Some(b"hello".to_vec())),
+            ]))
+        );
+        match rx2.try_recv().unwrap() {
+            RespValue::Array(Some(parts)) => {
+                assert_eq!(parts[0], RespValue::BulkString(Some(b"pmessage".to_vec())));
+                assert_eq!(parts[1], RespValue::BulkString(Some(b"n*".to_vec())));
+            }
+            other => panic!("unexpected push {:?}", other),
+        }
+
+        // Pattern does not match, nobody subscribed to the channel
+        assert_eq!(broker.publish(false, "sports", b"x"), 0);
+    }
+
+    #[test]
+    fn test_broker_shard_channels_are_separate() {
+        let mut broker = PubSubBroker::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        broker.subscribe(1, SubscriptionKind::Shard, names(&["orders"]), &tx);
+
+        assert_eq!(broker.publish(false, "orders", b"x"), 0);
+        assert_eq!(broker.publish(true, "orders", b"x"), 1);
+        match rx.try_recv().unwrap() {
+            RespValue::Array(Some(parts)) => {
+                assert_eq!(parts[0], RespValue::BulkString(Some(b"smessage".to_vec())));
+            }
+            other => panic!("unexpected push {:?}", other),
+        }
+        assert_eq!(broker.active_channels(true, None), names(&["orders"]));
+        assert!(broker.active_channels(false, None).is_empty());
+    }
+
+    #[test]
+    fn test_broker_unsubscribe_and_disconnect() {
+        let mut broker = PubSubBroker::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        broker.subscribe(1, SubscriptionKind::Channel, names(&["a", "b"]), &tx);
+        broker.subscribe(2, SubscriptionKind::Channel, names(&["a"]), &tx);
+        broker.subscribe(1, SubscriptionKind::Pattern, names(&["*"]), &tx);
+
+        assert_eq!(broker.num_subscribers(false, "a"), 2);
+        assert_eq!(broker.num_patterns(), 1);
+
+        broker.unsubscribe(2, SubscriptionKind::Channel, &names(&["a"]));
+        assert_eq!(broker.num_subscribers(false, "a"), 1);
+
+        broker.disconnect(1);
+        assert!(broker.active_channels(false, None).is_empty());
+        assert_eq!(broker.num_patterns(), 0);
+    }
+
+    #[test]
+    fn test_broker_prunes_closed_subscribers() {
+        let mut broker = PubSubBroker::new();
+        let (tx, rx) = mpsc::unbounded_channel();
+        broker.subscribe(1, SubscriptionKind::Channel, names(&["a"]), &tx);
+        drop(rx);
+
+        assert_eq!(broker.publish(false, "a", b"x"), 0);
+        assert!(broker.active_channels(false, None).is_empty());
+    }
+
+    #[test]
+    fn test_broker_redirected_invalidation() {
+        let mut broker = PubSubBroker::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        assert!(!broker.invalidate(7, Some(&[SDS::from("k")])));
+
+        broker.subscribe(
+            7,
+            SubscriptionKind::Channel,
+            names(&[INVALIDATE_CHANNEL]),
+            &tx,
+        );
+        assert!(broker.invalidate(7, Some(&[SDS::from("k")])));
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"message".to_vec())),
+                RespValue::BulkString(Some(INVALIDATE_CHANNEL.as_bytes().to_vec())),
+                RespValue::Array(Some(vec![RespValue::BulkString(Some(b"k".to_vec()))])),
+            ]))
+        );
+        // Only the redirect target gets it
+        assert!(!broker.invalidate(8, None));
+    }
+
+    #[tokio::test]
+    async fn test_actor_publish_roundtrip() {
+        let handle = PubSubActor::spawn();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let id = handle.next_subscriber_id();
+
+        handle.subscribe(id, SubscriptionKind::Channel, names(&["events"]), tx);
+        assert_eq!(
+            handle
+                .publish(false, "events".to_string(), b"1".to_vec())
+                .await,
+            1
+        );
+        assert!(rx.recv().await.is_some());
+
+        assert_eq!(
+            handle.numsub(false, names(&["events", "none"])).await,
+            vec![1, 0]
+        );
+        assert_eq!(
+            handle.channels(false, Some("ev*".to_string())).await,
+            names(&["events"])
+        );
+
+        handle.disconnect(id);
+        assert_eq!(
+            handle
+                .publish(false, "events".to_string(), b"2".to_vec())
+                .await,
+            0
+        );
+        assert!(handle.is_running());
+    }
+}
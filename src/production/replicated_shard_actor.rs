@@ -11,10 +11,12 @@
 //! └─────────────────────────┘        └─────────────────────────┘
 //! ```
 
-use crate::redis::{Command, CommandExecutor, RespValue};
+use super::ttl_manager::{run_active_expire_cycle, ACTIVE_EXPIRE_BUDGET};
+use crate::redis::{Command, CommandExecutor, FieldExpiry, RespValue, SDS};
 use crate::replication::state::ShardReplicaState;
-use crate::replication::{ConsistencyLevel, ReplicaId, ReplicationDelta};
+use crate::replication::{ConsistencyLevel, HyperLogLog, LwwRegister, ReplicaId, ReplicationDelta};
 use crate::simulator::VirtualTime;
+use std::collections::HashMap;
 use tokio::sync::{mpsc, oneshot};
 
 /// Messages for controlling the ReplicatedShardActor
@@ -44,12 +46,12 @@ pub enum ReplicatedShardMessage {
     /// Get snapshot of replicated keys for checkpointing
     GetSnapshot {
         response: oneshot::Sender<
-            std::collections::HashMap<String, crate::replication::state::ReplicatedValue>,
+            std::collections::HashMap<SDS, crate::replication::state::ReplicatedValue>,
         >,
     },
     /// Apply recovered state from persistence
     ApplyRecoveredState {
-        key: String,
+        key: SDS,
         value: crate::replication::state::ReplicatedValue,
     },
     /// Graceful shutdown
@@ -138,7 +140,7 @@ impl ReplicatedShardHandle {
     /// Get snapshot for checkpointing
     pub async fn get_snapshot(
         &self,
-    ) -> std::collections::HashMap<String, crate::replication::state::ReplicatedValue> {
+    ) -> std::collections::HashMap<SDS, crate::replication::state::ReplicatedValue> {
         let (tx, rx) = oneshot::channel();
         if self
             .tx
@@ -153,7 +155,7 @@ impl ReplicatedShardHandle {
     /// Apply recovered state (fire-and-forget)
     pub fn apply_recovered_state(
         &self,
-        key: String,
+        key: SDS,
         value: crate::replication::state::ReplicatedValue,
     ) {
         let _ = self
@@ -184,6 +186,20 @@ impl ReplicatedShardHandle {
     }
 }
 
+/// SET for a replicated key, which may not be UTF-8 (`Command::set` takes a
+/// `String`); `ex` is the remaining TTL in seconds
+fn set_command(key: SDS, value: SDS, ex: Option<i64>) -> Command {
+    Command::Set {
+        key,
+        value,
+        ex,
+        px: None,
+        nx: false,
+        xx: false,
+        get: false,
+    }
+}
+
 /// The ReplicatedShardActor owns its state exclusively - no Arc<RwLock<>> needed!
 pub struct ReplicatedShardActor {
     executor: CommandExecutor,
@@ -246,7 +262,11 @@ impl ReplicatedShardActor {
                     current_time,
                     response,
                 } => {
-                    let evicted = self.executor.evict_expired_direct(current_time);
+                    let evicted = run_active_expire_cycle(
+                        &mut self.executor,
+                        current_time,
+                        ACTIVE_EXPIRE_BUDGET,
+                    );
                     let _ = response.send(evicted);
                 }
 
@@ -262,7 +282,10 @@ impl ReplicatedShardActor {
                         .insert(key.clone(), value.clone());
 
                     // Also apply to executor for command execution
-                    if value.is_hash() {
+                    if let Some(hll) = value.get_hyperloglog() {
+                        let bytes = crate::redis::SDS::new(hll.to_redis_bytes());
+                        self.executor.execute(&set_command(key, bytes, None));
+                    } else if value.is_hash() {
                         // Recover hash data
                         if let Some(hash) = value.get_hash() {
                             let pairs: Vec<(crate::redis::SDS, crate::redis::SDS)> = hash
@@ -273,17 +296,18 @@ impl ReplicatedShardActor {
                                 })
                                 .collect();
                             if !pairs.is_empty() {
-                                let cmd = Command::HSet(key, pairs);
+                                let cmd = Command::HSet(key.clone(), pairs);
                                 self.executor.execute(&cmd);
+                                self.apply_hash_field_expiry(&key, hash);
                             }
                         }
                     } else if let Some(v) = value.get() {
                         if let Some(expiry_ms) = value.expiry_ms {
                             let seconds = (expiry_ms / 1000) as i64;
-                            let cmd = Command::setex(key, seconds, v.clone());
+                            let cmd = set_command(key, v.clone(), Some(seconds));
                             self.executor.execute(&cmd);
                         } else {
-                            let cmd = Command::set(key, v.clone());
+                            let cmd = set_command(key, v.clone(), None);
                             self.executor.execute(&cmd);
                         }
                     }
@@ -342,7 +366,7 @@ impl ReplicatedShardActor {
                         .record_write(key.clone(), value.clone(), expiry_ms),
                 )
             }
-            Command::Del(keys) => {
+            Command::Del(keys) | Command::Unlink(keys) => {
                 // Record deletion for each key
                 let mut result = None;
                 for key in keys {
@@ -354,7 +378,9 @@ impl ReplicatedShardActor {
             | Command::Decr(key)
             | Command::IncrBy(key, _)
             | Command::DecrBy(key, _)
+            | Command::IncrByFloat(key, _)
             | Command::Append(key, _)
+            | Command::SetRange(key, _, _)
             | Command::GetSet(key, _) => {
                 if let Some(value) = self.executor.get_data().get(key) {
                     if let Some(sds) = value.as_string() {
@@ -367,8 +393,78 @@ impl ReplicatedShardActor {
                 }
                 None
             }
+            // Replication covers database 0: copies into another one stay local
+            Command::Copy { db: Some(db), .. } if *db != 0 => None,
+            Command::Move(key, _) => {
+                if self.executor.get_data().contains_key(key) {
+                    return None;
+                }
+                self.replica_state.record_delete(key.clone())
+            }
+            Command::Rename(source, dest)
+            | Command::RenameNx(source, dest)
+            | Command::Copy { source, dest, .. } => {
+                if source == dest {
+                    return None;
+                }
+                let moved = !matches!(cmd, Command::Copy { .. })
+                    && !self.executor.get_data().contains_key(source);
+                let deleted = if moved {
+                    self.replica_state.record_delete(source.clone())
+                } else {
+                    None
+                };
+                let Some(sds) = self
+                    .executor
+                    .get_data()
+                    .get(dest)
+                    .and_then(|v| v.as_string())
+                    .cloned()
+                else {
+                    return deleted;
+                };
+                let expiry_ms = self.executor.key_ttl_ms(dest);
+                Some(
+                    self.replica_state
+                        .record_write(dest.clone(), sds, expiry_ms),
+                )
+            }
+            Command::GetDel(key) => {
+                if self.executor.get_data().contains_key(key) {
+                    return None;
+                }
+                self.replica_state.record_delete(key.clone())
+            }
+            Command::GetEx { key, .. } => match self.executor.get_data().get(key) {
+                Some(value) => {
+                    let sds = value.as_string()?.clone();
+                    let expiry_ms = self.executor.key_ttl_ms(key);
+                    Some(self.replica_state.record_write(key.clone(), sds, expiry_ms))
+                }
+                // An EXAT/PXAT in the past deletes the key
+                None => self.replica_state.record_delete(key.clone()),
+            },
+            Command::MSetNx(pairs) => {
+                // A rejected MSETNX leaves at least one key with its old value
+                let data = self.executor.get_data();
+                let applied = pairs
+                    .iter()
+                    .all(|(key, value)| data.get(key).and_then(|v| v.as_string()) == Some(value));
+                if !applied {
+                    return None;
+                }
+                let mut result = None;
+                for (key, value) in pairs {
+                    result = Some(self.replica_state.record_write(
+                        key.clone(),
+                        value.clone(),
+                        None,
+                    ));
+                }
+                result
+            }
             // Hash commands
-            Command::HSet(key, pairs) => {
+            Command::HSet(key, pairs) | Command::HMSet(key, pairs) => {
                 // TigerStyle: Preconditions
                 debug_assert!(!key.is_empty(), "Precondition: HSet key must not be empty");
                 debug_assert!(
@@ -439,6 +535,28 @@ impl ReplicatedShardActor {
                 }
                 None
             }
+            // Field-level writes and TTL changes replicate the resulting state
+            // of each field, with its TTL, so a deleted or expired field tombstones
+            Command::HSetNx(key, field, _) | Command::HIncrByFloat(key, field, _) => {
+                self.record_hash_fields(key, std::slice::from_ref(field))
+            }
+            Command::HGetDel(key, fields)
+            | Command::HGetEx { key, fields, .. }
+            | Command::HExpire { key, fields, .. }
+            | Command::HPersist(key, fields) => self.record_hash_fields(key, fields),
+            // HyperLogLog commands replicate the resulting registers
+            Command::PfAdd(key, _) | Command::PfMerge { dest: key, .. } => {
+                let hll = self
+                    .executor
+                    .get_data()
+                    .get(key)
+                    .and_then(|value| value.as_string())
+                    .and_then(|sds| HyperLogLog::from_redis_bytes(sds.as_bytes()))?;
+                Some(
+                    self.replica_state
+                        .record_hyperloglog_write(key.clone(), &hll),
+                )
+            }
             Command::FlushDb | Command::FlushAll => None,
             _ => None,
         }
@@ -455,7 +573,18 @@ impl ReplicatedShardActor {
 
         self.replica_state.apply_remote_delta(delta.clone());
 
-        if delta.value.is_hash() {
+        if delta.value.is_hyperloglog() {
+            // Write the joined registers, not just the remote ones
+            if let Some(hll) = self
+                .replica_state
+                .get_replicated(delta.key.as_bytes())
+                .and_then(|value| value.get_hyperloglog())
+            {
+                let bytes = crate::redis::SDS::new(hll.to_redis_bytes());
+                self.executor
+                    .execute(&set_command(delta.key.clone(), bytes, None));
+            }
+        } else if delta.value.is_hash() {
             // TigerStyle: Postcondition - replica_state should have the hash
             #[cfg(debug_assertions)]
             {
@@ -477,6 +606,7 @@ impl ReplicatedShardActor {
                 if !pairs.is_empty() {
                     let cmd = Command::HSet(delta.key.clone(), pairs.clone());
                     self.executor.execute(&cmd);
+                    self.apply_hash_field_expiry(&delta.key, hash);
 
                     // TigerStyle: Postcondition - executor should have the hash fields
                     #[cfg(debug_assertions)]
@@ -516,18 +646,49 @@ impl ReplicatedShardActor {
         } else if let Some(value) = delta.value.get() {
             if let Some(expiry_ms) = delta.value.expiry_ms {
                 let seconds = (expiry_ms / 1000) as i64;
-                let cmd = Command::setex(delta.key.clone(), seconds, value.clone());
+                let cmd = set_command(delta.key.clone(), value.clone(), Some(seconds));
                 self.executor.execute(&cmd);
             } else {
-                let cmd = Command::set(delta.key.clone(), value.clone());
+                let cmd = set_command(delta.key.clone(), value.clone(), None);
                 self.executor.execute(&cmd);
             }
         } else if delta.value.is_tombstone() {
-            let cmd = Command::del(delta.key.clone());
+            let cmd = Command::Del(vec![delta.key.clone()]);
             self.executor.execute(&cmd);
         }
     }
 
+    /// Record the executor's current value and TTL of each of `fields`
+    fn record_hash_fields(&mut self, key: &[u8], fields: &[SDS]) -> Option<ReplicationDelta> {
+        let hash = self.executor.get_data().get(key).and_then(|v| v.as_hash());
+        let states: Vec<_> = fields
+            .iter()
+            .map(|field| {
+                let state = hash.and_then(|h| h.get(field)).map(|value| {
+                    let expiry_ms = self.executor.hash_field_expiry_unix_ms(key, field);
+                    (value.clone(), expiry_ms)
+                });
+                (field.to_string(), state)
+            })
+            .collect();
+        self.replica_state
+            .record_hash_fields(SDS::from(key), states)
+    }
+
+    /// Apply each field's replicated TTL after its value was written by HSET
+    fn apply_hash_field_expiry(&mut self, key: &[u8], hash: &HashMap<String, LwwRegister<SDS>>) {
+        for (field, lww) in hash {
+            if let (Some(expiry_ms), false) = (lww.expiry_ms, lww.tombstone) {
+                self.executor.execute(&Command::HExpire {
+                    key: SDS::from(key),
+                    expiry: FieldExpiry::UnixMillis(expiry_ms as i64),
+                    condition: None,
+                    fields: vec![SDS::from_str(field)],
+                });
+            }
+        }
+    }
+
     /// Verify invariants (VOPR pattern)
     #[cfg(debug_assertions)]
     fn verify_invariants(&self) {
@@ -562,9 +723,7 @@ mod tests {
         assert!(delta.is_some());
 
         // Execute GET
-        let result = handle
-            .execute_readonly(Command::Get("key1".to_string()))
-            .await;
+        let result = handle.execute_readonly(Command::Get("key1".into())).await;
         assert!(matches!(result, RespValue::BulkString(Some(_))));
 
         handle.shutdown().await;
@@ -608,7 +767,7 @@ mod tests {
         let mut clock = crate::replication::lattice::LamportClock::new(remote_replica);
         clock.time = 100; // Advance clock to simulate remote write
         let delta = ReplicationDelta::new(
-            "remote_key".to_string(),
+            "remote_key".into(),
             crate::replication::state::ReplicatedValue::with_value(
                 crate::redis::SDS::from_str("remote_value"),
                 clock,
@@ -624,13 +783,91 @@ mod tests {
 
         // Verify it's there
         let result = handle
-            .execute_readonly(Command::Get("remote_key".to_string()))
+            .execute_readonly(Command::Get("remote_key".into()))
             .await;
         assert!(matches!(result, RespValue::BulkString(Some(_))));
 
         handle.shutdown().await;
     }
 
+    #[tokio::test]
+    async fn test_replicated_shard_actor_hyperloglog_converges() {
+        let r1 = ReplicatedShardActor::spawn(ReplicaId::new(1), ConsistencyLevel::Eventual, 0);
+        let r2 = ReplicatedShardActor::spawn(ReplicaId::new(2), ConsistencyLevel::Eventual, 0);
+
+        let pfadd = |range: std::ops::Range<u32>| {
+            Command::PfAdd(
+                "visitors".into(),
+                range
+                    .map(|i| crate::redis::SDS::from_str(&format!("user:{}", i)))
+                    .collect(),
+            )
+        };
+        let (_, delta1) = r1.execute(pfadd(0..100)).await;
+        let (_, delta2) = r2.execute(pfadd(50..150)).await;
+        assert!(delta1.as_ref().unwrap().value.is_hyperloglog());
+
+        // Concurrent PFADDs on both replicas join instead of overwriting
+        r1.apply_remote_delta(delta2.unwrap());
+        r2.apply_remote_delta(delta1.unwrap());
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+        let count = Command::PfCount(vec!["visitors".into()]);
+        let (count1, _) = r1.execute(count.clone()).await;
+        let (count2, _) = r2.execute(count).await;
+        assert_eq!(count1, count2);
+        match count1 {
+            RespValue::Integer(n) => assert!((147..=153).contains(&n), "estimate {}", n),
+            other => panic!("unexpected PFCOUNT reply {:?}", other),
+        }
+
+        r1.shutdown().await;
+        r2.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_replicated_shard_actor_hash_field_ttl_replicates() {
+        let r1 = ReplicatedShardActor::spawn(ReplicaId::new(1), ConsistencyLevel::Eventual, 0);
+        let r2 = ReplicatedShardActor::spawn(ReplicaId::new(2), ConsistencyLevel::Eventual, 0);
+        let fields = |names: &[&str]| names.iter().map(|f| SDS::from_str(f)).collect::<Vec<_>>();
+
+        let hset = Command::HSet(
+            "h".into(),
+            vec![
+                (SDS::from_str("a"), SDS::from_str("1")),
+                (SDS::from_str("b"), SDS::from_str("2")),
+            ],
+        );
+        let (_, delta) = r1.execute(hset).await;
+        r2.apply_remote_delta(delta.unwrap());
+
+        let hexpire = Command::HExpire {
+            key: "h".into(),
+            expiry: FieldExpiry::Seconds(100),
+            condition: None,
+            fields: fields(&["a"]),
+        };
+        let (_, delta) = r1.execute(hexpire).await;
+        r2.apply_remote_delta(delta.unwrap());
+        tokio::time::sleep(tokio::time::Duration::from_millis(10)).await;
+
+        let httl = Command::HTtl {
+            key: "h".into(),
+            fields: fields(&["a", "b"]),
+            millis: false,
+        };
+        match r2.execute(httl).await.0 {
+            RespValue::Array(Some(ttls)) => {
+                assert!(matches!(ttls[0], RespValue::Integer(t) if (1..=100).contains(&t)));
+                assert_eq!(ttls[1], RespValue::Integer(-1));
+            }
+            other => panic!("unexpected HTTL reply {:?}", other),
+        }
+
+        r1.shutdown().await;
+        r2.shutdown().await;
+    }
+
     #[tokio::test]
     async fn test_replicated_shard_actor_snapshot() {
         let handle = ReplicatedShardActor::spawn(ReplicaId::new(1), ConsistencyLevel::Eventual, 0);
@@ -652,8 +889,8 @@ mod tests {
         // Get snapshot
         let snapshot = handle.get_snapshot().await;
         assert_eq!(snapshot.len(), 2);
-        assert!(snapshot.contains_key("k1"));
-        assert!(snapshot.contains_key("k2"));
+        assert!(snapshot.contains_key(b"k1".as_slice()));
+        assert!(snapshot.contains_key(b"k2".as_slice()));
 
         handle.shutdown().await;
     }
@@ -1,7 +1,9 @@
 use super::gossip_actor::GossipActorHandle;
+use super::latency::LatencyMonitor;
 use super::replicated_shard_actor::{ReplicatedShardActor, ReplicatedShardHandle};
+use super::sharded_actor::hash_tag;
 use crate::io::{ProductionTimeSource, TimeSource};
-use crate::redis::{Command, RespValue};
+use crate::redis::{Command, RespValue, SDS};
 use crate::replication::gossip::GossipState;
 use crate::replication::{ReplicaId, ReplicationConfig, ReplicationDelta};
 use crate::simulator::VirtualTime;
@@ -27,9 +29,9 @@ pub enum GossipBackend {
 
 const NUM_SHARDS: usize = 16;
 
-fn hash_key(key: &str) -> usize {
+fn hash_key(key: &[u8]) -> usize {
     let mut hasher = DefaultHasher::new();
-    key.hash(&mut hasher);
+    hash_tag(key).hash(&mut hasher);
     (hasher.finish() as usize) % NUM_SHARDS
 }
 
@@ -61,6 +63,8 @@ pub struct ReplicatedShardedState<T: TimeSource = ProductionTimeSource> {
     delta_sink: Option<DeltaSinkSender>,
     /// Time source for getting current time
     time_source: T,
+    /// LATENCY samples from this server's background loops
+    latency: Arc<LatencyMonitor>,
 }
 
 /// Production-specific constructors
@@ -99,6 +103,7 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
             gossip_backend: GossipBackend::Locked(gossip_state),
             delta_sink: None,
             time_source,
+            latency: Arc::new(LatencyMonitor::default()),
         }
     }
 
@@ -125,6 +130,7 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
             gossip_backend: GossipBackend::Actor(gossip_handle),
             delta_sink: None,
             time_source,
+            latency: Arc::new(LatencyMonitor::default()),
         }
     }
 
@@ -138,6 +144,12 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
         self.delta_sink = None;
     }
 
+    /// The server's LATENCY monitor
+    #[inline]
+    pub fn latency(&self) -> &Arc<LatencyMonitor> {
+        &self.latency
+    }
+
     /// Check if streaming persistence is enabled
     pub fn has_streaming_persistence(&self) -> bool {
         self.delta_sink.is_some()
@@ -146,7 +158,7 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
     /// Execute a command (async - uses actor message passing)
     pub async fn execute(&self, cmd: Command) -> RespValue {
         if let Some(key) = cmd.get_primary_key() {
-            let shard_idx = hash_key(&key);
+            let shard_idx = hash_key(key);
             let (result, delta) = self.shards[shard_idx].execute(cmd).await;
 
             if let Some(delta) = delta {
@@ -196,7 +208,15 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
                     .iter()
                     .map(|(key, value)| {
                         let shard_idx = hash_key(key);
-                        let set_cmd = Command::set(key.clone(), value.clone());
+                        let set_cmd = Command::Set {
+                            key: key.clone(),
+                            value: value.clone(),
+                            ex: None,
+                            px: None,
+                            nx: false,
+                            xx: false,
+                            get: false,
+                        };
                         self.shards[shard_idx].execute(set_cmd)
                     })
                     .collect();
@@ -265,6 +285,9 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
                 );
                 RespValue::BulkString(Some(info.into_bytes()))
             }
+            Command::LatencyLatest => self.latency.latest_reply(),
+            Command::LatencyHistory(event) => self.latency.history_reply(event),
+            Command::LatencyReset(events) => RespValue::Integer(self.latency.reset(events) as i64),
             _ => RespValue::Error("ERR unknown command".to_string()),
         }
     }
@@ -354,9 +377,7 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
     ///
     /// Returns a HashMap of all keys to their ReplicatedValue across all shards.
     /// This is used by the CheckpointManager to create full state snapshots.
-    pub async fn snapshot_state(
-        &self,
-    ) -> HashMap<String, crate::replication::state::ReplicatedValue> {
+    pub async fn snapshot_state(&self) -> HashMap<SDS, crate::replication::state::ReplicatedValue> {
         let futures: Vec<_> = self
             .shards
             .iter()
@@ -378,7 +399,7 @@ impl<T: TimeSource> ReplicatedShardedState<T> {
     /// Then all deltas are applied in order (CRDT merge is idempotent).
     pub fn apply_recovered_state(
         &self,
-        checkpoint_state: Option<HashMap<String, crate::replication::state::ReplicatedValue>>,
+        checkpoint_state: Option<HashMap<SDS, crate::replication::state::ReplicatedValue>>,
         deltas: Vec<ReplicationDelta>,
     ) {
         // Step 1: Apply checkpoint state if present (fire-and-forget to actors)
@@ -413,6 +434,7 @@ impl<T: TimeSource> Clone for ReplicatedShardedState<T> {
             gossip_backend: self.gossip_backend.clone(),
             delta_sink: self.delta_sink.clone(),
             time_source: self.time_source.clone(),
+            latency: self.latency.clone(),
         }
     }
 }
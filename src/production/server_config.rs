@@ -9,7 +9,8 @@
 //! - `TLS_REQUIRE_CLIENT_CERT`: Require client certificates (default: false)
 //!
 //! ## ACL Configuration (requires `acl` feature)
-//! - `REDIS_REQUIRE_PASS`: Simple password for AUTH (optional)
+//! - `REDIS_REQUIRE_PASS`: Simple password for AUTH (optional, overrides the
+//!   config file's `[security] requirepass`)
 //! - `ACL_FILE`: Path to ACL configuration file (optional)
 
 use std::path::PathBuf;
@@ -82,6 +83,15 @@ impl ServerConfig {
         }
     }
 
+    /// Use the config file's `requirepass` unless REDIS_REQUIRE_PASS is set
+    pub fn with_file_requirepass(mut self, requirepass: Option<&str>) -> Self {
+        if self.acl.require_pass.is_none() {
+            self.acl.require_pass = requirepass.map(str::to_string);
+            self.acl.require_auth = self.acl.require_pass.is_some() || self.acl.acl_file.is_some();
+        }
+        self
+    }
+
     /// Check if TLS is enabled
     pub fn tls_enabled(&self) -> bool {
         self.tls.is_some()
@@ -137,4 +147,17 @@ mod tests {
         };
         assert!(config.tls_enabled());
     }
+
+    #[test]
+    fn test_file_requirepass_fallback() {
+        let config = ServerConfig::default().with_file_requirepass(Some("secret"));
+        assert_eq!(config.acl.require_pass.as_deref(), Some("secret"));
+        assert!(config.acl_enabled());
+
+        // The environment wins over the file
+        let mut config = ServerConfig::default();
+        config.acl.require_pass = Some("env".to_string());
+        let config = config.with_file_requirepass(Some("file"));
+        assert_eq!(config.acl.require_pass.as_deref(), Some("env"));
+    }
 }
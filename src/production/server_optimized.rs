@@ -1,6 +1,9 @@
 use super::connection_optimized::{ConnectionConfig, OptimizedConnectionHandler};
 use super::ttl_manager::TtlManagerActor;
-use super::{ConnectionPool, PerformanceConfig, ServerConfig, ShardedActorState};
+use super::{
+    ClientRegistry, ClusterConfig, ClusterState, ConfigRegistry, ConnectionPool, PerformanceConfig,
+    PubSubActor, ServerConfig, ShardedActorState,
+};
 use crate::observability::{DatadogConfig, Metrics};
 use crate::security::AclManager;
 use parking_lot::RwLock;
@@ -24,7 +27,11 @@ impl OptimizedRedisServer {
 
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         // Load performance configuration from file or environment
-        let perf_config = PerformanceConfig::from_env();
+        let config_path = PerformanceConfig::env_path();
+        let file_config = PerformanceConfig::try_from_file(&config_path);
+        // CONFIG REWRITE may only touch a file that loaded cleanly
+        let config_file = file_config.is_some().then_some(config_path);
+        let perf_config = file_config.unwrap_or_default();
         if let Err(e) = perf_config.validate() {
             error!("Invalid performance config: {}", e);
             return Err(e.into());
@@ -39,8 +46,9 @@ impl OptimizedRedisServer {
             perf_config.batching.min_pipeline_buffer,
         );
 
-        // Load security configuration
-        let server_config = ServerConfig::from_env();
+        // Load security configuration (the environment overrides the file)
+        let server_config = ServerConfig::from_env()
+            .with_file_requirepass(perf_config.security.requirepass.as_deref());
 
         // Build TLS acceptor if TLS is configured
         #[cfg(feature = "tls")]
@@ -75,7 +83,24 @@ impl OptimizedRedisServer {
         let acl_manager = Self::create_acl_manager(&server_config);
         let acl_manager = Arc::new(RwLock::new(acl_manager));
 
-        let state = ShardedActorState::with_perf_config(&perf_config);
+        let mut state = ShardedActorState::with_perf_config(&perf_config);
+
+        // Redis Cluster mode: serve only the slots this node owns
+        if let Some(cluster_config) = ClusterConfig::from_env() {
+            let cluster = ClusterState::new(&cluster_config).map_err(|e| {
+                error!("Invalid cluster config: {}", e);
+                e
+            })?;
+            info!(
+                "Cluster mode enabled: node {} ({}) of {}",
+                cluster.myself().id,
+                cluster_config.nodes[cluster_config.myself],
+                cluster_config.nodes.len()
+            );
+            state = state.with_cluster(cluster);
+        } else if std::env::var("REDIS_CLUSTER_NODES").is_ok() {
+            warn!("REDIS_CLUSTER_NODES is set but REDIS_CLUSTER_MYSELF is missing or not listed; cluster mode disabled");
+        }
         let connection_pool = Arc::new(ConnectionPool::new(10000, 512));
 
         // Create connection config from performance config
@@ -92,8 +117,22 @@ impl OptimizedRedisServer {
         );
 
         // Spawn TTL manager actor with shutdown handle
-        let _ttl_handle = TtlManagerActor::spawn(state.clone(), metrics.clone());
-        info!("TTL manager started (100ms interval)");
+        let ttl_interval_ms = perf_config.ttl.interval_ms();
+        let ttl_handle =
+            TtlManagerActor::spawn_with_interval(state.clone(), ttl_interval_ms, metrics.clone());
+        info!("TTL manager started ({}ms interval)", ttl_interval_ms);
+
+        // CONFIG GET/SET/REWRITE over the file and environment settings
+        let config = Arc::new(
+            ConfigRegistry::new(config_file, perf_config, server_config)
+                .with_targets(state.clone(), acl_manager.clone(), ttl_handle),
+        );
+
+        // Pub/Sub broker sits beside the shards: messages are not routed by key
+        let pubsub = PubSubActor::spawn();
+
+        // Connections register here for CLIENT LIST/KILL/PAUSE
+        let clients = Arc::new(ClientRegistry::new());
 
         let listener = TcpListener::bind(&self.addr).await?;
         info!("Redis server listening on {}", self.addr);
@@ -107,6 +146,9 @@ impl OptimizedRedisServer {
                     let metrics_clone = metrics.clone();
                     let conn_config_clone = conn_config.clone();
                     let acl_manager_clone = acl_manager.clone();
+                    let pubsub_clone = pubsub.clone();
+                    let clients_clone = clients.clone();
+                    let config_clone = config.clone();
 
                     // Set TCP_NODELAY for lower latency before any wrapping
                     if let Err(e) = stream.set_nodelay(true) {
@@ -157,6 +199,9 @@ impl OptimizedRedisServer {
                             conn_config_clone,
                             acl_manager_clone,
                             client_cert_cn,
+                            pubsub_clone,
+                            clients_clone,
+                            config_clone,
                         );
                         handler.run().await;
                     });
@@ -1,8 +1,17 @@
 use crate::io::{ProductionTimeSource, TimeSource};
-use crate::redis::{Command, CommandExecutor, RespValue};
+use crate::redis::{
+    keyspace_info, BlockedClientId, Command, CommandExecutor, KeyspaceStats, MemoryConfig,
+    MemoryStats, RedisSet, RespValue, SetOperation, Value, DEFAULT_DATABASES, SDS,
+};
 use crate::simulator::VirtualTime;
+use ahash::AHashMap;
+use parking_lot::RwLock;
+use std::cell::Cell;
+use std::future::Future;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, oneshot};
 
 // P4 optimization: Use AHash for faster shard routing
@@ -11,11 +20,17 @@ use ahash::AHasher;
 
 #[cfg(not(feature = "opt-fxhash-routing"))]
 use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
 
 use super::adaptive_actor::{AdaptiveActor, AdaptiveActorConfig, AdaptiveActorHandle};
+use super::cluster::ClusterState;
+use super::latency::LatencyMonitor;
 use super::load_balancer::ScalingDecision;
 use super::perf_config::PerformanceConfig;
 use super::response_pool::{response_future, ResponsePool, ResponseSlot};
+use super::slowlog::SlowLog;
+use super::tracking::{TrackedClient, TrackingClientId, TrackingTable};
+use super::ttl_manager::{run_active_expire_cycle, ACTIVE_EXPIRE_BUDGET};
 
 /// Configuration for dynamic sharding behavior
 #[derive(Clone, Debug)]
@@ -32,6 +47,8 @@ pub struct ShardConfig {
     pub adaptive_replication: bool,
     /// Interval for load balancing checks (milliseconds, default: 10000)
     pub load_check_interval_ms: u64,
+    /// Logical databases for SELECT (default: 16)
+    pub databases: usize,
 }
 
 impl Default for ShardConfig {
@@ -47,6 +64,7 @@ impl Default for ShardConfig {
             auto_scale: false,
             adaptive_replication: false,
             load_check_interval_ms: 10000,
+            databases: DEFAULT_DATABASES,
         }
     }
 }
@@ -72,56 +90,158 @@ impl ShardConfig {
 pub enum ShardMessage {
     Command {
         cmd: Command,
+        /// Database selected by the issuing connection
+        db: usize,
         virtual_time: VirtualTime,
+        /// Tracking client that issued the command (NOLOOP skips its own writes)
+        caller: Option<TrackingClientId>,
         response_tx: oneshot::Sender<RespValue>,
     },
     /// Fire-and-forget batch command (no response needed)
     #[allow(dead_code)]
     BatchCommand {
         cmd: Command,
+        db: usize,
         virtual_time: VirtualTime,
     },
     EvictExpired {
         virtual_time: VirtualTime,
         response_tx: oneshot::Sender<usize>,
     },
+    /// Blocking command (BLPOP, BLMOVE, ...): the reply is sent once data
+    /// arrives or the client's timeout passes in virtual time
+    BlockingCommand {
+        client_id: BlockedClientId,
+        cmd: Command,
+        db: usize,
+        virtual_time: VirtualTime,
+        response_tx: oneshot::Sender<RespValue>,
+    },
+    /// Advance the shard clock so blocked clients past their deadline time out
+    ExpireBlocked { virtual_time: VirtualTime },
+    /// WATCH version stamps of keys on this shard
+    KeyVersions {
+        keys: Vec<SDS>,
+        db: usize,
+        response_tx: oneshot::Sender<Vec<u64>>,
+    },
+    /// Take (or copy, unless `remove`) a key's value and deadline: the source
+    /// half of a cross-shard RENAME or COPY
+    ExportKey {
+        key: SDS,
+        db: usize,
+        remove: bool,
+        virtual_time: VirtualTime,
+        response_tx: oneshot::Sender<Option<(Value, Option<VirtualTime>)>>,
+    },
+    /// Store a value taken by `ExportKey`; false if the key exists and not `replace`
+    ImportKey {
+        key: SDS,
+        db: usize,
+        value: Value,
+        deadline: Option<VirtualTime>,
+        replace: bool,
+        virtual_time: VirtualTime,
+        response_tx: oneshot::Sender<bool>,
+    },
+    /// Hand the shard to one transaction: once granted, the actor serves only
+    /// `session_rx` until its sender is dropped, then resumes its own queue
+    Exclusive {
+        session_rx: mpsc::UnboundedReceiver<ShardMessage>,
+        granted_tx: oneshot::Sender<()>,
+    },
+    /// Apply this shard's share of the maxmemory settings
+    SetMemoryConfig { config: MemoryConfig },
+    /// CONFIG RESETSTAT: zero the eviction counters
+    ResetStats,
+    /// Memory accounting and eviction counters for INFO
+    MemoryStats {
+        response_tx: oneshot::Sender<MemoryStats>,
+    },
+    /// Key counts of every database for INFO
+    KeyspaceStats {
+        response_tx: oneshot::Sender<Vec<KeyspaceStats>>,
+    },
+    /// CLIENT TRACKING ON: register (or re-register) a tracking client
+    EnableTracking {
+        client: TrackingClientId,
+        tracked: TrackedClient,
+    },
+    /// CLIENT TRACKING OFF or disconnect
+    DisableTracking { client: TrackingClientId },
+    /// A tracking client is about to read these keys of this shard
+    TrackReads {
+        client: TrackingClientId,
+        keys: Vec<SDS>,
+    },
     /// Fast path for GET - avoids Command enum overhead
     FastGet {
         key: bytes::Bytes,
+        db: usize,
         response_tx: oneshot::Sender<RespValue>,
     },
     /// Fast path for SET - avoids Command enum overhead
     FastSet {
         key: bytes::Bytes,
         value: bytes::Bytes,
+        db: usize,
         response_tx: oneshot::Sender<RespValue>,
     },
     /// Fast batch GET - multiple keys in single message for pipelining
     FastBatchGet {
         keys: Vec<bytes::Bytes>,
+        db: usize,
         response_tx: oneshot::Sender<Vec<RespValue>>,
     },
     /// Fast batch SET - multiple key-value pairs in single message for pipelining
     FastBatchSet {
         pairs: Vec<(bytes::Bytes, bytes::Bytes)>,
+        db: usize,
         response_tx: oneshot::Sender<Vec<RespValue>>,
     },
     /// Pooled fast GET - uses response slot instead of oneshot channel
     PooledFastGet {
         key: bytes::Bytes,
+        db: usize,
         response_slot: Arc<ResponseSlot<RespValue>>,
     },
     /// Pooled fast SET - uses response slot instead of oneshot channel
     PooledFastSet {
         key: bytes::Bytes,
         value: bytes::Bytes,
+        db: usize,
         response_slot: Arc<ResponseSlot<RespValue>>,
     },
 }
 
+impl ShardMessage {
+    /// Database whose keys the message reads or writes, if any
+    fn db(&self) -> Option<usize> {
+        match self {
+            ShardMessage::Command { db, .. }
+            | ShardMessage::BatchCommand { db, .. }
+            | ShardMessage::BlockingCommand { db, .. }
+            | ShardMessage::KeyVersions { db, .. }
+            | ShardMessage::ExportKey { db, .. }
+            | ShardMessage::ImportKey { db, .. }
+            | ShardMessage::FastGet { db, .. }
+            | ShardMessage::FastSet { db, .. }
+            | ShardMessage::FastBatchGet { db, .. }
+            | ShardMessage::FastBatchSet { db, .. }
+            | ShardMessage::PooledFastGet { db, .. }
+            | ShardMessage::PooledFastSet { db, .. } => Some(*db),
+            _ => None,
+        }
+    }
+}
+
 pub struct ShardActor {
     executor: CommandExecutor,
     rx: mpsc::UnboundedReceiver<ShardMessage>,
+    /// Reply channels of clients parked in the executor's wait queue
+    blocked_replies: AHashMap<BlockedClientId, oneshot::Sender<RespValue>>,
+    /// CLIENT TRACKING state for the keys of this shard
+    tracking: TrackingTable,
     #[allow(dead_code)]
     shard_id: usize,
     #[allow(dead_code)]
@@ -146,6 +266,8 @@ impl ShardActor {
         ShardActor {
             executor,
             rx,
+            blocked_replies: AHashMap::new(),
+            tracking: TrackingTable::new(),
             shard_id,
             num_shards,
         }
@@ -159,6 +281,7 @@ impl ShardActor {
         simulation_start_epoch: i64,
         shard_id: usize,
         num_shards: usize,
+        databases: usize,
         shared_script_cache: crate::redis::lua::SharedScriptCache,
     ) -> Self {
         debug_assert!(
@@ -169,9 +292,12 @@ impl ShardActor {
         );
         let mut executor = CommandExecutor::with_shared_script_cache(shared_script_cache);
         executor.set_simulation_start_epoch(simulation_start_epoch);
+        executor.set_databases(databases);
         ShardActor {
             executor,
             rx,
+            blocked_replies: AHashMap::new(),
+            tracking: TrackingTable::new(),
             shard_id,
             num_shards,
         }
@@ -180,116 +306,344 @@ impl ShardActor {
     async fn run(mut self) {
         while let Some(msg) = self.rx.recv().await {
             match msg {
-                ShardMessage::Command {
-                    cmd,
-                    virtual_time,
-                    response_tx,
-                } => {
-                    self.executor.set_time(virtual_time);
-                    let response = self.executor.execute(&cmd);
-                    let _ = response_tx.send(response);
-                }
-                ShardMessage::BatchCommand { cmd, virtual_time } => {
-                    // Fire-and-forget: execute without sending response
-                    self.executor.set_time(virtual_time);
-                    let _ = self.executor.execute(&cmd);
-                }
-                ShardMessage::EvictExpired {
-                    virtual_time,
-                    response_tx,
-                } => {
-                    let evicted = self.executor.evict_expired_direct(virtual_time);
-                    let _ = response_tx.send(evicted);
-                }
-                ShardMessage::FastGet { key, response_tx } => {
-                    // Fast path: direct GET without Command enum overhead
-                    let key_str = unsafe { std::str::from_utf8_unchecked(&key) };
-                    let response = self.executor.get_direct(key_str);
-                    let _ = response_tx.send(response);
-                }
-                ShardMessage::FastSet {
-                    key,
-                    value,
-                    response_tx,
+                ShardMessage::Exclusive {
+                    mut session_rx,
+                    granted_tx,
                 } => {
-                    // Fast path: direct SET without Command enum overhead
-                    let key_str = unsafe { std::str::from_utf8_unchecked(&key) };
-                    let response = self.executor.set_direct(key_str, &value);
-                    let _ = response_tx.send(response);
-                }
-                ShardMessage::FastBatchGet { keys, response_tx } => {
-                    // Batch GET: process multiple keys in single message
-                    let mut results = Vec::with_capacity(keys.len());
-                    for key in keys {
-                        let key_str = unsafe { std::str::from_utf8_unchecked(&key) };
-                        results.push(self.executor.get_direct(key_str));
+                    // Messages from other clients wait in `rx` meanwhile, which is
+                    // what makes EXEC atomic on this shard. Blocked clients wait
+                    // too: they are served once the transaction is done
+                    if granted_tx.send(()).is_ok() {
+                        self.executor.hold_blocked_clients(true);
+                        while let Some(msg) = session_rx.recv().await {
+                            self.handle(msg);
+                        }
+                        self.executor.hold_blocked_clients(false);
+                        self.deliver_unblocked();
+                        self.send_invalidations(None);
                     }
-                    let _ = response_tx.send(results);
                 }
-                ShardMessage::FastBatchSet { pairs, response_tx } => {
-                    // Batch SET: process multiple key-value pairs in single message
-                    let mut results = Vec::with_capacity(pairs.len());
-                    for (key, value) in pairs {
-                        let key_str = unsafe { std::str::from_utf8_unchecked(&key) };
-                        results.push(self.executor.set_direct(key_str, &value));
+                msg => self.handle(msg),
+            }
+        }
+    }
+
+    fn handle(&mut self, msg: ShardMessage) {
+        self.cancel_disconnected_clients();
+        if let Some(db) = msg.db() {
+            self.executor.select_db(db);
+        }
+        let caller = match &msg {
+            ShardMessage::Command { caller, .. } => *caller,
+            _ => None,
+        };
+        match msg {
+            ShardMessage::Command {
+                cmd,
+                virtual_time,
+                response_tx,
+                ..
+            } => {
+                self.executor.set_time(virtual_time);
+                let response = self.executor.execute(&cmd);
+                let _ = response_tx.send(response);
+            }
+            ShardMessage::BatchCommand {
+                cmd, virtual_time, ..
+            } => {
+                // Fire-and-forget: execute without sending response
+                self.executor.set_time(virtual_time);
+                let _ = self.executor.execute(&cmd);
+            }
+            ShardMessage::EvictExpired {
+                virtual_time,
+                response_tx,
+            } => {
+                let evicted =
+                    run_active_expire_cycle(&mut self.executor, virtual_time, ACTIVE_EXPIRE_BUDGET);
+                let _ = response_tx.send(evicted);
+            }
+            ShardMessage::BlockingCommand {
+                client_id,
+                cmd,
+                virtual_time,
+                response_tx,
+                ..
+            } => {
+                self.executor.set_time(virtual_time);
+                match self.executor.execute_blocking(client_id, &cmd) {
+                    Some(response) => {
+                        let _ = response_tx.send(response);
+                    }
+                    None => {
+                        debug_assert!(
+                            !self.blocked_replies.contains_key(&client_id),
+                            "Blocked client id {} reused",
+                            client_id
+                        );
+                        self.blocked_replies.insert(client_id, response_tx);
                     }
-                    let _ = response_tx.send(results);
                 }
-                ShardMessage::PooledFastGet { key, response_slot } => {
-                    // Pooled fast GET: uses response slot instead of oneshot
-                    let key_str = unsafe { std::str::from_utf8_unchecked(&key) };
-                    let response = self.executor.get_direct(key_str);
-                    response_slot.send(response);
+            }
+            ShardMessage::ExpireBlocked { virtual_time } => {
+                self.executor.set_time(virtual_time);
+            }
+            ShardMessage::KeyVersions {
+                keys, response_tx, ..
+            } => {
+                let versions = keys.iter().map(|k| self.executor.key_version(k)).collect();
+                let _ = response_tx.send(versions);
+            }
+            ShardMessage::ExportKey {
+                key,
+                remove,
+                virtual_time,
+                response_tx,
+                ..
+            } => {
+                self.executor.set_time(virtual_time);
+                let _ = response_tx.send(self.executor.export_key(&key, remove));
+            }
+            ShardMessage::ImportKey {
+                key,
+                value,
+                deadline,
+                replace,
+                virtual_time,
+                response_tx,
+                ..
+            } => {
+                self.executor.set_time(virtual_time);
+                let imported = self.executor.import_key(&key, value, deadline, replace);
+                let _ = response_tx.send(imported);
+            }
+            ShardMessage::SetMemoryConfig { config } => {
+                self.executor.set_memory_config(config);
+            }
+            ShardMessage::ResetStats => {
+                self.executor.reset_stats();
+            }
+            ShardMessage::MemoryStats { response_tx } => {
+                let _ = response_tx.send(self.executor.memory_stats());
+            }
+            ShardMessage::KeyspaceStats { response_tx } => {
+                let _ = response_tx.send(self.executor.keyspace_stats());
+            }
+            ShardMessage::EnableTracking { client, tracked } => {
+                self.tracking.enable(client, tracked);
+                self.executor.set_track_writes(true);
+            }
+            ShardMessage::DisableTracking { client } => {
+                self.tracking.disable(client);
+                self.executor.set_track_writes(!self.tracking.is_empty());
+            }
+            ShardMessage::TrackReads { client, keys } => {
+                self.tracking.record_reads(client, keys);
+            }
+            ShardMessage::Exclusive { .. } => {
+                // Dropping `granted_tx` refuses the request
+                debug_assert!(
+                    false,
+                    "Shard {} locked from inside a session",
+                    self.shard_id
+                );
+            }
+            ShardMessage::FastGet {
+                key, response_tx, ..
+            } => {
+                // Fast path: direct GET without Command enum overhead
+                let response = self.executor.get_direct(&key);
+                let _ = response_tx.send(response);
+            }
+            ShardMessage::FastSet {
+                key,
+                value,
+                response_tx,
+                ..
+            } => {
+                // Fast path: direct SET without Command enum overhead
+                let response = self.executor.set_direct(&key, &value);
+                let _ = response_tx.send(response);
+            }
+            ShardMessage::FastBatchGet {
+                keys, response_tx, ..
+            } => {
+                // Batch GET: process multiple keys in single message
+                let mut results = Vec::with_capacity(keys.len());
+                for key in keys {
+                    results.push(self.executor.get_direct(&key));
                 }
-                ShardMessage::PooledFastSet {
-                    key,
-                    value,
-                    response_slot,
-                } => {
-                    // Pooled fast SET: uses response slot instead of oneshot
-                    let key_str = unsafe { std::str::from_utf8_unchecked(&key) };
-                    let response = self.executor.set_direct(key_str, &value);
-                    response_slot.send(response);
+                let _ = response_tx.send(results);
+            }
+            ShardMessage::FastBatchSet {
+                pairs, response_tx, ..
+            } => {
+                // Batch SET: process multiple key-value pairs in single message
+                let mut results = Vec::with_capacity(pairs.len());
+                for (key, value) in pairs {
+                    results.push(self.executor.set_direct(&key, &value));
                 }
+                let _ = response_tx.send(results);
+            }
+            ShardMessage::PooledFastGet {
+                key, response_slot, ..
+            } => {
+                // Pooled fast GET: uses response slot instead of oneshot
+                let response = self.executor.get_direct(&key);
+                response_slot.send(response);
+            }
+            ShardMessage::PooledFastSet {
+                key,
+                value,
+                response_slot,
+                ..
+            } => {
+                // Pooled fast SET: uses response slot instead of oneshot
+                let response = self.executor.set_direct(&key, &value);
+                response_slot.send(response);
+            }
+        }
+        self.deliver_unblocked();
+        self.send_invalidations(caller);
+    }
+
+    /// CLIENT TRACKING: invalidate the keys written while handling a message
+    fn send_invalidations(&mut self, writer: Option<TrackingClientId>) {
+        if self.tracking.is_empty() {
+            return;
+        }
+        let written = self.executor.take_written_keys();
+        if written.as_ref().is_some_and(|keys| keys.is_empty()) {
+            return;
+        }
+        self.tracking.invalidate(written, writer);
+        // Clients whose connection went away were dropped
+        self.executor.set_track_writes(!self.tracking.is_empty());
+    }
+
+    /// Drop waiters whose client went away, so a later push is not handed
+    /// to a reply channel nobody reads (the element would be lost)
+    fn cancel_disconnected_clients(&mut self) {
+        if self.blocked_replies.is_empty() {
+            return;
+        }
+        let executor = &mut self.executor;
+        self.blocked_replies.retain(|&client_id, response_tx| {
+            if response_tx.is_closed() {
+                executor.cancel_blocked(client_id);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Send replies to clients the executor served or timed out
+    fn deliver_unblocked(&mut self) {
+        if self.blocked_replies.is_empty() {
+            return;
+        }
+        for (client_id, response) in self.executor.take_unblocked() {
+            if let Some(response_tx) = self.blocked_replies.remove(&client_id) {
+                let _ = response_tx.send(response);
             }
         }
+
+        // TigerStyle: Postconditions
+        debug_assert_eq!(
+            self.blocked_replies.len(),
+            self.executor.blocked_client_count(),
+            "Postcondition: every blocked client must have a reply channel"
+        );
     }
 }
 
+tokio::task_local! {
+    /// First send and last reply of the shard round trips made by the command
+    /// `time_shard_execution` is timing
+    static SHARD_SPAN: Cell<Option<(Instant, Instant)>>;
+}
+
+/// Run `fut`, returning how long it spent in shard actors: from the first
+/// command sent to a shard to the last reply, so a fan-out to several shards
+/// counts once. None if it never reached a shard.
+pub(crate) async fn time_shard_execution<F: Future>(fut: F) -> (F::Output, Option<Duration>) {
+    SHARD_SPAN
+        .scope(Cell::new(None), async move {
+            let output = fut.await;
+            let span = SHARD_SPAN.with(Cell::get);
+            (output, span.map(|(first, last)| last - first))
+        })
+        .await
+}
+
 #[derive(Clone)]
 pub struct ShardHandle {
     tx: mpsc::UnboundedSender<ShardMessage>,
     shard_id: usize,
+    /// Database the keyed messages sent through this handle run in
+    db: usize,
     /// Response pool for reducing channel allocation overhead
     response_pool: Arc<ResponsePool<RespValue>>,
 }
 
 impl ShardHandle {
+    /// The same shard, sending keyed messages to database `db`
+    fn in_db(&self, db: usize) -> ShardHandle {
+        ShardHandle { db, ..self.clone() }
+    }
+
     #[inline]
     async fn execute(&self, cmd: Command, virtual_time: VirtualTime) -> RespValue {
+        self.execute_as(cmd, virtual_time, None).await
+    }
+
+    /// Execute on behalf of `caller`, a tracking client
+    #[inline]
+    async fn execute_as(
+        &self,
+        cmd: Command,
+        virtual_time: VirtualTime,
+        caller: Option<TrackingClientId>,
+    ) -> RespValue {
         let (response_tx, response_rx) = oneshot::channel();
         let msg = ShardMessage::Command {
             cmd,
+            db: self.db,
             virtual_time,
+            caller,
             response_tx,
         };
 
+        let sent = SHARD_SPAN.try_with(|_| Instant::now()).ok();
         if self.tx.send(msg).is_err() {
             debug_assert!(false, "Shard {} channel closed unexpectedly", self.shard_id);
             return RespValue::Error("ERR shard unavailable".to_string());
         }
 
-        response_rx.await.unwrap_or_else(|_| {
+        let response = response_rx.await.unwrap_or_else(|_| {
             debug_assert!(false, "Shard {} response channel dropped", self.shard_id);
             RespValue::Error("ERR shard response failed".to_string())
-        })
+        });
+        if let Some(sent) = sent {
+            let replied = Instant::now();
+            SHARD_SPAN.with(|span| {
+                let (first, last) = span.get().unwrap_or((sent, replied));
+                span.set(Some((first.min(sent), last.max(replied))));
+            });
+        }
+        response
     }
 
     /// Fire-and-forget execution - no response channel allocation
     #[inline]
     #[allow(dead_code)]
     fn execute_fire_and_forget(&self, cmd: Command, virtual_time: VirtualTime) {
-        let msg = ShardMessage::BatchCommand { cmd, virtual_time };
+        let msg = ShardMessage::BatchCommand {
+            cmd,
+            db: self.db,
+            virtual_time,
+        };
         let _ = self.tx.send(msg);
     }
 
@@ -297,7 +651,11 @@ impl ShardHandle {
     #[inline]
     pub async fn fast_get(&self, key: bytes::Bytes) -> RespValue {
         let (response_tx, response_rx) = oneshot::channel();
-        let msg = ShardMessage::FastGet { key, response_tx };
+        let msg = ShardMessage::FastGet {
+            key,
+            db: self.db,
+            response_tx,
+        };
 
         if self.tx.send(msg).is_err() {
             return RespValue::Error("ERR shard unavailable".to_string());
@@ -314,6 +672,7 @@ impl ShardHandle {
         let (response_tx, response_rx) = oneshot::channel();
         let msg = ShardMessage::FastSet {
             key,
+            db: self.db,
             value,
             response_tx,
         };
@@ -333,6 +692,7 @@ impl ShardHandle {
         let response_slot = self.response_pool.acquire();
         let msg = ShardMessage::PooledFastGet {
             key,
+            db: self.db,
             response_slot: response_slot.clone(),
         };
 
@@ -352,6 +712,7 @@ impl ShardHandle {
         let response_slot = self.response_pool.acquire();
         let msg = ShardMessage::PooledFastSet {
             key,
+            db: self.db,
             value,
             response_slot: response_slot.clone(),
         };
@@ -373,7 +734,11 @@ impl ShardHandle {
             return Vec::new();
         }
         let (response_tx, response_rx) = oneshot::channel();
-        let msg = ShardMessage::FastBatchGet { keys, response_tx };
+        let msg = ShardMessage::FastBatchGet {
+            keys,
+            db: self.db,
+            response_tx,
+        };
 
         if self.tx.send(msg).is_err() {
             return vec![RespValue::Error("ERR shard unavailable".to_string())];
@@ -391,7 +756,11 @@ impl ShardHandle {
             return Vec::new();
         }
         let (response_tx, response_rx) = oneshot::channel();
-        let msg = ShardMessage::FastBatchSet { pairs, response_tx };
+        let msg = ShardMessage::FastBatchSet {
+            pairs,
+            db: self.db,
+            response_tx,
+        };
 
         if self.tx.send(msg).is_err() {
             return vec![RespValue::Error("ERR shard unavailable".to_string())];
@@ -402,6 +771,132 @@ impl ShardHandle {
             .unwrap_or_else(|_| vec![RespValue::Error("ERR shard response failed".to_string())])
     }
 
+    /// Park a blocking command on this shard; the receiver resolves when it is served
+    fn execute_blocking(
+        &self,
+        client_id: BlockedClientId,
+        cmd: Command,
+        virtual_time: VirtualTime,
+    ) -> Option<oneshot::Receiver<RespValue>> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let msg = ShardMessage::BlockingCommand {
+            client_id,
+            cmd,
+            db: self.db,
+            virtual_time,
+            response_tx,
+        };
+        self.tx.send(msg).ok()?;
+        Some(response_rx)
+    }
+
+    fn expire_blocked(&self, virtual_time: VirtualTime) {
+        let _ = self.tx.send(ShardMessage::ExpireBlocked { virtual_time });
+    }
+
+    async fn key_versions(&self, keys: Vec<SDS>) -> Option<Vec<u64>> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let msg = ShardMessage::KeyVersions {
+            keys,
+            db: self.db,
+            response_tx,
+        };
+        self.tx.send(msg).ok()?;
+        response_rx.await.ok()
+    }
+
+    async fn export_key(
+        &self,
+        key: &[u8],
+        remove: bool,
+        virtual_time: VirtualTime,
+    ) -> Option<(Value, Option<VirtualTime>)> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let msg = ShardMessage::ExportKey {
+            key: SDS::from(key),
+            db: self.db,
+            remove,
+            virtual_time,
+            response_tx,
+        };
+        self.tx.send(msg).ok()?;
+        response_rx.await.ok()?
+    }
+
+    async fn import_key(
+        &self,
+        key: &[u8],
+        (value, deadline): (Value, Option<VirtualTime>),
+        replace: bool,
+        virtual_time: VirtualTime,
+    ) -> bool {
+        let (response_tx, response_rx) = oneshot::channel();
+        let msg = ShardMessage::ImportKey {
+            key: SDS::from(key),
+            db: self.db,
+            value,
+            deadline,
+            replace,
+            virtual_time,
+            response_tx,
+        };
+        if self.tx.send(msg).is_err() {
+            return false;
+        }
+        response_rx.await.unwrap_or(false)
+    }
+
+    /// Take this shard exclusively. Until the returned handle is dropped the
+    /// shard only serves messages sent through it.
+    async fn lock(&self) -> Option<ShardHandle> {
+        let (session_tx, session_rx) = mpsc::unbounded_channel();
+        let (granted_tx, granted_rx) = oneshot::channel();
+        let msg = ShardMessage::Exclusive {
+            session_rx,
+            granted_tx,
+        };
+        self.tx.send(msg).ok()?;
+        granted_rx.await.ok()?;
+        Some(ShardHandle {
+            tx: session_tx,
+            shard_id: self.shard_id,
+            db: self.db,
+            response_pool: self.response_pool.clone(),
+        })
+    }
+
+    fn set_memory_config(&self, config: MemoryConfig) {
+        let _ = self.tx.send(ShardMessage::SetMemoryConfig { config });
+    }
+
+    fn send(&self, msg: ShardMessage) {
+        let _ = self.tx.send(msg);
+    }
+
+    async fn memory_stats(&self) -> MemoryStats {
+        let (response_tx, response_rx) = oneshot::channel();
+        if self
+            .tx
+            .send(ShardMessage::MemoryStats { response_tx })
+            .is_err()
+        {
+            return MemoryStats::default();
+        }
+        response_rx.await.unwrap_or_default()
+    }
+
+    async fn keyspace_stats(&self) -> Vec<KeyspaceStats> {
+        let (response_tx, response_rx) = oneshot::channel();
+        if self
+            .tx
+            .send(ShardMessage::KeyspaceStats { response_tx })
+            .is_err()
+        {
+            return Vec::new();
+        }
+        response_rx.await.unwrap_or_default()
+    }
+
     #[inline]
     async fn evict_expired(&self, virtual_time: VirtualTime) -> usize {
         let (response_tx, response_rx) = oneshot::channel();
@@ -418,22 +913,28 @@ impl ShardHandle {
     }
 }
 
-/// Hash key string to shard index
-/// P4 optimization: Use AHash when opt-fxhash-routing is enabled
+/// Part of `key` that decides its shard (Redis Cluster hash tags)
+///
+/// If the key contains `{...}` with a non-empty body, only the body between
+/// the first `{` and the first `}` after it is hashed, so `{user:1}:name` and
+/// `{user:1}:email` always land on the same shard. Otherwise the whole key is used.
 #[inline]
-fn hash_key(key: &str, num_shards: usize) -> usize {
-    debug_assert!(num_shards > 0, "num_shards must be positive");
-
-    #[cfg(feature = "opt-fxhash-routing")]
-    let mut hasher = AHasher::default();
-
-    #[cfg(not(feature = "opt-fxhash-routing"))]
-    let mut hasher = DefaultHasher::new();
+pub(crate) fn hash_tag(key: &[u8]) -> &[u8] {
+    if let Some(open) = key.iter().position(|&b| b == b'{') {
+        let rest = &key[open + 1..];
+        if let Some(close) = rest.iter().position(|&b| b == b'}') {
+            if close > 0 {
+                return &rest[..close];
+            }
+        }
+    }
+    key
+}
 
-    key.hash(&mut hasher);
-    let idx = (hasher.finish() as usize) % num_shards;
-    debug_assert!(idx < num_shards, "Hash produced invalid shard index");
-    idx
+/// Hash a key to its shard index
+#[inline]
+fn hash_key(key: &[u8], num_shards: usize) -> usize {
+    hash_key_bytes(key, num_shards)
 }
 
 /// Fast path: hash key bytes directly without UTF-8 validation overhead
@@ -448,12 +949,19 @@ fn hash_key_bytes(key: &[u8], num_shards: usize) -> usize {
     #[cfg(not(feature = "opt-fxhash-routing"))]
     let mut hasher = DefaultHasher::new();
 
-    key.hash(&mut hasher);
+    hash_tag(key).hash(&mut hasher);
     let idx = (hasher.finish() as usize) % num_shards;
     debug_assert!(idx < num_shards, "Hash produced invalid shard index");
     idx
 }
 
+/// Error for a multi-key command whose keys live on different shards
+pub(crate) const CROSSSLOT_ERROR: &str = "CROSSSLOT Keys in request don't hash to the same slot";
+
+/// How often a blocked client pokes its shard so timeouts fire. The shard only
+/// sees time advance through messages; 100ms matches Redis' timeout resolution.
+const BLOCKED_CLIENT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
 /// Pool configuration defaults (used when no PerformanceConfig provided)
 const DEFAULT_RESPONSE_POOL_CAPACITY: usize = 256;
 const DEFAULT_RESPONSE_POOL_PREWARM: usize = 64;
@@ -479,6 +987,16 @@ pub struct ShardedActorState<T: TimeSource = ProductionTimeSource> {
     /// Shared script cache for Lua scripts (allows SCRIPT LOAD to work across all shards)
     #[allow(dead_code)]
     shared_script_cache: crate::redis::lua::SharedScriptCache,
+    /// Ids for clients parked on blocking commands (unique across shards)
+    next_blocked_client_id: Arc<AtomicU64>,
+    /// RANDOMKEY calls so far, rotating the shard asked first
+    random_key_calls: Arc<AtomicU64>,
+    /// Slot ownership when running in Redis Cluster mode
+    cluster: Option<Arc<RwLock<ClusterState>>>,
+    /// SLOWLOG entries, shared by every connection
+    slowlog: Arc<SlowLog>,
+    /// LATENCY samples from this server's background loops
+    latency: Arc<LatencyMonitor>,
 }
 
 /// Production-specific constructors (use ProductionTimeSource)
@@ -503,7 +1021,10 @@ impl ShardedActorState<ProductionTimeSource> {
     /// This constructor allows tuning all performance-critical parameters
     /// via the external configuration file.
     pub fn with_perf_config(perf_config: &PerformanceConfig) -> Self {
-        let shard_config = ShardConfig::with_shards(perf_config.num_shards);
+        let shard_config = ShardConfig {
+            databases: perf_config.databases,
+            ..ShardConfig::with_shards(perf_config.num_shards)
+        };
         Self::with_perf_config_and_time_source(
             perf_config,
             shard_config,
@@ -545,12 +1066,14 @@ impl<T: TimeSource> ShardedActorState<T> {
                     epoch,
                     shard_id,
                     num_shards,
+                    config.databases,
                     shared_script_cache.clone(),
                 );
                 tokio::spawn(actor.run());
                 ShardHandle {
                     tx,
                     shard_id,
+                    db: 0,
                     response_pool: response_pool.clone(),
                 }
             })
@@ -582,6 +1105,11 @@ impl<T: TimeSource> ShardedActorState<T> {
             adaptive_handle,
             response_pool,
             shared_script_cache,
+            next_blocked_client_id: Arc::new(AtomicU64::new(0)),
+            random_key_calls: Arc::new(AtomicU64::new(0)),
+            cluster: None,
+            slowlog: Arc::new(SlowLog::default()),
+            latency: Arc::new(LatencyMonitor::default()),
         }
     }
 
@@ -621,12 +1149,14 @@ impl<T: TimeSource> ShardedActorState<T> {
                     epoch,
                     shard_id,
                     num_shards,
+                    shard_config.databases,
                     shared_script_cache.clone(),
                 );
                 tokio::spawn(actor.run());
                 ShardHandle {
                     tx,
                     shard_id,
+                    db: 0,
                     response_pool: response_pool.clone(),
                 }
             })
@@ -649,7 +1179,7 @@ impl<T: TimeSource> ShardedActorState<T> {
             None
         };
 
-        ShardedActorState {
+        let state = ShardedActorState {
             shards: Arc::new(shards),
             num_shards,
             start_millis,
@@ -658,7 +1188,104 @@ impl<T: TimeSource> ShardedActorState<T> {
             adaptive_handle,
             response_pool,
             shared_script_cache,
+            next_blocked_client_id: Arc::new(AtomicU64::new(0)),
+            random_key_calls: Arc::new(AtomicU64::new(0)),
+            cluster: None,
+            slowlog: Arc::new(SlowLog::new(&perf_config.slowlog)),
+            latency: Arc::new(LatencyMonitor::new(&perf_config.latency)),
+        };
+        state.set_memory_config(perf_config.memory.memory_config());
+        state
+    }
+
+    /// Apply server-wide maxmemory settings; each shard gets an equal share of
+    /// the limit and evicts on its own
+    pub fn set_memory_config(&self, config: MemoryConfig) {
+        let per_shard = config.per_shard(self.num_shards);
+        for shard in self.shards.iter() {
+            shard.set_memory_config(per_shard);
+        }
+    }
+
+    /// CONFIG RESETSTAT: zero the counters INFO reports
+    pub fn reset_stats(&self) {
+        for shard in self.shards.iter() {
+            shard.send(ShardMessage::ResetStats);
+        }
+    }
+
+    /// Memory accounting and eviction counters summed over all shards
+    pub async fn memory_stats(&self) -> MemoryStats {
+        Self::memory_stats_of(&self.shards).await
+    }
+
+    async fn memory_stats_of(shards: &[ShardHandle]) -> MemoryStats {
+        let mut total = MemoryStats::default();
+        for shard in shards.iter() {
+            total.merge(&shard.memory_stats().await);
+        }
+        total
+    }
+
+    /// Key counts of every database summed over `shards`
+    async fn keyspace_stats_of(shards: &[ShardHandle]) -> Vec<KeyspaceStats> {
+        let mut total: Vec<KeyspaceStats> = Vec::new();
+        for shard in shards.iter() {
+            let stats = shard.keyspace_stats().await;
+            total.resize(total.len().max(stats.len()), KeyspaceStats::default());
+            for (db, stats) in stats.iter().enumerate() {
+                total[db].merge(stats);
+            }
         }
+        total
+    }
+
+    /// Number of logical databases (`databases` setting)
+    pub fn databases(&self) -> usize {
+        self.config.databases
+    }
+
+    /// Database this state's commands run in
+    pub fn selected_db(&self) -> usize {
+        self.shards[0].db
+    }
+
+    /// The same server, with commands running in database `db`. Connections
+    /// keep one per SELECT; the clone shares every shard and counter.
+    pub fn with_db(&self, db: usize) -> Self {
+        debug_assert!(
+            db < self.config.databases,
+            "Precondition: DB index {} out of range",
+            db
+        );
+        ShardedActorState {
+            shards: Arc::new(self.shards.iter().map(|shard| shard.in_db(db)).collect()),
+            ..self.clone()
+        }
+    }
+
+    /// Enable Redis Cluster mode with the given slot map
+    pub fn with_cluster(mut self, cluster: ClusterState) -> Self {
+        self.cluster = Some(Arc::new(RwLock::new(cluster)));
+        self
+    }
+
+    /// The server's SLOWLOG
+    #[inline]
+    pub fn slowlog(&self) -> &Arc<SlowLog> {
+        &self.slowlog
+    }
+
+    /// The server's LATENCY monitor
+    #[inline]
+    pub fn latency(&self) -> &Arc<LatencyMonitor> {
+        &self.latency
+    }
+
+    /// Slot map, when running in Redis Cluster mode
+    #[inline]
+    pub fn cluster(&self) -> Option<&Arc<RwLock<ClusterState>>> {
+        self.cluster.as_ref()
     }
 
     /// Get current number of shards
@@ -843,7 +1470,7 @@ impl<T: TimeSource> ShardedActorState<T> {
     /// results in original order. This reduces channel round-trips from N to num_shards.
     ///
     /// Returns Vec<RespValue> in the same order as input keys.
-    pub async fn fast_batch_get_pipeline(&self, keys: Vec<bytes::Bytes>) -> Vec<RespValue> {
+    pub async fn fast_batch_get_pipeline(&self, keys: &[bytes::Bytes]) -> Vec<RespValue> {
         if keys.is_empty() {
             return Vec::new();
         }
@@ -877,7 +1504,7 @@ impl<T: TimeSource> ShardedActorState<T> {
 
         // Reconstruct results in original order
         for (indices, shard_results) in all_results {
-            for (i, resp) in indices.into_iter().zip(shard_results.into_iter()) {
+            for (i, resp) in indices.into_iter().zip(shard_results) {
                 results[i] = resp;
             }
         }
@@ -894,7 +1521,7 @@ impl<T: TimeSource> ShardedActorState<T> {
     /// Returns Vec<RespValue> in the same order as input pairs.
     pub async fn fast_batch_set_pipeline(
         &self,
-        pairs: Vec<(bytes::Bytes, bytes::Bytes)>,
+        pairs: &[(bytes::Bytes, bytes::Bytes)],
     ) -> Vec<RespValue> {
         if pairs.is_empty() {
             return Vec::new();
@@ -933,7 +1560,7 @@ impl<T: TimeSource> ShardedActorState<T> {
 
         // Reconstruct results in original order
         for (indices, shard_results) in all_results {
-            for (i, resp) in indices.into_iter().zip(shard_results.into_iter()) {
+            for (i, resp) in indices.into_iter().zip(shard_results) {
                 results[i] = resp;
             }
         }
@@ -942,13 +1569,115 @@ impl<T: TimeSource> ShardedActorState<T> {
     }
 
     pub async fn execute(&self, cmd: &Command) -> RespValue {
+        if self.needs_shard_locks(cmd) {
+            return self.execute_locked(cmd).await;
+        }
+        let virtual_time = self.get_current_virtual_time();
+        self.route(&self.shards, cmd, virtual_time, None).await
+    }
+
+    /// A cross-shard MSETNX must see every key missing before it writes any,
+    /// a cross-shard RENAME, COPY or SMOVE must not expose the key (member) on
+    /// neither or both shards, and SUNIONSTORE and friends must not store a
+    /// result from inputs changing under them, so they run with all their
+    /// shards locked, like a one-command EXEC
+    fn needs_shard_locks(&self, cmd: &Command) -> bool {
+        matches!(
+            cmd,
+            Command::MSetNx(_)
+                | Command::Rename(_, _)
+                | Command::RenameNx(_, _)
+                | Command::Copy { .. }
+                | Command::SMove(_, _, _)
+                | Command::SetOp { dest: Some(_), .. }
+        ) && self.spans_shards(cmd)
+    }
+
+    async fn execute_locked(&self, cmd: &Command) -> RespValue {
+        match self
+            .execute_transaction(&[], std::slice::from_ref(cmd))
+            .await
+        {
+            RespValue::Array(Some(mut replies)) if replies.len() == 1 => {
+                replies.pop().unwrap_or(RespValue::BulkString(None))
+            }
+            other => other,
+        }
+    }
+
+    /// CLIENT TRACKING ON: register `client` on every shard
+    pub fn enable_tracking(&self, client: TrackingClientId, tracked: TrackedClient) {
+        for shard in self.shards.iter() {
+            shard.send(ShardMessage::EnableTracking {
+                client,
+                tracked: tracked.clone(),
+            });
+        }
+    }
+
+    /// CLIENT TRACKING OFF, or the tracking connection closed
+    pub fn disable_tracking(&self, client: TrackingClientId) {
+        for shard in self.shards.iter() {
+            shard.send(ShardMessage::DisableTracking { client });
+        }
+    }
+
+    /// Execute for a tracking client. With `track_reads`, the keys of a read
+    /// command are registered on their shards first; the registration is
+    /// queued ahead of any later write, so no invalidation can be missed.
+    pub async fn execute_tracked(
+        &self,
+        cmd: &Command,
+        client: TrackingClientId,
+        track_reads: bool,
+    ) -> RespValue {
+        if track_reads && cmd.is_read_only() && !matches!(cmd, Command::Keys(_)) {
+            let mut by_shard: BTreeMap<usize, Vec<SDS>> = BTreeMap::new();
+            for key in cmd.get_keys() {
+                by_shard
+                    .entry(hash_key(&key, self.num_shards))
+                    .or_default()
+                    .push(key);
+            }
+            for (shard_idx, keys) in by_shard {
+                self.shards[shard_idx].send(ShardMessage::TrackReads { client, keys });
+            }
+        }
+        if self.needs_shard_locks(cmd) {
+            return self.execute_locked(cmd).await;
+        }
         let virtual_time = self.get_current_virtual_time();
+        self.route(&self.shards, cmd, virtual_time, Some(client))
+            .await
+    }
 
+    /// Route a command to `shards`: the regular shard handles, or the locked
+    /// sessions of a transaction. `caller` is the tracking client issuing it;
+    /// it only reaches single-shard commands, so NOLOOP does not cover fan-outs.
+    async fn route(
+        &self,
+        shards: &[ShardHandle],
+        cmd: &Command,
+        virtual_time: VirtualTime,
+        caller: Option<TrackingClientId>,
+    ) -> RespValue {
         match cmd {
             Command::Ping => RespValue::SimpleString("PONG".to_string()),
 
+            // Transactions are per connection and run through `execute_transaction`
+            Command::Multi
+            | Command::Exec
+            | Command::Discard
+            | Command::Watch(_)
+            | Command::Unwatch => RespValue::Error(format!(
+                "ERR {} is only supported on client connections",
+                cmd.name()
+            )),
+
             Command::Info => {
                 let adaptive_info = self.get_adaptive_info().await;
+                // `shards`, not `self.shards`: inside EXEC the shards are locked
+                let memory = Self::memory_stats_of(shards).await;
                 let info = format!(
                     "# Server\r\n\
                      redis_mode:tiger_style\r\n\
@@ -956,21 +1685,59 @@ impl<T: TimeSource> ShardedActorState<T> {
                      architecture:actor_message_passing\r\n\
                      allocator:jemalloc\r\n\
                      \r\n\
+                     # Memory\r\n\
+                     used_memory:{}\r\n\
+                     maxmemory:{}\r\n\
+                     maxmemory_policy:{}\r\n\
+                     \r\n\
                      # Stats\r\n\
+                     expired_keys:{}\r\n\
+                     expired_time_cap_reached_count:{}\r\n\
+                     evicted_keys:{}\r\n\
+                     oom_rejected_commands:{}\r\n\
                      current_time_ms:{}\r\n\
                      \r\n\
+                     {}\
+                     \r\n\
                      {}",
                     self.num_shards,
+                    memory.used_memory,
+                    memory.maxmemory,
+                    memory.policy.name(),
+                    memory.expired_keys,
+                    memory.expired_time_cap_reached_count,
+                    memory.evicted_keys,
+                    memory.oom_rejections,
                     virtual_time.as_millis(),
+                    keyspace_info(&Self::keyspace_stats_of(shards).await),
                     adaptive_info
                 );
                 RespValue::BulkString(Some(info.into_bytes()))
             }
 
-            Command::FlushDb | Command::FlushAll => {
+            Command::SlowlogGet(count) => self.slowlog.get_reply(*count),
+            Command::SlowlogLen => RespValue::Integer(self.slowlog.len() as i64),
+            Command::SlowlogReset => {
+                self.slowlog.reset();
+                RespValue::SimpleString("OK".to_string())
+            }
+            Command::LatencyLatest => self.latency.latest_reply(),
+            Command::LatencyHistory(event) => self.latency.history_reply(event),
+            Command::LatencyReset(events) => RespValue::Integer(self.latency.reset(events) as i64),
+
+            // The selected database is kept by the connection, see `with_db`
+            Command::Select(_) => {
+                RespValue::Error("ERR SELECT is only supported on client connections".to_string())
+            }
+
+            Command::SwapDb(a, b) if (*a).max(*b) >= self.config.databases => {
+                RespValue::Error("ERR DB index is out of range".to_string())
+            }
+
+            Command::FlushDb | Command::FlushAll | Command::SwapDb(_, _) => {
                 let mut futures = Vec::with_capacity(self.num_shards);
-                for shard in self.shards.iter() {
-                    futures.push(shard.execute(Command::FlushDb, virtual_time));
+                for shard in shards.iter() {
+                    futures.push(shard.execute(cmd.clone(), virtual_time));
                 }
                 for future in futures {
                     let _ = future.await;
@@ -980,7 +1747,7 @@ impl<T: TimeSource> ShardedActorState<T> {
 
             Command::Keys(pattern) => {
                 let mut futures = Vec::with_capacity(self.num_shards);
-                for shard in self.shards.iter() {
+                for shard in shards.iter() {
                     futures.push(shard.execute(Command::Keys(pattern.clone()), virtual_time));
                 }
 
@@ -993,12 +1760,27 @@ impl<T: TimeSource> ShardedActorState<T> {
                 RespValue::Array(Some(all_keys))
             }
 
-            Command::MGet(keys) => {
+            Command::DbSize => {
+                let mut futures = Vec::with_capacity(self.num_shards);
+                for shard in shards.iter() {
+                    futures.push(shard.execute(Command::DbSize, virtual_time));
+                }
+
+                let mut total = 0;
+                for future in futures {
+                    if let RespValue::Integer(n) = future.await {
+                        total += n;
+                    }
+                }
+                RespValue::Integer(total)
+            }
+
+            Command::MGet(keys) => {
                 // Optimization: Group keys by shard to reduce actor messages (Abseil Tip #5)
                 let num_shards = self.num_shards;
 
                 // Build shard batches: (shard_idx, original_indices, keys)
-                let mut shard_batches: std::collections::HashMap<usize, (Vec<usize>, Vec<String>)> =
+                let mut shard_batches: std::collections::HashMap<usize, (Vec<usize>, Vec<SDS>)> =
                     std::collections::HashMap::new();
                 for (original_idx, key) in keys.iter().enumerate() {
                     let shard_idx = hash_key(key, num_shards);
@@ -1013,7 +1795,7 @@ impl<T: TimeSource> ShardedActorState<T> {
                 let futures: Vec<_> = shard_batches
                     .iter()
                     .map(|(&shard_idx, (_, batch_keys))| {
-                        self.shards[shard_idx]
+                        shards[shard_idx]
                             .execute(Command::BatchGet(batch_keys.clone()), virtual_time)
                     })
                     .collect();
@@ -1038,10 +1820,8 @@ impl<T: TimeSource> ShardedActorState<T> {
             Command::MSet(pairs) => {
                 // Group key-value pairs by target shard (using HashMap for dynamic shard count)
                 let num_shards = self.num_shards;
-                let mut shard_batches: std::collections::HashMap<
-                    usize,
-                    Vec<(String, crate::redis::SDS)>,
-                > = std::collections::HashMap::new();
+                let mut shard_batches: std::collections::HashMap<usize, Vec<(SDS, SDS)>> =
+                    std::collections::HashMap::new();
                 for (key, value) in pairs {
                     let shard_idx = hash_key(key, num_shards);
                     shard_batches
@@ -1058,7 +1838,7 @@ impl<T: TimeSource> ShardedActorState<T> {
                 if shard_batches.len() == 1 {
                     // TigerStyle: Use explicit pattern match instead of unwrap
                     if let Some((shard_idx, batch)) = shard_batches.into_iter().next() {
-                        self.shards[shard_idx]
+                        shards[shard_idx]
                             .execute(Command::BatchSet(batch), virtual_time)
                             .await;
                     } else {
@@ -1072,7 +1852,7 @@ impl<T: TimeSource> ShardedActorState<T> {
                     let futures: Vec<_> = shard_batches
                         .into_iter()
                         .map(|(shard_idx, batch)| {
-                            self.shards[shard_idx].execute(Command::BatchSet(batch), virtual_time)
+                            shards[shard_idx].execute(Command::BatchSet(batch), virtual_time)
                         })
                         .collect();
                     futures::future::join_all(futures).await;
@@ -1087,7 +1867,7 @@ impl<T: TimeSource> ShardedActorState<T> {
                     .iter()
                     .map(|key| {
                         let shard_idx = hash_key(key, num_shards);
-                        self.shards[shard_idx]
+                        shards[shard_idx]
                             .execute(Command::Exists(vec![key.clone()]), virtual_time)
                     })
                     .collect();
@@ -1107,19 +1887,550 @@ impl<T: TimeSource> ShardedActorState<T> {
                 RespValue::Integer(count)
             }
 
+            Command::Del(keys) | Command::Unlink(keys) | Command::Touch(keys) if keys.len() > 1 => {
+                // Each key is handled on its own shard; the reply is the total count
+                let num_shards = self.num_shards;
+                let single = |key: &SDS| match cmd {
+                    Command::Unlink(_) => Command::Unlink(vec![key.clone()]),
+                    Command::Touch(_) => Command::Touch(vec![key.clone()]),
+                    _ => Command::Del(vec![key.clone()]),
+                };
+                let futures: Vec<_> = keys
+                    .iter()
+                    .map(|key| {
+                        let shard_idx = hash_key(key, num_shards);
+                        shards[shard_idx].execute(single(key), virtual_time)
+                    })
+                    .collect();
+                let mut count = 0i64;
+                for result in futures::future::join_all(futures).await {
+                    match result {
+                        RespValue::Integer(n) => count += n,
+                        other => return other,
+                    }
+                }
+                RespValue::Integer(count)
+            }
+
+            // Blocking reads park on the shard owning their streams, like BLPOP
+            Command::XRead {
+                block_ms: Some(_), ..
+            }
+            | Command::XReadGroup {
+                block_ms: Some(_), ..
+            } => self.execute_blocking(cmd, virtual_time).await,
+
+            // Multi-stream reads: each stream (and its consumer groups) lives on the
+            // shard owning its key, so fan out one single-stream read per key and
+            // concatenate the per-stream replies in argument order.
+            Command::XRead { streams, .. } | Command::XReadGroup { streams, .. }
+                if streams.len() > 1 =>
+            {
+                let num_shards = self.num_shards;
+                let futures: Vec<_> = streams
+                    .iter()
+                    .map(|stream| {
+                        let single = match cmd {
+                            Command::XRead { count, .. } => Command::XRead {
+                                count: *count,
+                                block_ms: None,
+                                streams: vec![stream.clone()],
+                            },
+                            Command::XReadGroup {
+                                group,
+                                consumer,
+                                count,
+                                noack,
+                                ..
+                            } => Command::XReadGroup {
+                                group: group.clone(),
+                                consumer: consumer.clone(),
+                                count: *count,
+                                block_ms: None,
+                                noack: *noack,
+                                streams: vec![stream.clone()],
+                            },
+                            _ => unreachable!("outer match only admits XREAD/XREADGROUP"),
+                        };
+                        let shard_idx = hash_key(&stream.0, num_shards);
+                        shards[shard_idx].execute(single, virtual_time)
+                    })
+                    .collect();
+
+                let mut merged = Vec::new();
+                for result in futures::future::join_all(futures).await {
+                    match result {
+                        RespValue::Array(Some(per_stream)) => merged.extend(per_stream),
+                        RespValue::Array(None) => {}
+                        other => return other,
+                    }
+                }
+                if merged.is_empty() {
+                    RespValue::Array(None)
+                } else {
+                    RespValue::Array(Some(merged))
+                }
+            }
+
+            // Set algebra over keys on several shards gathers each input with
+            // SMEMBERS and combines them here. Inside EXEC every involved shard
+            // is locked, so the gather and the store are atomic there too.
+            Command::SetOp { .. } | Command::SInterCard { .. } if self.spans_shards(cmd) => {
+                self.set_op_across_shards(shards, cmd, virtual_time).await
+            }
+            Command::SMove(source, dest, member) if self.spans_shards(cmd) => {
+                self.smove_across_shards(shards, source, dest, member, virtual_time)
+                    .await
+            }
+            // Only reached with the involved shards locked, see `execute_locked`
+            Command::MSetNx(pairs) if self.spans_shards(cmd) => {
+                self.msetnx_across_shards(shards, pairs, virtual_time).await
+            }
+            Command::Rename(_, _) | Command::RenameNx(_, _) | Command::Copy { .. }
+                if self.spans_shards(cmd) =>
+            {
+                self.move_key_across_shards(shards, cmd, virtual_time).await
+            }
+
+            Command::RandomKey => {
+                // Start from a different shard each call; the shard picks at random
+                let start = self.random_key_calls.fetch_add(1, Ordering::Relaxed) as usize;
+                for i in 0..shards.len() {
+                    let shard = &shards[(start + i) % shards.len()];
+                    match shard.execute(Command::RandomKey, virtual_time).await {
+                        RespValue::BulkString(None) => {}
+                        reply => return reply,
+                    }
+                }
+                RespValue::BulkString(None)
+            }
+
+            Command::BLPop { .. }
+            | Command::BRPop { .. }
+            | Command::BLMove { .. }
+            | Command::BZPopMin { .. }
+            | Command::BZPopMax { .. } => self.execute_blocking(cmd, virtual_time).await,
+
             _ => {
+                if let Some(error) = self.cross_shard_error(cmd) {
+                    return error;
+                }
                 if let Some(key) = cmd.get_primary_key() {
                     let shard_idx = hash_key(key, self.num_shards);
                     debug_assert!(shard_idx < self.num_shards, "Invalid shard index for key");
-                    self.shards[shard_idx]
-                        .execute(cmd.clone(), virtual_time)
+                    shards[shard_idx]
+                        .execute_as(cmd.clone(), virtual_time, caller)
                         .await
                 } else {
-                    self.shards[0].execute(cmd.clone(), virtual_time).await
+                    shards[0]
+                        .execute_as(cmd.clone(), virtual_time, caller)
+                        .await
                 }
             }
         }
     }
+
+    /// CROSSSLOT error if `cmd` is routed to a single shard but its keys live
+    /// on several. MGET, MSET, MSETNX, DEL, UNLINK, EXISTS, TOUCH, XREAD,
+    /// RENAME, COPY and the set commands fan out per key and never fail;
+    /// everything else (RPOPLPUSH, LMOVE, BLPOP, XREAD BLOCK, EVAL, ...) needs all its keys
+    /// on one shard, which `{hashtag}` keys guarantee.
+    pub fn cross_shard_error(&self, cmd: &Command) -> Option<RespValue> {
+        if matches!(
+            cmd,
+            Command::MGet(_)
+                | Command::MSet(_)
+                | Command::Del(_)
+                | Command::Exists(_)
+                | Command::XRead { .. }
+                | Command::XReadGroup { .. }
+                | Command::Keys(_)
+                | Command::SetOp { .. }
+                | Command::SInterCard { .. }
+                | Command::SMove(_, _, _)
+                | Command::MSetNx(_)
+                | Command::Unlink(_)
+                | Command::Touch(_)
+                | Command::Rename(_, _)
+                | Command::RenameNx(_, _)
+                | Command::Copy { .. }
+        ) {
+            return None;
+        }
+
+        self.spans_shards(cmd)
+            .then(|| RespValue::Error(CROSSSLOT_ERROR.to_string()))
+    }
+
+    /// True if the keys of `cmd` live on more than one shard
+    fn spans_shards(&self, cmd: &Command) -> bool {
+        // Borrowed keys: single-key commands on the hot path allocate nothing
+        let mut first = None;
+        let mut spans = false;
+        cmd.for_each_key(|key| {
+            let shard_idx = hash_key(key, self.num_shards);
+            spans |= *first.get_or_insert(shard_idx) != shard_idx;
+        });
+        spans
+    }
+
+    /// Cross-shard SUNION/SINTER/SDIFF, their STORE forms and SINTERCARD:
+    /// scatter SMEMBERS to the shard of every input, combine the gathered sets
+    /// here, and write a STORE result to the destination's shard with SETSTORE
+    /// (the STORE forms hold all their shards locked)
+    async fn set_op_across_shards(
+        &self,
+        shards: &[ShardHandle],
+        cmd: &Command,
+        virtual_time: VirtualTime,
+    ) -> RespValue {
+        let (op, dest, keys, limit) = match cmd {
+            Command::SetOp { op, dest, keys } => (Some(*op), dest.as_ref(), keys, 0),
+            Command::SInterCard { keys, limit } => (None, None, keys, *limit),
+            _ => unreachable!("outer match only admits set algebra"),
+        };
+
+        let num_shards = self.num_shards;
+        let futures: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                let shard_idx = hash_key(key, num_shards);
+                shards[shard_idx].execute(Command::SMembers(key.clone()), virtual_time)
+            })
+            .collect();
+        let mut inputs = Vec::with_capacity(keys.len());
+        for result in futures::future::join_all(futures).await {
+            match result {
+                RespValue::Array(Some(members)) => {
+                    let mut set = RedisSet::new();
+                    for member in members {
+                        if let RespValue::BulkString(Some(bytes)) = member {
+                            set.add(SDS::new(bytes));
+                        }
+                    }
+                    inputs.push(set);
+                }
+                // WRONGTYPE from any input fails the whole command
+                other => return other,
+            }
+        }
+        debug_assert_eq!(inputs.len(), keys.len(), "Gathered one set per input key");
+
+        let inputs: Vec<&RedisSet> = inputs.iter().collect();
+        let result = match op {
+            Some(SetOperation::Union) => RedisSet::union(&inputs),
+            Some(SetOperation::Inter) => RedisSet::intersection(&inputs),
+            Some(SetOperation::Diff) => RedisSet::difference(&inputs),
+            None => return RespValue::Integer(RedisSet::intersection_card(&inputs, limit) as i64),
+        };
+        let members = result.members();
+        match dest {
+            Some(dest) => {
+                let shard_idx = hash_key(dest, num_shards);
+                shards[shard_idx]
+                    .execute(Command::SetStore(dest.clone(), members), virtual_time)
+                    .await
+            }
+            None => RespValue::Array(Some(
+                members
+                    .iter()
+                    .map(|m| RespValue::BulkString(Some(m.as_bytes().to_vec())))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Cross-shard SMOVE: type-check both keys, then SREM on the source's shard
+    /// and SADD on the destination's. The caller holds both shards locked.
+    async fn smove_across_shards(
+        &self,
+        shards: &[ShardHandle],
+        source: &[u8],
+        dest: &[u8],
+        member: &SDS,
+        virtual_time: VirtualTime,
+    ) -> RespValue {
+        let source_shard = &shards[hash_key(source, self.num_shards)];
+        let dest_shard = &shards[hash_key(dest, self.num_shards)];
+
+        match source_shard
+            .execute(Command::SCard(SDS::from(source)), virtual_time)
+            .await
+        {
+            RespValue::Integer(0) => return RespValue::Integer(0),
+            RespValue::Integer(_) => {}
+            other => return other,
+        }
+        if let error @ RespValue::Error(_) = dest_shard
+            .execute(Command::SCard(SDS::from(dest)), virtual_time)
+            .await
+        {
+            return error;
+        }
+        match source_shard
+            .execute(
+                Command::SRem(SDS::from(source), vec![member.clone()]),
+                virtual_time,
+            )
+            .await
+        {
+            RespValue::Integer(1) => {}
+            other => return other,
+        }
+        dest_shard
+            .execute(
+                Command::SAdd(SDS::from(dest), vec![member.clone()]),
+                virtual_time,
+            )
+            .await;
+        RespValue::Integer(1)
+    }
+
+    /// Cross-shard MSETNX: check every shard for existing keys, then send each
+    /// shard its share. The caller holds all the involved shards locked.
+    async fn msetnx_across_shards(
+        &self,
+        shards: &[ShardHandle],
+        pairs: &[(SDS, SDS)],
+        virtual_time: VirtualTime,
+    ) -> RespValue {
+        let mut batches: BTreeMap<usize, Vec<(SDS, SDS)>> = BTreeMap::new();
+        for (key, value) in pairs {
+            batches
+                .entry(hash_key(key, self.num_shards))
+                .or_default()
+                .push((key.clone(), value.clone()));
+        }
+
+        for (&shard_idx, batch) in &batches {
+            let keys = batch.iter().map(|(key, _)| key.clone()).collect();
+            match shards[shard_idx]
+                .execute(Command::Exists(keys), virtual_time)
+                .await
+            {
+                RespValue::Integer(0) => {}
+                RespValue::Integer(_) => return RespValue::Integer(0),
+                other => return other,
+            }
+        }
+        for (shard_idx, batch) in batches {
+            let reply = shards[shard_idx]
+                .execute(Command::MSetNx(batch), virtual_time)
+                .await;
+            debug_assert_eq!(
+                reply,
+                RespValue::Integer(1),
+                "Locked shard {} must still accept its MSETNX share",
+                shard_idx
+            );
+        }
+        RespValue::Integer(1)
+    }
+
+    /// Cross-shard RENAME, RENAMENX or COPY: the value and its TTL leave the
+    /// source shard and are stored on the destination's. The caller holds both
+    /// shards locked.
+    async fn move_key_across_shards(
+        &self,
+        shards: &[ShardHandle],
+        cmd: &Command,
+        virtual_time: VirtualTime,
+    ) -> RespValue {
+        let (source, dest) = match cmd {
+            Command::Rename(source, dest) | Command::RenameNx(source, dest) => (source, dest),
+            Command::Copy { source, dest, .. } => (source, dest),
+            _ => unreachable!("only RENAME, RENAMENX and COPY move keys"),
+        };
+        let source_shard = &shards[hash_key(source, self.num_shards)];
+        let mut dest_shard = shards[hash_key(dest, self.num_shards)].clone();
+        if let Command::Copy { db: Some(db), .. } = cmd {
+            if *db >= self.config.databases {
+                return RespValue::Error("ERR DB index is out of range".to_string());
+            }
+            dest_shard = dest_shard.in_db(*db);
+        }
+
+        match cmd {
+            Command::Rename(_, _) => {
+                match source_shard.export_key(source, true, virtual_time).await {
+                    Some(entry) => {
+                        dest_shard.import_key(dest, entry, true, virtual_time).await;
+                        RespValue::SimpleString("OK".to_string())
+                    }
+                    None => RespValue::Error("ERR no such key".to_string()),
+                }
+            }
+            Command::RenameNx(_, _) => {
+                let exists = Command::Exists(vec![dest.clone()]);
+                if dest_shard.execute(exists, virtual_time).await != RespValue::Integer(0) {
+                    // Redis reports a missing source before an existing destination
+                    let exists = Command::Exists(vec![source.clone()]);
+                    if source_shard.execute(exists, virtual_time).await == RespValue::Integer(0) {
+                        return RespValue::Error("ERR no such key".to_string());
+                    }
+                    return RespValue::Integer(0);
+                }
+                match source_shard.export_key(source, true, virtual_time).await {
+                    Some(entry) => {
+                        let stored = dest_shard
+                            .import_key(dest, entry, false, virtual_time)
+                            .await;
+                        debug_assert!(stored, "Locked destination {} must still be free", dest);
+                        RespValue::Integer(1)
+                    }
+                    None => RespValue::Error("ERR no such key".to_string()),
+                }
+            }
+            Command::Copy { replace, .. } => {
+                let Some(entry) = source_shard.export_key(source, false, virtual_time).await else {
+                    return RespValue::Integer(0);
+                };
+                let copied = dest_shard
+                    .import_key(dest, entry, *replace, virtual_time)
+                    .await;
+                RespValue::Integer(copied as i64)
+            }
+            _ => unreachable!("only RENAME, RENAMENX and COPY move keys"),
+        }
+    }
+
+    /// Run a blocking command on the shard owning its keys and wait for the
+    /// reply. All keys of a BLPOP/BLMOVE/XREAD BLOCK must live on one shard.
+    async fn execute_blocking(&self, cmd: &Command, virtual_time: VirtualTime) -> RespValue {
+        if self.spans_shards(cmd) {
+            return RespValue::Error(CROSSSLOT_ERROR.to_string());
+        }
+        let Some(key) = cmd.get_primary_key() else {
+            debug_assert!(false, "Blocking command {} without a key", cmd.name());
+            return RespValue::Error("ERR wrong number of arguments".to_string());
+        };
+        let shard = &self.shards[hash_key(key, self.num_shards)];
+        let client_id = self.next_blocked_client_id.fetch_add(1, Ordering::Relaxed);
+
+        let Some(mut response_rx) = shard.execute_blocking(client_id, cmd.clone(), virtual_time)
+        else {
+            debug_assert!(
+                false,
+                "Shard {} channel closed unexpectedly",
+                shard.shard_id
+            );
+            return RespValue::Error("ERR shard unavailable".to_string());
+        };
+
+        loop {
+            match tokio::time::timeout(BLOCKED_CLIENT_POLL_INTERVAL, &mut response_rx).await {
+                Ok(response) => {
+                    return response.unwrap_or_else(|_| {
+                        RespValue::Error("ERR shard response failed".to_string())
+                    })
+                }
+                Err(_) => shard.expire_blocked(self.get_current_virtual_time()),
+            }
+        }
+    }
+
+    /// Current WATCH version stamps of `keys`, in argument order
+    pub async fn key_versions(&self, keys: &[SDS]) -> Vec<u64> {
+        let futures: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                let shard_idx = hash_key(key, self.num_shards);
+                self.shards[shard_idx].key_versions(vec![key.clone()])
+            })
+            .collect();
+        futures::future::join_all(futures)
+            .await
+            .into_iter()
+            .map(|versions| versions.and_then(|v| v.first().copied()).unwrap_or(0))
+            .collect()
+    }
+
+    /// Run a MULTI/EXEC block atomically.
+    ///
+    /// Every shard the transaction touches is locked first, in shard order so
+    /// that concurrent EXECs cannot deadlock. Then the watched version stamps
+    /// are checked and the queued commands run; no other client can interleave
+    /// on those shards until the locks are dropped on return. Returns a null
+    /// array when a watched key changed.
+    pub async fn execute_transaction(
+        &self,
+        watched: &[(SDS, u64)],
+        commands: &[Command],
+    ) -> RespValue {
+        let virtual_time = self.get_current_virtual_time();
+
+        let mut involved = std::collections::BTreeSet::new();
+        for (key, _) in watched {
+            involved.insert(hash_key(key, self.num_shards));
+        }
+        for cmd in commands {
+            if matches!(cmd, Command::Select(_)) {
+                continue;
+            }
+            if cmd.get_primary_key().is_none() {
+                // Keyless commands (FLUSHDB, KEYS, ...) may fan out to any shard
+                involved.extend(0..self.num_shards);
+                break;
+            }
+            for key in cmd.get_keys() {
+                involved.insert(hash_key(&key, self.num_shards));
+            }
+        }
+
+        let mut sessions: Vec<ShardHandle> = self.shards.iter().cloned().collect();
+        for &shard_idx in &involved {
+            match self.shards[shard_idx].lock().await {
+                Some(session) => sessions[shard_idx] = session,
+                None => {
+                    debug_assert!(false, "Shard {} refused a transaction lock", shard_idx);
+                    return RespValue::Error("ERR shard unavailable".to_string());
+                }
+            }
+        }
+
+        for (key, version) in watched {
+            let shard_idx = hash_key(key, self.num_shards);
+            let current = sessions[shard_idx].key_versions(vec![key.clone()]).await;
+            if current.as_deref() != Some(&[*version][..]) {
+                return RespValue::Array(None);
+            }
+        }
+
+        let mut results = Vec::with_capacity(commands.len());
+        for cmd in commands {
+            if let Command::Select(db) = cmd {
+                // The commands queued after SELECT run in the new database
+                let reply = if *db < self.config.databases {
+                    for session in sessions.iter_mut() {
+                        *session = session.in_db(*db);
+                    }
+                    RespValue::SimpleString("OK".to_string())
+                } else {
+                    RespValue::Error("ERR DB index is out of range".to_string())
+                };
+                results.push(reply);
+                continue;
+            }
+            let response = match cmd.get_primary_key() {
+                // Inside EXEC a blocking command never blocks
+                Some(key) if cmd.block_timeout_ms().is_some() && !self.spans_shards(cmd) => {
+                    let shard_idx = hash_key(key, self.num_shards);
+                    sessions[shard_idx].execute(cmd.clone(), virtual_time).await
+                }
+                _ => self.route(&sessions, cmd, virtual_time, None).await,
+            };
+            results.push(response);
+        }
+
+        // TigerStyle: Postconditions
+        debug_assert_eq!(
+            results.len(),
+            commands.len(),
+            "Postcondition: EXEC must reply once per queued command"
+        );
+
+        RespValue::Array(Some(results))
+    }
 }
 
 impl Default for ShardedActorState {
@@ -1127,3 +2438,848 @@ impl Default for ShardedActorState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::production::EXPIRE_CYCLE;
+    use crate::redis::SDS;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_blpop_woken_by_push_from_another_client() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let blpop = Command::BLPop {
+            keys: vec!["jobs".into()],
+            timeout_ms: 0,
+        };
+
+        let waiter = {
+            let state = state.clone();
+            tokio::spawn(async move { state.execute(&blpop).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        state
+            .execute(&Command::RPush("jobs".into(), vec![SDS::from_str("j1")]))
+            .await;
+        let reply = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter must be woken")
+            .unwrap();
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"jobs".to_vec())),
+                RespValue::BulkString(Some(b"j1".to_vec())),
+            ]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_exec_is_atomic_for_blocked_clients() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let blpop = Command::BLPop {
+            keys: vec!["q2".into()],
+            timeout_ms: 0,
+        };
+        let waiter = {
+            let state = state.clone();
+            tokio::spawn(async move { state.execute(&blpop).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The push and the delete land together: the waiter never sees `a`
+        let reply = state
+            .execute_transaction(
+                &[],
+                &[
+                    Command::RPush("q2".into(), vec![SDS::from_str("a")]),
+                    Command::Del(vec!["q2".into()]),
+                ],
+            )
+            .await;
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![RespValue::Integer(1), RespValue::Integer(1)]))
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        // Served once the transaction is over, with what it left behind
+        let reply = state
+            .execute_transaction(
+                &[],
+                &[Command::RPush("q2".into(), vec![SDS::from_str("b")])],
+            )
+            .await;
+        assert_eq!(reply, RespValue::Array(Some(vec![RespValue::Integer(1)])));
+        let reply = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter must be woken")
+            .unwrap();
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"q2".to_vec())),
+                RespValue::BulkString(Some(b"b".to_vec())),
+            ]))
+        );
+        assert_eq!(
+            state.execute(&Command::LLen("q2".into())).await,
+            RespValue::Integer(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocking_timeout_and_disconnect() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let reply = state
+            .execute(&Command::BZPopMin {
+                keys: vec!["z".into()],
+                timeout_ms: 50,
+            })
+            .await;
+        assert_eq!(reply, RespValue::Array(None));
+
+        // A waiter that goes away must not swallow the next push
+        let brpop = Command::BRPop {
+            keys: vec!["q".into()],
+            timeout_ms: 0,
+        };
+        let _ = tokio::time::timeout(Duration::from_millis(20), state.execute(&brpop)).await;
+        state
+            .execute(&Command::RPush("q".into(), vec![SDS::from_str("x")]))
+            .await;
+        assert_eq!(
+            state.execute(&Command::LLen("q".into())).await,
+            RespValue::Integer(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_xread_block_woken_by_xadd_from_another_client() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let xread = Command::XRead {
+            count: None,
+            block_ms: Some(0),
+            streams: vec![("events".into(), "$".to_string())],
+        };
+
+        let waiter = {
+            let state = state.clone();
+            let xread = xread.clone();
+            tokio::spawn(async move { state.execute(&xread).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished(), "XREAD BLOCK 0 must wait for data");
+
+        state
+            .execute(&Command::XAdd {
+                key: "events".into(),
+                id: "1-0".to_string(),
+                fields: vec![(SDS::from_str("f"), SDS::from_str("v"))],
+                nomkstream: false,
+                trim: None,
+            })
+            .await;
+        let reply = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("waiter must be woken")
+            .unwrap();
+        assert!(
+            matches!(reply, RespValue::Array(Some(ref streams)) if streams.len() == 1),
+            "{:?}",
+            reply
+        );
+
+        // A blocking read over streams on several shards cannot park anywhere
+        let other = key_on_other_shard(b"events", 2);
+        let spanning = Command::XRead {
+            count: None,
+            block_ms: Some(0),
+            streams: vec![("events".into(), "$".to_string()), (other, "$".to_string())],
+        };
+        assert_eq!(
+            state.execute(&spanning).await,
+            RespValue::Error(CROSSSLOT_ERROR.to_string())
+        );
+    }
+
+    /// A key whose shard differs from `key`'s
+    fn key_on_other_shard(key: &[u8], num_shards: usize) -> SDS {
+        (0..)
+            .map(|i| SDS::from(format!("other{}", i)))
+            .find(|k| hash_key(k, num_shards) != hash_key(key, num_shards))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hash_tag() {
+        assert_eq!(hash_tag(b"{user:1}:name"), b"user:1");
+        assert_eq!(hash_tag(b"prefix{tag}suffix"), b"tag");
+        assert_eq!(hash_tag(b"a{b}{c}"), b"b");
+        // Empty or unterminated tags hash the whole key
+        assert_eq!(hash_tag(b"{}key"), b"{}key");
+        assert_eq!(hash_tag(b"x{}{y}"), b"x{}{y}");
+        assert_eq!(hash_tag(b"{open"), b"{open");
+        assert_eq!(hash_tag(b"plain"), b"plain");
+
+        for shards in [2, 16, 256] {
+            assert_eq!(
+                hash_key(b"{user:1}:name", shards),
+                hash_key(b"{user:1}:email", shards)
+            );
+            assert_eq!(hash_key(b"user:1", shards), hash_key(b"{user:1}:x", shards));
+            assert_eq!(hash_key(b"k", shards), hash_key_bytes(b"k", shards));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_key_commands_with_hash_tags() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        state
+            .execute(&Command::RPush(
+                "{q}:src".into(),
+                vec![SDS::from_str("a"), SDS::from_str("b")],
+            ))
+            .await;
+        assert_eq!(
+            state
+                .execute(&Command::RPopLPush("{q}:src".into(), "{q}:dst".into()))
+                .await,
+            RespValue::BulkString(Some(b"b".to_vec()))
+        );
+        assert_eq!(
+            state.execute(&Command::LLen("{q}:dst".into())).await,
+            RespValue::Integer(1)
+        );
+
+        // Without a shared tag the keys may land on different shards
+        let other = key_on_other_shard(b"src", 8);
+        state
+            .execute(&Command::RPush("src".into(), vec![SDS::from_str("a")]))
+            .await;
+        let crossslot = RespValue::Error(CROSSSLOT_ERROR.to_string());
+        assert_eq!(
+            state
+                .execute(&Command::RPopLPush("src".into(), other.clone()))
+                .await,
+            crossslot
+        );
+        assert_eq!(
+            state
+                .execute(&Command::BLPop {
+                    keys: vec!["src".into(), other.clone()],
+                    timeout_ms: 0,
+                })
+                .await,
+            crossslot
+        );
+        // Nothing was popped by the rejected commands
+        assert_eq!(
+            state.execute(&Command::LLen("src".into())).await,
+            RespValue::Integer(1)
+        );
+
+        // DEL fans out per key instead
+        state
+            .execute(&Command::set(other.to_string(), SDS::from_str("v")))
+            .await;
+        assert_eq!(
+            state
+                .execute(&Command::Del(vec!["src".into(), other, "missing".into()]))
+                .await,
+            RespValue::Integer(2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_commands_scatter_gather_across_shards() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        let a = SDS::from("a");
+        let b = key_on_other_shard(&a, 8);
+        let dest = (0..)
+            .map(|i| SDS::from(format!("dest{}", i)))
+            .find(|k| {
+                let shard = hash_key(k, 8);
+                shard != hash_key(&a, 8) && shard != hash_key(&b, 8)
+            })
+            .unwrap();
+        let sadd = |key: &SDS, members: &[&str]| {
+            Command::SAdd(
+                key.clone(),
+                members.iter().map(|m| SDS::from_str(m)).collect(),
+            )
+        };
+        let sorted = |reply: RespValue| match reply {
+            RespValue::Array(Some(members)) => {
+                let mut members: Vec<RespValue> = members;
+                members.sort_by_key(|m| format!("{:?}", m));
+                members
+            }
+            other => panic!("expected an array, got {:?}", other),
+        };
+        let bulk = |s: &str| RespValue::BulkString(Some(s.as_bytes().to_vec()));
+        state.execute(&sadd(&a, &["1", "2", "3"])).await;
+        state.execute(&sadd(&b, &["2", "3", "4"])).await;
+
+        let sinter = Command::SetOp {
+            op: SetOperation::Inter,
+            dest: None,
+            keys: vec![a.clone(), b.clone()],
+        };
+        assert_eq!(
+            sorted(state.execute(&sinter).await),
+            vec![bulk("2"), bulk("3")]
+        );
+        assert_eq!(
+            state
+                .execute(&Command::SInterCard {
+                    keys: vec![a.clone(), b.clone()],
+                    limit: 1,
+                })
+                .await,
+            RespValue::Integer(1)
+        );
+
+        // The STORE result lands on the destination's own shard
+        let sunionstore = Command::SetOp {
+            op: SetOperation::Union,
+            dest: Some(dest.clone()),
+            keys: vec![a.clone(), b.clone()],
+        };
+        assert_eq!(state.execute(&sunionstore).await, RespValue::Integer(4));
+        assert_eq!(
+            state.execute(&Command::SCard(dest.clone())).await,
+            RespValue::Integer(4)
+        );
+
+        // SMOVE removes from one shard and adds on the other
+        assert_eq!(
+            state
+                .execute(&Command::SMove(a.clone(), b.clone(), SDS::from_str("1")))
+                .await,
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            state
+                .execute(&Command::SIsMember(b.clone(), SDS::from_str("1")))
+                .await,
+            RespValue::Integer(1)
+        );
+        state
+            .execute(&Command::set(dest.to_string(), SDS::from_str("v")))
+            .await;
+        assert!(matches!(
+            state
+                .execute(&Command::SMove(a.clone(), dest.clone(), SDS::from_str("2")))
+                .await,
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+        assert!(matches!(
+            state
+                .execute(&Command::SetOp {
+                    op: SetOperation::Diff,
+                    dest: None,
+                    keys: vec![a.clone(), dest.clone()],
+                })
+                .await,
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+
+        // Inside EXEC the gather runs on the locked shards
+        let reply = state
+            .execute_transaction(
+                &[],
+                &[Command::SetOp {
+                    op: SetOperation::Diff,
+                    dest: None,
+                    keys: vec![b.clone(), a.clone()],
+                }],
+            )
+            .await;
+        let RespValue::Array(Some(mut replies)) = reply else {
+            panic!("EXEC replies with an array");
+        };
+        assert_eq!(sorted(replies.pop().unwrap()), vec![bulk("1"), bulk("4")]);
+    }
+
+    #[tokio::test]
+    async fn test_smove_across_shards_never_loses_the_member() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        let a = SDS::from("a");
+        let b = key_on_other_shard(&a, 8);
+        let member = SDS::from_str("m");
+        state
+            .execute(&Command::SAdd(a.clone(), vec![member.clone()]))
+            .await;
+
+        // A destination of the wrong type leaves the source alone
+        state
+            .execute(&Command::set(b.to_string(), SDS::from_str("v")))
+            .await;
+        assert!(matches!(
+            state
+                .execute(&Command::SMove(a.clone(), b.clone(), member.clone()))
+                .await,
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+        assert_eq!(
+            state.execute(&Command::SMembers(a.clone())).await,
+            RespValue::Array(Some(vec![RespValue::BulkString(Some(b"m".to_vec()))]))
+        );
+        state.execute(&Command::Del(vec![b.clone()])).await;
+
+        // Moving back and forth, the member is always in exactly one set
+        let mover = tokio::spawn({
+            let state = state.clone();
+            let (a, b, member) = (a.clone(), b.clone(), member.clone());
+            async move {
+                for _ in 0..100 {
+                    for (from, to) in [(&a, &b), (&b, &a)] {
+                        let moved = state
+                            .execute(&Command::SMove(from.clone(), to.clone(), member.clone()))
+                            .await;
+                        assert_eq!(moved, RespValue::Integer(1));
+                    }
+                }
+            }
+        });
+        let check = [
+            Command::SIsMember(a.clone(), member.clone()),
+            Command::SIsMember(b.clone(), member.clone()),
+        ];
+        while !mover.is_finished() {
+            let reply = state.execute_transaction(&[], &check).await;
+            let RespValue::Array(Some(replies)) = reply else {
+                panic!("EXEC replies with an array");
+            };
+            let found: i64 = replies
+                .iter()
+                .map(|r| match r {
+                    RespValue::Integer(n) => *n,
+                    other => panic!("unexpected reply {:?}", other),
+                })
+                .sum();
+            assert_eq!(found, 1, "member in {} sets", found);
+        }
+        mover.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_msetnx_is_all_or_nothing_across_shards() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        let a = SDS::from("a");
+        let b = key_on_other_shard(&a, 8);
+        let msetnx = |pairs: &[(&SDS, &str)]| {
+            Command::MSetNx(
+                pairs
+                    .iter()
+                    .map(|(k, v)| ((*k).clone(), SDS::from_str(v)))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            state.cross_shard_error(&msetnx(&[(&a, "1"), (&b, "2")])),
+            None
+        );
+
+        state
+            .execute(&Command::set(b.to_string(), SDS::from_str("old")))
+            .await;
+        assert_eq!(
+            state.execute(&msetnx(&[(&a, "1"), (&b, "2")])).await,
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            state.execute(&Command::Get(a.clone())).await,
+            RespValue::BulkString(None)
+        );
+
+        state.execute(&Command::Del(vec![b.clone()])).await;
+        assert_eq!(
+            state.execute(&msetnx(&[(&a, "1"), (&b, "2")])).await,
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            state
+                .execute(&Command::MGet(vec![a.clone(), b.clone()]))
+                .await,
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"1".to_vec())),
+                RespValue::BulkString(Some(b"2".to_vec())),
+            ]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rename_and_copy_across_shards() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        let a = SDS::from("a");
+        let b = key_on_other_shard(&a, 8);
+        let bulk = |s: &str| RespValue::BulkString(Some(s.as_bytes().to_vec()));
+        state
+            .execute(&Command::Set {
+                key: a.clone(),
+                value: SDS::from_str("v"),
+                ex: Some(100),
+                px: None,
+                nx: false,
+                xx: false,
+                get: false,
+            })
+            .await;
+
+        let rename = Command::Rename(a.clone(), b.clone());
+        assert_eq!(state.cross_shard_error(&rename), None);
+        assert_eq!(
+            state.execute(&rename).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(state.execute(&Command::Get(b.clone())).await, bulk("v"));
+        // The production clock keeps running
+        assert!(matches!(
+            state.execute(&Command::Ttl(b.clone())).await,
+            RespValue::Integer(99..=100)
+        ));
+        assert_eq!(
+            state.execute(&Command::Exists(vec![a.clone()])).await,
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            state.execute(&rename).await,
+            RespValue::Error("ERR no such key".to_string())
+        );
+
+        let copy = |replace| Command::Copy {
+            source: b.clone(),
+            dest: a.clone(),
+            replace,
+            db: None,
+        };
+        assert_eq!(state.execute(&copy(false)).await, RespValue::Integer(1));
+        assert_eq!(state.execute(&copy(false)).await, RespValue::Integer(0));
+        assert_eq!(state.execute(&copy(true)).await, RespValue::Integer(1));
+        assert!(matches!(
+            state.execute(&Command::Ttl(a.clone())).await,
+            RespValue::Integer(99..=100)
+        ));
+        assert_eq!(
+            state
+                .execute(&Command::RenameNx(b.clone(), a.clone()))
+                .await,
+            RespValue::Integer(0)
+        );
+
+        let keys = vec![a.clone(), b.clone(), SDS::from("missing")];
+        assert_eq!(
+            state.execute(&Command::Touch(keys.clone())).await,
+            RespValue::Integer(2)
+        );
+        assert!(matches!(
+            state.execute(&Command::RandomKey).await,
+            RespValue::BulkString(Some(key)) if key == a.as_bytes() || key == b.as_bytes()
+        ));
+        assert_eq!(
+            state.execute(&Command::Unlink(keys)).await,
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            state.execute(&Command::RandomKey).await,
+            RespValue::BulkString(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fast_path_and_command_path_agree_on_shard() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        for i in 0..32 {
+            let key = format!("key:{}", i);
+            state
+                .fast_set(bytes::Bytes::from(key.clone()), bytes::Bytes::from("v"))
+                .await;
+            assert_eq!(
+                state.execute(&Command::Get(key.into())).await,
+                RespValue::BulkString(Some(b"v".to_vec()))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_utf8_keys_stay_distinct_across_shards() {
+        // Both keys decode lossily to "k\u{FFFD}"
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        let ff = SDS::from(&b"k\xff"[..]);
+        let fe = SDS::from(&b"k\xfe"[..]);
+        state
+            .fast_set(
+                bytes::Bytes::from_static(b"k\xff"),
+                bytes::Bytes::from("ff"),
+            )
+            .await;
+        state
+            .execute(&Command::Set {
+                key: fe.clone(),
+                value: SDS::from_str("fe"),
+                ex: None,
+                px: None,
+                nx: false,
+                xx: false,
+                get: false,
+            })
+            .await;
+
+        assert_eq!(
+            state.execute(&Command::Get(ff.clone())).await,
+            RespValue::BulkString(Some(b"ff".to_vec()))
+        );
+        assert_eq!(
+            state.execute(&Command::Get(fe)).await,
+            RespValue::BulkString(Some(b"fe".to_vec()))
+        );
+        assert_eq!(state.execute(&Command::DbSize).await, RespValue::Integer(2));
+        assert_eq!(
+            state.execute(&Command::Exists(vec![ff])).await,
+            RespValue::Integer(1)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_maxmemory_split_across_shards() {
+        use crate::redis::{EvictionPolicy, MemoryConfig};
+
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(4);
+        for i in 0..200 {
+            state
+                .fast_set(
+                    bytes::Bytes::from(format!("key:{}", i)),
+                    bytes::Bytes::from("x".repeat(100)),
+                )
+                .await;
+        }
+        let used = state.memory_stats().await.used_memory;
+        assert!(used >= 200 * 100);
+
+        state.set_memory_config(MemoryConfig {
+            maxmemory: used / 2,
+            policy: EvictionPolicy::AllKeysLru,
+            samples: 5,
+        });
+        let stats = state.memory_stats().await;
+        assert!(stats.used_memory <= used / 2);
+        assert!(stats.evicted_keys >= 50);
+        assert_eq!(stats.maxmemory, used / 2 / 4 * 4);
+
+        let info = match state.execute(&Command::Info).await {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected INFO reply {:?}", other),
+        };
+        assert!(info.contains("maxmemory_policy:allkeys-lru\r\n"));
+        assert!(info.contains(&format!("evicted_keys:{}\r\n", stats.evicted_keys)));
+        assert!(info.contains(&format!("used_memory:{}\r\n", stats.used_memory)));
+    }
+
+    #[tokio::test]
+    async fn test_databases_across_shards() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        let db1 = state.with_db(1);
+        assert_eq!((state.databases(), db1.selected_db()), (16, 1));
+        let a = SDS::from("a");
+        let b = key_on_other_shard(&a, 8);
+        let bulk = |s: &str| RespValue::BulkString(Some(s.as_bytes().to_vec()));
+        let set = |key: &SDS, value: &str| Command::Set {
+            key: key.clone(),
+            value: SDS::from_str(value),
+            ex: None,
+            px: None,
+            nx: false,
+            xx: false,
+            get: false,
+        };
+
+        state.execute(&set(&a, "zero")).await;
+        db1.execute(&set(&a, "one")).await;
+        db1.execute(&set(&b, "one")).await;
+        assert_eq!(state.execute(&Command::Get(a.clone())).await, bulk("zero"));
+        assert_eq!(db1.execute(&Command::DbSize).await, RespValue::Integer(2));
+        assert_eq!(
+            state.execute(&Command::Select(1)).await,
+            RespValue::Error("ERR SELECT is only supported on client connections".to_string())
+        );
+
+        // COPY DB between databases and shards
+        let copy = Command::Copy {
+            source: a.clone(),
+            dest: b.clone(),
+            replace: false,
+            db: Some(2),
+        };
+        assert_eq!(state.execute(&copy).await, RespValue::Integer(1));
+        assert_eq!(
+            state.with_db(2).execute(&Command::Get(b.clone())).await,
+            bulk("zero")
+        );
+
+        let info = match state.execute(&Command::Info).await {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected INFO reply {:?}", other),
+        };
+        assert!(
+            info.contains("db0:keys=1,expires=0,avg_ttl=0\r\n"),
+            "{}",
+            info
+        );
+        assert!(
+            info.contains("db1:keys=2,expires=0,avg_ttl=0\r\n"),
+            "{}",
+            info
+        );
+
+        assert_eq!(
+            state.execute(&Command::SwapDb(0, 1)).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(state.execute(&Command::Get(b.clone())).await, bulk("one"));
+        assert_eq!(db1.execute(&Command::Get(a.clone())).await, bulk("zero"));
+        assert!(matches!(
+            state.execute(&Command::SwapDb(0, 16)).await,
+            RespValue::Error(_)
+        ));
+
+        // SELECT inside MULTI moves the rest of the transaction
+        let reply = state
+            .execute_transaction(
+                &[],
+                &[
+                    Command::Select(3),
+                    set(&b, "three"),
+                    Command::Get(b.clone()),
+                ],
+            )
+            .await;
+        assert_eq!(
+            reply,
+            RespValue::Array(Some(vec![
+                RespValue::SimpleString("OK".to_string()),
+                RespValue::SimpleString("OK".to_string()),
+                bulk("three"),
+            ]))
+        );
+        assert_eq!(state.execute(&Command::Get(b.clone())).await, bulk("one"));
+
+        assert_eq!(
+            db1.execute(&Command::FlushDb).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(db1.execute(&Command::DbSize).await, RespValue::Integer(0));
+        assert_eq!(state.execute(&Command::DbSize).await, RespValue::Integer(2));
+        state.execute(&Command::FlushAll).await;
+        for db in [0, 2, 3] {
+            assert_eq!(
+                state.with_db(db).execute(&Command::DbSize).await,
+                RespValue::Integer(0)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_ignores_writes_and_flushes_in_other_dbs() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(4);
+        let db1 = state.with_db(1);
+        let w = SDS::from("w");
+        state
+            .execute(&Command::set("w".to_string(), SDS::from_str("0")))
+            .await;
+        let version = state.key_versions(std::slice::from_ref(&w)).await[0];
+        let watched = [(w.clone(), version)];
+        let exec = [Command::Get(w.clone())];
+
+        // Same name written in db1, then db1 flushed: the db0 watcher commits
+        db1.execute(&Command::set("w".to_string(), SDS::from_str("1")))
+            .await;
+        db1.execute(&Command::FlushDb).await;
+        assert!(matches!(
+            state.execute_transaction(&watched, &exec).await,
+            RespValue::Array(Some(_))
+        ));
+
+        // Flushing db0 itself aborts it
+        state.execute(&Command::FlushDb).await;
+        assert_eq!(
+            state.execute_transaction(&watched, &exec).await,
+            RespValue::Array(None)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_time_shard_execution_spans_shard_round_trips() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(4);
+        let (reply, shard_time) = time_shard_execution(state.execute(&Command::Ping)).await;
+        assert_eq!(reply, RespValue::SimpleString("PONG".to_string()));
+        assert!(shard_time.is_none(), "PING never reaches a shard");
+
+        let keys: Vec<SDS> = (0..16).map(|i| SDS::from_str(&format!("k{}", i))).collect();
+        let (_, shard_time) =
+            time_shard_execution(state.execute(&Command::Exists(keys.clone()))).await;
+        assert!(shard_time.is_some());
+
+        // Not timing: execute works the same outside the scope
+        assert_eq!(
+            state.execute(&Command::Exists(keys)).await,
+            RespValue::Integer(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_slowlog_commands_share_one_log_across_dbs() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let db1 = state.with_db(1);
+        state
+            .slowlog()
+            .record(Duration::from_secs(1), &[b"DEBUG", b"SLEEP"], "a", None);
+
+        assert_eq!(
+            db1.execute(&Command::SlowlogLen).await,
+            RespValue::Integer(1)
+        );
+        match state.execute(&Command::SlowlogGet(Some(10))).await {
+            RespValue::Array(Some(entries)) => assert_eq!(entries.len(), 1),
+            other => panic!("unexpected reply {:?}", other),
+        }
+        assert_eq!(
+            db1.execute(&Command::SlowlogReset).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(
+            state.execute(&Command::SlowlogLen).await,
+            RespValue::Integer(0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_latency_monitor_is_per_server() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let other = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        state.latency().set_threshold(1);
+        state
+            .latency()
+            .add_sample(EXPIRE_CYCLE, Duration::from_secs(1));
+
+        let events = |reply| match reply {
+            RespValue::Array(Some(events)) => events.len(),
+            other => panic!("unexpected reply {:?}", other),
+        };
+        assert_eq!(
+            events(state.with_db(1).execute(&Command::LatencyLatest).await),
+            1
+        );
+        assert_eq!(events(other.execute(&Command::LatencyLatest).await), 0);
+        assert_eq!(
+            other.execute(&Command::LatencyReset(vec![])).await,
+            RespValue::Integer(0)
+        );
+        assert_eq!(events(state.execute(&Command::LatencyLatest).await), 1);
+    }
+}
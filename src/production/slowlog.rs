@@ -0,0 +1,236 @@
+//! SLOWLOG: a ring buffer of the commands that ran longest
+//!
+//! Execution time is what a command spends in shard actors: `ShardHandle`
+//! measures every round trip and `time_shard_execution` adds them up for the
+//! command a connection is running. The GET/SET fast paths time their one
+//! round trip, and a pipelined batch charges each command an equal share.
+//! Time spent parsing, queueing on the socket or blocked (BLPOP) does not
+//! count, as in Redis.
+//!
+//! Arguments are truncated like Redis does: at most `SLOWLOG_ENTRY_MAX_ARGC`
+//! of them, each cut to `SLOWLOG_ENTRY_MAX_STRING` bytes.
+
+use super::perf_config::SlowlogConfig;
+use crate::redis::RespValue;
+use parking_lot::Mutex;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Arguments kept per entry; the last one says how many were dropped
+pub const SLOWLOG_ENTRY_MAX_ARGC: usize = 32;
+/// Bytes kept per argument
+pub const SLOWLOG_ENTRY_MAX_STRING: usize = 128;
+
+/// One logged command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowlogEntry {
+    pub id: u64,
+    /// Unix time the command finished, in seconds
+    pub timestamp: u64,
+    pub duration_us: u64,
+    pub args: Vec<Vec<u8>>,
+    pub client_addr: String,
+    pub client_name: String,
+}
+
+/// Server-wide slow log shared by all connections
+#[derive(Debug)]
+pub struct SlowLog {
+    /// Newest first
+    entries: Mutex<VecDeque<SlowlogEntry>>,
+    next_id: AtomicU64,
+    /// Microseconds; negative = disabled
+    log_slower_than: AtomicI64,
+    max_len: AtomicUsize,
+}
+
+impl Default for SlowLog {
+    fn default() -> Self {
+        Self::new(&SlowlogConfig::default())
+    }
+}
+
+impl SlowLog {
+    pub fn new(config: &SlowlogConfig) -> Self {
+        SlowLog {
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(0),
+            log_slower_than: AtomicI64::new(config.log_slower_than),
+            max_len: AtomicUsize::new(config.max_len),
+        }
+    }
+
+    /// Apply CONFIG SET slowlog-*; a shorter log drops its oldest entries
+    pub fn configure(&self, config: &SlowlogConfig) {
+        self.log_slower_than
+            .store(config.log_slower_than, Ordering::Relaxed);
+        self.max_len.store(config.max_len, Ordering::Relaxed);
+        self.entries.lock().truncate(config.max_len);
+    }
+
+    /// Commands at least this slow are logged; None when logging is off
+    #[inline]
+    pub fn threshold(&self) -> Option<Duration> {
+        let micros = self.log_slower_than.load(Ordering::Relaxed);
+        u64::try_from(micros).ok().map(Duration::from_micros)
+    }
+
+    /// Log a command that took `duration`, if it is slow enough
+    pub fn record(
+        &self,
+        duration: Duration,
+        args: &[&[u8]],
+        client_addr: &str,
+        client_name: Option<&str>,
+    ) {
+        match self.threshold() {
+            Some(threshold) if duration >= threshold => {}
+            _ => return,
+        }
+        let max_len = self.max_len.load(Ordering::Relaxed);
+        if max_len == 0 {
+            return;
+        }
+        let entry = SlowlogEntry {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            duration_us: duration.as_micros() as u64,
+            args: truncate_args(args),
+            client_addr: client_addr.to_string(),
+            client_name: client_name.unwrap_or_default().to_string(),
+        };
+        let mut entries = self.entries.lock();
+        entries.push_front(entry);
+        entries.truncate(max_len);
+    }
+
+    /// The `count` newest entries (all of them for None)
+    pub fn get(&self, count: Option<usize>) -> Vec<SlowlogEntry> {
+        let entries = self.entries.lock();
+        let count = count.unwrap_or(entries.len());
+        entries.iter().take(count).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    pub fn reset(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// SLOWLOG GET reply: `[id, timestamp, microseconds, [args], addr, name]`
+    /// per entry, newest first
+    pub fn get_reply(&self, count: Option<usize>) -> RespValue {
+        let bulk = |bytes: &[u8]| RespValue::BulkString(Some(bytes.to_vec()));
+        RespValue::Array(Some(
+            self.get(count)
+                .into_iter()
+                .map(|entry| {
+                    RespValue::Array(Some(vec![
+                        RespValue::Integer(entry.id as i64),
+                        RespValue::Integer(entry.timestamp as i64),
+                        RespValue::Integer(entry.duration_us as i64),
+                        RespValue::Array(Some(entry.args.iter().map(|a| bulk(a)).collect())),
+                        bulk(entry.client_addr.as_bytes()),
+                        bulk(entry.client_name.as_bytes()),
+                    ]))
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn truncate_args(args: &[&[u8]]) -> Vec<Vec<u8>> {
+    let kept = if args.len() > SLOWLOG_ENTRY_MAX_ARGC {
+        SLOWLOG_ENTRY_MAX_ARGC - 1
+    } else {
+        args.len()
+    };
+    let mut truncated: Vec<Vec<u8>> = args[..kept]
+        .iter()
+        .map(|arg| {
+            if arg.len() > SLOWLOG_ENTRY_MAX_STRING {
+                let mut cut = arg[..SLOWLOG_ENTRY_MAX_STRING].to_vec();
+                cut.extend_from_slice(
+                    format!("... ({} more bytes)", arg.len() - SLOWLOG_ENTRY_MAX_STRING).as_bytes(),
+                );
+                cut
+            } else {
+                arg.to_vec()
+            }
+        })
+        .collect();
+    if kept < args.len() {
+        truncated.push(format!("... ({} more arguments)", args.len() - kept).into_bytes());
+    }
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(log_slower_than: i64, max_len: usize) -> SlowlogConfig {
+        SlowlogConfig {
+            log_slower_than,
+            max_len,
+        }
+    }
+
+    #[test]
+    fn test_threshold_and_ring_buffer() {
+        let slowlog = SlowLog::new(&config(1000, 2));
+        let args: [&[u8]; 2] = [b"GET", b"k"];
+
+        slowlog.record(Duration::from_micros(999), &args, "1.2.3.4:5", None);
+        assert!(slowlog.is_empty());
+
+        for _ in 0..3 {
+            slowlog.record(Duration::from_millis(2), &args, "1.2.3.4:5", Some("app"));
+        }
+        let entries = slowlog.get(None);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, 2, "newest first");
+        assert_eq!(entries[1].id, 1);
+        assert_eq!(entries[0].duration_us, 2000);
+        assert_eq!(entries[0].client_name, "app");
+        assert_eq!(slowlog.get(Some(1)).len(), 1);
+
+        slowlog.reset();
+        assert_eq!(slowlog.len(), 0);
+
+        // Negative disables, zero logs everything
+        slowlog.configure(&config(-1, 2));
+        slowlog.record(Duration::from_secs(1), &args, "a", None);
+        assert!(slowlog.is_empty());
+        slowlog.configure(&config(0, 2));
+        slowlog.record(Duration::ZERO, &args, "a", None);
+        assert_eq!(slowlog.len(), 1);
+    }
+
+    #[test]
+    fn test_args_are_truncated() {
+        let long = vec![b'x'; SLOWLOG_ENTRY_MAX_STRING + 10];
+        let many: Vec<&[u8]> = vec![b"a".as_slice(); 40];
+
+        let truncated = truncate_args(&[b"SET", b"k", &long]);
+        assert_eq!(truncated.len(), 3);
+        assert!(truncated[2].ends_with(b"x... (10 more bytes)"));
+        assert_eq!(
+            truncated[2].len(),
+            SLOWLOG_ENTRY_MAX_STRING + "... (10 more bytes)".len()
+        );
+
+        let truncated = truncate_args(&many);
+        assert_eq!(truncated.len(), SLOWLOG_ENTRY_MAX_ARGC);
+        assert_eq!(truncated[31], b"... (9 more arguments)".to_vec());
+    }
+}
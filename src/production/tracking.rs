@@ -0,0 +1,295 @@
+//! Client-side caching: the per-shard invalidation table behind CLIENT TRACKING
+//!
+//! Every shard actor owns a `TrackingTable` for the keys it stores. A client
+//! that enables tracking is registered on all shards. In the default mode the
+//! owning shard remembers which clients read each key; in BCAST mode written
+//! keys are matched against the client's prefixes instead, so no reads are
+//! recorded.
+//!
+//! After each message the shard drains the keys its executor wrote and sends
+//! every interested client a single invalidation for them. It goes out as a
+//! RESP3 push `["invalidate", [keys]]` on the client's own connection, or,
+//! with REDIRECT, as a `__redis__:invalidate` message to another client
+//! through the Pub/Sub broker.
+//!
+//! As in Redis, a default-mode entry is dropped once invalidated: the client
+//! has to read the key again to hear about its next change.
+
+use super::pubsub::{PubSubHandle, PushSender, SubscriberId};
+use crate::redis::{RespValue, SDS};
+use ahash::AHashMap;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Tracking clients share their identity with Pub/Sub subscribers (CLIENT ID)
+pub type TrackingClientId = SubscriberId;
+
+/// Keys remembered per shard; past this, keys are invalidated to make room
+/// (Redis `tracking-table-max-keys`)
+pub const TRACKING_TABLE_MAX_KEYS: usize = 1_000_000;
+
+/// Pub/Sub channel that redirected invalidations are delivered on
+pub const INVALIDATE_CHANNEL: &str = "__redis__:invalidate";
+
+/// Where a client's invalidation messages go
+#[derive(Clone, Debug)]
+pub enum InvalidationTarget {
+    /// RESP3 push on the tracking connection itself
+    Push(PushSender),
+    /// `__redis__:invalidate` message to another client (REDIRECT id)
+    Redirect {
+        pubsub: PubSubHandle,
+        client: SubscriberId,
+    },
+}
+
+/// A tracking client, as registered on every shard
+#[derive(Clone, Debug)]
+pub struct TrackedClient {
+    pub target: InvalidationTarget,
+    /// BCAST prefixes (an empty prefix matches every key); None in default mode
+    pub bcast_prefixes: Option<Vec<SDS>>,
+    /// Keys this client writes itself are not invalidated for it
+    pub noloop: bool,
+}
+
+/// Tracking clients and the keys they read, for one shard
+#[derive(Default)]
+pub struct TrackingTable {
+    clients: BTreeMap<TrackingClientId, TrackedClient>,
+    /// Default mode: key -> clients that read it since its last invalidation
+    keys: AHashMap<SDS, BTreeSet<TrackingClientId>>,
+}
+
+impl TrackingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True when no client tracks anything on this shard
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Number of keys remembered for default-mode clients
+    #[inline]
+    pub fn tracked_keys(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Register `client`, replacing its previous options
+    pub fn enable(&mut self, id: TrackingClientId, client: TrackedClient) {
+        self.clients.insert(id, client);
+    }
+
+    /// Forget `client` and every key it read
+    pub fn disable(&mut self, id: TrackingClientId) {
+        if self.clients.remove(&id).is_none() {
+            return;
+        }
+        self.keys.retain(|_, readers| {
+            readers.remove(&id);
+            !readers.is_empty()
+        });
+        self.verify_invariants();
+    }
+
+    /// Remember that `id` read `keys` (default mode only)
+    pub fn record_reads(&mut self, id: TrackingClientId, keys: Vec<SDS>) {
+        match self.clients.get(&id) {
+            Some(client) if client.bcast_prefixes.is_none() => {}
+            _ => return,
+        }
+        for key in keys {
+            self.keys.entry(key).or_default().insert(id);
+        }
+        while self.keys.len() > TRACKING_TABLE_MAX_KEYS {
+            let Some(victim) = self.keys.keys().next().cloned() else {
+                break;
+            };
+            self.invalidate(Some(vec![victim]), None);
+        }
+        self.verify_invariants();
+    }
+
+    /// Notify clients about `written` keys (`None`: the keyspace was flushed).
+    /// `writer` is the tracking client that issued the write, if any.
+    pub fn invalidate(&mut self, written: Option<Vec<SDS>>, writer: Option<TrackingClientId>) {
+        let Some(written) = written else {
+            // FLUSHDB/FLUSHALL: every client drops its whole cache
+            self.keys.clear();
+            let ids: Vec<TrackingClientId> = self.clients.keys().copied().collect();
+            for id in ids {
+                self.send(id, None);
+            }
+            return;
+        };
+
+        let skip = |clients: &BTreeMap<TrackingClientId, TrackedClient>, id: TrackingClientId| {
+            writer == Some(id) && clients.get(&id).is_some_and(|c| c.noloop)
+        };
+        // One message per client, keys in a deterministic order
+        let mut pending: BTreeMap<TrackingClientId, BTreeSet<SDS>> = BTreeMap::new();
+        for key in written {
+            if let Some(readers) = self.keys.remove(&key) {
+                for id in readers {
+                    if !skip(&self.clients, id) {
+                        pending.entry(id).or_default().insert(key.clone());
+                    }
+                }
+            }
+            for (&id, client) in &self.clients {
+                let Some(prefixes) = &client.bcast_prefixes else {
+                    continue;
+                };
+                if prefixes.iter().any(|p| key.starts_with(p)) && !skip(&self.clients, id) {
+                    pending.entry(id).or_default().insert(key.clone());
+                }
+            }
+        }
+        for (id, keys) in pending {
+            self.send(id, Some(keys.into_iter().collect()));
+        }
+        self.verify_invariants();
+    }
+
+    /// Deliver one invalidation; a client whose connection is gone is dropped
+    fn send(&mut self, id: TrackingClientId, keys: Option<Vec<SDS>>) {
+        let Some(client) = self.clients.get(&id) else {
+            return;
+        };
+        let delivered = match &client.target {
+            InvalidationTarget::Push(push_tx) => {
+                push_tx.send(invalidation_push(keys.as_deref())).is_ok()
+            }
+            InvalidationTarget::Redirect { pubsub, client } => {
+                pubsub.invalidate(*client, keys);
+                true
+            }
+        };
+        if !delivered {
+            self.disable(id);
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn verify_invariants(&self) {
+        for (key, readers) in &self.keys {
+            debug_assert!(
+                !readers.is_empty(),
+                "Invariant violated: tracked key '{}' has no readers",
+                key
+            );
+            debug_assert!(
+                readers.iter().all(|id| self.clients.contains_key(id)),
+                "Invariant violated: key '{}' tracked for an unregistered client",
+                key
+            );
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    fn verify_invariants(&self) {}
+}
+
+/// `["invalidate", keys]`, with a null key list after a flush
+pub fn invalidation_keys(keys: Option<&[SDS]>) -> RespValue {
+    match keys {
+        Some(keys) => RespValue::Array(Some(
+            keys.iter()
+                .map(|k| RespValue::BulkString(Some(k.as_bytes().to_vec())))
+                .collect(),
+        )),
+        None => RespValue::Null,
+    }
+}
+
+fn invalidation_push(keys: Option<&[SDS]>) -> RespValue {
+    RespValue::Push(vec![
+        RespValue::BulkString(Some(b"invalidate".to_vec())),
+        invalidation_keys(keys),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn keys(list: &[&str]) -> Vec<SDS> {
+        list.iter().map(|&s| SDS::from(s)).collect()
+    }
+
+    fn client(push_tx: &PushSender, prefixes: Option<&[&str]>, noloop: bool) -> TrackedClient {
+        TrackedClient {
+            target: InvalidationTarget::Push(push_tx.clone()),
+            bcast_prefixes: prefixes.map(keys),
+            noloop,
+        }
+    }
+
+    #[test]
+    fn test_default_mode_invalidates_readers_once() {
+        let mut table = TrackingTable::new();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        table.enable(1, client(&tx, None, false));
+        table.record_reads(1, keys(&["a", "b"]));
+
+        table.invalidate(Some(keys(&["b", "a", "c"])), None);
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            invalidation_push(Some(&keys(&["a", "b"])))
+        );
+        assert_eq!(table.tracked_keys(), 0);
+
+        // Not read again: no second invalidation
+        table.invalidate(Some(keys(&["a"])), None);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_bcast_prefixes_and_noloop() {
+        let mut table = TrackingTable::new();
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, mut rx2) = mpsc::unbounded_channel();
+        table.enable(1, client(&tx1, Some(&["user:"]), true));
+        table.enable(2, client(&tx2, Some(&[""]), false));
+
+        table.invalidate(Some(keys(&["user:1", "order:1"])), Some(1));
+        // Client 1 wrote the keys itself (NOLOOP); client 2 matches everything
+        assert!(rx1.try_recv().is_err());
+        assert_eq!(
+            rx2.try_recv().unwrap(),
+            invalidation_push(Some(&keys(&["order:1", "user:1"])))
+        );
+
+        table.invalidate(Some(keys(&["user:2"])), Some(2));
+        assert_eq!(
+            rx1.try_recv().unwrap(),
+            invalidation_push(Some(&keys(&["user:2"])))
+        );
+    }
+
+    #[test]
+    fn test_flush_and_closed_connections() {
+        let mut table = TrackingTable::new();
+        let (tx1, mut rx1) = mpsc::unbounded_channel();
+        let (tx2, rx2) = mpsc::unbounded_channel();
+        table.enable(1, client(&tx1, None, false));
+        table.enable(2, client(&tx2, None, false));
+        table.record_reads(1, keys(&["a"]));
+        table.record_reads(2, keys(&["a", "b"]));
+        drop(rx2);
+
+        table.invalidate(None, None);
+        assert_eq!(rx1.try_recv().unwrap(), invalidation_push(None));
+        assert_eq!(table.tracked_keys(), 0);
+
+        // Client 2's connection is gone: it was unregistered
+        table.record_reads(2, keys(&["c"]));
+        assert_eq!(table.tracked_keys(), 0);
+        table.disable(1);
+        assert!(table.is_empty());
+    }
+}
@@ -0,0 +1,470 @@
+//! Per-connection MULTI/EXEC/WATCH state
+//!
+//! Each client connection owns its transaction: queued commands and watched
+//! keys never live in a shard, so two clients on the same shard cannot see
+//! each other's MULTI. EXEC hands the queue to
+//! `ShardedActorState::execute_transaction`, which locks every shard the
+//! transaction touches and checks the WATCH version stamps atomically.
+//!
+//! Connection-level commands never reach the shards: the few Redis allows in
+//! MULTI (PUBLISH, CONFIG GET, CLIENT ID, ...) are handed back to the
+//! connection to run once the shard commands have committed, and the rest
+//! are refused at queue time.
+
+use super::sharded_actor::ShardedActorState;
+use crate::io::TimeSource;
+use crate::redis::{Command, RespValue, SDS};
+
+/// Outcome of EXEC, see [`TransactionState::exec`]
+#[derive(Debug)]
+pub enum ExecOutcome {
+    /// The final reply: an error, a WATCH abort, or every command's reply
+    Done(RespValue),
+    /// The shard commands committed with `replies`; the connection runs each
+    /// `(index, command)` in order and puts its reply at `index`
+    Deferred {
+        replies: Vec<RespValue>,
+        deferred: Vec<(usize, Command)>,
+    },
+}
+
+/// MULTI/EXEC/WATCH state of one client connection
+#[derive(Debug, Default)]
+pub struct TransactionState {
+    in_multi: bool,
+    queued: Vec<Command>,
+    /// A command failed to queue (parse or ACL error): EXEC must abort
+    dirty: bool,
+    /// Watched keys and their version stamps at WATCH time, in WATCH order
+    watched: Vec<(SDS, u64)>,
+}
+
+impl TransactionState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// True between MULTI and EXEC/DISCARD
+    #[inline]
+    pub fn in_multi(&self) -> bool {
+        self.in_multi
+    }
+
+    /// Number of commands queued since MULTI
+    #[inline]
+    pub fn queued_len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Commands queued since MULTI, in order
+    #[inline]
+    pub fn queued(&self) -> &[Command] {
+        &self.queued
+    }
+
+    /// True if `handle` must see `cmd` instead of it being executed directly
+    #[inline]
+    pub fn intercepts(&self, cmd: &Command) -> bool {
+        self.in_multi
+            || matches!(
+                cmd,
+                Command::Multi
+                    | Command::Exec
+                    | Command::Discard
+                    | Command::Watch(_)
+                    | Command::Unwatch
+            )
+    }
+
+    /// Connection-level commands that may be queued in MULTI: the connection
+    /// runs them at EXEC, after the shard commands
+    pub fn runs_on_connection(cmd: &Command) -> bool {
+        matches!(
+            cmd,
+            Command::Publish { .. }
+                | Command::SPublish { .. }
+                | Command::PubSubChannels(_)
+                | Command::PubSubNumSub(_)
+                | Command::PubSubNumPat
+                | Command::PubSubShardChannels(_)
+                | Command::PubSubShardNumSub(_)
+                | Command::ConfigGet(_)
+                | Command::ClientId
+        )
+    }
+
+    /// Connection-level commands that cannot run as part of a transaction
+    fn not_allowed_in_multi(cmd: &Command) -> bool {
+        matches!(
+            cmd,
+            Command::Auth { .. }
+                | Command::Hello { .. }
+                | Command::AclWhoami
+                | Command::AclList
+                | Command::AclUsers
+                | Command::AclGetUser { .. }
+                | Command::AclSetUser { .. }
+                | Command::AclDelUser { .. }
+                | Command::AclCat { .. }
+                | Command::AclGenPass { .. }
+                | Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::PSubscribe(_)
+                | Command::PUnsubscribe(_)
+                | Command::SSubscribe(_)
+                | Command::SUnsubscribe(_)
+                | Command::ClusterSlots
+                | Command::ClusterShards
+                | Command::ClusterNodes
+                | Command::ClusterInfo
+                | Command::ClusterMyId
+                | Command::ClusterKeySlot(_)
+                | Command::ClusterSetSlot { .. }
+                | Command::Asking
+                | Command::ReadOnly
+                | Command::ReadWrite
+                | Command::ClientTracking { .. }
+                | Command::ClientCaching(_)
+                | Command::ClientGetRedir
+                | Command::ClientList { .. }
+                | Command::ClientInfo
+                | Command::ClientSetName(_)
+                | Command::ClientGetName
+                | Command::ClientKill(_)
+                | Command::ClientPause { .. }
+                | Command::ClientUnpause
+                | Command::ClientNoEvict(_)
+                | Command::ClientReply(_)
+                | Command::Monitor
+                | Command::ConfigSet(_)
+                | Command::ConfigRewrite
+                | Command::ConfigResetStat
+        )
+    }
+
+    /// Record a command that was rejected while queueing, so EXEC aborts
+    pub fn mark_dirty(&mut self) {
+        if self.in_multi {
+            self.dirty = true;
+        }
+    }
+
+    /// Handle a command this state intercepts (see [`Self::intercepts`])
+    pub async fn handle<T: TimeSource>(
+        &mut self,
+        state: &ShardedActorState<T>,
+        cmd: &Command,
+    ) -> RespValue {
+        debug_assert!(
+            self.intercepts(cmd),
+            "Precondition: command not intercepted"
+        );
+
+        match cmd {
+            Command::Multi => {
+                if self.in_multi {
+                    return RespValue::Error("ERR MULTI calls can not be nested".to_string());
+                }
+                self.in_multi = true;
+                RespValue::SimpleString("OK".to_string())
+            }
+            // Without a connection, connection-level commands cannot run
+            Command::Exec => match self.exec(state).await {
+                ExecOutcome::Done(reply) => reply,
+                ExecOutcome::Deferred {
+                    mut replies,
+                    deferred,
+                } => {
+                    for (index, cmd) in deferred {
+                        replies[index] = RespValue::Error(format!(
+                            "ERR {} is only supported on client connections",
+                            cmd.name()
+                        ));
+                    }
+                    RespValue::Array(Some(replies))
+                }
+            },
+            Command::Discard => {
+                if !self.in_multi {
+                    return RespValue::Error("ERR DISCARD without MULTI".to_string());
+                }
+                self.reset();
+                RespValue::SimpleString("OK".to_string())
+            }
+            Command::Watch(_) if self.in_multi => {
+                RespValue::Error("ERR WATCH inside MULTI is not allowed".to_string())
+            }
+            Command::Watch(keys) => {
+                let versions = state.key_versions(keys).await;
+                for (key, version) in keys.iter().zip(versions) {
+                    // Re-watching a key keeps the stamp from the first WATCH
+                    if !self.watched.iter().any(|(k, _)| k == key) {
+                        self.watched.push((key.clone(), version));
+                    }
+                }
+                RespValue::SimpleString("OK".to_string())
+            }
+            Command::Unwatch if !self.in_multi => {
+                self.watched.clear();
+                RespValue::SimpleString("OK".to_string())
+            }
+            Command::Unknown(name) => {
+                self.dirty = true;
+                RespValue::Error(format!("ERR unknown command '{}'", name))
+            }
+            _ if Self::not_allowed_in_multi(cmd) => {
+                self.dirty = true;
+                RespValue::Error("ERR Command not allowed inside a transaction".to_string())
+            }
+            _ => {
+                // Rejected at queue time, as Redis Cluster does, so EXEC aborts
+                if let Some(error) = state.cross_shard_error(cmd) {
+                    self.dirty = true;
+                    return error;
+                }
+                self.queued.push(cmd.clone());
+                RespValue::SimpleString("QUEUED".to_string())
+            }
+        }
+    }
+
+    /// EXEC: run the queued shard commands atomically, leaving the
+    /// connection-level ones (see [`Self::runs_on_connection`]) to the caller
+    pub async fn exec<T: TimeSource>(&mut self, state: &ShardedActorState<T>) -> ExecOutcome {
+        if !self.in_multi {
+            return ExecOutcome::Done(RespValue::Error("ERR EXEC without MULTI".to_string()));
+        }
+        let dirty = self.dirty;
+        let queued = std::mem::take(&mut self.queued);
+        let watched = std::mem::take(&mut self.watched);
+        self.reset();
+        if dirty {
+            return ExecOutcome::Done(RespValue::Error(
+                "EXECABORT Transaction discarded because of previous errors.".to_string(),
+            ));
+        }
+
+        let (deferred, on_shards): (Vec<_>, Vec<_>) = queued
+            .into_iter()
+            .enumerate()
+            .partition(|(_, cmd)| Self::runs_on_connection(cmd));
+        let (indices, commands): (Vec<usize>, Vec<Command>) = on_shards.into_iter().unzip();
+        let reply = state.execute_transaction(&watched, &commands).await;
+        if deferred.is_empty() {
+            return ExecOutcome::Done(reply);
+        }
+        let RespValue::Array(Some(shard_replies)) = reply else {
+            // WATCH abort: nothing runs
+            return ExecOutcome::Done(reply);
+        };
+
+        let mut replies = vec![RespValue::Array(None); indices.len() + deferred.len()];
+        for (index, reply) in indices.into_iter().zip(shard_replies) {
+            replies[index] = reply;
+        }
+        ExecOutcome::Deferred { replies, deferred }
+    }
+
+    /// Forget MULTI state and watched keys (EXEC, DISCARD)
+    fn reset(&mut self) {
+        self.in_multi = false;
+        self.dirty = false;
+        self.queued.clear();
+        self.watched.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::ProductionTimeSource;
+
+    fn set(key: &str, value: &str) -> Command {
+        Command::set(key.to_string(), SDS::from_str(value))
+    }
+
+    fn get(key: &str) -> Command {
+        Command::Get(key.into())
+    }
+
+    #[tokio::test]
+    async fn test_queues_are_per_connection() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(1);
+        let mut a = TransactionState::new();
+        let b = TransactionState::new();
+
+        assert_eq!(
+            a.handle(&state, &Command::Multi).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(
+            a.handle(&state, &set("k", "from-a")).await,
+            RespValue::SimpleString("QUEUED".to_string())
+        );
+
+        // Another client on the same shard is unaffected by a's MULTI
+        assert!(!b.intercepts(&set("k", "from-b")));
+        assert_eq!(
+            state.execute(&set("k", "from-b")).await,
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(
+            state.execute(&get("k")).await,
+            RespValue::BulkString(Some(b"from-b".to_vec()))
+        );
+
+        assert_eq!(
+            a.handle(&state, &Command::Exec).await,
+            RespValue::Array(Some(vec![RespValue::SimpleString("OK".to_string())]))
+        );
+        assert!(!a.in_multi());
+        assert_eq!(
+            state.execute(&get("k")).await,
+            RespValue::BulkString(Some(b"from-a".to_vec()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_watch_aborts_on_concurrent_write() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(4);
+        state.execute(&set("balance", "10")).await;
+
+        let mut client = TransactionState::new();
+        client
+            .handle(&state, &Command::Watch(vec!["balance".into()]))
+            .await;
+        // Same value written back: the version stamp still changes
+        state.execute(&set("balance", "10")).await;
+
+        client.handle(&state, &Command::Multi).await;
+        client.handle(&state, &set("balance", "0")).await;
+        assert_eq!(
+            client.handle(&state, &Command::Exec).await,
+            RespValue::Array(None)
+        );
+        assert_eq!(
+            state.execute(&get("balance")).await,
+            RespValue::BulkString(Some(b"10".to_vec()))
+        );
+
+        // Watches are cleared by EXEC, so a retry without WATCH commits
+        client.handle(&state, &Command::Multi).await;
+        client.handle(&state, &set("balance", "0")).await;
+        assert!(matches!(
+            client.handle(&state, &Command::Exec).await,
+            RespValue::Array(Some(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_execabort() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let mut client = TransactionState::new();
+
+        assert!(matches!(
+            client.handle(&state, &Command::Exec).await,
+            RespValue::Error(ref e) if e == "ERR EXEC without MULTI"
+        ));
+        assert!(matches!(
+            client.handle(&state, &Command::Discard).await,
+            RespValue::Error(ref e) if e == "ERR DISCARD without MULTI"
+        ));
+
+        client.handle(&state, &Command::Multi).await;
+        assert!(matches!(
+            client.handle(&state, &Command::Multi).await,
+            RespValue::Error(ref e) if e == "ERR MULTI calls can not be nested"
+        ));
+        assert!(matches!(
+            client.handle(&state, &Command::Watch(vec!["k".into()])).await,
+            RespValue::Error(ref e) if e == "ERR WATCH inside MULTI is not allowed"
+        ));
+        client.handle(&state, &set("k", "v")).await;
+        client.mark_dirty();
+        assert!(matches!(
+            client.handle(&state, &Command::Exec).await,
+            RespValue::Error(ref e) if e.starts_with("EXECABORT")
+        ));
+        assert_eq!(state.execute(&get("k")).await, RespValue::BulkString(None));
+        assert!(!client.in_multi());
+    }
+
+    #[tokio::test]
+    async fn test_connection_commands_are_deferred_or_refused() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
+        let mut client = TransactionState::new();
+        let publish = Command::Publish {
+            channel: "ch".to_string(),
+            message: SDS::from_str("hi"),
+        };
+
+        client.handle(&state, &Command::Multi).await;
+        client.handle(&state, &set("k", "v")).await;
+        assert_eq!(
+            client.handle(&state, &publish).await,
+            RespValue::SimpleString("QUEUED".to_string())
+        );
+        client.handle(&state, &get("k")).await;
+        match client.exec(&state).await {
+            ExecOutcome::Deferred { replies, deferred } => {
+                assert_eq!(replies[0], RespValue::SimpleString("OK".to_string()));
+                assert_eq!(replies[2], RespValue::BulkString(Some(b"v".to_vec())));
+                assert_eq!(deferred.len(), 1);
+                assert_eq!(deferred[0].0, 1);
+                assert_eq!(deferred[0].1.name(), "PUBLISH");
+            }
+            other => panic!("PUBLISH must be left to the connection, got {:?}", other),
+        }
+
+        // A WATCH abort runs nothing, the connection-level commands included
+        client
+            .handle(&state, &Command::Watch(vec!["k".into()]))
+            .await;
+        state.execute(&set("k", "w")).await;
+        client.handle(&state, &Command::Multi).await;
+        client.handle(&state, &publish).await;
+        assert!(matches!(
+            client.exec(&state).await,
+            ExecOutcome::Done(RespValue::Array(None))
+        ));
+
+        client.handle(&state, &Command::Multi).await;
+        assert!(matches!(
+            client.handle(&state, &Command::Monitor).await,
+            RespValue::Error(ref e) if e == "ERR Command not allowed inside a transaction"
+        ));
+        assert!(matches!(
+            client.handle(&state, &Command::Exec).await,
+            RespValue::Error(ref e) if e.starts_with("EXECABORT")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_crossslot_command_aborts_exec() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(8);
+        let other = (0..)
+            .map(|i| SDS::from(format!("dst{}", i)))
+            .find(|k| {
+                state
+                    .cross_shard_error(&Command::RPopLPush("src".into(), k.clone()))
+                    .is_some()
+            })
+            .unwrap();
+        let mut client = TransactionState::new();
+
+        client.handle(&state, &Command::Multi).await;
+        client.handle(&state, &set("src", "v")).await;
+        assert!(matches!(
+            client.handle(&state, &Command::RPopLPush("src".into(), other)).await,
+            RespValue::Error(ref e) if e.starts_with("CROSSSLOT")
+        ));
+        assert!(matches!(
+            client.handle(&state, &Command::Exec).await,
+            RespValue::Error(ref e) if e.starts_with("EXECABORT")
+        ));
+        assert_eq!(
+            state.execute(&get("src")).await,
+            RespValue::BulkString(None)
+        );
+    }
+}
@@ -0,0 +1,634 @@
+//! Deterministic Simulation Testing for MULTI/EXEC/WATCH
+//!
+//! VOPR-style harness that interleaves several clients, each with its own
+//! `TransactionState`, against one `ShardedActorState`:
+//! - Clients run WATCH / read / MULTI / EXEC bank transfers across shards,
+//!   one step at a time, in an order chosen by a seeded RNG
+//! - Plain writes from other clients touch watched keys in between
+//! - An auditor snapshots every account inside MULTI/EXEC, sometimes
+//!   concurrently with another client's EXEC
+//!
+//! Invariants: EXEC aborts exactly when a watched key was written by someone
+//! else, queued commands never leak into other clients, the balance total is
+//! conserved, and every snapshot is consistent with the shadow model.
+
+use super::sharded_actor::{ShardConfig, ShardedActorState};
+use super::transaction::TransactionState;
+use crate::io::simulation::SimulatedRng;
+use crate::io::Rng;
+use crate::redis::{Command, RespValue, SDS};
+
+/// Configuration for Transaction DST
+#[derive(Debug, Clone)]
+pub struct TransactionDSTConfig {
+    /// Random seed for reproducibility
+    pub seed: u64,
+    /// Number of concurrent clients running transfers
+    pub num_clients: usize,
+    /// Number of account keys (spread over the shards)
+    pub num_accounts: usize,
+    /// Number of shard actors
+    pub num_shards: usize,
+    /// Starting balance of each account
+    pub initial_balance: i64,
+    /// Probability that an idle client starts a transfer (vs a plain write)
+    pub transfer_prob: f64,
+    /// Probability of an audit snapshot per step
+    pub audit_prob: f64,
+    /// Probability that an EXEC runs concurrently with an audit
+    pub concurrent_exec_prob: f64,
+    /// Probability of DISCARD instead of EXEC
+    pub discard_prob: f64,
+}
+
+impl Default for TransactionDSTConfig {
+    fn default() -> Self {
+        TransactionDSTConfig {
+            seed: 0,
+            num_clients: 4,
+            num_accounts: 6,
+            num_shards: 4,
+            initial_balance: 100,
+            transfer_prob: 0.8,
+            audit_prob: 0.1,
+            concurrent_exec_prob: 0.3,
+            discard_prob: 0.05,
+        }
+    }
+}
+
+impl TransactionDSTConfig {
+    pub fn new(seed: u64) -> Self {
+        TransactionDSTConfig {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Few hot accounts, many clients: most transactions conflict
+    pub fn high_contention(seed: u64) -> Self {
+        TransactionDSTConfig {
+            seed,
+            num_clients: 8,
+            num_accounts: 3,
+            transfer_prob: 0.7,
+            ..Default::default()
+        }
+    }
+
+    /// Single shard: isolation must not depend on keys landing on different shards
+    pub fn single_shard(seed: u64) -> Self {
+        TransactionDSTConfig {
+            seed,
+            num_shards: 1,
+            ..Default::default()
+        }
+    }
+}
+
+/// Operation type for logging
+#[derive(Debug, Clone)]
+pub enum TransactionOp {
+    Watch {
+        client: usize,
+        from: usize,
+        to: usize,
+    },
+    Read {
+        client: usize,
+    },
+    Queue {
+        client: usize,
+    },
+    Exec {
+        client: usize,
+        concurrent_audit: bool,
+    },
+    Discard {
+        client: usize,
+    },
+    PlainWrite {
+        client: usize,
+        account: usize,
+    },
+    Audit,
+}
+
+/// Result of a Transaction DST run
+#[derive(Debug, Clone)]
+pub struct TransactionDSTResult {
+    pub seed: u64,
+    pub total_operations: u64,
+    pub commits: u64,
+    pub aborts: u64,
+    pub discards: u64,
+    pub plain_writes: u64,
+    pub audits: u64,
+    pub concurrent_execs: u64,
+    pub invariant_violations: Vec<String>,
+    pub last_op: Option<TransactionOp>,
+}
+
+impl TransactionDSTResult {
+    pub fn new(seed: u64) -> Self {
+        TransactionDSTResult {
+            seed,
+            total_operations: 0,
+            commits: 0,
+            aborts: 0,
+            discards: 0,
+            plain_writes: 0,
+            audits: 0,
+            concurrent_execs: 0,
+            invariant_violations: Vec::new(),
+            last_op: None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.invariant_violations.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Seed {}: {} ops, {} commits, {} aborts, {} discards, {} plain writes, {} audits ({} concurrent), {}",
+            self.seed,
+            self.total_operations,
+            self.commits,
+            self.aborts,
+            self.discards,
+            self.plain_writes,
+            self.audits,
+            self.concurrent_execs,
+            if self.is_success() {
+                "PASS".to_string()
+            } else {
+                format!("FAIL ({} violations)", self.invariant_violations.len())
+            }
+        )
+    }
+}
+
+/// Where a client is in its WATCH/MULTI/EXEC transfer
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClientPhase {
+    Idle,
+    Watched {
+        from: usize,
+        to: usize,
+    },
+    Read {
+        from: usize,
+        to: usize,
+        from_balance: i64,
+        to_balance: i64,
+    },
+    Queued {
+        from: usize,
+        to: usize,
+        amount: i64,
+    },
+}
+
+struct SimClient {
+    txn: TransactionState,
+    phase: ClientPhase,
+    /// Someone else committed a write to a watched key since WATCH
+    watch_broken: bool,
+}
+
+/// Transaction DST harness
+pub struct TransactionDSTHarness {
+    config: TransactionDSTConfig,
+    rng: SimulatedRng,
+    runtime: tokio::runtime::Runtime,
+    state: ShardedActorState,
+    clients: Vec<SimClient>,
+    auditor: TransactionState,
+    /// Shadow model: committed balance of each account
+    balances: Vec<i64>,
+    result: TransactionDSTResult,
+}
+
+impl TransactionDSTHarness {
+    pub fn new(config: TransactionDSTConfig) -> Self {
+        debug_assert!(config.num_accounts >= 2, "transfers need two accounts");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("failed to build DST runtime");
+        // Shard actors are spawned on the harness runtime
+        let state = {
+            let _guard = runtime.enter();
+            ShardedActorState::with_config(ShardConfig::with_shards(config.num_shards))
+        };
+
+        let harness = TransactionDSTHarness {
+            rng: SimulatedRng::new(config.seed),
+            runtime,
+            state,
+            clients: (0..config.num_clients)
+                .map(|_| SimClient {
+                    txn: TransactionState::new(),
+                    phase: ClientPhase::Idle,
+                    watch_broken: false,
+                })
+                .collect(),
+            auditor: TransactionState::new(),
+            balances: vec![config.initial_balance; config.num_accounts],
+            result: TransactionDSTResult::new(config.seed),
+            config,
+        };
+        for account in 0..harness.config.num_accounts {
+            let cmd = Command::set(
+                Self::account_key(account).to_string(),
+                SDS::from_str(&harness.config.initial_balance.to_string()),
+            );
+            harness.runtime.block_on(harness.state.execute(&cmd));
+        }
+        harness
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::new(TransactionDSTConfig::new(seed))
+    }
+
+    fn account_key(account: usize) -> SDS {
+        SDS::from(format!("account:{}", account))
+    }
+
+    fn parse_balance(reply: &RespValue) -> Option<i64> {
+        match reply {
+            RespValue::BulkString(Some(data)) => std::str::from_utf8(data).ok()?.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn violation(&mut self, msg: String) {
+        self.result.invariant_violations.push(msg);
+    }
+
+    /// A commit wrote `accounts`: break the WATCH of every other client on them
+    fn record_write(&mut self, writer: Option<usize>, accounts: &[usize]) {
+        for (i, client) in self.clients.iter_mut().enumerate() {
+            if Some(i) == writer {
+                continue;
+            }
+            let watching = match client.phase {
+                ClientPhase::Watched { from, to }
+                | ClientPhase::Read { from, to, .. }
+                | ClientPhase::Queued { from, to, .. } => {
+                    accounts.iter().any(|a| *a == from || *a == to)
+                }
+                ClientPhase::Idle => false,
+            };
+            if watching {
+                client.watch_broken = true;
+            }
+        }
+    }
+
+    /// Run one step of the simulation
+    pub fn step(&mut self) {
+        self.result.total_operations += 1;
+
+        if self.rng.gen_bool(self.config.audit_prob) {
+            self.result.last_op = Some(TransactionOp::Audit);
+            let snapshot = self.runtime.block_on(Self::audit(
+                &self.state,
+                &mut self.auditor,
+                self.config.num_accounts,
+            ));
+            let current = self.balances.clone();
+            self.check_snapshot(snapshot, std::slice::from_ref(&current));
+            return;
+        }
+
+        let client = self.rng.gen_range(0, self.config.num_clients as u64) as usize;
+        match self.clients[client].phase {
+            ClientPhase::Idle if self.rng.gen_bool(self.config.transfer_prob) => {
+                let from = self.rng.gen_range(0, self.config.num_accounts as u64) as usize;
+                let offset = self.rng.gen_range(1, self.config.num_accounts as u64) as usize;
+                let to = (from + offset) % self.config.num_accounts;
+                self.result.last_op = Some(TransactionOp::Watch { client, from, to });
+
+                let cmd = Command::Watch(vec![Self::account_key(from), Self::account_key(to)]);
+                let c = &mut self.clients[client];
+                let reply = self.runtime.block_on(c.txn.handle(&self.state, &cmd));
+                if reply != RespValue::SimpleString("OK".to_string()) {
+                    self.violation(format!("WATCH by client {} replied {:?}", client, reply));
+                }
+                let c = &mut self.clients[client];
+                c.phase = ClientPhase::Watched { from, to };
+                c.watch_broken = false;
+            }
+            ClientPhase::Idle => {
+                // Plain write that changes no balance but bumps the version stamp
+                let account = self.rng.gen_range(0, self.config.num_accounts as u64) as usize;
+                self.result.last_op = Some(TransactionOp::PlainWrite { client, account });
+                let cmd = Command::IncrBy(Self::account_key(account), 0);
+                let reply = self.runtime.block_on(self.state.execute(&cmd));
+                if reply != RespValue::Integer(self.balances[account]) {
+                    self.violation(format!(
+                        "plain INCRBY {} 0 replied {:?}, expected {} (queued by another client?)",
+                        account, reply, self.balances[account]
+                    ));
+                }
+                self.result.plain_writes += 1;
+                self.record_write(Some(client), &[account]);
+            }
+            ClientPhase::Watched { from, to } => {
+                self.result.last_op = Some(TransactionOp::Read { client });
+                let from_reply = self
+                    .runtime
+                    .block_on(self.state.execute(&Command::Get(Self::account_key(from))));
+                let to_reply = self
+                    .runtime
+                    .block_on(self.state.execute(&Command::Get(Self::account_key(to))));
+                match (
+                    Self::parse_balance(&from_reply),
+                    Self::parse_balance(&to_reply),
+                ) {
+                    (Some(from_balance), Some(to_balance)) => {
+                        self.clients[client].phase = ClientPhase::Read {
+                            from,
+                            to,
+                            from_balance,
+                            to_balance,
+                        };
+                    }
+                    _ => self.violation(format!(
+                        "client {} read non-balances {:?} / {:?}",
+                        client, from_reply, to_reply
+                    )),
+                }
+            }
+            ClientPhase::Read {
+                from,
+                to,
+                from_balance,
+                to_balance,
+            } => {
+                self.result.last_op = Some(TransactionOp::Queue { client });
+                let amount = self.rng.gen_range(0, from_balance.max(0) as u64 + 1) as i64;
+                let commands = [
+                    Command::Multi,
+                    Command::set(
+                        Self::account_key(from).to_string(),
+                        SDS::from_str(&(from_balance - amount).to_string()),
+                    ),
+                    Command::set(
+                        Self::account_key(to).to_string(),
+                        SDS::from_str(&(to_balance + amount).to_string()),
+                    ),
+                ];
+                let mut replies = Vec::new();
+                for cmd in &commands {
+                    let c = &mut self.clients[client];
+                    replies.push(self.runtime.block_on(c.txn.handle(&self.state, cmd)));
+                }
+                let expected = ["OK", "QUEUED", "QUEUED"];
+                for (reply, expected) in replies.iter().zip(expected) {
+                    if *reply != RespValue::SimpleString(expected.to_string()) {
+                        self.violation(format!(
+                            "client {} queueing replied {:?}, expected {}",
+                            client, reply, expected
+                        ));
+                    }
+                }
+                self.clients[client].phase = ClientPhase::Queued { from, to, amount };
+            }
+            ClientPhase::Queued { from, to, amount } => {
+                if self.rng.gen_bool(self.config.discard_prob) {
+                    self.result.last_op = Some(TransactionOp::Discard { client });
+                    let c = &mut self.clients[client];
+                    let reply = self
+                        .runtime
+                        .block_on(c.txn.handle(&self.state, &Command::Discard));
+                    if reply != RespValue::SimpleString("OK".to_string()) {
+                        self.violation(format!("DISCARD by client {} replied {:?}", client, reply));
+                    }
+                    self.clients[client].phase = ClientPhase::Idle;
+                    self.result.discards += 1;
+                    return;
+                }
+                self.exec_transfer(client, from, to, amount);
+            }
+        }
+    }
+
+    fn exec_transfer(&mut self, client: usize, from: usize, to: usize, amount: i64) {
+        let concurrent_audit = self.rng.gen_bool(self.config.concurrent_exec_prob);
+        self.result.last_op = Some(TransactionOp::Exec {
+            client,
+            concurrent_audit,
+        });
+
+        let before = self.balances.clone();
+        let mut after = before.clone();
+        after[from] -= amount;
+        after[to] += amount;
+
+        let c = &mut self.clients[client];
+        let (reply, snapshot) = if concurrent_audit {
+            self.result.concurrent_execs += 1;
+            let state = &self.state;
+            let exec = c.txn.handle(state, &Command::Exec);
+            let audit = Self::audit(state, &mut self.auditor, self.config.num_accounts);
+            let (reply, snapshot) = self.runtime.block_on(async { futures::join!(exec, audit) });
+            (reply, Some(snapshot))
+        } else {
+            (
+                self.runtime
+                    .block_on(c.txn.handle(&self.state, &Command::Exec)),
+                None,
+            )
+        };
+        let watch_broken = c.watch_broken;
+        c.phase = ClientPhase::Idle;
+
+        let ok = RespValue::SimpleString("OK".to_string());
+        match reply {
+            RespValue::Array(None) => {
+                self.result.aborts += 1;
+                if !watch_broken {
+                    self.violation(format!(
+                        "EXEC by client {} aborted although its watched keys were untouched",
+                        client
+                    ));
+                }
+                if let Some(snapshot) = snapshot {
+                    self.check_snapshot(snapshot, &[before]);
+                }
+            }
+            RespValue::Array(Some(replies)) if replies == [ok.clone(), ok] => {
+                self.result.commits += 1;
+                if watch_broken {
+                    self.violation(format!(
+                        "EXEC by client {} committed although a watched key changed",
+                        client
+                    ));
+                }
+                self.balances = after.clone();
+                self.record_write(Some(client), &[from, to]);
+                if let Some(snapshot) = snapshot {
+                    // The audit ran entirely before or entirely after the EXEC
+                    self.check_snapshot(snapshot, &[before, after]);
+                }
+            }
+            other => self.violation(format!("EXEC by client {} replied {:?}", client, other)),
+        }
+    }
+
+    /// Read every account inside one MULTI/EXEC
+    async fn audit(
+        state: &ShardedActorState,
+        auditor: &mut TransactionState,
+        num_accounts: usize,
+    ) -> RespValue {
+        auditor.handle(state, &Command::Multi).await;
+        for account in 0..num_accounts {
+            auditor
+                .handle(state, &Command::Get(Self::account_key(account)))
+                .await;
+        }
+        auditor.handle(state, &Command::Exec).await
+    }
+
+    /// A snapshot must match one of the allowed committed states exactly
+    fn check_snapshot(&mut self, snapshot: RespValue, allowed: &[Vec<i64>]) {
+        self.result.audits += 1;
+        let balances: Option<Vec<i64>> = match &snapshot {
+            RespValue::Array(Some(replies)) => replies.iter().map(Self::parse_balance).collect(),
+            _ => None,
+        };
+        match balances {
+            Some(balances) if allowed.contains(&balances) => {}
+            _ => self.violation(format!(
+                "audit saw {:?}, expected one of {:?}",
+                snapshot, allowed
+            )),
+        }
+    }
+
+    /// Check the store against the shadow model
+    fn check_invariants(&mut self) {
+        let total: i64 = self.balances.iter().sum();
+        let expected_total = self.config.initial_balance * self.config.num_accounts as i64;
+        if total != expected_total {
+            self.violation(format!(
+                "shadow total {} != initial total {}",
+                total, expected_total
+            ));
+        }
+        for account in 0..self.config.num_accounts {
+            let cmd = Command::Get(Self::account_key(account));
+            let reply = self.runtime.block_on(self.state.execute(&cmd));
+            if Self::parse_balance(&reply) != Some(self.balances[account]) {
+                self.violation(format!(
+                    "account {} is {:?}, shadow model says {}",
+                    account, reply, self.balances[account]
+                ));
+            }
+        }
+    }
+
+    /// Run multiple operations, checking the store against the model at the end
+    pub fn run(&mut self, operations: usize) {
+        for _ in 0..operations {
+            self.step();
+            if !self.result.invariant_violations.is_empty() {
+                break;
+            }
+        }
+        self.check_invariants();
+    }
+
+    pub fn result(&self) -> &TransactionDSTResult {
+        &self.result
+    }
+}
+
+/// Run a batch of DST tests with different seeds
+pub fn run_transaction_batch(
+    start_seed: u64,
+    num_seeds: usize,
+    ops_per_seed: usize,
+    config_fn: fn(u64) -> TransactionDSTConfig,
+) -> Vec<TransactionDSTResult> {
+    (0..num_seeds)
+        .map(|i| {
+            let seed = start_seed + i as u64;
+            let config = config_fn(seed);
+            let mut harness = TransactionDSTHarness::new(config);
+            harness.run(ops_per_seed);
+            harness.result().clone()
+        })
+        .collect()
+}
+
+/// Summarize batch results
+pub fn summarize_transaction_batch(results: &[TransactionDSTResult]) -> String {
+    let total = results.len();
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    let failed = total - passed;
+    let total_ops: u64 = results.iter().map(|r| r.total_operations).sum();
+    let total_commits: u64 = results.iter().map(|r| r.commits).sum();
+    let total_aborts: u64 = results.iter().map(|r| r.aborts).sum();
+
+    let mut summary = format!(
+        "Transaction DST Summary\n\
+         =======================\n\
+         Seeds: {} total, {} passed, {} failed\n\
+         Total operations: {} ({} commits, {} aborts)\n",
+        total, passed, failed, total_ops, total_commits, total_aborts
+    );
+
+    if failed > 0 {
+        summary.push_str("\nFailed seeds:\n");
+        for result in results.iter().filter(|r| !r.is_success()) {
+            summary.push_str(&format!("  Seed {}: {}\n", result.seed, result.summary()));
+            for violation in &result.invariant_violations {
+                summary.push_str(&format!("    - {}\n", violation));
+            }
+        }
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_dst_single_seed() {
+        let mut harness = TransactionDSTHarness::with_seed(42);
+        harness.run(300);
+        let result = harness.result();
+        println!("{}", result.summary());
+        assert!(result.is_success(), "{:?}", result.invariant_violations);
+        assert!(result.commits > 0, "some transfers must commit");
+    }
+
+    #[test]
+    fn test_transaction_dst_contention_aborts() {
+        let mut harness = TransactionDSTHarness::new(TransactionDSTConfig::high_contention(7));
+        harness.run(500);
+        let result = harness.result();
+        println!("{}", result.summary());
+        assert!(result.is_success(), "{:?}", result.invariant_violations);
+        assert!(result.aborts > 0, "contended WATCHes must abort");
+    }
+
+    #[test]
+    fn test_transaction_dst_deterministic() {
+        let run = |seed| {
+            let mut harness = TransactionDSTHarness::with_seed(seed);
+            harness.run(200);
+            let r = harness.result().clone();
+            (r.commits, r.aborts, r.discards, r.audits)
+        };
+        assert_eq!(run(12345), run(12345), "same seed must replay identically");
+    }
+}
@@ -1,7 +1,9 @@
 //! TtlManagerActor - Background actor for TTL key expiration
 //!
 //! This actor periodically sends eviction messages to shard actors.
-//! Follows the actor pattern with proper shutdown handling.
+//! Follows the actor pattern with proper shutdown handling. Each shard's
+//! active expire cycle reclaims expired keys and then expired hash fields
+//! (HEXPIRE), deleting hashes left empty.
 //!
 //! ## Design (TigerBeetle/FoundationDB inspired)
 //!
@@ -12,22 +14,59 @@
 //! └─────────────────────┘     └─────────────────────┘
 //! ```
 
+use super::latency::EXPIRE_CYCLE;
 use super::ShardedActorState;
 use crate::io::TimeSource;
 use crate::observability::Metrics;
+use crate::redis::CommandExecutor;
+use crate::simulator::VirtualTime;
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
 use tokio::time::{interval, Duration};
 use tracing::debug;
 
 const TTL_CHECK_INTERVAL_MS: u64 = 100;
+
+/// Wall-clock budget of a shard's active expire cycle per tick: a quarter of
+/// the default interval, as Redis gives its slow cycle 25% of each period
+pub(crate) const ACTIVE_EXPIRE_BUDGET: std::time::Duration =
+    std::time::Duration::from_millis(TTL_CHECK_INTERVAL_MS / 4);
 const SHUTDOWN_CHECK_INTERVAL_MS: u64 = 50;
 
+/// Keys reclaimed between two checks of the time budget (Redis checks every 16)
+const ACTIVE_EXPIRE_SLICE_KEYS: usize = 16;
+
+/// Run a shard's slow active expire cycle: slices of the executor's cycle
+/// until no due key is left or `budget` of wall-clock time is spent. A cycle
+/// stopped at the budget is counted in INFO and leaves the rest to the next
+/// commands and ticks. Returns the number of keys evicted.
+pub(crate) fn run_active_expire_cycle(
+    executor: &mut CommandExecutor,
+    current_time: VirtualTime,
+    budget: std::time::Duration,
+) -> usize {
+    let started = std::time::Instant::now();
+    let mut evicted = 0;
+    loop {
+        let slice = executor.active_expire_cycle(current_time, ACTIVE_EXPIRE_SLICE_KEYS);
+        evicted += slice;
+        if slice < ACTIVE_EXPIRE_SLICE_KEYS {
+            return evicted;
+        }
+        if started.elapsed() >= budget {
+            executor.record_expire_cycle_timeout();
+            return evicted;
+        }
+    }
+}
+
 /// Messages for controlling the TtlManagerActor
 #[derive(Debug)]
 pub enum TtlMessage {
     /// Trigger an immediate eviction check
     Tick,
+    /// Change the time between periodic checks (CONFIG SET hz)
+    SetInterval { interval_ms: u64 },
     /// Graceful shutdown
     Shutdown { response: oneshot::Sender<()> },
 }
@@ -45,6 +84,16 @@ impl TtlManagerHandle {
         let _ = self.tx.send(TtlMessage::Tick);
     }
 
+    /// Change the time between periodic checks
+    #[inline]
+    pub fn set_interval(&self, interval_ms: u64) {
+        debug_assert!(
+            interval_ms > 0,
+            "Precondition: TTL interval must be positive"
+        );
+        let _ = self.tx.send(TtlMessage::SetInterval { interval_ms });
+    }
+
     /// Graceful shutdown - waits for confirmation
     pub async fn shutdown(&self) {
         let (tx, rx) = oneshot::channel();
@@ -134,6 +183,11 @@ impl<T: TimeSource + Clone + Send + 'static> TtlManagerActor<T> {
                         Some(TtlMessage::Tick) => {
                             self.do_eviction().await;
                         }
+                        Some(TtlMessage::SetInterval { interval_ms }) => {
+                            debug!("TTL manager interval set to {}ms", interval_ms);
+                            self.interval_ms = interval_ms;
+                            tick_interval = interval(Duration::from_millis(interval_ms));
+                        }
                         Some(TtlMessage::Shutdown { response }) => {
                             debug!("TTL manager shutting down");
                             let _ = response.send(());
@@ -162,7 +216,11 @@ impl<T: TimeSource + Clone + Send + 'static> TtlManagerActor<T> {
 
     /// Perform eviction on all shards
     async fn do_eviction(&self) {
+        let started = std::time::Instant::now();
         let evicted = self.state.evict_expired_all_shards().await;
+        self.state
+            .latency()
+            .add_sample(EXPIRE_CYCLE, started.elapsed());
         if evicted > 0 {
             debug!("TTL manager evicted {} expired keys", evicted);
             self.metrics.record_ttl_eviction(evicted);
@@ -182,11 +240,85 @@ mod tests {
     use super::*;
     use crate::io::ProductionTimeSource;
     use crate::observability::DatadogConfig;
+    use crate::redis::{Command, RespValue, SDS};
 
+    #[allow(clippy::default_constructed_unit_structs)] // DatadogConfig has fields with `datadog`
     fn test_metrics() -> Arc<Metrics> {
         Arc::new(Metrics::new(&DatadogConfig::default()))
     }
 
+    fn info(executor: &mut CommandExecutor) -> String {
+        match executor.execute(&Command::Info) {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected INFO reply {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_active_expire_cycle_stops_at_time_budget() {
+        let mut executor = CommandExecutor::new();
+        for i in 0..100 {
+            executor.execute(&Command::setex(format!("k{}", i), 1, SDS::from_str("v")));
+        }
+
+        // A zero budget still makes progress, one slice per cycle
+        let now = VirtualTime::from_millis(1_000);
+        let first = run_active_expire_cycle(&mut executor, now, Duration::ZERO);
+        assert_eq!(first, ACTIVE_EXPIRE_SLICE_KEYS);
+        let mut total = first;
+        while total < 100 {
+            total += run_active_expire_cycle(&mut executor, now, Duration::ZERO);
+        }
+        assert_eq!(total, 100);
+
+        let stats = info(&mut executor);
+        assert!(stats.contains("expired_keys:100\r\n"));
+        assert!(stats.contains("expired_time_cap_reached_count:6\r\n"));
+
+        // A generous budget drains everything in one call
+        for i in 0..100 {
+            executor.execute(&Command::setex(format!("k{}", i), 1, SDS::from_str("v")));
+        }
+        let now = VirtualTime::from_millis(3_000);
+        assert_eq!(
+            run_active_expire_cycle(&mut executor, now, ACTIVE_EXPIRE_BUDGET),
+            100
+        );
+        assert!(info(&mut executor).contains("expired_time_cap_reached_count:6\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_server_info_reports_active_expiry() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(4);
+        for i in 0..20 {
+            state
+                .execute(&Command::Set {
+                    key: SDS::from(format!("k{}", i)),
+                    value: SDS::from_str("v"),
+                    ex: None,
+                    px: Some(1),
+                    nx: false,
+                    xx: false,
+                    get: false,
+                })
+                .await;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // Later SETs may already have reclaimed some keys; both count in INFO
+        while state.evict_expired_all_shards().await > 0 {}
+
+        let info = match state.execute(&Command::Info).await {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected INFO reply {:?}", other),
+        };
+        assert!(info.contains("expired_keys:20\r\n"), "{}", info);
+        assert!(
+            info.contains("expired_time_cap_reached_count:0\r\n"),
+            "{}",
+            info
+        );
+    }
+
     #[tokio::test]
     async fn test_ttl_manager_shutdown() {
         let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
@@ -234,6 +366,18 @@ mod tests {
         handle.shutdown().await;
     }
 
+    #[tokio::test]
+    async fn test_ttl_manager_set_interval() {
+        let state = ShardedActorState::<ProductionTimeSource>::with_shards(1);
+        let handle = TtlManagerActor::spawn(state, test_metrics());
+
+        handle.set_interval(10);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(handle.is_running());
+
+        handle.shutdown().await;
+    }
+
     #[tokio::test]
     async fn test_ttl_manager_multiple_handles() {
         let state = ShardedActorState::<ProductionTimeSource>::with_shards(2);
@@ -0,0 +1,456 @@
+//! Bit-level operations on string values (SETBIT, BITCOUNT, BITPOS, BITOP, BITFIELD)
+//!
+//! Bitmaps are plain `SDS` strings. Bit 0 is the most significant bit of the
+//! first byte, as in Redis, and writes past the end zero-pad the string.
+//! Counting and scanning work on whole 64-bit words where they can.
+
+use super::data::{SDS, SSO_MAX_LEN};
+
+/// Largest bit offset (Redis caps strings at 512MB)
+pub const BITMAP_MAX_OFFSET: u64 = (512 * 1024 * 1024 * 8) - 1;
+
+/// Unit of BITCOUNT/BITPOS ranges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitUnit {
+    #[default]
+    Byte,
+    Bit,
+}
+
+/// BITOP operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+/// BITFIELD integer type: `i1`..`i64` or `u1`..`u63`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BitFieldType {
+    pub signed: bool,
+    pub bits: u8,
+}
+
+/// BITFIELD OVERFLOW mode for the SET and INCRBY operations that follow it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BitFieldOverflow {
+    #[default]
+    Wrap,
+    Sat,
+    Fail,
+}
+
+/// One BITFIELD operation; offsets are in bits, `#N` already resolved
+#[derive(Debug, Clone, PartialEq)]
+pub enum BitFieldOp {
+    Get {
+        ty: BitFieldType,
+        offset: u64,
+    },
+    Set {
+        ty: BitFieldType,
+        offset: u64,
+        value: i64,
+    },
+    IncrBy {
+        ty: BitFieldType,
+        offset: u64,
+        increment: i64,
+    },
+    Overflow(BitFieldOverflow),
+}
+
+impl BitFieldType {
+    /// Parse `i8`, `u16`, ...
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || {
+            "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is."
+                .to_string()
+        };
+        let signed = match s.as_bytes().first() {
+            Some(b'i' | b'I') => true,
+            Some(b'u' | b'U') => false,
+            _ => return Err(invalid()),
+        };
+        let bits: u8 = s[1..].parse().map_err(|_| invalid())?;
+        let max = if signed { 64 } else { 63 };
+        if bits == 0 || bits > max {
+            return Err(invalid());
+        }
+        Ok(BitFieldType { signed, bits })
+    }
+
+    /// Parse an offset: plain bits, or `#N` for the N-th field of this type
+    pub fn parse_offset(&self, s: &str) -> Result<u64, String> {
+        let invalid = || "bit offset is not an integer or out of range".to_string();
+        let offset = match s.strip_prefix('#') {
+            Some(index) => index
+                .parse::<u64>()
+                .ok()
+                .and_then(|n| n.checked_mul(self.bits as u64)),
+            None => s.parse::<u64>().ok(),
+        }
+        .ok_or_else(invalid)?;
+        if offset + self.bits as u64 - 1 > BITMAP_MAX_OFFSET {
+            return Err(invalid());
+        }
+        Ok(offset)
+    }
+
+    fn min(&self) -> i128 {
+        if self.signed {
+            -(1i128 << (self.bits - 1))
+        } else {
+            0
+        }
+    }
+
+    fn max(&self) -> i128 {
+        if self.signed {
+            (1i128 << (self.bits - 1)) - 1
+        } else {
+            (1i128 << self.bits) - 1
+        }
+    }
+
+    /// Fit `value` into this type; None if it overflows in FAIL mode
+    pub fn apply_overflow(&self, value: i128, mode: BitFieldOverflow) -> Option<i64> {
+        let (min, max) = (self.min(), self.max());
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+        match mode {
+            BitFieldOverflow::Wrap => {
+                Some(((value - min).rem_euclid(1i128 << self.bits) + min) as i64)
+            }
+            BitFieldOverflow::Sat => Some(value.clamp(min, max) as i64),
+            BitFieldOverflow::Fail => None,
+        }
+    }
+}
+
+/// Resolve a Redis-style inclusive `[start, end]` range over `len` units
+/// (negative indexes count from the end); None if it is empty
+pub fn normalize_range(start: i64, end: i64, len: i64) -> Option<(u64, u64)> {
+    if start < 0 && end < 0 && start > end {
+        return None;
+    }
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let end = if end < 0 { (len + end).max(0) } else { end };
+    let end = end.min(len - 1);
+    if start > end {
+        return None;
+    }
+    Some((start as u64, end as u64))
+}
+
+/// Number of set bits in `bytes`
+pub fn popcount(bytes: &[u8]) -> u64 {
+    let mut chunks = bytes.chunks_exact(8);
+    let mut count: u64 = 0;
+    for chunk in chunks.by_ref() {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        count += u64::from_ne_bytes(word).count_ones() as u64;
+    }
+    count
+        + chunks
+            .remainder()
+            .iter()
+            .map(|b| b.count_ones() as u64)
+            .sum::<u64>()
+}
+
+/// Combine `sources` (missing keys are empty strings) for BITOP
+pub fn bitop(op: BitOperation, sources: &[&[u8]]) -> Vec<u8> {
+    debug_assert!(!sources.is_empty(), "Precondition: BITOP needs a source");
+    debug_assert!(
+        op != BitOperation::Not || sources.len() == 1,
+        "Precondition: BITOP NOT takes exactly one source"
+    );
+    let len = sources.iter().map(|s| s.len()).max().unwrap_or(0);
+    let byte = |source: &[u8], i: usize| source.get(i).copied().unwrap_or(0);
+    let result: Vec<u8> = (0..len)
+        .map(|i| match op {
+            BitOperation::Not => !byte(sources[0], i),
+            BitOperation::And => sources.iter().fold(0xff, |acc, s| acc & byte(s, i)),
+            BitOperation::Or => sources.iter().fold(0, |acc, s| acc | byte(s, i)),
+            BitOperation::Xor => sources.iter().fold(0, |acc, s| acc ^ byte(s, i)),
+        })
+        .collect();
+
+    // TigerStyle: Postconditions
+    debug_assert_eq!(
+        result.len(),
+        len,
+        "Postcondition: BITOP result has the longest length"
+    );
+    result
+}
+
+impl SDS {
+    /// Bit at `offset`; bits past the end read as 0
+    pub fn get_bit(&self, offset: u64) -> bool {
+        let byte = (offset / 8) as usize;
+        match self.as_bytes().get(byte) {
+            Some(b) => b & (0x80 >> (offset % 8)) != 0,
+            None => false,
+        }
+    }
+
+    /// Set the bit at `offset`, growing the string as needed; returns the old bit
+    pub fn set_bit(&mut self, offset: u64, on: bool) -> bool {
+        debug_assert!(
+            offset <= BITMAP_MAX_OFFSET,
+            "Precondition: bit offset out of range"
+        );
+        let byte = (offset / 8) as usize;
+        let mask = 0x80u8 >> (offset % 8);
+        let bytes = self.bytes_mut(byte + 1);
+        let old = bytes[byte] & mask != 0;
+        if on {
+            bytes[byte] |= mask;
+        } else {
+            bytes[byte] &= !mask;
+        }
+
+        // TigerStyle: Postconditions
+        debug_assert_eq!(
+            self.get_bit(offset),
+            on,
+            "Postcondition: bit must be updated"
+        );
+        old
+    }
+
+    /// BITCOUNT over an optional inclusive range
+    pub fn bit_count(&self, range: Option<(i64, i64, BitUnit)>) -> u64 {
+        let bytes = self.as_bytes();
+        let Some((start, end, unit)) = range else {
+            return popcount(bytes);
+        };
+        let total = match unit {
+            BitUnit::Byte => bytes.len() as i64,
+            BitUnit::Bit => bytes.len() as i64 * 8,
+        };
+        let Some((start, end)) = normalize_range(start, end, total) else {
+            return 0;
+        };
+        match unit {
+            BitUnit::Byte => popcount(&bytes[start as usize..=end as usize]),
+            BitUnit::Bit => {
+                let (first, last) = ((start / 8) as usize, (end / 8) as usize);
+                let mut count = popcount(&bytes[first..=last]);
+                // Drop the bits of the edge bytes that fall outside the range
+                let head = bytes[first] & !(0xffu8 >> (start % 8));
+                let tail = bytes[last] & (0xffu8 >> (end % 8 + 1));
+                count -= (head.count_ones() + tail.count_ones()) as u64;
+                count
+            }
+        }
+    }
+
+    /// BITPOS: first bit equal to `bit` in the range, or -1. Looking for a
+    /// clear bit without an explicit end, the string counts as zero-padded.
+    pub fn bit_pos(&self, bit: bool, start: i64, end: Option<i64>, unit: BitUnit) -> i64 {
+        let bytes = self.as_bytes();
+        let total = match unit {
+            BitUnit::Byte => bytes.len() as i64,
+            BitUnit::Bit => bytes.len() as i64 * 8,
+        };
+        let Some((start, last)) = normalize_range(start, end.unwrap_or(-1), total) else {
+            return -1;
+        };
+        let (first_bit, last_bit) = match unit {
+            BitUnit::Byte => (start * 8, last * 8 + 7),
+            BitUnit::Bit => (start, last),
+        };
+        match find_bit(bytes, bit, first_bit, last_bit) {
+            Some(pos) => pos as i64,
+            None if !bit && end.is_none() => last_bit as i64 + 1,
+            None => -1,
+        }
+    }
+
+    /// Read an integer field
+    pub fn bitfield_get(&self, ty: BitFieldType, offset: u64) -> i64 {
+        let mut raw: u64 = 0;
+        for i in 0..ty.bits as u64 {
+            raw = (raw << 1) | self.get_bit(offset + i) as u64;
+        }
+        if ty.signed && ty.bits < 64 && raw & (1 << (ty.bits - 1)) != 0 {
+            // Sign-extend
+            raw |= u64::MAX << ty.bits;
+        }
+        raw as i64
+    }
+
+    /// Write an integer field (the value must already fit the type)
+    pub fn bitfield_set(&mut self, ty: BitFieldType, offset: u64, value: i64) {
+        let bytes = self.bytes_mut(((offset + ty.bits as u64 - 1) / 8 + 1) as usize);
+        let raw = value as u64;
+        for i in 0..ty.bits as u64 {
+            let on = raw & (1 << (ty.bits as u64 - 1 - i)) != 0;
+            let pos = offset + i;
+            let mask = 0x80u8 >> (pos % 8);
+            if on {
+                bytes[(pos / 8) as usize] |= mask;
+            } else {
+                bytes[(pos / 8) as usize] &= !mask;
+            }
+        }
+
+        // TigerStyle: Postconditions
+        debug_assert_eq!(
+            self.bitfield_get(ty, offset),
+            value,
+            "Postcondition: field must read back as written"
+        );
+    }
+
+    /// Zero-pad the string to at least `min_len` bytes
+    pub fn grow_to(&mut self, min_len: usize) {
+        self.bytes_mut(min_len);
+    }
+
+    /// Mutable bytes, zero-padded to at least `min_len`
+    pub(crate) fn bytes_mut(&mut self, min_len: usize) -> &mut [u8] {
+        if let SDS::Inline { len, data } = self {
+            if min_len > SSO_MAX_LEN {
+                let mut heap = Vec::with_capacity(min_len);
+                heap.extend_from_slice(&data[..*len as usize]);
+                *self = SDS::Heap(heap);
+            }
+        }
+        match self {
+            SDS::Inline { len, data } => {
+                let current = *len as usize;
+                if current < min_len {
+                    data[current..min_len].fill(0);
+                    *len = min_len as u8;
+                }
+                &mut data[..*len as usize]
+            }
+            SDS::Heap(data) => {
+                if data.len() < min_len {
+                    data.resize(min_len, 0);
+                }
+                data
+            }
+        }
+    }
+}
+
+/// First bit equal to `bit` in `[first, last]`, skipping whole words
+fn find_bit(bytes: &[u8], bit: bool, first: u64, last: u64) -> Option<u64> {
+    let skip_byte = if bit { 0x00 } else { 0xff };
+    let mut pos = first;
+    while pos <= last {
+        let index = (pos / 8) as usize;
+        if pos % 8 == 0 && pos + 63 <= last && index + 8 <= bytes.len() {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[index..index + 8]);
+            if word == [skip_byte; 8] {
+                pos += 64;
+                continue;
+            }
+        }
+        if pos % 8 == 0 && pos + 7 <= last && bytes[index] == skip_byte {
+            pos += 8;
+            continue;
+        }
+        if (bytes[index] & (0x80 >> (pos % 8)) != 0) == bit {
+            return Some(pos);
+        }
+        pos += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn i(bits: u8) -> BitFieldType {
+        BitFieldType { signed: true, bits }
+    }
+
+    fn u(bits: u8) -> BitFieldType {
+        BitFieldType {
+            signed: false,
+            bits,
+        }
+    }
+
+    #[test]
+    fn test_set_bit_grows_inline_and_heap() {
+        let mut s = SDS::from_str("");
+        assert!(!s.set_bit(7, true));
+        assert_eq!(s.as_bytes(), &[0x01]);
+        assert!(s.set_bit(7, false));
+        assert!(!s.set_bit(8 * 40, true));
+        assert_eq!(s.len(), 41);
+        assert!(s.get_bit(8 * 40));
+        assert!(!s.get_bit(1_000_000));
+    }
+
+    #[test]
+    fn test_bit_count_ranges() {
+        let s = SDS::from_str("foobar");
+        assert_eq!(s.bit_count(None), 26);
+        assert_eq!(s.bit_count(Some((0, 0, BitUnit::Byte))), 4);
+        assert_eq!(s.bit_count(Some((1, 1, BitUnit::Byte))), 6);
+        assert_eq!(s.bit_count(Some((5, 30, BitUnit::Bit))), 17);
+        assert_eq!(s.bit_count(Some((-2, -1, BitUnit::Byte))), 7);
+        assert_eq!(s.bit_count(Some((-1, -2, BitUnit::Byte))), 0);
+        assert_eq!(popcount(&[0xff; 19]), 152);
+    }
+
+    #[test]
+    fn test_bit_pos() {
+        let s = SDS::new(vec![0xff, 0xf0, 0x00]);
+        assert_eq!(s.bit_pos(false, 0, None, BitUnit::Byte), 12);
+        assert_eq!(s.bit_pos(true, 2, None, BitUnit::Byte), -1);
+        assert_eq!(s.bit_pos(true, 7, Some(15), BitUnit::Bit), 7);
+
+        let ones = SDS::new(vec![0xff; 20]);
+        // Zero-padded on the right unless an explicit end is given
+        assert_eq!(ones.bit_pos(false, 0, None, BitUnit::Byte), 160);
+        assert_eq!(ones.bit_pos(false, 0, Some(-1), BitUnit::Byte), -1);
+    }
+
+    #[test]
+    fn test_bitop_and_bitfield_overflow() {
+        assert_eq!(
+            bitop(BitOperation::And, &[&[0xff, 0x0f], &[0xf0]]),
+            vec![0xf0, 0x00]
+        );
+        assert_eq!(bitop(BitOperation::Not, &[&[0x0f]]), vec![0xf0]);
+
+        let mut s = SDS::from_str("");
+        s.bitfield_set(i(8), 0, -100);
+        assert_eq!(s.bitfield_get(i(8), 0), -100);
+        assert_eq!(s.bitfield_get(u(8), 0), 156);
+        assert_eq!(
+            i(8).apply_overflow(-100 - 100, BitFieldOverflow::Wrap),
+            Some(56)
+        );
+        assert_eq!(u(2).apply_overflow(5, BitFieldOverflow::Sat), Some(3));
+        assert_eq!(u(2).apply_overflow(5, BitFieldOverflow::Fail), None);
+        assert_eq!(
+            i(64).apply_overflow(i64::MAX as i128 + 1, BitFieldOverflow::Wrap),
+            Some(i64::MIN)
+        );
+        assert_eq!(
+            BitFieldType::parse("u64"),
+            Err(BitFieldType::parse("x").unwrap_err())
+        );
+        assert_eq!(i(8).parse_offset("#2"), Ok(16));
+    }
+}
@@ -0,0 +1,131 @@
+//! Wait-queue registry for blocking commands
+//!
+//! BLPOP, BRPOP, BLMOVE, BZPOPMIN, BZPOPMAX and XREAD/XREADGROUP BLOCK park
+//! the client when none of their keys has data. The registry lives inside
+//! `CommandExecutor`, so every deadline is expressed in `VirtualTime` and DST
+//! can drive timeouts deterministically by advancing the clock.
+//!
+//! Waiters are kept in arrival order: when a key becomes non-empty, the client
+//! that blocked first is served first (FIFO fairness, as in Redis).
+
+use super::commands::Command;
+use super::data::SDS;
+use crate::simulator::VirtualTime;
+use ahash::AHashMap;
+use std::collections::VecDeque;
+
+/// Identifier chosen by the caller for a blocked client (connection, DST client, ...)
+pub type BlockedClientId = u64;
+
+/// A client parked on a blocking command
+#[derive(Debug, Clone)]
+pub struct BlockedClient {
+    pub id: BlockedClientId,
+    pub cmd: Command,
+    /// Database the client blocked in; only writes there can serve it
+    pub db: usize,
+    /// None = block forever (timeout 0)
+    pub deadline: Option<VirtualTime>,
+}
+
+/// Arrival-ordered registry of blocked clients
+#[derive(Debug, Default)]
+pub struct WaitQueue {
+    clients: VecDeque<BlockedClient>,
+    /// Number of waiters per key, for cheap "does this write wake anyone?" checks
+    key_refs: AHashMap<SDS, usize>,
+}
+
+impl WaitQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Park a client at the back of the queue
+    pub fn push(&mut self, client: BlockedClient) {
+        debug_assert!(
+            !self.clients.iter().any(|c| c.id == client.id),
+            "Precondition: client {} is already blocked",
+            client.id
+        );
+        for key in client.cmd.get_keys() {
+            *self.key_refs.entry(key).or_insert(0) += 1;
+        }
+        self.clients.push_back(client);
+        self.verify_invariants();
+    }
+
+    /// Remove a client (served, timed out, or disconnected)
+    pub fn remove(&mut self, id: BlockedClientId) -> Option<BlockedClient> {
+        let pos = self.clients.iter().position(|c| c.id == id)?;
+        let client = self.clients.remove(pos)?;
+        for key in client.cmd.get_keys() {
+            if let Some(refs) = self.key_refs.get_mut(&key) {
+                *refs -= 1;
+                if *refs == 0 {
+                    self.key_refs.remove(&key);
+                }
+            }
+        }
+        self.verify_invariants();
+        Some(client)
+    }
+
+    /// True if at least one client waits on `key`
+    #[inline]
+    pub fn is_waiting_on(&self, key: &[u8]) -> bool {
+        self.key_refs.contains_key(key)
+    }
+
+    /// Waiters in arrival order
+    pub fn iter(&self) -> impl Iterator<Item = &BlockedClient> {
+        self.clients.iter()
+    }
+
+    /// Earliest deadline among blocked clients
+    pub fn next_deadline(&self) -> Option<VirtualTime> {
+        self.clients.iter().filter_map(|c| c.deadline).min()
+    }
+
+    /// Remove and return every client whose deadline is at or before `now`
+    pub fn take_expired(&mut self, now: VirtualTime) -> Vec<BlockedClient> {
+        let expired: Vec<BlockedClientId> = self
+            .clients
+            .iter()
+            .filter(|c| c.deadline.is_some_and(|d| d <= now))
+            .map(|c| c.id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.remove(id))
+            .collect()
+    }
+
+    #[cfg(debug_assertions)]
+    fn verify_invariants(&self) {
+        let total_refs: usize = self.key_refs.values().sum();
+        let expected: usize = self.clients.iter().map(|c| c.cmd.get_keys().len()).sum();
+        debug_assert_eq!(
+            total_refs, expected,
+            "Invariant violated: key refcounts must match blocked clients"
+        );
+        debug_assert!(
+            self.key_refs.values().all(|&n| n > 0),
+            "Invariant violated: zero refcount left in key_refs"
+        );
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    fn verify_invariants(&self) {}
+}
@@ -1,7 +1,8 @@
 use ahash::{AHashMap, AHashSet};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::ops::Range;
 
 // ============================================================================
 // Skip List Implementation for Sorted Sets
@@ -131,6 +132,13 @@ impl SkipList {
             .then_with(|| member1.cmp(member2))
     }
 
+    /// Scores within epsilon are the same score; `==` catches the infinities,
+    /// whose difference is NaN
+    #[inline]
+    fn same_score(a: f64, b: f64) -> bool {
+        a == b || (a - b).abs() < f64::EPSILON
+    }
+
     /// Insert a new element. Returns true if new element, false if updated.
     pub fn insert(&mut self, member: String, score: f64) -> bool {
         let mut update = [0usize; SKIPLIST_MAXLEVEL];
@@ -164,7 +172,7 @@ impl SkipList {
             let fwd_node = self.nodes[fwd].as_ref().unwrap();
             if fwd_node.member == member {
                 // Update score - need to reposition if score changed
-                if (fwd_node.score - score).abs() > f64::EPSILON {
+                if !Self::same_score(fwd_node.score, score) {
                     // Remove and re-insert with new score
                     self.delete_node(fwd, &update);
                     self.insert_internal(member, score, &mut update, &mut rank);
@@ -357,7 +365,7 @@ impl SkipList {
         let node = self.nodes[x].as_ref().unwrap();
         if let Some(fwd) = node.levels[0].forward {
             let fwd_node = self.nodes[fwd].as_ref().unwrap();
-            if fwd_node.member == member && (fwd_node.score - score).abs() < f64::EPSILON {
+            if fwd_node.member == member && Self::same_score(fwd_node.score, score) {
                 self.delete_node(fwd, &update);
                 return true;
             }
@@ -393,7 +401,7 @@ impl SkipList {
         let node = self.nodes[x].as_ref().unwrap();
         if let Some(fwd) = node.levels[0].forward {
             let fwd_node = self.nodes[fwd].as_ref().unwrap();
-            if fwd_node.member == member && (fwd_node.score - score).abs() < f64::EPSILON {
+            if fwd_node.member == member && Self::same_score(fwd_node.score, score) {
                 return Some(rank);
             }
         }
@@ -510,6 +518,74 @@ impl SkipList {
             current: header.levels[0].forward,
         }
     }
+
+    /// Iterate in order starting at the first element with score >= `min`.
+    /// O(log n) to find the start, then O(1) per element.
+    pub fn iter_from_score(&self, min: f64) -> SkipListIter<'_> {
+        let mut x = 0;
+
+        for i in (0..self.level).rev() {
+            loop {
+                let node = self.nodes[x].as_ref().unwrap();
+                if let Some(fwd) = node.levels[i].forward {
+                    if self.nodes[fwd].as_ref().unwrap().score < min {
+                        x = fwd;
+                        continue;
+                    }
+                }
+                break;
+            }
+        }
+
+        let current = self.nodes[x].as_ref().unwrap().levels[0].forward;
+
+        // TigerStyle: Postconditions
+        debug_assert!(
+            current.map_or(true, |idx| self.nodes[idx].as_ref().unwrap().score >= min),
+            "Postcondition violated: iter_from_score started below min"
+        );
+
+        SkipListIter {
+            skiplist: self,
+            current,
+        }
+    }
+
+    /// Number of leading elements for which `pred(score, member)` holds.
+    /// `pred` must hold for a prefix of the order and fail after it, as range
+    /// bounds do. O(log n): whole spans are skipped on the upper levels.
+    pub fn count_while(&self, pred: impl Fn(f64, &str) -> bool) -> usize {
+        let mut rank = 0;
+        let mut x = 0;
+
+        for i in (0..self.level).rev() {
+            loop {
+                let node = self.nodes[x].as_ref().unwrap();
+                if let Some(fwd) = node.levels[i].forward {
+                    let fwd_node = self.nodes[fwd].as_ref().unwrap();
+                    if pred(fwd_node.score, &fwd_node.member) {
+                        rank += node.levels[i].span;
+                        x = fwd;
+                        continue;
+                    }
+                }
+                break;
+            }
+        }
+
+        // TigerStyle: Postconditions
+        debug_assert!(
+            rank <= self.length,
+            "Postcondition violated: count_while rank exceeds length"
+        );
+        debug_assert!(
+            self.get_by_rank(rank)
+                .map_or(true, |(member, score)| !pred(score, member)),
+            "Postcondition violated: count_while stopped before the predicate failed"
+        );
+
+        rank
+    }
 }
 
 pub struct SkipListIter<'a> {
@@ -532,7 +608,10 @@ impl<'a> Iterator for SkipListIter<'a> {
 }
 
 /// Small String Optimization threshold - strings up to this size are stored inline
-const SSO_MAX_LEN: usize = 23;
+pub(crate) const SSO_MAX_LEN: usize = 23;
+
+/// Largest string value, Redis's default proto-max-bulk-len (512MB)
+pub(crate) const STRING_MAX_LEN: u64 = 512 * 1024 * 1024;
 
 /// Simple Dynamic String with Small String Optimization (SSO)
 ///
@@ -659,10 +738,6 @@ impl SDS {
         }
     }
 
-    pub fn to_string(&self) -> String {
-        String::from_utf8_lossy(self.as_bytes()).to_string()
-    }
-
     pub fn append(&mut self, other: &SDS) {
         // TigerStyle: Preconditions - capture state for postcondition check
         #[cfg(debug_assertions)]
@@ -706,6 +781,63 @@ impl SDS {
 
         self.verify_invariants();
     }
+
+    /// Parse a numeric argument. Bytes that are not UTF-8 never parse.
+    pub fn parse<F: std::str::FromStr>(&self) -> Result<F, F::Err> {
+        String::from_utf8_lossy(self.as_bytes()).parse()
+    }
+
+    /// Uppercased text of an option argument (lossy, options are ASCII)
+    pub fn to_uppercase(&self) -> String {
+        String::from_utf8_lossy(self.as_bytes()).to_uppercase()
+    }
+
+    /// Case-insensitive comparison with an option name
+    pub fn eq_ignore_ascii_case(&self, other: &str) -> bool {
+        self.as_bytes().eq_ignore_ascii_case(other.as_bytes())
+    }
+
+    /// GETRANGE - bytes in the inclusive range `[start, end]`; negative
+    /// indexes count from the end and out-of-range ones are clamped
+    pub fn get_range(&self, start: i64, end: i64) -> &[u8] {
+        let bytes = self.as_bytes();
+        let len = bytes.len() as i64;
+        if start < 0 && end < 0 && start > end {
+            return &[];
+        }
+        let start = if start < 0 { len + start } else { start }.max(0);
+        let end = if end < 0 { len + end } else { end }.max(0).min(len - 1);
+        if len == 0 || start > end {
+            return &[];
+        }
+        &bytes[start as usize..=end as usize]
+    }
+
+    /// SETRANGE - overwrite from `offset`, zero-padding any gap past the end.
+    /// Returns the new length.
+    pub fn set_range(&mut self, offset: usize, value: &[u8]) -> usize {
+        #[cfg(debug_assertions)]
+        let pre_len = self.len();
+
+        let end = offset + value.len();
+        self.bytes_mut(end)[offset..end].copy_from_slice(value);
+
+        // TigerStyle: Postconditions
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(
+            self.len(),
+            pre_len.max(end),
+            "Postcondition violated: len must cover the written range"
+        );
+        debug_assert_eq!(
+            &self.as_bytes()[offset..end],
+            value,
+            "Postcondition violated: range must read back as written"
+        );
+
+        self.verify_invariants();
+        self.len()
+    }
 }
 
 impl PartialEq for SDS {
@@ -722,6 +854,77 @@ impl std::hash::Hash for SDS {
     }
 }
 
+impl PartialEq<str> for SDS {
+    fn eq(&self, other: &str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<&str> for SDS {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialOrd for SDS {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SDS {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl std::ops::Deref for SDS {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+// Keys are SDS too: maps keyed by SDS can be probed with a `&[u8]`
+impl std::borrow::Borrow<[u8]> for SDS {
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// Lossy UTF-8, for logs and error messages; replies use `as_bytes`
+impl std::fmt::Display for SDS {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&String::from_utf8_lossy(self.as_bytes()))
+    }
+}
+
+impl From<&str> for SDS {
+    fn from(s: &str) -> Self {
+        SDS::from_str(s)
+    }
+}
+
+impl From<String> for SDS {
+    fn from(s: String) -> Self {
+        SDS::new(s.into_bytes())
+    }
+}
+
+impl From<&[u8]> for SDS {
+    fn from(bytes: &[u8]) -> Self {
+        SDS::new(bytes.to_vec())
+    }
+}
+
+impl From<Vec<u8>> for SDS {
+    fn from(bytes: Vec<u8>) -> Self {
+        SDS::new(bytes)
+    }
+}
+
 // Custom serialization to store as bytes (backwards compatible)
 impl Serialize for SDS {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -749,6 +952,7 @@ pub enum Value {
     Set(RedisSet),
     Hash(RedisHash),
     SortedSet(RedisSortedSet),
+    Stream(RedisStream),
     Null,
 }
 
@@ -787,6 +991,213 @@ impl Value {
             _ => None,
         }
     }
+
+    pub fn as_stream(&self) -> Option<&RedisStream> {
+        match self {
+            Value::Stream(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Estimated heap footprint in bytes, for maxmemory accounting.
+    ///
+    /// Like Redis `MEMORY USAGE`, collections are not walked: the size of a few
+    /// elements is averaged and scaled by the length, so the cost is O(1) in
+    /// the collection size.
+    pub fn memory_usage(&self) -> usize {
+        let base = std::mem::size_of::<Value>();
+        match self {
+            Value::String(s) => base + sds_heap_bytes(s),
+            Value::List(l) => {
+                base + sampled_bytes(
+                    l.len(),
+                    l.items
+                        .iter()
+                        .map(|item| std::mem::size_of::<SDS>() + sds_heap_bytes(item)),
+                )
+            }
+            Value::Set(s) => {
+                base + sampled_bytes(
+                    s.len(),
+                    s.members
+                        .iter()
+                        .map(|m| HASH_ENTRY_OVERHEAD + std::mem::size_of::<String>() + m.len()),
+                )
+            }
+            Value::Hash(h) => {
+                base + sampled_bytes(
+                    h.len(),
+                    h.fields.iter().map(|(field, value)| {
+                        HASH_ENTRY_OVERHEAD
+                            + std::mem::size_of::<String>()
+                            + field.len()
+                            + std::mem::size_of::<SDS>()
+                            + sds_heap_bytes(value)
+                    }),
+                )
+            }
+            Value::SortedSet(zs) => {
+                // Each member lives in the score map and in a skip list node
+                base + sampled_bytes(
+                    zs.len(),
+                    zs.members.keys().map(|m| {
+                        HASH_ENTRY_OVERHEAD
+                            + SKIPLIST_NODE_OVERHEAD
+                            + 2 * (std::mem::size_of::<String>() + m.len())
+                            + 2 * std::mem::size_of::<f64>()
+                    }),
+                )
+            }
+            Value::Stream(stream) => {
+                let entries = sampled_bytes(
+                    stream.entries.len(),
+                    stream.entries.values().map(|fields| {
+                        BTREE_ENTRY_OVERHEAD
+                            + std::mem::size_of::<StreamId>()
+                            + fields
+                                .iter()
+                                .map(|(f, v)| {
+                                    2 * std::mem::size_of::<SDS>()
+                                        + sds_heap_bytes(f)
+                                        + sds_heap_bytes(v)
+                                })
+                                .sum::<usize>()
+                    }),
+                );
+                let groups: usize = stream
+                    .groups
+                    .iter()
+                    .map(|(name, group)| {
+                        BTREE_ENTRY_OVERHEAD
+                            + name.len()
+                            + std::mem::size_of::<StreamConsumerGroup>()
+                            + group.pel.len()
+                                * (BTREE_ENTRY_OVERHEAD + std::mem::size_of::<StreamPendingEntry>())
+                            + group.consumers.len()
+                                * (BTREE_ENTRY_OVERHEAD + std::mem::size_of::<StreamConsumer>())
+                    })
+                    .sum();
+                base + entries + groups
+            }
+            Value::Null => base,
+        }
+    }
+
+    /// Encoding Redis would pick for this value under its default config, as
+    /// reported by OBJECT ENCODING
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(s) if integer_string(s) => "int",
+            Value::String(s) if s.len() <= EMBSTR_MAX_LEN => "embstr",
+            Value::String(_) => "raw",
+            Value::List(l) => {
+                let bytes: usize = l.items.iter().map(|item| item.len()).sum();
+                if bytes <= LIST_LISTPACK_MAX_BYTES {
+                    "listpack"
+                } else {
+                    "quicklist"
+                }
+            }
+            Value::Set(s) => {
+                let integers = s.members.iter().all(|m| integer_str(m));
+                if integers && s.len() <= INTSET_MAX_ENTRIES {
+                    "intset"
+                } else if fits_listpack(s.len(), s.members.iter().map(|m| m.len())) {
+                    "listpack"
+                } else {
+                    "hashtable"
+                }
+            }
+            Value::Hash(h) => {
+                let lens = h.fields.iter().flat_map(|(f, v)| [f.len(), v.len()]);
+                match (fits_listpack(h.len(), lens), h.field_deadlines.is_empty()) {
+                    (true, true) => "listpack",
+                    (true, false) => "listpackex",
+                    (false, _) => "hashtable",
+                }
+            }
+            Value::SortedSet(zs) => {
+                if fits_listpack(zs.len(), zs.members.keys().map(|m| m.len())) {
+                    "listpack"
+                } else {
+                    "skiplist"
+                }
+            }
+            Value::Stream(_) => "stream",
+            Value::Null => "none",
+        }
+    }
+
+    /// OBJECT REFCOUNT: small integer strings are shared objects in Redis
+    pub fn refcount(&self) -> i64 {
+        match self {
+            Value::String(s) if shared_integer(s) => i32::MAX as i64,
+            _ => 1,
+        }
+    }
+}
+
+/// Redis `OBJ_ENCODING_EMBSTR_SIZE_LIMIT`
+const EMBSTR_MAX_LEN: usize = 44;
+/// Default `list-max-listpack-size -2`: 8 KB per listpack
+const LIST_LISTPACK_MAX_BYTES: usize = 8 * 1024;
+/// Default `set-max-intset-entries`
+const INTSET_MAX_ENTRIES: usize = 512;
+/// Default `*-max-listpack-entries` of sets, hashes and sorted sets
+const LISTPACK_MAX_ENTRIES: usize = 128;
+/// Default `*-max-listpack-value`
+const LISTPACK_MAX_VALUE: usize = 64;
+/// Redis shares the integers 0..OBJ_SHARED_INTEGERS between keys
+const OBJ_SHARED_INTEGERS: i64 = 10_000;
+
+/// True if `s` is the canonical form of an i64 (what Redis stores as "int")
+fn integer_str(s: &str) -> bool {
+    s.parse::<i64>().is_ok_and(|n| n.to_string() == s)
+}
+
+fn integer_string(s: &SDS) -> bool {
+    std::str::from_utf8(s.as_bytes()).is_ok_and(integer_str)
+}
+
+fn shared_integer(s: &SDS) -> bool {
+    integer_string(s)
+        && std::str::from_utf8(s.as_bytes())
+            .ok()
+            .and_then(|s| s.parse::<i64>().ok())
+            .is_some_and(|n| (0..OBJ_SHARED_INTEGERS).contains(&n))
+}
+
+/// Small sets, hashes and sorted sets are stored as a listpack
+fn fits_listpack(len: usize, mut lens: impl Iterator<Item = usize>) -> bool {
+    len <= LISTPACK_MAX_ENTRIES && lens.all(|l| l <= LISTPACK_MAX_VALUE)
+}
+
+/// Elements sampled per collection by [`Value::memory_usage`] (Redis default)
+const MEMORY_USAGE_SAMPLES: usize = 5;
+/// Per-entry bookkeeping of a hash table (hash, control byte, padding)
+const HASH_ENTRY_OVERHEAD: usize = 16;
+/// Forward pointers and span of a skip list node
+const SKIPLIST_NODE_OVERHEAD: usize = 48;
+/// Amortized B-tree node overhead per entry
+const BTREE_ENTRY_OVERHEAD: usize = 16;
+
+/// Bytes an SDS occupies beyond its own struct (inline strings need none)
+fn sds_heap_bytes(s: &SDS) -> usize {
+    match s {
+        SDS::Inline { .. } => 0,
+        SDS::Heap(v) => v.capacity(),
+    }
+}
+
+/// Average size of the first few elements, scaled to `len` elements
+fn sampled_bytes(len: usize, sizes: impl Iterator<Item = usize>) -> usize {
+    let (sampled, total) = sizes
+        .take(MEMORY_USAGE_SAMPLES)
+        .fold((0usize, 0usize), |(n, sum), size| (n + 1, sum + size));
+    if sampled == 0 {
+        return 0;
+    }
+    total / sampled * len
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -1087,6 +1498,104 @@ impl RedisList {
 
         self.verify_invariants();
     }
+
+    /// LPOS - indexes of the elements equal to `element`. A positive `rank`
+    /// skips the first `rank - 1` matches from the head, a negative one scans
+    /// from the tail. At most `count` matches (0 = all) among the first
+    /// `maxlen` elements scanned (0 = all) are returned, in scan order.
+    pub fn positions(&self, element: &SDS, rank: i64, count: usize, maxlen: usize) -> Vec<usize> {
+        // TigerStyle: Precondition
+        debug_assert!(rank != 0, "Precondition: LPOS rank must not be zero");
+
+        let len = self.items.len();
+        let scanned = if maxlen == 0 { len } else { maxlen.min(len) };
+        let indexes: Box<dyn Iterator<Item = usize>> = if rank > 0 {
+            Box::new(0..scanned)
+        } else {
+            Box::new((len - scanned..len).rev())
+        };
+        let limit = if count == 0 { usize::MAX } else { count };
+        let result: Vec<usize> = indexes
+            .filter(|&i| self.items[i] == *element)
+            .skip((rank.unsigned_abs() - 1) as usize)
+            .take(limit)
+            .collect();
+
+        // TigerStyle: Postcondition
+        debug_assert!(
+            result.iter().all(|&i| self.items[i] == *element),
+            "Postcondition violated: every position must hold the element"
+        );
+        result
+    }
+
+    /// LINSERT - insert `value` before or after the first `pivot`. Returns the
+    /// new length, or None when the pivot is not in the list.
+    pub fn insert(&mut self, pivot: &SDS, value: SDS, before: bool) -> Option<usize> {
+        let at = self.items.iter().position(|item| item == pivot)?;
+        let index = if before { at } else { at + 1 };
+
+        #[cfg(debug_assertions)]
+        let pre_len = self.items.len();
+
+        self.items.insert(index, value);
+
+        // TigerStyle: Postconditions
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(
+            self.items.len(),
+            pre_len + 1,
+            "Postcondition violated: len must increase by 1 after insert"
+        );
+
+        self.verify_invariants();
+        Some(self.items.len())
+    }
+
+    /// LREM - remove up to `|count|` elements equal to `element`, from the
+    /// head when `count > 0`, from the tail when negative, all when 0.
+    /// Returns the number removed.
+    pub fn remove(&mut self, count: i64, element: &SDS) -> usize {
+        #[cfg(debug_assertions)]
+        let pre_len = self.items.len();
+
+        let limit = if count == 0 {
+            usize::MAX
+        } else {
+            count.unsigned_abs().min(usize::MAX as u64) as usize
+        };
+        let mut removed = 0;
+        if count >= 0 {
+            self.items.retain(|item| {
+                if removed < limit && item == element {
+                    removed += 1;
+                    false
+                } else {
+                    true
+                }
+            });
+        } else {
+            let mut index = self.items.len();
+            while index > 0 && removed < limit {
+                index -= 1;
+                if self.items[index] == *element {
+                    self.items.remove(index);
+                    removed += 1;
+                }
+            }
+        }
+
+        // TigerStyle: Postconditions
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(
+            self.items.len(),
+            pre_len - removed,
+            "Postcondition violated: len must decrease by the removed count"
+        );
+
+        self.verify_invariants();
+        removed
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -1287,17 +1796,89 @@ impl RedisSet {
         self.verify_invariants();
         result
     }
+
+    /// SUNION: members of any of `sets`
+    pub fn union(sets: &[&RedisSet]) -> RedisSet {
+        let mut members = AHashSet::new();
+        for set in sets {
+            members.extend(set.members.iter().cloned());
+        }
+        let result = RedisSet { members };
+
+        // TigerStyle: Postcondition
+        debug_assert!(
+            sets.iter().all(|set| set.len() <= result.len()),
+            "Postcondition violated: union must be at least as large as each input"
+        );
+        result
+    }
+
+    /// SINTER: members of every one of `sets`. Walks the smallest set, probing
+    /// the others. An empty `sets` has an empty intersection.
+    pub fn intersection(sets: &[&RedisSet]) -> RedisSet {
+        let members = Self::intersecting(sets).cloned().collect();
+        let result = RedisSet { members };
+
+        // TigerStyle: Postcondition
+        debug_assert!(
+            sets.iter().all(|set| result.len() <= set.len()),
+            "Postcondition violated: intersection must be no larger than any input"
+        );
+        result
+    }
+
+    /// SINTERCARD: size of the intersection, stopping early at `limit` (0 means no limit)
+    pub fn intersection_card(sets: &[&RedisSet], limit: usize) -> usize {
+        let limit = if limit == 0 { usize::MAX } else { limit };
+        Self::intersecting(sets).take(limit).count()
+    }
+
+    /// SDIFF: members of the first set found in none of the others
+    pub fn difference(sets: &[&RedisSet]) -> RedisSet {
+        let members = match sets.split_first() {
+            Some((first, others)) => first
+                .members
+                .iter()
+                .filter(|m| !others.iter().any(|set| set.members.contains(*m)))
+                .cloned()
+                .collect(),
+            None => AHashSet::new(),
+        };
+        let result = RedisSet { members };
+
+        // TigerStyle: Postcondition
+        debug_assert!(
+            sets.first()
+                .map_or(true, |first| result.len() <= first.len()),
+            "Postcondition violated: difference must be no larger than the first input"
+        );
+        result
+    }
+
+    /// Members of the smallest of `sets` that every other set contains too
+    fn intersecting<'a>(sets: &[&'a RedisSet]) -> impl Iterator<Item = &'a String> {
+        let smallest = sets.iter().copied().min_by_key(|set| set.len());
+        let sets: Vec<&'a RedisSet> = sets.to_vec();
+        smallest
+            .into_iter()
+            .flat_map(|set| set.members.iter())
+            .filter(move |m| sets.iter().all(|set| set.members.contains(*m)))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct RedisHash {
     fields: AHashMap<String, SDS>,
+    /// Per-field deadlines (HEXPIRE), in the executor's virtual milliseconds.
+    /// A field without an entry never expires.
+    field_deadlines: AHashMap<String, u64>,
 }
 
 impl RedisHash {
     pub fn new() -> Self {
         RedisHash {
             fields: AHashMap::new(),
+            field_deadlines: AHashMap::new(),
         }
     }
 
@@ -1319,7 +1900,16 @@ impl RedisHash {
             "Invariant violated: is_empty() must equal fields.is_empty()"
         );
 
-        // Invariant 3: All keys should be retrievable
+        // Invariant 3: Only existing fields can have a deadline
+        for field in self.field_deadlines.keys() {
+            debug_assert!(
+                self.fields.contains_key(field),
+                "Invariant violated: deadline for missing field '{}'",
+                field
+            );
+        }
+
+        // Invariant 4: All keys should be retrievable
         for (key, value) in &self.fields {
             let key_sds = SDS::from_str(key);
             debug_assert!(
@@ -1333,7 +1923,7 @@ impl RedisHash {
                 "Invariant violated: value for key '{}' must be consistent",
                 key
             );
-            // Invariant 4: Key converted to SDS and back should match
+            // Invariant 5: Key converted to SDS and back should match
             debug_assert_eq!(
                 key_sds.to_string(),
                 *key,
@@ -1358,6 +1948,8 @@ impl RedisHash {
         };
 
         self.fields.insert(field_str.clone(), value.clone());
+        // Like Redis, overwriting a field clears its TTL
+        self.field_deadlines.remove(&field_str);
 
         // TigerStyle: Postcondition - verify the set succeeded
         debug_assert!(
@@ -1393,6 +1985,7 @@ impl RedisHash {
         let existed = self.fields.contains_key(&field_str);
 
         let removed = self.fields.remove(&field_str).is_some();
+        self.field_deadlines.remove(&field_str);
 
         // TigerStyle: Postconditions
         debug_assert!(
@@ -1448,19 +2041,148 @@ impl RedisHash {
     pub fn iter(&self) -> impl Iterator<Item = (&String, &SDS)> {
         self.fields.iter()
     }
-}
 
-/// Redis Sorted Set using Skip List for O(log n) operations
-#[derive(Clone, Debug)]
-pub struct RedisSortedSet {
-    /// HashMap for O(1) score lookup by member
-    members: AHashMap<String, f64>,
-    /// Skip list for O(log n) sorted operations
-    skiplist: SkipList,
-}
+    /// Deadline of `field`, if it has a TTL
+    pub fn field_deadline(&self, field: &SDS) -> Option<u64> {
+        self.field_deadlines.get(&field.to_string()).copied()
+    }
 
-impl RedisSortedSet {
-    pub fn new() -> Self {
+    /// Set the deadline of an existing field. Returns false if there is no such field.
+    pub fn set_field_deadline(&mut self, field: &SDS, deadline_ms: u64) -> bool {
+        let field_str = field.to_string();
+        if !self.fields.contains_key(&field_str) {
+            return false;
+        }
+        self.field_deadlines.insert(field_str, deadline_ms);
+
+        debug_assert_eq!(
+            self.field_deadline(field),
+            Some(deadline_ms),
+            "Postcondition violated: deadline must be set"
+        );
+        self.verify_invariants();
+        true
+    }
+
+    /// Drop the TTL of `field`. Returns true if it had one.
+    pub fn persist_field(&mut self, field: &SDS) -> bool {
+        let removed = self.field_deadlines.remove(&field.to_string()).is_some();
+        self.verify_invariants();
+        removed
+    }
+
+    /// Earliest field deadline, for the executor's expiry index
+    pub fn next_field_deadline(&self) -> Option<u64> {
+        self.field_deadlines.values().min().copied()
+    }
+
+    /// Delete every field whose deadline is at or before `now_ms`.
+    /// Returns the number of fields removed.
+    pub fn remove_expired_fields(&mut self, now_ms: u64) -> usize {
+        let due: Vec<String> = self
+            .field_deadlines
+            .iter()
+            .filter(|(_, &deadline)| deadline <= now_ms)
+            .map(|(field, _)| field.clone())
+            .collect();
+        for field in &due {
+            self.fields.remove(field);
+            self.field_deadlines.remove(field);
+        }
+
+        // TigerStyle: Postcondition - no due field survives
+        debug_assert!(
+            self.next_field_deadline().is_none_or(|d| d > now_ms),
+            "Postcondition violated: expired fields must be removed"
+        );
+        self.verify_invariants();
+        due.len()
+    }
+}
+
+/// One end of a score range: `1.5`, `(1.5` (exclusive), `-inf` or `+inf`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (exclusive, value) = match s.strip_prefix('(') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        match value.parse::<f64>() {
+            Ok(value) if !value.is_nan() => Ok(ScoreBound { value, exclusive }),
+            _ => Err("ERR min or max is not a float".to_string()),
+        }
+    }
+
+    /// As a lower bound: `score` sorts before the range
+    fn below(&self, score: f64) -> bool {
+        score < self.value || (self.exclusive && score == self.value)
+    }
+
+    /// As an upper bound: `score` is not past the range
+    fn reaches(&self, score: f64) -> bool {
+        score < self.value || (!self.exclusive && score == self.value)
+    }
+}
+
+/// One end of a lexicographical range: `[a` (inclusive), `(a` (exclusive),
+/// `-` or `+`. Only meaningful when every member has the same score.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexBound {
+    NegInf,
+    PosInf,
+    Inclusive(String),
+    Exclusive(String),
+}
+
+impl LexBound {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.as_bytes().first() {
+            Some(b'-') if s.len() == 1 => Ok(LexBound::NegInf),
+            Some(b'+') if s.len() == 1 => Ok(LexBound::PosInf),
+            Some(b'[') => Ok(LexBound::Inclusive(s[1..].to_string())),
+            Some(b'(') => Ok(LexBound::Exclusive(s[1..].to_string())),
+            _ => Err("ERR min or max not valid string range item".to_string()),
+        }
+    }
+
+    /// As a lower bound: `member` sorts before the range
+    fn below(&self, member: &str) -> bool {
+        match self {
+            LexBound::NegInf => false,
+            LexBound::PosInf => true,
+            LexBound::Inclusive(bound) => member < bound.as_str(),
+            LexBound::Exclusive(bound) => member <= bound.as_str(),
+        }
+    }
+
+    /// As an upper bound: `member` is not past the range
+    fn reaches(&self, member: &str) -> bool {
+        match self {
+            LexBound::NegInf => false,
+            LexBound::PosInf => true,
+            LexBound::Inclusive(bound) => member <= bound.as_str(),
+            LexBound::Exclusive(bound) => member < bound.as_str(),
+        }
+    }
+}
+
+/// Redis Sorted Set using Skip List for O(log n) operations
+#[derive(Clone, Debug)]
+pub struct RedisSortedSet {
+    /// HashMap for O(1) score lookup by member
+    members: AHashMap<String, f64>,
+    /// Skip list for O(log n) sorted operations
+    skiplist: SkipList,
+}
+
+impl RedisSortedSet {
+    pub fn new() -> Self {
         RedisSortedSet {
             members: AHashMap::new(),
             skiplist: SkipList::new(),
@@ -1508,7 +2230,7 @@ impl RedisSortedSet {
         match self.members.entry(key) {
             Entry::Occupied(mut entry) => {
                 let old_score = *entry.get();
-                if (old_score - score).abs() < f64::EPSILON {
+                if SkipList::same_score(old_score, score) {
                     return false; // Score unchanged
                 }
                 // Update score
@@ -1563,219 +2285,1291 @@ impl RedisSortedSet {
             if let Some(score) = score {
                 self.skiplist.remove_with_score(&key, score);
             }
-        }
-
-        #[cfg(debug_assertions)]
-        {
-            debug_assert_eq!(removed, existed);
-            if existed {
-                debug_assert_eq!(self.members.len(), pre_len - 1);
+        }
+
+        #[cfg(debug_assertions)]
+        {
+            debug_assert_eq!(removed, existed);
+            if existed {
+                debug_assert_eq!(self.members.len(), pre_len - 1);
+            }
+            self.verify_invariants();
+        }
+
+        removed
+    }
+
+    /// Remove and return the member with the lowest score. O(log n)
+    pub fn pop_min(&mut self) -> Option<(SDS, f64)> {
+        let (member, score) = self.range(0, 0).into_iter().next()?;
+        self.remove(&member);
+        Some((member, score))
+    }
+
+    /// Remove and return the member with the highest score. O(log n)
+    pub fn pop_max(&mut self) -> Option<(SDS, f64)> {
+        let (member, score) = self.rev_range(0, 0).into_iter().next()?;
+        self.remove(&member);
+        Some((member, score))
+    }
+
+    /// Get score of member. O(1)
+    pub fn score(&self, member: &SDS) -> Option<f64> {
+        self.members.get(&member.to_string()).copied()
+    }
+
+    /// Get rank of member (0-indexed). O(log n)
+    pub fn rank(&self, member: &SDS) -> Option<usize> {
+        let key = member.to_string();
+        let score = self.members.get(&key)?;
+        self.skiplist.rank(&key, *score)
+    }
+
+    /// Get rank of member counting from the highest score. O(log n)
+    pub fn rev_rank(&self, member: &SDS) -> Option<usize> {
+        let rank = self.rank(member)?;
+        Some(self.len() - 1 - rank)
+    }
+
+    /// Add `delta` to the member's score (0 if absent) and return the new score
+    pub fn incr(&mut self, member: SDS, delta: f64) -> Result<f64, String> {
+        let score = self.score(&member).unwrap_or(0.0) + delta;
+        if score.is_nan() {
+            return Err("ERR resulting score is not a number (NaN)".to_string());
+        }
+        self.add(member.clone(), score);
+        // `add` ignores sub-epsilon changes; report the score actually stored
+        Ok(self.score(&member).unwrap_or(score))
+    }
+
+    /// Get member and score at rank (0-indexed). O(log n)
+    pub fn get_by_rank(&self, rank: usize) -> Option<(&str, f64)> {
+        self.skiplist.get_by_rank(rank)
+    }
+
+    /// Get range by rank [start, stop] (inclusive). O(log n + k)
+    pub fn range(&self, start: isize, stop: isize) -> Vec<(SDS, f64)> {
+        let len = self.skiplist.len() as isize;
+        if len == 0 {
+            return Vec::new();
+        }
+
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start.min(len)
+        };
+        let stop = if stop < 0 {
+            (len + stop).max(-1)
+        } else {
+            stop.min(len - 1)
+        };
+
+        if start > stop || start >= len {
+            return Vec::new();
+        }
+
+        self.skiplist
+            .range(start as usize, stop as usize)
+            .into_iter()
+            .map(|(m, s)| (SDS::from_str(m), s))
+            .collect()
+    }
+
+    /// Get range in reverse by rank. O(log n + k)
+    pub fn rev_range(&self, start: isize, stop: isize) -> Vec<(SDS, f64)> {
+        let len = self.skiplist.len() as isize;
+        if len == 0 {
+            return Vec::new();
+        }
+
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start.min(len)
+        };
+        let stop = if stop < 0 {
+            (len + stop).max(-1)
+        } else {
+            stop.min(len - 1)
+        };
+
+        if start > stop || start >= len {
+            return Vec::new();
+        }
+
+        self.skiplist
+            .rev_range(start as usize, stop as usize)
+            .into_iter()
+            .map(|(m, s)| (SDS::from_str(m), s))
+            .collect()
+    }
+
+    /// Ranks selected by inclusive `start`/`stop` indexes, negative ones
+    /// counting from the end. With `rev` the indexes count from the highest
+    /// score; the returned ranks are always in ascending order.
+    pub fn rank_range(&self, start: isize, stop: isize, rev: bool) -> Range<usize> {
+        let len = self.len() as isize;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+        if start > stop || start >= len {
+            return 0..0;
+        }
+        let (start, end) = (start as usize, stop as usize + 1);
+        if rev {
+            self.len() - end..self.len() - start
+        } else {
+            start..end
+        }
+    }
+
+    /// Ranks of the members with a score between `min` and `max`. O(log n)
+    pub fn score_range(&self, min: &ScoreBound, max: &ScoreBound) -> Range<usize> {
+        let start = self.skiplist.count_while(|score, _| min.below(score));
+        let end = self.skiplist.count_while(|score, _| max.reaches(score));
+        start..end.max(start)
+    }
+
+    /// Ranks of the members between `min` and `max` in byte order. O(log n)
+    pub fn lex_range(&self, min: &LexBound, max: &LexBound) -> Range<usize> {
+        let start = self.skiplist.count_while(|_, member| min.below(member));
+        let end = self.skiplist.count_while(|_, member| max.reaches(member));
+        start..end.max(start)
+    }
+
+    /// Members in `ranks`, skipping `offset` and keeping at most `count`.
+    /// With `rev` the walk starts at the highest rank. O(log n + k)
+    pub fn range_in_ranks(
+        &self,
+        ranks: Range<usize>,
+        rev: bool,
+        offset: usize,
+        count: Option<usize>,
+    ) -> Vec<(SDS, f64)> {
+        debug_assert!(
+            ranks.end <= self.len(),
+            "Precondition violated: ranks past the end of the set"
+        );
+        let (lo, hi) = if rev {
+            let hi = ranks.end.saturating_sub(offset).max(ranks.start);
+            let lo = count.map_or(ranks.start, |c| hi.saturating_sub(c).max(ranks.start));
+            (lo, hi)
+        } else {
+            let lo = ranks.start.saturating_add(offset).min(ranks.end);
+            let hi = count.map_or(ranks.end, |c| lo.saturating_add(c).min(ranks.end));
+            (lo, hi)
+        };
+        if lo >= hi {
+            return Vec::new();
+        }
+        let items = self
+            .skiplist
+            .range(lo, hi - 1)
+            .into_iter()
+            .map(|(m, s)| (SDS::from_str(m), s));
+        if rev {
+            items.rev().collect()
+        } else {
+            items.collect()
+        }
+    }
+
+    /// Remove every member in `ranks`, returning how many were removed. O(k log n)
+    pub fn remove_ranks(&mut self, ranks: Range<usize>) -> usize {
+        if ranks.is_empty() {
+            return 0;
+        }
+        #[cfg(debug_assertions)]
+        let pre_len = self.len();
+
+        let doomed: Vec<(String, f64)> = self
+            .skiplist
+            .range(ranks.start, ranks.end - 1)
+            .into_iter()
+            .map(|(m, s)| (m.to_string(), s))
+            .collect();
+        for (member, score) in &doomed {
+            self.members.remove(member);
+            self.skiplist.remove_with_score(member, *score);
+        }
+
+        #[cfg(debug_assertions)]
+        {
+            debug_assert_eq!(
+                self.len(),
+                pre_len - doomed.len(),
+                "Postcondition violated: len must decrease by removed count"
+            );
+            self.verify_invariants();
+        }
+
+        doomed.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Get the skiplist length (for DST invariant checking)
+    pub fn skiplist_len(&self) -> usize {
+        self.skiplist.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Iterate members with score in [min, max) in score order. O(log n + k)
+    pub fn iter_score_range(&self, min: f64, max: f64) -> impl Iterator<Item = (&str, f64)> {
+        self.skiplist
+            .iter_from_score(min)
+            .take_while(move |(_, score)| *score < max)
+    }
+
+    /// Check if the set is sorted. Always true for a correctly functioning skiplist.
+    pub fn is_sorted(&self) -> bool {
+        let mut prev_score = f64::NEG_INFINITY;
+        let mut prev_member = String::new();
+        for (member, score) in self.skiplist.iter() {
+            if score < prev_score || (score == prev_score && member < prev_member.as_str()) {
+                return false;
+            }
+            prev_score = score;
+            prev_member = member.to_string();
+        }
+        true
+    }
+
+    /// ZCOUNT - count elements in score range. O(log n)
+    pub fn count_in_range(&self, min: &str, max: &str) -> Result<usize, String> {
+        let min = ScoreBound::parse(min)?;
+        let max = ScoreBound::parse(max)?;
+        Ok(self.score_range(&min, &max).len())
+    }
+
+    /// ZRANGEBYSCORE - get elements by score range. O(log n + k)
+    pub fn range_by_score(
+        &self,
+        min: &str,
+        max: &str,
+        with_scores: bool,
+        limit: Option<(isize, usize)>,
+    ) -> Result<Vec<(String, Option<f64>)>, String> {
+        let min = ScoreBound::parse(min)?;
+        let max = ScoreBound::parse(max)?;
+
+        // A negative LIMIT offset selects nothing
+        let (offset, count) = match limit {
+            Some((offset, _)) if offset < 0 => return Ok(Vec::new()),
+            Some((offset, count)) => (offset as usize, Some(count)),
+            None => (0, None),
+        };
+        let results = self
+            .range_in_ranks(self.score_range(&min, &max), false, offset, count)
+            .into_iter()
+            .map(|(member, score)| (member.to_string(), with_scores.then_some(score)))
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Iterate over member-score pairs in sorted order (for ZSCAN). O(n)
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.skiplist.iter()
+    }
+}
+
+impl PartialEq for RedisSortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.members == other.members
+    }
+}
+
+// ============================================================================
+// Streams
+// ============================================================================
+//
+// Entries are kept in a BTreeMap keyed by StreamId, which gives the same
+// ordered-by-ID iteration Redis gets from its radix tree: range scans,
+// reverse scans and trimming from the head are all O(log n + k).
+
+/// Stream entry ID (`<ms>-<seq>`), ordered by milliseconds then sequence
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        StreamId { ms, seq }
+    }
+
+    /// Parse `ms-seq` or `ms`. A missing sequence part becomes `missing_seq`.
+    pub fn parse(s: &str, missing_seq: u64) -> Option<StreamId> {
+        match s.split_once('-') {
+            Some((ms, seq)) => Some(StreamId {
+                ms: ms.parse().ok()?,
+                seq: seq.parse().ok()?,
+            }),
+            None => Some(StreamId {
+                ms: s.parse().ok()?,
+                seq: missing_seq,
+            }),
+        }
+    }
+
+    /// Parse the start of an XRANGE-style interval: `-`, `(id` or `id`
+    pub fn parse_range_start(s: &str) -> Result<StreamId, String> {
+        if s == "-" {
+            return Ok(StreamId::MIN);
+        }
+        if let Some(rest) = s.strip_prefix('(') {
+            let id = StreamId::parse(rest, 0).ok_or_else(Self::invalid_id_error)?;
+            return id
+                .next()
+                .ok_or_else(|| "ERR invalid start ID for the interval".to_string());
+        }
+        StreamId::parse(s, 0).ok_or_else(Self::invalid_id_error)
+    }
+
+    /// Parse the end of an XRANGE-style interval: `+`, `(id` or `id`
+    pub fn parse_range_end(s: &str) -> Result<StreamId, String> {
+        if s == "+" {
+            return Ok(StreamId::MAX);
+        }
+        if let Some(rest) = s.strip_prefix('(') {
+            let id = StreamId::parse(rest, u64::MAX).ok_or_else(Self::invalid_id_error)?;
+            return id
+                .prev()
+                .ok_or_else(|| "ERR invalid end ID for the interval".to_string());
+        }
+        StreamId::parse(s, u64::MAX).ok_or_else(Self::invalid_id_error)
+    }
+
+    /// Parse a strict ID argument (XDEL, XACK, XCLAIM, XGROUP ...)
+    pub fn parse_strict(s: &str) -> Result<StreamId, String> {
+        StreamId::parse(s, 0).ok_or_else(Self::invalid_id_error)
+    }
+
+    pub fn invalid_id_error() -> String {
+        "ERR Invalid stream ID specified as stream command argument".to_string()
+    }
+
+    /// Smallest ID strictly greater than this one
+    pub fn next(&self) -> Option<StreamId> {
+        if self.seq < u64::MAX {
+            Some(StreamId::new(self.ms, self.seq + 1))
+        } else if self.ms < u64::MAX {
+            Some(StreamId::new(self.ms + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    /// Largest ID strictly smaller than this one
+    pub fn prev(&self) -> Option<StreamId> {
+        if self.seq > 0 {
+            Some(StreamId::new(self.ms, self.seq - 1))
+        } else if self.ms > 0 {
+            Some(StreamId::new(self.ms - 1, u64::MAX))
+        } else {
+            None
+        }
+    }
+}
+
+impl std::fmt::Display for StreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// Field-value pairs of a single stream entry
+pub type StreamFields = Vec<(SDS, SDS)>;
+
+/// Pending entry in a consumer group (delivered but not yet acknowledged)
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamPendingEntry {
+    pub consumer: String,
+    pub delivery_time_ms: u64,
+    pub delivery_count: u64,
+}
+
+/// Consumer inside a consumer group
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamConsumer {
+    pub seen_time_ms: u64,
+    /// IDs owned by this consumer; always a subset of the group PEL
+    pub pending: BTreeSet<StreamId>,
+}
+
+impl StreamConsumer {
+    fn new(now_ms: u64) -> Self {
+        StreamConsumer {
+            seen_time_ms: now_ms,
+            pending: BTreeSet::new(),
+        }
+    }
+}
+
+/// Consumer group state: last delivered ID plus the pending entries list (PEL)
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamConsumerGroup {
+    pub last_delivered_id: StreamId,
+    pel: BTreeMap<StreamId, StreamPendingEntry>,
+    consumers: BTreeMap<String, StreamConsumer>,
+}
+
+impl StreamConsumerGroup {
+    fn new(last_delivered_id: StreamId) -> Self {
+        StreamConsumerGroup {
+            last_delivered_id,
+            pel: BTreeMap::new(),
+            consumers: BTreeMap::new(),
+        }
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pel.len()
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = (&StreamId, &StreamPendingEntry)> {
+        self.pel.iter()
+    }
+
+    pub fn consumers(&self) -> impl Iterator<Item = (&String, &StreamConsumer)> {
+        self.consumers.iter()
+    }
+
+    fn consumer_mut(&mut self, name: &str, now_ms: u64) -> &mut StreamConsumer {
+        let consumer = self
+            .consumers
+            .entry(name.to_string())
+            .or_insert_with(|| StreamConsumer::new(now_ms));
+        consumer.seen_time_ms = now_ms;
+        consumer
+    }
+
+    /// Assign `id` to `consumer`, moving it out of any previous owner's list
+    fn assign(&mut self, id: StreamId, consumer: &str, delivery_time_ms: u64, delivery_count: u64) {
+        if let Some(prev) = self.pel.get(&id) {
+            if prev.consumer != consumer {
+                let prev_owner = prev.consumer.clone();
+                if let Some(c) = self.consumers.get_mut(&prev_owner) {
+                    c.pending.remove(&id);
+                }
+            }
+        }
+        self.pel.insert(
+            id,
+            StreamPendingEntry {
+                consumer: consumer.to_string(),
+                delivery_time_ms,
+                delivery_count,
+            },
+        );
+        self.consumers
+            .entry(consumer.to_string())
+            .or_insert_with(|| StreamConsumer::new(delivery_time_ms))
+            .pending
+            .insert(id);
+    }
+
+    /// Remove `id` from the PEL. Returns true if it was pending.
+    fn unassign(&mut self, id: &StreamId) -> bool {
+        match self.pel.remove(id) {
+            Some(entry) => {
+                if let Some(c) = self.consumers.get_mut(&entry.consumer) {
+                    c.pending.remove(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Options for XCLAIM
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamClaimOptions {
+    /// IDLE ms: set the idle time of claimed entries
+    pub idle_ms: Option<u64>,
+    /// TIME ms-unix-time: set the delivery time of claimed entries
+    pub time_ms: Option<u64>,
+    /// RETRYCOUNT n: set the delivery counter
+    pub retry_count: Option<u64>,
+    /// FORCE: create PEL entries for IDs that exist in the stream but are not pending
+    pub force: bool,
+    /// JUSTID: return IDs only and do not increment the delivery counter
+    pub just_id: bool,
+    /// LASTID id: advance the group's last delivered ID
+    pub last_id: Option<StreamId>,
+}
+
+/// Result of XAUTOCLAIM: (next cursor, claimed entries, IDs no longer in the stream)
+pub type StreamAutoClaim = (StreamId, Vec<(StreamId, StreamFields)>, Vec<StreamId>);
+
+/// Pending entry as reported by XPENDING's extended form
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamPendingInfo {
+    pub id: StreamId,
+    pub consumer: String,
+    pub idle_ms: u64,
+    pub delivery_count: u64,
+}
+
+/// Redis Stream: append-only log of field-value entries with consumer groups
+#[derive(Clone, Debug, PartialEq)]
+pub struct RedisStream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    /// Last ID ever added; never goes backwards, even after XDEL/XTRIM
+    last_id: StreamId,
+    /// Number of entries ever added (monotonic)
+    entries_added: u64,
+    groups: BTreeMap<String, StreamConsumerGroup>,
+}
+
+impl RedisStream {
+    pub fn new() -> Self {
+        RedisStream {
+            entries: BTreeMap::new(),
+            last_id: StreamId::MIN,
+            entries_added: 0,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    /// VOPR: Verify all invariants hold for this stream
+    /// Called in debug builds after every mutation
+    #[cfg(debug_assertions)]
+    fn verify_invariants(&self) {
+        // Invariant 1: last_id is at least the largest entry ID
+        if let Some((max_id, _)) = self.entries.iter().next_back() {
+            debug_assert!(
+                *max_id <= self.last_id,
+                "Invariant violated: entry {} is beyond last_id {}",
+                max_id,
+                self.last_id
+            );
+        }
+
+        // Invariant 2: can't hold more entries than were ever added
+        debug_assert!(
+            self.entries.len() as u64 <= self.entries_added,
+            "Invariant violated: len {} > entries_added {}",
+            self.entries.len(),
+            self.entries_added
+        );
+
+        // Invariant 3: every entry has at least one field-value pair
+        for (id, fields) in &self.entries {
+            debug_assert!(
+                !fields.is_empty(),
+                "Invariant violated: entry {} has no fields",
+                id
+            );
+        }
+
+        // Invariant 4: group PEL and consumer pending lists agree
+        for (name, group) in &self.groups {
+            let mut owned = 0usize;
+            for (consumer_name, consumer) in &group.consumers {
+                for id in &consumer.pending {
+                    debug_assert_eq!(
+                        group.pel.get(id).map(|p| p.consumer.as_str()),
+                        Some(consumer_name.as_str()),
+                        "Invariant violated: group '{}' consumer '{}' owns {} not in PEL",
+                        name,
+                        consumer_name,
+                        id
+                    );
+                }
+                owned += consumer.pending.len();
+            }
+            debug_assert_eq!(
+                owned,
+                group.pel.len(),
+                "Invariant violated: group '{}' PEL size must equal consumer-owned IDs",
+                name
+            );
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    fn verify_invariants(&self) {}
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn get(&self, id: &StreamId) -> Option<&StreamFields> {
+        self.entries.get(id)
+    }
+
+    /// Resolve an XADD ID argument (`*`, `ms-*`, `ms` or `ms-seq`) against the top item
+    pub fn next_id(&self, spec: &str, now_ms: u64) -> Result<StreamId, String> {
+        const TOO_SMALL: &str =
+            "ERR The ID specified in XADD is equal or smaller than the target stream top item";
+
+        if spec == "*" {
+            if self.last_id == StreamId::MAX {
+                return Err(
+                    "ERR The stream has exhausted the last possible ID, unable to add more items"
+                        .to_string(),
+                );
+            }
+            if now_ms > self.last_id.ms {
+                return Ok(StreamId::new(now_ms, 0));
+            }
+            // Clock didn't advance (or went backwards): keep IDs monotonic
+            return self.last_id.next().ok_or_else(|| TOO_SMALL.to_string());
+        }
+
+        let id = if let Some(ms) = spec.strip_suffix("-*") {
+            let ms: u64 = ms.parse().map_err(|_| StreamId::invalid_id_error())?;
+            if ms < self.last_id.ms {
+                return Err(TOO_SMALL.to_string());
+            }
+            if ms == self.last_id.ms && self.entries_added > 0 {
+                if self.last_id.seq == u64::MAX {
+                    return Err(TOO_SMALL.to_string());
+                }
+                StreamId::new(ms, self.last_id.seq + 1)
+            } else if ms == 0 {
+                // 0-0 is never a valid entry ID
+                StreamId::new(0, 1)
+            } else {
+                StreamId::new(ms, 0)
+            }
+        } else {
+            StreamId::parse(spec, 0).ok_or_else(StreamId::invalid_id_error)?
+        };
+
+        if id == StreamId::MIN {
+            return Err("ERR The ID specified in XADD must be greater than 0-0".to_string());
+        }
+        if id <= self.last_id {
+            return Err(TOO_SMALL.to_string());
+        }
+        Ok(id)
+    }
+
+    /// Append an entry. The ID must come from `next_id`.
+    pub fn add(&mut self, id: StreamId, fields: StreamFields) {
+        // TigerStyle: Preconditions
+        debug_assert!(id > self.last_id, "Precondition: ID must be monotonic");
+        debug_assert!(!fields.is_empty(), "Precondition: entry must have fields");
+
+        #[cfg(debug_assertions)]
+        let pre_len = self.entries.len();
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added = self.entries_added.saturating_add(1);
+
+        // TigerStyle: Postconditions
+        #[cfg(debug_assertions)]
+        debug_assert_eq!(
+            self.entries.len(),
+            pre_len + 1,
+            "Postcondition violated: len must grow by one after add"
+        );
+
+        self.verify_invariants();
+    }
+
+    /// Entries with start <= id <= end in ascending order
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+    ) -> Vec<(StreamId, &StreamFields)> {
+        if start > end {
+            return Vec::new();
+        }
+        self.entries
+            .range(start..=end)
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields))
+            .collect()
+    }
+
+    /// Entries with start <= id <= end in descending order
+    pub fn rev_range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+    ) -> Vec<(StreamId, &StreamFields)> {
+        if start > end {
+            return Vec::new();
+        }
+        self.entries
+            .range(start..=end)
+            .rev()
+            .take(count.unwrap_or(usize::MAX))
+            .map(|(id, fields)| (*id, fields))
+            .collect()
+    }
+
+    /// Delete a single entry. Pending entries keep referencing the ID.
+    pub fn delete(&mut self, id: &StreamId) -> bool {
+        let removed = self.entries.remove(id).is_some();
+
+        // TigerStyle: Postcondition
+        debug_assert!(
+            !self.entries.contains_key(id),
+            "Postcondition violated: entry must not exist after delete"
+        );
+
+        self.verify_invariants();
+        removed
+    }
+
+    /// Evict oldest entries until at most `maxlen` remain (at most `limit` evictions)
+    pub fn trim_maxlen(&mut self, maxlen: usize, limit: Option<usize>) -> usize {
+        let mut removed = 0usize;
+        while self.entries.len() > maxlen && limit.is_none_or(|l| removed < l) {
+            self.entries.pop_first();
+            removed += 1;
+        }
+
+        // TigerStyle: Postcondition
+        debug_assert!(
+            limit.is_some() || self.entries.len() <= maxlen,
+            "Postcondition violated: len must be <= maxlen after untrimmed-limit trim"
+        );
+
+        self.verify_invariants();
+        removed
+    }
+
+    /// Evict entries with ID < `min_id` (at most `limit` evictions)
+    pub fn trim_minid(&mut self, min_id: StreamId, limit: Option<usize>) -> usize {
+        let mut removed = 0usize;
+        while limit.is_none_or(|l| removed < l) {
+            match self.entries.first_key_value() {
+                Some((id, _)) if *id < min_id => {
+                    self.entries.pop_first();
+                    removed += 1;
+                }
+                _ => break,
+            }
+        }
+
+        self.verify_invariants();
+        removed
+    }
+
+    // ------------------------------------------------------------------------
+    // Consumer groups
+    // ------------------------------------------------------------------------
+
+    pub fn group(&self, name: &str) -> Option<&StreamConsumerGroup> {
+        self.groups.get(name)
+    }
+
+    pub fn groups_len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// XGROUP CREATE. Returns false if the group already exists.
+    pub fn create_group(&mut self, name: &str, last_delivered_id: StreamId) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups.insert(
+            name.to_string(),
+            StreamConsumerGroup::new(last_delivered_id),
+        );
+        self.verify_invariants();
+        true
+    }
+
+    /// XGROUP DESTROY. Returns false if the group did not exist.
+    pub fn destroy_group(&mut self, name: &str) -> bool {
+        let removed = self.groups.remove(name).is_some();
+        self.verify_invariants();
+        removed
+    }
+
+    /// XGROUP SETID. Returns false if the group does not exist.
+    pub fn set_group_id(&mut self, name: &str, last_delivered_id: StreamId) -> bool {
+        match self.groups.get_mut(name) {
+            Some(group) => {
+                group.last_delivered_id = last_delivered_id;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// XREADGROUP for one stream.
+    ///
+    /// `after: None` is the special `>` ID: deliver never-delivered entries and
+    /// add them to the PEL (unless `noack`). `after: Some(id)` replays the
+    /// consumer's own pending entries with IDs greater than `id`; entries deleted
+    /// from the stream are returned with `None` fields.
+    /// Returns None if the group does not exist.
+    pub fn read_group(
+        &mut self,
+        group_name: &str,
+        consumer: &str,
+        after: Option<StreamId>,
+        count: Option<usize>,
+        noack: bool,
+        now_ms: u64,
+    ) -> Option<Vec<(StreamId, Option<StreamFields>)>> {
+        let group = self.groups.get_mut(group_name)?;
+        let count = count.unwrap_or(usize::MAX);
+        let mut result = Vec::new();
+
+        match after {
+            None => {
+                let start = match group.last_delivered_id.next() {
+                    Some(id) => id,
+                    None => {
+                        group.consumer_mut(consumer, now_ms);
+                        return Some(result);
+                    }
+                };
+                for (id, fields) in self.entries.range(start..).take(count) {
+                    result.push((*id, Some(fields.clone())));
+                }
+                if let Some((last, _)) = result.last() {
+                    group.last_delivered_id = *last;
+                }
+                if !noack {
+                    for (id, _) in &result {
+                        group.assign(*id, consumer, now_ms, 1);
+                    }
+                }
+                group.consumer_mut(consumer, now_ms);
+                // TigerStyle: Postcondition
+                debug_assert!(
+                    result.iter().all(|(id, _)| *id <= group.last_delivered_id),
+                    "Postcondition violated: delivered IDs must not exceed last_delivered_id"
+                );
+            }
+            Some(after) => {
+                let ids: Vec<StreamId> = match (after.next(), group.consumers.get(consumer)) {
+                    (Some(start), Some(c)) => {
+                        c.pending.range(start..).take(count).copied().collect()
+                    }
+                    _ => Vec::new(),
+                };
+                for id in ids {
+                    if let Some(pending) = group.pel.get_mut(&id) {
+                        pending.delivery_time_ms = now_ms;
+                        pending.delivery_count = pending.delivery_count.saturating_add(1);
+                    }
+                    result.push((id, self.entries.get(&id).cloned()));
+                }
+                group.consumer_mut(consumer, now_ms);
             }
-            self.verify_invariants();
         }
 
-        removed
+        self.verify_invariants();
+        Some(result)
     }
 
-    /// Get score of member. O(1)
-    pub fn score(&self, member: &SDS) -> Option<f64> {
-        self.members.get(&member.to_string()).copied()
+    /// XACK. Returns None if the group does not exist.
+    pub fn ack(&mut self, group_name: &str, ids: &[StreamId]) -> Option<usize> {
+        let group = self.groups.get_mut(group_name)?;
+        let acked = ids.iter().filter(|id| group.unassign(id)).count();
+        self.verify_invariants();
+        Some(acked)
     }
 
-    /// Get rank of member (0-indexed). O(log n)
-    pub fn rank(&self, member: &SDS) -> Option<usize> {
-        let key = member.to_string();
-        let score = self.members.get(&key)?;
-        self.skiplist.rank(&key, *score)
+    /// XPENDING extended form. Returns None if the group does not exist.
+    pub fn pending_range(
+        &self,
+        group_name: &str,
+        start: StreamId,
+        end: StreamId,
+        count: usize,
+        consumer: Option<&str>,
+        min_idle_ms: Option<u64>,
+        now_ms: u64,
+    ) -> Option<Vec<StreamPendingInfo>> {
+        let group = self.groups.get(group_name)?;
+        if start > end {
+            return Some(Vec::new());
+        }
+        Some(
+            group
+                .pel
+                .range(start..=end)
+                .filter(|(_, p)| consumer.is_none_or(|c| p.consumer == c))
+                .map(|(id, p)| StreamPendingInfo {
+                    id: *id,
+                    consumer: p.consumer.clone(),
+                    idle_ms: now_ms.saturating_sub(p.delivery_time_ms),
+                    delivery_count: p.delivery_count,
+                })
+                .filter(|info| min_idle_ms.is_none_or(|idle| info.idle_ms >= idle))
+                .take(count)
+                .collect(),
+        )
     }
 
-    /// Get range by rank [start, stop] (inclusive). O(log n + k)
-    pub fn range(&self, start: isize, stop: isize) -> Vec<(SDS, f64)> {
-        let len = self.skiplist.len() as isize;
-        if len == 0 {
-            return Vec::new();
+    /// XCLAIM. Returns None if the group does not exist.
+    pub fn claim(
+        &mut self,
+        group_name: &str,
+        consumer: &str,
+        min_idle_ms: u64,
+        ids: &[StreamId],
+        options: &StreamClaimOptions,
+        now_ms: u64,
+    ) -> Option<Vec<(StreamId, StreamFields)>> {
+        let group = self.groups.get_mut(group_name)?;
+
+        if let Some(last_id) = options.last_id {
+            if last_id > group.last_delivered_id {
+                group.last_delivered_id = last_id;
+            }
         }
 
-        let start = if start < 0 {
-            (len + start).max(0)
-        } else {
-            start.min(len)
-        };
-        let stop = if stop < 0 {
-            (len + stop).max(-1)
-        } else {
-            stop.min(len - 1)
+        let delivery_time_ms = match (options.time_ms, options.idle_ms) {
+            (Some(time), _) => time,
+            (None, Some(idle)) => now_ms.saturating_sub(idle),
+            (None, None) => now_ms,
         };
 
-        if start > stop || start >= len {
-            return Vec::new();
+        let mut claimed = Vec::new();
+        for id in ids {
+            let existing = group.pel.get(id).cloned();
+            let delivery_count = match existing {
+                Some(pending) => {
+                    if !self.entries.contains_key(id) {
+                        // Entry was deleted from the stream: drop the dangling PEL entry
+                        group.unassign(id);
+                        continue;
+                    }
+                    let idle = now_ms.saturating_sub(pending.delivery_time_ms);
+                    if min_idle_ms > 0 && idle < min_idle_ms {
+                        continue;
+                    }
+                    pending.delivery_count
+                }
+                None => {
+                    if !options.force || !self.entries.contains_key(id) {
+                        continue;
+                    }
+                    0
+                }
+            };
+            let delivery_count = match options.retry_count {
+                Some(n) => n,
+                None if options.just_id => delivery_count,
+                None => delivery_count.saturating_add(1),
+            };
+            group.assign(*id, consumer, delivery_time_ms, delivery_count);
+            if let Some(fields) = self.entries.get(id) {
+                claimed.push((*id, fields.clone()));
+            }
         }
+        group.consumer_mut(consumer, now_ms);
 
-        self.skiplist
-            .range(start as usize, stop as usize)
-            .into_iter()
-            .map(|(m, s)| (SDS::from_str(m), s))
-            .collect()
+        self.verify_invariants();
+        Some(claimed)
     }
 
-    /// Get range in reverse by rank. O(log n + k)
-    pub fn rev_range(&self, start: isize, stop: isize) -> Vec<(SDS, f64)> {
-        let len = self.skiplist.len() as isize;
-        if len == 0 {
-            return Vec::new();
+    /// XAUTOCLAIM. Scans the PEL from `start`, looking at up to `count * 10` entries.
+    /// Returns None if the group does not exist.
+    pub fn autoclaim(
+        &mut self,
+        group_name: &str,
+        consumer: &str,
+        min_idle_ms: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+        now_ms: u64,
+    ) -> Option<StreamAutoClaim> {
+        debug_assert!(count > 0, "Precondition: XAUTOCLAIM count must be positive");
+
+        let group = self.groups.get_mut(group_name)?;
+        let mut attempts = count.saturating_mul(10);
+        let mut claimed = Vec::new();
+        let mut deleted = Vec::new();
+        let mut next = StreamId::MIN;
+
+        let candidates: Vec<(StreamId, StreamPendingEntry)> = group
+            .pel
+            .range(start..)
+            .map(|(id, p)| (*id, p.clone()))
+            .collect();
+        let mut iter = candidates.into_iter().peekable();
+        // Deleted entries count against COUNT too: it bounds the reply size
+        while attempts > 0 && claimed.len() + deleted.len() < count {
+            let Some((id, pending)) = iter.next() else {
+                break;
+            };
+            attempts -= 1;
+            if !self.entries.contains_key(&id) {
+                group.unassign(&id);
+                deleted.push(id);
+                continue;
+            }
+            if now_ms.saturating_sub(pending.delivery_time_ms) < min_idle_ms {
+                continue;
+            }
+            let delivery_count = if just_id {
+                pending.delivery_count
+            } else {
+                pending.delivery_count.saturating_add(1)
+            };
+            group.assign(id, consumer, now_ms, delivery_count);
+            if let Some(fields) = self.entries.get(&id) {
+                claimed.push((id, fields.clone()));
+            }
         }
-
-        let start = if start < 0 {
-            (len + start).max(0)
-        } else {
-            start.min(len)
-        };
-        let stop = if stop < 0 {
-            (len + stop).max(-1)
-        } else {
-            stop.min(len - 1)
-        };
-
-        if start > stop || start >= len {
-            return Vec::new();
+        if let Some((id, _)) = iter.peek() {
+            next = *id;
         }
+        group.consumer_mut(consumer, now_ms);
 
-        self.skiplist
-            .rev_range(start as usize, stop as usize)
-            .into_iter()
-            .map(|(m, s)| (SDS::from_str(m), s))
-            .collect()
+        self.verify_invariants();
+        Some((next, claimed, deleted))
     }
+}
 
-    pub fn len(&self) -> usize {
-        self.members.len()
-    }
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
 
-    /// Get the skiplist length (for DST invariant checking)
-    pub fn skiplist_len(&self) -> usize {
-        self.skiplist.len()
+    fn fields(v: &str) -> StreamFields {
+        vec![(SDS::from_str("f"), SDS::from_str(v))]
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.members.is_empty()
+    #[test]
+    fn test_stream_id_parse_and_display() {
+        assert_eq!(StreamId::parse("5-3", 0), Some(StreamId::new(5, 3)));
+        assert_eq!(StreamId::parse("5", 0), Some(StreamId::new(5, 0)));
+        assert_eq!(
+            StreamId::parse("5", u64::MAX),
+            Some(StreamId::new(5, u64::MAX))
+        );
+        assert_eq!(StreamId::parse("x-1", 0), None);
+        assert_eq!(StreamId::new(7, 2).to_string(), "7-2");
+
+        assert_eq!(StreamId::parse_range_start("-"), Ok(StreamId::MIN));
+        assert_eq!(StreamId::parse_range_end("+"), Ok(StreamId::MAX));
+        assert_eq!(StreamId::parse_range_start("(1-1"), Ok(StreamId::new(1, 2)));
+        assert_eq!(
+            StreamId::parse_range_end("(1-0"),
+            Ok(StreamId::new(0, u64::MAX))
+        );
     }
 
-    /// Check if the set is sorted. Always true for a correctly functioning skiplist.
-    pub fn is_sorted(&self) -> bool {
-        let mut prev_score = f64::NEG_INFINITY;
-        let mut prev_member = String::new();
-        for (member, score) in self.skiplist.iter() {
-            if score < prev_score || (score == prev_score && member < prev_member.as_str()) {
-                return false;
-            }
-            prev_score = score;
-            prev_member = member.to_string();
-        }
-        true
+    #[test]
+    fn test_stream_next_id() {
+        let mut stream = RedisStream::new();
+        let id = stream.next_id("*", 100).unwrap();
+        assert_eq!(id, StreamId::new(100, 0));
+        stream.add(id, fields("a"));
+
+        // Same millisecond increments the sequence
+        assert_eq!(stream.next_id("*", 100).unwrap(), StreamId::new(100, 1));
+        // Clock going backwards still yields a greater ID
+        assert_eq!(stream.next_id("*", 50).unwrap(), StreamId::new(100, 1));
+        // Partial auto-sequence
+        assert_eq!(stream.next_id("100-*", 0).unwrap(), StreamId::new(100, 1));
+        assert_eq!(stream.next_id("200-*", 0).unwrap(), StreamId::new(200, 0));
+
+        // Explicit IDs must be greater than the top item
+        assert!(stream.next_id("100-0", 0).is_err());
+        assert!(stream.next_id("0-0", 0).is_err());
+        assert_eq!(stream.next_id("100-5", 0).unwrap(), StreamId::new(100, 5));
     }
 
-    /// Parse score bound (handles -inf, +inf, exclusive with parenthesis)
-    fn parse_score_bound(s: &str, _is_min: bool) -> Result<(f64, bool), String> {
-        let s = s.trim();
-        if s == "-inf" {
-            return Ok((f64::NEG_INFINITY, false));
-        }
-        if s == "+inf" || s == "inf" {
-            return Ok((f64::INFINITY, false));
+    #[test]
+    fn test_stream_range_and_delete() {
+        let mut stream = RedisStream::new();
+        for i in 1..=5 {
+            stream.add(StreamId::new(i, 0), fields(&i.to_string()));
         }
+        assert_eq!(stream.len(), 5);
 
-        let (exclusive, num_str) = if s.starts_with('(') {
-            (true, &s[1..])
-        } else {
-            (false, s)
-        };
-
-        let score = num_str
-            .parse::<f64>()
-            .map_err(|_| "ERR min or max is not a float".to_string())?;
-
-        Ok((score, exclusive))
-    }
-
-    /// ZCOUNT - count elements in score range. O(log n + k)
-    pub fn count_in_range(&self, min: &str, max: &str) -> Result<usize, String> {
-        let (min_score, min_exclusive) = Self::parse_score_bound(min, true)?;
-        let (max_score, max_exclusive) = Self::parse_score_bound(max, false)?;
+        let ids: Vec<u64> = stream
+            .range(StreamId::new(2, 0), StreamId::new(4, 0), None)
+            .iter()
+            .map(|(id, _)| id.ms)
+            .collect();
+        assert_eq!(ids, vec![2, 3, 4]);
 
-        let count = self
-            .skiplist
+        let ids: Vec<u64> = stream
+            .rev_range(StreamId::MIN, StreamId::MAX, Some(2))
             .iter()
-            .filter(|(_, score)| {
-                let above_min = if min_exclusive {
-                    *score > min_score
-                } else {
-                    *score >= min_score
-                };
-                let below_max = if max_exclusive {
-                    *score < max_score
-                } else {
-                    *score <= max_score
-                };
-                above_min && below_max
-            })
-            .count();
+            .map(|(id, _)| id.ms)
+            .collect();
+        assert_eq!(ids, vec![5, 4]);
+
+        assert!(stream.delete(&StreamId::new(3, 0)));
+        assert!(!stream.delete(&StreamId::new(3, 0)));
+        assert_eq!(stream.len(), 4);
+        // Deleting never moves last_id backwards
+        assert!(stream.delete(&StreamId::new(5, 0)));
+        assert_eq!(stream.last_id(), StreamId::new(5, 0));
+        assert_eq!(stream.entries_added(), 5);
+    }
 
-        Ok(count)
+    #[test]
+    fn test_stream_trim() {
+        let mut stream = RedisStream::new();
+        for i in 1..=10 {
+            stream.add(StreamId::new(i, 0), fields("v"));
+        }
+        assert_eq!(stream.trim_maxlen(7, None), 3);
+        assert_eq!(stream.len(), 7);
+        assert_eq!(stream.trim_maxlen(2, Some(1)), 1);
+        assert_eq!(stream.len(), 6);
+        assert_eq!(stream.trim_minid(StreamId::new(8, 0), None), 3);
+        let first = stream.range(StreamId::MIN, StreamId::MAX, Some(1));
+        assert_eq!(first[0].0, StreamId::new(8, 0));
     }
 
-    /// ZRANGEBYSCORE - get elements by score range. O(log n + k)
-    pub fn range_by_score(
-        &self,
-        min: &str,
-        max: &str,
-        with_scores: bool,
-        limit: Option<(isize, usize)>,
-    ) -> Result<Vec<(String, Option<f64>)>, String> {
-        let (min_score, min_exclusive) = Self::parse_score_bound(min, true)?;
-        let (max_score, max_exclusive) = Self::parse_score_bound(max, false)?;
+    #[test]
+    fn test_stream_consumer_group_lifecycle() {
+        let mut stream = RedisStream::new();
+        for i in 1..=3 {
+            stream.add(StreamId::new(i, 0), fields("v"));
+        }
+        assert!(stream.create_group("g", StreamId::MIN));
+        assert!(!stream.create_group("g", StreamId::MIN));
+
+        // New entries go to the PEL of the reading consumer
+        let delivered = stream
+            .read_group("g", "alice", None, Some(2), false, 10)
+            .unwrap();
+        assert_eq!(delivered.len(), 2);
+        assert_eq!(stream.group("g").unwrap().pending_len(), 2);
+        assert_eq!(
+            stream.group("g").unwrap().last_delivered_id,
+            StreamId::new(2, 0)
+        );
 
-        let mut results: Vec<_> = self
-            .skiplist
-            .iter()
-            .filter(|(_, score)| {
-                let above_min = if min_exclusive {
-                    *score > min_score
-                } else {
-                    *score >= min_score
-                };
-                let below_max = if max_exclusive {
-                    *score < max_score
-                } else {
-                    *score <= max_score
-                };
-                above_min && below_max
-            })
-            .map(|(member, score)| {
-                (
-                    member.to_string(),
-                    if with_scores { Some(score) } else { None },
-                )
-            })
-            .collect();
+        // Next consumer only gets what is left
+        let delivered = stream
+            .read_group("g", "bob", None, None, false, 10)
+            .unwrap();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].0, StreamId::new(3, 0));
 
-        // Apply LIMIT
-        if let Some((offset, count)) = limit {
-            let start = offset.max(0) as usize;
-            results = results.into_iter().skip(start).take(count).collect();
-        }
+        // History replay returns only the consumer's own pending entries
+        let history = stream
+            .read_group("g", "alice", Some(StreamId::MIN), None, false, 20)
+            .unwrap();
+        assert_eq!(history.len(), 2);
 
-        Ok(results)
+        assert_eq!(
+            stream.ack("g", &[StreamId::new(1, 0), StreamId::new(9, 0)]),
+            Some(1)
+        );
+        assert_eq!(stream.group("g").unwrap().pending_len(), 2);
+        assert_eq!(stream.ack("missing", &[StreamId::new(1, 0)]), None);
+
+        assert!(stream.destroy_group("g"));
+        assert_eq!(stream.groups_len(), 0);
     }
 
-    /// Iterate over member-score pairs in sorted order (for ZSCAN). O(n)
-    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
-        self.skiplist.iter()
+    #[test]
+    fn test_stream_noack_skips_pel() {
+        let mut stream = RedisStream::new();
+        stream.add(StreamId::new(1, 0), fields("v"));
+        stream.create_group("g", StreamId::MIN);
+        let delivered = stream.read_group("g", "c", None, None, true, 0).unwrap();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(stream.group("g").unwrap().pending_len(), 0);
     }
-}
 
-impl PartialEq for RedisSortedSet {
-    fn eq(&self, other: &Self) -> bool {
-        self.members == other.members
+    #[test]
+    fn test_stream_claim_and_autoclaim() {
+        let mut stream = RedisStream::new();
+        for i in 1..=4 {
+            stream.add(StreamId::new(i, 0), fields("v"));
+        }
+        stream.create_group("g", StreamId::MIN);
+        stream.read_group("g", "alice", None, None, false, 0);
+
+        // Not idle long enough
+        let claimed = stream
+            .claim(
+                "g",
+                "bob",
+                100,
+                &[StreamId::new(1, 0)],
+                &StreamClaimOptions::default(),
+                50,
+            )
+            .unwrap();
+        assert!(claimed.is_empty());
+
+        let claimed = stream
+            .claim(
+                "g",
+                "bob",
+                100,
+                &[StreamId::new(1, 0)],
+                &StreamClaimOptions::default(),
+                200,
+            )
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        let pending = stream
+            .pending_range(
+                "g",
+                StreamId::MIN,
+                StreamId::MAX,
+                10,
+                Some("bob"),
+                None,
+                200,
+            )
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].delivery_count, 2);
+
+        // Deleted entries are reported and dropped from the PEL
+        stream.delete(&StreamId::new(2, 0));
+        let (next, claimed, deleted) = stream
+            .autoclaim("g", "carol", 0, StreamId::MIN, 2, false, 300)
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(deleted, vec![StreamId::new(2, 0)]);
+        assert_eq!(next, StreamId::new(3, 0));
+        assert_eq!(stream.group("g").unwrap().pending_len(), 3);
     }
 }
 
@@ -1943,6 +3737,62 @@ mod sorted_set_tests {
         assert_eq!(zset.len(), 2);
         assert!(zset.is_sorted());
     }
+
+    #[test]
+    fn test_score_range_bounds() {
+        // dave:50 alice:100 charlie:150 bob:200
+        let zset = create_test_set();
+        let bound = |s: &str| ScoreBound::parse(s).unwrap();
+
+        assert_eq!(zset.score_range(&bound("100"), &bound("150")), 1..3);
+        assert_eq!(zset.score_range(&bound("(100"), &bound("150")), 2..3);
+        assert_eq!(zset.score_range(&bound("100"), &bound("(150")), 1..2);
+        assert_eq!(zset.score_range(&bound("-inf"), &bound("+inf")), 0..4);
+        assert_eq!(zset.score_range(&bound("(200"), &bound("+inf")), 4..4);
+        assert_eq!(zset.score_range(&bound("150"), &bound("100")), 2..2);
+        assert!(ScoreBound::parse("nan").is_err());
+        assert!(ScoreBound::parse("(abc").is_err());
+    }
+
+    #[test]
+    fn test_lex_range_bounds() {
+        let mut zset = RedisSortedSet::new();
+        for member in ["a", "b", "c", "d", "e"] {
+            zset.add(SDS::from_str(member), 0.0);
+        }
+        let bound = |s: &str| LexBound::parse(s).unwrap();
+
+        assert_eq!(zset.lex_range(&bound("-"), &bound("+")), 0..5);
+        assert_eq!(zset.lex_range(&bound("[b"), &bound("[d")), 1..4);
+        assert_eq!(zset.lex_range(&bound("(b"), &bound("(d")), 2..3);
+        assert_eq!(zset.lex_range(&bound("+"), &bound("-")), 5..5);
+        assert!(LexBound::parse("b").is_err());
+    }
+
+    #[test]
+    fn test_range_in_ranks_offset_count_rev() {
+        // dave:50 alice:100 charlie:150 bob:200
+        let zset = create_test_set();
+        let members = |items: Vec<(SDS, f64)>| -> Vec<String> {
+            items.into_iter().map(|(m, _)| m.to_string()).collect()
+        };
+
+        assert_eq!(
+            members(zset.range_in_ranks(0..4, false, 1, Some(2))),
+            vec!["alice", "charlie"]
+        );
+        assert_eq!(
+            members(zset.range_in_ranks(0..4, true, 1, Some(2))),
+            vec!["charlie", "alice"]
+        );
+        assert_eq!(
+            members(zset.range_in_ranks(1..3, true, 0, None)),
+            vec!["charlie", "alice"]
+        );
+        assert!(zset.range_in_ranks(0..4, false, 9, None).is_empty());
+        assert_eq!(zset.rank_range(0, 0, true), 3..4);
+        assert_eq!(zset.rank_range(-2, -1, false), 2..4);
+    }
 }
 
 #[cfg(test)]
@@ -2035,6 +3885,32 @@ mod hash_tests {
         assert_eq!(hash.len(), 0);
         assert!(!hash.exists(&SDS::from_str("counter")));
     }
+
+    #[test]
+    fn test_hash_field_deadlines() {
+        let mut hash = RedisHash::new();
+        hash.set(SDS::from_str("a"), SDS::from_str("1"));
+        hash.set(SDS::from_str("b"), SDS::from_str("2"));
+        hash.set(SDS::from_str("c"), SDS::from_str("3"));
+
+        assert!(!hash.set_field_deadline(&SDS::from_str("missing"), 100));
+        assert!(hash.set_field_deadline(&SDS::from_str("a"), 100));
+        assert!(hash.set_field_deadline(&SDS::from_str("b"), 200));
+        assert!(hash.set_field_deadline(&SDS::from_str("c"), 300));
+        assert_eq!(hash.next_field_deadline(), Some(100));
+
+        // Overwriting a field clears its TTL, PERSIST drops it explicitly
+        hash.set(SDS::from_str("c"), SDS::from_str("30"));
+        assert_eq!(hash.field_deadline(&SDS::from_str("c")), None);
+        assert!(hash.persist_field(&SDS::from_str("b")));
+        assert!(!hash.persist_field(&SDS::from_str("b")));
+
+        assert_eq!(hash.remove_expired_fields(99), 0);
+        assert_eq!(hash.remove_expired_fields(100), 1);
+        assert!(!hash.exists(&SDS::from_str("a")));
+        assert_eq!(hash.len(), 2);
+        assert_eq!(hash.next_field_deadline(), None);
+    }
 }
 
 #[cfg(test)]
@@ -2157,6 +4033,72 @@ mod list_tests {
         assert!(list.is_empty());
         assert_eq!(list.len(), 0);
     }
+
+    fn list_of(items: &[&str]) -> RedisList {
+        let mut list = RedisList::new();
+        for item in items {
+            list.rpush(SDS::from_str(item));
+        }
+        list
+    }
+
+    #[test]
+    fn test_list_positions() {
+        let list = list_of(&["a", "b", "c", "1", "2", "3", "c", "c"]);
+        let c = SDS::from_str("c");
+
+        assert_eq!(list.positions(&c, 1, 1, 0), vec![2]);
+        assert_eq!(list.positions(&c, 2, 1, 0), vec![6]);
+        assert_eq!(list.positions(&c, -1, 1, 0), vec![7]);
+        assert_eq!(list.positions(&c, 1, 0, 0), vec![2, 6, 7]);
+        assert_eq!(list.positions(&c, -1, 2, 0), vec![7, 6]);
+        // MAXLEN bounds the number of compared elements
+        assert_eq!(list.positions(&c, 1, 0, 3), vec![2]);
+        assert_eq!(list.positions(&c, -1, 0, 2), vec![7, 6]);
+        assert!(list.positions(&c, 4, 1, 0).is_empty());
+        assert!(list.positions(&SDS::from_str("z"), 1, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn test_list_insert() {
+        let mut list = list_of(&["a", "c"]);
+
+        assert_eq!(
+            list.insert(&SDS::from_str("c"), SDS::from_str("b"), true),
+            Some(3)
+        );
+        assert_eq!(
+            list.insert(&SDS::from_str("c"), SDS::from_str("d"), false),
+            Some(4)
+        );
+        assert_eq!(
+            list.insert(&SDS::from_str("z"), SDS::from_str("x"), true),
+            None
+        );
+
+        let items: Vec<String> = list.range(0, -1).iter().map(|v| v.to_string()).collect();
+        assert_eq!(items, vec!["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_list_remove() {
+        let x = SDS::from_str("x");
+
+        let mut list = list_of(&["x", "a", "x", "b", "x"]);
+        assert_eq!(list.remove(2, &x), 2);
+        let items: Vec<String> = list.range(0, -1).iter().map(|v| v.to_string()).collect();
+        assert_eq!(items, vec!["a", "b", "x"]);
+
+        let mut list = list_of(&["x", "a", "x", "b", "x"]);
+        assert_eq!(list.remove(-2, &x), 2);
+        let items: Vec<String> = list.range(0, -1).iter().map(|v| v.to_string()).collect();
+        assert_eq!(items, vec!["x", "a", "b"]);
+
+        let mut list = list_of(&["x", "a", "x"]);
+        assert_eq!(list.remove(0, &x), 2);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list.remove(0, &x), 0);
+    }
 }
 
 #[cfg(test)]
@@ -2254,12 +4196,77 @@ mod set_tests {
         assert!(set.is_empty());
         assert_eq!(set.len(), 0);
     }
+
+    fn set_of(members: &[&str]) -> RedisSet {
+        let mut set = RedisSet::new();
+        for m in members {
+            set.add(SDS::from_str(m));
+        }
+        set
+    }
+
+    fn sorted(set: &RedisSet) -> Vec<String> {
+        let mut members: Vec<String> = set.members().iter().map(|m| m.to_string()).collect();
+        members.sort();
+        members
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let a = set_of(&["a", "b", "c", "d"]);
+        let b = set_of(&["c", "d", "e"]);
+        let c = set_of(&["a", "c"]);
+        let empty = RedisSet::new();
+
+        assert_eq!(
+            sorted(&RedisSet::union(&[&a, &b])),
+            ["a", "b", "c", "d", "e"]
+        );
+        assert_eq!(sorted(&RedisSet::intersection(&[&a, &b, &c])), ["c"]);
+        assert_eq!(sorted(&RedisSet::difference(&[&a, &b])), ["a", "b"]);
+        assert_eq!(sorted(&RedisSet::difference(&[&b, &a, &c])), ["e"]);
+
+        // A missing key is an empty set
+        assert!(RedisSet::intersection(&[&a, &empty]).is_empty());
+        assert_eq!(RedisSet::union(&[&empty, &c]).len(), 2);
+
+        assert_eq!(RedisSet::intersection_card(&[&a, &b], 0), 2);
+        assert_eq!(RedisSet::intersection_card(&[&a, &b], 1), 1);
+        assert_eq!(RedisSet::intersection_card(&[&a, &b], 5), 2);
+    }
 }
 
 #[cfg(test)]
 mod sds_tests {
     use super::*;
 
+    #[test]
+    fn test_sds_get_range() {
+        let sds = SDS::from_str("This is a string");
+
+        assert_eq!(sds.get_range(0, 3), b"This");
+        assert_eq!(sds.get_range(-3, -1), b"ing");
+        assert_eq!(sds.get_range(0, -1), b"This is a string");
+        assert_eq!(sds.get_range(10, 100), b"string");
+        assert_eq!(sds.get_range(-100, 3), b"This");
+        assert_eq!(sds.get_range(5, 3), b"");
+        assert_eq!(sds.get_range(-1, -5), b"");
+        assert_eq!(SDS::from_str("").get_range(0, -1), b"");
+    }
+
+    #[test]
+    fn test_sds_set_range() {
+        let mut sds = SDS::from_str("Hello World");
+        assert_eq!(sds.set_range(6, b"Redis"), 11);
+        assert_eq!(sds.as_bytes(), b"Hello Redis");
+
+        // Writing past the end zero-pads the gap, growing out of inline storage
+        let mut sds = SDS::from_str("ab");
+        assert_eq!(sds.set_range(30, b"z"), 31);
+        assert_eq!(&sds.as_bytes()[..3], b"ab\0");
+        assert_eq!(sds.as_bytes()[30], b'z');
+    }
+
     #[test]
     fn test_sds_new() {
         let data = vec![104, 101, 108, 108, 111]; // "hello"
@@ -0,0 +1,480 @@
+//! Memory accounting and maxmemory eviction
+//!
+//! Every shard's `CommandExecutor` owns a `MemoryTracker` holding the
+//! estimated size of each key (see `Value::memory_usage`). When a write would
+//! run the shard past its `maxmemory`, the executor evicts keys chosen by the
+//! configured policy. As in Redis, victims are found by sampling
+//! `maxmemory-samples` keys and evicting the best candidate, so LRU and LFU are
+//! approximate and each eviction costs O(samples) regardless of key count.
+//!
+//! Sampling draws from a seeded xorshift generator and LFU decay runs on
+//! `VirtualTime`, so DST replays the same evictions for the same seed.
+
+use super::data::{Value, SDS};
+use crate::simulator::VirtualTime;
+use ahash::AHashMap;
+
+/// Keys sampled per eviction round (Redis `maxmemory-samples` default)
+pub const DEFAULT_MAXMEMORY_SAMPLES: usize = 5;
+
+/// Dictionary entry, expiry slot and object header of a key, beyond key and value bytes
+const KEY_OVERHEAD: usize = 64;
+
+/// Counter given to new keys so they are not evicted before being read (Redis `LFU_INIT_VAL`)
+const LFU_INIT_VAL: u8 = 5;
+/// Redis `lfu-log-factor`: higher values make the counter saturate more slowly
+const LFU_LOG_FACTOR: u64 = 10;
+/// Redis `lfu-decay-time`: minutes of idleness that take one point off the counter
+const LFU_DECAY_MINUTES: u64 = 1;
+
+/// Which keys may be evicted when a shard reaches `maxmemory`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Refuse writes that may grow memory with an OOM error
+    #[default]
+    NoEviction,
+    AllKeysLru,
+    AllKeysLfu,
+    AllKeysRandom,
+    /// Only keys with a TTL are candidates
+    VolatileLru,
+    VolatileLfu,
+    VolatileRandom,
+    /// Evict the key with the nearest expiry first
+    VolatileTtl,
+}
+
+impl EvictionPolicy {
+    /// Parse a `maxmemory-policy` name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "noeviction" => Some(EvictionPolicy::NoEviction),
+            "allkeys-lru" => Some(EvictionPolicy::AllKeysLru),
+            "allkeys-lfu" => Some(EvictionPolicy::AllKeysLfu),
+            "allkeys-random" => Some(EvictionPolicy::AllKeysRandom),
+            "volatile-lru" => Some(EvictionPolicy::VolatileLru),
+            "volatile-lfu" => Some(EvictionPolicy::VolatileLfu),
+            "volatile-random" => Some(EvictionPolicy::VolatileRandom),
+            "volatile-ttl" => Some(EvictionPolicy::VolatileTtl),
+            _ => None,
+        }
+    }
+
+    /// The `maxmemory-policy` name, as shown by INFO
+    pub fn name(&self) -> &'static str {
+        match self {
+            EvictionPolicy::NoEviction => "noeviction",
+            EvictionPolicy::AllKeysLru => "allkeys-lru",
+            EvictionPolicy::AllKeysLfu => "allkeys-lfu",
+            EvictionPolicy::AllKeysRandom => "allkeys-random",
+            EvictionPolicy::VolatileLru => "volatile-lru",
+            EvictionPolicy::VolatileLfu => "volatile-lfu",
+            EvictionPolicy::VolatileRandom => "volatile-random",
+            EvictionPolicy::VolatileTtl => "volatile-ttl",
+        }
+    }
+
+    /// True if only keys with a TTL may be evicted
+    #[inline]
+    pub fn is_volatile(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::VolatileLru
+                | EvictionPolicy::VolatileLfu
+                | EvictionPolicy::VolatileRandom
+                | EvictionPolicy::VolatileTtl
+        )
+    }
+
+    /// True if the policy ranks keys by access frequency
+    #[inline]
+    pub fn is_lfu(&self) -> bool {
+        matches!(
+            self,
+            EvictionPolicy::AllKeysLfu | EvictionPolicy::VolatileLfu
+        )
+    }
+}
+
+/// maxmemory settings of one shard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryConfig {
+    /// Byte limit; 0 = unlimited
+    pub maxmemory: usize,
+    pub policy: EvictionPolicy,
+    /// Keys sampled per eviction round
+    pub samples: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        MemoryConfig {
+            maxmemory: 0,
+            policy: EvictionPolicy::NoEviction,
+            samples: DEFAULT_MAXMEMORY_SAMPLES,
+        }
+    }
+}
+
+impl MemoryConfig {
+    /// Share of a server-wide config given to each of `num_shards` shards
+    pub fn per_shard(&self, num_shards: usize) -> MemoryConfig {
+        debug_assert!(num_shards > 0, "Precondition: at least one shard");
+        let maxmemory = if self.maxmemory == 0 {
+            0
+        } else {
+            (self.maxmemory / num_shards).max(1)
+        };
+        MemoryConfig { maxmemory, ..*self }
+    }
+}
+
+/// Memory, eviction and expiry counters of a shard, summed across shards for INFO
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub used_memory: usize,
+    pub maxmemory: usize,
+    pub policy: EvictionPolicy,
+    /// Keys removed to stay under maxmemory
+    pub evicted_keys: u64,
+    /// Writes refused with OOM
+    pub oom_rejections: u64,
+    /// Keys reclaimed once their TTL passed
+    pub expired_keys: u64,
+    /// Active expire cycles stopped at their time budget
+    pub expired_time_cap_reached_count: u64,
+}
+
+impl MemoryStats {
+    /// Add another shard's counters (the policy is the same on every shard)
+    pub fn merge(&mut self, other: &MemoryStats) {
+        self.used_memory += other.used_memory;
+        self.maxmemory += other.maxmemory;
+        self.policy = other.policy;
+        self.evicted_keys += other.evicted_keys;
+        self.oom_rejections += other.oom_rejections;
+        self.expired_keys += other.expired_keys;
+        self.expired_time_cap_reached_count += other.expired_time_cap_reached_count;
+    }
+}
+
+/// Accounted size of `key` holding `value`
+#[inline]
+pub fn key_memory(key: &[u8], value: &Value) -> usize {
+    KEY_OVERHEAD + key.len() + value.memory_usage()
+}
+
+/// Keys in a vector for O(1) random picks, with positions for O(1) removal
+#[derive(Debug, Default)]
+struct KeyPool {
+    keys: Vec<SDS>,
+    positions: AHashMap<SDS, usize>,
+}
+
+impl KeyPool {
+    fn insert(&mut self, key: &[u8]) {
+        if !self.positions.contains_key(key) {
+            self.positions.insert(SDS::from(key), self.keys.len());
+            self.keys.push(SDS::from(key));
+        }
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        if let Some(pos) = self.positions.remove(key) {
+            self.keys.swap_remove(pos);
+            if let Some(moved) = self.keys.get(pos) {
+                self.positions.insert(moved.clone(), pos);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.keys.clear();
+        self.positions.clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct KeyMemory {
+    bytes: usize,
+    /// Logarithmic access counter (Redis LFU)
+    lfu_counter: u8,
+    /// Virtual minute of the last LFU decay
+    lfu_minutes: u64,
+}
+
+/// Per-key memory accounting of one shard
+#[derive(Debug)]
+pub struct MemoryTracker {
+    entries: AHashMap<SDS, KeyMemory>,
+    all_keys: KeyPool,
+    /// Keys with a TTL, the candidates of the volatile-* policies
+    volatile_keys: KeyPool,
+    used: usize,
+    rng_state: u64,
+}
+
+impl Default for MemoryTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryTracker {
+    pub fn new() -> Self {
+        MemoryTracker {
+            entries: AHashMap::new(),
+            all_keys: KeyPool::default(),
+            volatile_keys: KeyPool::default(),
+            used: 0,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Total accounted bytes
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Number of accounted keys
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Accounted bytes of `key`
+    pub fn bytes(&self, key: &[u8]) -> Option<usize> {
+        self.entries.get(key).map(|e| e.bytes)
+    }
+
+    /// Record the current size of `key`; `volatile` = the key has a TTL
+    pub fn update(&mut self, key: &[u8], bytes: usize, volatile: bool, now: VirtualTime) {
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                self.used = self.used - entry.bytes + bytes;
+                entry.bytes = bytes;
+            }
+            None => {
+                self.entries.insert(
+                    SDS::from(key),
+                    KeyMemory {
+                        bytes,
+                        lfu_counter: LFU_INIT_VAL,
+                        lfu_minutes: minutes(now),
+                    },
+                );
+                self.all_keys.insert(key);
+                self.used += bytes;
+            }
+        }
+        if volatile {
+            self.volatile_keys.insert(key);
+        } else {
+            self.volatile_keys.remove(key);
+        }
+        self.verify_invariants();
+    }
+
+    /// Forget `key` (deleted, expired or evicted)
+    pub fn remove(&mut self, key: &[u8]) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.used -= entry.bytes;
+            self.all_keys.remove(key);
+            self.volatile_keys.remove(key);
+        }
+        self.verify_invariants();
+    }
+
+    /// Forget every key (FLUSHDB)
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.all_keys.clear();
+        self.volatile_keys.clear();
+        self.used = 0;
+    }
+
+    /// Count an access to `key`: decay the LFU counter for the idle time, then
+    /// increment it with probability 1 / ((counter - LFU_INIT_VAL) * log_factor + 1)
+    pub fn record_access(&mut self, key: &[u8], now: VirtualTime) {
+        let roll = self.next_random();
+        if let Some(entry) = self.entries.get_mut(key) {
+            let mut counter = decayed_counter(entry, now);
+            if counter < u8::MAX {
+                let base = counter.saturating_sub(LFU_INIT_VAL) as u64;
+                if roll % (base * LFU_LOG_FACTOR + 1) == 0 {
+                    counter += 1;
+                }
+            }
+            entry.lfu_counter = counter;
+            entry.lfu_minutes = minutes(now);
+        }
+    }
+
+    /// LFU counter of `key` as of `now` (0 for unknown keys)
+    pub fn lfu_counter(&self, key: &[u8], now: VirtualTime) -> u8 {
+        self.entries
+            .get(key)
+            .map_or(0, |entry| decayed_counter(entry, now))
+    }
+
+    /// Up to `count` random keys (with replacement), only keys with a TTL if `volatile`
+    pub fn sample(&mut self, count: usize, volatile: bool) -> Vec<SDS> {
+        let len = if volatile {
+            self.volatile_keys.keys.len()
+        } else {
+            self.all_keys.keys.len()
+        };
+        if len == 0 {
+            return Vec::new();
+        }
+        (0..count)
+            .map(|_| {
+                let i = (self.next_random() % len as u64) as usize;
+                let pool = if volatile {
+                    &self.volatile_keys
+                } else {
+                    &self.all_keys
+                };
+                pool.keys[i].clone()
+            })
+            .collect()
+    }
+
+    /// xorshift64: deterministic, so evictions replay under DST
+    fn next_random(&mut self) -> u64 {
+        let mut x = self.rng_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng_state = x;
+        x
+    }
+
+    #[cfg(debug_assertions)]
+    fn verify_invariants(&self) {
+        debug_assert_eq!(
+            self.entries.len(),
+            self.all_keys.keys.len(),
+            "Invariant violated: every accounted key must be in the sampling pool"
+        );
+        debug_assert_eq!(
+            self.all_keys.keys.len(),
+            self.all_keys.positions.len(),
+            "Invariant violated: pool positions out of sync"
+        );
+        debug_assert!(
+            self.volatile_keys.keys.len() <= self.entries.len(),
+            "Invariant violated: more volatile keys than keys"
+        );
+        if self.entries.len() <= 64 {
+            let sum: usize = self.entries.values().map(|e| e.bytes).sum();
+            debug_assert_eq!(
+                self.used, sum,
+                "Invariant violated: used memory must equal the sum of key sizes"
+            );
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    fn verify_invariants(&self) {}
+}
+
+#[inline]
+fn minutes(time: VirtualTime) -> u64 {
+    time.as_millis() / 60_000
+}
+
+/// Counter minus one per `LFU_DECAY_MINUTES` elapsed since the last decay
+fn decayed_counter(entry: &KeyMemory, now: VirtualTime) -> u8 {
+    let periods = minutes(now).saturating_sub(entry.lfu_minutes) / LFU_DECAY_MINUTES;
+    entry
+        .lfu_counter
+        .saturating_sub(periods.min(u8::MAX as u64) as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_names_round_trip() {
+        for name in [
+            "noeviction",
+            "allkeys-lru",
+            "allkeys-lfu",
+            "allkeys-random",
+            "volatile-lru",
+            "volatile-lfu",
+            "volatile-random",
+            "volatile-ttl",
+        ] {
+            assert_eq!(EvictionPolicy::from_name(name).unwrap().name(), name);
+        }
+        assert_eq!(
+            EvictionPolicy::from_name("ALLKEYS-LRU"),
+            Some(EvictionPolicy::AllKeysLru)
+        );
+        assert_eq!(EvictionPolicy::from_name("lru"), None);
+    }
+
+    #[test]
+    fn test_tracker_accounting() {
+        let now = VirtualTime::from_millis(0);
+        let mut tracker = MemoryTracker::new();
+        tracker.update(b"a", 100, false, now);
+        tracker.update(b"b", 50, true, now);
+        assert_eq!(tracker.used(), 150);
+
+        tracker.update(b"a", 10, false, now);
+        assert_eq!(tracker.used(), 60);
+        assert_eq!(tracker.sample(10, true), vec![SDS::from("b"); 10]);
+
+        tracker.update(b"b", 50, false, now);
+        assert!(tracker.sample(10, true).is_empty());
+
+        tracker.remove(b"a");
+        assert_eq!(tracker.used(), 50);
+        assert_eq!(tracker.len(), 1);
+        tracker.clear();
+        assert_eq!(tracker.used(), 0);
+        assert!(tracker.sample(5, false).is_empty());
+    }
+
+    #[test]
+    fn test_lfu_counter_grows_and_decays() {
+        let mut tracker = MemoryTracker::new();
+        let start = VirtualTime::from_millis(0);
+        tracker.update(b"hot", 1, false, start);
+        tracker.update(b"cold", 1, false, start);
+        for _ in 0..1000 {
+            tracker.record_access(b"hot", start);
+        }
+        let hot = tracker.lfu_counter(b"hot", start);
+        assert!(hot > LFU_INIT_VAL, "counter {} did not grow", hot);
+        assert_eq!(tracker.lfu_counter(b"cold", start), LFU_INIT_VAL);
+
+        // One point per idle minute
+        let later = VirtualTime::from_millis(3 * 60_000);
+        assert_eq!(tracker.lfu_counter(b"hot", later), hot - 3);
+        assert_eq!(tracker.lfu_counter(b"cold", later), LFU_INIT_VAL - 3);
+    }
+
+    #[test]
+    fn test_per_shard_config() {
+        let config = MemoryConfig {
+            maxmemory: 1000,
+            policy: EvictionPolicy::AllKeysLru,
+            samples: 5,
+        };
+        assert_eq!(config.per_shard(4).maxmemory, 250);
+        assert_eq!(config.per_shard(4).policy, EvictionPolicy::AllKeysLru);
+        assert_eq!(MemoryConfig::default().per_shard(4).maxmemory, 0);
+    }
+}
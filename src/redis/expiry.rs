@@ -0,0 +1,160 @@
+//! Time-ordered TTL index
+//!
+//! `ExpiryIndex` maps keys to deadlines like a hash map, and also keeps the
+//! keys ordered by deadline in a `BTreeMap`. Active expiry pops due keys from
+//! the front instead of scanning every key with a TTL, so a cycle costs
+//! O(expired * log n) and finding nothing to do is O(log n).
+//!
+//! Keys sharing a deadline are ordered by insertion sequence, which keeps the
+//! expiry order deterministic under DST.
+
+use super::data::SDS;
+use crate::simulator::VirtualTime;
+use ahash::AHashMap;
+use std::collections::BTreeMap;
+
+/// Deadline plus insertion sequence: unique, so it can key the ordered map
+type DeadlineSlot = (VirtualTime, u64);
+
+/// Key -> deadline map with deadline-ordered iteration
+#[derive(Debug, Default)]
+pub struct ExpiryIndex {
+    deadlines: AHashMap<SDS, DeadlineSlot>,
+    by_deadline: BTreeMap<DeadlineSlot, SDS>,
+    next_seq: u64,
+}
+
+impl ExpiryIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.deadlines.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+
+    #[inline]
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.deadlines.contains_key(key)
+    }
+
+    /// Deadline of `key`, if it has a TTL
+    #[inline]
+    pub fn get(&self, key: &[u8]) -> Option<&VirtualTime> {
+        self.deadlines.get(key).map(|(deadline, _)| deadline)
+    }
+
+    /// Set the deadline of `key`, returning the previous one
+    pub fn insert(&mut self, key: SDS, deadline: VirtualTime) -> Option<VirtualTime> {
+        let slot = (deadline, self.next_seq);
+        self.next_seq += 1;
+        let previous = self.deadlines.insert(key.clone(), slot);
+        if let Some(old) = previous {
+            self.by_deadline.remove(&old);
+        }
+        self.by_deadline.insert(slot, key);
+        self.verify_invariants();
+        previous.map(|(deadline, _)| deadline)
+    }
+
+    /// Drop the TTL of `key`, returning its deadline
+    pub fn remove(&mut self, key: &[u8]) -> Option<VirtualTime> {
+        let slot = self.deadlines.remove(key)?;
+        self.by_deadline.remove(&slot);
+        self.verify_invariants();
+        Some(slot.0)
+    }
+
+    pub fn clear(&mut self) {
+        self.deadlines.clear();
+        self.by_deadline.clear();
+    }
+
+    /// Earliest deadline
+    #[inline]
+    pub fn next_deadline(&self) -> Option<VirtualTime> {
+        self.by_deadline
+            .first_key_value()
+            .map(|(&(deadline, _), _)| deadline)
+    }
+
+    /// Remove and return the key with the earliest deadline if it is at or before `now`
+    pub fn pop_expired(&mut self, now: VirtualTime) -> Option<SDS> {
+        let entry = self.by_deadline.first_entry()?;
+        if entry.key().0 > now {
+            return None;
+        }
+        let key = entry.remove();
+        self.deadlines.remove(&key);
+        self.verify_invariants();
+        Some(key)
+    }
+
+    /// Keys and deadlines in deadline order
+    pub fn iter(&self) -> impl Iterator<Item = (&SDS, &VirtualTime)> {
+        self.by_deadline
+            .iter()
+            .map(|((deadline, _), key)| (key, deadline))
+    }
+
+    #[cfg(debug_assertions)]
+    fn verify_invariants(&self) {
+        debug_assert_eq!(
+            self.deadlines.len(),
+            self.by_deadline.len(),
+            "Invariant violated: key map and deadline order must hold the same keys"
+        );
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    fn verify_invariants(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> VirtualTime {
+        VirtualTime::from_millis(ms)
+    }
+
+    #[test]
+    fn test_pops_in_deadline_order() {
+        let mut index = ExpiryIndex::new();
+        index.insert("c".into(), at(300));
+        index.insert("a".into(), at(100));
+        index.insert("b".into(), at(200));
+        index.insert("a2".into(), at(100));
+
+        assert_eq!(index.next_deadline(), Some(at(100)));
+        assert_eq!(index.pop_expired(at(150)), Some(SDS::from("a")));
+        assert_eq!(index.pop_expired(at(150)), Some(SDS::from("a2")));
+        assert_eq!(index.pop_expired(at(150)), None);
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index.iter().map(|(k, _)| k.as_bytes()).collect::<Vec<_>>(),
+            vec![b"b", b"c"]
+        );
+    }
+
+    #[test]
+    fn test_reinsert_and_remove_update_order() {
+        let mut index = ExpiryIndex::new();
+        index.insert("k".into(), at(100));
+        assert_eq!(index.insert("k".into(), at(500)), Some(at(100)));
+        assert_eq!(index.pop_expired(at(200)), None);
+        assert_eq!(index.get(b"k"), Some(&at(500)));
+
+        assert_eq!(index.remove(b"k"), Some(at(500)));
+        assert_eq!(index.remove(b"k"), None);
+        assert!(index.is_empty());
+        assert_eq!(index.next_deadline(), None);
+    }
+}
@@ -0,0 +1,636 @@
+//! Geospatial indexing on sorted sets (GEOADD, GEOSEARCH and friends)
+//!
+//! A point is stored as a sorted set member whose score is the 52-bit
+//! interleaved geohash of its coordinates: 26 bits of latitude on the even
+//! bits and 26 bits of longitude on the odd bits, exactly as Redis encodes
+//! it, so scores are interchangeable with a real server. Searches cover the
+//! query shape with the geohash box of the centre and its eight neighbours
+//! and walk each box as a score range of the skip list.
+
+use super::data::{RedisSortedSet, SDS};
+
+/// Bits per coordinate; a score carries `2 * GEO_STEP_MAX` bits
+pub const GEO_STEP_MAX: u8 = 26;
+
+pub const GEO_LAT_MIN: f64 = -85.05112878;
+pub const GEO_LAT_MAX: f64 = 85.05112878;
+pub const GEO_LONG_MIN: f64 = -180.0;
+pub const GEO_LONG_MAX: f64 = 180.0;
+
+/// Earth's quadratic mean radius for WGS-84, as used by Redis
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const MERCATOR_MAX: f64 = 20037726.37;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Distance unit of GEODIST and the search commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GeoUnit {
+    #[default]
+    Meters,
+    Kilometers,
+    Feet,
+    Miles,
+}
+
+impl GeoUnit {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "m" => Ok(GeoUnit::Meters),
+            "km" => Ok(GeoUnit::Kilometers),
+            "ft" => Ok(GeoUnit::Feet),
+            "mi" => Ok(GeoUnit::Miles),
+            _ => Err("unsupported unit provided. please use M, KM, FT, MI".to_string()),
+        }
+    }
+
+    pub fn to_meters(self) -> f64 {
+        match self {
+            GeoUnit::Meters => 1.0,
+            GeoUnit::Kilometers => 1000.0,
+            GeoUnit::Feet => 0.3048,
+            GeoUnit::Miles => 1609.34,
+        }
+    }
+}
+
+/// Centre of a search: FROMMEMBER (resolved at execution) or FROMLONLAT
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoOrigin {
+    Member(SDS),
+    LonLat(f64, f64),
+}
+
+/// Search area, in the query's unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GeoShape {
+    /// BYRADIUS radius
+    Radius(f64),
+    /// BYBOX width height, centred on the origin
+    Box { width: f64, height: f64 },
+}
+
+/// Parsed GEOSEARCH / GEORADIUS query
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoQuery {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    pub unit: GeoUnit,
+    /// ASC (false) or DESC (true); none keeps discovery order
+    pub descending: Option<bool>,
+    /// COUNT n
+    pub count: Option<usize>,
+    /// ANY: stop as soon as `count` matches were found
+    pub any: bool,
+    pub with_dist: bool,
+    pub with_coord: bool,
+    pub with_hash: bool,
+}
+
+/// STORE / STOREDIST / GEOSEARCHSTORE destination
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoStore {
+    pub dest: SDS,
+    /// Store distances (in the query unit) instead of geohash scores
+    pub store_dist: bool,
+}
+
+/// Which command produced a search, for its name and reply rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoSearchKind {
+    GeoSearch,
+    GeoSearchStore,
+    GeoRadius,
+    GeoRadiusRo,
+    GeoRadiusByMember,
+    GeoRadiusByMemberRo,
+}
+
+impl GeoSearchKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            GeoSearchKind::GeoSearch => "GEOSEARCH",
+            GeoSearchKind::GeoSearchStore => "GEOSEARCHSTORE",
+            GeoSearchKind::GeoRadius => "GEORADIUS",
+            GeoSearchKind::GeoRadiusRo => "GEORADIUS_RO",
+            GeoSearchKind::GeoRadiusByMember => "GEORADIUSBYMEMBER",
+            GeoSearchKind::GeoRadiusByMemberRo => "GEORADIUSBYMEMBER_RO",
+        }
+    }
+}
+
+/// A search match; `dist` is in meters
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoPoint {
+    pub member: String,
+    pub score: f64,
+    pub lon: f64,
+    pub lat: f64,
+    pub dist: f64,
+}
+
+/// Geohash cell: `step` bits per coordinate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HashBits {
+    bits: u64,
+    step: u8,
+}
+
+impl HashBits {
+    const ZERO: HashBits = HashBits { bits: 0, step: 0 };
+
+    fn is_zero(self) -> bool {
+        self.bits == 0 && self.step == 0
+    }
+
+    /// Score range [min, max) covered by this cell
+    fn score_range(self) -> (f64, f64) {
+        let shift = 52 - self.step as u32 * 2;
+        (
+            (self.bits << shift) as f64,
+            ((self.bits + 1) << shift) as f64,
+        )
+    }
+}
+
+/// Coordinate bounds of a geohash cell
+#[derive(Debug, Clone, Copy)]
+struct Area {
+    lon_min: f64,
+    lon_max: f64,
+    lat_min: f64,
+    lat_max: f64,
+}
+
+/// Spread the low 32 bits of `v` onto the even bits of a u64
+fn spread(v: u32) -> u64 {
+    let mut x = v as u64;
+    x = (x | (x << 16)) & 0x0000_FFFF_0000_FFFF;
+    x = (x | (x << 8)) & 0x00FF_00FF_00FF_00FF;
+    x = (x | (x << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    (x | (x << 1)) & 0x5555_5555_5555_5555
+}
+
+/// Inverse of `spread`: gather the even bits of `v`
+fn squash(v: u64) -> u32 {
+    let mut x = v & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0F0F_0F0F_0F0F_0F0F;
+    x = (x | (x >> 4)) & 0x00FF_00FF_00FF_00FF;
+    x = (x | (x >> 8)) & 0x0000_FFFF_0000_FFFF;
+    ((x | (x >> 16)) & 0x0000_0000_FFFF_FFFF) as u32
+}
+
+/// Latitude on the even bits, longitude on the odd bits
+fn interleave(lat: u32, lon: u32) -> u64 {
+    spread(lat) | (spread(lon) << 1)
+}
+
+/// Returns (latitude, longitude) offsets
+fn deinterleave(bits: u64) -> (u32, u32) {
+    (squash(bits), squash(bits >> 1))
+}
+
+fn deg_rad(deg: f64) -> f64 {
+    deg * (std::f64::consts::PI / 180.0)
+}
+
+fn rad_deg(rad: f64) -> f64 {
+    rad / (std::f64::consts::PI / 180.0)
+}
+
+/// True if the pair is inside the range Redis can index
+pub fn valid_lon_lat(lon: f64, lat: f64) -> bool {
+    (GEO_LONG_MIN..=GEO_LONG_MAX).contains(&lon) && (GEO_LAT_MIN..=GEO_LAT_MAX).contains(&lat)
+}
+
+fn encode_in(lat_range: (f64, f64), lon: f64, lat: f64, step: u8) -> HashBits {
+    debug_assert!(
+        (1..=32).contains(&step),
+        "Precondition violated: geohash step out of range"
+    );
+    debug_assert!(
+        valid_lon_lat(lon, lat),
+        "Precondition violated: coordinates outside the indexable range"
+    );
+    let cells = (1u64 << step) as f64;
+    let lat_offset = (lat - lat_range.0) / (lat_range.1 - lat_range.0) * cells;
+    let lon_offset = (lon - GEO_LONG_MIN) / (GEO_LONG_MAX - GEO_LONG_MIN) * cells;
+    HashBits {
+        bits: interleave(lat_offset as u32, lon_offset as u32),
+        step,
+    }
+}
+
+fn encode(lon: f64, lat: f64, step: u8) -> HashBits {
+    encode_in((GEO_LAT_MIN, GEO_LAT_MAX), lon, lat, step)
+}
+
+fn decode_area(hash: HashBits) -> Area {
+    let (lat, lon) = deinterleave(hash.bits);
+    let cells = (1u64 << hash.step) as f64;
+    let lat_scale = GEO_LAT_MAX - GEO_LAT_MIN;
+    let lon_scale = GEO_LONG_MAX - GEO_LONG_MIN;
+    Area {
+        lat_min: GEO_LAT_MIN + (lat as f64 / cells) * lat_scale,
+        lat_max: GEO_LAT_MIN + ((lat as f64 + 1.0) / cells) * lat_scale,
+        lon_min: GEO_LONG_MIN + (lon as f64 / cells) * lon_scale,
+        lon_max: GEO_LONG_MIN + ((lon as f64 + 1.0) / cells) * lon_scale,
+    }
+}
+
+/// Sorted set score for a coordinate pair, or None outside the indexable range
+pub fn geo_encode(lon: f64, lat: f64) -> Option<f64> {
+    if !valid_lon_lat(lon, lat) {
+        return None;
+    }
+    Some(encode(lon, lat, GEO_STEP_MAX).bits as f64)
+}
+
+/// Centre of the cell a score names, as (longitude, latitude)
+pub fn geo_decode(score: f64) -> (f64, f64) {
+    let area = decode_area(HashBits {
+        bits: score as u64,
+        step: GEO_STEP_MAX,
+    });
+    let lon = ((area.lon_min + area.lon_max) / 2.0).clamp(GEO_LONG_MIN, GEO_LONG_MAX);
+    let lat = ((area.lat_min + area.lat_max) / 2.0).clamp(GEO_LAT_MIN, GEO_LAT_MAX);
+    (lon, lat)
+}
+
+fn lat_distance(lat1: f64, lat2: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (deg_rad(lat2) - deg_rad(lat1)).abs()
+}
+
+/// Haversine distance in meters
+pub fn geo_distance(lon1: f64, lat1: f64, lon2: f64, lat2: f64) -> f64 {
+    let v = ((deg_rad(lon2) - deg_rad(lon1)) / 2.0).sin();
+    // Same meridian: skip the trigonometry
+    if v == 0.0 {
+        return lat_distance(lat1, lat2);
+    }
+    let lat1r = deg_rad(lat1);
+    let lat2r = deg_rad(lat2);
+    let u = ((lat2r - lat1r) / 2.0).sin();
+    let a = u * u + lat1r.cos() * lat2r.cos() * v * v;
+    2.0 * EARTH_RADIUS_IN_METERS * a.sqrt().asin()
+}
+
+/// 11-character standard geohash (GEOHASH reply) for a stored score
+pub fn geohash_string(score: f64) -> String {
+    let (lon, lat) = geo_decode(score);
+    // Standard geohashes use the full [-90, 90] latitude range
+    let hash = encode_in((-90.0, 90.0), lon, lat, GEO_STEP_MAX);
+    (0..11)
+        .map(|i| {
+            // 52 bits fill ten characters; the eleventh is always '0'
+            let idx = if i == 10 {
+                0
+            } else {
+                (hash.bits >> (52 - (i + 1) * 5)) & 0x1f
+            };
+            GEOHASH_ALPHABET[idx as usize] as char
+        })
+        .collect()
+}
+
+/// Coordinate reply format: `%.17Lf` with trailing zeros removed
+pub fn format_coordinate(value: f64) -> String {
+    let s = format!("{:.17}", value);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+/// Distance reply format: four decimals
+pub fn format_distance(value: f64) -> String {
+    format!("{:.4}", value)
+}
+
+fn move_x(hash: &mut HashBits, d: i8) {
+    if d == 0 {
+        return;
+    }
+    let x = hash.bits & 0xaaaa_aaaa_aaaa_aaaa;
+    let y = hash.bits & 0x5555_5555_5555_5555;
+    let zz = 0x5555_5555_5555_5555u64 >> (64 - hash.step as u32 * 2);
+    let x = if d > 0 {
+        x.wrapping_add(zz + 1)
+    } else {
+        (x | zz).wrapping_sub(zz + 1)
+    };
+    let x = x & (0xaaaa_aaaa_aaaa_aaaau64 >> (64 - hash.step as u32 * 2));
+    hash.bits = x | y;
+}
+
+fn move_y(hash: &mut HashBits, d: i8) {
+    if d == 0 {
+        return;
+    }
+    let x = hash.bits & 0xaaaa_aaaa_aaaa_aaaa;
+    let y = hash.bits & 0x5555_5555_5555_5555;
+    let zz = 0xaaaa_aaaa_aaaa_aaaau64 >> (64 - hash.step as u32 * 2);
+    let y = if d > 0 {
+        y.wrapping_add(zz + 1)
+    } else {
+        (y | zz).wrapping_sub(zz + 1)
+    };
+    let y = y & (0x5555_5555_5555_5555u64 >> (64 - hash.step as u32 * 2));
+    hash.bits = x | y;
+}
+
+fn neighbour(hash: HashBits, dx: i8, dy: i8) -> HashBits {
+    let mut n = hash;
+    move_x(&mut n, dx);
+    move_y(&mut n, dy);
+    n
+}
+
+/// Coarsest step whose cells still cover `radius_meters` around `lat`
+fn estimate_steps(radius_meters: f64, lat: f64) -> u8 {
+    if radius_meters == 0.0 {
+        return GEO_STEP_MAX;
+    }
+    let mut range = radius_meters;
+    let mut step: i32 = 1;
+    while range < MERCATOR_MAX {
+        range *= 2.0;
+        step += 1;
+    }
+    step -= 2;
+    // Meridians converge towards the poles, so widen the cells there
+    if !(-66.0..=66.0).contains(&lat) {
+        step -= 1;
+        if !(-80.0..=80.0).contains(&lat) {
+            step -= 1;
+        }
+    }
+    step.clamp(1, GEO_STEP_MAX as i32) as u8
+}
+
+/// The centre cell and its eight neighbours, in Redis' visiting order
+/// (centre, N, S, E, W, NE, NW, SE, SW); cells that cannot intersect the
+/// shape are zeroed
+fn search_cells(lon: f64, lat: f64, shape: GeoShape, unit: GeoUnit) -> [HashBits; 9] {
+    let conversion = unit.to_meters();
+    let (half_width, half_height) = match shape {
+        GeoShape::Radius(r) => (r * conversion, r * conversion),
+        GeoShape::Box { width, height } => (width / 2.0 * conversion, height / 2.0 * conversion),
+    };
+
+    // Bounding box of the shape
+    let lat_delta = rad_deg(half_height / EARTH_RADIUS_IN_METERS);
+    let lon_delta_top =
+        rad_deg(half_width / EARTH_RADIUS_IN_METERS / deg_rad(lat + lat_delta).cos());
+    let lon_delta_bottom =
+        rad_deg(half_width / EARTH_RADIUS_IN_METERS / deg_rad(lat - lat_delta).cos());
+    let lon_delta = if lat < 0.0 {
+        lon_delta_bottom
+    } else {
+        lon_delta_top
+    };
+    let (min_lon, max_lon) = (lon - lon_delta, lon + lon_delta);
+    let (min_lat, max_lat) = (lat - lat_delta, lat + lat_delta);
+
+    let radius_meters = match shape {
+        GeoShape::Radius(_) => half_width,
+        GeoShape::Box { .. } => half_width.hypot(half_height),
+    };
+    let mut steps = estimate_steps(radius_meters, lat);
+    let mut centre = encode(lon, lat, steps);
+
+    // Near a cell edge the estimate can be one step too fine
+    let decrease = decode_area(neighbour(centre, 0, 1)).lat_max < max_lat
+        || decode_area(neighbour(centre, 0, -1)).lat_min > min_lat
+        || decode_area(neighbour(centre, 1, 0)).lon_max < max_lon
+        || decode_area(neighbour(centre, -1, 0)).lon_min > min_lon;
+    if steps > 1 && decrease {
+        steps -= 1;
+        centre = encode(lon, lat, steps);
+    }
+
+    let mut cells = [
+        centre,
+        neighbour(centre, 0, 1),
+        neighbour(centre, 0, -1),
+        neighbour(centre, 1, 0),
+        neighbour(centre, -1, 0),
+        neighbour(centre, 1, 1),
+        neighbour(centre, -1, 1),
+        neighbour(centre, 1, -1),
+        neighbour(centre, -1, -1),
+    ];
+
+    // Drop neighbours on sides the shape does not reach
+    if steps >= 2 {
+        let area = decode_area(centre);
+        let mut drop = |indices: [usize; 3]| {
+            for i in indices {
+                cells[i] = HashBits::ZERO;
+            }
+        };
+        if area.lat_min < min_lat {
+            drop([2, 8, 7]);
+        }
+        if area.lat_max > max_lat {
+            drop([1, 5, 6]);
+        }
+        if area.lon_min < min_lon {
+            drop([4, 8, 6]);
+        }
+        if area.lon_max > max_lon {
+            drop([3, 7, 5]);
+        }
+    }
+    cells
+}
+
+/// Distance in meters from the origin if (lon, lat) lies inside the shape
+fn within_shape(
+    origin: (f64, f64),
+    shape: GeoShape,
+    unit: GeoUnit,
+    lon: f64,
+    lat: f64,
+) -> Option<f64> {
+    let conversion = unit.to_meters();
+    match shape {
+        GeoShape::Radius(radius) => {
+            let dist = geo_distance(origin.0, origin.1, lon, lat);
+            (dist <= radius * conversion).then_some(dist)
+        }
+        GeoShape::Box { width, height } => {
+            if lat_distance(lat, origin.1) > height * conversion / 2.0 {
+                return None;
+            }
+            if geo_distance(lon, lat, origin.0, lat) > width * conversion / 2.0 {
+                return None;
+            }
+            Some(geo_distance(origin.0, origin.1, lon, lat))
+        }
+    }
+}
+
+/// Members of `zset` inside the shape centred on `origin`, in discovery
+/// order. Each candidate cell is read as a score range of the skip list,
+/// so only points near the shape are visited. A non-zero `limit` stops
+/// the search once that many matches were found (COUNT ... ANY).
+pub fn geo_search(
+    zset: &RedisSortedSet,
+    origin: (f64, f64),
+    shape: GeoShape,
+    unit: GeoUnit,
+    limit: usize,
+) -> Vec<GeoPoint> {
+    debug_assert!(
+        valid_lon_lat(origin.0, origin.1),
+        "Precondition violated: search origin outside the indexable range"
+    );
+
+    let cells = search_cells(origin.0, origin.1, shape, unit);
+    let mut found = Vec::new();
+    let mut last_processed = 0;
+    for (i, cell) in cells.iter().enumerate() {
+        if cell.is_zero() {
+            continue;
+        }
+        // Huge radii make neighbours collapse onto the same cell
+        if last_processed != 0 && *cell == cells[last_processed] {
+            continue;
+        }
+        if limit != 0 && found.len() >= limit {
+            break;
+        }
+        let (min, max) = cell.score_range();
+        for (member, score) in zset.iter_score_range(min, max) {
+            let (lon, lat) = geo_decode(score);
+            if let Some(dist) = within_shape(origin, shape, unit, lon, lat) {
+                found.push(GeoPoint {
+                    member: member.to_string(),
+                    score,
+                    lon,
+                    lat,
+                    dist,
+                });
+            }
+            if limit != 0 && found.len() >= limit {
+                break;
+            }
+        }
+        last_processed = i;
+    }
+
+    // TigerStyle: Postconditions
+    debug_assert!(
+        limit == 0 || found.len() <= limit,
+        "Postcondition violated: search returned more than its limit"
+    );
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interleave_roundtrip() {
+        for (lat, lon) in [(0u32, 0u32), (1, 0), (0, 1), (0x3ff_ffff, 0x155_5555)] {
+            assert_eq!(deinterleave(interleave(lat, lon)), (lat, lon));
+        }
+        assert_eq!(interleave(1, 0), 1);
+        assert_eq!(interleave(0, 1), 2);
+    }
+
+    #[test]
+    fn test_encode_matches_redis() {
+        // GEOADD Sicily 13.361389 38.115556 "Palermo" stores this score
+        assert_eq!(geo_encode(13.361389, 38.115556), Some(3479099956230698.0));
+        assert_eq!(geo_encode(15.087269, 37.502669), Some(3479447370796909.0));
+        assert_eq!(geo_encode(0.0, 86.0), None);
+        assert_eq!(geo_encode(181.0, 0.0), None);
+    }
+
+    #[test]
+    fn test_decode_and_format() {
+        let (lon, lat) = geo_decode(3479099956230698.0);
+        assert_eq!(format_coordinate(lon), "13.36138933897018433");
+        assert_eq!(format_coordinate(lat), "38.11555639549629859");
+        assert_eq!(geohash_string(3479099956230698.0), "sqc8b49rny0");
+        assert_eq!(geohash_string(3479447370796909.0), "sqdtr74hyu0");
+    }
+
+    #[test]
+    fn test_distance_matches_redis() {
+        let (lon1, lat1) = geo_decode(3479099956230698.0);
+        let (lon2, lat2) = geo_decode(3479447370796909.0);
+        let meters = geo_distance(lon1, lat1, lon2, lat2);
+        assert_eq!(format_distance(meters), "166274.1516");
+        assert_eq!(
+            format_distance(meters / GeoUnit::Kilometers.to_meters()),
+            "166.2742"
+        );
+        assert_eq!(
+            format_distance(meters / GeoUnit::Miles.to_meters()),
+            "103.3182"
+        );
+    }
+
+    #[test]
+    fn test_search_radius_box_and_limit() {
+        let mut zset = RedisSortedSet::new();
+        let points = [
+            ("Palermo", 13.361389, 38.115556),
+            ("Catania", 15.087269, 37.502669),
+            ("Agrigento", 13.583333, 37.316667),
+            ("Paris", 2.352222, 48.856613),
+        ];
+        for (name, lon, lat) in points {
+            zset.add(SDS::from_str(name), geo_encode(lon, lat).unwrap());
+        }
+
+        let mut names: Vec<String> = geo_search(
+            &zset,
+            (15.0, 37.0),
+            GeoShape::Radius(200.0),
+            GeoUnit::Kilometers,
+            0,
+        )
+        .into_iter()
+        .map(|p| p.member)
+        .collect();
+        names.sort();
+        assert_eq!(names, vec!["Agrigento", "Catania", "Palermo"]);
+
+        let boxed = geo_search(
+            &zset,
+            (15.0, 37.0),
+            GeoShape::Box {
+                width: 400.0,
+                height: 400.0,
+            },
+            GeoUnit::Kilometers,
+            0,
+        );
+        assert_eq!(boxed.len(), 3);
+
+        let limited = geo_search(
+            &zset,
+            (15.0, 37.0),
+            GeoShape::Radius(200.0),
+            GeoUnit::Kilometers,
+            1,
+        );
+        assert_eq!(limited.len(), 1);
+    }
+
+    #[test]
+    fn test_unit_parse() {
+        assert_eq!(GeoUnit::parse("KM"), Ok(GeoUnit::Kilometers));
+        assert_eq!(GeoUnit::parse("ft"), Ok(GeoUnit::Feet));
+        assert!(GeoUnit::parse("yd").is_err());
+    }
+}
@@ -8,7 +8,7 @@
 use super::data::{RedisHash, SDS};
 use crate::io::simulation::SimulatedRng;
 use crate::io::Rng;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 /// Configuration for Hash DST
 #[derive(Debug, Clone)]
@@ -23,6 +23,9 @@ pub struct HashDSTConfig {
     pub delete_prob: f64,
     /// Probability of update (set existing field)
     pub update_prob: f64,
+    /// Probability of giving a field a TTL (HEXPIRE); virtual time advances
+    /// every operation and due fields are reaped
+    pub expire_prob: f64,
 }
 
 impl Default for HashDSTConfig {
@@ -33,6 +36,7 @@ impl Default for HashDSTConfig {
             num_values: 50,
             delete_prob: 0.15,
             update_prob: 0.3,
+            expire_prob: 0.1,
         }
     }
 }
@@ -53,6 +57,7 @@ impl HashDSTConfig {
             num_values: 20,
             delete_prob: 0.2,
             update_prob: 0.5,
+            expire_prob: 0.15,
         }
     }
 
@@ -64,6 +69,7 @@ impl HashDSTConfig {
             num_values: 30,
             delete_prob: 0.4,
             update_prob: 0.3,
+            expire_prob: 0.1,
         }
     }
 }
@@ -73,6 +79,7 @@ impl HashDSTConfig {
 pub enum HashOp {
     Set { field: String, value: String },
     Delete { field: String },
+    Expire { field: String, deadline_ms: u64 },
 }
 
 /// Result of a Hash DST run
@@ -83,6 +90,8 @@ pub struct HashDSTResult {
     pub sets: u64,
     pub updates: u64,
     pub deletes: u64,
+    pub expires: u64,
+    pub expired_fields: u64,
     pub invariant_violations: Vec<String>,
     pub last_op: Option<HashOp>,
 }
@@ -95,6 +104,8 @@ impl HashDSTResult {
             sets: 0,
             updates: 0,
             deletes: 0,
+            expires: 0,
+            expired_fields: 0,
             invariant_violations: Vec::new(),
             last_op: None,
         }
@@ -106,12 +117,14 @@ impl HashDSTResult {
 
     pub fn summary(&self) -> String {
         format!(
-            "Seed {}: {} ops (sets:{}, updates:{}, deletes:{}), {} violations",
+            "Seed {}: {} ops (sets:{}, updates:{}, deletes:{}, expires:{}, expired:{}), {} violations",
             self.seed,
             self.total_operations,
             self.sets,
             self.updates,
             self.deletes,
+            self.expires,
+            self.expired_fields,
             self.invariant_violations.len()
         )
     }
@@ -125,6 +138,9 @@ pub struct HashDSTHarness {
     result: HashDSTResult,
     /// Track expected fields and their values for cross-checking
     expected_fields: HashSet<String>,
+    /// Expected field deadlines, and the virtual clock they are checked against
+    expected_deadlines: HashMap<String, u64>,
+    now_ms: u64,
 }
 
 impl HashDSTHarness {
@@ -136,6 +152,8 @@ impl HashDSTHarness {
             rng,
             hash: RedisHash::new(),
             expected_fields: HashSet::new(),
+            expected_deadlines: HashMap::new(),
+            now_ms: 0,
         }
     }
 
@@ -156,6 +174,31 @@ impl HashDSTHarness {
     fn run_single_op(&mut self) {
         let op_type = self.rng.gen_range(0, 100);
         let delete_threshold = (self.config.delete_prob * 100.0) as u64;
+        let expire_threshold = delete_threshold + (self.config.expire_prob * 100.0) as u64;
+
+        // Advance virtual time and reap due fields, like the active expire cycle
+        self.now_ms += self.rng.gen_range(0, 20);
+        let reaped = self.hash.remove_expired_fields(self.now_ms) as u64;
+        let now_ms = self.now_ms;
+        let due: Vec<String> = self
+            .expected_deadlines
+            .iter()
+            .filter(|(_, &deadline)| deadline <= now_ms)
+            .map(|(field, _)| field.clone())
+            .collect();
+        for field in &due {
+            self.expected_deadlines.remove(field);
+            self.expected_fields.remove(field);
+        }
+        if reaped != due.len() as u64 {
+            self.result.invariant_violations.push(format!(
+                "Reaped {} fields at {}ms, expected {}",
+                reaped,
+                now_ms,
+                due.len()
+            ));
+        }
+        self.result.expired_fields += reaped;
 
         if op_type < delete_threshold && !self.expected_fields.is_empty() {
             // Delete operation - pick an existing field
@@ -164,10 +207,32 @@ impl HashDSTHarness {
                 field: field.clone(),
             });
             let existed = self.expected_fields.remove(&field);
+            self.expected_deadlines.remove(&field);
             self.hash.delete(&SDS::from_str(&field));
             if existed {
                 self.result.deletes += 1;
             }
+        } else if op_type < expire_threshold {
+            // Expire operation - only existing fields take a TTL
+            let field = self.random_field();
+            let deadline_ms = self.now_ms + 1 + self.rng.gen_range(0, 500);
+            self.result.last_op = Some(HashOp::Expire {
+                field: field.clone(),
+                deadline_ms,
+            });
+            let set = self
+                .hash
+                .set_field_deadline(&SDS::from_str(&field), deadline_ms);
+            if set != self.expected_fields.contains(&field) {
+                self.result.invariant_violations.push(format!(
+                    "set_field_deadline returned {} for field '{}'",
+                    set, field
+                ));
+            }
+            if set {
+                self.expected_deadlines.insert(field, deadline_ms);
+                self.result.expires += 1;
+            }
         } else {
             // Set operation
             let field = self.random_field();
@@ -179,6 +244,8 @@ impl HashDSTHarness {
 
             let is_update = self.expected_fields.contains(&field);
             self.hash.set(SDS::from_str(&field), SDS::from_str(&value));
+            // Writing a field clears its TTL
+            self.expected_deadlines.remove(&field);
             self.expected_fields.insert(field);
 
             if is_update {
@@ -242,6 +309,27 @@ impl HashDSTHarness {
             }
         }
 
+        // Invariant 6: Field deadlines match, and none is already due
+        for field in &self.expected_fields {
+            let actual = self.hash.field_deadline(&SDS::from_str(field));
+            if actual != self.expected_deadlines.get(field).copied() {
+                return Err(format!(
+                    "Deadline mismatch for '{}': actual={:?}, expected={:?}",
+                    field,
+                    actual,
+                    self.expected_deadlines.get(field)
+                ));
+            }
+        }
+        let next = self.expected_deadlines.values().min().copied();
+        if self.hash.next_field_deadline() != next {
+            return Err(format!(
+                "Next deadline mismatch: actual={:?}, expected={:?}",
+                self.hash.next_field_deadline(),
+                next
+            ));
+        }
+
         Ok(())
     }
 
@@ -332,6 +420,20 @@ mod tests {
         assert!(result.is_success());
     }
 
+    #[test]
+    fn test_hash_dst_field_expiry() {
+        let config = HashDSTConfig {
+            expire_prob: 0.4,
+            ..HashDSTConfig::small_fields(7)
+        };
+        let mut harness = HashDSTHarness::new(config);
+        harness.run(1000);
+        let result = harness.result();
+        println!("{}", result.summary());
+        assert!(result.is_success(), "{:?}", result.invariant_violations);
+        assert!(result.expires > 0 && result.expired_fields > 0);
+    }
+
     #[test]
     fn test_hash_dst_10_seeds() {
         let results = run_hash_batch(0, 10, 500, HashDSTConfig::new);
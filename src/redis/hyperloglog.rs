@@ -0,0 +1,635 @@
+//! HyperLogLog cardinality estimation (PFADD, PFCOUNT, PFMERGE)
+//!
+//! HLLs are plain `SDS` strings with Redis's exact layout, so GET (and
+//! replication of the raw bytes) round-trips with a real Redis:
+//!
+//! ```text
+//! +------+---+-----+----------+
+//! | HYLL | E | N/U | Cardin.  |  16-byte header, then registers
+//! +------+---+-----+----------+
+//! ```
+//!
+//! `E` is 0 for the dense encoding (16384 packed 6-bit registers) and 1 for
+//! the sparse run-length encoding used while most registers are still zero.
+//! The last 8 header bytes cache the cardinality (little endian); the top bit
+//! of the last byte marks the cache stale. Sparse HLLs are promoted to dense
+//! once a register exceeds 32 or the string would grow past 3000 bytes.
+
+use super::data::SDS;
+
+/// Precision: 2^14 registers, ~0.81% standard error
+const HLL_P: u32 = 14;
+/// Bits of the hash left for the run of zeroes
+const HLL_Q: u32 = 64 - HLL_P;
+pub const HLL_REGISTERS: usize = 1 << HLL_P;
+const HLL_P_MASK: u64 = HLL_REGISTERS as u64 - 1;
+const HLL_BITS: usize = 6;
+const HLL_REGISTER_MAX: u8 = (1 << HLL_BITS) - 1;
+const HLL_HDR_SIZE: usize = 16;
+const HLL_DENSE_SIZE: usize = HLL_HDR_SIZE + (HLL_REGISTERS * HLL_BITS).div_ceil(8);
+const HLL_DENSE: u8 = 0;
+const HLL_SPARSE: u8 = 1;
+/// Offset of the encoding byte and of the cached cardinality in the header
+const HLL_ENCODING_OFFSET: usize = 4;
+const HLL_CARD_OFFSET: usize = 8;
+/// Redis's `hll-sparse-max-bytes` default (header included)
+const HLL_SPARSE_MAX_BYTES: usize = 3000;
+const HLL_ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+const HLL_HASH_SEED: u64 = 0xadc8_3b19;
+
+// Sparse opcodes: ZERO 00xxxxxx, XZERO 01xxxxxx yyyyyyyy, VAL 1vvvvvxx
+const SPARSE_XZERO_BIT: u8 = 0x40;
+const SPARSE_VAL_BIT: u8 = 0x80;
+const SPARSE_VAL_MAX_VALUE: u8 = 32;
+const SPARSE_VAL_MAX_LEN: usize = 4;
+const SPARSE_ZERO_MAX_LEN: usize = 64;
+const SPARSE_XZERO_MAX_LEN: usize = 16384;
+
+/// Why a string cannot be used as an HLL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HllError {
+    /// Not an HLL at all (bad magic, encoding or length)
+    WrongType,
+    /// Looks like an HLL but the sparse registers do not add up
+    Corrupt,
+}
+
+impl HllError {
+    pub fn message(self) -> &'static str {
+        match self {
+            HllError::WrongType => "WRONGTYPE Key is not a valid HyperLogLog string value.",
+            HllError::Corrupt => "INVALIDOBJ Corrupted HLL object detected",
+        }
+    }
+}
+
+/// Sparse opcode decoded at some offset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SparseOp {
+    Zero(usize),
+    XZero(usize),
+    Val { value: u8, len: usize },
+}
+
+impl SparseOp {
+    #[inline]
+    fn decode(bytes: &[u8], at: usize) -> Option<SparseOp> {
+        let op = *bytes.get(at)?;
+        if op & SPARSE_VAL_BIT != 0 {
+            Some(SparseOp::Val {
+                value: ((op >> 2) & 0x1f) + 1,
+                len: (op & 0x3) as usize + 1,
+            })
+        } else if op & SPARSE_XZERO_BIT != 0 {
+            let low = *bytes.get(at + 1)?;
+            Some(SparseOp::XZero(
+                ((((op & 0x3f) as usize) << 8) | low as usize) + 1,
+            ))
+        } else {
+            Some(SparseOp::Zero((op & 0x3f) as usize + 1))
+        }
+    }
+
+    /// Registers covered by this opcode
+    #[inline]
+    fn span(self) -> usize {
+        match self {
+            SparseOp::Zero(len) | SparseOp::XZero(len) | SparseOp::Val { len, .. } => len,
+        }
+    }
+
+    /// Encoded size in bytes
+    #[inline]
+    fn size(self) -> usize {
+        match self {
+            SparseOp::XZero(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Append the encoding of a run of `len` zero registers
+    fn push_zeros(out: &mut Vec<u8>, len: usize) {
+        debug_assert!((1..=SPARSE_XZERO_MAX_LEN).contains(&len));
+        if len > SPARSE_ZERO_MAX_LEN {
+            let encoded = len - 1;
+            out.push((encoded >> 8) as u8 | SPARSE_XZERO_BIT);
+            out.push((encoded & 0xff) as u8);
+        } else {
+            out.push((len - 1) as u8);
+        }
+    }
+}
+
+#[inline]
+fn sparse_val(value: u8, len: usize) -> u8 {
+    debug_assert!((1..=SPARSE_VAL_MAX_VALUE).contains(&value));
+    debug_assert!((1..=SPARSE_VAL_MAX_LEN).contains(&len));
+    (((value - 1) << 2) | (len - 1) as u8) | SPARSE_VAL_BIT
+}
+
+/// MurmurHash2, 64-bit version, as used by Redis for HLL elements
+fn murmurhash64a(data: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (data.len() as u64).wrapping_mul(M);
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut k = u64::from_le_bytes(chunk.try_into().expect("8-byte chunk"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+
+    let tail = chunks.remainder();
+    if !tail.is_empty() {
+        for (i, &byte) in tail.iter().enumerate().rev() {
+            h ^= (byte as u64) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
+
+/// Register index of `element` and the length of its run of zeroes plus one
+pub fn hll_pattern(element: &[u8]) -> (usize, u8) {
+    let hash = murmurhash64a(element, HLL_HASH_SEED);
+    let index = (hash & HLL_P_MASK) as usize;
+    // The sentinel bit bounds the count to Q + 1
+    let rest = (hash >> HLL_P) | (1 << HLL_Q);
+    let count = rest.trailing_zeros() as u8 + 1;
+
+    // TigerStyle: Postconditions
+    debug_assert!(index < HLL_REGISTERS);
+    debug_assert!((1..=HLL_Q as u8 + 1).contains(&count));
+    (index, count)
+}
+
+/// A new, empty HLL: sparse, with a valid cached cardinality of zero
+pub fn hll_new() -> Vec<u8> {
+    let runs = HLL_REGISTERS.div_ceil(SPARSE_XZERO_MAX_LEN);
+    let mut hll = Vec::with_capacity(HLL_HDR_SIZE + runs * 2);
+    hll.extend_from_slice(b"HYLL");
+    hll.push(HLL_SPARSE);
+    hll.resize(HLL_HDR_SIZE, 0);
+    let mut remaining = HLL_REGISTERS;
+    while remaining > 0 {
+        let run = remaining.min(SPARSE_XZERO_MAX_LEN);
+        SparseOp::push_zeros(&mut hll, run);
+        remaining -= run;
+    }
+    hll
+}
+
+/// Check the header the way Redis does before touching an HLL
+pub fn hll_validate(hll: &[u8]) -> Result<(), HllError> {
+    if hll.len() < HLL_HDR_SIZE || &hll[..4] != b"HYLL" {
+        return Err(HllError::WrongType);
+    }
+    match hll[HLL_ENCODING_OFFSET] {
+        HLL_SPARSE => Ok(()),
+        HLL_DENSE if hll.len() == HLL_DENSE_SIZE => Ok(()),
+        _ => Err(HllError::WrongType),
+    }
+}
+
+#[inline]
+fn is_sparse(hll: &[u8]) -> bool {
+    hll[HLL_ENCODING_OFFSET] == HLL_SPARSE
+}
+
+/// True for the dense encoding (call after `hll_validate`)
+pub fn hll_is_dense(hll: &[u8]) -> bool {
+    hll[HLL_ENCODING_OFFSET] == HLL_DENSE
+}
+
+/// Mark the cached cardinality stale
+pub fn hll_invalidate_cache(hll: &mut [u8]) {
+    hll[HLL_CARD_OFFSET + 7] |= 1 << 7;
+}
+
+#[inline]
+fn dense_get(registers: &[u8], index: usize) -> u8 {
+    let byte = index * HLL_BITS / 8;
+    let shift = (index * HLL_BITS) & 7;
+    let low = registers[byte] as u16;
+    let high = registers.get(byte + 1).copied().unwrap_or(0) as u16;
+    (((low >> shift) | (high << (8 - shift))) as u8) & HLL_REGISTER_MAX
+}
+
+#[inline]
+fn dense_put(registers: &mut [u8], index: usize, value: u8) {
+    debug_assert!(value <= HLL_REGISTER_MAX);
+    let byte = index * HLL_BITS / 8;
+    let shift = (index * HLL_BITS) & 7;
+    let value = value as u16;
+    let mask = HLL_REGISTER_MAX as u16;
+    registers[byte] &= !((mask << shift) as u8);
+    registers[byte] |= (value << shift) as u8;
+    if byte + 1 < registers.len() {
+        registers[byte + 1] &= !((mask >> (8 - shift)) as u8);
+        registers[byte + 1] |= (value >> (8 - shift)) as u8;
+    }
+}
+
+/// Raise a dense register to `count`; true if it changed
+fn dense_set(hll: &mut [u8], index: usize, count: u8) -> bool {
+    let registers = &mut hll[HLL_HDR_SIZE..];
+    if count > dense_get(registers, index) {
+        dense_put(registers, index, count);
+        true
+    } else {
+        false
+    }
+}
+
+/// Rewrite a sparse HLL as dense, keeping the header (and cached cardinality)
+fn sparse_to_dense(hll: &mut Vec<u8>) -> Result<(), HllError> {
+    if !is_sparse(hll) {
+        return Ok(());
+    }
+    let mut dense = vec![0u8; HLL_DENSE_SIZE];
+    dense[..HLL_HDR_SIZE].copy_from_slice(&hll[..HLL_HDR_SIZE]);
+    dense[HLL_ENCODING_OFFSET] = HLL_DENSE;
+
+    let mut index = 0;
+    let mut at = HLL_HDR_SIZE;
+    while let Some(op) = SparseOp::decode(hll, at) {
+        if let SparseOp::Val { value, len } = op {
+            if index + len > HLL_REGISTERS {
+                break;
+            }
+            for register in index..index + len {
+                dense_put(&mut dense[HLL_HDR_SIZE..], register, value);
+            }
+        }
+        index += op.span();
+        at += op.size();
+    }
+    if index != HLL_REGISTERS {
+        return Err(HllError::Corrupt);
+    }
+    *hll = dense;
+    Ok(())
+}
+
+/// Raise a sparse register to `count`, splitting the opcode that covers it.
+/// Promotes to dense when the value does not fit a VAL opcode or the string
+/// would outgrow `HLL_SPARSE_MAX_BYTES`. True if the register changed.
+fn sparse_set(hll: &mut Vec<u8>, index: usize, count: u8) -> Result<bool, HllError> {
+    if count > SPARSE_VAL_MAX_VALUE {
+        return promote_and_set(hll, index, count);
+    }
+
+    // Step 1: find the opcode covering `index`
+    let mut at = HLL_HDR_SIZE;
+    let mut prev = None;
+    let mut first = 0;
+    let op = loop {
+        let op = SparseOp::decode(hll, at).ok_or(HllError::Corrupt)?;
+        if index < first + op.span() {
+            break op;
+        }
+        prev = Some(at);
+        at += op.size();
+        first += op.span();
+    };
+
+    // Step 2: cheap cases that update the opcode in place
+    match op {
+        SparseOp::Val { value, .. } if value >= count => return Ok(false),
+        SparseOp::Val { len: 1, .. } | SparseOp::Zero(1) => {
+            hll[at] = sparse_val(count, 1);
+            merge_adjacent_vals(hll, prev.unwrap_or(HLL_HDR_SIZE));
+            hll_invalidate_cache(hll);
+            return Ok(true);
+        }
+        _ => {}
+    }
+
+    // Step 3: split the run into up to 5 opcodes around the new value
+    let last = first + op.span() - 1;
+    let mut seq = Vec::with_capacity(5);
+    match op {
+        SparseOp::Val { value, .. } => {
+            if index != first {
+                seq.push(sparse_val(value, index - first));
+            }
+            seq.push(sparse_val(count, 1));
+            if index != last {
+                seq.push(sparse_val(value, last - index));
+            }
+        }
+        SparseOp::Zero(_) | SparseOp::XZero(_) => {
+            if index != first {
+                SparseOp::push_zeros(&mut seq, index - first);
+            }
+            seq.push(sparse_val(count, 1));
+            if index != last {
+                SparseOp::push_zeros(&mut seq, last - index);
+            }
+        }
+    }
+    if seq.len() > op.size() && hll.len() + seq.len() - op.size() > HLL_SPARSE_MAX_BYTES {
+        return promote_and_set(hll, index, count);
+    }
+    hll.splice(at..at + op.size(), seq);
+
+    // Step 4: coalesce equal neighbouring VALs
+    merge_adjacent_vals(hll, prev.unwrap_or(HLL_HDR_SIZE));
+    hll_invalidate_cache(hll);
+    Ok(true)
+}
+
+fn promote_and_set(hll: &mut Vec<u8>, index: usize, count: u8) -> Result<bool, HllError> {
+    sparse_to_dense(hll)?;
+    let updated = dense_set(hll, index, count);
+    debug_assert!(updated, "promotion only happens for a register that grows");
+    Ok(updated)
+}
+
+/// Scan up to 5 opcodes from `at`, merging adjacent VALs with the same value
+fn merge_adjacent_vals(hll: &mut Vec<u8>, mut at: usize) {
+    for _ in 0..5 {
+        let Some(op) = SparseOp::decode(hll, at) else {
+            break;
+        };
+        let SparseOp::Val { value, len } = op else {
+            at += op.size();
+            continue;
+        };
+        if let Some(SparseOp::Val {
+            value: next_value,
+            len: next_len,
+        }) = SparseOp::decode(hll, at + 1)
+        {
+            if value == next_value && len + next_len <= SPARSE_VAL_MAX_LEN {
+                hll[at + 1] = sparse_val(value, len + next_len);
+                hll.remove(at);
+                continue;
+            }
+        }
+        at += 1;
+    }
+}
+
+/// Raise register `index` to `count` whatever the encoding; true if it changed
+pub fn hll_set(hll: &mut Vec<u8>, index: usize, count: u8) -> Result<bool, HllError> {
+    debug_assert!(index < HLL_REGISTERS);
+    if is_sparse(hll) {
+        sparse_set(hll, index, count)
+    } else {
+        Ok(dense_set(hll, index, count))
+    }
+}
+
+/// Add an element; true if a register changed (the cache is then stale)
+pub fn hll_add(hll: &mut Vec<u8>, element: &[u8]) -> Result<bool, HllError> {
+    let (index, count) = hll_pattern(element);
+    let updated = hll_set(hll, index, count)?;
+    if updated {
+        hll_invalidate_cache(hll);
+    }
+    Ok(updated)
+}
+
+/// Fold this HLL into `max`, one byte per register (Redis `hllMerge`)
+pub fn hll_merge_into(hll: &[u8], max: &mut [u8]) -> Result<(), HllError> {
+    debug_assert_eq!(max.len(), HLL_REGISTERS);
+    if !is_sparse(hll) {
+        let registers = &hll[HLL_HDR_SIZE..];
+        for (index, slot) in max.iter_mut().enumerate() {
+            *slot = (*slot).max(dense_get(registers, index));
+        }
+        return Ok(());
+    }
+
+    let mut index = 0;
+    let mut at = HLL_HDR_SIZE;
+    while let Some(op) = SparseOp::decode(hll, at) {
+        if let SparseOp::Val { value, len } = op {
+            if index + len > HLL_REGISTERS {
+                break;
+            }
+            for slot in &mut max[index..index + len] {
+                *slot = (*slot).max(value);
+            }
+        }
+        index += op.span();
+        at += op.size();
+    }
+    if index != HLL_REGISTERS {
+        return Err(HllError::Corrupt);
+    }
+    Ok(())
+}
+
+/// All registers, one byte each
+pub fn hll_registers(hll: &[u8]) -> Result<Vec<u8>, HllError> {
+    hll_validate(hll)?;
+    let mut registers = vec![0u8; HLL_REGISTERS];
+    hll_merge_into(hll, &mut registers)?;
+    Ok(registers)
+}
+
+/// Write merged registers into `hll` the way PFMERGE does: go dense first if
+/// any input was dense, raise each nonzero register in index order (which may
+/// promote a sparse HLL on the way) and mark the cache stale
+pub fn hll_store(hll: &mut Vec<u8>, registers: &[u8], dense: bool) -> Result<(), HllError> {
+    debug_assert_eq!(registers.len(), HLL_REGISTERS);
+    if dense {
+        sparse_to_dense(hll)?;
+    }
+    for (index, &count) in registers.iter().enumerate() {
+        if count > 0 {
+            hll_set(hll, index, count)?;
+        }
+    }
+    hll_invalidate_cache(hll);
+    Ok(())
+}
+
+/// The HLL Redis's PFMERGE writes into a new key from `registers`
+pub fn hll_from_registers(registers: &[u8]) -> Vec<u8> {
+    let mut hll = hll_new();
+    hll_store(&mut hll, registers, false).expect("freshly built HLL is well formed");
+    hll
+}
+
+/// Estimated cardinality of raw registers (Ertl's improved estimator)
+pub fn hll_estimate(registers: &[u8]) -> u64 {
+    debug_assert_eq!(registers.len(), HLL_REGISTERS);
+    let mut histogram = [0u32; 64];
+    for &register in registers {
+        histogram[register as usize] += 1;
+    }
+
+    let m = HLL_REGISTERS as f64;
+    let q = HLL_Q as usize;
+    let mut z = m * tau((m - histogram[q + 1] as f64) / m);
+    for j in (1..=q).rev() {
+        z += histogram[j] as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histogram[0] as f64 / m);
+    (HLL_ALPHA_INF * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// Cardinality of a single HLL, served from and stored into the header cache.
+/// The flag is true when the cache was refreshed (the string changed).
+pub fn hll_count(hll: &mut [u8]) -> Result<(u64, bool), HllError> {
+    let card = &mut hll[HLL_CARD_OFFSET..HLL_HDR_SIZE];
+    if card[7] & (1 << 7) == 0 {
+        let cached = u64::from_le_bytes(card.try_into().expect("8-byte cardinality"));
+        return Ok((cached, false));
+    }
+    let mut registers = vec![0u8; HLL_REGISTERS];
+    hll_merge_into(hll, &mut registers)?;
+    let count = hll_estimate(&registers);
+    hll[HLL_CARD_OFFSET..HLL_HDR_SIZE].copy_from_slice(&count.to_le_bytes());
+    Ok((count, true))
+}
+
+impl SDS {
+    /// The bytes as an owned, growable buffer, for in-place HLL updates
+    pub(crate) fn heap_bytes_mut(&mut self) -> &mut Vec<u8> {
+        if let SDS::Inline { .. } = self {
+            *self = SDS::Heap(self.as_bytes().to_vec());
+        }
+        match self {
+            SDS::Heap(bytes) => bytes,
+            SDS::Inline { .. } => unreachable!("promoted to heap above"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_murmurhash64a_pattern() {
+        assert_eq!(murmurhash64a(b"", 0), 0);
+        assert_eq!(murmurhash64a(b"a", HLL_HASH_SEED), 0x53d2_470a_9b43_b1a7);
+        assert_eq!(
+            murmurhash64a(b"hello world!", HLL_HASH_SEED),
+            0x0fc4_4401_1f57_220c
+        );
+        assert_eq!(hll_pattern(b"a"), (12711, 2));
+        assert_eq!(hll_pattern(b"hello"), (9216, 1));
+    }
+
+    #[test]
+    fn test_new_hll_layout() {
+        let hll = hll_new();
+        assert_eq!(hll, b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f\xff".to_vec());
+        assert_eq!(hll_validate(&hll), Ok(()));
+        assert_eq!(hll_validate(b"HYLL"), Err(HllError::WrongType));
+        assert_eq!(
+            hll_validate(b"hello world, not an hll"),
+            Err(HllError::WrongType)
+        );
+    }
+
+    #[test]
+    fn test_sparse_set_splits_and_merges_runs() {
+        let mut hll = hll_new();
+        assert_eq!(hll_set(&mut hll, 100, 3), Ok(true));
+        assert_eq!(hll_set(&mut hll, 100, 2), Ok(false));
+        // XZERO(100) VAL(3,1) XZERO(16283)
+        assert_eq!(hll.len(), HLL_HDR_SIZE + 5);
+        assert_eq!(hll_set(&mut hll, 101, 3), Ok(true));
+        // The two VAL(3,1) opcodes coalesce into VAL(3,2)
+        assert_eq!(hll.len(), HLL_HDR_SIZE + 5);
+        let registers = hll_registers(&hll).unwrap();
+        assert_eq!(&registers[99..103], &[0, 3, 3, 0]);
+    }
+
+    #[test]
+    fn test_promotes_to_dense() {
+        let mut hll = hll_new();
+        assert_eq!(hll_set(&mut hll, 7, 40), Ok(true));
+        assert!(!is_sparse(&hll));
+        assert_eq!(hll.len(), HLL_DENSE_SIZE);
+        assert_eq!(hll_registers(&hll).unwrap()[7], 40);
+
+        let mut hll = hll_new();
+        for i in 0..5000u32 {
+            hll_add(&mut hll, format!("element:{}", i).as_bytes()).unwrap();
+        }
+        assert!(!is_sparse(&hll));
+    }
+
+    #[test]
+    fn test_count_is_close_and_cached() {
+        let mut hll = hll_new();
+        assert_eq!(hll_count(&mut hll), Ok((0, false)));
+        for i in 0..10_000u32 {
+            hll_add(&mut hll, format!("user:{}", i).as_bytes()).unwrap();
+        }
+        let (count, refreshed) = hll_count(&mut hll).unwrap();
+        assert!(refreshed);
+        assert!((9_800..=10_200).contains(&count), "estimate {}", count);
+        assert_eq!(hll_count(&mut hll), Ok((count, false)));
+    }
+
+    #[test]
+    fn test_from_registers_round_trips() {
+        let mut hll = hll_new();
+        for i in 0..300u32 {
+            hll_add(&mut hll, format!("k{}", i).as_bytes()).unwrap();
+        }
+        let registers = hll_registers(&hll).unwrap();
+        let rebuilt = hll_from_registers(&registers);
+        assert_eq!(hll_registers(&rebuilt).unwrap(), registers);
+        assert_eq!(hll_estimate(&registers), hll_count(&mut hll).unwrap().0);
+    }
+
+    #[test]
+    fn test_corrupt_sparse_detected() {
+        let mut hll = hll_new();
+        hll.truncate(HLL_HDR_SIZE + 1);
+        assert_eq!(hll_registers(&hll), Err(HllError::Corrupt));
+        assert_eq!(hll_add(&mut hll, b"x"), Err(HllError::Corrupt));
+    }
+}
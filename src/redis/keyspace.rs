@@ -0,0 +1,110 @@
+//! Logical databases (SELECT)
+//!
+//! Every executor holds `databases` independent keyspaces. Commands run
+//! against the selected one, which lives directly in the executor's fields so
+//! single-database workloads pay nothing for the feature; the others are
+//! parked in a `Database` each. Selecting another database trades the two
+//! keyspaces with `std::mem::swap`, which is O(1) whatever their size.
+//!
+//! WATCH version stamps are part of the keyspace, so a write to the same name
+//! in another database never aborts a transaction. They come from one clock
+//! shared by all databases, so a stamp is never reused after a swap. Blocked
+//! clients remember the database they blocked in and are only served from it.
+
+use super::data::{Value, SDS};
+use super::eviction::MemoryTracker;
+use super::expiry::ExpiryIndex;
+use crate::simulator::VirtualTime;
+use ahash::AHashMap;
+
+/// Number of databases when `databases` is not configured (as in Redis)
+pub const DEFAULT_DATABASES: usize = 16;
+
+/// Keyspace of a database that is not selected
+#[derive(Debug, Default)]
+pub struct Database {
+    pub data: AHashMap<SDS, Value>,
+    pub expirations: ExpiryIndex,
+    pub field_expirations: ExpiryIndex,
+    pub access_times: AHashMap<SDS, VirtualTime>,
+    pub memory: MemoryTracker,
+    pub key_versions: AHashMap<SDS, u64>,
+    pub missing_key_version: u64,
+}
+
+impl Database {
+    /// Earliest key or hash field deadline
+    pub fn next_deadline(&self) -> Option<VirtualTime> {
+        match (
+            self.expirations.next_deadline(),
+            self.field_expirations.next_deadline(),
+        ) {
+            (Some(key), Some(field)) => Some(key.min(field)),
+            (key, field) => key.or(field),
+        }
+    }
+}
+
+/// Key counts of one database, summed across shards for INFO
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyspaceStats {
+    pub keys: usize,
+    /// Keys with a TTL
+    pub expires: usize,
+    /// Remaining TTLs of the `expires` keys added up, for avg_ttl
+    pub ttl_sum_ms: u64,
+}
+
+impl KeyspaceStats {
+    /// Add another shard's counts for the same database
+    pub fn merge(&mut self, other: &KeyspaceStats) {
+        self.keys += other.keys;
+        self.expires += other.expires;
+        self.ttl_sum_ms += other.ttl_sum_ms;
+    }
+}
+
+/// INFO `# Keyspace` section: one line per non-empty database, indexed by position
+pub fn keyspace_info(stats: &[KeyspaceStats]) -> String {
+    let mut info = String::from("# Keyspace\r\n");
+    for (db, stats) in stats.iter().enumerate().filter(|(_, s)| s.keys > 0) {
+        let avg_ttl = match stats.expires {
+            0 => 0,
+            expires => stats.ttl_sum_ms / expires as u64,
+        };
+        info.push_str(&format!(
+            "db{}:keys={},expires={},avg_ttl={}\r\n",
+            db, stats.keys, stats.expires, avg_ttl
+        ));
+    }
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyspace_info_skips_empty_databases() {
+        let mut db3 = KeyspaceStats {
+            keys: 2,
+            expires: 1,
+            ttl_sum_ms: 3000,
+        };
+        db3.merge(&KeyspaceStats {
+            keys: 1,
+            expires: 1,
+            ttl_sum_ms: 1000,
+        });
+        let mut stats = vec![KeyspaceStats::default(); 4];
+        stats[0].keys = 5;
+        stats[3] = db3;
+        assert_eq!(
+            keyspace_info(&stats),
+            "# Keyspace\r\n\
+             db0:keys=5,expires=0,avg_ttl=0\r\n\
+             db3:keys=3,expires=2,avg_ttl=2000\r\n"
+        );
+        assert_eq!(keyspace_info(&[]), "# Keyspace\r\n");
+    }
+}
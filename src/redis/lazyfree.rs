@@ -0,0 +1,84 @@
+//! Background freeing of unlinked values (Redis lazyfree)
+//!
+//! Dropping a big collection walks every element, which would stall the shard
+//! for as long as it takes. UNLINK detaches the value from the keyspace right
+//! away and hands it to a dedicated thread that drops it. Values whose free
+//! effort is at most `LAZYFREE_THRESHOLD` are dropped inline, where the
+//! hand-off would cost more than the free itself.
+//!
+//! Freeing has no observable effect on replies, so the thread does not break
+//! DST determinism.
+
+use super::data::Value;
+use std::sync::mpsc;
+use std::sync::OnceLock;
+
+/// Values costing more than this many element frees go to the background
+/// thread (Redis `LAZYFREE_THRESHOLD`)
+pub const LAZYFREE_THRESHOLD: usize = 64;
+
+static FREER: OnceLock<mpsc::Sender<Value>> = OnceLock::new();
+
+/// Number of allocations dropping `value` frees: one per element for
+/// collections, one for a string
+pub fn free_effort(value: &Value) -> usize {
+    match value {
+        Value::List(l) => l.len(),
+        Value::Set(s) => s.len(),
+        Value::Hash(h) => h.len(),
+        Value::SortedSet(zs) => zs.len(),
+        Value::Stream(stream) => stream.len(),
+        Value::String(_) | Value::Null => 1,
+    }
+}
+
+/// Drop `value`, on the background thread if it is big. Returns true if the
+/// value was handed off.
+pub fn free(value: Value) -> bool {
+    if free_effort(&value) <= LAZYFREE_THRESHOLD {
+        return false;
+    }
+    // If the thread could not be spawned its receiver is gone, the send fails
+    // and the value is dropped here instead
+    freer().send(value).is_ok()
+}
+
+fn freer() -> &'static mpsc::Sender<Value> {
+    FREER.get_or_init(|| {
+        let (tx, rx) = mpsc::channel::<Value>();
+        let _ = std::thread::Builder::new()
+            .name("lazyfree".to_string())
+            .spawn(move || {
+                for value in rx {
+                    drop(value);
+                }
+            });
+        tx
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis::{RedisList, SDS};
+
+    fn list(len: usize) -> Value {
+        let mut l = RedisList::new();
+        for i in 0..len {
+            l.rpush(SDS::from_str(&i.to_string()));
+        }
+        Value::List(l)
+    }
+
+    #[test]
+    fn test_small_values_are_freed_inline() {
+        assert!(!free(Value::String(SDS::from_str("v"))));
+        assert!(!free(list(LAZYFREE_THRESHOLD)));
+    }
+
+    #[test]
+    fn test_big_values_go_to_the_background() {
+        assert_eq!(free_effort(&list(1000)), 1000);
+        assert!(free(list(1000)));
+    }
+}
@@ -8,6 +8,7 @@
 use super::data::{RedisList, SDS};
 use crate::io::simulation::SimulatedRng;
 use crate::io::Rng;
+use std::collections::VecDeque;
 
 /// Configuration for List DST
 #[derive(Debug, Clone)]
@@ -24,6 +25,12 @@ pub struct ListDSTConfig {
     pub lset_prob: f64,
     /// Probability of trim operation
     pub trim_prob: f64,
+    /// Probability of linsert operation
+    pub insert_prob: f64,
+    /// Probability of lrem operation
+    pub rem_prob: f64,
+    /// Probability of lpos lookup
+    pub pos_prob: f64,
 }
 
 impl Default for ListDSTConfig {
@@ -35,6 +42,9 @@ impl Default for ListDSTConfig {
             left_prob: 0.5,
             lset_prob: 0.05,
             trim_prob: 0.02,
+            insert_prob: 0.05,
+            rem_prob: 0.03,
+            pos_prob: 0.05,
         }
     }
 }
@@ -56,10 +66,13 @@ impl ListDSTConfig {
             left_prob: 0.5,
             lset_prob: 0.02,
             trim_prob: 0.01,
+            insert_prob: 0.02,
+            rem_prob: 0.02,
+            pos_prob: 0.02,
         }
     }
 
-    /// Configuration focusing on modifications (lset, trim)
+    /// Configuration focusing on modifications (lset, trim, linsert, lrem)
     pub fn modify_heavy(seed: u64) -> Self {
         ListDSTConfig {
             seed,
@@ -68,6 +81,9 @@ impl ListDSTConfig {
             left_prob: 0.5,
             lset_prob: 0.15,
             trim_prob: 0.05,
+            insert_prob: 0.1,
+            rem_prob: 0.05,
+            pos_prob: 0.05,
         }
     }
 }
@@ -81,6 +97,9 @@ pub enum ListOp {
     RPop,
     LSet { index: isize, value: String },
     Trim { start: isize, stop: isize },
+    Insert { pivot: String, value: String, before: bool },
+    Rem { count: i64, value: String },
+    Pos { value: String, rank: i64, count: usize, maxlen: usize },
 }
 
 /// Result of a List DST run
@@ -94,6 +113,9 @@ pub struct ListDSTResult {
     pub rpops: u64,
     pub lsets: u64,
     pub trims: u64,
+    pub inserts: u64,
+    pub rems: u64,
+    pub positions: u64,
     pub invariant_violations: Vec<String>,
     pub last_op: Option<ListOp>,
 }
@@ -109,6 +131,9 @@ impl ListDSTResult {
             rpops: 0,
             lsets: 0,
             trims: 0,
+            inserts: 0,
+            rems: 0,
+            positions: 0,
             invariant_violations: Vec::new(),
             last_op: None,
         }
@@ -120,7 +145,8 @@ impl ListDSTResult {
 
     pub fn summary(&self) -> String {
         format!(
-            "Seed {}: {} ops (lpush:{}, rpush:{}, lpop:{}, rpop:{}, lset:{}, trim:{}), {} violations",
+            "Seed {}: {} ops (lpush:{}, rpush:{}, lpop:{}, rpop:{}, lset:{}, trim:{}, \
+             linsert:{}, lrem:{}, lpos:{}), {} violations",
             self.seed,
             self.total_operations,
             self.lpushes,
@@ -129,6 +155,9 @@ impl ListDSTResult {
             self.rpops,
             self.lsets,
             self.trims,
+            self.inserts,
+            self.rems,
+            self.positions,
             self.invariant_violations.len()
         )
     }
@@ -142,6 +171,8 @@ pub struct ListDSTHarness {
     result: ListDSTResult,
     /// Track expected length for cross-checking
     expected_len: usize,
+    /// Reference model of the list contents
+    model: VecDeque<String>,
 }
 
 impl ListDSTHarness {
@@ -153,6 +184,7 @@ impl ListDSTHarness {
             rng,
             list: RedisList::new(),
             expected_len: 0,
+            model: VecDeque::new(),
         }
     }
 
@@ -169,7 +201,10 @@ impl ListDSTHarness {
         let op_type = self.rng.gen_range(0, 100);
         let trim_threshold = (self.config.trim_prob * 100.0) as u64;
         let lset_threshold = trim_threshold + (self.config.lset_prob * 100.0) as u64;
-        let pop_threshold = lset_threshold + (self.config.pop_prob * 100.0) as u64;
+        let insert_threshold = lset_threshold + (self.config.insert_prob * 100.0) as u64;
+        let rem_threshold = insert_threshold + (self.config.rem_prob * 100.0) as u64;
+        let pos_threshold = rem_threshold + (self.config.pos_prob * 100.0) as u64;
+        let pop_threshold = pos_threshold + (self.config.pop_prob * 100.0) as u64;
 
         if op_type < trim_threshold && !self.list.is_empty() {
             // Trim operation
@@ -178,6 +213,8 @@ impl ListDSTHarness {
             let stop = self.rng.gen_range(start as u64, len as u64) as isize;
             self.result.last_op = Some(ListOp::Trim { start, stop });
             self.list.trim(start, stop);
+            self.model.truncate(stop as usize + 1);
+            self.model.drain(..start as usize);
             self.expected_len = (stop - start + 1).max(0) as usize;
             self.result.trims += 1;
         } else if op_type < lset_threshold && !self.list.is_empty() {
@@ -190,18 +227,107 @@ impl ListDSTHarness {
                 value: value.clone(),
             });
             let _ = self.list.set(index, SDS::from_str(&value));
+            self.model[index as usize] = value;
             // Length doesn't change
             self.result.lsets += 1;
+        } else if op_type < insert_threshold && !self.list.is_empty() {
+            // LInsert operation, pivot may be missing from the list
+            let pivot = self.random_value();
+            let value = self.random_value();
+            let before = self.rng.gen_range(0, 2) == 0;
+            self.result.last_op = Some(ListOp::Insert {
+                pivot: pivot.clone(),
+                value: value.clone(),
+                before,
+            });
+            let inserted = self
+                .list
+                .insert(&SDS::from_str(&pivot), SDS::from_str(&value), before);
+            if let Some(at) = self.model.iter().position(|item| *item == pivot) {
+                self.model.insert(if before { at } else { at + 1 }, value);
+                self.expected_len += 1;
+            }
+            if inserted.is_some_and(|len| len != self.expected_len) {
+                self.result.invariant_violations.push(format!(
+                    "LINSERT returned {:?}, expected length {}",
+                    inserted, self.expected_len
+                ));
+            }
+            self.result.inserts += 1;
+        } else if op_type < rem_threshold && !self.list.is_empty() {
+            // LRem operation with a signed count
+            let value = self.random_value();
+            let count = self.rng.gen_range(0, 5) as i64 - 2;
+            self.result.last_op = Some(ListOp::Rem {
+                count,
+                value: value.clone(),
+            });
+            let removed = self.list.remove(count, &SDS::from_str(&value));
+            let matches: Vec<usize> = (0..self.model.len())
+                .filter(|&i| self.model[i] == value)
+                .collect();
+            let limit = if count == 0 {
+                matches.len()
+            } else {
+                count.unsigned_abs() as usize
+            };
+            let doomed: Vec<usize> = if count >= 0 {
+                matches.into_iter().take(limit).collect()
+            } else {
+                matches.into_iter().rev().take(limit).collect()
+            };
+            let mut index = 0;
+            self.model.retain(|_| {
+                index += 1;
+                !doomed.contains(&(index - 1))
+            });
+            if removed != doomed.len() {
+                self.result.invariant_violations.push(format!(
+                    "LREM removed {}, expected {}",
+                    removed,
+                    doomed.len()
+                ));
+            }
+            self.expected_len -= doomed.len();
+            self.result.rems += 1;
+        } else if op_type < pos_threshold && !self.list.is_empty() {
+            // LPos lookup with random RANK/COUNT/MAXLEN
+            let value = self.random_value();
+            let rank = match self.rng.gen_range(0, 4) as i64 - 2 {
+                0 => 1,
+                rank => rank,
+            };
+            let count = self.rng.gen_range(0, 3) as usize;
+            let maxlen = self.rng.gen_range(0, self.model.len() as u64 + 1) as usize;
+            self.result.last_op = Some(ListOp::Pos {
+                value: value.clone(),
+                rank,
+                count,
+                maxlen,
+            });
+            let actual = self
+                .list
+                .positions(&SDS::from_str(&value), rank, count, maxlen);
+            let expected = self.model_positions(&value, rank, count, maxlen);
+            if actual != expected {
+                self.result.invariant_violations.push(format!(
+                    "LPOS mismatch: actual={:?}, expected={:?}",
+                    actual, expected
+                ));
+            }
+            self.result.positions += 1;
         } else if op_type < pop_threshold && !self.list.is_empty() {
             // Pop operation
             let use_left = self.rng.gen_range(0, 100) < (self.config.left_prob * 100.0) as u64;
             if use_left {
                 self.result.last_op = Some(ListOp::LPop);
                 self.list.lpop();
+                self.model.pop_front();
                 self.result.lpops += 1;
             } else {
                 self.result.last_op = Some(ListOp::RPop);
                 self.list.rpop();
+                self.model.pop_back();
                 self.result.rpops += 1;
             }
             self.expected_len = self.expected_len.saturating_sub(1);
@@ -214,12 +340,14 @@ impl ListDSTHarness {
                     value: value.clone(),
                 });
                 self.list.lpush(SDS::from_str(&value));
+                self.model.push_front(value);
                 self.result.lpushes += 1;
             } else {
                 self.result.last_op = Some(ListOp::RPush {
                     value: value.clone(),
                 });
                 self.list.rpush(SDS::from_str(&value));
+                self.model.push_back(value);
                 self.result.rpushes += 1;
             }
             self.expected_len += 1;
@@ -271,9 +399,40 @@ impl ListDSTHarness {
             }
         }
 
+        // Invariant 5: Contents must match the reference model
+        for (i, expected) in self.model.iter().enumerate() {
+            let actual = self.list.get(i as isize).map(|v| v.to_string());
+            if actual.as_deref() != Some(expected.as_str()) {
+                return Err(format!(
+                    "Content mismatch at {}: actual={:?}, expected={}",
+                    i, actual, expected
+                ));
+            }
+        }
+
         Ok(())
     }
 
+    /// Reference LPOS: collect every match in scan order, then apply
+    /// MAXLEN, RANK and COUNT in turn
+    fn model_positions(&self, value: &str, rank: i64, count: usize, maxlen: usize) -> Vec<usize> {
+        let len = self.model.len();
+        let scan: Vec<usize> = if rank > 0 {
+            (0..len).collect()
+        } else {
+            (0..len).rev().collect()
+        };
+        let matches: Vec<usize> = scan
+            .iter()
+            .enumerate()
+            .filter(|&(scanned, &i)| (maxlen == 0 || scanned < maxlen) && self.model[i] == value)
+            .map(|(_, &i)| i)
+            .collect();
+        let skip = rank.unsigned_abs() as usize - 1;
+        let take = if count == 0 { usize::MAX } else { count };
+        matches.into_iter().skip(skip).take(take).collect()
+    }
+
     pub fn run(&mut self, operations: usize) {
         for _ in 0..operations {
             self.run_single_op();
@@ -1,18 +1,51 @@
+mod bitmap;
+mod blocking;
 mod commands;
 mod data;
+mod eviction;
+mod expiry;
+mod geo;
+mod hyperloglog;
+mod keyspace;
+mod lazyfree;
 pub mod hash_dst;
 pub mod list_dst;
 pub mod lua;
 mod resp;
+mod resp3;
 mod resp_optimized;
 mod server;
 pub mod set_dst;
 pub mod sorted_set_dst;
+pub mod stream_dst;
 #[cfg(test)]
 mod tests;
 
-pub use commands::{Command, CommandExecutor};
-pub use data::{RedisHash, RedisList, RedisSet, RedisSortedSet, Value, SDS};
+pub use bitmap::{
+    BitFieldOp, BitFieldOverflow, BitFieldType, BitOperation, BitUnit, BITMAP_MAX_OFFSET,
+};
+pub use blocking::{BlockedClient, BlockedClientId, WaitQueue};
+pub use commands::{
+    ClientKillFilter, ClientPauseMode, ClientReplyMode, ClientTrackingOptions, ClientType,
+    ClusterSetSlotAction, Command, CommandExecutor, ExpireCondition, FieldExpiry, ObjectSubcommand,
+    SetOperation, StreamTrim, StreamTrimStrategy, ZAggregate, ZRangeBounds, ZRangeSpec,
+};
+pub use data::{
+    LexBound, RedisHash, RedisList, RedisSet, RedisSortedSet, RedisStream, ScoreBound,
+    StreamConsumerGroup, StreamId, Value, SDS,
+};
+pub use eviction::{
+    key_memory, EvictionPolicy, MemoryConfig, MemoryStats, MemoryTracker, DEFAULT_MAXMEMORY_SAMPLES,
+};
+pub use expiry::ExpiryIndex;
+pub use geo::{
+    geo_decode, geo_distance, geo_encode, GeoOrigin, GeoQuery, GeoSearchKind, GeoShape, GeoStore,
+    GeoUnit,
+};
+pub use hyperloglog::{
+    hll_estimate, hll_from_registers, hll_pattern, hll_registers, HllError, HLL_REGISTERS,
+};
+pub use keyspace::{keyspace_info, KeyspaceStats, DEFAULT_DATABASES};
 pub use hash_dst::{
     run_hash_batch, summarize_hash_batch, HashDSTConfig, HashDSTHarness, HashDSTResult,
 };
@@ -21,6 +54,7 @@ pub use list_dst::{
 };
 pub use lua::ScriptCache;
 pub use resp::{RespParser, RespValue};
+pub use resp3::{into_push, upgrade_exec_reply, upgrade_reply};
 pub use resp_optimized::{BufferPool, RespCodec, RespValueZeroCopy};
 pub use server::{RedisClient, RedisServer};
 pub use set_dst::{
@@ -30,3 +64,6 @@ pub use sorted_set_dst::{
     run_sorted_set_batch, summarize_batch, SortedSetDSTConfig, SortedSetDSTHarness,
     SortedSetDSTResult,
 };
+pub use stream_dst::{
+    run_stream_batch, summarize_stream_batch, StreamDSTConfig, StreamDSTHarness, StreamDSTResult,
+};
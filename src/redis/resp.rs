@@ -5,10 +5,49 @@ pub enum RespValue {
     Integer(i64),
     BulkString(Option<Vec<u8>>),
     Array(Option<Vec<RespValue>>),
+    // RESP3 types (sent only to clients that negotiated protocol 3 via HELLO)
+    Null,
+    Boolean(bool),
+    Double(f64),
+    /// Integer of arbitrary size, kept in its decimal form
+    BigNumber(String),
+    /// Three-letter format ("txt", "mkd") and the text
+    VerbatimString(String, Vec<u8>),
+    Map(Vec<(RespValue, RespValue)>),
+    Set(Vec<RespValue>),
+    /// Out-of-band data (Pub/Sub messages, invalidations), not a command reply
+    Push(Vec<RespValue>),
 }
 
 pub struct RespParser;
 
+impl RespValue {
+    /// Downgrade RESP3-only types to their RESP2 equivalents, the way Redis
+    /// replies to a client that has not sent `HELLO 3`
+    pub fn into_resp2(self) -> RespValue {
+        match self {
+            RespValue::Null => RespValue::BulkString(None),
+            RespValue::Boolean(b) => RespValue::Integer(b as i64),
+            RespValue::Double(d) => RespValue::BulkString(Some(format_double(d).into_bytes())),
+            RespValue::BigNumber(n) => RespValue::BulkString(Some(n.into_bytes())),
+            RespValue::VerbatimString(_, text) => RespValue::BulkString(Some(text)),
+            RespValue::Map(pairs) => RespValue::Array(Some(
+                pairs
+                    .into_iter()
+                    .flat_map(|(k, v)| [k.into_resp2(), v.into_resp2()])
+                    .collect(),
+            )),
+            RespValue::Set(elements) | RespValue::Push(elements) => RespValue::Array(Some(
+                elements.into_iter().map(RespValue::into_resp2).collect(),
+            )),
+            RespValue::Array(Some(elements)) => RespValue::Array(Some(
+                elements.into_iter().map(RespValue::into_resp2).collect(),
+            )),
+            other => other,
+        }
+    }
+}
+
 impl RespParser {
     pub fn parse(input: &[u8]) -> Result<(RespValue, usize), String> {
         if input.is_empty() {
@@ -21,10 +60,113 @@ impl RespParser {
             b':' => Self::parse_integer(input),
             b'$' => Self::parse_bulk_string(input),
             b'*' => Self::parse_array(input),
+            b'_' => Self::parse_null(input),
+            b'#' => Self::parse_boolean(input),
+            b',' => Self::parse_double(input),
+            b'(' => Self::parse_big_number(input),
+            b'=' => Self::parse_verbatim_string(input),
+            b'%' => Self::parse_map(input),
+            b'~' | b'>' => Self::parse_set_or_push(input),
             _ => Err(format!("Unknown RESP type: {}", input[0] as char)),
         }
     }
 
+    /// Text of a single-line frame (`<type><text>\r\n`) and its length
+    fn parse_line(input: &[u8]) -> Result<(&[u8], usize), String> {
+        match Self::find_crlf(input) {
+            Some(pos) => Ok((&input[1..pos], pos + 2)),
+            None => Err("No CRLF found".to_string()),
+        }
+    }
+
+    /// Element count of an aggregate header
+    fn parse_len(line: &[u8]) -> Result<usize, String> {
+        String::from_utf8_lossy(line)
+            .parse::<usize>()
+            .map_err(|e| e.to_string())
+    }
+
+    fn parse_null(input: &[u8]) -> Result<(RespValue, usize), String> {
+        let (_, consumed) = Self::parse_line(input)?;
+        Ok((RespValue::Null, consumed))
+    }
+
+    fn parse_boolean(input: &[u8]) -> Result<(RespValue, usize), String> {
+        let (line, consumed) = Self::parse_line(input)?;
+        match line {
+            b"t" => Ok((RespValue::Boolean(true), consumed)),
+            b"f" => Ok((RespValue::Boolean(false), consumed)),
+            _ => Err("Invalid boolean".to_string()),
+        }
+    }
+
+    fn parse_double(input: &[u8]) -> Result<(RespValue, usize), String> {
+        let (line, consumed) = Self::parse_line(input)?;
+        let d = match line {
+            b"inf" => f64::INFINITY,
+            b"-inf" => f64::NEG_INFINITY,
+            b"nan" => f64::NAN,
+            _ => String::from_utf8_lossy(line)
+                .parse::<f64>()
+                .map_err(|e| e.to_string())?,
+        };
+        Ok((RespValue::Double(d), consumed))
+    }
+
+    fn parse_big_number(input: &[u8]) -> Result<(RespValue, usize), String> {
+        let (line, consumed) = Self::parse_line(input)?;
+        let s = String::from_utf8_lossy(line).to_string();
+        Ok((RespValue::BigNumber(s), consumed))
+    }
+
+    fn parse_verbatim_string(input: &[u8]) -> Result<(RespValue, usize), String> {
+        let (line, header) = Self::parse_line(input)?;
+        let len = Self::parse_len(line)?;
+        let end = header + len;
+        if end + 2 > input.len() {
+            return Err("Incomplete verbatim string".to_string());
+        }
+        let body = &input[header..end];
+        if len < 4 || body[3] != b':' {
+            return Err("Invalid verbatim string".to_string());
+        }
+        let format = String::from_utf8_lossy(&body[..3]).to_string();
+        Ok((
+            RespValue::VerbatimString(format, body[4..].to_vec()),
+            end + 2,
+        ))
+    }
+
+    fn parse_map(input: &[u8]) -> Result<(RespValue, usize), String> {
+        let (line, mut offset) = Self::parse_line(input)?;
+        let len = Self::parse_len(line)?;
+        let mut pairs = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (key, consumed) = Self::parse(&input[offset..])?;
+            offset += consumed;
+            let (value, consumed) = Self::parse(&input[offset..])?;
+            offset += consumed;
+            pairs.push((key, value));
+        }
+        Ok((RespValue::Map(pairs), offset))
+    }
+
+    fn parse_set_or_push(input: &[u8]) -> Result<(RespValue, usize), String> {
+        let (line, mut offset) = Self::parse_line(input)?;
+        let len = Self::parse_len(line)?;
+        let mut elements = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (value, consumed) = Self::parse(&input[offset..])?;
+            elements.push(value);
+            offset += consumed;
+        }
+        if input[0] == b'>' {
+            Ok((RespValue::Push(elements), offset))
+        } else {
+            Ok((RespValue::Set(elements), offset))
+        }
+    }
+
     fn parse_simple_string(input: &[u8]) -> Result<(RespValue, usize), String> {
         if let Some(pos) = Self::find_crlf(input) {
             let s = String::from_utf8_lossy(&input[1..pos]).to_string();
@@ -130,10 +272,51 @@ impl RespParser {
                 }
                 result
             }
+            RespValue::Null => b"_\r\n".to_vec(),
+            RespValue::Boolean(b) => if *b { b"#t\r\n" } else { b"#f\r\n" }.to_vec(),
+            RespValue::Double(d) => format!(",{}\r\n", format_double(*d)).into_bytes(),
+            RespValue::BigNumber(n) => format!("({}\r\n", n).into_bytes(),
+            RespValue::VerbatimString(format, text) => {
+                let mut result = format!("={}\r\n{}:", text.len() + 4, format).into_bytes();
+                result.extend_from_slice(text);
+                result.extend_from_slice(b"\r\n");
+                result
+            }
+            RespValue::Map(pairs) => {
+                let mut result = format!("%{}\r\n", pairs.len()).into_bytes();
+                for (key, value) in pairs {
+                    result.extend_from_slice(&Self::encode(key));
+                    result.extend_from_slice(&Self::encode(value));
+                }
+                result
+            }
+            RespValue::Set(elements) | RespValue::Push(elements) => {
+                let prefix = if matches!(value, RespValue::Set(_)) {
+                    '~'
+                } else {
+                    '>'
+                };
+                let mut result = format!("{}{}\r\n", prefix, elements.len()).into_bytes();
+                for element in elements {
+                    result.extend_from_slice(&Self::encode(element));
+                }
+                result
+            }
         }
     }
 }
 
+/// RESP3 double text: `inf`, `-inf`, `nan`, or the shortest form that round-trips
+pub fn format_double(d: f64) -> String {
+    if d.is_nan() {
+        "nan".to_string()
+    } else if d.is_infinite() {
+        if d > 0.0 { "inf" } else { "-inf" }.to_string()
+    } else {
+        d.to_string()
+    }
+}
+
 // Static response optimization helpers (P1: opt-static-responses)
 impl RespValue {
     /// Static "OK" response - avoids allocation on every SET
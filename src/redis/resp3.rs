@@ -0,0 +1,251 @@
+//! RESP3 reply shapes
+//!
+//! The executor builds every reply in RESP2 form. A connection that switched
+//! to protocol 3 with `HELLO 3` passes each reply through [`upgrade_reply`],
+//! which rewrites it into the RESP3 type Redis uses for that command: maps for
+//! field/value replies, sets for set members, doubles for scores, verbatim
+//! strings for INFO-style text, and `_` for every null.
+//!
+//! Upgrading per command keeps the executor, the shard actors and the DST
+//! harnesses protocol-agnostic; only the connection knows the protocol.
+
+use super::commands::Command;
+use super::resp::RespValue;
+
+/// Rewrite the RESP2 `reply` to `cmd` into its RESP3 form
+pub fn upgrade_reply(cmd: &Command, reply: RespValue) -> RespValue {
+    match (cmd, reply) {
+        (_, error @ RespValue::Error(_)) => error,
+        (Command::HGetAll(_) | Command::ConfigGet(_), RespValue::Array(Some(flat))) => {
+            RespValue::Map(pairs(flat))
+        }
+        (Command::ZScore(..) | Command::ZIncrBy(..), RespValue::BulkString(Some(score))) => {
+            double(score)
+        }
+        (Command::ZMScore(..), RespValue::Array(Some(scores))) => {
+            RespValue::Array(Some(scores.into_iter().map(to_double).collect()))
+        }
+        (Command::ZRevRange(_, _, _, true), RespValue::Array(Some(flat)))
+        | (
+            Command::ZRangeByScore {
+                with_scores: true, ..
+            }
+            | Command::ZRange {
+                with_scores: true, ..
+            }
+            | Command::ZRevRangeByScore {
+                with_scores: true, ..
+            }
+            | Command::ZRandMember {
+                count: Some(_),
+                with_scores: true,
+                ..
+            }
+            | Command::ZSetOp {
+                with_scores: true, ..
+            }
+            | Command::ZPopMin(_, Some(_))
+            | Command::ZPopMax(_, Some(_)),
+            RespValue::Array(Some(flat)),
+        ) => RespValue::Array(Some(
+            pairs(flat)
+                .into_iter()
+                .map(|(member, score)| RespValue::Array(Some(vec![member, to_double(score)])))
+                .collect(),
+        )),
+        (
+            Command::HRandField {
+                count: Some(_),
+                with_values: true,
+                ..
+            },
+            RespValue::Array(Some(flat)),
+        ) => RespValue::Array(Some(
+            pairs(flat)
+                .into_iter()
+                .map(|(field, value)| RespValue::Array(Some(vec![field, value])))
+                .collect(),
+        )),
+        (Command::Lcs { idx: true, .. }, RespValue::Array(Some(flat))) => {
+            RespValue::Map(pairs(flat))
+        }
+        (
+            Command::BZPopMin { .. }
+            | Command::BZPopMax { .. }
+            | Command::ZPopMin(_, None)
+            | Command::ZPopMax(_, None),
+            RespValue::Array(Some(mut popped)),
+        ) if popped.len() == 3 || popped.len() == 2 => {
+            let score = popped.pop().map(to_double).unwrap_or(RespValue::Null);
+            popped.push(score);
+            upgrade_nulls(RespValue::Array(Some(popped)))
+        }
+        (
+            Command::SMembers(_) | Command::SPop(_, Some(_)) | Command::SetOp { dest: None, .. },
+            RespValue::Array(Some(members)),
+        ) => RespValue::Set(members),
+        (
+            Command::Info | Command::ClusterInfo | Command::ClusterNodes,
+            RespValue::BulkString(Some(text)),
+        ) => RespValue::VerbatimString("txt".to_string(), text),
+        (Command::XRead { .. } | Command::XReadGroup { .. }, RespValue::Array(Some(streams))) => {
+            RespValue::Map(
+                streams
+                    .into_iter()
+                    .filter_map(|stream| match stream {
+                        RespValue::Array(Some(mut kv)) if kv.len() == 2 => {
+                            let entries = kv.pop()?;
+                            let key = kv.pop()?;
+                            Some((key, upgrade_nulls(entries)))
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+            )
+        }
+        (_, reply) => upgrade_nulls(reply),
+    }
+}
+
+/// Upgrade an EXEC reply: one reply per queued command, in order
+pub fn upgrade_exec_reply(queued: &[Command], reply: RespValue) -> RespValue {
+    match reply {
+        RespValue::Array(Some(replies)) if replies.len() == queued.len() => RespValue::Array(Some(
+            queued
+                .iter()
+                .zip(replies)
+                .map(|(cmd, reply)| upgrade_reply(cmd, reply))
+                .collect(),
+        )),
+        // Aborted (WATCH) or EXECABORT
+        other => upgrade_nulls(other),
+    }
+}
+
+/// Subscribe confirmations and Pub/Sub messages are push frames in RESP3
+pub fn into_push(reply: RespValue) -> RespValue {
+    match reply {
+        RespValue::Array(Some(elements)) => RespValue::Push(elements),
+        other => other,
+    }
+}
+
+/// Null bulk strings and null arrays, at any depth, become `_`
+fn upgrade_nulls(reply: RespValue) -> RespValue {
+    match reply {
+        RespValue::BulkString(None) | RespValue::Array(None) => RespValue::Null,
+        RespValue::Array(Some(elements)) => {
+            RespValue::Array(Some(elements.into_iter().map(upgrade_nulls).collect()))
+        }
+        other => other,
+    }
+}
+
+/// Flat `[k1, v1, k2, v2, ...]` into pairs
+fn pairs(flat: Vec<RespValue>) -> Vec<(RespValue, RespValue)> {
+    debug_assert!(flat.len() % 2 == 0, "Precondition: odd-length pair reply");
+    let mut pairs = Vec::with_capacity(flat.len() / 2);
+    let mut iter = flat.into_iter();
+    while let (Some(k), Some(v)) = (iter.next(), iter.next()) {
+        pairs.push((upgrade_nulls(k), upgrade_nulls(v)));
+    }
+    pairs
+}
+
+fn to_double(value: RespValue) -> RespValue {
+    match value {
+        RespValue::BulkString(Some(score)) => double(score),
+        other => upgrade_nulls(other),
+    }
+}
+
+/// A score stored as text ("1.5", "inf", "-inf"); left as a string if unparsable
+fn double(score: Vec<u8>) -> RespValue {
+    let parsed = std::str::from_utf8(&score)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok());
+    match parsed {
+        Some(d) => RespValue::Double(d),
+        None => RespValue::BulkString(Some(score)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redis::SDS;
+
+    fn bulk(s: &str) -> RespValue {
+        RespValue::BulkString(Some(s.as_bytes().to_vec()))
+    }
+
+    #[test]
+    fn test_hgetall_becomes_map() {
+        let reply = RespValue::Array(Some(vec![bulk("f1"), bulk("v1"), bulk("f2"), bulk("v2")]));
+        assert_eq!(
+            upgrade_reply(&Command::HGetAll("h".into()), reply),
+            RespValue::Map(vec![(bulk("f1"), bulk("v1")), (bulk("f2"), bulk("v2"))])
+        );
+    }
+
+    #[test]
+    fn test_scores_become_doubles() {
+        let zscore = Command::ZScore("z".into(), SDS::from_str("m"));
+        assert_eq!(upgrade_reply(&zscore, bulk("1.5")), RespValue::Double(1.5));
+        assert_eq!(
+            upgrade_reply(&zscore, RespValue::BulkString(None)),
+            RespValue::Null
+        );
+
+        let range = Command::ZRevRange("z".into(), 0, -1, true);
+        let reply = RespValue::Array(Some(vec![bulk("a"), bulk("2"), bulk("b"), bulk("inf")]));
+        assert_eq!(
+            upgrade_reply(&range, reply),
+            RespValue::Array(Some(vec![
+                RespValue::Array(Some(vec![bulk("a"), RespValue::Double(2.0)])),
+                RespValue::Array(Some(vec![bulk("b"), RespValue::Double(f64::INFINITY)])),
+            ]))
+        );
+
+        // ZPOPMIN without a count is a single pair, with a count a list of pairs
+        let pop = Command::ZPopMin("z".into(), None);
+        let reply = RespValue::Array(Some(vec![bulk("a"), bulk("1")]));
+        assert_eq!(
+            upgrade_reply(&pop, reply),
+            RespValue::Array(Some(vec![bulk("a"), RespValue::Double(1.0)]))
+        );
+        let pop = Command::ZPopMin("z".into(), Some(1));
+        let reply = RespValue::Array(Some(vec![bulk("a"), bulk("1")]));
+        assert_eq!(
+            upgrade_reply(&pop, reply),
+            RespValue::Array(Some(vec![RespValue::Array(Some(vec![
+                bulk("a"),
+                RespValue::Double(1.0)
+            ]))]))
+        );
+    }
+
+    #[test]
+    fn test_nulls_sets_and_text() {
+        let mget = Command::MGet(vec!["a".into(), "b".into()]);
+        let reply = RespValue::Array(Some(vec![bulk("1"), RespValue::BulkString(None)]));
+        assert_eq!(
+            upgrade_reply(&mget, reply),
+            RespValue::Array(Some(vec![bulk("1"), RespValue::Null]))
+        );
+
+        let smembers = Command::SMembers("s".into());
+        let reply = RespValue::Array(Some(vec![bulk("x")]));
+        assert_eq!(
+            upgrade_reply(&smembers, reply),
+            RespValue::Set(vec![bulk("x")])
+        );
+
+        assert_eq!(
+            upgrade_reply(&Command::Info, bulk("# Server\r\n")),
+            RespValue::VerbatimString("txt".to_string(), b"# Server\r\n".to_vec())
+        );
+        let error = RespValue::Error("ERR boom".to_string());
+        assert_eq!(upgrade_reply(&Command::Info, error.clone()), error);
+    }
+}
@@ -1,3 +1,4 @@
+use super::resp::format_double;
 use bytes::{Buf, Bytes, BytesMut};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -7,6 +8,16 @@ pub enum RespValueZeroCopy {
     Integer(i64),
     BulkString(Option<Bytes>),
     Array(Option<Vec<RespValueZeroCopy>>),
+    // RESP3 types
+    Null,
+    Boolean(bool),
+    Double(f64),
+    BigNumber(Bytes),
+    /// Three-letter format and the text
+    VerbatimString(Bytes, Bytes),
+    Map(Vec<(RespValueZeroCopy, RespValueZeroCopy)>),
+    Set(Vec<RespValueZeroCopy>),
+    Push(Vec<RespValueZeroCopy>),
 }
 
 pub struct RespCodec;
@@ -38,10 +49,116 @@ impl RespCodec {
             b':' => Self::parse_integer(input),
             b'$' => Self::parse_bulk_string(input),
             b'*' => Self::parse_array(input),
+            b'_' => Self::parse_null(input),
+            b'#' => Self::parse_boolean(input),
+            b',' => Self::parse_double(input),
+            b'(' => Self::parse_big_number(input),
+            b'=' => Self::parse_verbatim_string(input),
+            b'%' => Self::parse_map(input),
+            b'~' | b'>' => Self::parse_set_or_push(input),
             _ => Err(format!("Unknown RESP type: {}", input[0] as char)),
         }
     }
 
+    /// Text of a single-line frame and the bytes it occupies
+    fn parse_line(input: &[u8]) -> Result<(&[u8], usize), String> {
+        match Self::find_crlf(input) {
+            Some(pos) => Ok((&input[1..pos], pos + 2)),
+            None => Err("Incomplete".to_string()),
+        }
+    }
+
+    fn parse_len(line: &[u8]) -> Result<usize, String> {
+        let s = std::str::from_utf8(line).map_err(|e| e.to_string())?;
+        s.parse::<usize>().map_err(|e| e.to_string())
+    }
+
+    fn parse_null(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
+        let (_, consumed) = Self::parse_line(input)?;
+        Ok((RespValueZeroCopy::Null, consumed))
+    }
+
+    fn parse_boolean(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
+        let (line, consumed) = Self::parse_line(input)?;
+        match line {
+            b"t" => Ok((RespValueZeroCopy::Boolean(true), consumed)),
+            b"f" => Ok((RespValueZeroCopy::Boolean(false), consumed)),
+            _ => Err("Invalid boolean".to_string()),
+        }
+    }
+
+    fn parse_double(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
+        let (line, consumed) = Self::parse_line(input)?;
+        let d = match line {
+            b"inf" => f64::INFINITY,
+            b"-inf" => f64::NEG_INFINITY,
+            b"nan" => f64::NAN,
+            _ => std::str::from_utf8(line)
+                .map_err(|e| e.to_string())?
+                .parse::<f64>()
+                .map_err(|e| e.to_string())?,
+        };
+        Ok((RespValueZeroCopy::Double(d), consumed))
+    }
+
+    fn parse_big_number(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
+        let (line, consumed) = Self::parse_line(input)?;
+        Ok((
+            RespValueZeroCopy::BigNumber(Bytes::copy_from_slice(line)),
+            consumed,
+        ))
+    }
+
+    fn parse_verbatim_string(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
+        let (line, start) = Self::parse_line(input)?;
+        let len = Self::parse_len(line)?;
+        let end = start + len;
+        if end + 2 > input.len() {
+            return Err("Incomplete".to_string());
+        }
+        let body = &input[start..end];
+        if len < 4 || body[3] != b':' {
+            return Err("Invalid verbatim string".to_string());
+        }
+        Ok((
+            RespValueZeroCopy::VerbatimString(
+                Bytes::copy_from_slice(&body[..3]),
+                Bytes::copy_from_slice(&body[4..]),
+            ),
+            end + 2,
+        ))
+    }
+
+    fn parse_map(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
+        let (line, mut offset) = Self::parse_line(input)?;
+        let len = Self::parse_len(line)?;
+        let mut pairs = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (key, consumed) = Self::try_parse(&input[offset..])?;
+            offset += consumed;
+            let (value, consumed) = Self::try_parse(&input[offset..])?;
+            offset += consumed;
+            pairs.push((key, value));
+        }
+        Ok((RespValueZeroCopy::Map(pairs), offset))
+    }
+
+    fn parse_set_or_push(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
+        let (line, mut offset) = Self::parse_line(input)?;
+        let len = Self::parse_len(line)?;
+        let mut elements = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (value, consumed) = Self::try_parse(&input[offset..])?;
+            elements.push(value);
+            offset += consumed;
+        }
+        if input[0] == b'>' {
+            Ok((RespValueZeroCopy::Push(elements), offset))
+        } else {
+            Ok((RespValueZeroCopy::Set(elements), offset))
+        }
+    }
+
     fn parse_simple_string(input: &[u8]) -> Result<(RespValueZeroCopy, usize), String> {
         if let Some(pos) = Self::find_crlf(input) {
             let data = Bytes::copy_from_slice(&input[1..pos]);
@@ -177,6 +294,51 @@ impl RespCodec {
                     Self::encode_into(elem, buf);
                 }
             }
+            RespValueZeroCopy::Null => buf.extend_from_slice(b"_\r\n"),
+            RespValueZeroCopy::Boolean(b) => {
+                buf.extend_from_slice(if *b { b"#t\r\n" } else { b"#f\r\n" })
+            }
+            RespValueZeroCopy::Double(d) => {
+                buf.put_u8(b',');
+                buf.extend_from_slice(format_double(*d).as_bytes());
+                buf.extend_from_slice(b"\r\n");
+            }
+            RespValueZeroCopy::BigNumber(n) => {
+                buf.put_u8(b'(');
+                buf.extend_from_slice(n);
+                buf.extend_from_slice(b"\r\n");
+            }
+            RespValueZeroCopy::VerbatimString(format, text) => {
+                buf.put_u8(b'=');
+                buf.extend_from_slice((text.len() + 4).to_string().as_bytes());
+                buf.extend_from_slice(b"\r\n");
+                buf.extend_from_slice(format);
+                buf.put_u8(b':');
+                buf.extend_from_slice(text);
+                buf.extend_from_slice(b"\r\n");
+            }
+            RespValueZeroCopy::Map(pairs) => {
+                buf.put_u8(b'%');
+                buf.extend_from_slice(pairs.len().to_string().as_bytes());
+                buf.extend_from_slice(b"\r\n");
+                for (key, value) in pairs {
+                    Self::encode_into(key, buf);
+                    Self::encode_into(value, buf);
+                }
+            }
+            RespValueZeroCopy::Set(elements) | RespValueZeroCopy::Push(elements) => {
+                let prefix = if matches!(value, RespValueZeroCopy::Set(_)) {
+                    b'~'
+                } else {
+                    b'>'
+                };
+                buf.put_u8(prefix);
+                buf.extend_from_slice(elements.len().to_string().as_bytes());
+                buf.extend_from_slice(b"\r\n");
+                for elem in elements {
+                    Self::encode_into(elem, buf);
+                }
+            }
         }
     }
 }
@@ -4,6 +4,10 @@
 //! - Deterministic random operation generation
 //! - Invariant checking after each operation
 //! - Seed-based reproducibility for debugging
+//!
+//! A second set is kept alongside the first so SMOVE and the set algebra
+//! (union, intersection, difference, SINTERCARD) can be checked against
+//! `HashSet` operations on the expected members.
 
 use super::data::{RedisSet, SDS};
 use crate::io::simulation::SimulatedRng;
@@ -19,6 +23,8 @@ pub struct SetDSTConfig {
     pub num_members: usize,
     /// Probability of remove operation
     pub remove_prob: f64,
+    /// Probability of a move, a write to the second set, or an algebra check
+    pub algebra_prob: f64,
 }
 
 impl Default for SetDSTConfig {
@@ -27,6 +33,7 @@ impl Default for SetDSTConfig {
             seed: 0,
             num_members: 100,
             remove_prob: 0.25,
+            algebra_prob: 0.15,
         }
     }
 }
@@ -45,6 +52,7 @@ impl SetDSTConfig {
             seed,
             num_members: 10,
             remove_prob: 0.3,
+            algebra_prob: 0.15,
         }
     }
 
@@ -54,6 +62,7 @@ impl SetDSTConfig {
             seed,
             num_members: 50,
             remove_prob: 0.45,
+            algebra_prob: 0.15,
         }
     }
 
@@ -63,6 +72,17 @@ impl SetDSTConfig {
             seed,
             num_members: 500,
             remove_prob: 0.15,
+            algebra_prob: 0.1,
+        }
+    }
+
+    /// Configuration dominated by moves and algebra checks
+    pub fn algebra_heavy(seed: u64) -> Self {
+        SetDSTConfig {
+            seed,
+            num_members: 40,
+            remove_prob: 0.15,
+            algebra_prob: 0.5,
         }
     }
 }
@@ -72,6 +92,18 @@ impl SetDSTConfig {
 pub enum SetOp {
     Add { member: String },
     Remove { member: String },
+    /// SADD to the second set
+    AddOther {
+        member: String,
+    },
+    /// SMOVE from the first set to the second
+    Move {
+        member: String,
+    },
+    /// Union, intersection, both differences and SINTERCARD with `limit`
+    Algebra {
+        limit: usize,
+    },
 }
 
 /// Result of a Set DST run
@@ -83,6 +115,9 @@ pub struct SetDSTResult {
     pub add_existed: u64,
     pub removes: u64,
     pub remove_not_found: u64,
+    pub other_adds: u64,
+    pub moves: u64,
+    pub algebra_checks: u64,
     pub invariant_violations: Vec<String>,
     pub last_op: Option<SetOp>,
 }
@@ -96,6 +131,9 @@ impl SetDSTResult {
             add_existed: 0,
             removes: 0,
             remove_not_found: 0,
+            other_adds: 0,
+            moves: 0,
+            algebra_checks: 0,
             invariant_violations: Vec::new(),
             last_op: None,
         }
@@ -107,13 +145,17 @@ impl SetDSTResult {
 
     pub fn summary(&self) -> String {
         format!(
-            "Seed {}: {} ops (adds:{}, existed:{}, removes:{}, not_found:{}), {} violations",
+            "Seed {}: {} ops (adds:{}, existed:{}, removes:{}, not_found:{}, other_adds:{}, \
+             moves:{}, algebra:{}), {} violations",
             self.seed,
             self.total_operations,
             self.adds,
             self.add_existed,
             self.removes,
             self.remove_not_found,
+            self.other_adds,
+            self.moves,
+            self.algebra_checks,
             self.invariant_violations.len()
         )
     }
@@ -124,9 +166,12 @@ pub struct SetDSTHarness {
     config: SetDSTConfig,
     rng: SimulatedRng,
     set: RedisSet,
+    /// Second set, the other operand of moves and the set algebra
+    other: RedisSet,
     result: SetDSTResult,
     /// Track expected members for cross-checking
     expected_members: HashSet<String>,
+    expected_other: HashSet<String>,
 }
 
 impl SetDSTHarness {
@@ -137,7 +182,9 @@ impl SetDSTHarness {
             config,
             rng,
             set: RedisSet::new(),
+            other: RedisSet::new(),
             expected_members: HashSet::new(),
+            expected_other: HashSet::new(),
         }
     }
 
@@ -150,9 +197,107 @@ impl SetDSTHarness {
         format!("member:{}", idx)
     }
 
+    /// Run a move, a write to the second set, or an algebra check
+    fn run_algebra_op(&mut self) -> Result<(), String> {
+        match self.rng.gen_range(0, 3) {
+            0 => {
+                let member = self.random_member();
+                self.result.last_op = Some(SetOp::AddOther {
+                    member: member.clone(),
+                });
+                self.expected_other.insert(member.clone());
+                self.other.add(SDS::from_str(&member));
+                self.result.other_adds += 1;
+                Ok(())
+            }
+            1 => {
+                let member = self.random_member();
+                self.result.last_op = Some(SetOp::Move {
+                    member: member.clone(),
+                });
+                let expected = self.expected_members.remove(&member);
+                let sds = SDS::from_str(&member);
+                let moved = self.set.remove(&sds);
+                if moved {
+                    self.expected_other.insert(member);
+                    self.other.add(sds);
+                }
+                self.result.moves += 1;
+                if moved != expected {
+                    return Err(format!(
+                        "Move mismatch: expected moved={}, actual moved={}",
+                        expected, moved
+                    ));
+                }
+                Ok(())
+            }
+            _ => {
+                let limit = self.rng.gen_range(0, 5) as usize;
+                self.result.last_op = Some(SetOp::Algebra { limit });
+                self.result.algebra_checks += 1;
+                self.check_algebra(limit)
+            }
+        }
+    }
+
+    /// Compare the set algebra of the two sets with `HashSet`'s
+    fn check_algebra(&self, limit: usize) -> Result<(), String> {
+        let names = |set: &RedisSet| -> HashSet<String> {
+            set.members().iter().map(|m| m.to_string()).collect()
+        };
+        let (a, b) = (&self.expected_members, &self.expected_other);
+        let inputs = [&self.set, &self.other];
+        let reversed = [&self.other, &self.set];
+
+        let checks: [(&str, HashSet<String>, HashSet<String>); 4] = [
+            (
+                "union",
+                names(&RedisSet::union(&inputs)),
+                a.union(b).cloned().collect(),
+            ),
+            (
+                "intersection",
+                names(&RedisSet::intersection(&inputs)),
+                a.intersection(b).cloned().collect(),
+            ),
+            (
+                "difference",
+                names(&RedisSet::difference(&inputs)),
+                a.difference(b).cloned().collect(),
+            ),
+            (
+                "reversed difference",
+                names(&RedisSet::difference(&reversed)),
+                b.difference(a).cloned().collect(),
+            ),
+        ];
+        for (name, actual, expected) in checks {
+            if actual != expected {
+                return Err(format!(
+                    "{} mismatch: actual={} members, expected={}",
+                    name,
+                    actual.len(),
+                    expected.len()
+                ));
+            }
+        }
+
+        let card = RedisSet::intersection_card(&inputs, limit);
+        let full = a.intersection(b).count();
+        let expected_card = if limit == 0 { full } else { full.min(limit) };
+        if card != expected_card {
+            return Err(format!(
+                "SINTERCARD mismatch with limit {}: actual={}, expected={}",
+                limit, card, expected_card
+            ));
+        }
+        Ok(())
+    }
+
     fn run_single_op(&mut self) {
         let op_type = self.rng.gen_range(0, 100);
         let remove_threshold = (self.config.remove_prob * 100.0) as u64;
+        let algebra_threshold = remove_threshold + (self.config.algebra_prob * 100.0) as u64;
 
         if op_type < remove_threshold {
             // Remove operation
@@ -176,6 +321,10 @@ impl SetDSTHarness {
                     existed, removed
                 ));
             }
+        } else if op_type < algebra_threshold {
+            if let Err(violation) = self.run_algebra_op() {
+                self.result.invariant_violations.push(violation);
+            }
         } else {
             // Add operation
             let member = self.random_member();
@@ -250,6 +399,17 @@ impl SetDSTHarness {
             ));
         }
 
+        // Invariant 5: The second set matches its expected members
+        let other_members: HashSet<String> =
+            self.other.members().iter().map(|m| m.to_string()).collect();
+        if other_members != self.expected_other || self.other.len() != self.expected_other.len() {
+            return Err(format!(
+                "Second set mismatch: actual={} members, expected={}",
+                self.other.len(),
+                self.expected_other.len()
+            ));
+        }
+
         Ok(())
     }
 
@@ -340,6 +500,16 @@ mod tests {
         assert!(result.is_success());
     }
 
+    #[test]
+    fn test_set_dst_algebra_heavy() {
+        let results = run_set_batch(500, 10, 500, SetDSTConfig::algebra_heavy);
+        let summary = summarize_set_batch(&results);
+        println!("{}", summary);
+
+        assert!(results.iter().all(|r| r.is_success()), "{}", summary);
+        assert!(results.iter().all(|r| r.moves > 0 && r.algebra_checks > 0));
+    }
+
     #[test]
     fn test_set_dst_10_seeds() {
         let results = run_set_batch(0, 10, 500, SetDSTConfig::new);
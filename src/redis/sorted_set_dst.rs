@@ -5,6 +5,10 @@
 //! - Invariant checking after each operation
 //! - Seed-based reproducibility for debugging
 //!
+//! Pops, increments and the rank/score/lex range operations are checked
+//! against a brute-force scan of the set, which exercises the skip list's
+//! span-based rank counting.
+//!
 //! ## Usage
 //!
 //! ```rust,ignore
@@ -15,7 +19,7 @@
 //! }
 //! ```
 
-use super::data::{RedisSortedSet, SDS};
+use super::data::{LexBound, RedisSortedSet, ScoreBound, SDS};
 use crate::io::simulation::SimulatedRng;
 use crate::io::Rng;
 
@@ -30,8 +34,13 @@ pub struct SortedSetDSTConfig {
     pub update_prob: f64,
     /// Probability of remove operation
     pub remove_prob: f64,
-    /// Maximum score value
+    /// Maximum score value; 0 gives every member the same score, which the
+    /// lex range operations need to be checked
     pub max_score: f64,
+    /// Probability of a pop, range removal or range query
+    pub range_prob: f64,
+    /// Probability of an increment
+    pub incr_prob: f64,
 }
 
 impl Default for SortedSetDSTConfig {
@@ -42,6 +51,8 @@ impl Default for SortedSetDSTConfig {
             update_prob: 0.3,
             remove_prob: 0.1,
             max_score: 1000.0,
+            range_prob: 0.1,
+            incr_prob: 0.1,
         }
     }
 }
@@ -63,6 +74,8 @@ impl SortedSetDSTConfig {
             update_prob: 0.5,
             remove_prob: 0.2,
             max_score: 100.0,
+            range_prob: 0.1,
+            incr_prob: 0.1,
         }
     }
 
@@ -74,6 +87,34 @@ impl SortedSetDSTConfig {
             update_prob: 0.1,
             remove_prob: 0.05,
             max_score: 10000.0,
+            range_prob: 0.05,
+            incr_prob: 0.05,
+        }
+    }
+
+    /// Configuration dominated by pops and range operations
+    pub fn range_heavy(seed: u64) -> Self {
+        SortedSetDSTConfig {
+            seed,
+            num_keys: 200,
+            update_prob: 0.3,
+            remove_prob: 0.05,
+            max_score: 50.0,
+            range_prob: 0.3,
+            incr_prob: 0.15,
+        }
+    }
+
+    /// Configuration where every member scores 0, so lex ranges are checked
+    pub fn equal_scores(seed: u64) -> Self {
+        SortedSetDSTConfig {
+            seed,
+            num_keys: 100,
+            update_prob: 0.3,
+            remove_prob: 0.05,
+            max_score: 0.0,
+            range_prob: 0.3,
+            incr_prob: 0.0,
         }
     }
 }
@@ -81,8 +122,38 @@ impl SortedSetDSTConfig {
 /// Operation type for logging
 #[derive(Debug, Clone)]
 pub enum SortedSetOp {
-    Add { member: String, score: f64 },
-    Remove { member: String },
+    Add {
+        member: String,
+        score: f64,
+    },
+    Remove {
+        member: String,
+    },
+    IncrBy {
+        member: String,
+        delta: f64,
+    },
+    PopMin,
+    PopMax,
+    RemoveRangeByRank {
+        start: isize,
+        stop: isize,
+    },
+    RemoveRangeByScore {
+        min: ScoreBound,
+        max: ScoreBound,
+    },
+    RemoveRangeByLex {
+        min: LexBound,
+        max: LexBound,
+    },
+    RangeByScore {
+        min: ScoreBound,
+        max: ScoreBound,
+        rev: bool,
+        offset: usize,
+        count: Option<usize>,
+    },
 }
 
 /// Result of a Sorted Set DST run
@@ -98,6 +169,14 @@ pub struct SortedSetDSTResult {
     pub updates: u64,
     /// Remove operations
     pub removes: u64,
+    /// Increment operations
+    pub incrs: u64,
+    /// Pops from either end
+    pub pops: u64,
+    /// Removals by rank, score or lex range
+    pub range_removes: u64,
+    /// Score range queries checked against a scan
+    pub range_queries: u64,
     /// Invariant violations found (with operation context)
     pub invariant_violations: Vec<String>,
     /// Last operation before failure (if any)
@@ -112,6 +191,10 @@ impl SortedSetDSTResult {
             adds: 0,
             updates: 0,
             removes: 0,
+            incrs: 0,
+            pops: 0,
+            range_removes: 0,
+            range_queries: 0,
             invariant_violations: Vec::new(),
             last_op: None,
         }
@@ -123,12 +206,17 @@ impl SortedSetDSTResult {
 
     pub fn summary(&self) -> String {
         format!(
-            "Seed {}: {} ops ({} adds, {} updates, {} removes), {} violations",
+            "Seed {}: {} ops ({} adds, {} updates, {} removes, {} incrs, {} pops, \
+             {} range removes, {} range queries), {} violations",
             self.seed,
             self.total_operations,
             self.adds,
             self.updates,
             self.removes,
+            self.incrs,
+            self.pops,
+            self.range_removes,
+            self.range_queries,
             self.invariant_violations.len()
         )
     }
@@ -170,11 +258,245 @@ impl SortedSetDSTHarness {
         raw as f64 / 100.0
     }
 
+    /// A random score bound: an infinity, a score already in the set, or a random score
+    fn random_score_bound(&mut self) -> ScoreBound {
+        let exclusive = self.rng.gen_bool(0.5);
+        let value = match self.rng.gen_range(0, 10) {
+            0 => f64::NEG_INFINITY,
+            1 => f64::INFINITY,
+            2..=5 if !self.sorted_set.is_empty() => {
+                let rank = self.rng.gen_range(0, self.sorted_set.len() as u64) as usize;
+                self.sorted_set
+                    .get_by_rank(rank)
+                    .map_or(0.0, |(_, score)| score)
+            }
+            _ => self.random_score(),
+        };
+        ScoreBound { value, exclusive }
+    }
+
+    /// A random lex bound: `-`, `+`, or a member name inclusive or exclusive
+    fn random_lex_bound(&mut self) -> LexBound {
+        match self.rng.gen_range(0, 10) {
+            0 => LexBound::NegInf,
+            1 => LexBound::PosInf,
+            roll => {
+                let member = self.random_member();
+                if roll % 2 == 0 {
+                    LexBound::Inclusive(member)
+                } else {
+                    LexBound::Exclusive(member)
+                }
+            }
+        }
+    }
+
+    /// Members with a score inside `min..max`, by scanning the whole set
+    fn scan_score_range(&self, min: &ScoreBound, max: &ScoreBound) -> Vec<(String, f64)> {
+        self.sorted_set
+            .iter()
+            .filter(|(_, score)| {
+                let above_min = if min.exclusive {
+                    *score > min.value
+                } else {
+                    *score >= min.value
+                };
+                let below_max = if max.exclusive {
+                    *score < max.value
+                } else {
+                    *score <= max.value
+                };
+                above_min && below_max
+            })
+            .map(|(member, score)| (member.to_string(), score))
+            .collect()
+    }
+
+    /// Members inside the lex range `min..max`, by scanning the whole set
+    fn scan_lex_range(&self, min: &LexBound, max: &LexBound) -> Vec<String> {
+        self.sorted_set
+            .iter()
+            .filter(|(member, _)| {
+                let above_min = match min {
+                    LexBound::NegInf => true,
+                    LexBound::PosInf => false,
+                    LexBound::Inclusive(m) => *member >= m.as_str(),
+                    LexBound::Exclusive(m) => *member > m.as_str(),
+                };
+                let below_max = match max {
+                    LexBound::NegInf => false,
+                    LexBound::PosInf => true,
+                    LexBound::Inclusive(m) => *member <= m.as_str(),
+                    LexBound::Exclusive(m) => *member < m.as_str(),
+                };
+                above_min && below_max
+            })
+            .map(|(member, _)| member.to_string())
+            .collect()
+    }
+
+    /// Run a pop, range removal or range query, checking it against a scan
+    fn run_range_op(&mut self) -> Result<(), String> {
+        match self.rng.gen_range(0, 6) {
+            0 | 1 => {
+                let max = self.rng.gen_bool(0.5);
+                self.result.last_op = Some(if max {
+                    SortedSetOp::PopMax
+                } else {
+                    SortedSetOp::PopMin
+                });
+                let popped = if max {
+                    self.sorted_set.pop_max()
+                } else {
+                    self.sorted_set.pop_min()
+                };
+                self.result.pops += 1;
+                if let Some((member, score)) = popped {
+                    if self.sorted_set.score(&member).is_some() {
+                        return Err(format!("Popped member '{}' is still present", member));
+                    }
+                    let beyond =
+                        self.sorted_set
+                            .iter()
+                            .any(|(_, s)| if max { s > score } else { s < score });
+                    if beyond {
+                        return Err(format!(
+                            "Popped score {} is not the {} of the set",
+                            score,
+                            if max { "maximum" } else { "minimum" }
+                        ));
+                    }
+                }
+            }
+            2 => {
+                let bound = self.sorted_set.len() as u64 + 2;
+                let start = self.rng.gen_range(0, 2 * bound + 1) as isize - bound as isize;
+                let stop = self.rng.gen_range(0, 2 * bound + 1) as isize - bound as isize;
+                self.result.last_op = Some(SortedSetOp::RemoveRangeByRank { start, stop });
+                let expected = self.sorted_set.range(start, stop);
+                let ranks = self.sorted_set.rank_range(start, stop, false);
+                let removed = self.sorted_set.remove_ranks(ranks);
+                self.result.range_removes += 1;
+                if removed != expected.len() {
+                    return Err(format!(
+                        "Rank range removed {} members, range({}, {}) had {}",
+                        removed,
+                        start,
+                        stop,
+                        expected.len()
+                    ));
+                }
+                if let Some((member, _)) = expected
+                    .iter()
+                    .find(|(member, _)| self.sorted_set.score(member).is_some())
+                {
+                    return Err(format!(
+                        "Member '{}' survived its rank range removal",
+                        member
+                    ));
+                }
+            }
+            3 => {
+                let min = self.random_score_bound();
+                let max = self.random_score_bound();
+                self.result.last_op = Some(SortedSetOp::RemoveRangeByScore { min, max });
+                let expected = self.scan_score_range(&min, &max);
+                let ranks = self.sorted_set.score_range(&min, &max);
+                let removed = self.sorted_set.remove_ranks(ranks);
+                self.result.range_removes += 1;
+                if removed != expected.len() || !self.scan_score_range(&min, &max).is_empty() {
+                    return Err(format!(
+                        "Score range removed {} members, scan found {}",
+                        removed,
+                        expected.len()
+                    ));
+                }
+            }
+            4 => {
+                let min = self.random_lex_bound();
+                let max = self.random_lex_bound();
+                self.result.last_op = Some(SortedSetOp::RemoveRangeByLex {
+                    min: min.clone(),
+                    max: max.clone(),
+                });
+                let expected = self.scan_lex_range(&min, &max);
+                let ranks = self.sorted_set.lex_range(&min, &max);
+                let removed = self.sorted_set.remove_ranks(ranks);
+                self.result.range_removes += 1;
+                // Lex ranges are only defined when every member has the same score
+                let equal_scores = self.config.max_score == 0.0;
+                if equal_scores
+                    && (removed != expected.len() || !self.scan_lex_range(&min, &max).is_empty())
+                {
+                    return Err(format!(
+                        "Lex range removed {} members, scan found {}",
+                        removed,
+                        expected.len()
+                    ));
+                }
+            }
+            _ => {
+                let min = self.random_score_bound();
+                let max = self.random_score_bound();
+                let rev = self.rng.gen_bool(0.5);
+                let offset = self.rng.gen_range(0, 5) as usize;
+                let count = match self.rng.gen_range(0, 7) {
+                    0 => None,
+                    c => Some(c as usize - 1),
+                };
+                self.result.last_op = Some(SortedSetOp::RangeByScore {
+                    min,
+                    max,
+                    rev,
+                    offset,
+                    count,
+                });
+                self.result.range_queries += 1;
+
+                let mut expected = self.scan_score_range(&min, &max);
+                let ranks = self.sorted_set.score_range(&min, &max);
+                if ranks.len() != expected.len() {
+                    return Err(format!(
+                        "Score range counts {} members, scan found {}",
+                        ranks.len(),
+                        expected.len()
+                    ));
+                }
+                if rev {
+                    expected.reverse();
+                }
+                let expected: Vec<String> = expected
+                    .into_iter()
+                    .skip(offset)
+                    .take(count.unwrap_or(usize::MAX))
+                    .map(|(member, _)| member)
+                    .collect();
+                let actual: Vec<String> = self
+                    .sorted_set
+                    .range_in_ranks(ranks, rev, offset, count)
+                    .into_iter()
+                    .map(|(member, _)| member.to_string())
+                    .collect();
+                if actual != expected {
+                    return Err(format!(
+                        "Score range returned {:?}, scan expected {:?}",
+                        actual, expected
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Run a single random operation
     fn run_single_op(&mut self) {
         let op_type = self.rng.gen_range(0, 100);
+        let remove_threshold = (self.config.remove_prob * 100.0) as u64;
+        let range_threshold = remove_threshold + (self.config.range_prob * 100.0) as u64;
+        let incr_threshold = range_threshold + (self.config.incr_prob * 100.0) as u64;
 
-        if op_type < (self.config.remove_prob * 100.0) as u64 {
+        let mut op_result = Ok(());
+        if op_type < remove_threshold {
             // Remove operation
             let member = self.random_member();
             self.result.last_op = Some(SortedSetOp::Remove {
@@ -182,6 +504,31 @@ impl SortedSetDSTHarness {
             });
             self.sorted_set.remove(&SDS::from_str(&member));
             self.result.removes += 1;
+        } else if op_type < range_threshold {
+            op_result = self.run_range_op();
+        } else if op_type < incr_threshold {
+            // Increment operation, by a delta of either sign
+            let member = self.random_member();
+            let delta = self.random_score() - self.config.max_score / 2.0;
+            self.result.last_op = Some(SortedSetOp::IncrBy {
+                member: member.clone(),
+                delta,
+            });
+            let sds = SDS::from_str(&member);
+            let before = self.sorted_set.score(&sds).unwrap_or(0.0);
+            let after = self.sorted_set.incr(sds.clone(), delta);
+            self.result.incrs += 1;
+            op_result = match (after, self.sorted_set.score(&sds)) {
+                (Ok(after), Some(stored))
+                    if after == stored && (after - (before + delta)).abs() <= f64::EPSILON =>
+                {
+                    Ok(())
+                }
+                (after, stored) => Err(format!(
+                    "Increment of {} by {} returned {:?}, stored {:?}",
+                    before, delta, after, stored
+                )),
+            };
         } else {
             // Add/update operation
             let member = self.random_member();
@@ -201,8 +548,8 @@ impl SortedSetDSTHarness {
 
         self.result.total_operations += 1;
 
-        // Verify invariants after each operation
-        if let Err(violation) = self.check_invariants() {
+        // Verify the operation's result, then invariants after each operation
+        if let Err(violation) = op_result.and_then(|()| self.check_invariants()) {
             self.result.invariant_violations.push(format!(
                 "Op #{}: {:?} - {}",
                 self.result.total_operations,
@@ -252,6 +599,19 @@ impl SortedSetDSTHarness {
             }
         }
 
+        // Invariant 4: rank and rev_rank agree with each member's position
+        let len = range.len();
+        for (position, (member, _)) in range.iter().enumerate() {
+            let rank = self.sorted_set.rank(member);
+            let rev_rank = self.sorted_set.rev_rank(member);
+            if rank != Some(position) || rev_rank != Some(len - 1 - position) {
+                return Err(format!(
+                    "Rank mismatch for '{}' at position {}: rank={:?}, rev_rank={:?}",
+                    member, position, rank, rev_rank
+                ));
+            }
+        }
+
         Ok(())
     }
 
@@ -348,6 +708,27 @@ mod tests {
         assert!(result.is_success());
     }
 
+    #[test]
+    fn test_sorted_set_dst_range_heavy() {
+        let results = run_sorted_set_batch(100, 10, 500, SortedSetDSTConfig::range_heavy);
+        let summary = summarize_batch(&results);
+        println!("{}", summary);
+
+        assert!(results.iter().all(|r| r.is_success()), "{}", summary);
+        assert!(results
+            .iter()
+            .all(|r| r.range_removes > 0 && r.range_queries > 0));
+    }
+
+    #[test]
+    fn test_sorted_set_dst_equal_scores_lex_ranges() {
+        let results = run_sorted_set_batch(200, 10, 500, SortedSetDSTConfig::equal_scores);
+        let summary = summarize_batch(&results);
+        println!("{}", summary);
+
+        assert!(results.iter().all(|r| r.is_success()), "{}", summary);
+    }
+
     #[test]
     fn test_sorted_set_dst_10_seeds() {
         let results = run_sorted_set_batch(0, 10, 500, SortedSetDSTConfig::new);
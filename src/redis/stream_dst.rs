@@ -0,0 +1,659 @@
+//! Deterministic Simulation Testing for Streams
+//!
+//! VOPR-style testing harness for RedisStream that enables:
+//! - Deterministic random operation generation (including a skewed clock)
+//! - Invariant checking against a shadow model after each operation
+//! - Seed-based reproducibility for debugging
+
+use super::data::{RedisStream, StreamClaimOptions, StreamId, SDS};
+use crate::io::simulation::SimulatedRng;
+use crate::io::Rng;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Configuration for Stream DST
+#[derive(Debug, Clone)]
+pub struct StreamDSTConfig {
+    /// Random seed for reproducibility
+    pub seed: u64,
+    /// Number of consumer groups
+    pub num_groups: usize,
+    /// Number of consumers per group
+    pub num_consumers: usize,
+    /// Probability of XDEL
+    pub del_prob: f64,
+    /// Probability of XTRIM (MAXLEN or MINID)
+    pub trim_prob: f64,
+    /// Probability of XREADGROUP
+    pub read_prob: f64,
+    /// Probability of XACK
+    pub ack_prob: f64,
+    /// Probability of XCLAIM / XAUTOCLAIM
+    pub claim_prob: f64,
+    /// Probability that the clock jumps backwards before an XADD
+    pub clock_skew_prob: f64,
+}
+
+impl Default for StreamDSTConfig {
+    fn default() -> Self {
+        StreamDSTConfig {
+            seed: 0,
+            num_groups: 2,
+            num_consumers: 3,
+            del_prob: 0.05,
+            trim_prob: 0.03,
+            read_prob: 0.25,
+            ack_prob: 0.15,
+            claim_prob: 0.05,
+            clock_skew_prob: 0.05,
+        }
+    }
+}
+
+impl StreamDSTConfig {
+    pub fn new(seed: u64) -> Self {
+        StreamDSTConfig {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Configuration focusing on consumer groups (reads, acks, claims)
+    pub fn consumer_heavy(seed: u64) -> Self {
+        StreamDSTConfig {
+            seed,
+            num_groups: 3,
+            num_consumers: 5,
+            del_prob: 0.03,
+            trim_prob: 0.02,
+            read_prob: 0.3,
+            ack_prob: 0.2,
+            claim_prob: 0.15,
+            clock_skew_prob: 0.02,
+        }
+    }
+
+    /// Configuration with lots of deletes, trims and clock skew
+    pub fn high_churn(seed: u64) -> Self {
+        StreamDSTConfig {
+            seed,
+            num_groups: 1,
+            num_consumers: 2,
+            del_prob: 0.15,
+            trim_prob: 0.1,
+            read_prob: 0.15,
+            ack_prob: 0.1,
+            claim_prob: 0.05,
+            clock_skew_prob: 0.2,
+        }
+    }
+}
+
+/// Operation type for logging
+#[derive(Debug, Clone)]
+pub enum StreamOp {
+    Add {
+        id: StreamId,
+    },
+    Del {
+        id: StreamId,
+    },
+    TrimMaxLen {
+        maxlen: usize,
+    },
+    TrimMinId {
+        min_id: StreamId,
+    },
+    ReadGroup {
+        group: String,
+        consumer: String,
+        count: usize,
+    },
+    Ack {
+        group: String,
+        id: StreamId,
+    },
+    Claim {
+        group: String,
+        consumer: String,
+        id: StreamId,
+    },
+    AutoClaim {
+        group: String,
+        consumer: String,
+        count: usize,
+    },
+}
+
+/// Result of a Stream DST run
+#[derive(Debug, Clone)]
+pub struct StreamDSTResult {
+    pub seed: u64,
+    pub total_operations: u64,
+    pub adds: u64,
+    pub dels: u64,
+    pub trims: u64,
+    pub reads: u64,
+    pub acks: u64,
+    pub claims: u64,
+    pub invariant_violations: Vec<String>,
+    pub last_op: Option<StreamOp>,
+}
+
+impl StreamDSTResult {
+    pub fn new(seed: u64) -> Self {
+        StreamDSTResult {
+            seed,
+            total_operations: 0,
+            adds: 0,
+            dels: 0,
+            trims: 0,
+            reads: 0,
+            acks: 0,
+            claims: 0,
+            invariant_violations: Vec::new(),
+            last_op: None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.invariant_violations.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Seed {}: {} ops (add:{}, del:{}, trim:{}, read:{}, ack:{}, claim:{}), {} violations",
+            self.seed,
+            self.total_operations,
+            self.adds,
+            self.dels,
+            self.trims,
+            self.reads,
+            self.acks,
+            self.claims,
+            self.invariant_violations.len()
+        )
+    }
+}
+
+/// DST harness for RedisStream
+pub struct StreamDSTHarness {
+    config: StreamDSTConfig,
+    rng: SimulatedRng,
+    stream: RedisStream,
+    result: StreamDSTResult,
+    /// Simulated wall clock in milliseconds (may jump backwards)
+    clock_ms: u64,
+    /// Shadow model: IDs expected to be in the stream
+    expected_ids: BTreeSet<StreamId>,
+    /// Shadow model: pending IDs per group
+    expected_pending: BTreeMap<String, BTreeSet<StreamId>>,
+    /// Highest ID ever returned by next_id (must be monotonic)
+    last_added: StreamId,
+    /// Violation detected while applying an operation
+    op_violation: Option<String>,
+}
+
+impl StreamDSTHarness {
+    pub fn new(config: StreamDSTConfig) -> Self {
+        let rng = SimulatedRng::new(config.seed);
+        let mut stream = RedisStream::new();
+        let mut expected_pending = BTreeMap::new();
+        for g in 0..config.num_groups {
+            let name = format!("group:{}", g);
+            stream.create_group(&name, StreamId::MIN);
+            expected_pending.insert(name, BTreeSet::new());
+        }
+        StreamDSTHarness {
+            result: StreamDSTResult::new(config.seed),
+            config,
+            rng,
+            stream,
+            clock_ms: 1_000,
+            expected_ids: BTreeSet::new(),
+            expected_pending,
+            last_added: StreamId::MIN,
+            op_violation: None,
+        }
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self::new(StreamDSTConfig::new(seed))
+    }
+
+    fn random_group(&mut self) -> String {
+        let idx = self.rng.gen_range(0, self.config.num_groups.max(1) as u64);
+        format!("group:{}", idx)
+    }
+
+    fn random_consumer(&mut self) -> String {
+        let idx = self
+            .rng
+            .gen_range(0, self.config.num_consumers.max(1) as u64);
+        format!("consumer:{}", idx)
+    }
+
+    fn random_pending(&mut self, group: &str) -> Option<StreamId> {
+        let pending = self.expected_pending.get(group)?;
+        if pending.is_empty() {
+            return None;
+        }
+        let idx = self.rng.gen_range(0, pending.len() as u64) as usize;
+        pending.iter().nth(idx).copied()
+    }
+
+    fn run_single_op(&mut self) {
+        let op_type = self.rng.gen_range(0, 100);
+        let del_threshold = (self.config.del_prob * 100.0) as u64;
+        let trim_threshold = del_threshold + (self.config.trim_prob * 100.0) as u64;
+        let read_threshold = trim_threshold + (self.config.read_prob * 100.0) as u64;
+        let ack_threshold = read_threshold + (self.config.ack_prob * 100.0) as u64;
+        let claim_threshold = ack_threshold + (self.config.claim_prob * 100.0) as u64;
+
+        if op_type < del_threshold && !self.expected_ids.is_empty() {
+            self.do_del();
+        } else if op_type < trim_threshold && !self.expected_ids.is_empty() {
+            self.do_trim();
+        } else if op_type < read_threshold && self.config.num_groups > 0 {
+            self.do_read_group();
+        } else if op_type < ack_threshold && self.config.num_groups > 0 {
+            self.do_ack();
+        } else if op_type < claim_threshold && self.config.num_groups > 0 {
+            self.do_claim();
+        } else {
+            self.do_add();
+        }
+
+        self.result.total_operations += 1;
+
+        let check = match self.op_violation.take() {
+            Some(violation) => Err(violation),
+            None => self.check_invariants(),
+        };
+        if let Err(violation) = check {
+            self.result.invariant_violations.push(format!(
+                "Op #{}: {:?} - {}",
+                self.result.total_operations, self.result.last_op, violation
+            ));
+        }
+    }
+
+    fn do_add(&mut self) {
+        // Advance (or occasionally skew back) the simulated clock
+        if self.rng.gen_range(0, 100) < (self.config.clock_skew_prob * 100.0) as u64 {
+            self.clock_ms = self.clock_ms.saturating_sub(self.rng.gen_range(1, 50));
+        } else {
+            self.clock_ms += self.rng.gen_range(0, 3);
+        }
+
+        let id = match self.stream.next_id("*", self.clock_ms) {
+            Ok(id) => id,
+            Err(e) => {
+                self.op_violation = Some(format!("next_id failed: {}", e));
+                return;
+            }
+        };
+        self.result.last_op = Some(StreamOp::Add { id });
+        if id <= self.last_added {
+            self.op_violation = Some(format!(
+                "ID not monotonic: {} after {}",
+                id, self.last_added
+            ));
+            return;
+        }
+        let value = self.rng.gen_range(0, 1000);
+        self.stream.add(
+            id,
+            vec![(SDS::from_str("v"), SDS::from_str(&value.to_string()))],
+        );
+        self.last_added = id;
+        self.expected_ids.insert(id);
+        self.result.adds += 1;
+    }
+
+    fn do_del(&mut self) {
+        let idx = self.rng.gen_range(0, self.expected_ids.len() as u64) as usize;
+        let Some(id) = self.expected_ids.iter().nth(idx).copied() else {
+            return;
+        };
+        self.result.last_op = Some(StreamOp::Del { id });
+        if !self.stream.delete(&id) {
+            self.op_violation = Some(format!("delete of existing {} returned false", id));
+        }
+        self.expected_ids.remove(&id);
+        self.result.dels += 1;
+    }
+
+    fn do_trim(&mut self) {
+        let len = self.expected_ids.len();
+        let removed = if self.rng.gen_bool(0.5) {
+            let maxlen = self.rng.gen_range(0, len as u64 + 2) as usize;
+            self.result.last_op = Some(StreamOp::TrimMaxLen { maxlen });
+            let expected_removed = len.saturating_sub(maxlen);
+            for _ in 0..expected_removed {
+                self.expected_ids.pop_first();
+            }
+            (self.stream.trim_maxlen(maxlen, None), expected_removed)
+        } else {
+            let idx = self.rng.gen_range(0, len as u64) as usize;
+            let Some(min_id) = self.expected_ids.iter().nth(idx).copied() else {
+                return;
+            };
+            self.result.last_op = Some(StreamOp::TrimMinId { min_id });
+            let before = self.expected_ids.len();
+            self.expected_ids.retain(|id| *id >= min_id);
+            (
+                self.stream.trim_minid(min_id, None),
+                before - self.expected_ids.len(),
+            )
+        };
+        if removed.0 != removed.1 {
+            self.op_violation = Some(format!(
+                "trim removed {} entries, expected {}",
+                removed.0, removed.1
+            ));
+        }
+        self.result.trims += 1;
+    }
+
+    fn do_read_group(&mut self) {
+        let group = self.random_group();
+        let consumer = self.random_consumer();
+        let count = self.rng.gen_range(1, 6) as usize;
+        self.result.last_op = Some(StreamOp::ReadGroup {
+            group: group.clone(),
+            consumer: consumer.clone(),
+            count,
+        });
+
+        let before = self
+            .stream
+            .group(&group)
+            .map(|g| g.last_delivered_id)
+            .unwrap_or_default();
+        let Some(entries) =
+            self.stream
+                .read_group(&group, &consumer, None, Some(count), false, self.clock_ms)
+        else {
+            self.op_violation = Some(format!("group {} missing", group));
+            return;
+        };
+
+        // Delivered entries: new, in order, present in the stream, and at most `count`
+        let expected: Vec<StreamId> = self
+            .expected_ids
+            .range(before.next().unwrap_or(StreamId::MAX)..)
+            .take(count)
+            .copied()
+            .collect();
+        let delivered: Vec<StreamId> = entries.iter().map(|(id, _)| *id).collect();
+        if delivered != expected {
+            self.op_violation = Some(format!(
+                "read_group delivered {:?}, expected {:?}",
+                delivered, expected
+            ));
+            return;
+        }
+        if let Some(pending) = self.expected_pending.get_mut(&group) {
+            pending.extend(delivered);
+        }
+        self.result.reads += 1;
+    }
+
+    fn do_ack(&mut self) {
+        let group = self.random_group();
+        let Some(id) = self.random_pending(&group) else {
+            return;
+        };
+        self.result.last_op = Some(StreamOp::Ack {
+            group: group.clone(),
+            id,
+        });
+        if self.stream.ack(&group, &[id]) != Some(1) {
+            self.op_violation = Some(format!("ack of pending {} did not return 1", id));
+        }
+        if let Some(pending) = self.expected_pending.get_mut(&group) {
+            pending.remove(&id);
+        }
+        self.result.acks += 1;
+    }
+
+    fn do_claim(&mut self) {
+        let group = self.random_group();
+        let consumer = self.random_consumer();
+        if self.rng.gen_bool(0.5) {
+            let Some(id) = self.random_pending(&group) else {
+                return;
+            };
+            self.result.last_op = Some(StreamOp::Claim {
+                group: group.clone(),
+                consumer: consumer.clone(),
+                id,
+            });
+            self.stream.claim(
+                &group,
+                &consumer,
+                0,
+                &[id],
+                &StreamClaimOptions::default(),
+                self.clock_ms,
+            );
+            // Claiming an entry deleted from the stream drops it from the PEL
+            if !self.expected_ids.contains(&id) {
+                if let Some(pending) = self.expected_pending.get_mut(&group) {
+                    pending.remove(&id);
+                }
+            }
+        } else {
+            let count = self.rng.gen_range(1, 6) as usize;
+            self.result.last_op = Some(StreamOp::AutoClaim {
+                group: group.clone(),
+                consumer: consumer.clone(),
+                count,
+            });
+            let Some((_, _, deleted)) = self.stream.autoclaim(
+                &group,
+                &consumer,
+                0,
+                StreamId::MIN,
+                count,
+                false,
+                self.clock_ms,
+            ) else {
+                self.op_violation = Some(format!("group {} missing", group));
+                return;
+            };
+            if let Some(pending) = self.expected_pending.get_mut(&group) {
+                for id in deleted {
+                    if self.expected_ids.contains(&id) {
+                        self.op_violation =
+                            Some(format!("autoclaim reported live entry {} as deleted", id));
+                    }
+                    pending.remove(&id);
+                }
+            }
+        }
+        self.result.claims += 1;
+    }
+
+    fn check_invariants(&self) -> Result<(), String> {
+        // Invariant 1: Length must match the shadow model
+        if self.stream.len() != self.expected_ids.len() {
+            return Err(format!(
+                "Length mismatch: actual={}, expected={}",
+                self.stream.len(),
+                self.expected_ids.len()
+            ));
+        }
+
+        // Invariant 2: Full range returns exactly the expected IDs in order
+        let forward: Vec<StreamId> = self
+            .stream
+            .range(StreamId::MIN, StreamId::MAX, None)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        let expected: Vec<StreamId> = self.expected_ids.iter().copied().collect();
+        if forward != expected {
+            return Err(format!(
+                "Range mismatch: actual={:?}, expected={:?}",
+                forward, expected
+            ));
+        }
+
+        // Invariant 3: Reverse range is the mirror of the forward range
+        let mut backward: Vec<StreamId> = self
+            .stream
+            .rev_range(StreamId::MIN, StreamId::MAX, None)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        backward.reverse();
+        if backward != forward {
+            return Err("Reverse range is not the mirror of the forward range".to_string());
+        }
+
+        // Invariant 4: last_id never falls behind the entries
+        if self.stream.last_id() != self.last_added {
+            return Err(format!(
+                "last_id mismatch: actual={}, expected={}",
+                self.stream.last_id(),
+                self.last_added
+            ));
+        }
+
+        // Invariant 5: Group PELs match the model and never run ahead of delivery
+        for (name, expected_pending) in &self.expected_pending {
+            let group = self
+                .stream
+                .group(name)
+                .ok_or_else(|| format!("Group {} missing", name))?;
+            let pending: BTreeSet<StreamId> = group.pending().map(|(id, _)| *id).collect();
+            if &pending != expected_pending {
+                return Err(format!(
+                    "Group {} PEL mismatch: actual={:?}, expected={:?}",
+                    name, pending, expected_pending
+                ));
+            }
+            let owned: usize = group.consumers().map(|(_, c)| c.pending.len()).sum();
+            if owned != group.pending_len() {
+                return Err(format!(
+                    "Group {} consumers own {} IDs but PEL has {}",
+                    name,
+                    owned,
+                    group.pending_len()
+                ));
+            }
+            if let Some(max_pending) = pending.last() {
+                if *max_pending > group.last_delivered_id {
+                    return Err(format!(
+                        "Group {} has pending {} beyond last delivered {}",
+                        name, max_pending, group.last_delivered_id
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn run(&mut self, operations: usize) {
+        for _ in 0..operations {
+            self.run_single_op();
+            if !self.result.invariant_violations.is_empty() {
+                break;
+            }
+        }
+    }
+
+    pub fn result(&self) -> &StreamDSTResult {
+        &self.result
+    }
+
+    pub fn stream(&self) -> &RedisStream {
+        &self.stream
+    }
+}
+
+/// Run a batch of DST tests
+pub fn run_stream_batch(
+    start_seed: u64,
+    num_seeds: usize,
+    ops_per_seed: usize,
+    config_fn: fn(u64) -> StreamDSTConfig,
+) -> Vec<StreamDSTResult> {
+    (0..num_seeds)
+        .map(|i| {
+            let seed = start_seed + i as u64;
+            let config = config_fn(seed);
+            let mut harness = StreamDSTHarness::new(config);
+            harness.run(ops_per_seed);
+            harness.result().clone()
+        })
+        .collect()
+}
+
+/// Summarize batch results
+pub fn summarize_stream_batch(results: &[StreamDSTResult]) -> String {
+    let total = results.len();
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    let failed = total - passed;
+    let total_ops: u64 = results.iter().map(|r| r.total_operations).sum();
+
+    let mut summary = format!(
+        "Stream DST Summary\n\
+         ==================\n\
+         Seeds: {} total, {} passed, {} failed\n\
+         Total operations: {}\n",
+        total, passed, failed, total_ops
+    );
+
+    if failed > 0 {
+        summary.push_str("\nFailed seeds:\n");
+        for result in results.iter().filter(|r| !r.is_success()) {
+            summary.push_str(&format!("  Seed {}: {}\n", result.seed, result.summary()));
+            for violation in &result.invariant_violations {
+                summary.push_str(&format!("    - {}\n", violation));
+            }
+        }
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_dst_single_seed() {
+        let mut harness = StreamDSTHarness::with_seed(12345);
+        harness.run(200);
+        let result = harness.result();
+        println!("{}", result.summary());
+        assert!(
+            result.is_success(),
+            "Seed 12345 failed: {:?}",
+            result.invariant_violations
+        );
+    }
+
+    #[test]
+    fn test_stream_dst_consumer_heavy() {
+        let config = StreamDSTConfig::consumer_heavy(42);
+        let mut harness = StreamDSTHarness::new(config);
+        harness.run(500);
+        let result = harness.result();
+        println!("{}", result.summary());
+        assert!(result.is_success(), "{:?}", result.invariant_violations);
+    }
+
+    #[test]
+    fn test_stream_dst_10_seeds() {
+        let results = run_stream_batch(0, 10, 500, StreamDSTConfig::new);
+        let summary = summarize_stream_batch(&results);
+        println!("{}", summary);
+
+        let passed = results.iter().filter(|r| r.is_success()).count();
+        assert_eq!(passed, 10, "All 10 seeds should pass");
+    }
+}
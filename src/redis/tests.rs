@@ -1,3 +1,48 @@
+/// Helpers shared by the command test modules: build a client's RESP array
+/// from string arguments, parse it, and run it
+#[cfg(test)]
+mod support {
+    use super::super::{Command, CommandExecutor, RespValue, RespValueZeroCopy};
+    use bytes::Bytes;
+
+    /// Parse `args` with the RESP parser
+    pub(super) fn parse(args: &[&str]) -> Result<Command, String> {
+        let resp = RespValue::Array(Some(
+            args.iter()
+                .map(|a| RespValue::BulkString(Some(a.as_bytes().to_vec())))
+                .collect(),
+        ));
+        Command::from_resp(&resp)
+    }
+
+    /// Parse `args` with the RESP parser and the zero-copy parser
+    pub(super) fn parse_both(args: &[&str]) -> (Result<Command, String>, Result<Command, String>) {
+        let zero_copy = RespValueZeroCopy::Array(Some(
+            args.iter()
+                .map(|a| RespValueZeroCopy::BulkString(Some(Bytes::copy_from_slice(a.as_bytes()))))
+                .collect(),
+        ));
+        (parse(args), Command::from_resp_zero_copy(&zero_copy))
+    }
+
+    /// Both parsers accept `args` and build the same command, which is returned
+    pub(super) fn assert_parsers_agree(args: &[&str]) -> Command {
+        let (old, new) = parse_both(args);
+        let old = old.unwrap_or_else(|e| panic!("{:?}: {}", args, e));
+        let new = new.unwrap_or_else(|e| panic!("{:?}: {}", args, e));
+        assert_eq!(format!("{:?}", old), format!("{:?}", new), "{:?}", args);
+        old
+    }
+
+    pub(super) fn run(executor: &mut CommandExecutor, args: &[&str]) -> RespValue {
+        executor.execute(&parse(args).unwrap())
+    }
+
+    pub(super) fn bulk(s: &str) -> RespValue {
+        RespValue::BulkString(Some(s.as_bytes().to_vec()))
+    }
+}
+
 #[cfg(test)]
 mod resp_parser_tests {
     use super::super::{RespCodec, RespParser, RespValue, RespValueZeroCopy};
@@ -42,6 +87,31 @@ mod resp_parser_tests {
                         .zip(a2.iter())
                         .all(|(v1, v2)| values_equivalent(v1, v2))
             }
+            (RespValue::Null, RespValueZeroCopy::Null) => true,
+            (RespValue::Boolean(b1), RespValueZeroCopy::Boolean(b2)) => b1 == b2,
+            (RespValue::Double(d1), RespValueZeroCopy::Double(d2)) => {
+                d1 == d2 || (d1.is_nan() && d2.is_nan())
+            }
+            (RespValue::BigNumber(n1), RespValueZeroCopy::BigNumber(n2)) => {
+                n1.as_bytes() == n2.as_ref()
+            }
+            (RespValue::VerbatimString(f1, t1), RespValueZeroCopy::VerbatimString(f2, t2)) => {
+                f1.as_bytes() == f2.as_ref() && t1.as_slice() == t2.as_ref()
+            }
+            (RespValue::Map(m1), RespValueZeroCopy::Map(m2)) => {
+                m1.len() == m2.len()
+                    && m1.iter().zip(m2.iter()).all(|((k1, v1), (k2, v2))| {
+                        values_equivalent(k1, k2) && values_equivalent(v1, v2)
+                    })
+            }
+            (RespValue::Set(a1), RespValueZeroCopy::Set(a2))
+            | (RespValue::Push(a1), RespValueZeroCopy::Push(a2)) => {
+                a1.len() == a2.len()
+                    && a1
+                        .iter()
+                        .zip(a2.iter())
+                        .all(|(v1, v2)| values_equivalent(v1, v2))
+            }
             _ => false,
         }
     }
@@ -89,11 +159,88 @@ mod resp_parser_tests {
         test_parse_equivalence(b"*3\r\n$3\r\nSET\r\n$5\r\nmykey\r\n$7\r\nmyvalue\r\n");
         test_parse_equivalence(b"*2\r\n$3\r\nGET\r\n$5\r\nmykey\r\n");
     }
+
+    #[test]
+    fn test_resp3_types() {
+        test_parse_equivalence(b"_\r\n");
+        test_parse_equivalence(b"#t\r\n");
+        test_parse_equivalence(b"#f\r\n");
+        test_parse_equivalence(b",3.14\r\n");
+        test_parse_equivalence(b",-inf\r\n");
+        test_parse_equivalence(b",nan\r\n");
+        test_parse_equivalence(b"(3492890328409238509324850943850943825024385\r\n");
+        test_parse_equivalence(b"=15\r\ntxt:Some string\r\n");
+        test_parse_equivalence(b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n");
+        test_parse_equivalence(b"~2\r\n$1\r\na\r\n$1\r\nb\r\n");
+        test_parse_equivalence(b">3\r\n$7\r\nmessage\r\n$2\r\nch\r\n$2\r\nhi\r\n");
+    }
+
+    #[test]
+    fn test_resp3_incomplete_frames() {
+        test_parse_equivalence(b"%1\r\n+key\r\n");
+        test_parse_equivalence(b"=15\r\ntxt:Some");
+        test_parse_equivalence(b",1.5");
+    }
+
+    #[test]
+    fn test_resp3_encode_roundtrip() {
+        let value = RespValue::Map(vec![
+            (
+                RespValue::BulkString(Some(b"score".to_vec())),
+                RespValue::Double(1.5),
+            ),
+            (
+                RespValue::SimpleString("members".to_string()),
+                RespValue::Set(vec![RespValue::Integer(1), RespValue::Null]),
+            ),
+            (
+                RespValue::SimpleString("flag".to_string()),
+                RespValue::Boolean(true),
+            ),
+            (
+                RespValue::SimpleString("info".to_string()),
+                RespValue::VerbatimString("txt".to_string(), b"a:1".to_vec()),
+            ),
+        ]);
+        let encoded = RespParser::encode(&value);
+        let (decoded, consumed) = RespParser::parse(&encoded).unwrap();
+        assert_eq!(consumed, encoded.len());
+        assert_eq!(decoded, value);
+
+        let mut buf = BytesMut::from(&encoded[..]);
+        let zero_copy = RespCodec::parse(&mut buf).unwrap().unwrap();
+        assert_eq!(&RespCodec::encode(&zero_copy)[..], &encoded[..]);
+    }
+
+    #[test]
+    fn test_resp3_downgrade_to_resp2() {
+        let value = RespValue::Map(vec![(
+            RespValue::BulkString(Some(b"f".to_vec())),
+            RespValue::Double(2.5),
+        )]);
+        assert_eq!(
+            value.into_resp2(),
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"f".to_vec())),
+                RespValue::BulkString(Some(b"2.5".to_vec())),
+            ]))
+        );
+        assert_eq!(RespValue::Null.into_resp2(), RespValue::BulkString(None));
+        assert_eq!(RespValue::Boolean(true).into_resp2(), RespValue::Integer(1));
+        assert_eq!(
+            RespValue::Push(vec![RespValue::Null]).into_resp2(),
+            RespValue::Array(Some(vec![RespValue::BulkString(None)]))
+        );
+    }
 }
 
 #[cfg(test)]
 mod command_parser_tests {
-    use super::super::{Command, RespValue, RespValueZeroCopy};
+    use super::super::{
+        ClientKillFilter, ClientPauseMode, ClientReplyMode, ClientTrackingOptions, ClientType,
+        Command, RespValue, RespValueZeroCopy,
+    };
+    use super::support::parse_both;
     use bytes::Bytes;
 
     #[test]
@@ -275,17 +422,17 @@ mod command_parser_tests {
         let mut executor = CommandExecutor::new();
 
         // First HINCRBY creates the field with the increment value
-        let cmd = Command::HIncrBy("myhash".to_string(), SDS::from_str("counter"), 5);
+        let cmd = Command::HIncrBy("myhash".into(), SDS::from_str("counter"), 5);
         let result = executor.execute(&cmd);
         assert_eq!(result, RespValue::Integer(5));
 
         // Second HINCRBY increments existing value
-        let cmd = Command::HIncrBy("myhash".to_string(), SDS::from_str("counter"), 3);
+        let cmd = Command::HIncrBy("myhash".into(), SDS::from_str("counter"), 3);
         let result = executor.execute(&cmd);
         assert_eq!(result, RespValue::Integer(8));
 
         // Negative increment (decrement)
-        let cmd = Command::HIncrBy("myhash".to_string(), SDS::from_str("counter"), -2);
+        let cmd = Command::HIncrBy("myhash".into(), SDS::from_str("counter"), -2);
         let result = executor.execute(&cmd);
         assert_eq!(result, RespValue::Integer(6));
     }
@@ -357,6 +504,309 @@ mod command_parser_tests {
             _ => panic!("Commands don't match"),
         }
     }
+
+    #[test]
+    fn test_hello_from_both_parsers() {
+        let (old_cmd, new_cmd) =
+            parse_both(&["HELLO", "3", "AUTH", "alice", "pw", "SETNAME", "app"]);
+        for cmd in [old_cmd.unwrap(), new_cmd.unwrap()] {
+            match cmd {
+                Command::Hello {
+                    protover,
+                    auth,
+                    setname,
+                } => {
+                    assert_eq!(protover, Some(3));
+                    assert_eq!(auth, Some(("alice".to_string(), "pw".to_string())));
+                    assert_eq!(setname.as_deref(), Some("app"));
+                }
+                other => panic!("Expected HELLO, got {:?}", other),
+            }
+        }
+
+        let (old_cmd, _) = parse_both(&["HELLO"]);
+        assert!(matches!(
+            old_cmd,
+            Ok(Command::Hello {
+                protover: None,
+                auth: None,
+                setname: None
+            })
+        ));
+
+        let (old_err, new_err) = parse_both(&["HELLO", "three"]);
+        assert_eq!(
+            old_err.unwrap_err(),
+            "Protocol version is not an integer or out of range"
+        );
+        assert!(new_err.is_err());
+        let (old_err, _) = parse_both(&["HELLO", "3", "AUTH", "alice"]);
+        assert_eq!(old_err.unwrap_err(), "Syntax error in HELLO option 'AUTH'");
+    }
+
+    #[test]
+    fn test_client_tracking_from_both_parsers() {
+        let (old_cmd, new_cmd) = parse_both(&[
+            "CLIENT", "TRACKING", "on", "REDIRECT", "7", "BCAST", "PREFIX", "a:", "PREFIX", "b:",
+            "NOLOOP",
+        ]);
+        let expected = ClientTrackingOptions {
+            redirect: Some(7),
+            prefixes: vec!["a:".into(), "b:".into()],
+            bcast: true,
+            noloop: true,
+            ..Default::default()
+        };
+        for cmd in [old_cmd.unwrap(), new_cmd.unwrap()] {
+            match cmd {
+                Command::ClientTracking { on, options } => {
+                    assert!(on);
+                    assert_eq!(options, expected);
+                }
+                other => panic!("Expected CLIENT TRACKING, got {:?}", other),
+            }
+        }
+        assert!(matches!(
+            parse_both(&["CLIENT", "CACHING", "yes"]).1,
+            Ok(Command::ClientCaching(true))
+        ));
+
+        for (args, error) in [
+            (
+                &["CLIENT", "TRACKING", "ON", "PREFIX", "a:"][..],
+                "PREFIX option requires BCAST mode to be enabled",
+            ),
+            (
+                &["CLIENT", "TRACKING", "ON", "OPTIN", "OPTOUT"][..],
+                "You can't use both OPTIN and OPTOUT",
+            ),
+            (
+                &["CLIENT", "TRACKING", "ON", "BCAST", "OPTIN"][..],
+                "OPTIN and OPTOUT are not compatible with BCAST",
+            ),
+        ] {
+            let (old_err, new_err) = parse_both(args);
+            assert_eq!(old_err.unwrap_err(), error);
+            assert_eq!(new_err.unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_client_admin_commands_from_both_parsers() {
+        for args in [
+            &["CLIENT", "LIST"][..],
+            &["CLIENT", "LIST", "TYPE", "pubsub", "ID", "3", "4"][..],
+            &["CLIENT", "INFO"][..],
+            &["CLIENT", "SETNAME", "worker"][..],
+            &["CLIENT", "GETNAME"][..],
+            &["CLIENT", "KILL", "127.0.0.1:6000"][..],
+            &["CLIENT", "KILL", "ID", "7", "USER", "alice", "SKIPME", "no"][..],
+            &["CLIENT", "PAUSE", "500"][..],
+            &["CLIENT", "PAUSE", "500", "write"][..],
+            &["CLIENT", "UNPAUSE"][..],
+            &["CLIENT", "NO-EVICT", "on"][..],
+            &["CLIENT", "REPLY", "skip"][..],
+        ] {
+            let (old_cmd, new_cmd) = parse_both(args);
+            assert_eq!(
+                format!("{:?}", old_cmd.unwrap()),
+                format!("{:?}", new_cmd.unwrap()),
+                "{:?}",
+                args
+            );
+        }
+
+        let (cmd, _) = parse_both(&["CLIENT", "LIST", "TYPE", "pubsub", "ID", "3", "4"]);
+        assert!(matches!(
+            cmd.unwrap(),
+            Command::ClientList { client_type: Some(ClientType::PubSub), ids } if ids == vec![3, 4]
+        ));
+        let (cmd, _) = parse_both(&["CLIENT", "KILL", "ID", "7", "USER", "alice", "SKIPME", "no"]);
+        let Command::ClientKill(filter) = cmd.unwrap() else {
+            panic!("Expected CLIENT KILL");
+        };
+        assert_eq!(
+            filter,
+            ClientKillFilter {
+                id: Some(7),
+                user: Some("alice".to_string()),
+                skipme: false,
+                ..Default::default()
+            }
+        );
+        let (cmd, _) = parse_both(&["CLIENT", "KILL", "127.0.0.1:6000"]);
+        assert!(matches!(
+            cmd.unwrap(),
+            Command::ClientKill(ClientKillFilter {
+                legacy: true,
+                skipme: false,
+                ..
+            })
+        ));
+        let (cmd, _) = parse_both(&["CLIENT", "PAUSE", "500"]);
+        assert!(matches!(
+            cmd.unwrap(),
+            Command::ClientPause {
+                timeout_ms: 500,
+                mode: ClientPauseMode::All
+            }
+        ));
+        let (cmd, _) = parse_both(&["CLIENT", "REPLY", "OFF"]);
+        assert!(matches!(
+            cmd.unwrap(),
+            Command::ClientReply(ClientReplyMode::Off)
+        ));
+
+        for (args, error) in [
+            (&["CLIENT", "PAUSE", "-1"][..], "timeout is negative"),
+            (
+                &["CLIENT", "PAUSE", "x"][..],
+                "timeout is not an integer or out of range",
+            ),
+            (&["CLIENT", "PAUSE", "10", "READ"][..], "syntax error"),
+            (&["CLIENT", "REPLY", "maybe"][..], "syntax error"),
+            (
+                &["CLIENT", "KILL", "ID", "0"][..],
+                "client-id should be greater than 0",
+            ),
+            (&["CLIENT", "KILL", "ID", "7", "SKIPME"][..], "syntax error"),
+            (
+                &["CLIENT", "LIST", "TYPE", "robot"][..],
+                "Unknown client type 'robot'",
+            ),
+            (&["CLIENT", "LIST", "ID", "x"][..], "Invalid client ID"),
+            (
+                &["CLIENT", "SETNAME"][..],
+                "wrong number of arguments for 'client|setname' command",
+            ),
+        ] {
+            let (old_err, new_err) = parse_both(args);
+            assert_eq!(old_err.unwrap_err(), error);
+            assert_eq!(new_err.unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_config_commands_from_both_parsers() {
+        let (old, new) = parse_both(&["CONFIG", "GET", "maxmemory*", "hz"]);
+        for cmd in [old.unwrap(), new.unwrap()] {
+            assert!(
+                matches!(cmd, Command::ConfigGet(patterns) if patterns == ["maxmemory*", "hz"])
+            );
+        }
+
+        let (old, new) = parse_both(&["config", "set", "timeout", "30", "hz", "20"]);
+        for cmd in [old.unwrap(), new.unwrap()] {
+            let Command::ConfigSet(pairs) = cmd else {
+                panic!("Expected CONFIG SET");
+            };
+            assert_eq!(
+                pairs,
+                vec![
+                    ("timeout".to_string(), "30".to_string()),
+                    ("hz".to_string(), "20".to_string()),
+                ]
+            );
+        }
+
+        assert!(matches!(
+            parse_both(&["CONFIG", "REWRITE"]).0.unwrap(),
+            Command::ConfigRewrite
+        ));
+        assert!(matches!(
+            parse_both(&["CONFIG", "RESETSTAT"]).1.unwrap(),
+            Command::ConfigResetStat
+        ));
+
+        for (args, error) in [
+            (
+                &["CONFIG", "SET", "timeout"][..],
+                "wrong number of arguments for 'config|set' command",
+            ),
+            (
+                &["CONFIG", "GET"][..],
+                "wrong number of arguments for 'config|get' command",
+            ),
+            (
+                &["CONFIG", "HELP2"][..],
+                "Unknown CONFIG subcommand 'HELP2'",
+            ),
+        ] {
+            let (old_err, new_err) = parse_both(args);
+            assert_eq!(old_err.unwrap_err(), error);
+            assert_eq!(new_err.unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_slowlog_and_latency_from_both_parsers() {
+        for (args, count) in [
+            (&["SLOWLOG", "GET"][..], Some(10)),
+            (&["slowlog", "get", "3"][..], Some(3)),
+            (&["SLOWLOG", "GET", "-1"][..], None),
+        ] {
+            let (old, new) = parse_both(args);
+            for cmd in [old.unwrap(), new.unwrap()] {
+                assert!(matches!(cmd, Command::SlowlogGet(c) if c == count));
+            }
+        }
+        assert!(matches!(
+            parse_both(&["SLOWLOG", "LEN"]).0.unwrap(),
+            Command::SlowlogLen
+        ));
+        assert!(matches!(
+            parse_both(&["SLOWLOG", "RESET"]).1.unwrap(),
+            Command::SlowlogReset
+        ));
+
+        let (old, new) = parse_both(&["LATENCY", "HISTORY", "expire-cycle"]);
+        for cmd in [old.unwrap(), new.unwrap()] {
+            assert!(matches!(cmd, Command::LatencyHistory(event) if event == "expire-cycle"));
+        }
+        let (old, new) = parse_both(&["latency", "reset", "a", "b"]);
+        for cmd in [old.unwrap(), new.unwrap()] {
+            assert!(matches!(cmd, Command::LatencyReset(events) if events == ["a", "b"]));
+        }
+        assert!(matches!(
+            parse_both(&["LATENCY", "LATEST"]).0.unwrap(),
+            Command::LatencyLatest
+        ));
+
+        for (args, error) in [
+            (
+                &["SLOWLOG", "GET", "-2"][..],
+                "count should be greater than or equal to -1",
+            ),
+            (
+                &["SLOWLOG", "GET", "x"][..],
+                "value is not an integer or out of range",
+            ),
+            (
+                &["SLOWLOG", "LEN", "1"][..],
+                "wrong number of arguments for 'slowlog|len' command",
+            ),
+            (
+                &["LATENCY", "HISTORY"][..],
+                "wrong number of arguments for 'latency|history' command",
+            ),
+            (
+                &["LATENCY", "DOCTOR"][..],
+                "Unknown LATENCY subcommand 'DOCTOR'",
+            ),
+        ] {
+            let (old_err, new_err) = parse_both(args);
+            assert_eq!(old_err.unwrap_err(), error);
+            assert_eq!(new_err.unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_monitor_from_both_parsers() {
+        let (old, new) = parse_both(&["monitor"]);
+        for cmd in [old.unwrap(), new.unwrap()] {
+            assert!(matches!(cmd, Command::Monitor));
+        }
+    }
 }
 
 #[cfg(test)]
@@ -451,7 +901,7 @@ mod lua_scripting_tests {
         let mut executor = CommandExecutor::new();
         let cmd = Command::Eval {
             script: "return KEYS[1]".to_string(),
-            keys: vec!["mykey".to_string()],
+            keys: vec!["mykey".into()],
             args: vec![],
         };
         let result = executor.execute(&cmd);
@@ -475,7 +925,7 @@ mod lua_scripting_tests {
         let mut executor = CommandExecutor::new();
         let cmd = Command::Eval {
             script: "return #KEYS".to_string(),
-            keys: vec!["key1".to_string(), "key2".to_string(), "key3".to_string()],
+            keys: vec!["key1".into(), "key2".into(), "key3".into()],
             args: vec![],
         };
         let result = executor.execute(&cmd);
@@ -499,7 +949,7 @@ mod lua_scripting_tests {
         let mut executor = CommandExecutor::new();
         let cmd = Command::Eval {
             script: "return KEYS[1] .. ':' .. ARGV[1]".to_string(),
-            keys: vec!["prefix".to_string()],
+            keys: vec!["prefix".into()],
             args: vec![SDS::from_str("suffix")],
         };
         let result = executor.execute(&cmd);
@@ -840,7 +1290,7 @@ mod lua_scripting_tests {
                 return redis.call("GET", KEYS[1])
             "#
             .to_string(),
-            keys: vec!["mykey".to_string()],
+            keys: vec!["mykey".into()],
             args: vec![SDS::from_str("myvalue")],
         };
         let result = executor.execute(&cmd);
@@ -1173,7 +1623,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("myvalue"),
             ex: None,
             px: None,
@@ -1186,7 +1636,7 @@ mod new_command_tests {
         assert_eq!(result, RespValue::SimpleString("OK".to_string()));
 
         // Verify the value was set
-        let get_cmd = Command::Get("mykey".to_string());
+        let get_cmd = Command::Get("mykey".into());
         assert_eq!(
             executor.execute(&get_cmd),
             RespValue::BulkString(Some(b"myvalue".to_vec()))
@@ -1199,7 +1649,7 @@ mod new_command_tests {
 
         // First set the key
         executor.execute(&Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("original"),
             ex: None,
             px: None,
@@ -1210,7 +1660,7 @@ mod new_command_tests {
 
         // NX should fail when key exists
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("newvalue"),
             ex: None,
             px: None,
@@ -1223,7 +1673,7 @@ mod new_command_tests {
         assert_eq!(result, RespValue::BulkString(None));
 
         // Value should be unchanged
-        let get_cmd = Command::Get("mykey".to_string());
+        let get_cmd = Command::Get("mykey".into());
         assert_eq!(
             executor.execute(&get_cmd),
             RespValue::BulkString(Some(b"original".to_vec()))
@@ -1236,7 +1686,7 @@ mod new_command_tests {
 
         // First set the key
         executor.execute(&Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("original"),
             ex: None,
             px: None,
@@ -1247,7 +1697,7 @@ mod new_command_tests {
 
         // XX should succeed when key exists
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("newvalue"),
             ex: None,
             px: None,
@@ -1260,7 +1710,7 @@ mod new_command_tests {
         assert_eq!(result, RespValue::SimpleString("OK".to_string()));
 
         // Value should be updated
-        let get_cmd = Command::Get("mykey".to_string());
+        let get_cmd = Command::Get("mykey".into());
         assert_eq!(
             executor.execute(&get_cmd),
             RespValue::BulkString(Some(b"newvalue".to_vec()))
@@ -1273,7 +1723,7 @@ mod new_command_tests {
 
         // XX should fail when key doesn't exist
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("myvalue"),
             ex: None,
             px: None,
@@ -1286,7 +1736,7 @@ mod new_command_tests {
         assert_eq!(result, RespValue::BulkString(None));
 
         // Key should not exist
-        let get_cmd = Command::Get("mykey".to_string());
+        let get_cmd = Command::Get("mykey".into());
         assert_eq!(executor.execute(&get_cmd), RespValue::BulkString(None));
     }
 
@@ -1296,7 +1746,7 @@ mod new_command_tests {
 
         // First set the key
         executor.execute(&Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("original"),
             ex: None,
             px: None,
@@ -1307,7 +1757,7 @@ mod new_command_tests {
 
         // SET with GET should return old value
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("newvalue"),
             ex: None,
             px: None,
@@ -1320,7 +1770,7 @@ mod new_command_tests {
         assert_eq!(result, RespValue::BulkString(Some(b"original".to_vec())));
 
         // Value should be updated
-        let get_cmd = Command::Get("mykey".to_string());
+        let get_cmd = Command::Get("mykey".into());
         assert_eq!(
             executor.execute(&get_cmd),
             RespValue::BulkString(Some(b"newvalue".to_vec()))
@@ -1332,7 +1782,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("myvalue"),
             ex: None,
             px: None,
@@ -1353,7 +1803,7 @@ mod new_command_tests {
         executor.set_time(VirtualTime::from_millis(0));
 
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("myvalue"),
             ex: Some(10), // 10 seconds
             px: None,
@@ -1364,7 +1814,7 @@ mod new_command_tests {
         executor.execute(&cmd);
 
         // Key should exist immediately
-        let get_cmd = Command::Get("mykey".to_string());
+        let get_cmd = Command::Get("mykey".into());
         assert_eq!(
             executor.execute(&get_cmd),
             RespValue::BulkString(Some(b"myvalue".to_vec()))
@@ -1385,7 +1835,7 @@ mod new_command_tests {
         executor.set_time(VirtualTime::from_millis(0));
 
         let cmd = Command::Set {
-            key: "mykey".to_string(),
+            key: "mykey".into(),
             value: SDS::from_str("myvalue"),
             ex: None,
             px: Some(500), // 500 milliseconds
@@ -1397,7 +1847,7 @@ mod new_command_tests {
 
         // Key should exist at 400ms
         executor.set_time(VirtualTime::from_millis(400));
-        let get_cmd = Command::Get("mykey".to_string());
+        let get_cmd = Command::Get("mykey".into());
         assert_eq!(
             executor.execute(&get_cmd),
             RespValue::BulkString(Some(b"myvalue".to_vec()))
@@ -1419,7 +1869,7 @@ mod new_command_tests {
         // Set multiple keys
         for key in ["key1", "key2", "key3"] {
             executor.execute(&Command::Set {
-                key: key.to_string(),
+                key: key.into(),
                 value: SDS::from_str("value"),
                 ex: None,
                 px: None,
@@ -1431,10 +1881,10 @@ mod new_command_tests {
 
         // Delete all at once
         let cmd = Command::Del(vec![
-            "key1".to_string(),
-            "key2".to_string(),
-            "key3".to_string(),
-            "nonexistent".to_string(),
+            "key1".into(),
+            "key2".into(),
+            "key3".into(),
+            "nonexistent".into(),
         ]);
         let result = executor.execute(&cmd);
 
@@ -1444,7 +1894,7 @@ mod new_command_tests {
         // All keys should be gone
         for key in ["key1", "key2", "key3"] {
             assert_eq!(
-                executor.execute(&Command::Get(key.to_string())),
+                executor.execute(&Command::Get(key.into())),
                 RespValue::BulkString(None)
             );
         }
@@ -1460,18 +1910,18 @@ mod new_command_tests {
 
         // Create list
         executor.execute(&Command::RPush(
-            "mylist".to_string(),
+            "mylist".into(),
             vec![SDS::from_str("a"), SDS::from_str("b"), SDS::from_str("c")],
         ));
 
         // Set element at index 1
-        let cmd = Command::LSet("mylist".to_string(), 1, SDS::from_str("B"));
+        let cmd = Command::LSet("mylist".into(), 1, SDS::from_str("B"));
         let result = executor.execute(&cmd);
 
         assert_eq!(result, RespValue::SimpleString("OK".to_string()));
 
         // Verify
-        let lrange = Command::LRange("mylist".to_string(), 0, -1);
+        let lrange = Command::LRange("mylist".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements[1], RespValue::BulkString(Some(b"B".to_vec())));
         } else {
@@ -1484,17 +1934,17 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::RPush(
-            "mylist".to_string(),
+            "mylist".into(),
             vec![SDS::from_str("a"), SDS::from_str("b"), SDS::from_str("c")],
         ));
 
         // Set last element using negative index
-        let cmd = Command::LSet("mylist".to_string(), -1, SDS::from_str("C"));
+        let cmd = Command::LSet("mylist".into(), -1, SDS::from_str("C"));
         let result = executor.execute(&cmd);
 
         assert_eq!(result, RespValue::SimpleString("OK".to_string()));
 
-        let lrange = Command::LRange("mylist".to_string(), 0, -1);
+        let lrange = Command::LRange("mylist".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements[2], RespValue::BulkString(Some(b"C".to_vec())));
         } else {
@@ -1506,12 +1956,9 @@ mod new_command_tests {
     fn test_lset_out_of_range() {
         let mut executor = CommandExecutor::new();
 
-        executor.execute(&Command::RPush(
-            "mylist".to_string(),
-            vec![SDS::from_str("a")],
-        ));
+        executor.execute(&Command::RPush("mylist".into(), vec![SDS::from_str("a")]));
 
-        let cmd = Command::LSet("mylist".to_string(), 5, SDS::from_str("X"));
+        let cmd = Command::LSet("mylist".into(), 5, SDS::from_str("X"));
         let result = executor.execute(&cmd);
 
         assert!(matches!(result, RespValue::Error(_)));
@@ -1526,7 +1973,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::RPush(
-            "mylist".to_string(),
+            "mylist".into(),
             vec![
                 SDS::from_str("a"),
                 SDS::from_str("b"),
@@ -1537,12 +1984,12 @@ mod new_command_tests {
         ));
 
         // Keep only elements 1-3 (b, c, d)
-        let cmd = Command::LTrim("mylist".to_string(), 1, 3);
+        let cmd = Command::LTrim("mylist".into(), 1, 3);
         let result = executor.execute(&cmd);
 
         assert_eq!(result, RespValue::SimpleString("OK".to_string()));
 
-        let lrange = Command::LRange("mylist".to_string(), 0, -1);
+        let lrange = Command::LRange("mylist".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements.len(), 3);
             assert_eq!(elements[0], RespValue::BulkString(Some(b"b".to_vec())));
@@ -1558,15 +2005,15 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::RPush(
-            "mylist".to_string(),
+            "mylist".into(),
             vec![SDS::from_str("a"), SDS::from_str("b"), SDS::from_str("c")],
         ));
 
         // Keep last 2 elements
-        let cmd = Command::LTrim("mylist".to_string(), -2, -1);
+        let cmd = Command::LTrim("mylist".into(), -2, -1);
         executor.execute(&cmd);
 
-        let lrange = Command::LRange("mylist".to_string(), 0, -1);
+        let lrange = Command::LRange("mylist".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements.len(), 2);
             assert_eq!(elements[0], RespValue::BulkString(Some(b"b".to_vec())));
@@ -1585,18 +2032,18 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::RPush(
-            "source".to_string(),
+            "source".into(),
             vec![SDS::from_str("a"), SDS::from_str("b"), SDS::from_str("c")],
         ));
 
-        let cmd = Command::RPopLPush("source".to_string(), "dest".to_string());
+        let cmd = Command::RPopLPush("source".into(), "dest".into());
         let result = executor.execute(&cmd);
 
         // Should return the popped element
         assert_eq!(result, RespValue::BulkString(Some(b"c".to_vec())));
 
         // Source should have [a, b]
-        let lrange = Command::LRange("source".to_string(), 0, -1);
+        let lrange = Command::LRange("source".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements.len(), 2);
         } else {
@@ -1604,7 +2051,7 @@ mod new_command_tests {
         }
 
         // Dest should have [c]
-        let lrange = Command::LRange("dest".to_string(), 0, -1);
+        let lrange = Command::LRange("dest".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements.len(), 1);
             assert_eq!(elements[0], RespValue::BulkString(Some(b"c".to_vec())));
@@ -1618,15 +2065,15 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::RPush(
-            "mylist".to_string(),
+            "mylist".into(),
             vec![SDS::from_str("a"), SDS::from_str("b"), SDS::from_str("c")],
         ));
 
         // Rotate: pop from right, push to left
-        let cmd = Command::RPopLPush("mylist".to_string(), "mylist".to_string());
+        let cmd = Command::RPopLPush("mylist".into(), "mylist".into());
         executor.execute(&cmd);
 
-        let lrange = Command::LRange("mylist".to_string(), 0, -1);
+        let lrange = Command::LRange("mylist".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements.len(), 3);
             assert_eq!(elements[0], RespValue::BulkString(Some(b"c".to_vec())));
@@ -1641,7 +2088,7 @@ mod new_command_tests {
     fn test_rpoplpush_empty_source() {
         let mut executor = CommandExecutor::new();
 
-        let cmd = Command::RPopLPush("empty".to_string(), "dest".to_string());
+        let cmd = Command::RPopLPush("empty".into(), "dest".into());
         let result = executor.execute(&cmd);
 
         assert_eq!(result, RespValue::BulkString(None));
@@ -1656,13 +2103,13 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::RPush(
-            "src".to_string(),
+            "src".into(),
             vec![SDS::from_str("a"), SDS::from_str("b"), SDS::from_str("c")],
         ));
 
         let cmd = Command::LMove {
-            source: "src".to_string(),
-            dest: "dst".to_string(),
+            source: "src".into(),
+            dest: "dst".into(),
             wherefrom: "LEFT".to_string(),
             whereto: "LEFT".to_string(),
         };
@@ -1671,11 +2118,11 @@ mod new_command_tests {
         assert_eq!(result, RespValue::BulkString(Some(b"a".to_vec())));
 
         // src should be [b, c]
-        let llen = Command::LLen("src".to_string());
+        let llen = Command::LLen("src".into());
         assert_eq!(executor.execute(&llen), RespValue::Integer(2));
 
         // dst should be [a]
-        let lrange = Command::LRange("dst".to_string(), 0, -1);
+        let lrange = Command::LRange("dst".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements[0], RespValue::BulkString(Some(b"a".to_vec())));
         } else {
@@ -1688,21 +2135,21 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::RPush(
-            "src".to_string(),
+            "src".into(),
             vec![SDS::from_str("a"), SDS::from_str("b"), SDS::from_str("c")],
         ));
-        executor.execute(&Command::RPush("dst".to_string(), vec![SDS::from_str("x")]));
+        executor.execute(&Command::RPush("dst".into(), vec![SDS::from_str("x")]));
 
         let cmd = Command::LMove {
-            source: "src".to_string(),
-            dest: "dst".to_string(),
+            source: "src".into(),
+            dest: "dst".into(),
             wherefrom: "RIGHT".to_string(),
             whereto: "RIGHT".to_string(),
         };
         executor.execute(&cmd);
 
         // dst should be [x, c]
-        let lrange = Command::LRange("dst".to_string(), 0, -1);
+        let lrange = Command::LRange("dst".into(), 0, -1);
         if let RespValue::Array(Some(elements)) = executor.execute(&lrange) {
             assert_eq!(elements.len(), 2);
             assert_eq!(elements[1], RespValue::BulkString(Some(b"c".to_vec())));
@@ -1720,7 +2167,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::ZAdd {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             pairs: vec![
                 (1.0, SDS::from_str("one")),
                 (2.0, SDS::from_str("two")),
@@ -1733,7 +2180,7 @@ mod new_command_tests {
             ch: false,
         });
 
-        let cmd = Command::ZCount("myzset".to_string(), "1".to_string(), "2".to_string());
+        let cmd = Command::ZCount("myzset".into(), "1".to_string(), "2".to_string());
         let result = executor.execute(&cmd);
 
         assert_eq!(result, RespValue::Integer(2));
@@ -1744,7 +2191,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::ZAdd {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             pairs: vec![
                 (1.0, SDS::from_str("one")),
                 (2.0, SDS::from_str("two")),
@@ -1757,7 +2204,7 @@ mod new_command_tests {
             ch: false,
         });
 
-        let cmd = Command::ZCount("myzset".to_string(), "-inf".to_string(), "+inf".to_string());
+        let cmd = Command::ZCount("myzset".into(), "-inf".to_string(), "+inf".to_string());
         let result = executor.execute(&cmd);
 
         assert_eq!(result, RespValue::Integer(3));
@@ -1768,7 +2215,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::ZAdd {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             pairs: vec![
                 (1.0, SDS::from_str("one")),
                 (2.0, SDS::from_str("two")),
@@ -1782,7 +2229,7 @@ mod new_command_tests {
         });
 
         // Exclusive min: (1 means > 1, not >= 1
-        let cmd = Command::ZCount("myzset".to_string(), "(1".to_string(), "3".to_string());
+        let cmd = Command::ZCount("myzset".into(), "(1".to_string(), "3".to_string());
         let result = executor.execute(&cmd);
 
         assert_eq!(result, RespValue::Integer(2)); // two and three
@@ -1797,7 +2244,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::ZAdd {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             pairs: vec![
                 (1.0, SDS::from_str("one")),
                 (2.0, SDS::from_str("two")),
@@ -1811,7 +2258,7 @@ mod new_command_tests {
         });
 
         let cmd = Command::ZRangeByScore {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             min: "1".to_string(),
             max: "2".to_string(),
             with_scores: false,
@@ -1833,7 +2280,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::ZAdd {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             pairs: vec![(1.5, SDS::from_str("one")), (2.5, SDS::from_str("two"))],
             nx: false,
             xx: false,
@@ -1843,7 +2290,7 @@ mod new_command_tests {
         });
 
         let cmd = Command::ZRangeByScore {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             min: "-inf".to_string(),
             max: "+inf".to_string(),
             with_scores: true,
@@ -1863,7 +2310,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::ZAdd {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             pairs: vec![
                 (1.0, SDS::from_str("a")),
                 (2.0, SDS::from_str("b")),
@@ -1878,7 +2325,7 @@ mod new_command_tests {
         });
 
         let cmd = Command::ZRangeByScore {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             min: "-inf".to_string(),
             max: "+inf".to_string(),
             with_scores: false,
@@ -1905,7 +2352,7 @@ mod new_command_tests {
 
         for i in 0..25 {
             executor.execute(&Command::Set {
-                key: format!("key:{}", i),
+                key: SDS::from(format!("key:{}", i)),
                 value: SDS::from_str("value"),
                 ex: None,
                 px: None,
@@ -1939,7 +2386,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::Set {
-            key: "user:1".to_string(),
+            key: "user:1".into(),
             value: SDS::from_str("alice"),
             ex: None,
             px: None,
@@ -1948,7 +2395,7 @@ mod new_command_tests {
             get: false,
         });
         executor.execute(&Command::Set {
-            key: "user:2".to_string(),
+            key: "user:2".into(),
             value: SDS::from_str("bob"),
             ex: None,
             px: None,
@@ -1957,7 +2404,7 @@ mod new_command_tests {
             get: false,
         });
         executor.execute(&Command::Set {
-            key: "session:1".to_string(),
+            key: "session:1".into(),
             value: SDS::from_str("data"),
             ex: None,
             px: None,
@@ -1968,7 +2415,7 @@ mod new_command_tests {
 
         let cmd = Command::Scan {
             cursor: 0,
-            pattern: Some("user:*".to_string()),
+            pattern: Some("user:*".into()),
             count: Some(100),
         };
         let result = executor.execute(&cmd);
@@ -1991,7 +2438,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::HSet(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![
                 (SDS::from_str("field1"), SDS::from_str("value1")),
                 (SDS::from_str("field2"), SDS::from_str("value2")),
@@ -2000,7 +2447,7 @@ mod new_command_tests {
         ));
 
         let cmd = Command::HScan {
-            key: "myhash".to_string(),
+            key: "myhash".into(),
             cursor: 0,
             pattern: None,
             count: Some(10),
@@ -2027,7 +2474,7 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::ZAdd {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             pairs: vec![
                 (1.0, SDS::from_str("one")),
                 (2.0, SDS::from_str("two")),
@@ -2041,7 +2488,7 @@ mod new_command_tests {
         });
 
         let cmd = Command::ZScan {
-            key: "myzset".to_string(),
+            key: "myzset".into(),
             cursor: 0,
             pattern: None,
             count: Some(10),
@@ -2072,7 +2519,7 @@ mod new_command_tests {
 
         // Queue commands
         let r1 = executor.execute(&Command::Set {
-            key: "foo".to_string(),
+            key: "foo".into(),
             value: SDS::from_str("bar"),
             ex: None,
             px: None,
@@ -2082,7 +2529,7 @@ mod new_command_tests {
         });
         assert_eq!(r1, RespValue::SimpleString("QUEUED".to_string()));
 
-        let r2 = executor.execute(&Command::Incr("counter".to_string()));
+        let r2 = executor.execute(&Command::Incr("counter".into()));
         assert_eq!(r2, RespValue::SimpleString("QUEUED".to_string()));
 
         // Execute transaction
@@ -2098,7 +2545,7 @@ mod new_command_tests {
 
         // Values should be set
         assert_eq!(
-            executor.execute(&Command::Get("foo".to_string())),
+            executor.execute(&Command::Get("foo".into())),
             RespValue::BulkString(Some(b"bar".to_vec()))
         );
     }
@@ -2109,7 +2556,7 @@ mod new_command_tests {
 
         executor.execute(&Command::Multi);
         executor.execute(&Command::Set {
-            key: "foo".to_string(),
+            key: "foo".into(),
             value: SDS::from_str("bar"),
             ex: None,
             px: None,
@@ -2123,7 +2570,7 @@ mod new_command_tests {
 
         // Value should NOT be set
         assert_eq!(
-            executor.execute(&Command::Get("foo".to_string())),
+            executor.execute(&Command::Get("foo".into())),
             RespValue::BulkString(None)
         );
     }
@@ -2155,7 +2602,7 @@ mod new_command_tests {
 
         // Set initial value
         executor.execute(&Command::Set {
-            key: "watched".to_string(),
+            key: "watched".into(),
             value: SDS::from_str("initial"),
             ex: None,
             px: None,
@@ -2165,12 +2612,12 @@ mod new_command_tests {
         });
 
         // Watch the key
-        executor.execute(&Command::Watch(vec!["watched".to_string()]));
+        executor.execute(&Command::Watch(vec!["watched".into()]));
 
         // Start transaction
         executor.execute(&Command::Multi);
         executor.execute(&Command::Set {
-            key: "watched".to_string(),
+            key: "watched".into(),
             value: SDS::from_str("updated"),
             ex: None,
             px: None,
@@ -2185,7 +2632,7 @@ mod new_command_tests {
 
         // Value should be updated
         assert_eq!(
-            executor.execute(&Command::Get("watched".to_string())),
+            executor.execute(&Command::Get("watched".into())),
             RespValue::BulkString(Some(b"updated".to_vec()))
         );
     }
@@ -2194,7 +2641,7 @@ mod new_command_tests {
     fn test_unwatch() {
         let mut executor = CommandExecutor::new();
 
-        executor.execute(&Command::Watch(vec!["key".to_string()]));
+        executor.execute(&Command::Watch(vec!["key".into()]));
         let result = executor.execute(&Command::Unwatch);
         assert_eq!(result, RespValue::SimpleString("OK".to_string()));
     }
@@ -2209,13 +2656,13 @@ mod new_command_tests {
 
         // Set hash field to non-integer string
         executor.execute(&Command::HSet(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![(SDS::from_str("field"), SDS::from_str("notanumber"))],
         ));
 
         // HINCRBY should fail
         let result = executor.execute(&Command::HIncrBy(
-            "myhash".to_string(),
+            "myhash".into(),
             SDS::from_str("field"),
             1,
         ));
@@ -2227,16 +2674,4263 @@ mod new_command_tests {
         let mut executor = CommandExecutor::new();
 
         executor.execute(&Command::HSet(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![(SDS::from_str("field"), SDS::from_str(&i64::MAX.to_string()))],
         ));
 
         // This should overflow
         let result = executor.execute(&Command::HIncrBy(
-            "myhash".to_string(),
+            "myhash".into(),
             SDS::from_str("field"),
             1,
         ));
         assert!(matches!(result, RespValue::Error(_)));
     }
 }
+
+#[cfg(test)]
+mod stream_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, bulk, run};
+
+    fn entry_ids(reply: &RespValue) -> Vec<String> {
+        match reply {
+            RespValue::Array(Some(entries)) => entries
+                .iter()
+                .map(|e| match e {
+                    RespValue::Array(Some(parts)) => match &parts[0] {
+                        RespValue::BulkString(Some(id)) => String::from_utf8(id.clone()).unwrap(),
+                        other => panic!("unexpected id {:?}", other),
+                    },
+                    other => panic!("unexpected entry {:?}", other),
+                })
+                .collect(),
+            other => panic!("unexpected reply {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stream_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec![
+                "XADD",
+                "s",
+                "NOMKSTREAM",
+                "MAXLEN",
+                "~",
+                "10",
+                "*",
+                "f",
+                "v",
+            ],
+            vec!["XRANGE", "s", "-", "+", "COUNT", "5"],
+            vec![
+                "XREADGROUP",
+                "GROUP",
+                "g",
+                "c",
+                "COUNT",
+                "2",
+                "NOACK",
+                "STREAMS",
+                "s",
+                ">",
+            ],
+            vec![
+                "XCLAIM",
+                "s",
+                "g",
+                "c",
+                "100",
+                "1-0",
+                "2-0",
+                "JUSTID",
+                "RETRYCOUNT",
+                "3",
+            ],
+            vec!["XAUTOCLAIM", "s", "g", "c", "0", "0-0", "COUNT", "5"],
+        ];
+        for args in cases {
+            assert_parsers_agree(&args);
+        }
+    }
+
+    #[test]
+    fn test_xadd_xlen_xrange() {
+        let mut executor = CommandExecutor::new();
+
+        assert_eq!(
+            run(&mut executor, &["XADD", "s", "1-1", "a", "1"]),
+            bulk("1-1")
+        );
+        assert_eq!(
+            run(&mut executor, &["XADD", "s", "1-*", "b", "2"]),
+            bulk("1-2")
+        );
+        assert_eq!(
+            run(&mut executor, &["XADD", "s", "5", "c", "3"]),
+            bulk("5-0")
+        );
+        assert_eq!(run(&mut executor, &["XLEN", "s"]), RespValue::Integer(3));
+        assert_eq!(
+            run(&mut executor, &["TYPE", "s"]),
+            RespValue::SimpleString("stream".to_string())
+        );
+
+        let reply = run(&mut executor, &["XRANGE", "s", "-", "+"]);
+        assert_eq!(entry_ids(&reply), vec!["1-1", "1-2", "5-0"]);
+        let reply = run(&mut executor, &["XRANGE", "s", "(1-1", "+", "COUNT", "1"]);
+        assert_eq!(entry_ids(&reply), vec!["1-2"]);
+        let reply = run(&mut executor, &["XREVRANGE", "s", "+", "-", "COUNT", "2"]);
+        assert_eq!(entry_ids(&reply), vec!["5-0", "1-2"]);
+    }
+
+    #[test]
+    fn test_xadd_errors() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["XADD", "s", "5-0", "f", "v"]);
+
+        let result = run(&mut executor, &["XADD", "s", "3-0", "f", "v"]);
+        assert!(matches!(result, RespValue::Error(ref e) if e.contains("equal or smaller")));
+        let result = run(&mut executor, &["XADD", "other", "0-0", "f", "v"]);
+        assert!(matches!(result, RespValue::Error(ref e) if e.contains("greater than 0-0")));
+        let result = run(&mut executor, &["XADD", "other", "bogus", "f", "v"]);
+        assert!(matches!(result, RespValue::Error(_)));
+        // Failed XADD must not create the key
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "other"]),
+            RespValue::Integer(0)
+        );
+
+        // NOMKSTREAM on a missing key returns nil
+        assert_eq!(
+            run(
+                &mut executor,
+                &["XADD", "missing", "NOMKSTREAM", "*", "f", "v"]
+            ),
+            RespValue::BulkString(None)
+        );
+
+        run(&mut executor, &["SET", "str", "x"]);
+        let result = run(&mut executor, &["XADD", "str", "*", "f", "v"]);
+        assert!(matches!(result, RespValue::Error(ref e) if e.starts_with("WRONGTYPE")));
+    }
+
+    #[test]
+    fn test_xdel_and_xtrim() {
+        let mut executor = CommandExecutor::new();
+        for i in 1..=6 {
+            let id = format!("{}-0", i);
+            run(&mut executor, &["XADD", "s", &id, "f", "v"]);
+        }
+        assert_eq!(
+            run(&mut executor, &["XDEL", "s", "2-0", "99-0"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["XTRIM", "s", "MAXLEN", "3"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["XTRIM", "s", "MINID", "6"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(run(&mut executor, &["XLEN", "s"]), RespValue::Integer(1));
+    }
+
+    #[test]
+    fn test_xgroup_create_errors() {
+        let mut executor = CommandExecutor::new();
+
+        let result = run(&mut executor, &["XGROUP", "CREATE", "s", "g", "$"]);
+        assert!(matches!(result, RespValue::Error(ref e) if e.contains("MKSTREAM")));
+        assert_eq!(
+            run(
+                &mut executor,
+                &["XGROUP", "CREATE", "s", "g", "$", "MKSTREAM"]
+            ),
+            RespValue::SimpleString("OK".to_string())
+        );
+        let result = run(&mut executor, &["XGROUP", "CREATE", "s", "g", "$"]);
+        assert!(matches!(result, RespValue::Error(ref e) if e.starts_with("BUSYGROUP")));
+
+        let result = run(
+            &mut executor,
+            &["XREADGROUP", "GROUP", "nope", "c", "STREAMS", "s", ">"],
+        );
+        assert!(matches!(result, RespValue::Error(ref e) if e.starts_with("NOGROUP")));
+
+        assert_eq!(
+            run(&mut executor, &["XGROUP", "DESTROY", "s", "g"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["XGROUP", "DESTROY", "s", "g"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_xreadgroup_xack_xpending() {
+        let mut executor = CommandExecutor::new();
+        for i in 1..=3 {
+            let id = format!("{}-0", i);
+            run(&mut executor, &["XADD", "s", &id, "f", "v"]);
+        }
+        run(&mut executor, &["XGROUP", "CREATE", "s", "g", "0"]);
+
+        let reply = run(
+            &mut executor,
+            &[
+                "XREADGROUP",
+                "GROUP",
+                "g",
+                "alice",
+                "COUNT",
+                "2",
+                "STREAMS",
+                "s",
+                ">",
+            ],
+        );
+        match reply {
+            RespValue::Array(Some(streams)) => {
+                assert_eq!(streams.len(), 1);
+                match &streams[0] {
+                    RespValue::Array(Some(pair)) => {
+                        assert_eq!(pair[0], bulk("s"));
+                        assert_eq!(entry_ids(&pair[1]), vec!["1-0", "2-0"]);
+                    }
+                    other => panic!("unexpected {:?}", other),
+                }
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Summary form: count, min, max, per-consumer counts
+        match run(&mut executor, &["XPENDING", "s", "g"]) {
+            RespValue::Array(Some(summary)) => {
+                assert_eq!(summary[0], RespValue::Integer(2));
+                assert_eq!(summary[1], bulk("1-0"));
+                assert_eq!(summary[2], bulk("2-0"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        assert_eq!(
+            run(&mut executor, &["XACK", "s", "g", "1-0", "1-0", "7-0"]),
+            RespValue::Integer(1)
+        );
+        match run(
+            &mut executor,
+            &["XPENDING", "s", "g", "-", "+", "10", "alice"],
+        ) {
+            RespValue::Array(Some(entries)) => assert_eq!(entries.len(), 1),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Nothing new for a second read past the last entry
+        run(
+            &mut executor,
+            &["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", ">"],
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["XREADGROUP", "GROUP", "g", "bob", "STREAMS", "s", ">"]
+            ),
+            RespValue::Array(None)
+        );
+    }
+
+    #[test]
+    fn test_xclaim_and_xautoclaim() {
+        let mut executor = CommandExecutor::new();
+        for i in 1..=3 {
+            let id = format!("{}-0", i);
+            run(&mut executor, &["XADD", "s", &id, "f", "v"]);
+        }
+        run(&mut executor, &["XGROUP", "CREATE", "s", "g", "0"]);
+        run(
+            &mut executor,
+            &["XREADGROUP", "GROUP", "g", "alice", "STREAMS", "s", ">"],
+        );
+
+        let reply = run(
+            &mut executor,
+            &["XCLAIM", "s", "g", "bob", "0", "1-0", "JUSTID"],
+        );
+        assert_eq!(reply, RespValue::Array(Some(vec![bulk("1-0")])));
+
+        run(&mut executor, &["XDEL", "s", "2-0"]);
+        match run(
+            &mut executor,
+            &["XAUTOCLAIM", "s", "g", "carol", "0", "0-0", "COUNT", "10"],
+        ) {
+            RespValue::Array(Some(parts)) => {
+                assert_eq!(parts[0], bulk("0-0"));
+                assert_eq!(entry_ids(&parts[1]), vec!["1-0", "3-0"]);
+                assert_eq!(parts[2], RespValue::Array(Some(vec![bulk("2-0")])));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_xread_plain() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["XADD", "s", "1-0", "f", "v"]);
+        run(&mut executor, &["XADD", "s", "2-0", "f", "v"]);
+
+        match run(
+            &mut executor,
+            &["XREAD", "COUNT", "10", "STREAMS", "s", "1-0"],
+        ) {
+            RespValue::Array(Some(streams)) => match &streams[0] {
+                RespValue::Array(Some(pair)) => assert_eq!(entry_ids(&pair[1]), vec!["2-0"]),
+                other => panic!("unexpected {:?}", other),
+            },
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(
+            run(&mut executor, &["XREAD", "STREAMS", "s", "$"]),
+            RespValue::Array(None)
+        );
+    }
+}
+
+#[cfg(test)]
+mod pubsub_command_tests {
+    use super::super::{Command, CommandExecutor, RespValue};
+    use super::support::parse;
+
+    #[test]
+    fn test_pubsub_parsing() {
+        assert!(matches!(
+            parse(&["SUBSCRIBE", "a", "b"]),
+            Ok(Command::Subscribe(ref c)) if c.len() == 2
+        ));
+        assert!(matches!(parse(&["UNSUBSCRIBE"]), Ok(Command::Unsubscribe(ref c)) if c.is_empty()));
+        assert!(matches!(
+            parse(&["psubscribe", "n*"]),
+            Ok(Command::PSubscribe(_))
+        ));
+        assert!(matches!(
+            parse(&["SSUBSCRIBE", "s"]),
+            Ok(Command::SSubscribe(_))
+        ));
+        assert!(matches!(
+            parse(&["PUBLISH", "a", "msg"]),
+            Ok(Command::Publish { .. })
+        ));
+        assert!(matches!(
+            parse(&["SPUBLISH", "a", "msg"]),
+            Ok(Command::SPublish { .. })
+        ));
+        assert!(matches!(
+            parse(&["PUBSUB", "CHANNELS", "a*"]),
+            Ok(Command::PubSubChannels(Some(_)))
+        ));
+        assert!(matches!(
+            parse(&["PUBSUB", "numpat"]),
+            Ok(Command::PubSubNumPat)
+        ));
+        assert!(matches!(
+            parse(&["PUBSUB", "SHARDNUMSUB", "x"]),
+            Ok(Command::PubSubShardNumSub(_))
+        ));
+
+        assert!(parse(&["SUBSCRIBE"]).is_err());
+        assert!(parse(&["PUBLISH", "a"]).is_err());
+        assert!(parse(&["PUBSUB", "BOGUS"]).is_err());
+    }
+
+    #[test]
+    fn test_pubsub_without_broker() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            executor.execute(&parse(&["PUBLISH", "a", "msg"]).unwrap()),
+            RespValue::Integer(0)
+        );
+        assert!(matches!(
+            executor.execute(&parse(&["SUBSCRIBE", "a"]).unwrap()),
+            RespValue::Error(_)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod blocking_command_tests {
+    use super::super::{Command, CommandExecutor, RespValue};
+    use super::support::{bulk, parse, run};
+    use crate::simulator::VirtualTime;
+
+    #[test]
+    fn test_blocking_parsing() {
+        assert!(matches!(
+            parse(&["BLPOP", "a", "b", "1.5"]),
+            Ok(Command::BLPop { ref keys, timeout_ms: 1500 }) if keys.len() == 2
+        ));
+        assert!(matches!(
+            parse(&["BRPOPLPUSH", "src", "dst", "0"]),
+            Ok(Command::BLMove { ref wherefrom, ref whereto, timeout_ms: 0, .. })
+                if wherefrom == "RIGHT" && whereto == "LEFT"
+        ));
+        assert!(matches!(
+            parse(&["bzpopmax", "z", "2"]),
+            Ok(Command::BZPopMax {
+                timeout_ms: 2000,
+                ..
+            })
+        ));
+        assert_eq!(
+            parse(&["BLPOP", "a", "-1"]).unwrap_err(),
+            "timeout is negative"
+        );
+        assert_eq!(
+            parse(&["BLPOP", "a", "soon"]).unwrap_err(),
+            "timeout is not a float or out of range"
+        );
+        assert!(parse(&["BLPOP", "a"]).is_err());
+        assert!(parse(&["BLMOVE", "a", "b", "UP", "LEFT", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_blocking_pop_serves_immediately_when_data_exists() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["RPUSH", "list", "a", "b"]);
+
+        let cmd = parse(&["BLPOP", "empty", "list", "0"]).unwrap();
+        assert_eq!(
+            executor.execute_blocking(1, &cmd),
+            Some(RespValue::Array(Some(vec![bulk("list"), bulk("a")])))
+        );
+        assert_eq!(executor.blocked_client_count(), 0);
+
+        // Without a client to park, an empty key yields the timeout reply
+        assert_eq!(
+            run(&mut executor, &["BLPOP", "empty", "0"]),
+            RespValue::Array(None)
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["BLMOVE", "empty", "x", "LEFT", "LEFT", "0"]
+            ),
+            RespValue::BulkString(None)
+        );
+    }
+
+    #[test]
+    fn test_blocked_clients_are_woken_in_fifo_order() {
+        let mut executor = CommandExecutor::new();
+        let blpop = parse(&["BLPOP", "jobs", "0"]).unwrap();
+        assert_eq!(executor.execute_blocking(1, &blpop), None);
+        assert_eq!(executor.execute_blocking(2, &blpop), None);
+        assert_eq!(executor.execute_blocking(3, &blpop), None);
+        assert_eq!(executor.blocked_client_count(), 3);
+
+        // Two elements: the two oldest waiters get one each, in push order
+        run(&mut executor, &["RPUSH", "jobs", "j1", "j2"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![
+                (1, RespValue::Array(Some(vec![bulk("jobs"), bulk("j1")]))),
+                (2, RespValue::Array(Some(vec![bulk("jobs"), bulk("j2")]))),
+            ]
+        );
+        assert_eq!(executor.blocked_client_count(), 1);
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "jobs"]),
+            RespValue::Integer(0)
+        );
+
+        run(&mut executor, &["LPUSH", "jobs", "j3"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![(3, RespValue::Array(Some(vec![bulk("jobs"), bulk("j3")])))]
+        );
+        assert_eq!(executor.blocked_client_count(), 0);
+    }
+
+    #[test]
+    fn test_blocking_timeout_in_virtual_time() {
+        let mut executor = CommandExecutor::new();
+        executor.set_time(VirtualTime::from_millis(1_000));
+        let blpop = parse(&["BLPOP", "q", "0.5"]).unwrap();
+        let bzpop = parse(&["BZPOPMIN", "z", "2"]).unwrap();
+        assert_eq!(executor.execute_blocking(1, &blpop), None);
+        assert_eq!(executor.execute_blocking(2, &bzpop), None);
+        assert_eq!(
+            executor.next_blocked_deadline(),
+            Some(VirtualTime::from_millis(1_500))
+        );
+
+        executor.set_time(VirtualTime::from_millis(1_499));
+        assert!(executor.take_unblocked().is_empty());
+
+        executor.set_time(VirtualTime::from_millis(1_500));
+        assert_eq!(executor.take_unblocked(), vec![(1, RespValue::Array(None))]);
+
+        // The TTL sweep advances time too
+        executor.evict_expired_direct(VirtualTime::from_millis(3_000));
+        assert_eq!(executor.take_unblocked(), vec![(2, RespValue::Array(None))]);
+        assert_eq!(executor.blocked_client_count(), 0);
+    }
+
+    #[test]
+    fn test_bzpop_and_blmove_wakeups() {
+        let mut executor = CommandExecutor::new();
+        let bzpopmax = parse(&["BZPOPMAX", "z", "0"]).unwrap();
+        let blmove = parse(&["BLMOVE", "src", "dst", "RIGHT", "LEFT", "0"]).unwrap();
+        let blpop_dst = parse(&["BLPOP", "dst", "0"]).unwrap();
+        assert_eq!(executor.execute_blocking(1, &bzpopmax), None);
+        assert_eq!(executor.execute_blocking(2, &blmove), None);
+        assert_eq!(executor.execute_blocking(3, &blpop_dst), None);
+
+        run(&mut executor, &["ZADD", "z", "1", "low", "5", "high"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![(
+                1,
+                RespValue::Array(Some(vec![bulk("z"), bulk("high"), bulk("5")]))
+            )]
+        );
+
+        // BLMOVE feeds dst, which in turn wakes the BLPOP waiting on it
+        run(&mut executor, &["RPUSH", "src", "item"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![
+                (2, bulk("item")),
+                (3, RespValue::Array(Some(vec![bulk("dst"), bulk("item")]))),
+            ]
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "src", "dst"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_blocking_wrongtype_and_cancel() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "s", "v"]);
+        let cmd = parse(&["BLPOP", "s", "0"]).unwrap();
+        assert!(matches!(
+            executor.execute_blocking(1, &cmd),
+            Some(RespValue::Error(ref e)) if e.starts_with("WRONGTYPE")
+        ));
+
+        // A cancelled (disconnected) waiter leaves pushed data in place
+        let cmd = parse(&["BRPOP", "q", "0"]).unwrap();
+        assert_eq!(executor.execute_blocking(2, &cmd), None);
+        assert!(executor.cancel_blocked(2));
+        assert!(!executor.cancel_blocked(2));
+        run(&mut executor, &["RPUSH", "q", "x"]);
+        assert!(executor.take_unblocked().is_empty());
+        assert_eq!(run(&mut executor, &["LLEN", "q"]), RespValue::Integer(1));
+    }
+
+    #[test]
+    fn test_transaction_push_wakes_after_exec() {
+        let mut executor = CommandExecutor::new();
+        let cmd = parse(&["BLPOP", "q", "0"]).unwrap();
+        assert_eq!(executor.execute_blocking(1, &cmd), None);
+
+        // Waiters are served once the whole transaction has run, as in Redis
+        run(&mut executor, &["MULTI"]);
+        run(&mut executor, &["RPUSH", "q", "a", "b"]);
+        run(&mut executor, &["LPOP", "q"]);
+        assert!(executor.take_unblocked().is_empty());
+        run(&mut executor, &["EXEC"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![(1, RespValue::Array(Some(vec![bulk("q"), bulk("b")])))]
+        );
+    }
+
+    /// `[[key, [[id, [field, value]]]]]`, an XREAD reply with one entry
+    fn stream_reply(key: &str, id: &str, field: &str, value: &str) -> RespValue {
+        RespValue::Array(Some(vec![RespValue::Array(Some(vec![
+            bulk(key),
+            RespValue::Array(Some(vec![RespValue::Array(Some(vec![
+                bulk(id),
+                RespValue::Array(Some(vec![bulk(field), bulk(value)])),
+            ]))])),
+        ]))]))
+    }
+
+    #[test]
+    fn test_xread_block_waits_for_new_entries() {
+        assert!(matches!(
+            parse(&["XREAD", "BLOCK", "0", "STREAMS", "s", "$"]),
+            Ok(Command::XRead {
+                block_ms: Some(0),
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse(&["XREAD", "STREAMS", "s", "$"]),
+            Ok(Command::XRead { block_ms: None, .. })
+        ));
+
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["XADD", "s", "1-0", "f", "old"]);
+
+        // `$` only waits for entries added after the call
+        let xread = parse(&["XREAD", "BLOCK", "0", "STREAMS", "s", "$"]).unwrap();
+        assert_eq!(executor.execute_blocking(1, &xread), None);
+        let xread_missing = parse(&["XREAD", "BLOCK", "0", "STREAMS", "new", "$"]).unwrap();
+        assert_eq!(executor.execute_blocking(2, &xread_missing), None);
+        run(&mut executor, &["SET", "other", "v"]);
+        assert!(executor.take_unblocked().is_empty());
+
+        run(&mut executor, &["XADD", "s", "2-0", "f", "new"]);
+        run(&mut executor, &["XADD", "new", "1-0", "f", "first"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![
+                (1, stream_reply("s", "2-0", "f", "new")),
+                (2, stream_reply("new", "1-0", "f", "first")),
+            ]
+        );
+
+        // Data already past the id is served at once
+        let xread = parse(&["XREAD", "BLOCK", "0", "STREAMS", "s", "1-0"]).unwrap();
+        assert_eq!(
+            executor.execute_blocking(3, &xread),
+            Some(stream_reply("s", "2-0", "f", "new"))
+        );
+
+        // Timeouts reply nil
+        executor.set_time(VirtualTime::from_millis(1_000));
+        let xread = parse(&["XREAD", "BLOCK", "100", "STREAMS", "s", "$"]).unwrap();
+        assert_eq!(executor.execute_blocking(4, &xread), None);
+        executor.set_time(VirtualTime::from_millis(1_100));
+        assert_eq!(executor.take_unblocked(), vec![(4, RespValue::Array(None))]);
+    }
+
+    #[test]
+    fn test_xreadgroup_block_delivers_to_waiting_consumer() {
+        let mut executor = CommandExecutor::new();
+        run(
+            &mut executor,
+            &["XGROUP", "CREATE", "s", "g", "$", "MKSTREAM"],
+        );
+        let xreadgroup = parse(&[
+            "XREADGROUP",
+            "GROUP",
+            "g",
+            "alice",
+            "BLOCK",
+            "0",
+            "STREAMS",
+            "s",
+            ">",
+        ])
+        .unwrap();
+        assert_eq!(executor.execute_blocking(1, &xreadgroup), None);
+
+        run(&mut executor, &["XADD", "s", "1-0", "f", "v"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![(1, stream_reply("s", "1-0", "f", "v"))]
+        );
+        // The delivered entry is pending for the consumer that was woken
+        assert!(matches!(
+            run(&mut executor, &["XPENDING", "s", "g"]),
+            RespValue::Array(Some(ref summary)) if summary[0] == RespValue::Integer(1)
+        ));
+
+        // History reads never block, even when empty
+        let history = parse(&[
+            "XREADGROUP",
+            "GROUP",
+            "g",
+            "bob",
+            "BLOCK",
+            "0",
+            "STREAMS",
+            "s",
+            "0",
+        ])
+        .unwrap();
+        assert!(matches!(
+            executor.execute_blocking(2, &history),
+            Some(RespValue::Array(Some(_)))
+        ));
+        assert_eq!(executor.blocked_client_count(), 0);
+    }
+}
+
+#[cfg(test)]
+mod cluster_command_tests {
+    use super::super::{ClusterSetSlotAction, Command, CommandExecutor, RespValue};
+    use super::support::parse;
+
+    #[test]
+    fn test_cluster_parsing() {
+        assert!(matches!(
+            parse(&["CLUSTER", "slots"]),
+            Ok(Command::ClusterSlots)
+        ));
+        assert!(matches!(
+            parse(&["CLUSTER", "SHARDS"]),
+            Ok(Command::ClusterShards)
+        ));
+        assert!(matches!(
+            parse(&["CLUSTER", "NODES"]),
+            Ok(Command::ClusterNodes)
+        ));
+        assert!(matches!(
+            parse(&["CLUSTER", "INFO"]),
+            Ok(Command::ClusterInfo)
+        ));
+        assert!(matches!(
+            parse(&["CLUSTER", "MYID"]),
+            Ok(Command::ClusterMyId)
+        ));
+        assert!(matches!(
+            parse(&["CLUSTER", "KEYSLOT", "foo"]),
+            Ok(Command::ClusterKeySlot(ref k)) if k == "foo"
+        ));
+        assert!(matches!(
+            parse(&["CLUSTER", "SETSLOT", "42", "migrating", "abc"]),
+            Ok(Command::ClusterSetSlot {
+                slot: 42,
+                action: ClusterSetSlotAction::Migrating(ref id),
+            }) if id == "abc"
+        ));
+        assert!(matches!(
+            parse(&["CLUSTER", "SETSLOT", "42", "STABLE"]),
+            Ok(Command::ClusterSetSlot {
+                action: ClusterSetSlotAction::Stable,
+                ..
+            })
+        ));
+        assert!(matches!(parse(&["ASKING"]), Ok(Command::Asking)));
+        assert!(matches!(parse(&["READONLY"]), Ok(Command::ReadOnly)));
+
+        assert!(parse(&["CLUSTER"]).is_err());
+        assert!(parse(&["CLUSTER", "BOGUS"]).is_err());
+        assert!(parse(&["CLUSTER", "KEYSLOT"]).is_err());
+        assert!(parse(&["CLUSTER", "SETSLOT", "16384", "STABLE"]).is_err());
+        assert!(parse(&["CLUSTER", "SETSLOT", "1", "NODE"]).is_err());
+    }
+
+    #[test]
+    fn test_cluster_commands_on_standalone_executor() {
+        let mut executor = CommandExecutor::new();
+        for cmd in [Command::ClusterSlots, Command::ClusterKeySlot("k".into())] {
+            assert_eq!(
+                executor.execute(&cmd),
+                RespValue::Error("ERR This instance has cluster support disabled".to_string())
+            );
+        }
+        // KEYSLOT names a key but never accesses it, so it is never redirected
+        assert!(Command::ClusterKeySlot("k".into()).get_keys().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod eviction_command_tests {
+    use super::super::{CommandExecutor, EvictionPolicy, MemoryConfig, RespValue};
+    use super::support::run;
+    use crate::simulator::VirtualTime;
+
+    fn exists(executor: &mut CommandExecutor, key: &str) -> bool {
+        run(executor, &["EXISTS", key]) == RespValue::Integer(1)
+    }
+
+    /// Cap the executor just below its current usage, forcing one eviction round
+    fn limit_below_usage(executor: &mut CommandExecutor, policy: EvictionPolicy, by: usize) {
+        let used = executor.memory_stats().used_memory;
+        executor.set_memory_config(MemoryConfig {
+            maxmemory: used - by,
+            policy,
+            samples: 16,
+        });
+    }
+
+    fn info(executor: &mut CommandExecutor) -> String {
+        match run(executor, &["INFO"]) {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected INFO reply {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_memory_accounting_follows_writes() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(executor.memory_stats().used_memory, 0);
+
+        run(&mut executor, &["SET", "small", "x"]);
+        let small = executor.memory_stats().used_memory;
+        assert!(small > 0);
+
+        run(&mut executor, &["SET", "big", &"x".repeat(10_000)]);
+        assert!(executor.memory_stats().used_memory >= small + 10_000);
+        run(&mut executor, &["RPUSH", "list", "a", "b", "c"]);
+        run(&mut executor, &["HSET", "hash", "f", "v"]);
+
+        run(&mut executor, &["DEL", "big"]);
+        run(&mut executor, &["EXPIRE", "list", "1"]);
+        executor.set_time(VirtualTime::from_millis(2000));
+        run(&mut executor, &["DEL", "hash"]);
+        assert_eq!(executor.memory_stats().used_memory, small);
+
+        run(&mut executor, &["FLUSHDB"]);
+        assert_eq!(executor.memory_stats().used_memory, 0);
+    }
+
+    #[test]
+    fn test_noeviction_rejects_writes_with_oom() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "a", "1"]);
+        run(&mut executor, &["SET", "b", "2"]);
+        limit_below_usage(&mut executor, EvictionPolicy::NoEviction, 1);
+
+        assert_eq!(
+            run(&mut executor, &["SET", "c", "3"]),
+            RespValue::Error("OOM command not allowed when used memory > 'maxmemory'.".to_string())
+        );
+        assert!(matches!(
+            executor.set_direct(b"c", b"3"),
+            RespValue::Error(ref e) if e.starts_with("OOM")
+        ));
+        assert!(!exists(&mut executor, "c"));
+
+        // Reads and commands that free memory still run
+        assert_eq!(
+            run(&mut executor, &["GET", "a"]),
+            RespValue::BulkString(Some(b"1".to_vec()))
+        );
+        assert_eq!(run(&mut executor, &["DEL", "a"]), RespValue::Integer(1));
+        assert_eq!(
+            run(&mut executor, &["SET", "c", "3"]),
+            RespValue::SimpleString("OK".to_string())
+        );
+
+        let info = info(&mut executor);
+        assert!(info.contains("maxmemory_policy:noeviction\r\n"));
+        assert!(info.contains("evicted_keys:0\r\n"));
+        assert!(info.contains("oom_rejected_commands:2\r\n"));
+    }
+
+    #[test]
+    fn test_allkeys_lru_evicts_least_recently_used() {
+        let mut executor = CommandExecutor::new();
+        for i in 0..10 {
+            executor.set_time(VirtualTime::from_millis(i * 1000));
+            run(&mut executor, &["SET", &format!("key{}", i), "value"]);
+        }
+        // key0 is the oldest write but the most recent read
+        executor.set_time(VirtualTime::from_millis(60_000));
+        run(&mut executor, &["GET", "key0"]);
+
+        limit_below_usage(&mut executor, EvictionPolicy::AllKeysLru, 1);
+        assert!(exists(&mut executor, "key0"));
+        assert!(!exists(&mut executor, "key1"));
+        assert_eq!(executor.memory_stats().evicted_keys, 1);
+
+        // Writes keep succeeding by evicting more keys
+        for i in 10..20 {
+            assert_eq!(
+                run(&mut executor, &["SET", &format!("key{}", i), "value"]),
+                RespValue::SimpleString("OK".to_string())
+            );
+        }
+        let stats = executor.memory_stats();
+        assert!(stats.evicted_keys >= 10);
+        assert!(info(&mut executor).contains(&format!("evicted_keys:{}\r\n", stats.evicted_keys)));
+    }
+
+    #[test]
+    fn test_allkeys_lfu_keeps_frequently_used_keys() {
+        let mut executor = CommandExecutor::new();
+        executor.set_memory_config(MemoryConfig {
+            policy: EvictionPolicy::AllKeysLfu,
+            ..MemoryConfig::default()
+        });
+        for i in 0..10 {
+            run(&mut executor, &["SET", &format!("key{}", i), "value"]);
+        }
+        for _ in 0..1000 {
+            run(&mut executor, &["GET", "key7"]);
+        }
+
+        // Room for about two keys: the hot one must be among the survivors
+        let per_key = executor.memory_stats().used_memory / 10;
+        executor.set_memory_config(MemoryConfig {
+            maxmemory: per_key * 2,
+            policy: EvictionPolicy::AllKeysLfu,
+            samples: 16,
+        });
+        assert!(exists(&mut executor, "key7"));
+        assert_eq!(executor.memory_stats().evicted_keys, 8);
+    }
+
+    #[test]
+    fn test_volatile_policies_only_evict_keys_with_ttl() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "persistent", "v"]);
+        run(&mut executor, &["SET", "later", "v", "EX", "1000"]);
+        run(&mut executor, &["SET", "sooner", "v", "EX", "10"]);
+
+        limit_below_usage(&mut executor, EvictionPolicy::VolatileTtl, 1);
+        assert!(!exists(&mut executor, "sooner"));
+        assert!(exists(&mut executor, "later"));
+
+        // Once no key has a TTL, volatile-* behaves like noeviction
+        executor.set_memory_config(MemoryConfig {
+            maxmemory: 1,
+            policy: EvictionPolicy::VolatileLru,
+            samples: 5,
+        });
+        assert!(!exists(&mut executor, "later"));
+        assert!(exists(&mut executor, "persistent"));
+        assert!(matches!(
+            run(&mut executor, &["SET", "new", "v"]),
+            RespValue::Error(ref e) if e.starts_with("OOM")
+        ));
+        assert_eq!(executor.memory_stats().evicted_keys, 2);
+    }
+
+    #[test]
+    fn test_eviction_is_seen_by_watch() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "watched", "v"]);
+        let before = executor.key_version(b"watched");
+        executor.set_memory_config(MemoryConfig {
+            maxmemory: 1,
+            policy: EvictionPolicy::AllKeysRandom,
+            samples: 5,
+        });
+        assert!(!exists(&mut executor, "watched"));
+        assert_ne!(executor.key_version(b"watched"), before);
+    }
+}
+
+#[cfg(test)]
+mod active_expiry_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::run;
+    use crate::simulator::VirtualTime;
+
+    fn set_with_ttl(executor: &mut CommandExecutor, count: usize, seconds: &str) {
+        for i in 0..count {
+            run(
+                executor,
+                &["SET", &format!("key:{}", i), "v", "EX", seconds],
+            );
+        }
+    }
+
+    #[test]
+    fn test_fast_cycle_is_bounded_and_lazy_expiry_hides_the_rest() {
+        let mut executor = CommandExecutor::new();
+        set_with_ttl(&mut executor, 200, "1");
+        run(&mut executor, &["SET", "persistent", "v"]);
+
+        executor.set_time(VirtualTime::from_millis(1_000));
+        // The fast cycle reclaimed one bounded batch of 64 keys
+        assert_eq!(
+            run(&mut executor, &["DBSIZE"]),
+            RespValue::Integer(201 - 64)
+        );
+        // Keys not reclaimed yet are already invisible
+        assert_eq!(
+            run(&mut executor, &["GET", "key:199"]),
+            RespValue::BulkString(None)
+        );
+        assert_eq!(
+            run(&mut executor, &["KEYS", "*"]),
+            RespValue::Array(Some(vec![RespValue::BulkString(Some(
+                b"persistent".to_vec()
+            ))]))
+        );
+
+        // Later commands drain the backlog
+        for _ in 0..4 {
+            executor.set_time(VirtualTime::from_millis(1_000));
+        }
+        assert_eq!(run(&mut executor, &["DBSIZE"]), RespValue::Integer(1));
+    }
+
+    #[test]
+    fn test_active_cycle_reclaims_in_deadline_order() {
+        let mut executor = CommandExecutor::new();
+        set_with_ttl(&mut executor, 50, "10");
+        run(&mut executor, &["SET", "soon", "v", "EX", "1"]);
+        run(&mut executor, &["SET", "later", "v", "EX", "100"]);
+
+        let evicted = executor.active_expire_cycle(VirtualTime::from_millis(1_000), usize::MAX);
+        assert_eq!(evicted, 1);
+        let evicted = executor.active_expire_cycle(VirtualTime::from_millis(10_000), usize::MAX);
+        assert_eq!(evicted, 50);
+        assert_eq!(run(&mut executor, &["DBSIZE"]), RespValue::Integer(1));
+        assert_eq!(
+            run(&mut executor, &["TTL", "later"]),
+            RespValue::Integer(90)
+        );
+    }
+
+    #[test]
+    fn test_active_cycle_stops_at_key_limit() {
+        let mut executor = CommandExecutor::new();
+        set_with_ttl(&mut executor, 100, "1");
+
+        // Each slice reclaims at most the limit; the caller repeats it
+        let now = VirtualTime::from_millis(1_000);
+        assert_eq!(executor.active_expire_cycle(now, 16), 16);
+        let mut total = 16;
+        while total < 100 {
+            total += executor.active_expire_cycle(now, 16);
+        }
+        assert_eq!(total, 100);
+        assert_eq!(executor.active_expire_cycle(now, 16), 0);
+
+        let info = match run(&mut executor, &["INFO"]) {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected INFO reply {:?}", other),
+        };
+        assert!(info.contains("expired_keys:100\r\n"));
+        assert!(info.contains("expired_time_cap_reached_count:0\r\n"));
+    }
+}
+
+#[cfg(test)]
+mod bitmap_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, parse, run};
+
+    fn ints(values: &[i64]) -> RespValue {
+        RespValue::Array(Some(
+            values.iter().map(|&v| RespValue::Integer(v)).collect(),
+        ))
+    }
+
+    #[test]
+    fn test_bitmap_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["SETBIT", "k", "7", "1"],
+            vec!["GETBIT", "k", "7"],
+            vec!["BITCOUNT", "k", "0", "-1", "BIT"],
+            vec!["BITPOS", "k", "0", "2"],
+            vec!["BITOP", "XOR", "dest", "a", "b"],
+            vec![
+                "BITFIELD", "k", "OVERFLOW", "SAT", "INCRBY", "u8", "#1", "10",
+            ],
+            vec!["BITFIELD_RO", "k", "GET", "i64", "0"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+        }
+
+        for (args, error) in [
+            (&["SETBIT", "k", "7", "2"][..], "bit is not an integer or out of range"),
+            (&["SETBIT", "k", "4294967296", "1"][..], "bit offset is not an integer or out of range"),
+            (&["BITCOUNT", "k", "0"][..], "syntax error"),
+            (&["BITPOS", "k", "2"][..], "The bit argument must be 1 or 0."),
+            (&["BITOP", "NOT", "d", "a", "b"][..], "BITOP NOT must be called with a single source key."),
+            (
+                &["BITFIELD", "k", "GET", "u64", "0"][..],
+                "Invalid bitfield type. Use something like i16 u8. Note that u64 is not supported but i64 is.",
+            ),
+            (&["BITFIELD", "k", "OVERFLOW", "BOUNCE"][..], "Invalid OVERFLOW type specified"),
+            (&["BITFIELD_RO", "k", "SET", "u8", "0", "1"][..], "BITFIELD_RO only supports the GET subcommand"),
+        ] {
+            assert_eq!(parse(args).unwrap_err(), error, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_setbit_getbit_bitcount_bitpos() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            run(&mut executor, &["SETBIT", "k", "7", "1"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["SETBIT", "k", "7", "1"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["SETBIT", "k", "100", "1"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &["STRLEN", "k"]), RespValue::Integer(13));
+        assert_eq!(
+            run(&mut executor, &["GETBIT", "k", "100"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["GETBIT", "k", "9999"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["BITCOUNT", "k"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["BITCOUNT", "k", "1", "-1"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["BITPOS", "k", "1"]),
+            RespValue::Integer(7)
+        );
+        assert_eq!(
+            run(&mut executor, &["BITPOS", "k", "1", "8", "-1", "BIT"]),
+            RespValue::Integer(100)
+        );
+        assert_eq!(
+            run(&mut executor, &["BITPOS", "missing", "0"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["BITPOS", "missing", "1"]),
+            RespValue::Integer(-1)
+        );
+
+        run(&mut executor, &["LPUSH", "list", "x"]);
+        assert!(matches!(
+            run(&mut executor, &["SETBIT", "list", "0", "1"]),
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+    }
+
+    #[test]
+    fn test_bitop() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "a", "foobar"]);
+        run(&mut executor, &["SET", "b", "abcdef"]);
+        assert_eq!(
+            run(&mut executor, &["BITOP", "AND", "dest", "a", "b"]),
+            RespValue::Integer(6)
+        );
+        assert_eq!(
+            run(&mut executor, &["GET", "dest"]),
+            RespValue::BulkString(Some(b"`bc`ab".to_vec()))
+        );
+        // Missing sources are zero-filled; NOT of nothing deletes the destination
+        assert_eq!(
+            run(&mut executor, &["BITOP", "OR", "dest", "a", "missing"]),
+            RespValue::Integer(6)
+        );
+        assert_eq!(
+            run(&mut executor, &["BITOP", "NOT", "dest", "missing"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "dest"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_bitfield_overflow_modes() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            run(
+                &mut executor,
+                &["BITFIELD", "k", "INCRBY", "i5", "100", "1", "GET", "u4", "0"]
+            ),
+            ints(&[1, 0])
+        );
+        let incr = [
+            "BITFIELD", "c", "INCRBY", "u2", "100", "1", "OVERFLOW", "SAT", "INCRBY", "u2", "102",
+            "1",
+        ];
+        assert_eq!(run(&mut executor, &incr), ints(&[1, 1]));
+        assert_eq!(run(&mut executor, &incr), ints(&[2, 2]));
+        assert_eq!(run(&mut executor, &incr), ints(&[3, 3]));
+        assert_eq!(run(&mut executor, &incr), ints(&[0, 3]));
+        assert_eq!(
+            run(
+                &mut executor,
+                &["BITFIELD", "c", "OVERFLOW", "FAIL", "SET", "u2", "102", "4"]
+            ),
+            RespValue::Array(Some(vec![RespValue::BulkString(None)]))
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["BITFIELD", "s", "SET", "i8", "#1", "-128", "GET", "i8", "8", "GET", "u8", "8"]
+            ),
+            ints(&[0, -128, 128])
+        );
+
+        // Reads never create the key
+        assert_eq!(
+            run(&mut executor, &["BITFIELD_RO", "missing", "GET", "u8", "0"]),
+            ints(&[0])
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "missing"]),
+            RespValue::Integer(0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod hyperloglog_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, parse, run};
+
+    fn get(executor: &mut CommandExecutor, key: &str) -> Vec<u8> {
+        match run(executor, &["GET", key]) {
+            RespValue::BulkString(Some(bytes)) => bytes,
+            other => panic!("GET {} returned {:?}", key, other),
+        }
+    }
+
+    #[test]
+    fn test_hyperloglog_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["PFADD", "hll", "a", "b"],
+            vec!["PFADD", "hll"],
+            vec!["PFCOUNT", "hll", "other"],
+            vec!["PFMERGE", "dest", "a", "b"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+        }
+
+        for args in [&["PFADD"][..], &["PFCOUNT"][..], &["PFMERGE"][..]] {
+            assert!(parse(args).is_err(), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_pfadd_pfcount() {
+        let mut executor = CommandExecutor::new();
+        // PFADD without elements still creates the key
+        assert_eq!(run(&mut executor, &["PFADD", "hll"]), RespValue::Integer(1));
+        assert_eq!(run(&mut executor, &["PFADD", "hll"]), RespValue::Integer(0));
+        assert_eq!(
+            get(&mut executor, "hll"),
+            b"HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x7f\xff".to_vec()
+        );
+        assert_eq!(
+            run(&mut executor, &["TYPE", "hll"]),
+            RespValue::SimpleString("string".to_string())
+        );
+
+        assert_eq!(
+            run(&mut executor, &["PFADD", "hll", "a", "b", "c"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["PFADD", "hll", "a", "b"]),
+            RespValue::Integer(0)
+        );
+        // The cache is stale after an update and refreshed by PFCOUNT
+        assert_eq!(get(&mut executor, "hll")[15] & 0x80, 0x80);
+        assert_eq!(
+            run(&mut executor, &["PFCOUNT", "hll"]),
+            RespValue::Integer(3)
+        );
+        assert_eq!(get(&mut executor, "hll")[8..16], [3, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            run(&mut executor, &["PFCOUNT", "missing"]),
+            RespValue::Integer(0)
+        );
+
+        let elements: Vec<String> = (0..20_000).map(|i| format!("visitor:{}", i)).collect();
+        let mut args = vec!["PFADD", "big"];
+        args.extend(elements.iter().map(String::as_str));
+        run(&mut executor, &args);
+        // Promoted to the dense encoding
+        assert_eq!(get(&mut executor, "big").len(), 16 + 12288);
+        match run(&mut executor, &["PFCOUNT", "big"]) {
+            RespValue::Integer(n) => assert!((19_600..=20_400).contains(&n), "estimate {}", n),
+            other => panic!("unexpected reply {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pfmerge_and_multi_key_pfcount() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["PFADD", "a", "x", "y", "z"]);
+        run(&mut executor, &["PFADD", "b", "z", "w"]);
+        assert_eq!(
+            run(&mut executor, &["PFCOUNT", "a", "b", "missing"]),
+            RespValue::Integer(4)
+        );
+        // Counting several keys leaves them untouched
+        assert_eq!(get(&mut executor, "a")[15] & 0x80, 0x80);
+
+        assert_eq!(
+            run(&mut executor, &["PFMERGE", "dest", "a", "b"]),
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, &["PFCOUNT", "dest"]),
+            RespValue::Integer(4)
+        );
+        // The destination takes part in the union
+        run(&mut executor, &["PFMERGE", "dest", "missing"]);
+        assert_eq!(
+            run(&mut executor, &["PFCOUNT", "dest"]),
+            RespValue::Integer(4)
+        );
+        assert_eq!(
+            run(&mut executor, &["PFMERGE", "empty"]),
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, &["PFCOUNT", "empty"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_hyperloglog_errors() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "str", "not an hll at all"]);
+        run(&mut executor, &["LPUSH", "list", "x"]);
+        for args in [
+            &["PFADD", "str", "a"][..],
+            &["PFCOUNT", "str"][..],
+            &["PFCOUNT", "missing", "str"][..],
+            &["PFMERGE", "dest", "str"][..],
+        ] {
+            assert_eq!(
+                run(&mut executor, args),
+                RespValue::Error(
+                    "WRONGTYPE Key is not a valid HyperLogLog string value.".to_string()
+                ),
+                "{:?}",
+                args
+            );
+        }
+        assert!(matches!(
+            run(&mut executor, &["PFADD", "list", "a"]),
+            RespValue::Error(e) if e == "WRONGTYPE Operation against a key holding the wrong kind of value"
+        ));
+
+        // Valid header, but the sparse registers stop short
+        run(
+            &mut executor,
+            &["SET", "bad", "HYLL\x01\0\0\0\0\0\0\0\0\0\0\0\x3f"],
+        );
+        assert_eq!(
+            run(&mut executor, &["PFADD", "bad", "a"]),
+            RespValue::Error("INVALIDOBJ Corrupted HLL object detected".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, &["PFCOUNT", "bad", "missing"]),
+            RespValue::Error("INVALIDOBJ Corrupted HLL object detected".to_string())
+        );
+    }
+}
+
+#[cfg(test)]
+mod geo_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, bulk, parse, run};
+
+    fn sicily() -> CommandExecutor {
+        let mut executor = CommandExecutor::new();
+        let added = run(
+            &mut executor,
+            &[
+                "GEOADD",
+                "Sicily",
+                "13.361389",
+                "38.115556",
+                "Palermo",
+                "15.087269",
+                "37.502669",
+                "Catania",
+                "12.758489",
+                "38.788135",
+                "edge1",
+                "17.241510",
+                "38.788135",
+                "edge2",
+            ],
+        );
+        assert_eq!(added, RespValue::Integer(4));
+        executor
+    }
+
+    #[test]
+    fn test_geo_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["GEOADD", "k", "NX", "CH", "13.36", "38.11", "a"],
+            vec!["GEODIST", "k", "a", "b", "km"],
+            vec!["GEOPOS", "k", "a", "b"],
+            vec!["GEOHASH", "k"],
+            vec![
+                "GEORADIUS",
+                "k",
+                "15",
+                "37",
+                "200",
+                "km",
+                "WITHDIST",
+                "COUNT",
+                "1",
+            ],
+            vec!["GEORADIUSBYMEMBER_RO", "k", "a", "10", "mi", "DESC"],
+            vec![
+                "GEOSEARCH",
+                "k",
+                "FROMMEMBER",
+                "a",
+                "BYBOX",
+                "1",
+                "2",
+                "m",
+                "ANY",
+                "COUNT",
+                "2",
+            ],
+            vec![
+                "GEOSEARCHSTORE",
+                "d",
+                "k",
+                "FROMLONLAT",
+                "1",
+                "2",
+                "BYRADIUS",
+                "5",
+                "ft",
+                "STOREDIST",
+            ],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> = vec![
+            (vec!["GEOADD", "k", "1", "2"], "wrong number of arguments for 'geoadd' command"),
+            (vec!["GEOADD", "k", "1", "2", "a", "3"], "syntax error"),
+            (vec!["GEOADD", "k", "NX", "XX", "1", "2", "a"], "syntax error"),
+            (
+                vec!["GEOADD", "k", "200", "100", "a"],
+                "invalid longitude,latitude pair 200.000000,100.000000",
+            ),
+            (
+                vec!["GEODIST", "k", "a", "b", "yd"],
+                "unsupported unit provided. please use M, KM, FT, MI",
+            ),
+            (vec!["GEORADIUS", "k", "1", "2", "-1", "m"], "radius cannot be negative"),
+            (vec!["GEORADIUS_RO", "k", "1", "2", "1", "m", "STORE", "d"], "syntax error"),
+            (
+                vec!["GEORADIUS", "k", "1", "2", "1", "m", "STORE", "d", "WITHDIST"],
+                "STORE option in GEORADIUS is not compatible with WITHDIST, WITHHASH and WITHCOORD options",
+            ),
+            (
+                vec!["GEOSEARCH", "k", "BYRADIUS", "1", "m", "ASC", "WITHDIST"],
+                "exactly one of FROMMEMBER or FROMLONLAT can be specified for geosearch",
+            ),
+            (
+                vec!["GEOSEARCH", "k", "FROMMEMBER", "a", "FROMLONLAT", "1", "2", "BYRADIUS", "1", "m"],
+                "syntax error",
+            ),
+            (
+                vec!["GEOSEARCH", "k", "FROMMEMBER", "a", "BYRADIUS", "1", "m", "ANY"],
+                "the ANY argument requires COUNT argument",
+            ),
+            (
+                vec!["GEOSEARCH", "k", "FROMMEMBER", "a", "BYRADIUS", "1", "m", "COUNT", "0"],
+                "COUNT must be > 0",
+            ),
+        ];
+        for (args, message) in errors {
+            assert_eq!(parse(&args).unwrap_err(), message, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_geoadd_geopos_geodist_geohash() {
+        let mut executor = sicily();
+        assert_eq!(
+            run(&mut executor, &["ZSCORE", "Sicily", "Palermo"]),
+            bulk("3479099956230698")
+        );
+        assert_eq!(
+            run(&mut executor, &["GEOPOS", "Sicily", "Palermo", "missing"]),
+            RespValue::Array(Some(vec![
+                RespValue::Array(Some(vec![
+                    bulk("13.36138933897018433"),
+                    bulk("38.11555639549629859")
+                ])),
+                RespValue::Array(None),
+            ]))
+        );
+        assert_eq!(
+            run(&mut executor, &["GEODIST", "Sicily", "Palermo", "Catania"]),
+            bulk("166274.1516")
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["GEODIST", "Sicily", "Palermo", "Catania", "km"]
+            ),
+            bulk("166.2742")
+        );
+        assert_eq!(
+            run(&mut executor, &["GEODIST", "Sicily", "Palermo", "missing"]),
+            RespValue::BulkString(None)
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["GEOHASH", "Sicily", "Palermo", "Catania", "missing"]
+            ),
+            RespValue::Array(Some(vec![
+                bulk("sqc8b49rny0"),
+                bulk("sqdtr74hyu0"),
+                RespValue::BulkString(None)
+            ]))
+        );
+
+        // NX leaves existing members alone; CH counts updates too
+        assert_eq!(
+            run(
+                &mut executor,
+                &["GEOADD", "Sicily", "NX", "13", "38", "Palermo"]
+            ),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["GEOADD", "Sicily", "CH", "13", "38", "Palermo"]
+            ),
+            RespValue::Integer(1)
+        );
+
+        run(&mut executor, &["SET", "str", "x"]);
+        assert!(matches!(
+            run(&mut executor, &["GEOPOS", "str", "a"]),
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+    }
+
+    #[test]
+    fn test_geosearch_radius_and_box() {
+        let mut executor = sicily();
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEOSEARCH",
+                    "Sicily",
+                    "FROMLONLAT",
+                    "15",
+                    "37",
+                    "BYRADIUS",
+                    "200",
+                    "km",
+                    "ASC"
+                ]
+            ),
+            RespValue::Array(Some(vec![bulk("Catania"), bulk("Palermo")]))
+        );
+
+        let entry = |name: &str, dist: &str, lon: &str, lat: &str| {
+            RespValue::Array(Some(vec![
+                bulk(name),
+                bulk(dist),
+                RespValue::Array(Some(vec![bulk(lon), bulk(lat)])),
+            ]))
+        };
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEOSEARCH",
+                    "Sicily",
+                    "FROMLONLAT",
+                    "15",
+                    "37",
+                    "BYBOX",
+                    "400",
+                    "400",
+                    "km",
+                    "ASC",
+                    "WITHCOORD",
+                    "WITHDIST"
+                ]
+            ),
+            RespValue::Array(Some(vec![
+                entry(
+                    "Catania",
+                    "56.4413",
+                    "15.08726745843887329",
+                    "37.50266842333162032"
+                ),
+                entry(
+                    "Palermo",
+                    "190.4424",
+                    "13.36138933897018433",
+                    "38.11555639549629859"
+                ),
+                entry(
+                    "edge2",
+                    "279.7403",
+                    "17.24151045083999634",
+                    "38.78813451624225195"
+                ),
+                entry(
+                    "edge1",
+                    "279.7405",
+                    "12.7584877610206604",
+                    "38.78813451624225195"
+                ),
+            ]))
+        );
+
+        // COUNT implies ASC and keeps the closest
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEOSEARCH",
+                    "Sicily",
+                    "FROMMEMBER",
+                    "Palermo",
+                    "BYRADIUS",
+                    "500",
+                    "km",
+                    "COUNT",
+                    "2"
+                ]
+            ),
+            RespValue::Array(Some(vec![bulk("Palermo"), bulk("edge1")]))
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEOSEARCH",
+                    "Sicily",
+                    "FROMMEMBER",
+                    "nobody",
+                    "BYRADIUS",
+                    "1",
+                    "km"
+                ]
+            ),
+            RespValue::Error("ERR could not decode requested zset member".to_string())
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEOSEARCH",
+                    "missing",
+                    "FROMMEMBER",
+                    "nobody",
+                    "BYRADIUS",
+                    "1",
+                    "km"
+                ]
+            ),
+            RespValue::Array(Some(vec![]))
+        );
+    }
+
+    #[test]
+    fn test_georadius_and_store() {
+        let mut executor = sicily();
+        assert_eq!(
+            run(
+                &mut executor,
+                &["GEORADIUS", "Sicily", "15", "37", "200", "km", "WITHHASH"]
+            ),
+            RespValue::Array(Some(vec![
+                RespValue::Array(Some(vec![
+                    bulk("Palermo"),
+                    RespValue::Integer(3479099956230698)
+                ])),
+                RespValue::Array(Some(vec![
+                    bulk("Catania"),
+                    RespValue::Integer(3479447370796909)
+                ])),
+            ]))
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEORADIUSBYMEMBER_RO",
+                    "Sicily",
+                    "Palermo",
+                    "200",
+                    "km",
+                    "DESC"
+                ]
+            ),
+            RespValue::Array(Some(vec![bulk("Catania"), bulk("edge1"), bulk("Palermo")]))
+        );
+
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEORADIUS",
+                    "Sicily",
+                    "15",
+                    "37",
+                    "200",
+                    "km",
+                    "STOREDIST",
+                    "dists"
+                ]
+            ),
+            RespValue::Integer(2)
+        );
+        let RespValue::BulkString(Some(dist)) = run(&mut executor, &["ZSCORE", "dists", "Catania"])
+        else {
+            panic!("STOREDIST did not store Catania");
+        };
+        let dist: f64 = String::from_utf8(dist).unwrap().parse().unwrap();
+        assert!((dist - 56.4413).abs() < 1e-4, "{}", dist);
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEOSEARCHSTORE",
+                    "copy",
+                    "Sicily",
+                    "FROMLONLAT",
+                    "15",
+                    "37",
+                    "BYRADIUS",
+                    "200",
+                    "km"
+                ]
+            ),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZSCORE", "copy", "Palermo"]),
+            bulk("3479099956230698")
+        );
+
+        // An empty result deletes the destination
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "GEOSEARCHSTORE",
+                    "copy",
+                    "Sicily",
+                    "FROMLONLAT",
+                    "0",
+                    "0",
+                    "BYRADIUS",
+                    "1",
+                    "m"
+                ]
+            ),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "copy"]),
+            RespValue::Integer(0)
+        );
+    }
+}
+
+#[cfg(test)]
+mod zset_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, bulk, parse, run};
+
+    fn array(items: &[&str]) -> RespValue {
+        RespValue::Array(Some(items.iter().map(|s| bulk(s)).collect()))
+    }
+
+    /// a:1 b:2 c:3 d:4 e:5
+    fn scores() -> CommandExecutor {
+        let mut executor = CommandExecutor::new();
+        let args = [
+            "ZADD", "z", "1", "a", "2", "b", "3", "c", "4", "d", "5", "e",
+        ];
+        assert_eq!(run(&mut executor, &args), RespValue::Integer(5));
+        executor
+    }
+
+    /// a..g, all scored 0, for the lex commands
+    fn letters() -> CommandExecutor {
+        let mut executor = CommandExecutor::new();
+        let mut args = vec!["ZADD", "lex"];
+        for member in ["a", "b", "c", "d", "e", "f", "g"] {
+            args.extend(["0", member]);
+        }
+        assert_eq!(run(&mut executor, &args), RespValue::Integer(7));
+        executor
+    }
+
+    #[test]
+    fn test_zset_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["ZRANGE", "k", "0", "-1", "WITHSCORES"],
+            vec![
+                "ZRANGE", "k", "(5", "1", "BYSCORE", "REV", "LIMIT", "1", "2",
+            ],
+            vec!["ZRANGE", "k", "[a", "+", "BYLEX"],
+            vec!["ZRANGESTORE", "d", "k", "0", "1"],
+            vec!["ZREVRANGEBYSCORE", "k", "+inf", "-inf", "WITHSCORES"],
+            vec!["ZRANGEBYLEX", "k", "-", "+", "LIMIT", "0", "1"],
+            vec!["ZREVRANGEBYLEX", "k", "+", "-"],
+            vec!["ZINCRBY", "k", "1.5", "m"],
+            vec!["ZMSCORE", "k", "a", "b"],
+            vec!["ZREVRANK", "k", "a"],
+            vec!["ZLEXCOUNT", "k", "-", "+"],
+            vec!["ZREMRANGEBYRANK", "k", "0", "-2"],
+            vec!["ZREMRANGEBYSCORE", "k", "-inf", "(2"],
+            vec!["ZREMRANGEBYLEX", "k", "[a", "(c"],
+            vec!["ZPOPMIN", "k"],
+            vec!["ZPOPMAX", "k", "3"],
+            vec!["ZRANDMEMBER", "k", "-5", "WITHSCORES"],
+            vec![
+                "ZUNION",
+                "2",
+                "a",
+                "b",
+                "WEIGHTS",
+                "1",
+                "2",
+                "AGGREGATE",
+                "MAX",
+            ],
+            vec!["ZINTERSTORE", "d", "2", "a", "b", "AGGREGATE", "min"],
+            vec!["ZDIFF", "2", "a", "b", "WITHSCORES"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> =
+            vec![
+            (vec!["ZRANGE", "k", "0"], "wrong number of arguments for 'zrange' command"),
+            (vec!["ZRANGE", "k", "a", "1"], "value is not an integer or out of range"),
+            (vec!["ZRANGE", "k", "0", "1", "BYSCORE", "BYLEX"], "syntax error"),
+            (
+                vec!["ZRANGE", "k", "0", "1", "LIMIT", "0", "1"],
+                "syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX",
+            ),
+            (
+                vec!["ZRANGE", "k", "-", "+", "BYLEX", "WITHSCORES"],
+                "syntax error, WITHSCORES not supported in combination with BYLEX",
+            ),
+            (vec!["ZRANGESTORE", "d", "k", "0", "1", "WITHSCORES"], "syntax error"),
+            (vec!["ZREVRANGEBYSCORE", "k", "1", "0", "REV"], "syntax error"),
+            (vec!["ZINCRBY", "k", "x", "m"], "value is not a valid float"),
+            (vec!["ZINCRBY", "k", "nan", "m"], "value is not a valid float"),
+            (vec!["ZMSCORE", "k"], "wrong number of arguments for 'zmscore' command"),
+            (vec!["ZPOPMIN", "k", "-1"], "value is out of range, must be positive"),
+            (vec!["ZPOPMAX", "k", "1", "2"], "syntax error"),
+            (vec!["ZRANDMEMBER", "k", "1", "SCORES"], "syntax error"),
+            (
+                vec!["ZUNION", "0", "a"],
+                "at least 1 input key is needed for 'zunion' command",
+            ),
+            (vec!["ZUNION", "3", "a", "b"], "syntax error"),
+            (vec!["ZINTER", "2", "a", "b", "WEIGHTS", "1"], "syntax error"),
+            (vec!["ZUNION", "1", "a", "WEIGHTS", "x"], "weight value is not a float"),
+            (vec!["ZUNION", "1", "a", "AGGREGATE", "AVG"], "syntax error"),
+            (vec!["ZDIFF", "1", "a", "WEIGHTS", "1"], "syntax error"),
+            (vec!["ZUNIONSTORE", "d", "1", "a", "WITHSCORES"], "syntax error"),
+        ];
+        for (args, message) in errors {
+            assert_eq!(parse(&args).unwrap_err(), message, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_zrange_by_rank_score_and_lex() {
+        let mut executor = scores();
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZRANGE", "z", "0", "1", "REV", "WITHSCORES"]
+            ),
+            array(&["e", "5", "d", "4"])
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANGE", "z", "(1", "4", "BYSCORE"]),
+            array(&["b", "c", "d"])
+        );
+        // REV takes the bounds as max, min
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZRANGE", "z", "+inf", "(1", "BYSCORE", "REV", "LIMIT", "1", "2"]
+            ),
+            array(&["d", "c"])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZREVRANGEBYSCORE", "z", "3", "-inf", "WITHSCORES"]
+            ),
+            array(&["c", "3", "b", "2", "a", "1"])
+        );
+        // A negative offset selects nothing; a negative count means no limit
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZRANGE", "z", "-inf", "+inf", "BYSCORE", "LIMIT", "-1", "2"]
+            ),
+            array(&[])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZRANGE", "z", "-inf", "+inf", "BYSCORE", "LIMIT", "3", "-1"]
+            ),
+            array(&["d", "e"])
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANGE", "z", "x", "1", "BYSCORE"]),
+            RespValue::Error("ERR min or max is not a float".to_string())
+        );
+
+        let mut executor = letters();
+        assert_eq!(
+            run(&mut executor, &["ZRANGEBYLEX", "lex", "[b", "(e"]),
+            array(&["b", "c", "d"])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZREVRANGEBYLEX", "lex", "+", "[e", "LIMIT", "0", "2"]
+            ),
+            array(&["g", "f"])
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANGE", "lex", "(f", "+", "BYLEX"]),
+            array(&["g"])
+        );
+        assert_eq!(
+            run(&mut executor, &["ZLEXCOUNT", "lex", "-", "(c"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZLEXCOUNT", "lex", "a", "+"]),
+            RespValue::Error("ERR min or max not valid string range item".to_string())
+        );
+    }
+
+    #[test]
+    fn test_zrangestore_zincrby_zmscore_zrevrank() {
+        let mut executor = scores();
+        assert_eq!(
+            run(&mut executor, &["ZRANGESTORE", "top", "z", "0", "1", "REV"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANGE", "top", "0", "-1", "WITHSCORES"]),
+            array(&["d", "4", "e", "5"])
+        );
+        // An empty range deletes the destination
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZRANGESTORE", "top", "z", "10", "20", "BYSCORE"]
+            ),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "top"]),
+            RespValue::Integer(0)
+        );
+
+        assert_eq!(
+            run(&mut executor, &["ZINCRBY", "z", "2.5", "a"]),
+            bulk("3.5")
+        );
+        assert_eq!(
+            run(&mut executor, &["ZINCRBY", "new", "-1", "m"]),
+            bulk("-1")
+        );
+        assert_eq!(
+            run(&mut executor, &["ZMSCORE", "z", "a", "missing", "e"]),
+            RespValue::Array(Some(vec![
+                bulk("3.5"),
+                RespValue::BulkString(None),
+                bulk("5")
+            ]))
+        );
+        assert_eq!(
+            run(&mut executor, &["ZREVRANK", "z", "e"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZREVRANK", "z", "b"]),
+            RespValue::Integer(4)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZREVRANK", "z", "missing"]),
+            RespValue::BulkString(None)
+        );
+
+        run(&mut executor, &["ZADD", "inf", "inf", "m"]);
+        assert_eq!(
+            run(&mut executor, &["ZINCRBY", "inf", "-inf", "m"]),
+            RespValue::Error("ERR resulting score is not a number (NaN)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_zremrange_and_zpop() {
+        let mut executor = scores();
+        assert_eq!(
+            run(&mut executor, &["ZREMRANGEBYRANK", "z", "-1", "-1"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZREMRANGEBYSCORE", "z", "(1", "2"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANGE", "z", "0", "-1"]),
+            array(&["a", "c", "d"])
+        );
+        assert_eq!(run(&mut executor, &["ZPOPMIN", "z"]), array(&["a", "1"]));
+        assert_eq!(
+            run(&mut executor, &["ZPOPMAX", "z", "5"]),
+            array(&["d", "4", "c", "3"])
+        );
+        // Popping the last member deletes the key
+        assert_eq!(run(&mut executor, &["EXISTS", "z"]), RespValue::Integer(0));
+        assert_eq!(run(&mut executor, &["ZPOPMIN", "z"]), array(&[]));
+
+        let mut executor = letters();
+        assert_eq!(
+            run(&mut executor, &["ZREMRANGEBYLEX", "lex", "[b", "(f"]),
+            RespValue::Integer(4)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANGE", "lex", "0", "-1"]),
+            array(&["a", "f", "g"])
+        );
+        assert_eq!(
+            run(&mut executor, &["ZREMRANGEBYLEX", "lex", "-", "+"]),
+            RespValue::Integer(3)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "lex"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_zrandmember() {
+        let mut executor = scores();
+        let RespValue::BulkString(Some(member)) = run(&mut executor, &["ZRANDMEMBER", "z"]) else {
+            panic!("ZRANDMEMBER without count returns a member");
+        };
+        assert!(b"abcde".contains(&member[0]));
+
+        // A positive count never repeats; past the cardinality it returns everything
+        let RespValue::Array(Some(distinct)) = run(&mut executor, &["ZRANDMEMBER", "z", "3"])
+        else {
+            panic!("ZRANDMEMBER with count returns an array");
+        };
+        assert_eq!(distinct.len(), 3);
+        for (i, member) in distinct.iter().enumerate() {
+            assert!(!distinct[i + 1..].contains(member));
+        }
+        assert_eq!(
+            run(&mut executor, &["ZRANDMEMBER", "z", "10", "WITHSCORES"]),
+            array(&["a", "1", "b", "2", "c", "3", "d", "4", "e", "5"])
+        );
+
+        // A negative count may repeat members
+        let RespValue::Array(Some(repeated)) = run(&mut executor, &["ZRANDMEMBER", "z", "-20"])
+        else {
+            panic!("ZRANDMEMBER with count returns an array");
+        };
+        assert_eq!(repeated.len(), 20);
+
+        assert_eq!(
+            run(&mut executor, &["ZRANDMEMBER", "missing"]),
+            RespValue::BulkString(None)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANDMEMBER", "missing", "-3"]),
+            array(&[])
+        );
+    }
+
+    #[test]
+    fn test_zunion_zinter_zdiff() {
+        let mut executor = CommandExecutor::new();
+        run(
+            &mut executor,
+            &["ZADD", "{t}a", "1", "x", "2", "y", "3", "z"],
+        );
+        run(
+            &mut executor,
+            &["ZADD", "{t}b", "10", "y", "20", "z", "30", "w"],
+        );
+        run(&mut executor, &["SADD", "{t}s", "z", "v"]);
+
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZUNION", "2", "{t}a", "{t}b", "WITHSCORES"]
+            ),
+            array(&["x", "1", "y", "12", "z", "23", "w", "30"])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "ZINTER",
+                    "2",
+                    "{t}a",
+                    "{t}b",
+                    "WEIGHTS",
+                    "2",
+                    "0.5",
+                    "WITHSCORES"
+                ]
+            ),
+            array(&["y", "9", "z", "16"])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "ZINTER",
+                    "2",
+                    "{t}a",
+                    "{t}b",
+                    "AGGREGATE",
+                    "MAX",
+                    "WITHSCORES"
+                ]
+            ),
+            array(&["y", "10", "z", "20"])
+        );
+        // Plain sets score every member 1
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZINTER", "2", "{t}a", "{t}s", "WITHSCORES"]
+            ),
+            array(&["z", "4"])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZDIFF", "3", "{t}a", "{t}b", "{t}s", "WITHSCORES"]
+            ),
+            array(&["x", "1"])
+        );
+
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "ZUNIONSTORE",
+                    "{t}d",
+                    "2",
+                    "{t}a",
+                    "{t}b",
+                    "AGGREGATE",
+                    "MIN"
+                ]
+            ),
+            RespValue::Integer(4)
+        );
+        assert_eq!(
+            run(&mut executor, &["ZRANGE", "{t}d", "0", "-1", "WITHSCORES"]),
+            array(&["x", "1", "y", "2", "z", "3", "w", "30"])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["ZINTERSTORE", "{t}d", "2", "{t}a", "{t}missing"]
+            ),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "{t}d"]),
+            RespValue::Integer(0)
+        );
+
+        run(&mut executor, &["SET", "{t}str", "v"]);
+        assert_eq!(
+            run(&mut executor, &["ZUNION", "2", "{t}a", "{t}str"]),
+            RespValue::Error(
+                "WRONGTYPE Operation against a key holding the wrong kind of value".to_string()
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod set_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, parse, run};
+
+    /// Members of an array reply, sorted since set replies have no order
+    fn sorted(reply: RespValue) -> Vec<String> {
+        let RespValue::Array(Some(members)) = reply else {
+            panic!("expected an array, got {:?}", reply);
+        };
+        let mut members: Vec<String> = members
+            .into_iter()
+            .map(|m| match m {
+                RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+                other => panic!("expected a bulk string, got {:?}", other),
+            })
+            .collect();
+        members.sort();
+        members
+    }
+
+    /// a = {1, 2, 3, 4}, b = {3, 4, 5}, c = {1, 3}
+    fn sets() -> CommandExecutor {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SADD", "a", "1", "2", "3", "4"]);
+        run(&mut executor, &["SADD", "b", "3", "4", "5"]);
+        run(&mut executor, &["SADD", "c", "1", "3"]);
+        executor
+    }
+
+    #[test]
+    fn test_set_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["SUNION", "a", "b"],
+            vec!["SINTER", "a"],
+            vec!["SDIFF", "a", "b", "c"],
+            vec!["SUNIONSTORE", "d", "a", "b"],
+            vec!["SINTERSTORE", "d", "a"],
+            vec!["SDIFFSTORE", "d", "a", "b"],
+            vec!["SINTERCARD", "2", "a", "b", "LIMIT", "3"],
+            vec!["SMOVE", "a", "b", "m"],
+            vec!["SRANDMEMBER", "a", "-3"],
+            vec!["SMISMEMBER", "a", "x", "y"],
+            vec!["SSCAN", "a", "0", "MATCH", "x*", "COUNT", "5"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> = vec![
+            (
+                vec!["SINTER"],
+                "wrong number of arguments for 'sinter' command",
+            ),
+            (
+                vec!["SUNIONSTORE", "d"],
+                "wrong number of arguments for 'sunionstore' command",
+            ),
+            (
+                vec!["SINTERCARD", "0", "a"],
+                "numkeys should be greater than 0",
+            ),
+            (
+                vec!["SINTERCARD", "3", "a", "b"],
+                "Number of keys can't be greater than number of args",
+            ),
+            (
+                vec!["SINTERCARD", "1", "a", "LIMIT", "-1"],
+                "LIMIT can't be negative",
+            ),
+            (vec!["SINTERCARD", "1", "a", "LIMIT"], "syntax error"),
+            (
+                vec!["SMOVE", "a", "b"],
+                "wrong number of arguments for 'smove' command",
+            ),
+            (
+                vec!["SRANDMEMBER", "a", "x"],
+                "value is not an integer or out of range",
+            ),
+            (
+                vec!["SMISMEMBER", "a"],
+                "wrong number of arguments for 'smismember' command",
+            ),
+            (vec!["SSCAN", "a", "x"], "invalid cursor"),
+            (vec!["SSCAN", "a", "0", "COUNT", "0"], "syntax error"),
+        ];
+        for (args, message) in errors {
+            assert_eq!(parse(&args).unwrap_err(), message, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_sunion_sinter_sdiff() {
+        let mut executor = sets();
+        assert_eq!(
+            sorted(run(&mut executor, &["SUNION", "a", "b", "missing"])),
+            ["1", "2", "3", "4", "5"]
+        );
+        assert_eq!(
+            sorted(run(&mut executor, &["SINTER", "a", "b", "c"])),
+            ["3"]
+        );
+        assert_eq!(sorted(run(&mut executor, &["SDIFF", "a", "b"])), ["1", "2"]);
+        assert!(sorted(run(&mut executor, &["SINTER", "a", "missing"])).is_empty());
+
+        // STORE forms overwrite the destination and delete it when empty
+        run(&mut executor, &["SET", "d", "string"]);
+        assert_eq!(
+            run(&mut executor, &["SINTERSTORE", "d", "a", "b"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(sorted(run(&mut executor, &["SMEMBERS", "d"])), ["3", "4"]);
+        assert_eq!(
+            run(&mut executor, &["SDIFFSTORE", "d", "c", "a"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "d"]), RespValue::Integer(0));
+
+        assert_eq!(
+            run(&mut executor, &["SINTERCARD", "2", "a", "b"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["SINTERCARD", "2", "a", "b", "LIMIT", "1"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["SINTERCARD", "2", "a", "missing"]),
+            RespValue::Integer(0)
+        );
+
+        run(&mut executor, &["SET", "s", "string"]);
+        for args in [
+            vec!["SUNION", "a", "s"],
+            vec!["SDIFFSTORE", "d", "a", "s"],
+            vec!["SINTERCARD", "2", "a", "s"],
+        ] {
+            assert!(
+                matches!(run(&mut executor, &args), RespValue::Error(e) if e.starts_with("WRONGTYPE")),
+                "{:?}",
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn test_smove_smismember_srandmember() {
+        let mut executor = sets();
+        assert_eq!(
+            run(&mut executor, &["SMOVE", "c", "b", "1"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["SMOVE", "c", "b", "1"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            sorted(run(&mut executor, &["SMEMBERS", "b"])),
+            ["1", "3", "4", "5"]
+        );
+
+        // Moving the last member deletes the source
+        assert_eq!(
+            run(&mut executor, &["SMOVE", "c", "new", "3"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "c"]), RespValue::Integer(0));
+        assert_eq!(sorted(run(&mut executor, &["SMEMBERS", "new"])), ["3"]);
+
+        run(&mut executor, &["SET", "s", "string"]);
+        assert!(matches!(
+            run(&mut executor, &["SMOVE", "a", "s", "2"]),
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+        assert_eq!(
+            run(&mut executor, &["SMOVE", "missing", "s", "2"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(sorted(run(&mut executor, &["SMEMBERS", "a"])).len(), 4);
+
+        assert_eq!(
+            run(&mut executor, &["SMISMEMBER", "a", "1", "9", "4"]),
+            RespValue::Array(Some(vec![
+                RespValue::Integer(1),
+                RespValue::Integer(0),
+                RespValue::Integer(1),
+            ]))
+        );
+        assert_eq!(
+            run(&mut executor, &["SMISMEMBER", "missing", "1"]),
+            RespValue::Array(Some(vec![RespValue::Integer(0)]))
+        );
+
+        let RespValue::BulkString(Some(member)) = run(&mut executor, &["SRANDMEMBER", "a"]) else {
+            panic!("SRANDMEMBER without count returns a member");
+        };
+        assert!(b"1234".contains(&member[0]));
+        let distinct = sorted(run(&mut executor, &["SRANDMEMBER", "a", "3"]));
+        assert_eq!(distinct.len(), 3);
+        assert!(distinct.windows(2).all(|w| w[0] != w[1]));
+        assert_eq!(
+            sorted(run(&mut executor, &["SRANDMEMBER", "a", "10"])),
+            ["1", "2", "3", "4"]
+        );
+        assert_eq!(
+            sorted(run(&mut executor, &["SRANDMEMBER", "a", "-10"])).len(),
+            10
+        );
+        assert_eq!(
+            run(&mut executor, &["SRANDMEMBER", "missing"]),
+            RespValue::BulkString(None)
+        );
+        assert_eq!(run(&mut executor, &["SCARD", "a"]), RespValue::Integer(4));
+    }
+
+    #[test]
+    fn test_sscan_pages_through_members() {
+        let mut executor = CommandExecutor::new();
+        let mut args = vec!["SADD", "s"];
+        let members: Vec<String> = (0..25).map(|i| format!("m{:02}", i)).collect();
+        args.extend(members.iter().map(|m| m.as_str()));
+        run(&mut executor, &args);
+
+        let mut seen = Vec::new();
+        let mut cursor = "0".to_string();
+        loop {
+            let RespValue::Array(Some(mut page)) =
+                run(&mut executor, &["SSCAN", "s", &cursor, "COUNT", "10"])
+            else {
+                panic!("SSCAN returns [cursor, members]");
+            };
+            seen.extend(sorted(page.pop().unwrap()));
+            let RespValue::BulkString(Some(next)) = page.pop().unwrap() else {
+                panic!("SSCAN cursor is a bulk string");
+            };
+            cursor = String::from_utf8(next).unwrap();
+            if cursor == "0" {
+                break;
+            }
+        }
+        assert_eq!(seen, members);
+
+        let RespValue::Array(Some(mut page)) =
+            run(&mut executor, &["SSCAN", "s", "0", "MATCH", "m1*"])
+        else {
+            panic!("SSCAN returns [cursor, members]");
+        };
+        assert_eq!(sorted(page.pop().unwrap()).len(), 10);
+    }
+}
+
+#[cfg(test)]
+mod hash_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, bulk, parse, run};
+    use crate::simulator::VirtualTime;
+
+    fn integers(values: &[i64]) -> RespValue {
+        RespValue::Array(Some(
+            values.iter().map(|&v| RespValue::Integer(v)).collect(),
+        ))
+    }
+
+    /// h = {a: 1, b: 2, c: 3}
+    fn hash() -> CommandExecutor {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["HSET", "h", "a", "1", "b", "2", "c", "3"]);
+        executor
+    }
+
+    #[test]
+    fn test_hash_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["HMGET", "h", "a", "b"],
+            vec!["HMSET", "h", "a", "1", "b", "2"],
+            vec!["HSETNX", "h", "a", "1"],
+            vec!["HINCRBYFLOAT", "h", "a", "1.5"],
+            vec!["HSTRLEN", "h", "a"],
+            vec!["HRANDFIELD", "h", "-3", "WITHVALUES"],
+            vec!["HGETDEL", "h", "FIELDS", "2", "a", "b"],
+            vec!["HGETEX", "h", "PX", "100", "FIELDS", "1", "a"],
+            vec!["HGETEX", "h", "PERSIST", "FIELDS", "1", "a"],
+            vec!["HEXPIRE", "h", "10", "NX", "FIELDS", "1", "a"],
+            vec!["HPEXPIREAT", "h", "1000", "FIELDS", "1", "a"],
+            vec!["HTTL", "h", "FIELDS", "1", "a"],
+            vec!["HPTTL", "h", "FIELDS", "1", "a"],
+            vec!["HPERSIST", "h", "FIELDS", "2", "a", "b"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+            assert_eq!(cmd.name(), args[0], "{:?}", args);
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> = vec![
+            (
+                vec!["HGETDEL", "h", "a"],
+                "wrong number of arguments for 'hgetdel' command",
+            ),
+            (
+                vec!["HGETDEL", "h", "FIELDS", "0", "a"],
+                "Parameter `numFields` should be greater than 0",
+            ),
+            (
+                vec!["HGETDEL", "h", "FIELDS", "2", "a"],
+                "The `numfields` parameter must match the number of arguments",
+            ),
+            (
+                vec!["HEXPIRE", "h", "10", "FIELD", "1", "a"],
+                "Mandatory argument FIELDS is missing or not at the right position",
+            ),
+            (
+                vec!["HEXPIRE", "h", "-1", "FIELDS", "1", "a"],
+                "invalid expire time, must be >= 0 and <= 2^48",
+            ),
+            (
+                vec!["HEXPIRE", "h", "x", "FIELDS", "1", "a"],
+                "value is not an integer or out of range",
+            ),
+            (vec!["HRANDFIELD", "h", "1", "WITHSCORES"], "syntax error"),
+        ];
+        for (args, message) in errors {
+            assert_eq!(parse(&args).unwrap_err(), message, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_hmget_hmset_hsetnx_hstrlen() {
+        let mut executor = hash();
+        assert_eq!(
+            run(&mut executor, &["HMGET", "h", "a", "x", "c"]),
+            RespValue::Array(Some(vec![
+                bulk("1"),
+                RespValue::BulkString(None),
+                bulk("3")
+            ]))
+        );
+        assert_eq!(
+            run(&mut executor, &["HMGET", "missing", "a"]),
+            RespValue::Array(Some(vec![RespValue::BulkString(None)]))
+        );
+        assert_eq!(
+            run(&mut executor, &["HMSET", "h", "a", "10", "d", "4"]),
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(run(&mut executor, &["HLEN", "h"]), RespValue::Integer(4));
+
+        assert_eq!(
+            run(&mut executor, &["HSETNX", "h", "a", "x"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["HSETNX", "h", "e", "hello"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["HSTRLEN", "h", "e"]),
+            RespValue::Integer(5)
+        );
+        assert_eq!(
+            run(&mut executor, &["HSTRLEN", "h", "x"]),
+            RespValue::Integer(0)
+        );
+
+        run(&mut executor, &["SET", "s", "v"]);
+        for args in [
+            vec!["HMGET", "s", "a"],
+            vec!["HSETNX", "s", "a", "1"],
+            vec!["HSTRLEN", "s", "a"],
+        ] {
+            assert!(
+                matches!(run(&mut executor, &args), RespValue::Error(e) if e.starts_with("WRONGTYPE")),
+                "{:?}",
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn test_hincrbyfloat() {
+        let mut executor = hash();
+        assert_eq!(
+            run(&mut executor, &["HINCRBYFLOAT", "h", "a", "0.5"]),
+            bulk("1.5")
+        );
+        assert_eq!(
+            run(&mut executor, &["HINCRBYFLOAT", "h", "new", "-2.25"]),
+            bulk("-2.25")
+        );
+        assert_eq!(
+            run(&mut executor, &["HINCRBYFLOAT", "h", "c", "2"]),
+            bulk("5")
+        );
+        run(&mut executor, &["HSET", "h", "s", "abc"]);
+        assert_eq!(
+            run(&mut executor, &["HINCRBYFLOAT", "h", "s", "1"]),
+            RespValue::Error("ERR hash value is not a float".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, &["HINCRBYFLOAT", "h", "a", "inf"]),
+            RespValue::Error("ERR increment would produce NaN or Infinity".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hrandfield() {
+        let mut executor = hash();
+        let fields = ["a", "b", "c"].map(bulk);
+        let single = run(&mut executor, &["HRANDFIELD", "h"]);
+        assert!(fields.contains(&single), "{:?}", single);
+
+        let RespValue::Array(Some(distinct)) = run(&mut executor, &["HRANDFIELD", "h", "5"]) else {
+            panic!("expected an array");
+        };
+        assert_eq!(distinct.len(), 3);
+
+        let RespValue::Array(Some(repeated)) = run(&mut executor, &["HRANDFIELD", "h", "-5"])
+        else {
+            panic!("expected an array");
+        };
+        assert_eq!(repeated.len(), 5);
+        assert!(repeated.iter().all(|f| fields.contains(f)));
+
+        let RespValue::Array(Some(pairs)) =
+            run(&mut executor, &["HRANDFIELD", "h", "2", "WITHVALUES"])
+        else {
+            panic!("expected an array");
+        };
+        assert_eq!(pairs.len(), 4);
+        for pair in pairs.chunks(2) {
+            let field = pair[0].clone();
+            let RespValue::BulkString(Some(name)) = &field else {
+                panic!("expected a field");
+            };
+            let name = String::from_utf8(name.clone()).unwrap();
+            assert_eq!(run(&mut executor, &["HGET", "h", &name]), pair[1]);
+        }
+
+        assert_eq!(
+            run(&mut executor, &["HRANDFIELD", "missing"]),
+            RespValue::BulkString(None)
+        );
+        assert_eq!(
+            run(&mut executor, &["HRANDFIELD", "missing", "3"]),
+            RespValue::Array(Some(Vec::new()))
+        );
+    }
+
+    #[test]
+    fn test_hgetdel_deletes_emptied_hash() {
+        let mut executor = hash();
+        assert_eq!(
+            run(&mut executor, &["HGETDEL", "h", "FIELDS", "2", "a", "x"]),
+            RespValue::Array(Some(vec![bulk("1"), RespValue::BulkString(None)]))
+        );
+        assert_eq!(run(&mut executor, &["HLEN", "h"]), RespValue::Integer(2));
+        run(&mut executor, &["HGETDEL", "h", "FIELDS", "2", "b", "c"]);
+        assert_eq!(run(&mut executor, &["EXISTS", "h"]), RespValue::Integer(0));
+
+        // HDEL of the last field deletes the key too
+        run(&mut executor, &["HSET", "g", "f", "v"]);
+        run(&mut executor, &["HDEL", "g", "f"]);
+        assert_eq!(run(&mut executor, &["EXISTS", "g"]), RespValue::Integer(0));
+    }
+
+    #[test]
+    fn test_hexpire_httl_hpersist() {
+        let mut executor = hash();
+        executor.set_time(VirtualTime::from_millis(1_000));
+
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HEXPIRE", "h", "10", "FIELDS", "2", "a", "x"]
+            ),
+            integers(&[1, -2])
+        );
+        assert_eq!(
+            run(&mut executor, &["HTTL", "h", "FIELDS", "3", "a", "b", "x"]),
+            integers(&[10, -1, -2])
+        );
+        assert_eq!(
+            run(&mut executor, &["HPTTL", "h", "FIELDS", "1", "a"]),
+            integers(&[10_000])
+        );
+
+        // NX/XX/GT/LT; no TTL counts as infinite
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HEXPIRE", "h", "20", "NX", "FIELDS", "2", "a", "b"]
+            ),
+            integers(&[0, 1])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HEXPIRE", "h", "5", "XX", "FIELDS", "2", "a", "c"]
+            ),
+            integers(&[1, 0])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HEXPIRE", "h", "30", "GT", "FIELDS", "2", "a", "c"]
+            ),
+            integers(&[1, 0])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HEXPIRE", "h", "30", "LT", "FIELDS", "1", "c"]
+            ),
+            integers(&[1])
+        );
+
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HPERSIST", "h", "FIELDS", "3", "a", "a", "x"]
+            ),
+            integers(&[1, -1, -2])
+        );
+        assert_eq!(
+            run(&mut executor, &["HTTL", "missing", "FIELDS", "1", "a"]),
+            integers(&[-2])
+        );
+
+        // Writing a field clears its TTL; a deadline in the past deletes it
+        run(&mut executor, &["HSET", "h", "b", "20"]);
+        assert_eq!(
+            run(&mut executor, &["HTTL", "h", "FIELDS", "1", "b"]),
+            integers(&[-1])
+        );
+        assert_eq!(
+            run(&mut executor, &["HPEXPIRE", "h", "0", "FIELDS", "1", "b"]),
+            integers(&[2])
+        );
+        assert_eq!(
+            run(&mut executor, &["HEXISTS", "h", "b"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_hexpireat_uses_unix_time() {
+        let mut executor = hash();
+        executor.set_simulation_start_epoch(1_700_000_000);
+        executor.set_time(VirtualTime::from_millis(1_000));
+
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HPEXPIREAT", "h", "1700000005000", "FIELDS", "1", "a"]
+            ),
+            integers(&[1])
+        );
+        assert_eq!(
+            run(&mut executor, &["HPTTL", "h", "FIELDS", "1", "a"]),
+            integers(&[4_000])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HEXPIREAT", "h", "1700000000", "FIELDS", "1", "b"]
+            ),
+            integers(&[2])
+        );
+        assert_eq!(run(&mut executor, &["HLEN", "h"]), RespValue::Integer(2));
+    }
+
+    #[test]
+    fn test_hgetex() {
+        let mut executor = hash();
+        executor.set_time(VirtualTime::from_millis(1_000));
+        assert_eq!(
+            run(
+                &mut executor,
+                &["HGETEX", "h", "EX", "5", "FIELDS", "2", "a", "x"]
+            ),
+            RespValue::Array(Some(vec![bulk("1"), RespValue::BulkString(None)]))
+        );
+        assert_eq!(
+            run(&mut executor, &["HTTL", "h", "FIELDS", "1", "a"]),
+            integers(&[5])
+        );
+        run(
+            &mut executor,
+            &["HGETEX", "h", "PERSIST", "FIELDS", "1", "a"],
+        );
+        assert_eq!(
+            run(&mut executor, &["HTTL", "h", "FIELDS", "1", "a"]),
+            integers(&[-1])
+        );
+        // Without an option it is a plain HMGET
+        assert_eq!(
+            run(&mut executor, &["HGETEX", "h", "FIELDS", "1", "b"]),
+            RespValue::Array(Some(vec![bulk("2")]))
+        );
+    }
+
+    #[test]
+    fn test_expired_fields_are_reaped() {
+        let mut executor = hash();
+        executor.set_time(VirtualTime::from_millis(0));
+        run(&mut executor, &["HPEXPIRE", "h", "100", "FIELDS", "1", "a"]);
+        run(&mut executor, &["HPEXPIRE", "h", "200", "FIELDS", "1", "b"]);
+        run(&mut executor, &["HSET", "g", "only", "v"]);
+        run(
+            &mut executor,
+            &["HPEXPIRE", "g", "100", "FIELDS", "1", "only"],
+        );
+
+        // Reads skip a due field even before any cycle reclaims it
+        executor.update_time_readonly(VirtualTime::from_millis(150));
+        assert_eq!(run(&mut executor, &["HLEN", "h"]), RespValue::Integer(2));
+        assert_eq!(
+            run(&mut executor, &["HGET", "h", "a"]),
+            RespValue::BulkString(None)
+        );
+
+        // The active cycle (TTL manager tick) reaps fields and emptied hashes
+        executor.evict_expired_direct(VirtualTime::from_millis(250));
+        assert_eq!(run(&mut executor, &["HLEN", "h"]), RespValue::Integer(1));
+        assert_eq!(run(&mut executor, &["EXISTS", "g"]), RespValue::Integer(0));
+        let RespValue::BulkString(Some(info)) = run(&mut executor, &["INFO"]) else {
+            panic!("expected INFO text");
+        };
+        let info = String::from_utf8(info).unwrap();
+        assert!(info.contains("expired_subkeys:3\r\n"), "{}", info);
+    }
+}
+
+#[cfg(test)]
+mod list_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, parse, run};
+
+    fn bulks(values: &[&str]) -> RespValue {
+        RespValue::Array(Some(
+            values
+                .iter()
+                .map(|v| RespValue::BulkString(Some(v.as_bytes().to_vec())))
+                .collect(),
+        ))
+    }
+
+    fn integers(values: &[i64]) -> RespValue {
+        RespValue::Array(Some(
+            values.iter().map(|&v| RespValue::Integer(v)).collect(),
+        ))
+    }
+
+    /// l = [a, b, c, 1, 2, 3, c, c]
+    fn list() -> CommandExecutor {
+        let mut executor = CommandExecutor::new();
+        run(
+            &mut executor,
+            &["RPUSH", "l", "a", "b", "c", "1", "2", "3", "c", "c"],
+        );
+        executor
+    }
+
+    fn contents(executor: &mut CommandExecutor, key: &str) -> RespValue {
+        run(executor, &["LRANGE", key, "0", "-1"])
+    }
+
+    #[test]
+    fn test_list_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["LPOP", "l"],
+            vec!["LPOP", "l", "3"],
+            vec!["RPOP", "l", "0"],
+            vec!["LPUSHX", "l", "a", "b"],
+            vec!["RPUSHX", "l", "a"],
+            vec!["LINSERT", "l", "before", "a", "b"],
+            vec!["LINSERT", "l", "AFTER", "a", "b"],
+            vec!["LREM", "l", "-2", "a"],
+            vec!["LPOS", "l", "c"],
+            vec!["LPOS", "l", "c", "RANK", "-1", "COUNT", "0", "MAXLEN", "5"],
+            vec!["LMPOP", "2", "a", "b", "LEFT"],
+            vec!["LMPOP", "1", "a", "right", "COUNT", "3"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+            assert_eq!(cmd.name(), args[0], "{:?}", args);
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> = vec![
+            (
+                vec!["LPOP", "l", "-1"],
+                "value is out of range, must be positive",
+            ),
+            (vec!["LINSERT", "l", "AROUND", "a", "b"], "syntax error"),
+            (
+                vec!["LREM", "l", "x", "a"],
+                "value is not an integer or out of range",
+            ),
+            (
+                vec!["LPOS", "l", "c", "RANK", "0"],
+                "RANK can't be zero: use 1 to start from the first match, 2 from the second \
+                 ... or use negative to start from the end of the list",
+            ),
+            (
+                vec!["LPOS", "l", "c", "RANK", "-9223372036854775808"],
+                "value is out of range, value must between -9223372036854775807 and \
+                 9223372036854775807",
+            ),
+            (
+                vec!["LPOS", "l", "c", "COUNT", "-1"],
+                "COUNT can't be negative",
+            ),
+            (
+                vec!["LPOS", "l", "c", "MAXLEN", "-1"],
+                "MAXLEN can't be negative",
+            ),
+            (vec!["LPOS", "l", "c", "COUNT"], "syntax error"),
+            (vec!["LPOS", "l", "c", "LIMIT", "1"], "syntax error"),
+            (
+                vec!["LPOS", "l"],
+                "wrong number of arguments for 'lpos' command",
+            ),
+            (
+                vec!["LMPOP", "0", "a", "LEFT"],
+                "numkeys should be greater than 0",
+            ),
+            (vec!["LMPOP", "2", "a", "LEFT"], "syntax error"),
+            (vec!["LMPOP", "1", "a", "UP"], "syntax error"),
+            (
+                vec!["LMPOP", "1", "a", "LEFT", "COUNT", "0"],
+                "count should be greater than 0",
+            ),
+            (vec!["LMPOP", "1", "a", "LEFT", "COUNT"], "syntax error"),
+        ];
+        for (args, message) in errors {
+            assert_eq!(parse(&args).unwrap_err(), message, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_pop_with_count() {
+        let mut executor = list();
+        assert_eq!(run(&mut executor, &["LPOP", "l", "2"]), bulks(&["a", "b"]));
+        assert_eq!(run(&mut executor, &["RPOP", "l", "2"]), bulks(&["c", "c"]));
+        assert_eq!(run(&mut executor, &["LPOP", "l", "0"]), bulks(&[]));
+        // Asking for more than the list holds pops everything and deletes the key
+        assert_eq!(
+            run(&mut executor, &["RPOP", "l", "10"]),
+            bulks(&["3", "2", "1", "c"])
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "l"]), RespValue::Integer(0));
+
+        // A missing key is a nil array with a count, a nil bulk without one
+        assert_eq!(
+            run(&mut executor, &["LPOP", "l", "1"]),
+            RespValue::Array(None)
+        );
+        assert_eq!(
+            run(&mut executor, &["RPOP", "l"]),
+            RespValue::BulkString(None)
+        );
+
+        // Popping the last element without a count deletes the key too
+        run(&mut executor, &["RPUSH", "one", "x"]);
+        run(&mut executor, &["LPOP", "one"]);
+        assert_eq!(
+            run(&mut executor, &["TYPE", "one"]),
+            RespValue::SimpleString("none".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pushx_only_touches_existing_lists() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            run(&mut executor, &["LPUSHX", "l", "a"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "l"]), RespValue::Integer(0));
+
+        run(&mut executor, &["RPUSH", "l", "m"]);
+        assert_eq!(
+            run(&mut executor, &["LPUSHX", "l", "b", "a"]),
+            RespValue::Integer(3)
+        );
+        assert_eq!(
+            run(&mut executor, &["RPUSHX", "l", "y", "z"]),
+            RespValue::Integer(5)
+        );
+        assert_eq!(
+            contents(&mut executor, "l"),
+            bulks(&["a", "b", "m", "y", "z"])
+        );
+
+        run(&mut executor, &["SET", "s", "v"]);
+        assert!(matches!(
+            run(&mut executor, &["RPUSHX", "s", "a"]),
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+    }
+
+    #[test]
+    fn test_linsert() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            run(&mut executor, &["LINSERT", "l", "BEFORE", "a", "x"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "l"]), RespValue::Integer(0));
+
+        run(&mut executor, &["RPUSH", "l", "a", "c", "a"]);
+        assert_eq!(
+            run(&mut executor, &["LINSERT", "l", "BEFORE", "c", "b"]),
+            RespValue::Integer(4)
+        );
+        // Only the first occurrence of the pivot is used
+        assert_eq!(
+            run(&mut executor, &["LINSERT", "l", "AFTER", "a", "z"]),
+            RespValue::Integer(5)
+        );
+        assert_eq!(
+            run(&mut executor, &["LINSERT", "l", "AFTER", "missing", "z"]),
+            RespValue::Integer(-1)
+        );
+        assert_eq!(
+            contents(&mut executor, "l"),
+            bulks(&["a", "z", "b", "c", "a"])
+        );
+    }
+
+    #[test]
+    fn test_lrem_signed_count() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["RPUSH", "l", "x", "a", "x", "b", "x", "x"]);
+
+        assert_eq!(
+            run(&mut executor, &["LREM", "l", "1", "x"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            contents(&mut executor, "l"),
+            bulks(&["a", "x", "b", "x", "x"])
+        );
+        assert_eq!(
+            run(&mut executor, &["LREM", "l", "-2", "x"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(contents(&mut executor, "l"), bulks(&["a", "x", "b"]));
+        assert_eq!(
+            run(&mut executor, &["LREM", "l", "0", "missing"]),
+            RespValue::Integer(0)
+        );
+
+        // Removing every element deletes the key
+        run(&mut executor, &["LREM", "l", "0", "x"]);
+        run(&mut executor, &["LREM", "l", "0", "a"]);
+        run(&mut executor, &["LREM", "l", "0", "b"]);
+        assert_eq!(run(&mut executor, &["EXISTS", "l"]), RespValue::Integer(0));
+        assert_eq!(
+            run(&mut executor, &["LREM", "l", "0", "a"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_lpos() {
+        let mut executor = list();
+        assert_eq!(
+            run(&mut executor, &["LPOS", "l", "c"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["LPOS", "l", "c", "RANK", "2"]),
+            RespValue::Integer(6)
+        );
+        assert_eq!(
+            run(&mut executor, &["LPOS", "l", "c", "RANK", "-1"]),
+            RespValue::Integer(7)
+        );
+        assert_eq!(
+            run(&mut executor, &["LPOS", "l", "c", "COUNT", "0"]),
+            integers(&[2, 6, 7])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["LPOS", "l", "c", "RANK", "-2", "COUNT", "5"]
+            ),
+            integers(&[6, 2])
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["LPOS", "l", "c", "COUNT", "0", "MAXLEN", "7"]
+            ),
+            integers(&[2, 6])
+        );
+        assert_eq!(
+            run(&mut executor, &["LPOS", "l", "z"]),
+            RespValue::BulkString(None)
+        );
+        assert_eq!(
+            run(&mut executor, &["LPOS", "l", "z", "COUNT", "1"]),
+            integers(&[])
+        );
+        assert_eq!(
+            run(&mut executor, &["LPOS", "missing", "c"]),
+            RespValue::BulkString(None)
+        );
+        assert_eq!(
+            run(&mut executor, &["LPOS", "missing", "c", "COUNT", "0"]),
+            integers(&[])
+        );
+    }
+
+    #[test]
+    fn test_lmpop() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            run(&mut executor, &["LMPOP", "2", "a", "b", "LEFT"]),
+            RespValue::Array(None)
+        );
+
+        run(&mut executor, &["RPUSH", "b", "1", "2", "3"]);
+        run(&mut executor, &["RPUSH", "c", "x"]);
+        // The first non-empty list wins
+        assert_eq!(
+            run(&mut executor, &["LMPOP", "3", "a", "b", "c", "RIGHT"]),
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"b".to_vec())),
+                bulks(&["3"])
+            ]))
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &["LMPOP", "2", "b", "c", "LEFT", "COUNT", "5"]
+            ),
+            RespValue::Array(Some(vec![
+                RespValue::BulkString(Some(b"b".to_vec())),
+                bulks(&["1", "2"])
+            ]))
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "b"]), RespValue::Integer(0));
+
+        run(&mut executor, &["SET", "s", "v"]);
+        assert!(matches!(
+            run(&mut executor, &["LMPOP", "2", "s", "c", "LEFT"]),
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+    }
+}
+
+mod string_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, bulk, parse, run};
+
+    fn range(start: i64, end: i64) -> RespValue {
+        RespValue::Array(Some(vec![
+            RespValue::Integer(start),
+            RespValue::Integer(end),
+        ]))
+    }
+
+    fn lcs_match(a: (i64, i64), b: (i64, i64), len: Option<i64>) -> RespValue {
+        let mut entry = vec![range(a.0, a.1), range(b.0, b.1)];
+        entry.extend(len.map(RespValue::Integer));
+        RespValue::Array(Some(entry))
+    }
+
+    fn lcs_idx(matches: Vec<RespValue>, len: i64) -> RespValue {
+        RespValue::Array(Some(vec![
+            bulk("matches"),
+            RespValue::Array(Some(matches)),
+            bulk("len"),
+            RespValue::Integer(len),
+        ]))
+    }
+
+    #[test]
+    fn test_string_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["INCRBYFLOAT", "k", "1.5"],
+            vec!["INCRBYFLOAT", "k", "-5.0e3"],
+            vec!["GETRANGE", "k", "0", "-1"],
+            vec!["SUBSTR", "k", "-3", "2"],
+            vec!["SETRANGE", "k", "5", "v"],
+            vec!["GETDEL", "k"],
+            vec!["GETEX", "k"],
+            vec!["GETEX", "k", "ex", "10"],
+            vec!["GETEX", "k", "PXAT", "1700000000000"],
+            vec!["GETEX", "k", "PERSIST"],
+            vec!["MSETNX", "a", "1", "b", "2"],
+            vec!["SETEX", "k", "10", "v"],
+            vec!["PSETEX", "k", "100", "v"],
+            vec!["LCS", "a", "b"],
+            vec!["LCS", "a", "b", "LEN"],
+            vec!["LCS", "a", "b", "idx", "MINMATCHLEN", "3", "WITHMATCHLEN"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+            // SUBSTR is an alias of GETRANGE, SETEX/PSETEX parse to SET
+            if !matches!(args[0], "SUBSTR" | "SETEX" | "PSETEX") {
+                assert_eq!(cmd.name(), args[0], "{:?}", args);
+            }
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> = vec![
+            (
+                vec!["INCRBYFLOAT", "k", "abc"],
+                "value is not a valid float",
+            ),
+            (
+                vec!["INCRBYFLOAT", "k", "nan"],
+                "value is not a valid float",
+            ),
+            (
+                vec!["GETRANGE", "k", "0", "x"],
+                "value is not an integer or out of range",
+            ),
+            (
+                vec!["GETRANGE", "k", "0"],
+                "wrong number of arguments for 'getrange' command",
+            ),
+            (vec!["SETRANGE", "k", "-1", "v"], "offset is out of range"),
+            (vec!["GETEX", "k", "EX", "10", "PERSIST"], "syntax error"),
+            (vec!["GETEX", "k", "EX"], "syntax error"),
+            (vec!["GETEX", "k", "KEEPTTL"], "syntax error"),
+            (
+                vec!["GETEX", "k", "EX", "0"],
+                "invalid expire time in 'getex' command",
+            ),
+            (
+                vec!["SETEX", "k", "0", "v"],
+                "invalid expire time in 'setex' command",
+            ),
+            (
+                vec!["PSETEX", "k", "-5", "v"],
+                "invalid expire time in 'psetex' command",
+            ),
+            (
+                vec!["SETEX", "k", "9223372036854775807", "v"],
+                "invalid expire time in 'setex' command",
+            ),
+            (
+                vec!["SETEX", "k", "ten", "v"],
+                "value is not an integer or out of range",
+            ),
+            (
+                vec!["MSETNX", "a", "1", "b"],
+                "MSETNX requires key-value pairs",
+            ),
+            (
+                vec!["LCS", "a", "b", "LEN", "IDX"],
+                "If you want both the length and indexes, please just use IDX.",
+            ),
+            (vec!["LCS", "a", "b", "MINMATCHLEN"], "syntax error"),
+            (vec!["LCS", "a", "b", "FAST"], "syntax error"),
+        ];
+        for (args, expected) in errors {
+            assert_eq!(parse(&args).unwrap_err(), expected, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_incrbyfloat_formats_like_redis() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "k", "10.50"]);
+        assert_eq!(
+            run(&mut executor, &["INCRBYFLOAT", "k", "0.1"]),
+            bulk("10.6")
+        );
+        assert_eq!(run(&mut executor, &["INCRBYFLOAT", "k", "-5"]), bulk("5.6"));
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("5.6"));
+
+        run(&mut executor, &["SET", "k", "5.0e3"]);
+        assert_eq!(
+            run(&mut executor, &["INCRBYFLOAT", "k", "2.0e2"]),
+            bulk("5200")
+        );
+        assert_eq!(
+            run(&mut executor, &["INCRBYFLOAT", "k", "-5200"]),
+            bulk("0")
+        );
+        assert_eq!(run(&mut executor, &["INCRBYFLOAT", "new", "3"]), bulk("3"));
+
+        // The TTL survives the increment
+        run(&mut executor, &["SET", "ttl", "1", "EX", "100"]);
+        run(&mut executor, &["INCRBYFLOAT", "ttl", "1.5"]);
+        assert_eq!(run(&mut executor, &["TTL", "ttl"]), RespValue::Integer(100));
+
+        run(&mut executor, &["SET", "text", "abc"]);
+        assert_eq!(
+            run(&mut executor, &["INCRBYFLOAT", "text", "1"]),
+            RespValue::Error("ERR value is not a valid float".to_string())
+        );
+        run(&mut executor, &["SET", "big", "1.7e308"]);
+        assert_eq!(
+            run(&mut executor, &["INCRBYFLOAT", "big", "1.7e308"]),
+            RespValue::Error("ERR increment would produce NaN or Infinity".to_string())
+        );
+        assert_eq!(run(&mut executor, &["GET", "big"]), bulk("1.7e308"));
+    }
+
+    #[test]
+    fn test_getrange_and_setrange() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "k", "This is a string"]);
+        assert_eq!(
+            run(&mut executor, &["GETRANGE", "k", "0", "3"]),
+            bulk("This")
+        );
+        assert_eq!(
+            run(&mut executor, &["GETRANGE", "k", "-3", "-1"]),
+            bulk("ing")
+        );
+        assert_eq!(
+            run(&mut executor, &["SUBSTR", "k", "10", "100"]),
+            bulk("string")
+        );
+        assert_eq!(run(&mut executor, &["GETRANGE", "k", "5", "3"]), bulk(""));
+        assert_eq!(
+            run(&mut executor, &["GETRANGE", "missing", "0", "-1"]),
+            bulk("")
+        );
+
+        run(&mut executor, &["SET", "k", "Hello World"]);
+        assert_eq!(
+            run(&mut executor, &["SETRANGE", "k", "6", "Redis"]),
+            RespValue::Integer(11)
+        );
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("Hello Redis"));
+
+        // Writing past the end zero-pads
+        assert_eq!(
+            run(&mut executor, &["SETRANGE", "pad", "3", "ab"]),
+            RespValue::Integer(5)
+        );
+        assert_eq!(
+            run(&mut executor, &["GET", "pad"]),
+            RespValue::BulkString(Some(b"\0\0\0ab".to_vec()))
+        );
+
+        // An empty value never creates the key
+        assert_eq!(
+            run(&mut executor, &["SETRANGE", "none", "10", ""]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "none"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["SETRANGE", "k", "100", ""]),
+            RespValue::Integer(11)
+        );
+
+        assert_eq!(
+            run(&mut executor, &["SETRANGE", "k", "536870911", "xy"]),
+            RespValue::Error(
+                "ERR string exceeds maximum allowed size (proto-max-bulk-len)".to_string()
+            )
+        );
+        run(&mut executor, &["RPUSH", "list", "a"]);
+        assert!(matches!(
+            run(&mut executor, &["SETRANGE", "list", "0", "x"]),
+            RespValue::Error(e) if e.starts_with("WRONGTYPE")
+        ));
+    }
+
+    #[test]
+    fn test_getdel_and_getex() {
+        let mut executor = CommandExecutor::new();
+        executor.set_simulation_start_epoch(1_700_000_000);
+        run(&mut executor, &["SET", "k", "v"]);
+        assert_eq!(run(&mut executor, &["GETDEL", "k"]), bulk("v"));
+        assert_eq!(run(&mut executor, &["EXISTS", "k"]), RespValue::Integer(0));
+        assert_eq!(
+            run(&mut executor, &["GETDEL", "k"]),
+            RespValue::BulkString(None)
+        );
+
+        run(&mut executor, &["SET", "k", "v"]);
+        assert_eq!(run(&mut executor, &["GETEX", "k", "EX", "50"]), bulk("v"));
+        assert_eq!(run(&mut executor, &["TTL", "k"]), RespValue::Integer(50));
+        assert_eq!(run(&mut executor, &["GETEX", "k", "PX", "1500"]), bulk("v"));
+        assert_eq!(run(&mut executor, &["PTTL", "k"]), RespValue::Integer(1500));
+        // Without options GETEX leaves the TTL alone
+        assert_eq!(run(&mut executor, &["GETEX", "k"]), bulk("v"));
+        assert_eq!(run(&mut executor, &["PTTL", "k"]), RespValue::Integer(1500));
+        assert_eq!(run(&mut executor, &["GETEX", "k", "PERSIST"]), bulk("v"));
+        assert_eq!(run(&mut executor, &["TTL", "k"]), RespValue::Integer(-1));
+
+        // A deadline in the past still returns the value, then deletes the key
+        assert_eq!(
+            run(&mut executor, &["GETEX", "k", "EXAT", "1600000000"]),
+            bulk("v")
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "k"]), RespValue::Integer(0));
+        assert_eq!(
+            run(&mut executor, &["GETEX", "k", "EX", "10"]),
+            RespValue::BulkString(None)
+        );
+
+        run(&mut executor, &["RPUSH", "list", "a"]);
+        for cmd in [&["GETDEL", "list"][..], &["GETEX", "list", "PERSIST"]] {
+            assert!(matches!(
+                run(&mut executor, cmd),
+                RespValue::Error(e) if e.starts_with("WRONGTYPE")
+            ));
+        }
+    }
+
+    #[test]
+    fn test_msetnx_and_setex() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            run(&mut executor, &["MSETNX", "a", "1", "b", "2"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["MSETNX", "b", "3", "c", "4"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &["GET", "b"]), bulk("2"));
+        assert_eq!(run(&mut executor, &["EXISTS", "c"]), RespValue::Integer(0));
+
+        assert_eq!(
+            run(&mut executor, &["SETEX", "k", "10", "v"]),
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(run(&mut executor, &["TTL", "k"]), RespValue::Integer(10));
+        run(&mut executor, &["PSETEX", "k", "2500", "w"]);
+        assert_eq!(run(&mut executor, &["PTTL", "k"]), RespValue::Integer(2500));
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("w"));
+    }
+
+    #[test]
+    fn test_lcs() {
+        let mut executor = CommandExecutor::new();
+        run(
+            &mut executor,
+            &["MSET", "key1", "ohmytext", "key2", "mynewtext"],
+        );
+        assert_eq!(run(&mut executor, &["LCS", "key1", "key2"]), bulk("mytext"));
+        assert_eq!(
+            run(&mut executor, &["LCS", "key1", "key2", "LEN"]),
+            RespValue::Integer(6)
+        );
+        assert_eq!(
+            run(&mut executor, &["LCS", "key1", "key2", "IDX"]),
+            lcs_idx(
+                vec![
+                    lcs_match((4, 7), (5, 8), None),
+                    lcs_match((2, 3), (0, 1), None),
+                ],
+                6
+            )
+        );
+        assert_eq!(
+            run(
+                &mut executor,
+                &[
+                    "LCS",
+                    "key1",
+                    "key2",
+                    "IDX",
+                    "MINMATCHLEN",
+                    "4",
+                    "WITHMATCHLEN"
+                ]
+            ),
+            lcs_idx(vec![lcs_match((4, 7), (5, 8), Some(4))], 6)
+        );
+
+        // Missing keys are empty strings
+        assert_eq!(run(&mut executor, &["LCS", "key1", "missing"]), bulk(""));
+        run(&mut executor, &["RPUSH", "list", "a"]);
+        assert_eq!(
+            run(&mut executor, &["LCS", "key1", "list"]),
+            RespValue::Error("ERR The specified keys must contain string values".to_string())
+        );
+    }
+}
+
+mod keyspace_command_tests {
+    use super::super::{CommandExecutor, EvictionPolicy, MemoryConfig, RespValue};
+    use super::support::{assert_parsers_agree, bulk, parse, run};
+    use crate::simulator::VirtualTime;
+
+    fn ok() -> RespValue {
+        RespValue::SimpleString("OK".to_string())
+    }
+
+    #[test]
+    fn test_keyspace_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["PEXPIRE", "k", "100"],
+            vec!["PEXPIRE", "k", "100", "xx", "GT"],
+            vec!["PEXPIRE", "k", "-1", "NX"],
+            vec!["EXPIRETIME", "k"],
+            vec!["PEXPIRETIME", "k"],
+            vec!["RENAME", "a", "b"],
+            vec!["RENAMENX", "a", "b"],
+            vec!["COPY", "a", "b"],
+            vec!["COPY", "a", "b", "replace"],
+            vec!["RANDOMKEY"],
+            vec!["TOUCH", "a", "b"],
+            vec!["UNLINK", "a"],
+            vec!["OBJECT", "encoding", "k"],
+            vec!["OBJECT", "IDLETIME", "k"],
+            vec!["OBJECT", "freq", "k"],
+            vec!["OBJECT", "REFCOUNT", "k"],
+            vec!["OBJECT", "help"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+            assert_eq!(cmd.name(), args[0], "{:?}", args);
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> = vec![
+            (
+                vec!["PEXPIRE", "k", "x"],
+                "value is not an integer or out of range",
+            ),
+            (
+                vec!["PEXPIRE", "k", "1", "NX", "XX"],
+                "NX and XX, GT or LT options at the same time are not compatible",
+            ),
+            (
+                vec!["PEXPIRE", "k", "1", "GT", "LT"],
+                "GT and LT options at the same time are not compatible",
+            ),
+            (
+                vec!["PEXPIRE", "k", "1", "KEEPTTL"],
+                "Unsupported option KEEPTTL",
+            ),
+            (
+                vec!["RENAME", "a"],
+                "wrong number of arguments for 'rename' command",
+            ),
+            (vec!["COPY", "a", "b", "FORCE"], "syntax error"),
+            (
+                vec!["RANDOMKEY", "a"],
+                "wrong number of arguments for 'randomkey' command",
+            ),
+            (
+                vec!["UNLINK"],
+                "wrong number of arguments for 'unlink' command",
+            ),
+            (
+                vec!["OBJECT", "SIZE", "k"],
+                "unknown subcommand 'SIZE'. Try OBJECT HELP.",
+            ),
+            (
+                vec!["OBJECT", "ENCODING"],
+                "wrong number of arguments for 'object|encoding' command",
+            ),
+            (
+                vec!["OBJECT", "HELP", "k"],
+                "unknown subcommand 'HELP'. Try OBJECT HELP.",
+            ),
+        ];
+        for (args, expected) in errors {
+            assert_eq!(parse(&args).unwrap_err(), expected, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_rename_keeps_value_and_ttl() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "a", "1", "EX", "100"]);
+        run(&mut executor, &["SET", "b", "2"]);
+        assert_eq!(run(&mut executor, &["RENAME", "a", "b"]), ok());
+        assert_eq!(run(&mut executor, &["GET", "b"]), bulk("1"));
+        assert_eq!(run(&mut executor, &["TTL", "b"]), RespValue::Integer(100));
+        assert_eq!(run(&mut executor, &["EXISTS", "a"]), RespValue::Integer(0));
+
+        // A persistent source clears the destination's old TTL
+        run(&mut executor, &["SET", "c", "3"]);
+        assert_eq!(run(&mut executor, &["RENAME", "c", "b"]), ok());
+        assert_eq!(run(&mut executor, &["TTL", "b"]), RespValue::Integer(-1));
+
+        assert_eq!(
+            run(&mut executor, &["RENAME", "missing", "b"]),
+            RespValue::Error("ERR no such key".to_string())
+        );
+        assert_eq!(run(&mut executor, &["RENAME", "b", "b"]), ok());
+
+        run(&mut executor, &["RPUSH", "l", "x", "y"]);
+        assert_eq!(
+            run(&mut executor, &["RENAMENX", "l", "b"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["RENAMENX", "l", "b"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["RENAMENX", "l", "l2"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(run(&mut executor, &["LLEN", "l2"]), RespValue::Integer(2));
+        assert_eq!(
+            run(&mut executor, &["RENAMENX", "l2", "l2"]),
+            RespValue::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_rename_moves_hash_field_ttls() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["HSET", "h", "a", "1", "b", "2"]);
+        run(&mut executor, &["HPEXPIRE", "h", "100", "FIELDS", "1", "a"]);
+        run(&mut executor, &["RENAME", "h", "h2"]);
+        executor.set_time(VirtualTime::from_millis(200));
+        assert_eq!(run(&mut executor, &["HLEN", "h2"]), RespValue::Integer(1));
+    }
+
+    #[test]
+    fn test_copy() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SADD", "s", "a", "b"]);
+        run(&mut executor, &["PEXPIRE", "s", "5000"]);
+        assert_eq!(
+            run(&mut executor, &["COPY", "s", "t"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(run(&mut executor, &["SCARD", "t"]), RespValue::Integer(2));
+        assert_eq!(run(&mut executor, &["PTTL", "t"]), RespValue::Integer(5000));
+
+        // The copy is independent of its source
+        run(&mut executor, &["SADD", "t", "c"]);
+        assert_eq!(run(&mut executor, &["SCARD", "s"]), RespValue::Integer(2));
+
+        assert_eq!(
+            run(&mut executor, &["COPY", "s", "t"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["COPY", "s", "t", "REPLACE"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(run(&mut executor, &["SCARD", "t"]), RespValue::Integer(2));
+        assert_eq!(
+            run(&mut executor, &["COPY", "missing", "t"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["COPY", "s", "s"]),
+            RespValue::Error("ERR source and destination objects are the same".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pexpire_conditions_and_expiretime() {
+        let mut executor = CommandExecutor::new();
+        executor.set_simulation_start_epoch(1_700_000_000);
+        run(&mut executor, &["SET", "k", "v"]);
+        assert_eq!(
+            run(&mut executor, &["EXPIRETIME", "k"]),
+            RespValue::Integer(-1)
+        );
+        assert_eq!(
+            run(&mut executor, &["PEXPIRETIME", "missing"]),
+            RespValue::Integer(-2)
+        );
+
+        // No TTL: XX and GT refuse, NX and LT accept
+        for flag in ["XX", "GT"] {
+            assert_eq!(
+                run(&mut executor, &["PEXPIRE", "k", "5000", flag]),
+                RespValue::Integer(0)
+            );
+        }
+        assert_eq!(
+            run(&mut executor, &["PEXPIRE", "k", "5000", "NX"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["PEXPIRE", "k", "9000", "NX"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["PEXPIRE", "k", "9000", "LT"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["PEXPIRE", "k", "9000", "XX", "GT"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["PEXPIRETIME", "k"]),
+            RespValue::Integer(1_700_000_009_000)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXPIRETIME", "k"]),
+            RespValue::Integer(1_700_000_009)
+        );
+
+        assert_eq!(
+            run(&mut executor, &["PEXPIRE", "k", "9223372036854775807"]),
+            RespValue::Error("ERR invalid expire time in 'pexpire' command".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, &["PEXPIRE", "missing", "100"]),
+            RespValue::Integer(0)
+        );
+        // A deadline in the past deletes the key
+        assert_eq!(
+            run(&mut executor, &["PEXPIRE", "k", "0"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "k"]), RespValue::Integer(0));
+    }
+
+    #[test]
+    fn test_touch_unlink_and_randomkey() {
+        let mut executor = CommandExecutor::new();
+        assert_eq!(
+            run(&mut executor, &["RANDOMKEY"]),
+            RespValue::BulkString(None)
+        );
+        run(&mut executor, &["SET", "a", "1"]);
+        run(&mut executor, &["SET", "b", "2", "PX", "10"]);
+        assert_eq!(
+            run(&mut executor, &["TOUCH", "a", "b", "missing"]),
+            RespValue::Integer(2)
+        );
+
+        // Expired keys are never returned
+        executor.set_time(VirtualTime::from_millis(20));
+        for _ in 0..10 {
+            assert_eq!(run(&mut executor, &["RANDOMKEY"]), bulk("a"));
+        }
+
+        let members: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+        let mut sadd = vec!["SADD", "big"];
+        sadd.extend(members.iter().map(|m| m.as_str()));
+        run(&mut executor, &sadd);
+        assert_eq!(
+            run(&mut executor, &["UNLINK", "a", "big", "missing"]),
+            RespValue::Integer(2)
+        );
+        assert_eq!(
+            run(&mut executor, &["EXISTS", "a", "big"]),
+            RespValue::Integer(0)
+        );
+        let RespValue::BulkString(Some(info)) = run(&mut executor, &["INFO"]) else {
+            panic!("INFO replies with a bulk string");
+        };
+        assert!(String::from_utf8(info)
+            .unwrap()
+            .contains("lazyfreed_objects:1\r\n"));
+    }
+
+    #[test]
+    fn test_object_help() {
+        let mut executor = CommandExecutor::new();
+        let RespValue::Array(Some(lines)) = run(&mut executor, &["OBJECT", "HELP"]) else {
+            panic!("OBJECT HELP must reply with an array");
+        };
+        assert_eq!(
+            lines.first(),
+            Some(&RespValue::SimpleString(
+                "OBJECT <subcommand> [<arg> [value] [opt] ...]. Subcommands are:".to_string()
+            ))
+        );
+        for subcommand in [
+            "ENCODING <key>",
+            "FREQ <key>",
+            "IDLETIME <key>",
+            "REFCOUNT <key>",
+        ] {
+            assert!(
+                lines.contains(&RespValue::SimpleString(subcommand.to_string())),
+                "{}",
+                subcommand
+            );
+        }
+    }
+
+    #[test]
+    fn test_object_encoding() {
+        let mut executor = CommandExecutor::new();
+        let long = "x".repeat(45);
+        let wide = "x".repeat(65);
+        let cases: Vec<(Vec<&str>, &str)> = vec![
+            (vec!["SET", "int", "123"], "int"),
+            (vec!["SET", "bigint", "-12345678"], "int"),
+            (vec!["SET", "padded", "012"], "embstr"),
+            (vec!["SET", "short", "hello"], "embstr"),
+            (vec!["SET", "long", &long], "raw"),
+            (vec!["RPUSH", "list", "a", "b"], "listpack"),
+            (vec!["SADD", "intset", "1", "2", "3"], "intset"),
+            (vec!["SADD", "set", "a", "1"], "listpack"),
+            (vec!["SADD", "bigset", "a", &wide], "hashtable"),
+            (vec!["HSET", "hash", "f", "v"], "listpack"),
+            (vec!["HSET", "bighash", "f", &wide], "hashtable"),
+            (vec!["ZADD", "zset", "1", "a"], "listpack"),
+            (vec!["ZADD", "bigzset", "1", &wide], "skiplist"),
+            (vec!["XADD", "stream", "*", "f", "v"], "stream"),
+        ];
+        for (write, encoding) in cases {
+            run(&mut executor, &write);
+            assert_eq!(
+                run(&mut executor, &["OBJECT", "ENCODING", write[1]]),
+                bulk(encoding),
+                "{:?}",
+                write
+            );
+        }
+        run(
+            &mut executor,
+            &["HPEXPIRE", "hash", "100", "FIELDS", "1", "f"],
+        );
+        assert_eq!(
+            run(&mut executor, &["OBJECT", "ENCODING", "hash"]),
+            bulk("listpackex")
+        );
+        assert_eq!(
+            run(&mut executor, &["OBJECT", "ENCODING", "missing"]),
+            RespValue::BulkString(None)
+        );
+
+        // Small integers are shared objects
+        assert_eq!(
+            run(&mut executor, &["OBJECT", "REFCOUNT", "int"]),
+            RespValue::Integer(i32::MAX as i64)
+        );
+        for key in ["bigint", "short"] {
+            assert_eq!(
+                run(&mut executor, &["OBJECT", "REFCOUNT", key]),
+                RespValue::Integer(1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_object_idletime_and_freq() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "k", "v"]);
+        run(&mut executor, &["GET", "k"]);
+        executor.set_time(VirtualTime::from_millis(5_500));
+        // OBJECT itself does not count as an access
+        for _ in 0..2 {
+            assert_eq!(
+                run(&mut executor, &["OBJECT", "IDLETIME", "k"]),
+                RespValue::Integer(5)
+            );
+        }
+        assert!(matches!(
+            run(&mut executor, &["OBJECT", "FREQ", "k"]),
+            RespValue::Error(e) if e.starts_with("ERR An LFU maxmemory policy is not selected")
+        ));
+        run(&mut executor, &["TOUCH", "k"]);
+        assert_eq!(
+            run(&mut executor, &["OBJECT", "IDLETIME", "k"]),
+            RespValue::Integer(0)
+        );
+
+        executor.set_memory_config(MemoryConfig {
+            policy: EvictionPolicy::AllKeysLfu,
+            ..MemoryConfig::default()
+        });
+        run(&mut executor, &["SET", "hot", "v"]);
+        assert_eq!(
+            run(&mut executor, &["OBJECT", "FREQ", "hot"]),
+            RespValue::Integer(5)
+        );
+        assert!(matches!(
+            run(&mut executor, &["OBJECT", "IDLETIME", "hot"]),
+            RespValue::Error(e) if e.starts_with("ERR An LFU maxmemory policy is selected")
+        ));
+    }
+}
+
+mod database_command_tests {
+    use super::super::{CommandExecutor, RespValue};
+    use super::support::{assert_parsers_agree, bulk, parse, run};
+    use crate::simulator::VirtualTime;
+
+    fn ok() -> RespValue {
+        RespValue::SimpleString("OK".to_string())
+    }
+
+    fn out_of_range() -> RespValue {
+        RespValue::Error("ERR DB index is out of range".to_string())
+    }
+
+    #[test]
+    fn test_database_commands_from_both_parsers() {
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["SELECT", "3"],
+            vec!["SWAPDB", "0", "1"],
+            vec!["MOVE", "k", "2"],
+            vec!["COPY", "a", "b", "DB", "4"],
+            vec!["COPY", "a", "b", "replace", "db", "0"],
+        ];
+        for args in cases {
+            let cmd = assert_parsers_agree(&args);
+            assert_eq!(cmd.name(), args[0], "{:?}", args);
+        }
+
+        let errors: Vec<(Vec<&str>, &str)> = vec![
+            (
+                vec!["SELECT", "x"],
+                "value is not an integer or out of range",
+            ),
+            (vec!["SELECT", "-1"], "DB index is out of range"),
+            (
+                vec!["SELECT"],
+                "wrong number of arguments for 'select' command",
+            ),
+            (vec!["SWAPDB", "a", "1"], "invalid first DB index"),
+            (vec!["SWAPDB", "0", "b"], "invalid second DB index"),
+            (
+                vec!["MOVE", "k", "x"],
+                "value is not an integer or out of range",
+            ),
+            (vec!["COPY", "a", "b", "DB"], "syntax error"),
+            (
+                vec!["COPY", "a", "b", "DB", "x"],
+                "value is not an integer or out of range",
+            ),
+        ];
+        for (args, expected) in errors {
+            assert_eq!(parse(&args).unwrap_err(), expected, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn test_select_isolates_keyspaces() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "k", "zero"]);
+        assert_eq!(run(&mut executor, &["SELECT", "1"]), ok());
+        assert_eq!(executor.selected_db(), 1);
+        assert_eq!(
+            run(&mut executor, &["GET", "k"]),
+            RespValue::BulkString(None)
+        );
+        run(&mut executor, &["SET", "k", "one"]);
+        run(&mut executor, &["SET", "only-in-1", "x"]);
+        assert_eq!(run(&mut executor, &["DBSIZE"]), RespValue::Integer(2));
+
+        assert_eq!(run(&mut executor, &["SELECT", "0"]), ok());
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("zero"));
+        assert_eq!(run(&mut executor, &["DBSIZE"]), RespValue::Integer(1));
+
+        assert_eq!(run(&mut executor, &["SELECT", "16"]), out_of_range());
+        executor.set_databases(2);
+        assert_eq!(run(&mut executor, &["SELECT", "2"]), out_of_range());
+        assert_eq!(executor.selected_db(), 0);
+    }
+
+    #[test]
+    fn test_flushdb_only_empties_the_selected_database() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "a", "0"]);
+        run(&mut executor, &["SELECT", "5"]);
+        run(&mut executor, &["SET", "b", "5"]);
+        assert_eq!(run(&mut executor, &["FLUSHDB"]), ok());
+        assert_eq!(run(&mut executor, &["DBSIZE"]), RespValue::Integer(0));
+        run(&mut executor, &["SELECT", "0"]);
+        assert_eq!(run(&mut executor, &["GET", "a"]), bulk("0"));
+
+        run(&mut executor, &["SELECT", "5"]);
+        run(&mut executor, &["SET", "b", "5"]);
+        assert_eq!(run(&mut executor, &["FLUSHALL"]), ok());
+        for db in ["0", "5"] {
+            run(&mut executor, &["SELECT", db]);
+            assert_eq!(run(&mut executor, &["DBSIZE"]), RespValue::Integer(0));
+        }
+        assert_eq!(executor.memory_stats().used_memory, 0);
+    }
+
+    #[test]
+    fn test_swapdb_trades_contents() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "k", "zero"]);
+        run(&mut executor, &["SELECT", "1"]);
+        run(&mut executor, &["SET", "k", "one"]);
+        run(&mut executor, &["SELECT", "2"]);
+        run(&mut executor, &["SET", "k", "two"]);
+
+        // Neither database selected, then the selected one on either side
+        assert_eq!(run(&mut executor, &["SWAPDB", "0", "1"]), ok());
+        assert_eq!(run(&mut executor, &["SWAPDB", "2", "0"]), ok());
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("one"));
+        run(&mut executor, &["SELECT", "0"]);
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("two"));
+        run(&mut executor, &["SELECT", "1"]);
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("zero"));
+
+        assert_eq!(run(&mut executor, &["SWAPDB", "1", "1"]), ok());
+        assert_eq!(run(&mut executor, &["SWAPDB", "0", "16"]), out_of_range());
+    }
+
+    #[test]
+    fn test_watch_stamps_are_per_database() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "w", "0"]);
+        let watch = |executor: &mut CommandExecutor| {
+            run(executor, &["SELECT", "0"]);
+            executor.key_version(b"w")
+        };
+
+        // A write to the same name, a FLUSHDB or a SWAPDB elsewhere leaves db0 alone
+        let version = watch(&mut executor);
+        run(&mut executor, &["SELECT", "1"]);
+        run(&mut executor, &["SET", "w", "1"]);
+        assert_eq!(run(&mut executor, &["FLUSHDB"]), ok());
+        assert_eq!(run(&mut executor, &["SWAPDB", "1", "2"]), ok());
+        assert_eq!(watch(&mut executor), version);
+
+        // Pruning the stamps of deleted keys in db1 keeps db0's
+        run(&mut executor, &["SELECT", "1"]);
+        for i in 0..1100 {
+            let key = format!("gone{}", i);
+            run(&mut executor, &["SET", &key, "x"]);
+            run(&mut executor, &["DEL", &key]);
+        }
+        assert_eq!(watch(&mut executor), version);
+
+        // Flushing or swapping db0 itself changes the stamp
+        run(&mut executor, &["SELECT", "1"]);
+        assert_eq!(run(&mut executor, &["SWAPDB", "0", "1"]), ok());
+        let swapped = watch(&mut executor);
+        assert_ne!(swapped, version);
+        assert_eq!(run(&mut executor, &["FLUSHDB"]), ok());
+        assert_ne!(watch(&mut executor), swapped);
+
+        // FLUSHALL from another database moves db0 on too
+        let flushed = watch(&mut executor);
+        run(&mut executor, &["SELECT", "3"]);
+        assert_eq!(run(&mut executor, &["FLUSHALL"]), ok());
+        assert_ne!(watch(&mut executor), flushed);
+    }
+
+    #[test]
+    fn test_move_and_copy_between_databases() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SET", "k", "v", "PX", "5000"]);
+        assert_eq!(
+            run(&mut executor, &["MOVE", "k", "1"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(run(&mut executor, &["EXISTS", "k"]), RespValue::Integer(0));
+        assert_eq!(
+            run(&mut executor, &["MOVE", "k", "1"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["MOVE", "k", "0"]),
+            RespValue::Error("ERR source and destination objects are the same".to_string())
+        );
+        assert_eq!(run(&mut executor, &["MOVE", "k", "99"]), out_of_range());
+
+        run(&mut executor, &["SELECT", "1"]);
+        assert_eq!(run(&mut executor, &["PTTL", "k"]), RespValue::Integer(5000));
+        // The destination already has the key
+        run(&mut executor, &["SELECT", "0"]);
+        run(&mut executor, &["SET", "k", "other"]);
+        assert_eq!(
+            run(&mut executor, &["MOVE", "k", "1"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("other"));
+
+        // COPY into another database may keep the name
+        assert_eq!(
+            run(&mut executor, &["COPY", "k", "k", "DB", "1"]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(
+            run(&mut executor, &["COPY", "k", "k", "DB", "1", "REPLACE"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["COPY", "k", "c", "DB", "0"]),
+            RespValue::Integer(1)
+        );
+        assert_eq!(
+            run(&mut executor, &["COPY", "k", "c", "DB", "16"]),
+            out_of_range()
+        );
+        run(&mut executor, &["SELECT", "1"]);
+        assert_eq!(run(&mut executor, &["GET", "k"]), bulk("other"));
+        assert_eq!(run(&mut executor, &["PTTL", "k"]), RespValue::Integer(-1));
+    }
+
+    #[test]
+    fn test_parked_databases_expire_and_count_in_info() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SELECT", "3"]);
+        run(&mut executor, &["SET", "short", "v", "PX", "100"]);
+        run(&mut executor, &["SET", "long", "v", "PX", "10000"]);
+        run(&mut executor, &["SELECT", "0"]);
+        run(&mut executor, &["SET", "k", "v"]);
+
+        let info = match run(&mut executor, &["INFO"]) {
+            RespValue::BulkString(Some(bytes)) => String::from_utf8(bytes).unwrap(),
+            other => panic!("unexpected INFO reply {:?}", other),
+        };
+        assert!(info.contains("# Keyspace\r\n"), "{}", info);
+        assert!(
+            info.contains("db0:keys=1,expires=0,avg_ttl=0\r\n"),
+            "{}",
+            info
+        );
+        assert!(
+            info.contains("db3:keys=2,expires=2,avg_ttl=5050\r\n"),
+            "{}",
+            info
+        );
+
+        let expired = executor.active_expire_cycle(VirtualTime::from_millis(200), usize::MAX);
+        assert_eq!(expired, 1);
+        assert_eq!(executor.selected_db(), 0);
+        let stats = executor.keyspace_stats();
+        assert_eq!(stats.len(), 16);
+        assert_eq!((stats[3].keys, stats[3].expires), (1, 1));
+    }
+
+    #[test]
+    fn test_blocked_client_is_served_from_its_database() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &["SELECT", "1"]);
+        let blpop = parse(&["BLPOP", "q", "0"]).unwrap();
+        assert_eq!(executor.execute_blocking(7, &blpop), None);
+
+        run(&mut executor, &["SELECT", "0"]);
+        run(&mut executor, &["RPUSH", "q", "from-0"]);
+        assert!(executor.take_unblocked().is_empty());
+
+        run(&mut executor, &["RPUSH", "other", "from-0"]);
+        assert_eq!(
+            run(&mut executor, &["MOVE", "other", "1"]),
+            RespValue::Integer(1)
+        );
+        assert!(executor.take_unblocked().is_empty());
+
+        run(&mut executor, &["SELECT", "1"]);
+        run(&mut executor, &["RPUSH", "q", "from-1"]);
+        assert_eq!(
+            executor.take_unblocked(),
+            vec![(7, RespValue::Array(Some(vec![bulk("q"), bulk("from-1")])))]
+        );
+        run(&mut executor, &["SELECT", "0"]);
+        assert_eq!(run(&mut executor, &["LLEN", "q"]), RespValue::Integer(1));
+    }
+}
+
+#[cfg(test)]
+mod binary_key_tests {
+    use super::super::data::SDS;
+    use super::super::{Command, CommandExecutor, RespValue, RespValueZeroCopy};
+    use bytes::Bytes;
+
+    // Invalid UTF-8; both decode lossily to "pb:\u{FFFD}"
+    const KEY_FF: &[u8] = b"pb:\xff";
+    const KEY_FE: &[u8] = b"pb:\xfe";
+
+    fn parse(args: &[&[u8]]) -> Command {
+        let resp = RespValue::Array(Some(
+            args.iter()
+                .map(|a| RespValue::BulkString(Some(a.to_vec())))
+                .collect(),
+        ));
+        Command::from_resp(&resp).unwrap()
+    }
+
+    fn run(executor: &mut CommandExecutor, args: &[&[u8]]) -> RespValue {
+        executor.execute(&parse(args))
+    }
+
+    fn bulk(value: &[u8]) -> RespValue {
+        RespValue::BulkString(Some(value.to_vec()))
+    }
+
+    #[test]
+    fn test_both_parsers_keep_key_bytes() {
+        let args: [&[u8]; 3] = [b"SET", KEY_FF, b"\x00\xc3"];
+        let zc = RespValueZeroCopy::Array(Some(
+            args.iter()
+                .map(|a| RespValueZeroCopy::BulkString(Some(Bytes::copy_from_slice(a))))
+                .collect(),
+        ));
+        let old = parse(&args);
+        let new = Command::from_resp_zero_copy(&zc).unwrap();
+        assert_eq!(format!("{:?}", old), format!("{:?}", new));
+        assert_eq!(old.get_primary_key(), Some(KEY_FF));
+    }
+
+    #[test]
+    fn test_non_utf8_keys_are_distinct() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &[b"SET", KEY_FF, b"ff"]);
+        run(&mut executor, &[b"SET", KEY_FE, b"fe"]);
+
+        assert_eq!(run(&mut executor, &[b"DBSIZE"]), RespValue::Integer(2));
+        assert_eq!(run(&mut executor, &[b"GET", KEY_FF]), bulk(b"ff"));
+        assert_eq!(run(&mut executor, &[b"GET", KEY_FE]), bulk(b"fe"));
+
+        run(&mut executor, &[b"DEL", KEY_FE]);
+        assert_eq!(
+            run(&mut executor, &[b"KEYS", b"pb:*"]),
+            RespValue::Array(Some(vec![bulk(KEY_FF)]))
+        );
+        assert_eq!(
+            run(&mut executor, &[b"KEYS", b"pb:\xff"]),
+            RespValue::Array(Some(vec![bulk(KEY_FF)]))
+        );
+    }
+
+    #[test]
+    fn test_non_utf8_keys_across_types_and_renames() {
+        let mut executor = CommandExecutor::new();
+        run(&mut executor, &[b"HSET", KEY_FF, b"f\x80", b"v"]);
+        assert_eq!(
+            run(&mut executor, &[b"RENAME", KEY_FF, KEY_FE]),
+            RespValue::SimpleString("OK".to_string())
+        );
+        assert_eq!(
+            run(&mut executor, &[b"EXISTS", KEY_FF]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &[b"HGET", KEY_FE, b"f\x80"]), bulk(b"v"));
+
+        run(&mut executor, &[b"RPUSH", b"\x80list", b"a"]);
+        run(&mut executor, &[b"PEXPIRE", b"\x80list", b"100000"]);
+        assert_eq!(
+            run(&mut executor, &[b"TYPE", b"\x80list"]),
+            RespValue::SimpleString("list".to_string())
+        );
+        assert!(matches!(
+            run(&mut executor, &[b"PTTL", b"\x80list"]),
+            RespValue::Integer(ms) if ms > 0
+        ));
+    }
+
+    #[cfg(feature = "lua")]
+    #[test]
+    fn test_lua_keys_are_binary_safe() {
+        let mut executor = CommandExecutor::new();
+        let cmd = Command::Eval {
+            script:
+                "redis.call('SET', KEYS[1], ARGV[1]) return {KEYS[1], redis.call('GET', KEYS[1])}"
+                    .to_string(),
+            keys: vec![SDS::from(KEY_FF)],
+            args: vec![SDS::from(&b"\xfe"[..])],
+        };
+        assert_eq!(
+            executor.execute(&cmd),
+            RespValue::Array(Some(vec![bulk(KEY_FF), bulk(b"\xfe")]))
+        );
+        assert_eq!(
+            run(&mut executor, &[b"EXISTS", KEY_FE]),
+            RespValue::Integer(0)
+        );
+        assert_eq!(run(&mut executor, &[b"GET", KEY_FF]), bulk(b"\xfe"));
+    }
+}
@@ -16,6 +16,7 @@
 
 use super::lattice::ReplicaId;
 use super::state::{ReplicatedValue, ReplicationDelta};
+use crate::redis::SDS;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
@@ -53,7 +54,7 @@ pub struct KeyDigest {
 }
 
 impl KeyDigest {
-    pub fn new(key: &str, value: &ReplicatedValue) -> Self {
+    pub fn new(key: &[u8], value: &ReplicatedValue) -> Self {
         let mut key_hasher = DefaultHasher::new();
         key.hash(&mut key_hasher);
         let key_hash = key_hasher.finish();
@@ -158,7 +159,7 @@ pub struct StateDigest {
 impl StateDigest {
     /// Create a digest from a key-value map
     pub fn from_state(
-        keys: &HashMap<String, ReplicatedValue>,
+        keys: &HashMap<SDS, ReplicatedValue>,
         replica_id: ReplicaId,
         generation: u64,
         depth: usize,
@@ -301,7 +302,7 @@ impl AntiEntropyManager {
     }
 
     /// Generate our current state digest
-    pub fn generate_digest(&self, keys: &HashMap<String, ReplicatedValue>) -> StateDigest {
+    pub fn generate_digest(&self, keys: &HashMap<SDS, ReplicatedValue>) -> StateDigest {
         StateDigest::from_state(
             keys,
             self.replica_id,
@@ -361,13 +362,13 @@ impl AntiEntropyManager {
     pub fn handle_sync_request(
         &mut self,
         request: SyncRequest,
-        our_keys: &HashMap<String, ReplicatedValue>,
+        our_keys: &HashMap<SDS, ReplicatedValue>,
     ) -> SyncResponse {
         let our_digest = self.generate_digest(our_keys);
         let depth = self.config.merkle_tree_depth;
 
         // Determine which keys to send
-        let keys_to_send: Vec<(&String, &ReplicatedValue)> =
+        let keys_to_send: Vec<(&SDS, &ReplicatedValue)> =
             if let Some(ref buckets) = request.requested_buckets {
                 // Only send keys in requested buckets
                 our_keys
@@ -405,7 +406,7 @@ impl AntiEntropyManager {
     /// Get all keys in specified buckets
     pub fn get_keys_in_buckets(
         &self,
-        keys: &HashMap<String, ReplicatedValue>,
+        keys: &HashMap<SDS, ReplicatedValue>,
         buckets: &[usize],
     ) -> Vec<ReplicationDelta> {
         let depth = self.config.merkle_tree_depth;
@@ -470,7 +471,6 @@ pub enum AntiEntropyMessage {
 mod tests {
     use super::super::lattice::LamportClock;
     use super::*;
-    use crate::redis::SDS;
 
     fn make_value(s: &str, timestamp: u64, replica: ReplicaId) -> ReplicatedValue {
         let clock = LamportClock {
@@ -484,13 +484,13 @@ mod tests {
     fn test_key_digest() {
         let r1 = ReplicaId::new(1);
         let value = make_value("test", 100, r1);
-        let digest1 = KeyDigest::new("key1", &value);
-        let digest2 = KeyDigest::new("key1", &value);
+        let digest1 = KeyDigest::new(b"key1", &value);
+        let digest2 = KeyDigest::new(b"key1", &value);
 
         assert_eq!(digest1, digest2);
 
         let value2 = make_value("different", 100, r1);
-        let digest3 = KeyDigest::new("key1", &value2);
+        let digest3 = KeyDigest::new(b"key1", &value2);
         assert_ne!(digest1.value_hash, digest3.value_hash);
     }
 
@@ -498,8 +498,8 @@ mod tests {
     fn test_state_digest_identical() {
         let r1 = ReplicaId::new(1);
         let mut keys = HashMap::new();
-        keys.insert("key1".to_string(), make_value("value1", 1, r1));
-        keys.insert("key2".to_string(), make_value("value2", 2, r1));
+        keys.insert("key1".into(), make_value("value1", 1, r1));
+        keys.insert("key2".into(), make_value("value2", 2, r1));
 
         let digest1 = StateDigest::from_state(&keys, r1, 1, 4);
         let digest2 = StateDigest::from_state(&keys, r1, 1, 4);
@@ -514,11 +514,11 @@ mod tests {
         let r2 = ReplicaId::new(2);
 
         let mut keys1 = HashMap::new();
-        keys1.insert("key1".to_string(), make_value("value1", 1, r1));
+        keys1.insert("key1".into(), make_value("value1", 1, r1));
 
         let mut keys2 = HashMap::new();
-        keys2.insert("key1".to_string(), make_value("value1", 1, r1));
-        keys2.insert("key2".to_string(), make_value("value2", 2, r2));
+        keys2.insert("key1".into(), make_value("value1", 1, r1));
+        keys2.insert("key2".into(), make_value("value2", 2, r2));
 
         let digest1 = StateDigest::from_state(&keys1, r1, 1, 4);
         let digest2 = StateDigest::from_state(&keys2, r2, 1, 4);
@@ -532,10 +532,10 @@ mod tests {
         let r2 = ReplicaId::new(2);
 
         let mut keys1 = HashMap::new();
-        keys1.insert("key1".to_string(), make_value("value1", 1, r1));
+        keys1.insert("key1".into(), make_value("value1", 1, r1));
 
         let mut keys2 = HashMap::new();
-        keys2.insert("key1".to_string(), make_value("different", 2, r2));
+        keys2.insert("key1".into(), make_value("different", 2, r2));
 
         let digest1 = StateDigest::from_state(&keys1, r1, 1, 4);
         let digest2 = StateDigest::from_state(&keys2, r2, 1, 4);
@@ -554,11 +554,11 @@ mod tests {
 
         // Node 1 has key1
         let mut keys1 = HashMap::new();
-        keys1.insert("key1".to_string(), make_value("value1", 1, r1));
+        keys1.insert("key1".into(), make_value("value1", 1, r1));
 
         // Node 2 has key2
         let mut keys2 = HashMap::new();
-        keys2.insert("key2".to_string(), make_value("value2", 2, r2));
+        keys2.insert("key2".into(), make_value("value2", 2, r2));
 
         let digest1 = manager1.generate_digest(&keys1);
         let digest2 = manager2.generate_digest(&keys2);
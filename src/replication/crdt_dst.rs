@@ -17,7 +17,7 @@
 //! }
 //! ```
 
-use super::lattice::{GCounter, ORSet, PNCounter, ReplicaId, VectorClock};
+use super::lattice::{GCounter, HyperLogLog, ORSet, PNCounter, ReplicaId, VectorClock};
 use crate::io::simulation::SimulatedRng;
 use crate::io::Rng;
 use std::collections::HashMap;
@@ -462,6 +462,101 @@ impl ORSetDSTHarness {
     }
 }
 
+// =============================================================================
+// HyperLogLog DST Harness
+// =============================================================================
+
+/// DST harness for HyperLogLog CRDT
+pub struct HyperLogLogDSTHarness {
+    config: CRDTDSTConfig,
+    rng: SimulatedRng,
+    replicas: Vec<HyperLogLog>,
+    /// Single HLL that saw every add, which every replica must converge to
+    reference: HyperLogLog,
+    result: CRDTDSTResult,
+}
+
+impl HyperLogLogDSTHarness {
+    pub fn new(config: CRDTDSTConfig) -> Self {
+        let rng = SimulatedRng::new(config.seed);
+        let replicas = (0..config.num_replicas)
+            .map(|_| HyperLogLog::new())
+            .collect();
+
+        HyperLogLogDSTHarness {
+            result: CRDTDSTResult::new(config.seed),
+            config,
+            rng,
+            replicas,
+            reference: HyperLogLog::new(),
+        }
+    }
+
+    pub fn run(&mut self, operations: usize) {
+        for _ in 0..operations {
+            let replica_idx = self.rng.gen_range(0, self.config.num_replicas as u64) as usize;
+            // Overlapping element space so replicas add duplicates
+            let element = format!("elem:{}", self.rng.gen_range(0, 1000));
+
+            self.replicas[replica_idx].add(element.as_bytes());
+            self.reference.add(element.as_bytes());
+
+            #[cfg(debug_assertions)]
+            self.replicas[replica_idx].verify_invariants();
+
+            self.result.total_operations += 1;
+            *self.result.ops_per_replica.entry(replica_idx).or_insert(0) += 1;
+        }
+    }
+
+    pub fn sync_all(&mut self) {
+        let max_rounds = 5;
+        for _round in 0..max_rounds {
+            for i in 0..self.replicas.len() {
+                for j in (i + 1)..self.replicas.len() {
+                    if self.rng.gen_bool(self.config.message_drop_prob) {
+                        self.result.messages_dropped += 1;
+                        continue;
+                    }
+
+                    let merged = self.replicas[i].merge(&self.replicas[j]);
+                    self.replicas[i] = merged.clone();
+                    self.replicas[j] = merged;
+                    self.result.syncs_performed += 1;
+                }
+            }
+        }
+    }
+
+    pub fn check_convergence(&mut self) {
+        let expected_count = self.reference.count();
+
+        for (i, replica) in self.replicas.iter().enumerate() {
+            #[cfg(debug_assertions)]
+            replica.verify_invariants();
+
+            if *replica != self.reference {
+                self.result.invariant_violations.push(format!(
+                    "Replica {} registers differ from the union of all adds (count {} vs {})",
+                    i,
+                    replica.count(),
+                    expected_count
+                ));
+            }
+        }
+
+        self.result.converged = self.result.invariant_violations.is_empty();
+    }
+
+    pub fn result(&self) -> &CRDTDSTResult {
+        &self.result
+    }
+
+    pub fn into_result(self) -> CRDTDSTResult {
+        self.result
+    }
+}
+
 // =============================================================================
 // VectorClock DST Harness
 // =============================================================================
@@ -635,6 +730,30 @@ pub fn run_orset_batch(
     results
 }
 
+/// Run a batch of HyperLogLog DST tests
+pub fn run_hyperloglog_batch(
+    base_seed: u64,
+    count: usize,
+    ops_per_run: usize,
+    config_fn: impl Fn(u64) -> CRDTDSTConfig,
+) -> Vec<CRDTDSTResult> {
+    let mut results = Vec::with_capacity(count);
+
+    for i in 0..count {
+        let seed = base_seed + i as u64;
+        let config = config_fn(seed);
+
+        let mut harness = HyperLogLogDSTHarness::new(config);
+        harness.run(ops_per_run);
+        harness.sync_all();
+        harness.check_convergence();
+
+        results.push(harness.into_result());
+    }
+
+    results
+}
+
 /// Run a batch of VectorClock DST tests
 pub fn run_vectorclock_batch(
     base_seed: u64,
@@ -798,6 +917,39 @@ mod tests {
         );
     }
 
+    // =========================================================================
+    // HyperLogLog Tests
+    // =========================================================================
+
+    #[test]
+    fn test_hyperloglog_dst_single_calm() {
+        let config = CRDTDSTConfig::calm(42);
+        let mut harness = HyperLogLogDSTHarness::new(config);
+
+        harness.run(100);
+        harness.sync_all();
+        harness.check_convergence();
+
+        let result = harness.result();
+        assert!(
+            result.is_success(),
+            "Calm should converge: {:?}",
+            result.invariant_violations
+        );
+    }
+
+    #[test]
+    fn test_hyperloglog_dst_100_seeds() {
+        let results = run_hyperloglog_batch(0, 100, 100, CRDTDSTConfig::calm);
+        let summary = summarize_batch(&results);
+        println!("HyperLogLog 100 seeds:\n{}", summary);
+
+        assert!(
+            results.iter().all(|r| r.is_success()),
+            "All calm runs should converge"
+        );
+    }
+
     // =========================================================================
     // VectorClock Tests
     // =========================================================================
@@ -196,7 +196,7 @@ impl GossipRouter {
 
         let mut selective_msgs = 0;
         for key in sample_keys {
-            let targets = ring.get_gossip_targets(key, self.my_replica);
+            let targets = ring.get_gossip_targets(key.as_bytes(), self.my_replica);
             selective_msgs += targets.len();
         }
 
@@ -239,7 +239,7 @@ mod tests {
         let replica_id = ReplicaId::new(1);
         let timestamp = LamportClock::new(replica_id);
         let value = ReplicatedValue::with_value(SDS::from_str("test"), timestamp);
-        ReplicationDelta::new(key.to_string(), value, replica_id)
+        ReplicationDelta::new(SDS::from(key), value, replica_id)
     }
 
     #[test]
@@ -70,7 +70,7 @@ impl HashRing {
     }
 
     /// Hash a key to a position on the ring
-    fn hash_key(key: &str) -> u64 {
+    fn hash_key(key: &[u8]) -> u64 {
         let mut hasher = DefaultHasher::new();
         key.hash(&mut hasher);
         hasher.finish()
@@ -112,7 +112,7 @@ impl HashRing {
     }
 
     /// Get the N nodes responsible for this key (in preference order)
-    pub fn get_replicas(&self, key: &str) -> Vec<ReplicaId> {
+    pub fn get_replicas(&self, key: &[u8]) -> Vec<ReplicaId> {
         self.get_replicas_with_rf(key, self.replication_factor)
     }
 
@@ -120,7 +120,7 @@ impl HashRing {
     ///
     /// This allows hot keys to have higher RF than normal keys.
     /// The RF is capped at the number of physical nodes.
-    pub fn get_replicas_with_rf(&self, key: &str, rf: usize) -> Vec<ReplicaId> {
+    pub fn get_replicas_with_rf(&self, key: &[u8], rf: usize) -> Vec<ReplicaId> {
         if self.ring.is_empty() {
             return vec![];
         }
@@ -156,22 +156,22 @@ impl HashRing {
     }
 
     /// Check if this node should store the key with custom RF
-    pub fn is_responsible_with_rf(&self, key: &str, node: ReplicaId, rf: usize) -> bool {
+    pub fn is_responsible_with_rf(&self, key: &[u8], node: ReplicaId, rf: usize) -> bool {
         self.get_replicas_with_rf(key, rf).contains(&node)
     }
 
     /// Check if this node should store the key
-    pub fn is_responsible(&self, key: &str, node: ReplicaId) -> bool {
+    pub fn is_responsible(&self, key: &[u8], node: ReplicaId) -> bool {
         self.get_replicas(key).contains(&node)
     }
 
     /// Get the primary (first) replica for a key
-    pub fn get_primary(&self, key: &str) -> Option<ReplicaId> {
+    pub fn get_primary(&self, key: &[u8]) -> Option<ReplicaId> {
         self.get_replicas(key).into_iter().next()
     }
 
     /// Get all nodes that need deltas for a key (excluding sender)
-    pub fn get_gossip_targets(&self, key: &str, sender: ReplicaId) -> Vec<ReplicaId> {
+    pub fn get_gossip_targets(&self, key: &[u8], sender: ReplicaId) -> Vec<ReplicaId> {
         self.get_replicas(key)
             .into_iter()
             .filter(|r| *r != sender)
@@ -209,7 +209,7 @@ impl HashRing {
             std::collections::HashMap::new();
 
         for key in sample_keys {
-            for replica in self.get_replicas(key) {
+            for replica in self.get_replicas(key.as_bytes()) {
                 *node_counts.entry(replica).or_insert(0) += 1;
             }
         }
@@ -276,7 +276,7 @@ mod tests {
         ];
         let ring = HashRing::new(nodes, 50, 3);
 
-        let replicas = ring.get_replicas("test_key");
+        let replicas = ring.get_replicas(b"test_key");
         assert_eq!(replicas.len(), 3);
 
         // All replicas should be unique
@@ -289,17 +289,17 @@ mod tests {
         let nodes = vec![ReplicaId::new(1), ReplicaId::new(2), ReplicaId::new(3)];
         let ring = HashRing::new(nodes, 50, 2);
 
-        let replicas = ring.get_replicas("my_key");
+        let replicas = ring.get_replicas(b"my_key");
 
         // Exactly 2 nodes should be responsible
         let responsible_count = (1..=3)
-            .filter(|&id| ring.is_responsible("my_key", ReplicaId::new(id)))
+            .filter(|&id| ring.is_responsible(b"my_key", ReplicaId::new(id)))
             .count();
         assert_eq!(responsible_count, 2);
 
         // Check consistency with get_replicas
         for r in &replicas {
-            assert!(ring.is_responsible("my_key", *r));
+            assert!(ring.is_responsible(b"my_key", *r));
         }
     }
 
@@ -310,8 +310,8 @@ mod tests {
         let ring2 = HashRing::new(nodes, 50, 3);
 
         // Same key should map to same replicas
-        assert_eq!(ring1.get_replicas("key1"), ring2.get_replicas("key1"));
-        assert_eq!(ring1.get_replicas("key2"), ring2.get_replicas("key2"));
+        assert_eq!(ring1.get_replicas(b"key1"), ring2.get_replicas(b"key1"));
+        assert_eq!(ring1.get_replicas(b"key2"), ring2.get_replicas(b"key2"));
     }
 
     #[test]
@@ -335,15 +335,16 @@ mod tests {
         let nodes = vec![ReplicaId::new(1), ReplicaId::new(2), ReplicaId::new(3)];
         let ring = HashRing::new(nodes, 50, 3);
 
-        let targets = ring.get_gossip_targets("key", ReplicaId::new(1));
+        let targets = ring.get_gossip_targets(b"key", ReplicaId::new(1));
 
         // Should not include sender
         assert!(
-            !targets.contains(&ReplicaId::new(1)) || !ring.is_responsible("key", ReplicaId::new(1))
+            !targets.contains(&ReplicaId::new(1))
+                || !ring.is_responsible(b"key", ReplicaId::new(1))
         );
 
         // Should be a subset of replicas
-        let replicas = ring.get_replicas("key");
+        let replicas = ring.get_replicas(b"key");
         for t in &targets {
             assert!(replicas.contains(t));
         }
@@ -391,7 +392,7 @@ mod tests {
         let nodes = vec![ReplicaId::new(1), ReplicaId::new(2)];
         let ring = HashRing::new(nodes, 50, 5);
 
-        let replicas = ring.get_replicas("key");
+        let replicas = ring.get_replicas(b"key");
         assert_eq!(replicas.len(), 2); // Capped at node count
     }
 
@@ -399,18 +400,18 @@ mod tests {
     fn test_empty_ring() {
         let ring = HashRing::new(vec![], 50, 3);
 
-        assert!(ring.get_replicas("key").is_empty());
-        assert!(!ring.is_responsible("key", ReplicaId::new(1)));
+        assert!(ring.get_replicas(b"key").is_empty());
+        assert!(!ring.is_responsible(b"key", ReplicaId::new(1)));
     }
 
     #[test]
     fn test_single_node() {
         let ring = HashRing::new(vec![ReplicaId::new(1)], 50, 3);
 
-        let replicas = ring.get_replicas("key");
+        let replicas = ring.get_replicas(b"key");
         assert_eq!(replicas.len(), 1);
         assert_eq!(replicas[0], ReplicaId::new(1));
-        assert!(ring.is_responsible("key", ReplicaId::new(1)));
+        assert!(ring.is_responsible(b"key", ReplicaId::new(1)));
     }
 
     #[test]
@@ -419,11 +420,11 @@ mod tests {
         let ring = HashRing::new(nodes, 50, 3); // Default RF=3
 
         // Normal key gets RF=3
-        let normal_replicas = ring.get_replicas("normal_key");
+        let normal_replicas = ring.get_replicas(b"normal_key");
         assert_eq!(normal_replicas.len(), 3);
 
         // Hot key can get RF=5
-        let hot_replicas = ring.get_replicas_with_rf("hot_key", 5);
+        let hot_replicas = ring.get_replicas_with_rf(b"hot_key", 5);
         assert_eq!(hot_replicas.len(), 5);
 
         // All replicas should be unique
@@ -431,7 +432,7 @@ mod tests {
         assert_eq!(unique.len(), 5);
 
         // RF > node count is capped
-        let over_replicas = ring.get_replicas_with_rf("key", 10);
+        let over_replicas = ring.get_replicas_with_rf(b"key", 10);
         assert_eq!(over_replicas.len(), 5); // Capped at 5 nodes
     }
 
@@ -442,10 +443,10 @@ mod tests {
 
         // With higher RF, more nodes become responsible
         let responsible_rf3: Vec<_> = (1..=5)
-            .filter(|&id| ring.is_responsible("key", ReplicaId::new(id)))
+            .filter(|&id| ring.is_responsible(b"key", ReplicaId::new(id)))
             .collect();
         let responsible_rf5: Vec<_> = (1..=5)
-            .filter(|&id| ring.is_responsible_with_rf("key", ReplicaId::new(id), 5))
+            .filter(|&id| ring.is_responsible_with_rf(b"key", ReplicaId::new(id), 5))
             .collect();
 
         assert_eq!(responsible_rf3.len(), 3);
@@ -1,3 +1,4 @@
+use crate::redis::{hll_estimate, hll_from_registers, hll_pattern, hll_registers, HLL_REGISTERS};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
@@ -62,6 +63,10 @@ pub struct LwwRegister<T> {
     pub value: Option<T>,
     pub timestamp: LamportClock,
     pub tombstone: bool,
+    /// Unix-ms deadline of the value (hash field TTL); None never expires.
+    /// Part of the register, so a TTL change wins or loses with its write.
+    #[serde(default)]
+    pub expiry_ms: Option<u64>,
 }
 
 impl<T: Clone> LwwRegister<T> {
@@ -93,6 +98,7 @@ impl<T: Clone> LwwRegister<T> {
             value: None,
             timestamp: LamportClock::new(replica_id),
             tombstone: false,
+            expiry_ms: None,
         }
     }
 
@@ -101,14 +107,21 @@ impl<T: Clone> LwwRegister<T> {
             value: Some(value),
             timestamp,
             tombstone: false,
+            expiry_ms: None,
         }
     }
 
     pub fn set(&mut self, value: T, clock: &mut LamportClock) {
+        self.set_with_expiry(value, None, clock);
+    }
+
+    /// Write a value together with its deadline as one LWW update
+    pub fn set_with_expiry(&mut self, value: T, expiry_ms: Option<u64>, clock: &mut LamportClock) {
         let ts = clock.tick();
         self.value = Some(value);
         self.timestamp = ts;
         self.tombstone = false;
+        self.expiry_ms = expiry_ms;
     }
 
     pub fn delete(&mut self, clock: &mut LamportClock) {
@@ -116,6 +129,7 @@ impl<T: Clone> LwwRegister<T> {
         self.value = None;
         self.timestamp = ts;
         self.tombstone = true;
+        self.expiry_ms = None;
     }
 
     pub fn merge(&self, other: &Self) -> Self {
@@ -739,6 +753,99 @@ impl<T: Clone + Eq + Hash> PartialEq for ORSet<T> {
 
 impl<T: Clone + Eq + Hash> Eq for ORSet<T> {}
 
+// ============================================================================
+// HyperLogLog - Cardinality estimator
+// ============================================================================
+
+/// HyperLogLog CRDT. Registers only grow and merge takes the register-wise
+/// max, so it is a join-semilattice and replicas converge without coordination.
+/// Converts to and from Redis's HLL string encoding.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HyperLogLog {
+    /// VOPR: Verify all invariants hold for this HLL
+    #[cfg(debug_assertions)]
+    pub fn verify_invariants(&self) {
+        // Invariant 1: exactly one register per bucket
+        debug_assert_eq!(
+            self.registers.len(),
+            HLL_REGISTERS,
+            "Invariant violated: HLL must have {} registers",
+            HLL_REGISTERS
+        );
+
+        // Invariant 2: registers fit in 6 bits
+        debug_assert!(
+            self.registers.iter().all(|&r| r < 64),
+            "Invariant violated: HLL register exceeds 6 bits"
+        );
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline(always)]
+    pub fn verify_invariants(&self) {}
+
+    pub fn new() -> Self {
+        HyperLogLog {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+
+    /// Add an element; true if the estimate may have changed
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let (index, count) = hll_pattern(element);
+        if count > self.registers[index] {
+            self.registers[index] = count;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Estimated number of distinct elements added
+    pub fn count(&self) -> u64 {
+        hll_estimate(&self.registers)
+    }
+
+    /// Merge with another HLL (take max per register)
+    pub fn merge(&self, other: &Self) -> Self {
+        let registers = self
+            .registers
+            .iter()
+            .zip(&other.registers)
+            .map(|(&a, &b)| a.max(b))
+            .collect();
+        let merged = HyperLogLog { registers };
+        merged.verify_invariants();
+        merged
+    }
+
+    /// Decode a Redis HLL string (as stored by PFADD/PFMERGE)
+    pub fn from_redis_bytes(bytes: &[u8]) -> Option<Self> {
+        let registers = hll_registers(bytes).ok()?;
+        Some(HyperLogLog { registers })
+    }
+
+    /// Encode as the HLL string Redis's PFMERGE would write for these registers
+    pub fn to_redis_bytes(&self) -> Vec<u8> {
+        hll_from_registers(&self.registers)
+    }
+
+    /// Check if no element was ever added
+    pub fn is_empty(&self) -> bool {
+        self.registers.iter().all(|&r| r == 0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -763,6 +870,30 @@ mod tests {
         assert_eq!(merged.get(), Some(&"value2_updated".to_string()));
     }
 
+    #[test]
+    fn test_lww_register_expiry_merges_with_value() {
+        let r1 = ReplicaId::new(1);
+        let r2 = ReplicaId::new(2);
+        let mut clock1 = LamportClock::new(r1);
+        let mut clock2 = LamportClock::new(r2);
+
+        let mut reg1: LwwRegister<String> = LwwRegister::new(r1);
+        reg1.set_with_expiry("v".to_string(), Some(5_000), &mut clock1);
+        let mut reg2 = reg1.clone();
+        clock2.update(&reg1.timestamp);
+
+        // A later plain write clears the TTL and wins the merge
+        reg2.set("v2".to_string(), &mut clock2);
+        let merged = reg1.merge(&reg2);
+        assert_eq!(merged.get(), Some(&"v2".to_string()));
+        assert_eq!(merged.expiry_ms, None);
+        assert_eq!(reg2.merge(&reg1).expiry_ms, None);
+
+        // Deleting drops the deadline with the value
+        reg1.delete(&mut clock1);
+        assert_eq!(reg1.expiry_ms, None);
+    }
+
     #[test]
     fn test_vector_clock_happens_before() {
         let r1 = ReplicaId::new(1);
@@ -1091,4 +1222,54 @@ mod tests {
         assert!(merged1.contains(&"a".to_string()));
         assert!(merged1.contains(&"b".to_string()));
     }
+
+    // ========================================================================
+    // HyperLogLog Tests
+    // ========================================================================
+
+    #[test]
+    fn test_hyperloglog_basic() {
+        let mut hll = HyperLogLog::new();
+        assert!(hll.is_empty());
+        assert_eq!(hll.count(), 0);
+
+        for i in 0..1000 {
+            hll.add(format!("visitor:{}", i).as_bytes());
+        }
+        assert!(!hll.add(b"visitor:1"));
+        let count = hll.count();
+        assert!((980..=1020).contains(&count), "estimate {}", count);
+    }
+
+    #[test]
+    fn test_hyperloglog_merge_laws() {
+        let mut a = HyperLogLog::new();
+        let mut b = HyperLogLog::new();
+        let mut c = HyperLogLog::new();
+        for i in 0..500 {
+            a.add(format!("a{}", i).as_bytes());
+            b.add(format!("b{}", i).as_bytes());
+            c.add(format!("a{}", i * 2).as_bytes());
+        }
+
+        assert_eq!(a.merge(&b), b.merge(&a));
+        assert_eq!(a.merge(&b).merge(&c), a.merge(&b.merge(&c)));
+        assert_eq!(a.merge(&a), a);
+
+        let union = a.merge(&b);
+        let count = union.count();
+        assert!((980..=1020).contains(&count), "estimate {}", count);
+    }
+
+    #[test]
+    fn test_hyperloglog_redis_encoding_round_trip() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..200 {
+            hll.add(format!("k{}", i).as_bytes());
+        }
+        let bytes = hll.to_redis_bytes();
+        assert_eq!(&bytes[..4], b"HYLL");
+        assert_eq!(HyperLogLog::from_redis_bytes(&bytes), Some(hll));
+        assert_eq!(HyperLogLog::from_redis_bytes(b"not an hll"), None);
+    }
 }
@@ -1,6 +1,7 @@
 use super::config::ConsistencyLevel;
 use super::lattice::{
-    GCounter, GSet, LamportClock, LwwRegister, ORSet, PNCounter, ReplicaId, VectorClock,
+    GCounter, GSet, HyperLogLog, LamportClock, LwwRegister, ORSet, PNCounter, ReplicaId,
+    VectorClock,
 };
 use crate::redis::SDS;
 use serde::{Deserialize, Serialize};
@@ -52,6 +53,8 @@ pub enum CrdtValue {
     ORSet(ORSet<String>),
     /// Hash map with per-field LWW semantics
     Hash(HashMap<String, LwwRegister<SDS>>),
+    /// HyperLogLog cardinality estimator (register-wise max)
+    HyperLogLog(HyperLogLog),
 }
 
 impl CrdtValue {
@@ -85,6 +88,11 @@ impl CrdtValue {
         CrdtValue::Hash(HashMap::new())
     }
 
+    /// Create a new HyperLogLog
+    pub fn new_hyperloglog() -> Self {
+        CrdtValue::HyperLogLog(HyperLogLog::new())
+    }
+
     /// Try to merge two CrdtValues of the same type.
     /// Returns an error if types don't match - this makes type conflicts explicit
     /// rather than silently discarding data (TigerStyle: explicit error handling).
@@ -111,6 +119,9 @@ impl CrdtValue {
                 }
                 Ok(CrdtValue::Hash(merged))
             }
+            (CrdtValue::HyperLogLog(a), CrdtValue::HyperLogLog(b)) => {
+                Ok(CrdtValue::HyperLogLog(a.merge(b)))
+            }
             // Type mismatch: return explicit error instead of silently discarding data
             _ => Err(CrdtTypeMismatchError {
                 self_type: self.type_name(),
@@ -192,6 +203,7 @@ impl CrdtValue {
             CrdtValue::GSet(_) => "gset",
             CrdtValue::ORSet(_) => "orset",
             CrdtValue::Hash(_) => "hash",
+            CrdtValue::HyperLogLog(_) => "hyperloglog",
         }
     }
 
@@ -295,6 +307,22 @@ impl CrdtValue {
             _ => None,
         }
     }
+
+    /// Get as HyperLogLog
+    pub fn as_hyperloglog(&self) -> Option<&HyperLogLog> {
+        match self {
+            CrdtValue::HyperLogLog(hll) => Some(hll),
+            _ => None,
+        }
+    }
+
+    /// Get as mutable HyperLogLog
+    pub fn as_hyperloglog_mut(&mut self) -> Option<&mut HyperLogLog> {
+        match self {
+            CrdtValue::HyperLogLog(hll) => Some(hll),
+            _ => None,
+        }
+    }
 }
 
 // ============================================================================
@@ -480,9 +508,20 @@ impl ReplicatedValue {
     }
 
     /// Set a field in the hash (creates Hash if needed)
+    pub fn hash_set(&mut self, field: String, value: SDS, clock: &mut LamportClock) {
+        self.hash_set_with_expiry(field, value, None, clock);
+    }
+
+    /// Set a field and its TTL (unix-ms deadline) in one register write
     ///
     /// TigerStyle: Preconditions checked, postconditions verified
-    pub fn hash_set(&mut self, field: String, value: SDS, clock: &mut LamportClock) {
+    pub fn hash_set_with_expiry(
+        &mut self,
+        field: String,
+        value: SDS,
+        expiry_ms: Option<u64>,
+        clock: &mut LamportClock,
+    ) {
         // TigerStyle: Preconditions
         debug_assert!(!field.is_empty(), "Precondition: field must not be empty");
 
@@ -499,7 +538,7 @@ impl ReplicatedValue {
             let lww = hash
                 .entry(field.clone())
                 .or_insert_with(|| LwwRegister::new(clock.replica_id));
-            lww.set(value, clock);
+            lww.set_with_expiry(value, expiry_ms, clock);
         }
         self.timestamp = *clock;
 
@@ -520,6 +559,13 @@ impl ReplicatedValue {
                 Some(expected_value.as_bytes()),
                 "Postcondition: field value must match set value"
             );
+            debug_assert_eq!(
+                self.get_hash()
+                    .and_then(|h| h.get(&field))
+                    .and_then(|lww| lww.expiry_ms),
+                expiry_ms,
+                "Postcondition: field expiry must match set expiry"
+            );
         }
     }
 
@@ -577,17 +623,27 @@ impl ReplicatedValue {
             }
         }
     }
+
+    /// Check if this is a HyperLogLog value
+    pub fn is_hyperloglog(&self) -> bool {
+        matches!(self.crdt, CrdtValue::HyperLogLog(_))
+    }
+
+    /// Get the HyperLogLog (if this is a HyperLogLog value)
+    pub fn get_hyperloglog(&self) -> Option<&HyperLogLog> {
+        self.crdt.as_hyperloglog()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReplicationDelta {
-    pub key: String,
+    pub key: SDS,
     pub value: ReplicatedValue,
     pub source_replica: ReplicaId,
 }
 
 impl ReplicationDelta {
-    pub fn new(key: String, value: ReplicatedValue, source_replica: ReplicaId) -> Self {
+    pub fn new(key: SDS, value: ReplicatedValue, source_replica: ReplicaId) -> Self {
         ReplicationDelta {
             key,
             value,
@@ -603,7 +659,7 @@ pub struct ShardReplicaState {
     pub vector_clock: VectorClock,
     pub consistency_level: ConsistencyLevel,
     pub pending_deltas: Vec<ReplicationDelta>,
-    pub replicated_keys: HashMap<String, ReplicatedValue>,
+    pub replicated_keys: HashMap<SDS, ReplicatedValue>,
 }
 
 impl ShardReplicaState {
@@ -620,10 +676,11 @@ impl ShardReplicaState {
 
     pub fn record_write(
         &mut self,
-        key: String,
+        key: impl Into<SDS>,
         value: SDS,
         expiry_ms: Option<u64>,
     ) -> ReplicationDelta {
+        let key = key.into();
         let mut replicated = self
             .replicated_keys
             .remove(&key)
@@ -644,7 +701,8 @@ impl ShardReplicaState {
         delta
     }
 
-    pub fn record_delete(&mut self, key: String) -> Option<ReplicationDelta> {
+    pub fn record_delete(&mut self, key: impl Into<SDS>) -> Option<ReplicationDelta> {
+        let key = key.into();
         if let Some(mut replicated) = self.replicated_keys.remove(&key) {
             replicated.delete(&mut self.lamport_clock);
             let delta = ReplicationDelta::new(key.clone(), replicated.clone(), self.replica_id);
@@ -659,11 +717,7 @@ impl ShardReplicaState {
     /// Record a hash field write (HSET)
     ///
     /// TigerStyle: Preconditions checked, postconditions verified
-    pub fn record_hash_write(
-        &mut self,
-        key: String,
-        fields: Vec<(String, SDS)>,
-    ) -> ReplicationDelta {
+    pub fn record_hash_write(&mut self, key: SDS, fields: Vec<(String, SDS)>) -> ReplicationDelta {
         // TigerStyle: Preconditions
         debug_assert!(!key.is_empty(), "Precondition: key must not be empty");
         debug_assert!(!fields.is_empty(), "Precondition: fields must not be empty");
@@ -733,7 +787,7 @@ impl ShardReplicaState {
     /// TigerStyle: Preconditions checked, postconditions verified
     pub fn record_hash_delete(
         &mut self,
-        key: String,
+        key: SDS,
         fields: Vec<String>,
     ) -> Option<ReplicationDelta> {
         // TigerStyle: Preconditions
@@ -785,6 +839,81 @@ impl ShardReplicaState {
         None
     }
 
+    /// Record the resulting state of hash fields after HSETNX, HINCRBYFLOAT,
+    /// HGETDEL, HGETEX, HEXPIRE or HPERSIST: `Some((value, expiry_ms))` writes
+    /// the field with its unix-ms TTL, `None` tombstones it. Returns None when
+    /// there is no hash to record into.
+    pub fn record_hash_fields(
+        &mut self,
+        key: SDS,
+        fields: Vec<(String, Option<(SDS, Option<u64>)>)>,
+    ) -> Option<ReplicationDelta> {
+        // TigerStyle: Preconditions
+        debug_assert!(!key.is_empty(), "Precondition: key must not be empty");
+        debug_assert!(!fields.is_empty(), "Precondition: fields must not be empty");
+
+        let writes = fields.iter().any(|(_, state)| state.is_some());
+        let mut replicated = match self.replicated_keys.remove(&key) {
+            Some(existing) if existing.is_hash() || writes => existing,
+            Some(existing) => {
+                self.replicated_keys.insert(key, existing);
+                return None;
+            }
+            None if writes => ReplicatedValue::new(self.replica_id),
+            None => return None,
+        };
+        if !replicated.is_hash() {
+            replicated.crdt = CrdtValue::new_hash();
+        }
+
+        for (field, state) in fields {
+            match state {
+                Some((value, expiry_ms)) => replicated.hash_set_with_expiry(
+                    field,
+                    value,
+                    expiry_ms,
+                    &mut self.lamport_clock,
+                ),
+                None => replicated.hash_delete(&field, &mut self.lamport_clock),
+            }
+        }
+
+        let delta = ReplicationDelta::new(key.clone(), replicated.clone(), self.replica_id);
+        self.replicated_keys.insert(key, replicated);
+        self.pending_deltas.push(delta.clone());
+
+        // TigerStyle: Postcondition
+        debug_assert!(
+            delta.value.is_hash(),
+            "Postcondition: recorded value must be a hash"
+        );
+        Some(delta)
+    }
+
+    /// Record an HLL write (PFADD, PFMERGE). The key's registers are joined
+    /// into the replicated HLL, so concurrent writes merge without conflicts.
+    pub fn record_hyperloglog_write(&mut self, key: SDS, hll: &HyperLogLog) -> ReplicationDelta {
+        // TigerStyle: Preconditions
+        debug_assert!(!key.is_empty(), "Precondition: key must not be empty");
+
+        let mut replicated = self.replicated_keys.remove(&key).unwrap_or_else(|| {
+            ReplicatedValue::with_crdt(CrdtValue::new_hyperloglog(), self.replica_id)
+        });
+
+        // A key that held another type is overwritten, as PFADD on a new key would
+        let merged = match replicated.get_hyperloglog() {
+            Some(existing) => existing.merge(hll),
+            None => hll.clone(),
+        };
+        replicated.crdt = CrdtValue::HyperLogLog(merged);
+        replicated.timestamp = self.lamport_clock.tick();
+
+        let delta = ReplicationDelta::new(key.clone(), replicated.clone(), self.replica_id);
+        self.replicated_keys.insert(key, replicated);
+        self.pending_deltas.push(delta.clone());
+        delta
+    }
+
     pub fn apply_remote_delta(&mut self, delta: ReplicationDelta) {
         // Update our clock from the delta's timestamp
         self.lamport_clock.update(&delta.value.timestamp);
@@ -801,8 +930,8 @@ impl ShardReplicaState {
         std::mem::take(&mut self.pending_deltas)
     }
 
-    pub fn get_replicated(&self, key: &str) -> Option<&ReplicatedValue> {
-        self.replicated_keys.get(key)
+    pub fn get_replicated(&self, key: impl AsRef<[u8]>) -> Option<&ReplicatedValue> {
+        self.replicated_keys.get(key.as_ref())
     }
 }
 
@@ -824,8 +953,8 @@ mod tests {
         state1.apply_remote_delta(delta2.clone());
         state2.apply_remote_delta(delta1.clone());
 
-        let val1 = state1.get_replicated("key1").unwrap().get();
-        let val2 = state2.get_replicated("key1").unwrap().get();
+        let val1 = state1.get_replicated(b"key1").unwrap().get();
+        let val2 = state2.get_replicated(b"key1").unwrap().get();
         assert_eq!(val1, val2);
     }
 
@@ -843,11 +972,11 @@ mod tests {
 
         // Write different fields from different replicas
         let delta1 = state1.record_hash_write(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![("field1".to_string(), SDS::from_str("value1"))],
         );
         let delta2 = state2.record_hash_write(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![("field2".to_string(), SDS::from_str("value2"))],
         );
 
@@ -856,8 +985,16 @@ mod tests {
         state2.apply_remote_delta(delta1.clone());
 
         // Both replicas should have both fields
-        let hash1 = state1.get_replicated("myhash").unwrap().get_hash().unwrap();
-        let hash2 = state2.get_replicated("myhash").unwrap().get_hash().unwrap();
+        let hash1 = state1
+            .get_replicated(b"myhash")
+            .unwrap()
+            .get_hash()
+            .unwrap();
+        let hash2 = state2
+            .get_replicated(b"myhash")
+            .unwrap()
+            .get_hash()
+            .unwrap();
 
         assert_eq!(hash1.len(), 2, "state1 should have 2 fields");
         assert_eq!(hash2.len(), 2, "state2 should have 2 fields");
@@ -897,11 +1034,11 @@ mod tests {
 
         // Write same field from both replicas (concurrent conflict)
         let delta1 = state1.record_hash_write(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![("field".to_string(), SDS::from_str("value_from_r1"))],
         );
         let delta2 = state2.record_hash_write(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![("field".to_string(), SDS::from_str("value_from_r2"))],
         );
 
@@ -910,8 +1047,8 @@ mod tests {
         state2.apply_remote_delta(delta1.clone());
 
         // Both replicas should converge to the same value (LWW semantics)
-        let val1 = state1.get_replicated("myhash").unwrap().hash_get("field");
-        let val2 = state2.get_replicated("myhash").unwrap().hash_get("field");
+        let val1 = state1.get_replicated(b"myhash").unwrap().hash_get("field");
+        let val2 = state2.get_replicated(b"myhash").unwrap().hash_get("field");
         assert_eq!(
             val1.map(|s| s.as_bytes()),
             val2.map(|s| s.as_bytes()),
@@ -929,7 +1066,7 @@ mod tests {
 
         // Create hash with multiple fields
         let delta1 = state1.record_hash_write(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![
                 ("field1".to_string(), SDS::from_str("value1")),
                 ("field2".to_string(), SDS::from_str("value2")),
@@ -938,8 +1075,7 @@ mod tests {
         state2.apply_remote_delta(delta1.clone());
 
         // Delete field1 from state1
-        let delete_delta =
-            state1.record_hash_delete("myhash".to_string(), vec!["field1".to_string()]);
+        let delete_delta = state1.record_hash_delete("myhash".into(), vec!["field1".to_string()]);
 
         // Apply deletion to state2
         if let Some(d) = delete_delta {
@@ -947,8 +1083,16 @@ mod tests {
         }
 
         // field1 should be tombstoned, field2 should remain
-        let hash1 = state1.get_replicated("myhash").unwrap().get_hash().unwrap();
-        let hash2 = state2.get_replicated("myhash").unwrap().get_hash().unwrap();
+        let hash1 = state1
+            .get_replicated(b"myhash")
+            .unwrap()
+            .get_hash()
+            .unwrap();
+        let hash2 = state2
+            .get_replicated(b"myhash")
+            .unwrap()
+            .get_hash()
+            .unwrap();
 
         // field1 should be tombstoned (value None or tombstone=true)
         let field1_lww_1 = hash1.get("field1").unwrap();
@@ -981,6 +1125,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hash_field_expiry_replicates() {
+        let r1 = ReplicaId::new(1);
+        let r2 = ReplicaId::new(2);
+
+        let mut state1 = ShardReplicaState::new(r1, ConsistencyLevel::Eventual);
+        let mut state2 = ShardReplicaState::new(r2, ConsistencyLevel::Eventual);
+
+        let delta = state1.record_hash_write(
+            "myhash".into(),
+            vec![("field".to_string(), SDS::from_str("value"))],
+        );
+        state2.apply_remote_delta(delta);
+
+        // HEXPIRE on r1 rewrites the field's register with its deadline
+        let expire = state1
+            .record_hash_fields(
+                "myhash".into(),
+                vec![(
+                    "field".to_string(),
+                    Some((SDS::from_str("value"), Some(1_700_000_000_000))),
+                )],
+            )
+            .expect("hash exists");
+        state2.apply_remote_delta(expire);
+
+        let lww = |state: &ShardReplicaState| {
+            state.get_replicated(b"myhash").unwrap().get_hash().unwrap()["field"].clone()
+        };
+        assert_eq!(lww(&state1).expiry_ms, Some(1_700_000_000_000));
+        assert_eq!(lww(&state2).expiry_ms, Some(1_700_000_000_000));
+
+        // HPERSIST on r2 wins over the older TTL on both replicas
+        let persist = state2
+            .record_hash_fields(
+                "myhash".into(),
+                vec![("field".to_string(), Some((SDS::from_str("value"), None)))],
+            )
+            .expect("hash exists");
+        state1.apply_remote_delta(persist);
+        assert_eq!(lww(&state1).expiry_ms, None);
+        assert_eq!(lww(&state2).expiry_ms, None);
+
+        // Deleting fields of a missing hash records nothing
+        assert!(state1
+            .record_hash_fields("missing".into(), vec![("f".to_string(), None)])
+            .is_none());
+    }
+
     #[test]
     fn test_hash_delete_wins_over_concurrent_write() {
         // Test that delete-after-write wins in LWW semantics
@@ -992,14 +1185,13 @@ mod tests {
 
         // r1 writes a field
         let write_delta = state1.record_hash_write(
-            "myhash".to_string(),
+            "myhash".into(),
             vec![("field".to_string(), SDS::from_str("value"))],
         );
         state2.apply_remote_delta(write_delta.clone());
 
         // r2 deletes the same field (happens after write due to clock)
-        let delete_delta =
-            state2.record_hash_delete("myhash".to_string(), vec!["field".to_string()]);
+        let delete_delta = state2.record_hash_delete("myhash".into(), vec!["field".to_string()]);
 
         // Apply delete to r1
         if let Some(d) = delete_delta {
@@ -1007,8 +1199,16 @@ mod tests {
         }
 
         // Both should have tombstoned field
-        let hash1 = state1.get_replicated("myhash").unwrap().get_hash().unwrap();
-        let hash2 = state2.get_replicated("myhash").unwrap().get_hash().unwrap();
+        let hash1 = state1
+            .get_replicated(b"myhash")
+            .unwrap()
+            .get_hash()
+            .unwrap();
+        let hash2 = state2
+            .get_replicated(b"myhash")
+            .unwrap()
+            .get_hash()
+            .unwrap();
 
         assert!(
             hash1.get("field").unwrap().tombstone,
@@ -1050,18 +1250,12 @@ mod tests {
                 let value = format!("value_{}_seed{}", i, seed);
 
                 let delta = match op_type {
-                    0 => state1.record_hash_write(
-                        "hash".to_string(),
-                        vec![(field, SDS::from_str(&value))],
-                    ),
-                    1 => state2.record_hash_write(
-                        "hash".to_string(),
-                        vec![(field, SDS::from_str(&value))],
-                    ),
-                    _ => state3.record_hash_write(
-                        "hash".to_string(),
-                        vec![(field, SDS::from_str(&value))],
-                    ),
+                    0 => state1
+                        .record_hash_write("hash".into(), vec![(field, SDS::from_str(&value))]),
+                    1 => state2
+                        .record_hash_write("hash".into(), vec![(field, SDS::from_str(&value))]),
+                    _ => state3
+                        .record_hash_write("hash".into(), vec![(field, SDS::from_str(&value))]),
                 };
                 deltas.push(delta);
             }
@@ -1074,9 +1268,9 @@ mod tests {
             }
 
             // All replicas should converge
-            let hash1 = state1.get_replicated("hash").map(|v| v.get_hash());
-            let hash2 = state2.get_replicated("hash").map(|v| v.get_hash());
-            let hash3 = state3.get_replicated("hash").map(|v| v.get_hash());
+            let hash1 = state1.get_replicated(b"hash").map(|v| v.get_hash());
+            let hash2 = state2.get_replicated(b"hash").map(|v| v.get_hash());
+            let hash3 = state3.get_replicated(b"hash").map(|v| v.get_hash());
 
             // Verify all replicas have same field values
             if let (Some(Some(h1)), Some(Some(h2)), Some(Some(h3))) = (hash1, hash2, hash3) {
@@ -1128,7 +1322,7 @@ mod tests {
 
         // Create a hash and verify invariants
         state.record_hash_write(
-            "test_hash".to_string(),
+            "test_hash".into(),
             vec![
                 ("a".to_string(), SDS::from_str("1")),
                 ("b".to_string(), SDS::from_str("2")),
@@ -1136,7 +1330,7 @@ mod tests {
             ],
         );
 
-        let replicated = state.get_replicated("test_hash").unwrap();
+        let replicated = state.get_replicated(b"test_hash").unwrap();
         assert!(replicated.is_hash(), "Value should be hash type");
 
         // Verify the hash-specific invariants
@@ -1144,9 +1338,9 @@ mod tests {
         replicated.verify_hash_invariants();
 
         // Delete a field and verify invariants still hold
-        state.record_hash_delete("test_hash".to_string(), vec!["b".to_string()]);
+        state.record_hash_delete("test_hash".into(), vec!["b".to_string()]);
 
-        let replicated = state.get_replicated("test_hash").unwrap();
+        let replicated = state.get_replicated(b"test_hash").unwrap();
         #[cfg(debug_assertions)]
         replicated.verify_hash_invariants();
     }
@@ -1170,21 +1364,21 @@ mod tests {
 
         // r1 sets initial value "10"
         let delta1 = state1.record_hash_write(
-            "counters".to_string(),
+            "counters".into(),
             vec![("hits".to_string(), SDS::from_str("10"))],
         );
         state2.apply_remote_delta(delta1);
 
         // r1 increments to "15" (simulating HINCRBY 5)
         let delta2 = state1.record_hash_write(
-            "counters".to_string(),
+            "counters".into(),
             vec![("hits".to_string(), SDS::from_str("15"))],
         );
         state2.apply_remote_delta(delta2);
 
         // Both should see "15"
-        let val1 = state1.get_replicated("counters").unwrap().hash_get("hits");
-        let val2 = state2.get_replicated("counters").unwrap().hash_get("hits");
+        let val1 = state1.get_replicated(b"counters").unwrap().hash_get("hits");
+        let val2 = state2.get_replicated(b"counters").unwrap().hash_get("hits");
 
         assert_eq!(val1.map(|s| s.to_string()), Some("15".to_string()));
         assert_eq!(val2.map(|s| s.to_string()), Some("15".to_string()));
@@ -1202,20 +1396,20 @@ mod tests {
 
         // Both start with same value "10"
         let init_delta = state1.record_hash_write(
-            "counters".to_string(),
+            "counters".into(),
             vec![("hits".to_string(), SDS::from_str("10"))],
         );
         state2.apply_remote_delta(init_delta);
 
         // r1 increments to "15" (HINCRBY 5)
         let delta1 = state1.record_hash_write(
-            "counters".to_string(),
+            "counters".into(),
             vec![("hits".to_string(), SDS::from_str("15"))],
         );
 
         // r2 concurrently increments to "13" (HINCRBY 3 from its view of "10")
         let delta2 = state2.record_hash_write(
-            "counters".to_string(),
+            "counters".into(),
             vec![("hits".to_string(), SDS::from_str("13"))],
         );
 
@@ -1225,8 +1419,8 @@ mod tests {
 
         // Both should converge to SAME value (LWW winner)
         // NOT "18" (10+5+3) - increments don't commute in LWW
-        let val1 = state1.get_replicated("counters").unwrap().hash_get("hits");
-        let val2 = state2.get_replicated("counters").unwrap().hash_get("hits");
+        let val1 = state1.get_replicated(b"counters").unwrap().hash_get("hits");
+        let val2 = state2.get_replicated(b"counters").unwrap().hash_get("hits");
 
         assert_eq!(
             val1.map(|s| s.to_string()),
@@ -1263,15 +1457,15 @@ mod tests {
             ];
 
             let delta1 = state1.record_hash_write(
-                "counter".to_string(),
+                "counter".into(),
                 vec![("val".to_string(), SDS::from_str(&values[0].to_string()))],
             );
             let delta2 = state2.record_hash_write(
-                "counter".to_string(),
+                "counter".into(),
                 vec![("val".to_string(), SDS::from_str(&values[1].to_string()))],
             );
             let delta3 = state3.record_hash_write(
-                "counter".to_string(),
+                "counter".into(),
                 vec![("val".to_string(), SDS::from_str(&values[2].to_string()))],
             );
 
@@ -1284,9 +1478,9 @@ mod tests {
             state3.apply_remote_delta(delta2.clone());
 
             // All three should converge
-            let v1 = state1.get_replicated("counter").unwrap().hash_get("val");
-            let v2 = state2.get_replicated("counter").unwrap().hash_get("val");
-            let v3 = state3.get_replicated("counter").unwrap().hash_get("val");
+            let v1 = state1.get_replicated(b"counter").unwrap().hash_get("val");
+            let v2 = state2.get_replicated(b"counter").unwrap().hash_get("val");
+            let v3 = state3.get_replicated(b"counter").unwrap().hash_get("val");
 
             assert_eq!(
                 v1.map(|s| s.to_string()),
@@ -1330,8 +1524,8 @@ mod tests {
         state2.apply_remote_delta(delta1.clone());
 
         // Both should converge to same winner
-        let val1 = state1.get_replicated("lock").unwrap().get();
-        let val2 = state2.get_replicated("lock").unwrap().get();
+        let val1 = state1.get_replicated(b"lock").unwrap().get();
+        let val2 = state2.get_replicated(b"lock").unwrap().get();
 
         assert_eq!(
             val1.map(|s| String::from_utf8_lossy(s.as_bytes()).to_string()),
@@ -1363,8 +1557,8 @@ mod tests {
         state2.apply_remote_delta(delta2.clone());
 
         // Both should converge
-        let val1 = state1.get_replicated("config").unwrap().get();
-        let val2 = state2.get_replicated("config").unwrap().get();
+        let val1 = state1.get_replicated(b"config").unwrap().get();
+        let val2 = state2.get_replicated(b"config").unwrap().get();
 
         assert_eq!(
             val1.map(|s| String::from_utf8_lossy(s.as_bytes()).to_string()),
@@ -1385,12 +1579,12 @@ mod tests {
 
             // Concurrent writes with seed-based values
             let delta1 = state1.record_write(
-                format!("key_{}", seed),
+                SDS::from(format!("key_{}", seed)),
                 SDS::from_str(&format!("value_r1_{}", seed)),
                 None,
             );
             let delta2 = state2.record_write(
-                format!("key_{}", seed),
+                SDS::from(format!("key_{}", seed)),
                 SDS::from_str(&format!("value_r2_{}", seed)),
                 None,
             );
@@ -1399,11 +1593,11 @@ mod tests {
             state2.apply_remote_delta(delta1.clone());
 
             let val1 = state1
-                .get_replicated(&format!("key_{}", seed))
+                .get_replicated(format!("key_{}", seed).as_bytes())
                 .unwrap()
                 .get();
             let val2 = state2
-                .get_replicated(&format!("key_{}", seed))
+                .get_replicated(format!("key_{}", seed).as_bytes())
                 .unwrap()
                 .get();
 
@@ -1576,4 +1770,35 @@ mod tests {
             panic!("GCounter merge should produce GCounter");
         }
     }
+
+    #[test]
+    fn test_hyperloglog_concurrent_writes_converge() {
+        let mut state1 = ShardReplicaState::new(ReplicaId::new(1), ConsistencyLevel::Eventual);
+        let mut state2 = ShardReplicaState::new(ReplicaId::new(2), ConsistencyLevel::Eventual);
+
+        let mut hll1 = HyperLogLog::new();
+        let mut hll2 = HyperLogLog::new();
+        for i in 0..300 {
+            hll1.add(format!("visitor:{}", i).as_bytes());
+            hll2.add(format!("visitor:{}", i + 200).as_bytes());
+        }
+        let delta1 = state1.record_hyperloglog_write("visitors".into(), &hll1);
+        let delta2 = state2.record_hyperloglog_write("visitors".into(), &hll2);
+
+        state1.apply_remote_delta(delta2);
+        state2.apply_remote_delta(delta1);
+
+        let merged1 = state1
+            .get_replicated(b"visitors")
+            .unwrap()
+            .get_hyperloglog();
+        let merged2 = state2
+            .get_replicated(b"visitors")
+            .unwrap()
+            .get_hyperloglog();
+        assert_eq!(merged1, merged2);
+        assert_eq!(merged1, Some(&hll1.merge(&hll2)));
+        let count = merged1.unwrap().count();
+        assert!((490..=510).contains(&count), "estimate {}", count);
+    }
 }
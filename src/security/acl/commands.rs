@@ -102,8 +102,9 @@ impl AclCommandHandler {
                 // List all categories
                 Ok(vec![
                     "read", "write", "admin", "dangerous", "keyspace",
-                    "string", "list", "set", "hash", "sortedset",
-                    "connection", "server", "scripting", "transaction",
+                    "string", "list", "set", "hash", "sortedset", "stream", "bitmap",
+                    "hyperloglog", "geo", "pubsub", "blocking", "connection", "server", "scripting",
+                    "transaction",
                 ]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -129,7 +130,7 @@ impl AclCommandHandler {
         use std::time::{SystemTime, UNIX_EPOCH};
 
         let bits = bits.unwrap_or(256).min(1024);
-        let bytes = (bits as usize + 7) / 8;
+        let bytes = (bits as usize).div_ceil(8);
 
         // Simple pseudo-random generation (not cryptographically secure)
         // In production, use proper random source
@@ -347,8 +348,8 @@ mod tests {
         // Add key pattern
         apply_rule(&mut user, "~user:*").unwrap();
         assert_eq!(user.keys.patterns.len(), 1);
-        assert!(user.keys.is_key_permitted("user:123"));
-        assert!(!user.keys.is_key_permitted("admin:secret"));
+        assert!(user.keys.is_key_permitted(b"user:123"));
+        assert!(!user.keys.is_key_permitted(b"admin:secret"));
     }
 
     #[test]
@@ -385,6 +386,6 @@ mod tests {
         let user = manager.get_user("newuser").unwrap();
         assert!(user.enabled);
         assert!(user.commands.categories.contains(&CommandCategory::Read));
-        assert!(user.keys.is_key_permitted("cache:foo"));
+        assert!(user.keys.is_key_permitted(b"cache:foo"));
     }
 }
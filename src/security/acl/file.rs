@@ -230,9 +230,9 @@ mod tests {
         let user = parse_user_line("user readonly on nopass ~cache:* ~session:* +@read", "test", 1).unwrap();
         assert_eq!(user.name, "readonly");
         assert!(!user.keys.allow_all);
-        assert!(user.keys.is_key_permitted("cache:foo"));
-        assert!(user.keys.is_key_permitted("session:123"));
-        assert!(!user.keys.is_key_permitted("admin:secret"));
+        assert!(user.keys.is_key_permitted(b"cache:foo"));
+        assert!(user.keys.is_key_permitted(b"session:123"));
+        assert!(!user.keys.is_key_permitted(b"admin:secret"));
     }
 
     #[test]
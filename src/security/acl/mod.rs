@@ -149,7 +149,7 @@ impl AclManager {
         &self,
         user: Option<&AclUser>,
         command: &str,
-        keys: &[&str],
+        keys: &[&[u8]],
     ) -> Result<(), AclError> {
         // If no user context and auth is required, reject
         if self.require_auth && user.is_none() {
@@ -179,7 +179,7 @@ impl AclManager {
         for key in keys {
             if !user.keys.is_key_permitted(key) {
                 return Err(AclError::KeyNotPermitted {
-                    key: (*key).to_string(),
+                    key: String::from_utf8_lossy(key).into_owned(),
                     user: user.name.clone(),
                 });
             }
@@ -226,6 +226,21 @@ impl AclManager {
             .cloned()
             .expect("default user must always exist")
     }
+
+    /// Replace the default user's passwords, like Redis `requirepass`
+    /// (None = nopass)
+    pub fn set_requirepass(&mut self, password: Option<&str>) {
+        let mut user = (*self.default_user()).clone();
+        user.clear_passwords();
+        match password {
+            Some(password) => {
+                user.add_password(password);
+                user.nopass = false;
+            }
+            None => user.nopass = true,
+        }
+        self.set_user(user);
+    }
 }
 
 impl Default for AclManager {
@@ -291,6 +306,20 @@ mod tests {
         assert!(matches!(result, Err(AclError::AuthFailed)));
     }
 
+    #[test]
+    fn test_set_requirepass() {
+        let mut manager = AclManager::new();
+        manager.set_requirepass(Some("s3cret"));
+        assert!(manager.authenticate("default", "s3cret").is_ok());
+        assert!(matches!(
+            manager.authenticate("default", "wrong"),
+            Err(AclError::AuthFailed)
+        ));
+
+        manager.set_requirepass(None);
+        assert!(manager.authenticate("default", "anything").is_ok());
+    }
+
     #[test]
     fn test_command_check() {
         let mut manager = AclManager::new();
@@ -304,11 +333,11 @@ mod tests {
         manager.set_user(user.clone());
 
         // GET should be allowed (read command)
-        let result = manager.check_command(Some(&user), "GET", &["mykey"]);
+        let result = manager.check_command(Some(&user), "GET", &[b"mykey".as_slice()]);
         assert!(result.is_ok(), "GET should be allowed: {:?}", result);
 
         // SET should be denied (write command)
-        let result = manager.check_command(Some(&user), "SET", &["mykey"]);
+        let result = manager.check_command(Some(&user), "SET", &[b"mykey".as_slice()]);
         assert!(matches!(result, Err(AclError::CommandNotPermitted { .. })));
     }
 }
@@ -40,8 +40,8 @@ impl KeyPattern {
     }
 
     /// Check if a key matches this pattern
-    pub fn matches(&self, key: &str) -> bool {
-        glob_match(&self.pattern, key)
+    pub fn matches(&self, key: &[u8]) -> bool {
+        glob_match(self.pattern.as_bytes(), key)
     }
 }
 
@@ -82,7 +82,7 @@ impl KeyPatterns {
     }
 
     /// Check if a key is permitted
-    pub fn is_key_permitted(&self, key: &str) -> bool {
+    pub fn is_key_permitted(&self, key: &[u8]) -> bool {
         if self.allow_all {
             return true;
         }
@@ -116,15 +116,9 @@ impl Default for KeyPatterns {
     }
 }
 
-/// Simple glob pattern matching (supports * and ?)
-fn glob_match(pattern: &str, text: &str) -> bool {
-    let pattern_chars: Vec<char> = pattern.chars().collect();
-    let text_chars: Vec<char> = text.chars().collect();
-
-    glob_match_impl(&pattern_chars, &text_chars)
-}
-
-fn glob_match_impl(pattern: &[char], text: &[char]) -> bool {
+/// Simple glob pattern matching (supports * and ?), over bytes since keys
+/// need not be UTF-8
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
     let mut p = 0;
     let mut t = 0;
     let mut star_p = None;
@@ -133,13 +127,13 @@ fn glob_match_impl(pattern: &[char], text: &[char]) -> bool {
     while t < text.len() {
         if p < pattern.len() {
             match pattern[p] {
-                '?' => {
+                b'?' => {
                     // ? matches any single character
                     p += 1;
                     t += 1;
                     continue;
                 }
-                '*' => {
+                b'*' => {
                     // * matches zero or more characters
                     star_p = Some(p);
                     star_t = Some(t);
@@ -166,7 +160,7 @@ fn glob_match_impl(pattern: &[char], text: &[char]) -> bool {
     }
 
     // Check remaining pattern characters (should all be *)
-    while p < pattern.len() && pattern[p] == '*' {
+    while p < pattern.len() && pattern[p] == b'*' {
         p += 1;
     }
 
@@ -179,44 +173,44 @@ mod tests {
 
     #[test]
     fn test_glob_match_exact() {
-        assert!(glob_match("foo", "foo"));
-        assert!(!glob_match("foo", "bar"));
-        assert!(!glob_match("foo", "foobar"));
-        assert!(!glob_match("foobar", "foo"));
+        assert!(glob_match(b"foo", b"foo"));
+        assert!(!glob_match(b"foo", b"bar"));
+        assert!(!glob_match(b"foo", b"foobar"));
+        assert!(!glob_match(b"foobar", b"foo"));
     }
 
     #[test]
     fn test_glob_match_star() {
-        assert!(glob_match("*", "anything"));
-        assert!(glob_match("*", ""));
-        assert!(glob_match("foo*", "foo"));
-        assert!(glob_match("foo*", "foobar"));
-        assert!(glob_match("*bar", "foobar"));
-        assert!(glob_match("*bar", "bar"));
-        assert!(glob_match("foo*bar", "foobar"));
-        assert!(glob_match("foo*bar", "foo123bar"));
-        assert!(!glob_match("foo*bar", "foobarbaz"));
+        assert!(glob_match(b"*", b"anything"));
+        assert!(glob_match(b"*", b""));
+        assert!(glob_match(b"foo*", b"foo"));
+        assert!(glob_match(b"foo*", b"foobar"));
+        assert!(glob_match(b"*bar", b"foobar"));
+        assert!(glob_match(b"*bar", b"bar"));
+        assert!(glob_match(b"foo*bar", b"foobar"));
+        assert!(glob_match(b"foo*bar", b"foo123bar"));
+        assert!(!glob_match(b"foo*bar", b"foobarbaz"));
     }
 
     #[test]
     fn test_glob_match_question() {
-        assert!(glob_match("fo?", "foo"));
-        assert!(glob_match("fo?", "for"));
-        assert!(!glob_match("fo?", "fo"));
-        assert!(!glob_match("fo?", "fooo"));
-        assert!(glob_match("???", "abc"));
-        assert!(!glob_match("???", "ab"));
+        assert!(glob_match(b"fo?", b"foo"));
+        assert!(glob_match(b"fo?", b"for"));
+        assert!(!glob_match(b"fo?", b"fo"));
+        assert!(!glob_match(b"fo?", b"fooo"));
+        assert!(glob_match(b"???", b"abc"));
+        assert!(!glob_match(b"???", b"ab"));
     }
 
     #[test]
     fn test_glob_match_complex() {
-        assert!(glob_match("user:*", "user:123"));
-        assert!(glob_match("user:*", "user:"));
-        assert!(!glob_match("user:*", "admin:123"));
-        assert!(glob_match("cache:*:data", "cache:foo:data"));
-        assert!(glob_match("*:*:*", "a:b:c"));
-        assert!(glob_match("user:???:*", "user:abc:data"));
-        assert!(!glob_match("user:???:*", "user:ab:data"));
+        assert!(glob_match(b"user:*", b"user:123"));
+        assert!(glob_match(b"user:*", b"user:"));
+        assert!(!glob_match(b"user:*", b"admin:123"));
+        assert!(glob_match(b"cache:*:data", b"cache:foo:data"));
+        assert!(glob_match(b"*:*:*", b"a:b:c"));
+        assert!(glob_match(b"user:???:*", b"user:abc:data"));
+        assert!(!glob_match(b"user:???:*", b"user:ab:data"));
     }
 
     #[test]
@@ -225,16 +219,26 @@ mod tests {
         patterns.add("user:*");
         patterns.add("cache:*");
 
-        assert!(patterns.is_key_permitted("user:123"));
-        assert!(patterns.is_key_permitted("cache:foo"));
-        assert!(!patterns.is_key_permitted("admin:secret"));
-        assert!(!patterns.is_key_permitted("other"));
+        assert!(patterns.is_key_permitted(b"user:123"));
+        assert!(patterns.is_key_permitted(b"cache:foo"));
+        assert!(!patterns.is_key_permitted(b"admin:secret"));
+        assert!(!patterns.is_key_permitted(b"other"));
     }
 
     #[test]
     fn test_key_patterns_allow_all() {
         let patterns = KeyPatterns::allow_all();
-        assert!(patterns.is_key_permitted("anything"));
-        assert!(patterns.is_key_permitted(""));
+        assert!(patterns.is_key_permitted(b"anything"));
+        assert!(patterns.is_key_permitted(b""));
+    }
+
+    #[test]
+    fn test_key_patterns_binary_keys() {
+        let mut patterns = KeyPatterns::deny_all();
+        patterns.add("pb:*");
+
+        assert!(patterns.is_key_permitted(b"pb:\x08\xff\xfe"));
+        assert!(!patterns.is_key_permitted(b"\xff\xfe:pb"));
+        assert!(glob_match(b"pb:?", b"pb:\xc3"));
     }
 }
@@ -26,6 +26,18 @@ pub enum CommandCategory {
     Hash,
     /// Sorted set commands
     SortedSet,
+    /// Stream commands
+    Stream,
+    /// Bitmap commands
+    Bitmap,
+    /// HyperLogLog commands
+    HyperLogLog,
+    /// Geospatial commands
+    Geo,
+    /// Pub/Sub commands
+    PubSub,
+    /// Commands that may block the connection
+    Blocking,
     /// Connection commands (AUTH, PING, etc.)
     Connection,
     /// Server commands (INFO, DBSIZE, etc.)
@@ -44,56 +56,110 @@ impl CommandCategory {
         match self {
             CommandCategory::Read => &[
                 "GET", "MGET", "HGET", "HGETALL", "HKEYS", "HVALS", "HLEN", "HEXISTS",
-                "LRANGE", "LINDEX", "LLEN", "SMEMBERS", "SISMEMBER", "SCARD",
+                "HMGET", "HSTRLEN", "HRANDFIELD", "HTTL", "HPTTL",
+                "LRANGE", "LINDEX", "LLEN", "LPOS", "SMEMBERS", "SISMEMBER", "SCARD",
+                "SUNION", "SINTER", "SDIFF", "SINTERCARD", "SRANDMEMBER", "SMISMEMBER",
                 "ZRANGE", "ZREVRANGE", "ZSCORE", "ZRANK", "ZCARD", "ZCOUNT",
-                "ZRANGEBYSCORE", "STRLEN", "EXISTS", "TYPE", "TTL", "PTTL",
-                "SCAN", "HSCAN", "ZSCAN", "KEYS", "DBSIZE", "INFO",
+                "ZRANGEBYSCORE", "ZREVRANGEBYSCORE", "ZRANGEBYLEX", "ZREVRANGEBYLEX",
+                "ZMSCORE", "ZREVRANK", "ZLEXCOUNT", "ZRANDMEMBER", "ZUNION", "ZINTER", "ZDIFF",
+                "STRLEN", "GETRANGE", "SUBSTR", "LCS", "EXISTS", "TYPE", "TTL", "PTTL",
+                "EXPIRETIME", "PEXPIRETIME", "RANDOMKEY", "TOUCH", "OBJECT",
+                "SCAN", "HSCAN", "SSCAN", "ZSCAN", "KEYS", "DBSIZE", "INFO",
+                "XLEN", "XRANGE", "XREVRANGE", "XREAD", "XPENDING",
+                "GETBIT", "BITCOUNT", "BITPOS", "BITFIELD_RO", "PFCOUNT",
+                "GEODIST", "GEOPOS", "GEOHASH", "GEOSEARCH", "GEORADIUS_RO",
+                "GEORADIUSBYMEMBER_RO",
             ],
             CommandCategory::Write => &[
-                "SET", "SETEX", "SETNX", "MSET", "APPEND", "GETSET",
-                "INCR", "DECR", "INCRBY", "DECRBY",
+                "SET", "SETEX", "PSETEX", "SETNX", "MSET", "MSETNX", "APPEND", "GETSET",
+                "SETRANGE", "GETDEL", "GETEX",
+                "INCR", "DECR", "INCRBY", "DECRBY", "INCRBYFLOAT",
                 "LPUSH", "RPUSH", "LPOP", "RPOP", "LSET", "LTRIM", "RPOPLPUSH", "LMOVE",
-                "HSET", "HDEL", "HINCRBY",
-                "SADD", "SREM",
-                "ZADD", "ZREM",
-                "DEL", "EXPIRE", "EXPIREAT", "PEXPIREAT", "PERSIST",
+                "LPUSHX", "RPUSHX", "LINSERT", "LREM", "LMPOP",
+                "BLPOP", "BRPOP", "BLMOVE", "BRPOPLPUSH",
+                "HSET", "HDEL", "HINCRBY", "HINCRBYFLOAT", "HMSET", "HSETNX", "HGETDEL", "HGETEX",
+                "HEXPIRE", "HPEXPIRE", "HEXPIREAT", "HPEXPIREAT", "HPERSIST",
+                "SADD", "SREM", "SMOVE", "SUNIONSTORE", "SINTERSTORE", "SDIFFSTORE",
+                "ZADD", "ZREM", "BZPOPMIN", "BZPOPMAX", "ZINCRBY", "ZPOPMIN", "ZPOPMAX",
+                "ZRANGESTORE", "ZREMRANGEBYRANK", "ZREMRANGEBYSCORE", "ZREMRANGEBYLEX",
+                "ZUNIONSTORE", "ZINTERSTORE", "ZDIFFSTORE",
+                "DEL", "UNLINK", "EXPIRE", "EXPIREAT", "PEXPIRE", "PEXPIREAT", "PERSIST",
+                "RENAME", "RENAMENX", "COPY", "MOVE", "SWAPDB",
+                "XADD", "XDEL", "XTRIM", "XGROUP", "XREADGROUP", "XACK", "XCLAIM", "XAUTOCLAIM",
+                "SETBIT", "BITOP", "BITFIELD", "PFADD", "PFMERGE",
+                "GEOADD", "GEOSEARCHSTORE", "GEORADIUS", "GEORADIUSBYMEMBER",
             ],
             CommandCategory::Admin => &[
                 "CONFIG", "DEBUG", "SHUTDOWN", "SLAVEOF", "REPLICAOF",
-                "BGREWRITEAOF", "BGSAVE", "SAVE", "LASTSAVE",
+                "BGREWRITEAOF", "BGSAVE", "SAVE", "LASTSAVE", "MONITOR", "SLOWLOG",
+                "LATENCY",
             ],
             CommandCategory::Dangerous => &[
-                "FLUSHALL", "FLUSHDB", "DEBUG", "SHUTDOWN",
+                "FLUSHALL", "FLUSHDB", "SWAPDB", "DEBUG", "SHUTDOWN",
             ],
             CommandCategory::Keyspace => &[
-                "KEYS", "SCAN", "HSCAN", "ZSCAN", "DEL", "EXISTS", "TYPE",
-                "EXPIRE", "EXPIREAT", "PEXPIREAT", "TTL", "PTTL", "PERSIST",
+                "KEYS", "SCAN", "HSCAN", "SSCAN", "ZSCAN", "DEL", "UNLINK", "EXISTS", "TYPE",
+                "EXPIRE", "EXPIREAT", "PEXPIRE", "PEXPIREAT", "EXPIRETIME", "PEXPIRETIME",
+                "TTL", "PTTL", "PERSIST", "RENAME", "RENAMENX", "COPY", "RANDOMKEY", "TOUCH",
+                "OBJECT", "MOVE", "SWAPDB",
             ],
             CommandCategory::String => &[
-                "GET", "SET", "SETEX", "SETNX", "MGET", "MSET",
-                "APPEND", "GETSET", "STRLEN", "GETRANGE",
-                "INCR", "DECR", "INCRBY", "DECRBY",
+                "GET", "SET", "SETEX", "PSETEX", "SETNX", "MGET", "MSET", "MSETNX",
+                "APPEND", "GETSET", "STRLEN", "GETRANGE", "SUBSTR", "SETRANGE",
+                "GETDEL", "GETEX", "LCS",
+                "INCR", "DECR", "INCRBY", "DECRBY", "INCRBYFLOAT",
             ],
             CommandCategory::List => &[
                 "LPUSH", "RPUSH", "LPOP", "RPOP", "LRANGE", "LINDEX",
-                "LLEN", "LSET", "LTRIM", "RPOPLPUSH", "LMOVE",
+                "LLEN", "LSET", "LTRIM", "RPOPLPUSH", "LMOVE", "LPUSHX", "RPUSHX",
+                "LINSERT", "LREM", "LPOS", "LMPOP",
+                "BLPOP", "BRPOP", "BLMOVE", "BRPOPLPUSH",
             ],
             CommandCategory::Set => &[
-                "SADD", "SREM", "SMEMBERS", "SISMEMBER", "SCARD",
+                "SADD", "SREM", "SMEMBERS", "SISMEMBER", "SCARD", "SUNION", "SINTER", "SDIFF",
+                "SUNIONSTORE", "SINTERSTORE", "SDIFFSTORE", "SINTERCARD", "SMOVE",
+                "SRANDMEMBER", "SMISMEMBER", "SSCAN",
             ],
             CommandCategory::Hash => &[
                 "HSET", "HGET", "HDEL", "HGETALL", "HKEYS", "HVALS",
-                "HLEN", "HEXISTS", "HINCRBY", "HSCAN",
+                "HLEN", "HEXISTS", "HINCRBY", "HSCAN", "HINCRBYFLOAT", "HMGET", "HMSET",
+                "HSETNX", "HSTRLEN", "HRANDFIELD", "HGETDEL", "HGETEX", "HEXPIRE", "HPEXPIRE",
+                "HEXPIREAT", "HPEXPIREAT", "HTTL", "HPTTL", "HPERSIST",
             ],
             CommandCategory::SortedSet => &[
                 "ZADD", "ZREM", "ZSCORE", "ZRANK", "ZRANGE", "ZREVRANGE",
                 "ZCARD", "ZCOUNT", "ZRANGEBYSCORE", "ZSCAN",
+                "BZPOPMIN", "BZPOPMAX", "ZINCRBY", "ZMSCORE", "ZREVRANK", "ZLEXCOUNT",
+                "ZRANGESTORE", "ZREVRANGEBYSCORE", "ZRANGEBYLEX", "ZREVRANGEBYLEX",
+                "ZREMRANGEBYRANK", "ZREMRANGEBYSCORE", "ZREMRANGEBYLEX", "ZPOPMIN", "ZPOPMAX",
+                "ZRANDMEMBER", "ZUNION", "ZINTER", "ZDIFF", "ZUNIONSTORE", "ZINTERSTORE",
+                "ZDIFFSTORE",
+            ],
+            CommandCategory::Stream => &[
+                "XADD", "XLEN", "XRANGE", "XREVRANGE", "XDEL", "XTRIM", "XREAD",
+                "XGROUP", "XREADGROUP", "XACK", "XPENDING", "XCLAIM", "XAUTOCLAIM",
+            ],
+            CommandCategory::Bitmap => &[
+                "SETBIT", "GETBIT", "BITCOUNT", "BITPOS", "BITOP", "BITFIELD", "BITFIELD_RO",
+            ],
+            CommandCategory::HyperLogLog => &["PFADD", "PFCOUNT", "PFMERGE"],
+            CommandCategory::Geo => &[
+                "GEOADD", "GEODIST", "GEOPOS", "GEOHASH", "GEOSEARCH", "GEOSEARCHSTORE",
+                "GEORADIUS", "GEORADIUS_RO", "GEORADIUSBYMEMBER", "GEORADIUSBYMEMBER_RO",
+            ],
+            CommandCategory::PubSub => &[
+                "SUBSCRIBE", "UNSUBSCRIBE", "PSUBSCRIBE", "PUNSUBSCRIBE",
+                "SSUBSCRIBE", "SUNSUBSCRIBE", "PUBLISH", "SPUBLISH", "PUBSUB",
+            ],
+            CommandCategory::Blocking => &[
+                "BLPOP", "BRPOP", "BLMOVE", "BRPOPLPUSH", "BZPOPMIN", "BZPOPMAX",
             ],
             CommandCategory::Connection => &[
-                "AUTH", "PING", "ECHO", "SELECT", "QUIT",
+                "AUTH", "HELLO", "CLIENT", "PING", "ECHO", "SELECT", "QUIT", "ASKING", "READONLY",
+                "READWRITE",
             ],
             CommandCategory::Server => &[
-                "INFO", "DBSIZE", "TIME", "COMMAND",
+                "INFO", "DBSIZE", "TIME", "COMMAND", "CLUSTER",
             ],
             CommandCategory::Scripting => &[
                 "EVAL", "EVALSHA", "SCRIPT",
@@ -118,6 +184,12 @@ impl CommandCategory {
             "list" => Some(CommandCategory::List),
             "set" => Some(CommandCategory::Set),
             "hash" => Some(CommandCategory::Hash),
+            "stream" => Some(CommandCategory::Stream),
+            "bitmap" => Some(CommandCategory::Bitmap),
+            "hyperloglog" => Some(CommandCategory::HyperLogLog),
+            "geo" => Some(CommandCategory::Geo),
+            "pubsub" => Some(CommandCategory::PubSub),
+            "blocking" => Some(CommandCategory::Blocking),
             "sortedset" | "zset" => Some(CommandCategory::SortedSet),
             "connection" => Some(CommandCategory::Connection),
             "server" => Some(CommandCategory::Server),
@@ -80,7 +80,7 @@ pub mod acl_noop {
             &self,
             _user: Option<&AclUser>,
             _command: &str,
-            _keys: &[&str],
+            _keys: &[&[u8]],
         ) -> Result<(), AclError> {
             Ok(())
         }
@@ -90,6 +90,12 @@ pub mod acl_noop {
             false
         }
 
+        /// No-op: auth never required when ACL feature disabled
+        pub fn set_require_auth(&mut self, _require: bool) {}
+
+        /// No-op: there are no passwords when ACL feature disabled
+        pub fn set_requirepass(&mut self, _password: Option<&str>) {}
+
         /// No-op: set user does nothing when ACL feature disabled
         pub fn set_user(&mut self, _user: AclUser) {}
 
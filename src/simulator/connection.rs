@@ -11,7 +11,7 @@
 //! deterministically.
 
 use super::{DeterministicRng, VirtualTime};
-use crate::redis::{Command, CommandExecutor, RespCodec, RespValue};
+use crate::redis::{Command, CommandExecutor, RespCodec, RespParser, RespValue};
 use bytes::{BufMut, BytesMut};
 use std::collections::VecDeque;
 
@@ -466,6 +466,7 @@ impl SimulatedConnection {
                     Self::encode_resp(elem, buf);
                 }
             }
+            other => buf.extend_from_slice(&RespParser::encode(other)),
         }
     }
 }
@@ -515,7 +516,7 @@ impl PipelineSimulator {
                             crate::redis::SDS::from_str(&format!("value{}", i)),
                         )
                     } else {
-                        Command::Get(format!("key{}", i % (i.max(1))))
+                        Command::Get(format!("key{}", i % (i.max(1))).into())
                     }
                 })
                 .collect();
@@ -611,8 +612,8 @@ mod tests {
         let commands = vec![
             Command::set("k1".to_string(), SDS::from_str("v1")),
             Command::set("k2".to_string(), SDS::from_str("v2")),
-            Command::Get("k1".to_string()),
-            Command::Get("k2".to_string()),
+            Command::Get("k1".into()),
+            Command::Get("k2".into()),
         ];
 
         // Batched (fixed behavior)
@@ -643,10 +644,10 @@ mod tests {
 
         conn.send_pipeline(vec![
             Command::set("counter".to_string(), SDS::from_str("0")),
-            Command::Incr("counter".to_string()),
-            Command::Incr("counter".to_string()),
-            Command::Incr("counter".to_string()),
-            Command::Get("counter".to_string()),
+            Command::Incr("counter".into()),
+            Command::Incr("counter".into()),
+            Command::Incr("counter".into()),
+            Command::Get("counter".into()),
         ]);
 
         let responses = conn.process();
@@ -697,7 +698,7 @@ mod tests {
 
         conn.send_pipeline(vec![
             Command::set("k1".to_string(), SDS::from_str("v1")),
-            Command::Get("k1".to_string()),
+            Command::Get("k1".into()),
         ]);
 
         let responses = conn.process();
@@ -742,14 +743,14 @@ mod tests {
         let mut conn1 = SimulatedConnection::new(seed);
         conn1.send_pipeline(vec![
             Command::set("k".to_string(), SDS::from_str("v")),
-            Command::Get("k".to_string()),
+            Command::Get("k".into()),
         ]);
         let responses1 = conn1.process();
 
         let mut conn2 = SimulatedConnection::new(seed);
         conn2.send_pipeline(vec![
             Command::set("k".to_string(), SDS::from_str("v")),
-            Command::Get("k".to_string()),
+            Command::Get("k".into()),
         ]);
         let responses2 = conn2.process();
 
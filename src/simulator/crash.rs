@@ -251,23 +251,18 @@ impl CrashSimulator {
             Some(NodeState::Recovering {
                 expected_completion,
                 recovery_start,
-            }) => {
-                if time >= *expected_completion {
-                    // Update stats
-                    let recovery_time = time.0 - recovery_start.0;
-                    let total_recoveries = self.stats.total_recoveries as f64;
-                    self.stats.average_recovery_time_ms = (self.stats.average_recovery_time_ms
-                        * total_recoveries
-                        + recovery_time as f64)
+            }) if time >= *expected_completion => {
+                // Update stats
+                let recovery_time = time.0 - recovery_start.0;
+                let total_recoveries = self.stats.total_recoveries as f64;
+                self.stats.average_recovery_time_ms =
+                    (self.stats.average_recovery_time_ms * total_recoveries + recovery_time as f64)
                         / (total_recoveries + 1.0);
-                    self.stats.total_recoveries += 1;
+                self.stats.total_recoveries += 1;
 
-                    // Mark as running
-                    self.node_states.insert(node_id, NodeState::Running);
-                    true
-                } else {
-                    false
-                }
+                // Mark as running
+                self.node_states.insert(node_id, NodeState::Running);
+                true
             }
             _ => false,
         }
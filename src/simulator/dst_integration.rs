@@ -112,7 +112,7 @@ impl SimulatedNode {
     }
 
     fn get(&mut self, key: &str) -> Option<String> {
-        let cmd = Command::Get(key.to_string());
+        let cmd = Command::Get(key.into());
         match self.executor.execute(&cmd) {
             RespValue::BulkString(Some(data)) => String::from_utf8(data).ok(),
             _ => None,
@@ -605,7 +605,7 @@ mod tests {
 
         // Sort keys by frequency
         let mut counts: Vec<(u64, u64)> = key_counts.into_iter().collect();
-        counts.sort_by(|a, b| b.1.cmp(&a.1));
+        counts.sort_by_key(|c| std::cmp::Reverse(c.1));
 
         // Verify hot keys exist (top 10 keys should have significant traffic)
         let top_10_accesses: u64 = counts.iter().take(10).map(|(_, c)| *c).sum();
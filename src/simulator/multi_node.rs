@@ -114,26 +114,36 @@ impl SimulatedNode {
             // Also apply to command executor for GET to work
             if !delta.value.is_tombstone() {
                 if let Some(value) = delta.value.get() {
-                    let _ = self
-                        .executor
-                        .execute(&Command::set(delta.key.clone(), value.clone()));
+                    let _ = self.executor.execute(&Command::Set {
+                        key: delta.key.clone(),
+                        value: value.clone(),
+                        ex: None,
+                        px: None,
+                        nx: false,
+                        xx: false,
+                        get: false,
+                    });
                 }
             } else {
-                let _ = self.executor.execute(&Command::del(delta.key.clone()));
+                let _ = self
+                    .executor
+                    .execute(&Command::Del(vec![delta.key.clone()]));
             }
         }
     }
 
     /// Get the value for a key from replica state
     pub fn get_replicated_value(&self, key: &str) -> Option<String> {
-        self.replica_state.get_replicated(key).and_then(|rv| {
-            if rv.is_tombstone() {
-                None
-            } else {
-                rv.get()
-                    .map(|sds| String::from_utf8_lossy(sds.as_bytes()).to_string())
-            }
-        })
+        self.replica_state
+            .get_replicated(key.as_bytes())
+            .and_then(|rv| {
+                if rv.is_tombstone() {
+                    None
+                } else {
+                    rv.get()
+                        .map(|sds| String::from_utf8_lossy(sds.as_bytes()).to_string())
+                }
+            })
     }
 }
 
@@ -718,7 +728,7 @@ mod tests {
                     SDS::from_str(&format!("value_{}", i)),
                     timestamp,
                 );
-                ReplicationDelta::new(key, value, replica_id)
+                ReplicationDelta::new(key.into(), value, replica_id)
             })
             .collect();
 
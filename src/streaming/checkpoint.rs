@@ -19,8 +19,9 @@
 //! All I/O through ObjectStore trait. Deterministic checkpoint naming.
 
 use crate::io::{ProductionTimeSource, TimeSource};
+use crate::redis::SDS;
 use crate::replication::state::ReplicatedValue;
-use crate::streaming::{Compression, ManifestManager, ObjectStore, SegmentError};
+use crate::streaming::{format_v1, Compression, ManifestManager, ObjectStore, SegmentError};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io::{Cursor, Read as IoRead, Write as IoWrite};
@@ -109,10 +110,11 @@ impl From<SegmentError> for CheckpointError {
 ///
 /// Similar to segments but contains full state snapshot:
 /// - Header: magic, version, flags, key_count
-/// - Data: serialized HashMap<String, ReplicatedValue>
+/// - Data: serialized HashMap<SDS, ReplicatedValue>
 /// - Footer: data_checksum, header fields repeated
 const CHECKPOINT_MAGIC: &[u8; 4] = b"RCHK";
-const CHECKPOINT_VERSION: u8 = 1;
+/// Version 2 added `expiry_ms` to `LwwRegister`; version 1 loads via `format_v1`
+const CHECKPOINT_VERSION: u8 = 2;
 
 /// Checkpoint header size in bytes
 const CHECKPOINT_HEADER_SIZE: usize = 48;
@@ -215,7 +217,7 @@ impl CheckpointHeader {
                 self.magic
             )));
         }
-        if self.version != CHECKPOINT_VERSION {
+        if self.version == 0 || self.version > CHECKPOINT_VERSION {
             return Err(CheckpointError::InvalidFormat(format!(
                 "Unsupported version: {}",
                 self.version
@@ -307,7 +309,7 @@ impl CheckpointFooter {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CheckpointData {
     /// All key-value state
-    pub state: HashMap<String, ReplicatedValue>,
+    pub state: HashMap<SDS, ReplicatedValue>,
 }
 
 /// Writes checkpoint files
@@ -324,7 +326,7 @@ impl CheckpointWriter {
     /// Write a checkpoint from state snapshot
     pub fn write(
         &self,
-        state: HashMap<String, ReplicatedValue>,
+        state: HashMap<SDS, ReplicatedValue>,
         timestamp_ms: u64,
         last_segment_id: u64,
     ) -> Result<Vec<u8>, CheckpointError> {
@@ -504,8 +506,12 @@ impl<'a> CheckpointReader<'a> {
             compressed_data.to_vec()
         };
 
-        let data: CheckpointData = bincode::deserialize(&uncompressed)
-            .map_err(|e| CheckpointError::Serialization(e.to_string()))?;
+        let data: CheckpointData = if self.header.version == 1 {
+            bincode::deserialize::<format_v1::CheckpointData>(&uncompressed).map(Into::into)
+        } else {
+            bincode::deserialize(&uncompressed)
+        }
+        .map_err(|e| CheckpointError::Serialization(e.to_string()))?;
 
         Ok(data)
     }
@@ -590,7 +596,7 @@ impl<S: ObjectStore + Clone, T: TimeSource> CheckpointManager<S, T> {
     /// Uses TimeSource for zero-cost abstraction (syscall in production, virtual clock in simulation).
     pub async fn create_checkpoint(
         &self,
-        state: HashMap<String, ReplicatedValue>,
+        state: HashMap<SDS, ReplicatedValue>,
         last_segment_id: u64,
     ) -> Result<CheckpointResult, CheckpointError> {
         let timestamp_ms = self.time_source.now_millis();
@@ -693,11 +699,10 @@ pub struct CheckpointResult {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::redis::SDS;
     use crate::replication::lattice::{LamportClock, ReplicaId};
     use crate::streaming::InMemoryObjectStore;
 
-    fn make_state(count: usize) -> HashMap<String, ReplicatedValue> {
+    fn make_state(count: usize) -> HashMap<SDS, ReplicatedValue> {
         let replica_id = ReplicaId::new(1);
         let mut state = HashMap::new();
 
@@ -707,7 +712,7 @@ mod tests {
                 replica_id,
             };
             let value = ReplicatedValue::with_value(SDS::from_str(&format!("value{}", i)), clock);
-            state.insert(format!("key{}", i), value);
+            state.insert(SDS::from(format!("key{}", i)), value);
         }
 
         state
@@ -736,6 +741,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_checkpoint_roundtrip_non_utf8_keys() {
+        let mut state = make_state(2);
+        let key = SDS::from(&b"\xff\xfeproto\x00"[..]);
+        let clock = LamportClock {
+            time: 9,
+            replica_id: ReplicaId::new(1),
+        };
+        state.insert(
+            key.clone(),
+            ReplicatedValue::with_value(SDS::from_str("bin"), clock),
+        );
+
+        let data = CheckpointWriter::new(Compression::None)
+            .write(state, 1000, 1)
+            .unwrap();
+        let loaded = CheckpointReader::open(&data).unwrap().load().unwrap();
+
+        assert_eq!(loaded.state.len(), 3);
+        assert_eq!(
+            loaded.state.get(&key).unwrap().get(),
+            Some(&SDS::from_str("bin"))
+        );
+    }
+
     #[test]
     fn test_checkpoint_empty_state() {
         let state = HashMap::new();
@@ -752,6 +782,53 @@ mod tests {
         assert!(loaded.state.is_empty());
     }
 
+    #[test]
+    fn test_checkpoint_v1_format_still_loads() {
+        // Version 1 checkpoints serialized registers without `expiry_ms`.
+        let clock = LamportClock {
+            time: 3,
+            replica_id: ReplicaId::new(1),
+        };
+        let legacy = format_v1::CheckpointData {
+            state: [(
+                SDS::from("key"),
+                format_v1::ReplicatedValue {
+                    crdt: format_v1::CrdtValue::Lww(format_v1::LwwRegister {
+                        value: Some(SDS::from_str("old")),
+                        timestamp: clock,
+                        tombstone: false,
+                    }),
+                    vector_clock: None,
+                    expiry_ms: None,
+                    timestamp: clock,
+                    replication_factor: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let serialized = bincode::serialize(&legacy).unwrap();
+
+        let mut header = CheckpointHeader::new(1, 1000, 0, false);
+        header.version = 1;
+        header.header_checksum = header.compute_checksum();
+        let mut data = Vec::new();
+        header.write_to(&mut data).unwrap();
+        data.extend_from_slice(&(serialized.len() as u32).to_le_bytes());
+        data.extend_from_slice(&serialized);
+        CheckpointFooter::new(crc32fast::hash(&serialized), serialized.len() as u64)
+            .write_to(&mut data)
+            .unwrap();
+
+        let reader = CheckpointReader::open(&data).unwrap();
+        reader.validate().unwrap();
+        let loaded = reader.load().unwrap();
+        assert_eq!(
+            loaded.state.get(&SDS::from("key")).unwrap().get(),
+            Some(&SDS::from_str("old"))
+        );
+    }
+
     #[test]
     fn test_checkpoint_large_state() {
         let state = make_state(1000);
@@ -19,6 +19,7 @@
 //! based on segment ID ordering.
 
 use crate::io::{ProductionTimeSource, TimeSource};
+use crate::redis::SDS;
 use crate::replication::state::ReplicationDelta;
 use crate::streaming::{
     Compression, Manifest, ManifestError, ManifestManager, ObjectStore, SegmentError, SegmentInfo,
@@ -253,7 +254,7 @@ impl<S: ObjectStore + Clone + 'static, T: TimeSource> Compactor<S, T> {
 
         // Load all deltas from selected segments, handling missing files gracefully
         let mut deltas_before = 0u64;
-        let mut key_to_delta: HashMap<String, ReplicationDelta> = HashMap::new();
+        let mut key_to_delta: HashMap<SDS, ReplicationDelta> = HashMap::new();
         let mut bytes_before = 0u64;
         let mut actually_compacted: Vec<&SegmentInfo> = Vec::new();
         let mut missing_segments: Vec<String> = Vec::new();
@@ -607,7 +608,6 @@ impl<S: ObjectStore + Clone + 'static, T: TimeSource> CompactionWorker<S, T> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::redis::SDS;
     use crate::replication::lattice::{LamportClock, ReplicaId};
     use crate::replication::state::ReplicatedValue;
     use crate::streaming::InMemoryObjectStore;
@@ -619,7 +619,7 @@ mod tests {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     fn make_tombstone(key: &str, ts: u64, replica: u64) -> ReplicationDelta {
@@ -630,7 +630,7 @@ mod tests {
         };
         let mut replicated = ReplicatedValue::new(replica_id);
         replicated.delete(&mut clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     async fn write_segment(
@@ -265,7 +265,7 @@ impl CompactionWorkload {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     /// Create a delta for a delete operation (tombstone)
@@ -277,7 +277,7 @@ impl CompactionWorkload {
         };
         let mut replicated = ReplicatedValue::new(replica_id);
         replicated.delete(&mut clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 }
 
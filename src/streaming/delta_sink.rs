@@ -163,7 +163,7 @@ mod tests {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     #[test]
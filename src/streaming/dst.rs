@@ -211,7 +211,7 @@ impl StreamingWorkload {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     /// Create a delta for a delete operation (tombstone)
@@ -223,7 +223,7 @@ impl StreamingWorkload {
         };
         let mut replicated = ReplicatedValue::new(replica_id);
         replicated.delete(&mut clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 }
 
@@ -0,0 +1,117 @@
+//! Version 1 Persistence Format
+//!
+//! Segments and checkpoints written before hash field TTLs serialized
+//! `LwwRegister` without `expiry_ms`. bincode is not self-describing, so
+//! those bytes only decode through these mirror types, which are converted
+//! to the current types with no field deadlines.
+
+use crate::redis::SDS;
+use crate::replication::lattice::{
+    self, GCounter, GSet, HyperLogLog, LamportClock, ORSet, PNCounter, ReplicaId, VectorClock,
+};
+use crate::replication::state;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct LwwRegister {
+    pub value: Option<SDS>,
+    pub timestamp: LamportClock,
+    pub tombstone: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum CrdtValue {
+    Lww(LwwRegister),
+    GCounter(GCounter),
+    PNCounter(PNCounter),
+    GSet(GSet<String>),
+    ORSet(ORSet<String>),
+    Hash(HashMap<String, LwwRegister>),
+    HyperLogLog(HyperLogLog),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ReplicatedValue {
+    pub crdt: CrdtValue,
+    pub vector_clock: Option<VectorClock>,
+    pub expiry_ms: Option<u64>,
+    pub timestamp: LamportClock,
+    pub replication_factor: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ReplicationDelta {
+    pub key: SDS,
+    pub value: ReplicatedValue,
+    pub source_replica: ReplicaId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CheckpointData {
+    pub state: HashMap<SDS, ReplicatedValue>,
+}
+
+impl From<LwwRegister> for lattice::LwwRegister<SDS> {
+    fn from(reg: LwwRegister) -> Self {
+        lattice::LwwRegister {
+            value: reg.value,
+            timestamp: reg.timestamp,
+            tombstone: reg.tombstone,
+            expiry_ms: None,
+        }
+    }
+}
+
+impl From<CrdtValue> for state::CrdtValue {
+    fn from(crdt: CrdtValue) -> Self {
+        match crdt {
+            CrdtValue::Lww(reg) => state::CrdtValue::Lww(reg.into()),
+            CrdtValue::GCounter(c) => state::CrdtValue::GCounter(c),
+            CrdtValue::PNCounter(c) => state::CrdtValue::PNCounter(c),
+            CrdtValue::GSet(s) => state::CrdtValue::GSet(s),
+            CrdtValue::ORSet(s) => state::CrdtValue::ORSet(s),
+            CrdtValue::Hash(fields) => state::CrdtValue::Hash(
+                fields
+                    .into_iter()
+                    .map(|(field, reg)| (field, reg.into()))
+                    .collect(),
+            ),
+            CrdtValue::HyperLogLog(h) => state::CrdtValue::HyperLogLog(h),
+        }
+    }
+}
+
+impl From<ReplicatedValue> for state::ReplicatedValue {
+    fn from(value: ReplicatedValue) -> Self {
+        state::ReplicatedValue {
+            crdt: value.crdt.into(),
+            vector_clock: value.vector_clock,
+            expiry_ms: value.expiry_ms,
+            timestamp: value.timestamp,
+            replication_factor: value.replication_factor,
+        }
+    }
+}
+
+impl From<ReplicationDelta> for state::ReplicationDelta {
+    fn from(delta: ReplicationDelta) -> Self {
+        state::ReplicationDelta {
+            key: delta.key,
+            value: delta.value.into(),
+            source_replica: delta.source_replica,
+        }
+    }
+}
+
+impl From<CheckpointData> for crate::streaming::CheckpointData {
+    fn from(data: CheckpointData) -> Self {
+        crate::streaming::CheckpointData {
+            state: data
+                .state
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        }
+    }
+}
@@ -13,7 +13,7 @@
 //! 5. integration.shutdown().await
 //! ```
 
-use crate::production::ReplicatedShardedState;
+use crate::production::{LatencyMonitor, ReplicatedShardedState};
 #[cfg(feature = "s3")]
 use crate::streaming::S3ObjectStore;
 use crate::streaming::{
@@ -133,6 +133,8 @@ pub struct StreamingIntegration<S: ObjectStore + Clone + 'static> {
     config: StreamingConfig,
     replica_id: u64,
     prefix: String,
+    /// Receives `persistence-flush` samples
+    latency: Arc<LatencyMonitor>,
 }
 
 impl StreamingIntegration<InMemoryObjectStore> {
@@ -145,6 +147,7 @@ impl StreamingIntegration<InMemoryObjectStore> {
             config,
             replica_id,
             prefix,
+            latency: Arc::new(LatencyMonitor::default()),
         }
     }
 }
@@ -167,6 +170,7 @@ impl StreamingIntegration<LocalFsObjectStore> {
             config,
             replica_id,
             prefix,
+            latency: Arc::new(LatencyMonitor::default()),
         })
     }
 }
@@ -180,9 +184,15 @@ impl<S: ObjectStore + Clone + Send + Sync + 'static> StreamingIntegration<S> {
             config,
             replica_id,
             prefix,
+            latency: Arc::new(LatencyMonitor::default()),
         }
     }
 
+    /// Report persistence flush times to the server's LATENCY monitor
+    pub fn set_latency_monitor(&mut self, latency: Arc<LatencyMonitor>) {
+        self.latency = latency;
+    }
+
     /// Perform recovery and apply state to the provided ReplicatedShardedState
     ///
     /// Returns recovery statistics.
@@ -252,7 +262,7 @@ impl<S: ObjectStore + Clone + Send + Sync + 'static> StreamingIntegration<S> {
         let (sender, receiver) = delta_sink_channel();
 
         // Create StreamingPersistence (will be owned exclusively by actor)
-        let persistence = StreamingPersistence::new(
+        let mut persistence = StreamingPersistence::new(
             self.store.clone(),
             self.prefix.clone(),
             self.replica_id,
@@ -260,6 +270,7 @@ impl<S: ObjectStore + Clone + Send + Sync + 'static> StreamingIntegration<S> {
         )
         .await
         .map_err(|e| IntegrationError::Persistence(e.to_string()))?;
+        persistence.set_latency_monitor(self.latency.clone());
 
         // Spawn persistence actor (owns state, processes messages)
         let (actor_handle, actor_task) = spawn_persistence_actor(persistence);
@@ -377,6 +388,9 @@ pub async fn create_integration(
 
 /// Trait for type-erased streaming integration
 pub trait StreamingIntegrationTrait: Send + Sync {
+    /// Report persistence flush times to the server's LATENCY monitor
+    fn set_latency_monitor(&mut self, latency: Arc<LatencyMonitor>);
+
     /// Perform recovery
     fn recover<'a>(
         &'a self,
@@ -407,6 +421,15 @@ enum StreamingIntegrationWrapper {
 }
 
 impl StreamingIntegrationTrait for StreamingIntegrationWrapper {
+    fn set_latency_monitor(&mut self, latency: Arc<LatencyMonitor>) {
+        match self {
+            StreamingIntegrationWrapper::InMemory(i) => i.set_latency_monitor(latency),
+            StreamingIntegrationWrapper::LocalFs(i) => i.set_latency_monitor(latency),
+            #[cfg(feature = "s3")]
+            StreamingIntegrationWrapper::S3(i) => i.set_latency_monitor(latency),
+        }
+    }
+
     fn recover<'a>(
         &'a self,
         state: &'a ReplicatedShardedState,
@@ -451,7 +474,7 @@ use tokio::sync::{mpsc, oneshot};
 #[derive(Debug)]
 pub enum PersistenceMessage {
     /// Push a delta to the buffer
-    PushDelta(ReplicationDelta),
+    PushDelta(Box<ReplicationDelta>),
     /// Push multiple deltas (batch)
     PushDeltas(Vec<ReplicationDelta>),
     /// Force a flush
@@ -482,7 +505,7 @@ impl<S: ObjectStore + Clone + Send + Sync + 'static> PersistenceActor<S> {
         while let Some(msg) = self.rx.recv().await {
             match msg {
                 PersistenceMessage::PushDelta(delta) => {
-                    if let Err(e) = self.persistence.push(delta) {
+                    if let Err(e) = self.persistence.push(*delta) {
                         error!("Failed to push delta: {}", e);
                     }
                     // Auto-flush if needed
@@ -548,7 +571,7 @@ impl PersistenceActorHandle {
     /// Push a delta (fire-and-forget)
     #[inline]
     pub fn push_delta(&self, delta: ReplicationDelta) {
-        let _ = self.tx.send(PersistenceMessage::PushDelta(delta));
+        let _ = self.tx.send(PersistenceMessage::PushDelta(Box::new(delta)));
     }
 
     /// Push multiple deltas (fire-and-forget)
@@ -26,6 +26,7 @@ pub mod compaction_dst;
 pub mod config;
 pub mod delta_sink;
 pub mod dst;
+mod format_v1;
 pub mod integration;
 pub mod manifest;
 pub mod object_store;
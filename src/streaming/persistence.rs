@@ -22,6 +22,7 @@
 //! All I/O through ObjectStore trait. Time through StreamingClock trait.
 //! Both abstractions enable deterministic simulation testing.
 
+use crate::production::{LatencyMonitor, PERSISTENCE_FLUSH};
 use crate::replication::state::ReplicationDelta;
 use crate::streaming::{
     Compression, Manifest, ManifestError, ManifestManager, ObjectStore, ProductionClock,
@@ -131,6 +132,8 @@ pub struct StreamingPersistence<
     last_flush: StreamingTimestamp,
     /// Statistics
     stats: PersistenceStats,
+    /// LATENCY monitor of the server being persisted
+    latency: Arc<LatencyMonitor>,
 }
 
 impl<S: ObjectStore + Clone + 'static> StreamingPersistence<S, ProductionClock> {
@@ -186,9 +189,15 @@ impl<S: ObjectStore + Clone + 'static, C: StreamingClock> StreamingPersistence<S
             clock,
             last_flush,
             stats: PersistenceStats::default(),
+            latency: Arc::new(LatencyMonitor::default()),
         })
     }
 
+    /// Report flush times to `latency` (off until set)
+    pub fn set_latency_monitor(&mut self, latency: Arc<LatencyMonitor>) {
+        self.latency = latency;
+    }
+
     /// Push a delta to the buffer
     ///
     /// Returns error if backpressure threshold is exceeded.
@@ -247,7 +256,8 @@ impl<S: ObjectStore + Clone + 'static, C: StreamingClock> StreamingPersistence<S
 
     /// Flush buffer to object store and update manifest
     ///
-    /// Returns info about the flush operation.
+    /// Returns info about the flush operation. Wall time of non-empty flushes
+    /// is reported to LATENCY as `persistence-flush`.
     pub async fn flush(&mut self) -> Result<FlushResult, PersistenceError> {
         if self.buffer.is_empty() {
             return Ok(FlushResult {
@@ -256,7 +266,14 @@ impl<S: ObjectStore + Clone + 'static, C: StreamingClock> StreamingPersistence<S
                 bytes_written: 0,
             });
         }
+        let started = std::time::Instant::now();
+        let result = self.write_segment().await;
+        self.latency
+            .add_sample(PERSISTENCE_FLUSH, started.elapsed());
+        result
+    }
 
+    async fn write_segment(&mut self) -> Result<FlushResult, PersistenceError> {
         let deltas = std::mem::take(&mut self.buffer);
         let deltas_count = deltas.len();
         self.buffer_size = 0;
@@ -453,7 +470,7 @@ mod tests {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     #[tokio::test]
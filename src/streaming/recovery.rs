@@ -17,6 +17,7 @@
 //! All I/O through ObjectStore trait. Recovery is deterministic given
 //! the same object store state.
 
+use crate::redis::SDS;
 use crate::replication::state::{ReplicatedValue, ReplicationDelta};
 use crate::streaming::{
     CheckpointError, CheckpointReader, Manifest, ManifestError, ManifestManager, ObjectStore,
@@ -125,7 +126,7 @@ pub struct RecoveredState {
     /// Manifest that was loaded
     pub manifest: Manifest,
     /// Checkpoint state (if checkpoint was loaded)
-    pub checkpoint_state: Option<HashMap<String, ReplicatedValue>>,
+    pub checkpoint_state: Option<HashMap<SDS, ReplicatedValue>>,
     /// Deltas to replay after checkpoint (in order)
     pub deltas: Vec<ReplicationDelta>,
     /// Recovery statistics
@@ -352,7 +353,6 @@ impl<S: ObjectStore + Clone + 'static> RecoveryManager<S> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::redis::SDS;
     use crate::replication::lattice::{LamportClock, ReplicaId};
     use crate::replication::state::ReplicatedValue;
     use crate::streaming::{
@@ -366,7 +366,7 @@ mod tests {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     async fn write_segment(
@@ -26,6 +26,7 @@
 //! └──────────────────────────────────┘
 //! ```
 
+use super::format_v1;
 use crate::replication::state::ReplicationDelta;
 use serde::{Deserialize, Serialize};
 
@@ -34,7 +35,10 @@ pub const SEGMENT_MAGIC: [u8; 4] = *b"RSEG";
 /// Reversed magic for footer validation
 pub const FOOTER_MAGIC: [u8; 4] = *b"GESR";
 /// Current segment format version
-pub const SEGMENT_VERSION: u8 = 1;
+///
+/// Version 2 added `expiry_ms` to `LwwRegister` (hash field TTLs).
+/// Version 1 segments are still read through `format_v1`.
+pub const SEGMENT_VERSION: u8 = 2;
 
 /// Header size in bytes
 const HEADER_SIZE: usize = 40;
@@ -179,7 +183,7 @@ impl SegmentHeader {
         if self.magic != SEGMENT_MAGIC {
             return Err(SegmentError::InvalidMagic);
         }
-        if self.version != SEGMENT_VERSION {
+        if self.version == 0 || self.version > SEGMENT_VERSION {
             return Err(SegmentError::UnsupportedVersion(self.version));
         }
         let expected = self.compute_checksum();
@@ -527,6 +531,7 @@ impl SegmentReader {
         let data = self.decompress_data()?;
         Ok(DeltaIterator {
             data,
+            version: self.segment.header.version,
             offset: 0,
             remaining: self.segment.header.record_count,
         })
@@ -541,6 +546,7 @@ impl SegmentReader {
 /// Iterator over deltas in a segment
 pub struct DeltaIterator {
     data: Vec<u8>,
+    version: u8,
     offset: usize,
     remaining: u32,
 }
@@ -573,7 +579,12 @@ impl Iterator for DeltaIterator {
         }
 
         // Deserialize delta
-        let result = bincode::deserialize(&self.data[self.offset..self.offset + len]);
+        let record = &self.data[self.offset..self.offset + len];
+        let result = if self.version == 1 {
+            bincode::deserialize::<format_v1::ReplicationDelta>(record).map(Into::into)
+        } else {
+            bincode::deserialize(record)
+        };
         self.offset += len;
         self.remaining -= 1;
 
@@ -595,7 +606,7 @@ mod tests {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     #[test]
@@ -630,6 +641,107 @@ mod tests {
         assert_eq!(deltas[2].key, "key3");
     }
 
+    #[test]
+    fn test_segment_roundtrip_non_utf8_key() {
+        let mut writer = SegmentWriter::new(Compression::None);
+        let key: &[u8] = b"\x0a\x03\xff\xfe\x80";
+        let clock = LamportClock {
+            time: 1,
+            replica_id: ReplicaId::new(1),
+        };
+        let delta = ReplicationDelta::new(
+            SDS::from(key),
+            ReplicatedValue::with_value(SDS::from_str("v"), clock),
+            ReplicaId::new(1),
+        );
+        writer.write_delta(&delta).unwrap();
+
+        let data = writer.finish().unwrap();
+        let deltas = SegmentReader::open(&data).unwrap().read_all().unwrap();
+        assert_eq!(deltas[0].key.as_bytes(), key);
+    }
+
+    #[test]
+    fn test_segment_v1_format_still_decodes() {
+        // Assemble a segment exactly as version 1 wrote it: registers
+        // serialized without `expiry_ms`.
+        let replica_id = ReplicaId::new(1);
+        let clock = LamportClock {
+            time: 7,
+            replica_id,
+        };
+        let register = |value: &str| format_v1::LwwRegister {
+            value: Some(SDS::from_str(value)),
+            timestamp: clock,
+            tombstone: false,
+        };
+        let value = |crdt| format_v1::ReplicatedValue {
+            crdt,
+            vector_clock: None,
+            expiry_ms: None,
+            timestamp: clock,
+            replication_factor: None,
+        };
+        let legacy = [
+            format_v1::ReplicationDelta {
+                key: SDS::from("str"),
+                value: value(format_v1::CrdtValue::Lww(register("v1"))),
+                source_replica: replica_id,
+            },
+            format_v1::ReplicationDelta {
+                key: SDS::from("hash"),
+                value: value(format_v1::CrdtValue::Hash(
+                    [("f".to_string(), register("fv"))].into_iter().collect(),
+                )),
+                source_replica: replica_id,
+            },
+        ];
+
+        let mut records = Vec::new();
+        for delta in &legacy {
+            let bytes = bincode::serialize(delta).unwrap();
+            records.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            records.extend_from_slice(&bytes);
+        }
+        let mut header = SegmentHeader::new(2, 7, 7, Compression::None);
+        header.version = 1;
+        header.header_checksum = header.compute_checksum();
+        let footer = SegmentFooter::new(
+            crc32fast::hash(&records),
+            records.len() as u64,
+            records.len() as u64,
+        );
+        let mut data = header.to_bytes();
+        data.extend_from_slice(&records);
+        data.extend_from_slice(&footer.to_bytes());
+
+        let reader = SegmentReader::open(&data).unwrap();
+        reader.validate().unwrap();
+        let deltas = reader.read_all().unwrap();
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].key, "str");
+        assert_eq!(deltas[0].value.get(), Some(&SDS::from_str("v1")));
+        match &deltas[1].value.crdt {
+            crate::replication::state::CrdtValue::Hash(fields) => {
+                let field = &fields["f"];
+                assert_eq!(field.get(), Some(&SDS::from_str("fv")));
+                assert_eq!(field.expiry_ms, None);
+            }
+            other => panic!("expected hash, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_segment_future_version_rejected() {
+        let mut header = SegmentHeader::new(1, 0, 0, Compression::None);
+        header.version = SEGMENT_VERSION + 1;
+        header.header_checksum = header.compute_checksum();
+        assert!(matches!(
+            header.validate(),
+            Err(SegmentError::UnsupportedVersion(v)) if v == SEGMENT_VERSION + 1
+        ));
+    }
+
     #[test]
     fn test_segment_empty_error() {
         let writer = SegmentWriter::new(Compression::None);
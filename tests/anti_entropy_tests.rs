@@ -249,13 +249,13 @@ fn test_merkle_digest_comparison() {
     let r2 = ReplicaId::new(2);
 
     // Create two key sets with some overlap
-    let mut keys1: HashMap<String, ReplicatedValue> = HashMap::new();
-    keys1.insert("shared".to_string(), make_value("same", 1, r1));
-    keys1.insert("only1".to_string(), make_value("val1", 2, r1));
+    let mut keys1: HashMap<SDS, ReplicatedValue> = HashMap::new();
+    keys1.insert("shared".into(), make_value("same", 1, r1));
+    keys1.insert("only1".into(), make_value("val1", 2, r1));
 
-    let mut keys2: HashMap<String, ReplicatedValue> = HashMap::new();
-    keys2.insert("shared".to_string(), make_value("same", 1, r1));
-    keys2.insert("only2".to_string(), make_value("val2", 3, r2));
+    let mut keys2: HashMap<SDS, ReplicatedValue> = HashMap::new();
+    keys2.insert("shared".into(), make_value("same", 1, r1));
+    keys2.insert("only2".into(), make_value("val2", 3, r2));
 
     let digest1 = StateDigest::from_state(&keys1, r1, 1, 4);
     let digest2 = StateDigest::from_state(&keys2, r2, 1, 4);
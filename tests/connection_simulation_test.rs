@@ -66,12 +66,12 @@ fn test_pipeline_ordering() {
 
     conn.send_pipeline(vec![
         Command::set("counter".to_string(), SDS::from_str("0")),
-        Command::Incr("counter".to_string()),
-        Command::Incr("counter".to_string()),
-        Command::Incr("counter".to_string()),
-        Command::Incr("counter".to_string()),
-        Command::Incr("counter".to_string()),
-        Command::Get("counter".to_string()),
+        Command::Incr("counter".into()),
+        Command::Incr("counter".into()),
+        Command::Incr("counter".into()),
+        Command::Incr("counter".into()),
+        Command::Incr("counter".into()),
+        Command::Get("counter".into()),
     ]);
 
     let responses = conn.process();
@@ -100,11 +100,11 @@ fn test_mixed_pipeline() {
     conn.send_pipeline(vec![
         Command::set("a".to_string(), SDS::from_str("1")),
         Command::set("b".to_string(), SDS::from_str("2")),
-        Command::Get("a".to_string()),
-        Command::Get("b".to_string()),
-        Command::Get("nonexistent".to_string()),
+        Command::Get("a".into()),
+        Command::Get("b".into()),
+        Command::Get("nonexistent".into()),
         Command::del("a".to_string()),
-        Command::Get("a".to_string()),
+        Command::Get("a".into()),
     ]);
 
     let responses = conn.process();
@@ -210,7 +210,7 @@ fn test_1000_seeds_connection_correctness() {
                 if i % 2 == 0 {
                     Command::set(format!("k{}", i), SDS::from_str(&format!("v{}", i)))
                 } else {
-                    Command::Get(format!("k{}", i - 1))
+                    Command::Get(SDS::from(format!("k{}", i - 1)))
                 }
             })
             .collect();
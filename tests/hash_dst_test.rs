@@ -92,6 +92,7 @@ fn test_hash_dst_high_delete_rate() {
         num_values: 20,
         delete_prob: 0.5,
         update_prob: 0.2,
+        expire_prob: 0.1,
     };
 
     let mut harness = HashDSTHarness::new(config);
@@ -110,6 +111,7 @@ fn test_hash_dst_tiny_field_space() {
         num_values: 10,
         delete_prob: 0.2,
         update_prob: 0.6,
+        expire_prob: 0.1,
     };
 
     let mut harness = HashDSTHarness::new(config);
@@ -128,6 +130,7 @@ fn test_hash_dst_mostly_updates() {
         num_values: 100,
         delete_prob: 0.1,
         update_prob: 0.7,
+        expire_prob: 0.1,
     };
 
     let mut harness = HashDSTHarness::new(config);
@@ -146,6 +149,7 @@ fn test_hash_dst_large_field_space() {
         num_values: 50,
         delete_prob: 0.1,
         update_prob: 0.1,
+        expire_prob: 0.1,
     };
 
     let mut harness = HashDSTHarness::new(config);
@@ -91,6 +91,9 @@ fn test_list_dst_mostly_pops() {
         left_prob: 0.5,
         lset_prob: 0.02,
         trim_prob: 0.01,
+        insert_prob: 0.05,
+        rem_prob: 0.03,
+        pos_prob: 0.05,
     };
 
     let mut harness = ListDSTHarness::new(config);
@@ -110,6 +113,9 @@ fn test_list_dst_heavy_trim() {
         left_prob: 0.5,
         lset_prob: 0.05,
         trim_prob: 0.15,
+        insert_prob: 0.05,
+        rem_prob: 0.03,
+        pos_prob: 0.05,
     };
 
     let mut harness = ListDSTHarness::new(config);
@@ -129,6 +135,9 @@ fn test_list_dst_heavy_lset() {
         left_prob: 0.5,
         lset_prob: 0.25,
         trim_prob: 0.02,
+        insert_prob: 0.05,
+        rem_prob: 0.03,
+        pos_prob: 0.05,
     };
 
     let mut harness = ListDSTHarness::new(config);
@@ -138,6 +147,37 @@ fn test_list_dst_heavy_lset() {
     assert!(result.is_success(), "Heavy lset should maintain invariants");
 }
 
+#[test]
+fn test_list_dst_heavy_insert_rem_pos() {
+    // Few distinct values so LINSERT pivots, LREM and LPOS hit duplicates often
+    let config = ListDSTConfig {
+        seed: 22222,
+        num_values: 8,
+        pop_prob: 0.1,
+        left_prob: 0.5,
+        lset_prob: 0.02,
+        trim_prob: 0.01,
+        insert_prob: 0.2,
+        rem_prob: 0.1,
+        pos_prob: 0.2,
+    };
+
+    let mut harness = ListDSTHarness::new(config);
+    harness.run(2000);
+    let result = harness.result();
+    println!(
+        "Heavy insert/rem/pos: {} (linsert: {}, lrem: {}, lpos: {})",
+        result.summary(),
+        result.inserts,
+        result.rems,
+        result.positions
+    );
+    assert!(
+        result.is_success(),
+        "LINSERT/LREM/LPOS should match the model"
+    );
+}
+
 // =============================================================================
 // Mixed Configuration Tests
 // =============================================================================
@@ -306,6 +306,427 @@ fn test_numeric_equivalence() {
     assert_eq!(tester.failed, 0, "Some numeric operations differed");
 }
 
+/// Test bitmap operations (SETBIT, BITCOUNT, BITPOS, BITOP, BITFIELD)
+#[test]
+#[ignore]
+fn test_bitmap_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== Bitmap Operations ===");
+
+    tester.cleanup();
+
+    // SETBIT / GETBIT, growing the string
+    tester.test(&["SETBIT", "bm", "7", "1"]);
+    tester.test(&["SETBIT", "bm", "7", "0"]);
+    tester.test(&["SETBIT", "bm", "100", "1"]);
+    tester.test(&["GETBIT", "bm", "100"]);
+    tester.test(&["GETBIT", "bm", "100000"]);
+    tester.test(&["GETBIT", "nonexistent", "0"]);
+    tester.test(&["STRLEN", "bm"]);
+    tester.test(&["SETBIT", "bm", "1", "2"]);
+    tester.test(&["SETBIT", "bm", "-1", "1"]);
+
+    // BITCOUNT with BYTE and BIT ranges
+    tester.setup(&["SET", "fb", "foobar"]);
+    tester.test(&["BITCOUNT", "fb"]);
+    tester.test(&["BITCOUNT", "fb", "0", "0"]);
+    tester.test(&["BITCOUNT", "fb", "1", "1"]);
+    tester.test(&["BITCOUNT", "fb", "-2", "-1"]);
+    tester.test(&["BITCOUNT", "fb", "1", "1", "BYTE"]);
+    tester.test(&["BITCOUNT", "fb", "5", "30", "BIT"]);
+    tester.test(&["BITCOUNT", "fb", "-1", "-2"]);
+    tester.test(&["BITCOUNT", "fb", "0", "100"]);
+    tester.test(&["BITCOUNT", "nonexistent"]);
+
+    // BITPOS
+    tester.setup(&["SET", "bp", "\u{7f}\u{70}\u{00}"]);
+    tester.test(&["BITPOS", "bp", "0"]);
+    tester.test(&["BITPOS", "bp", "1", "2"]);
+    tester.test(&["BITPOS", "bp", "1", "7", "15", "BIT"]);
+    tester.test(&["BITPOS", "fb", "1", "1", "-1"]);
+    tester.test(&["BITPOS", "fb", "0", "0", "-1", "BIT"]);
+    tester.test(&["BITPOS", "nonexistent", "0"]);
+    tester.test(&["BITPOS", "nonexistent", "1"]);
+    tester.setup(&["SET", "ones", "\u{7f}"]);
+    tester.test(&["BITPOS", "ones", "0"]);
+
+    // BITOP
+    tester.setup(&["SET", "{b}a", "foobar"]);
+    tester.setup(&["SET", "{b}b", "abcdef"]);
+    tester.test(&["BITOP", "AND", "{b}dest", "{b}a", "{b}b"]);
+    tester.test(&["GET", "{b}dest"]);
+    tester.test(&["BITOP", "OR", "{b}dest", "{b}a", "{b}b"]);
+    tester.test(&["GET", "{b}dest"]);
+    tester.test(&["BITOP", "XOR", "{b}dest", "{b}a", "{b}missing"]);
+    tester.test(&["BITOP", "NOT", "{b}dest", "{b}missing"]);
+    tester.test(&["EXISTS", "{b}dest"]);
+    tester.test(&["BITOP", "NOT", "{b}dest", "{b}a", "{b}b"]);
+
+    // BITFIELD / BITFIELD_RO with overflow modes
+    tester.test(&[
+        "BITFIELD", "bf", "INCRBY", "i5", "100", "1", "GET", "u4", "0",
+    ]);
+    tester.test(&[
+        "BITFIELD", "bf", "SET", "i8", "#1", "-128", "GET", "u8", "#1",
+    ]);
+    for _ in 0..4 {
+        tester.test(&[
+            "BITFIELD", "ov", "INCRBY", "u2", "100", "1", "OVERFLOW", "SAT", "INCRBY", "u2", "102",
+            "1",
+        ]);
+    }
+    tester.test(&[
+        "BITFIELD", "ov", "OVERFLOW", "FAIL", "INCRBY", "u2", "102", "1",
+    ]);
+    tester.test(&["BITFIELD", "ov", "OVERFLOW", "WRAP", "SET", "i4", "0", "9"]);
+    tester.test(&["BITFIELD_RO", "bf", "GET", "i8", "8"]);
+    tester.test(&["BITFIELD_RO", "nonexistent", "GET", "u8", "0"]);
+    tester.test(&["EXISTS", "nonexistent"]);
+    tester.test(&["BITFIELD", "bf", "GET", "u64", "0"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some bitmap operations differed");
+}
+
+/// Test HyperLogLog operations, including the raw encoding seen through GET
+#[test]
+#[ignore]
+fn test_hyperloglog_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== HyperLogLog Operations ===");
+
+    tester.cleanup();
+
+    // PFADD creates the key (sparse encoding) even without elements
+    tester.test(&["PFADD", "hll"]);
+    tester.test(&["GET", "hll"]);
+    tester.test(&["PFADD", "hll", "a", "b", "c", "d"]);
+    tester.test(&["PFADD", "hll", "a", "b"]);
+    tester.test(&["GET", "hll"]);
+    tester.test(&["PFCOUNT", "hll"]);
+    // The cached cardinality is now part of the value
+    tester.test(&["GET", "hll"]);
+    tester.test(&["PFCOUNT", "nonexistent"]);
+    tester.test(&["TYPE", "hll"]);
+
+    // Enough elements to promote to the dense encoding
+    let elements: Vec<String> = (0..3000).map(|i| format!("visitor:{}", i)).collect();
+    let mut args = vec!["PFADD", "{h}big"];
+    args.extend(elements.iter().map(String::as_str));
+    tester.test(&args);
+    tester.test(&["STRLEN", "{h}big"]);
+    tester.test(&["PFCOUNT", "{h}big"]);
+    tester.test(&["GET", "{h}big"]);
+
+    // PFMERGE and multi-key PFCOUNT
+    tester.setup(&["PFADD", "{h}a", "x", "y", "z"]);
+    tester.setup(&["PFADD", "{h}b", "z", "w"]);
+    tester.test(&["PFCOUNT", "{h}a", "{h}b", "{h}missing"]);
+    tester.test(&["PFMERGE", "{h}dest", "{h}a", "{h}b"]);
+    tester.test(&["GET", "{h}dest"]);
+    tester.test(&["PFCOUNT", "{h}dest"]);
+    tester.test(&["PFMERGE", "{h}dense", "{h}a", "{h}big"]);
+    tester.test(&["GET", "{h}dense"]);
+    tester.test(&["PFMERGE", "{h}empty"]);
+    tester.test(&["GET", "{h}empty"]);
+
+    // Errors
+    tester.setup(&["SET", "{h}str", "not an hll"]);
+    tester.setup(&["LPUSH", "{h}list", "x"]);
+    tester.test(&["PFADD", "{h}str", "a"]);
+    tester.test(&["PFCOUNT", "{h}str"]);
+    tester.test(&["PFMERGE", "{h}dest", "{h}str"]);
+    tester.test(&["PFADD", "{h}list", "a"]);
+    tester.test(&["PFCOUNT", "{h}a", "{h}list"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some HyperLogLog operations differed");
+}
+
+/// Test geospatial operations, including the geohash scores they store
+#[test]
+#[ignore]
+fn test_geo_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== Geo Operations ===");
+
+    tester.cleanup();
+
+    tester.test(&[
+        "GEOADD",
+        "{g}sicily",
+        "13.361389",
+        "38.115556",
+        "Palermo",
+        "15.087269",
+        "37.502669",
+        "Catania",
+        "12.758489",
+        "38.788135",
+        "edge1",
+        "17.241510",
+        "38.788135",
+        "edge2",
+    ]);
+    tester.test(&["ZRANGE", "{g}sicily", "0", "-1"]);
+    tester.test(&[
+        "GEOADD",
+        "{g}sicily",
+        "NX",
+        "CH",
+        "13",
+        "38",
+        "Palermo",
+        "14",
+        "37",
+        "x",
+    ]);
+    tester.test(&["GEOADD", "{g}sicily", "XX", "CH", "14.5", "37.5", "x"]);
+    tester.test(&["GEOPOS", "{g}sicily", "Palermo", "x", "missing"]);
+    tester.test(&["GEOPOS", "{g}nokey", "a"]);
+    tester.test(&["GEODIST", "{g}sicily", "Palermo", "Catania"]);
+    tester.test(&["GEODIST", "{g}sicily", "Palermo", "Catania", "mi"]);
+    tester.test(&["GEODIST", "{g}sicily", "Palermo", "missing"]);
+    tester.test(&["GEOHASH", "{g}sicily", "Palermo", "Catania", "missing"]);
+
+    // Searches, sorted so that discovery order does not matter
+    tester.test(&[
+        "GEOSEARCH",
+        "{g}sicily",
+        "FROMLONLAT",
+        "15",
+        "37",
+        "BYRADIUS",
+        "200",
+        "km",
+        "ASC",
+    ]);
+    tester.test(&[
+        "GEOSEARCH",
+        "{g}sicily",
+        "FROMLONLAT",
+        "15",
+        "37",
+        "BYBOX",
+        "400",
+        "400",
+        "km",
+        "DESC",
+        "WITHCOORD",
+        "WITHDIST",
+        "WITHHASH",
+    ]);
+    tester.test(&[
+        "GEOSEARCH",
+        "{g}sicily",
+        "FROMMEMBER",
+        "Palermo",
+        "BYRADIUS",
+        "300",
+        "km",
+        "COUNT",
+        "2",
+    ]);
+    tester.test(&[
+        "GEOSEARCH",
+        "{g}sicily",
+        "FROMMEMBER",
+        "missing",
+        "BYRADIUS",
+        "300",
+        "km",
+    ]);
+    tester.test(&[
+        "GEORADIUS",
+        "{g}sicily",
+        "15",
+        "37",
+        "200",
+        "km",
+        "WITHDIST",
+        "ASC",
+    ]);
+    tester.test(&[
+        "GEORADIUSBYMEMBER_RO",
+        "{g}sicily",
+        "Catania",
+        "250",
+        "km",
+        "ASC",
+        "COUNT",
+        "3",
+    ]);
+    tester.test(&[
+        "GEOSEARCHSTORE",
+        "{g}dest",
+        "{g}sicily",
+        "FROMLONLAT",
+        "15",
+        "37",
+        "BYRADIUS",
+        "200",
+        "km",
+    ]);
+    tester.test(&["ZRANGE", "{g}dest", "0", "-1"]);
+    tester.test(&[
+        "GEOSEARCHSTORE",
+        "{g}dest",
+        "{g}sicily",
+        "FROMLONLAT",
+        "0",
+        "0",
+        "BYRADIUS",
+        "1",
+        "m",
+    ]);
+    tester.test(&["EXISTS", "{g}dest"]);
+
+    // Errors
+    tester.test(&["GEOADD", "{g}sicily", "200", "100", "bad"]);
+    tester.test(&["GEOADD", "{g}sicily", "NX", "XX", "1", "2", "a"]);
+    tester.test(&["GEODIST", "{g}sicily", "Palermo", "Catania", "yards"]);
+    tester.test(&[
+        "GEOSEARCH",
+        "{g}sicily",
+        "FROMLONLAT",
+        "15",
+        "37",
+        "BYRADIUS",
+        "-1",
+        "km",
+    ]);
+    tester.test(&[
+        "GEOSEARCH",
+        "{g}sicily",
+        "FROMLONLAT",
+        "15",
+        "37",
+        "BYRADIUS",
+        "1",
+        "km",
+        "ANY",
+    ]);
+    tester.setup(&["SET", "{g}str", "x"]);
+    tester.test(&["GEOPOS", "{g}str", "a"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some geo operations differed");
+}
+
+#[test]
+#[ignore]
+fn test_sorted_set_commands_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== Sorted Set Commands ===");
+
+    tester.cleanup();
+
+    tester.test(&[
+        "ZADD", "{z}a", "1", "a", "2", "b", "3", "c", "4", "d", "5", "e",
+    ]);
+    tester.test(&[
+        "ZADD", "{z}lex", "0", "a", "0", "b", "0", "c", "0", "d", "0", "e",
+    ]);
+
+    // Ranges
+    tester.test(&["ZRANGE", "{z}a", "1", "-2", "WITHSCORES"]);
+    tester.test(&["ZRANGE", "{z}a", "0", "-1", "REV"]);
+    tester.test(&["ZRANGE", "{z}a", "(1", "4", "BYSCORE", "LIMIT", "1", "2"]);
+    tester.test(&[
+        "ZRANGE",
+        "{z}a",
+        "+inf",
+        "-inf",
+        "BYSCORE",
+        "REV",
+        "WITHSCORES",
+    ]);
+    tester.test(&["ZRANGE", "{z}lex", "[b", "(e", "BYLEX"]);
+    tester.test(&[
+        "ZRANGE", "{z}lex", "+", "-", "BYLEX", "REV", "LIMIT", "0", "2",
+    ]);
+    tester.test(&["ZREVRANGEBYSCORE", "{z}a", "4", "2", "WITHSCORES"]);
+    tester.test(&["ZRANGEBYLEX", "{z}lex", "-", "[c"]);
+    tester.test(&["ZREVRANGEBYLEX", "{z}lex", "+", "(b", "LIMIT", "1", "10"]);
+    tester.test(&["ZLEXCOUNT", "{z}lex", "[b", "+"]);
+    tester.test(&["ZRANGESTORE", "{z}dst", "{z}a", "2", "5", "BYSCORE"]);
+    tester.test(&["ZRANGE", "{z}dst", "0", "-1", "WITHSCORES"]);
+
+    // Scores and ranks
+    tester.test(&["ZINCRBY", "{z}a", "2.5", "a"]);
+    tester.test(&["ZINCRBY", "{z}a", "1", "new"]);
+    tester.test(&["ZMSCORE", "{z}a", "a", "missing", "new"]);
+    tester.test(&["ZREVRANK", "{z}a", "a"]);
+    tester.test(&["ZREVRANK", "{z}a", "missing"]);
+
+    // Removals and pops
+    tester.test(&["ZREMRANGEBYRANK", "{z}dst", "0", "0"]);
+    tester.test(&["ZREMRANGEBYSCORE", "{z}dst", "(3", "+inf"]);
+    tester.test(&["ZREMRANGEBYLEX", "{z}lex", "[d", "+"]);
+    tester.test(&["ZPOPMIN", "{z}a"]);
+    tester.test(&["ZPOPMAX", "{z}a", "2"]);
+    tester.test(&["ZPOPMIN", "{z}missing"]);
+    tester.test(&["ZRANDMEMBER", "{z}missing"]);
+    tester.test(&["ZRANDMEMBER", "{z}a", "0"]);
+
+    // Set operations
+    tester.test(&["ZADD", "{z}b", "10", "b", "20", "x"]);
+    tester.test(&["ZUNION", "2", "{z}a", "{z}b", "WITHSCORES"]);
+    tester.test(&[
+        "ZINTER",
+        "2",
+        "{z}a",
+        "{z}b",
+        "WEIGHTS",
+        "2",
+        "0.5",
+        "WITHSCORES",
+    ]);
+    tester.test(&[
+        "ZUNION",
+        "2",
+        "{z}a",
+        "{z}b",
+        "AGGREGATE",
+        "MAX",
+        "WITHSCORES",
+    ]);
+    tester.test(&["ZDIFF", "2", "{z}a", "{z}b", "WITHSCORES"]);
+    tester.test(&[
+        "ZUNIONSTORE",
+        "{z}u",
+        "2",
+        "{z}a",
+        "{z}b",
+        "AGGREGATE",
+        "MIN",
+    ]);
+    tester.test(&["ZRANGE", "{z}u", "0", "-1", "WITHSCORES"]);
+    tester.test(&["ZINTERSTORE", "{z}i", "2", "{z}a", "{z}missing"]);
+    tester.test(&["EXISTS", "{z}i"]);
+    tester.test(&["ZDIFFSTORE", "{z}d", "1", "{z}b"]);
+
+    // Errors
+    tester.test(&["ZRANGE", "{z}a", "0", "-1", "LIMIT", "0", "1"]);
+    tester.test(&["ZRANGE", "{z}lex", "a", "b", "BYLEX"]);
+    tester.test(&["ZRANGEBYSCORE", "{z}a", "x", "1"]);
+    tester.test(&["ZINCRBY", "{z}a", "nan", "a"]);
+    tester.test(&["ZUNION", "0", "{z}a"]);
+    tester.test(&["ZINTER", "2", "{z}a", "{z}b", "WEIGHTS", "1"]);
+    tester.test(&["ZPOPMIN", "{z}a", "-1"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some sorted set operations differed");
+}
+
 /// Test hash operations
 #[test]
 #[ignore]
@@ -453,6 +874,361 @@ fn test_list_equivalence() {
     assert_eq!(tester.failed, 0, "Some list operations differed");
 }
 
+/// Test LPOS, LINSERT, LREM, LPUSHX/RPUSHX, LPOP/RPOP with a count and LMPOP
+#[test]
+#[ignore]
+fn test_list_commands_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== List Commands ===");
+
+    tester.cleanup();
+
+    // LPUSHX/RPUSHX never create the key
+    tester.test(&["LPUSHX", "l", "a"]);
+    tester.test(&["EXISTS", "l"]);
+    tester.test(&["RPUSH", "l", "a", "b", "c", "1", "2", "3", "c", "c"]);
+    tester.test(&["RPUSHX", "l", "d", "e"]);
+    tester.test(&["LPUSHX", "l", "z"]);
+
+    // LPOS with RANK, COUNT and MAXLEN
+    tester.test(&["LPOS", "l", "c"]);
+    tester.test(&["LPOS", "l", "c", "RANK", "2"]);
+    tester.test(&["LPOS", "l", "c", "RANK", "-1"]);
+    tester.test(&["LPOS", "l", "c", "RANK", "5"]);
+    tester.test(&["LPOS", "l", "c", "COUNT", "0"]);
+    tester.test(&["LPOS", "l", "c", "COUNT", "2", "RANK", "-1"]);
+    tester.test(&["LPOS", "l", "c", "COUNT", "0", "MAXLEN", "4"]);
+    tester.test(&["LPOS", "l", "nope"]);
+    tester.test(&["LPOS", "l", "nope", "COUNT", "1"]);
+    tester.test(&["LPOS", "missing", "c"]);
+    tester.test(&["LPOS", "missing", "c", "COUNT", "0"]);
+    tester.test(&["LPOS", "l", "c", "RANK", "0"]);
+    tester.test(&["LPOS", "l", "c", "COUNT", "-1"]);
+    tester.test(&["LPOS", "l", "c", "MAXLEN", "-1"]);
+    tester.test(&["LPOS", "l", "c", "COUNT"]);
+
+    // LINSERT: -1 for a missing pivot, 0 for a missing key
+    tester.test(&["LINSERT", "l", "AFTER", "c", "X"]);
+    tester.test(&["LINSERT", "l", "before", "z", "Y"]);
+    tester.test(&["LINSERT", "l", "BEFORE", "nope", "Y"]);
+    tester.test(&["LINSERT", "missing", "BEFORE", "a", "Y"]);
+    tester.test(&["LINSERT", "l", "AROUND", "a", "Y"]);
+    tester.test(&["LRANGE", "l", "0", "-1"]);
+
+    // LREM with positive, negative and zero counts
+    tester.test(&["LREM", "l", "1", "c"]);
+    tester.test(&["LREM", "l", "-1", "c"]);
+    tester.test(&["LREM", "l", "0", "c"]);
+    tester.test(&["LREM", "l", "0", "nope"]);
+    tester.test(&["LREM", "missing", "0", "a"]);
+    tester.test(&["LRANGE", "l", "0", "-1"]);
+
+    // LPOP/RPOP with a count
+    tester.test(&["LPOP", "l", "2"]);
+    tester.test(&["RPOP", "l", "2"]);
+    tester.test(&["LPOP", "l", "0"]);
+    tester.test(&["LPOP", "l", "-1"]);
+    tester.test(&["LPOP", "missing", "2"]);
+    tester.test(&["RPOP", "l", "100"]);
+    tester.test(&["EXISTS", "l"]);
+
+    // LMPOP: hash-tagged keys so they share a slot
+    tester.test(&["LMPOP", "2", "{q}a", "{q}b", "LEFT"]);
+    tester.test(&["RPUSH", "{q}b", "1", "2", "3"]);
+    tester.test(&["LMPOP", "2", "{q}a", "{q}b", "RIGHT"]);
+    tester.test(&["LMPOP", "2", "{q}a", "{q}b", "LEFT", "COUNT", "5"]);
+    tester.test(&["EXISTS", "{q}b"]);
+    tester.test(&["LMPOP", "0", "{q}a", "LEFT"]);
+    tester.test(&["LMPOP", "1", "{q}a", "UP"]);
+    tester.test(&["LMPOP", "1", "{q}a", "LEFT", "COUNT", "0"]);
+
+    // WRONGTYPE
+    tester.test(&["SET", "s", "v"]);
+    tester.test(&["LPOS", "s", "v"]);
+    tester.test(&["LPUSHX", "s", "v"]);
+    tester.test(&["LPOP", "s", "1"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some list commands differed");
+}
+
+/// Test INCRBYFLOAT, GETRANGE, SETRANGE, GETDEL, GETEX, MSETNX, SETEX and LCS
+#[test]
+#[ignore]
+fn test_string_commands_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== String Commands ===");
+
+    tester.cleanup();
+
+    // INCRBYFLOAT formatting
+    tester.test(&["SET", "f", "10.50"]);
+    tester.test(&["INCRBYFLOAT", "f", "0.1"]);
+    tester.test(&["INCRBYFLOAT", "f", "-5"]);
+    tester.test(&["SET", "f", "5.0e3"]);
+    tester.test(&["INCRBYFLOAT", "f", "2.0e2"]);
+    tester.test(&["INCRBYFLOAT", "f", "-5200"]);
+    tester.test(&["INCRBYFLOAT", "f", "1e-5"]);
+    tester.test(&["INCRBYFLOAT", "new", "3"]);
+    tester.test(&["INCRBYFLOAT", "f", "abc"]);
+    tester.test(&["SET", "s", "abc"]);
+    tester.test(&["INCRBYFLOAT", "s", "1"]);
+
+    // GETRANGE/SUBSTR
+    tester.test(&["SET", "s", "This is a string"]);
+    tester.test(&["GETRANGE", "s", "0", "3"]);
+    tester.test(&["GETRANGE", "s", "-3", "-1"]);
+    tester.test(&["GETRANGE", "s", "5", "3"]);
+    tester.test(&["SUBSTR", "s", "10", "100"]);
+    tester.test(&["GETRANGE", "missing", "0", "-1"]);
+
+    // SETRANGE pads with zero bytes and never creates a key for ""
+    tester.test(&["SETRANGE", "s", "10", "Redis"]);
+    tester.test(&["GET", "s"]);
+    tester.test(&["SETRANGE", "pad", "3", "ab"]);
+    tester.test(&["GET", "pad"]);
+    tester.test(&["SETRANGE", "none", "5", ""]);
+    tester.test(&["EXISTS", "none"]);
+    tester.test(&["SETRANGE", "s", "-1", "x"]);
+
+    // GETDEL and GETEX
+    tester.test(&["GETDEL", "pad"]);
+    tester.test(&["GETDEL", "pad"]);
+    tester.test(&["GETEX", "s", "EX", "100"]);
+    tester.test(&["TTL", "s"]);
+    tester.test(&["GETEX", "s"]);
+    tester.test(&["TTL", "s"]);
+    tester.test(&["GETEX", "s", "PERSIST"]);
+    tester.test(&["TTL", "s"]);
+    tester.test(&["GETEX", "s", "EXAT", "1"]);
+    tester.test(&["EXISTS", "s"]);
+    tester.test(&["GETEX", "missing", "EX", "10"]);
+    tester.test(&["GETEX", "s", "EX", "0"]);
+    tester.test(&["GETEX", "s", "EX", "10", "PERSIST"]);
+
+    // MSETNX is all or nothing
+    tester.test(&["MSETNX", "{m}a", "1", "{m}b", "2"]);
+    tester.test(&["MSETNX", "{m}b", "3", "{m}c", "4"]);
+    tester.test(&["MGET", "{m}a", "{m}b", "{m}c"]);
+
+    // SETEX/PSETEX edge cases
+    tester.test(&["SETEX", "e", "100", "v"]);
+    tester.test(&["TTL", "e"]);
+    tester.test(&["SETEX", "e", "0", "v"]);
+    tester.test(&["PSETEX", "e", "-1", "v"]);
+    tester.test(&["SETEX", "e", "ten", "v"]);
+
+    // LCS
+    tester.test(&["MSET", "{l}1", "ohmytext", "{l}2", "mynewtext"]);
+    tester.test(&["LCS", "{l}1", "{l}2"]);
+    tester.test(&["LCS", "{l}1", "{l}2", "LEN"]);
+    tester.test(&["LCS", "{l}1", "{l}2", "IDX"]);
+    tester.test(&[
+        "LCS",
+        "{l}1",
+        "{l}2",
+        "IDX",
+        "MINMATCHLEN",
+        "4",
+        "WITHMATCHLEN",
+    ]);
+    tester.test(&["LCS", "{l}1", "{l}missing"]);
+    tester.test(&["LCS", "{l}1", "{l}2", "LEN", "IDX"]);
+
+    // WRONGTYPE
+    tester.test(&["RPUSH", "list", "a"]);
+    tester.test(&["GETRANGE", "list", "0", "1"]);
+    tester.test(&["SETRANGE", "list", "0", "x"]);
+    tester.test(&["GETDEL", "list"]);
+    tester.test(&["INCRBYFLOAT", "list", "1"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some string commands differed");
+}
+
+/// Test RENAME, COPY, TOUCH, UNLINK, PEXPIRE flags, EXPIRETIME and OBJECT
+#[test]
+#[ignore]
+fn test_keyspace_commands_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== Keyspace Commands ===");
+
+    tester.cleanup();
+
+    // RENAME/RENAMENX keep the TTL; hash-tagged keys share a slot
+    tester.test(&["SET", "{k}a", "1", "EX", "100"]);
+    tester.test(&["SET", "{k}b", "2"]);
+    tester.test(&["RENAME", "{k}a", "{k}b"]);
+    tester.test(&["GET", "{k}b"]);
+    tester.test(&["TTL", "{k}b"]);
+    tester.test(&["EXISTS", "{k}a"]);
+    tester.test(&["RENAME", "{k}missing", "{k}b"]);
+    tester.test(&["RENAME", "{k}b", "{k}b"]);
+    tester.test(&["SET", "{k}c", "3"]);
+    tester.test(&["RENAMENX", "{k}c", "{k}b"]);
+    tester.test(&["RENAMENX", "{k}c", "{k}d"]);
+    tester.test(&["RENAMENX", "{k}d", "{k}d"]);
+
+    // COPY
+    tester.test(&["COPY", "{k}b", "{k}e"]);
+    tester.test(&["COPY", "{k}b", "{k}e"]);
+    tester.test(&["COPY", "{k}d", "{k}e", "REPLACE"]);
+    tester.test(&["GET", "{k}e"]);
+    tester.test(&["COPY", "{k}b", "{k}b"]);
+    tester.test(&["COPY", "{k}missing", "{k}e"]);
+    tester.test(&["COPY", "{k}b", "{k}e", "FORCE"]);
+
+    // PEXPIRE conditions, EXPIRETIME
+    tester.test(&["SET", "p", "v"]);
+    tester.test(&["EXPIRETIME", "p"]);
+    tester.test(&["PEXPIRETIME", "missing"]);
+    tester.test(&["PEXPIRE", "p", "50000", "XX"]);
+    tester.test(&["PEXPIRE", "p", "50000", "GT"]);
+    tester.test(&["PEXPIRE", "p", "50000", "NX"]);
+    tester.test(&["PEXPIRE", "p", "90000", "LT"]);
+    tester.test(&["PEXPIRE", "p", "90000", "XX", "GT"]);
+    tester.test(&["PEXPIRE", "p", "1", "NX", "XX"]);
+    tester.test(&["PEXPIRE", "p", "1", "GT", "LT"]);
+    tester.test(&["PEXPIRE", "p", "1", "FOO"]);
+    tester.test(&["PEXPIRE", "p", "9223372036854775807"]);
+    tester.test(&["PEXPIRE", "p", "-1"]);
+    tester.test(&["EXISTS", "p"]);
+
+    // TOUCH, UNLINK
+    tester.test(&["TOUCH", "{k}b", "{k}e", "{k}missing"]);
+    tester.test(&["UNLINK", "{k}b", "{k}missing"]);
+    tester.test(&["EXISTS", "{k}b"]);
+
+    // OBJECT
+    tester.test(&["SET", "o", "123"]);
+    tester.test(&["OBJECT", "ENCODING", "o"]);
+    tester.test(&["SET", "o", "hello"]);
+    tester.test(&["OBJECT", "ENCODING", "o"]);
+    tester.test(&["SADD", "os", "1", "2"]);
+    tester.test(&["OBJECT", "ENCODING", "os"]);
+    tester.test(&["SADD", "os", "a"]);
+    tester.test(&["OBJECT", "ENCODING", "os"]);
+    tester.test(&["RPUSH", "ol", "a"]);
+    tester.test(&["OBJECT", "ENCODING", "ol"]);
+    tester.test(&["HSET", "oh", "f", "v"]);
+    tester.test(&["OBJECT", "ENCODING", "oh"]);
+    tester.test(&["ZADD", "oz", "1", "a"]);
+    tester.test(&["OBJECT", "ENCODING", "oz"]);
+    tester.test(&["OBJECT", "ENCODING", "missing"]);
+    tester.test(&["OBJECT", "REFCOUNT", "o"]);
+    tester.test(&["OBJECT", "IDLETIME", "o"]);
+    tester.test(&["OBJECT", "FREQ", "o"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some keyspace commands differed");
+}
+
+/// Test SELECT, SWAPDB, MOVE and COPY DB
+#[test]
+#[ignore]
+fn test_database_commands_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== Database Commands ===");
+
+    tester.cleanup();
+
+    tester.test(&["SET", "k", "zero"]);
+    tester.test(&["SELECT", "1"]);
+    tester.test(&["FLUSHDB"]);
+    tester.test(&["GET", "k"]);
+    tester.test(&["SET", "k", "one", "EX", "100"]);
+    tester.test(&["DBSIZE"]);
+    tester.test(&["SELECT", "16"]);
+    tester.test(&["SELECT", "-1"]);
+    tester.test(&["SELECT", "x"]);
+
+    // MOVE keeps the TTL and never overwrites
+    tester.test(&["MOVE", "k", "2"]);
+    tester.test(&["MOVE", "k", "2"]);
+    tester.test(&["MOVE", "k", "1"]);
+    tester.test(&["MOVE", "k", "16"]);
+    tester.test(&["SELECT", "2"]);
+    tester.test(&["TTL", "k"]);
+    tester.test(&["MOVE", "k", "0"]);
+
+    // COPY DB
+    tester.test(&["COPY", "k", "k", "DB", "0"]);
+    tester.test(&["COPY", "k", "k", "DB", "0", "REPLACE"]);
+    tester.test(&["COPY", "k", "k"]);
+    tester.test(&["COPY", "k", "k", "DB"]);
+
+    // SWAPDB
+    tester.test(&["SWAPDB", "0", "2"]);
+    tester.test(&["GET", "k"]);
+    tester.test(&["SWAPDB", "0", "16"]);
+    tester.test(&["SWAPDB", "x", "0"]);
+    tester.test(&["FLUSHDB"]);
+    tester.test(&["SELECT", "0"]);
+    tester.test(&["GET", "k"]);
+
+    tester.report();
+    assert_eq!(tester.failed, 0, "Some database commands differed");
+}
+
+/// Test HMSET, HGETDEL, HGETEX and per-field expiration (Redis 7.4+)
+#[test]
+#[ignore]
+fn test_hash_field_expiry_equivalence() {
+    let mut tester =
+        DifferentialTester::new(6379, 3000).expect("Failed to connect to both servers");
+
+    println!("\n=== Hash Field Expiry ===");
+
+    tester.cleanup();
+
+    tester.test(&["HMSET", "h", "a", "1", "b", "2", "c", "3", "d", "4"]);
+    tester.test(&["HRANDFIELD", "missing"]);
+    tester.test(&["HRANDFIELD", "h", "0"]);
+
+    // HEXPIRE and its conditions; -2 for missing fields
+    tester.test(&["HEXPIRE", "h", "100", "FIELDS", "2", "a", "x"]);
+    tester.test(&["HEXPIRE", "h", "200", "NX", "FIELDS", "2", "a", "b"]);
+    tester.test(&["HEXPIRE", "h", "50", "GT", "FIELDS", "2", "a", "c"]);
+    tester.test(&["HEXPIRE", "h", "50", "LT", "FIELDS", "2", "a", "c"]);
+    tester.test(&["HEXPIRE", "missing", "10", "FIELDS", "1", "a"]);
+    tester.test(&["HTTL", "h", "FIELDS", "4", "a", "b", "d", "x"]);
+
+    // A deadline in the past deletes the field
+    tester.test(&["HPEXPIRE", "h", "0", "FIELDS", "1", "d"]);
+    tester.test(&["HEXPIREAT", "h", "1", "FIELDS", "1", "c"]);
+    tester.test(&["HLEN", "h"]);
+
+    // Writes clear the TTL, HPERSIST drops it
+    tester.test(&["HSET", "h", "a", "10"]);
+    tester.test(&["HTTL", "h", "FIELDS", "1", "a"]);
+    tester.test(&["HPERSIST", "h", "FIELDS", "3", "a", "b", "x"]);
+
+    // HGETEX and HGETDEL
+    tester.test(&["HGETEX", "h", "EX", "100", "FIELDS", "2", "a", "x"]);
+    tester.test(&["HTTL", "h", "FIELDS", "1", "a"]);
+    tester.test(&["HGETEX", "h", "PERSIST", "FIELDS", "1", "a"]);
+    tester.test(&["HGETDEL", "h", "FIELDS", "2", "a", "b"]);
+    tester.test(&["EXISTS", "h"]);
+
+    // Errors
+    tester.test(&["HEXPIRE", "h", "10", "FIELDS", "2", "a"]);
+    tester.test(&["HEXPIRE", "h", "-1", "FIELDS", "1", "a"]);
+
+    tester.report();
+    assert_eq!(
+        tester.failed, 0,
+        "Some hash field expiry operations differed"
+    );
+}
+
 /// Test set operations
 #[test]
 #[ignore]
@@ -575,6 +1351,39 @@ fn test_set_equivalence() {
         }
     }
 
+    // STORE forms, compared by cardinality and membership
+    tester.test(&["SUNIONSTORE", "dest", "set1", "set2", "missing"]);
+    tester.test(&["SCARD", "dest"]);
+    tester.test(&["SINTERSTORE", "dest", "set1", "set2"]);
+    tester.test(&["SMISMEMBER", "dest", "a", "b", "c", "d"]);
+    tester.test(&["SDIFFSTORE", "dest", "set1", "set1"]);
+    tester.test(&["EXISTS", "dest"]);
+    tester.test(&["SINTERCARD", "2", "set1", "set2"]);
+    tester.test(&["SINTERCARD", "2", "set1", "set2", "LIMIT", "1"]);
+    tester.test(&["SINTERCARD", "2", "set1", "missing"]);
+
+    // SMOVE
+    tester.test(&["SMOVE", "set1", "set2", "a"]);
+    tester.test(&["SMOVE", "set1", "set2", "a"]);
+    tester.test(&["SISMEMBER", "set2", "a"]);
+    tester.test(&["SMOVE", "missing", "set2", "a"]);
+
+    // SRANDMEMBER with deterministic replies
+    tester.test(&["SRANDMEMBER", "missing"]);
+    tester.test(&["SRANDMEMBER", "missing", "-3"]);
+    tester.test(&["SRANDMEMBER", "set1", "0"]);
+    tester.setup(&["SADD", "single", "only"]);
+    tester.test(&["SRANDMEMBER", "single", "-3"]);
+    tester.test(&["SSCAN", "single", "0"]);
+
+    // Errors
+    tester.setup(&["SET", "str", "x"]);
+    tester.test(&["SINTER", "set1", "str"]);
+    tester.test(&["SMOVE", "set1", "str", "b"]);
+    tester.test(&["SINTERCARD", "0", "set1"]);
+    tester.test(&["SINTERCARD", "1", "set1", "LIMIT", "-1"]);
+    tester.test(&["SRANDMEMBER", "set1", "x"]);
+
     tester.report();
     assert_eq!(tester.failed, 0, "Some set operations differed");
 }
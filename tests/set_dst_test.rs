@@ -50,6 +50,19 @@ fn test_set_dst_100_seeds_large_members() {
     assert_eq!(passed, 100, "All 100 seeds should pass with large members");
 }
 
+#[test]
+fn test_set_dst_100_seeds_algebra_heavy() {
+    let results = run_set_batch(4000, 100, 500, SetDSTConfig::algebra_heavy);
+    let summary = summarize_set_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(
+        passed, 100,
+        "All 100 seeds should pass with moves and set algebra"
+    );
+}
+
 // =============================================================================
 // Stress Tests
 // =============================================================================
@@ -100,6 +113,7 @@ fn test_set_dst_high_remove_rate() {
         seed: 77777,
         num_members: 30,
         remove_prob: 0.6,
+        algebra_prob: 0.1,
     };
 
     let mut harness = SetDSTHarness::new(config);
@@ -116,6 +130,7 @@ fn test_set_dst_tiny_member_space() {
         seed: 88888,
         num_members: 3, // Only 3 members!
         remove_prob: 0.3,
+        algebra_prob: 0.1,
     };
 
     let mut harness = SetDSTHarness::new(config);
@@ -132,6 +147,7 @@ fn test_set_dst_mostly_adds() {
         seed: 11111,
         num_members: 200,
         remove_prob: 0.05,
+        algebra_prob: 0.1,
     };
 
     let mut harness = SetDSTHarness::new(config);
@@ -148,6 +164,7 @@ fn test_set_dst_balanced() {
         seed: 22222,
         num_members: 50,
         remove_prob: 0.5,
+        algebra_prob: 0.1,
     };
 
     let mut harness = SetDSTHarness::new(config);
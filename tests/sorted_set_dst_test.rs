@@ -107,6 +107,8 @@ fn test_sorted_set_dst_high_remove_rate() {
         update_prob: 0.2,
         remove_prob: 0.4, // 40% removes
         max_score: 100.0,
+        range_prob: 0.1,
+        incr_prob: 0.1,
     };
 
     let mut harness = SortedSetDSTHarness::new(config);
@@ -128,6 +130,8 @@ fn test_sorted_set_dst_tiny_keyspace() {
         update_prob: 0.5,
         remove_prob: 0.3,
         max_score: 10.0,
+        range_prob: 0.1,
+        incr_prob: 0.1,
     };
 
     let mut harness = SortedSetDSTHarness::new(config);
@@ -149,6 +153,8 @@ fn test_sorted_set_dst_score_precision() {
         update_prob: 0.4,
         remove_prob: 0.1,
         max_score: 1.0, // Scores between 0.00 and 1.00
+        range_prob: 0.1,
+        incr_prob: 0.1,
     };
 
     let mut harness = SortedSetDSTHarness::new(config);
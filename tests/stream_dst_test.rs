@@ -0,0 +1,202 @@
+//! Stream Deterministic Simulation Tests
+//!
+//! VOPR-style tests for RedisStream and consumer groups with multiple seeds.
+
+use redis_sim::redis::{
+    run_stream_batch, summarize_stream_batch, StreamDSTConfig, StreamDSTHarness,
+};
+
+// =============================================================================
+// Standard Configuration Tests - 100+ Seeds
+// =============================================================================
+
+#[test]
+fn test_stream_dst_100_seeds_standard() {
+    let results = run_stream_batch(0, 100, 500, StreamDSTConfig::new);
+    let summary = summarize_stream_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(
+        passed, 100,
+        "All 100 seeds should pass with standard config"
+    );
+}
+
+#[test]
+fn test_stream_dst_100_seeds_consumer_heavy() {
+    let results = run_stream_batch(1000, 100, 500, StreamDSTConfig::consumer_heavy);
+    let summary = summarize_stream_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(passed, 100, "All 100 seeds should pass with consumer heavy");
+}
+
+#[test]
+fn test_stream_dst_100_seeds_high_churn() {
+    let results = run_stream_batch(2000, 100, 500, StreamDSTConfig::high_churn);
+    let summary = summarize_stream_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(passed, 100, "All 100 seeds should pass with high churn");
+}
+
+// =============================================================================
+// Stress Tests
+// =============================================================================
+
+#[test]
+fn test_stream_dst_stress_2000_ops() {
+    let mut harness = StreamDSTHarness::with_seed(42);
+    harness.run(2000);
+    let result = harness.result();
+    println!("Stress 2000 ops: {}", result.summary());
+    assert!(result.is_success(), "2000 ops should maintain invariants");
+}
+
+#[test]
+fn test_stream_dst_stress_consumer_heavy_2000_ops() {
+    let config = StreamDSTConfig::consumer_heavy(99999);
+    let mut harness = StreamDSTHarness::new(config);
+    harness.run(2000);
+    let result = harness.result();
+    println!(
+        "Stress consumer heavy 2000 ops: {} (acks:{}, claims:{})",
+        result.summary(),
+        result.acks,
+        result.claims
+    );
+    assert!(
+        result.is_success(),
+        "Consumer heavy stress should maintain invariants"
+    );
+}
+
+// =============================================================================
+// Edge Case Tests
+// =============================================================================
+
+#[test]
+fn test_stream_dst_constant_clock_skew() {
+    // Clock jumps backwards on most adds; IDs must stay monotonic
+    let config = StreamDSTConfig {
+        seed: 77777,
+        clock_skew_prob: 0.8,
+        ..StreamDSTConfig::default()
+    };
+
+    let mut harness = StreamDSTHarness::new(config);
+    harness.run(1000);
+    let result = harness.result();
+    println!("Constant clock skew: {}", result.summary());
+    assert!(result.is_success(), "Clock skew should maintain invariants");
+}
+
+#[test]
+fn test_stream_dst_deletes_under_pending() {
+    // Deletes and trims racing with unacked deliveries leave dangling PEL entries
+    let config = StreamDSTConfig {
+        seed: 88888,
+        num_groups: 2,
+        num_consumers: 2,
+        del_prob: 0.2,
+        trim_prob: 0.1,
+        read_prob: 0.3,
+        ack_prob: 0.0,
+        claim_prob: 0.2,
+        clock_skew_prob: 0.0,
+    };
+
+    let mut harness = StreamDSTHarness::new(config);
+    harness.run(1000);
+    let result = harness.result();
+    println!(
+        "Deletes under pending: {} (claims: {})",
+        result.summary(),
+        result.claims
+    );
+    assert!(
+        result.is_success(),
+        "Dangling PEL entries should be handled"
+    );
+}
+
+#[test]
+fn test_stream_dst_no_groups() {
+    let config = StreamDSTConfig {
+        seed: 11111,
+        num_groups: 0,
+        ..StreamDSTConfig::default()
+    };
+
+    let mut harness = StreamDSTHarness::new(config);
+    harness.run(500);
+    let result = harness.result();
+    println!("No groups: {}", result.summary());
+    assert!(
+        result.is_success(),
+        "Plain stream ops should maintain invariants"
+    );
+    assert_eq!(harness.stream().groups_len(), 0);
+}
+
+// =============================================================================
+// Mixed Configuration Tests
+// =============================================================================
+
+#[test]
+fn test_stream_dst_50_seeds_mixed_configs() {
+    let mut failures = Vec::new();
+
+    for seed in 0..50 {
+        let config = match seed % 3 {
+            0 => StreamDSTConfig::new(seed),
+            1 => StreamDSTConfig::consumer_heavy(seed),
+            _ => StreamDSTConfig::high_churn(seed),
+        };
+
+        let mut harness = StreamDSTHarness::new(config);
+        harness.run(500);
+        let result = harness.result();
+
+        if !result.is_success() {
+            failures.push(result.clone());
+        }
+    }
+
+    for f in &failures {
+        println!("FAILED: {}", f.summary());
+        for v in &f.invariant_violations {
+            println!("  {}", v);
+        }
+    }
+
+    assert!(failures.is_empty(), "{} seeds failed", failures.len());
+}
+
+// =============================================================================
+// Longer Tests (ignored by default)
+// =============================================================================
+
+#[test]
+#[ignore]
+fn test_stream_dst_500_seeds() {
+    let results = run_stream_batch(0, 500, 500, StreamDSTConfig::new);
+    let summary = summarize_stream_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(passed, 500, "All 500 seeds should pass");
+}
+
+#[test]
+#[ignore]
+fn test_stream_dst_stress_10000_ops() {
+    let mut harness = StreamDSTHarness::with_seed(31415);
+    harness.run(10000);
+    let result = harness.result();
+    println!("Stress 10000 ops: {}", result.summary());
+    assert!(result.is_success(), "10000 ops should maintain invariants");
+}
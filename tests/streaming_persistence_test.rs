@@ -105,11 +105,11 @@ async fn test_streaming_persistence_basic_flow() {
     // Verify delta contents
     let keys: Vec<_> = deltas
         .iter()
-        .map(|d| d.as_ref().unwrap().key.as_str())
+        .map(|d| d.as_ref().unwrap().key.as_bytes())
         .collect();
-    assert!(keys.contains(&"key1"), "Should contain key1");
-    assert!(keys.contains(&"key2"), "Should contain key2");
-    assert!(keys.contains(&"key3"), "Should contain key3");
+    assert!(keys.contains(&b"key1".as_slice()), "Should contain key1");
+    assert!(keys.contains(&b"key2".as_slice()), "Should contain key2");
+    assert!(keys.contains(&b"key3".as_slice()), "Should contain key3");
 }
 
 /// Test persistence with local filesystem store
@@ -277,8 +277,8 @@ async fn test_streaming_persistence_state_recovery() {
         }
     }
 
-    assert!(recovered_keys.contains(&"persistent_key".to_string()));
-    assert!(recovered_keys.contains(&"another_key".to_string()));
+    assert!(recovered_keys.contains(&SDS::from("persistent_key")));
+    assert!(recovered_keys.contains(&SDS::from("another_key")));
 }
 
 /// Test multiple replicas writing to separate prefixes
@@ -349,7 +349,11 @@ async fn test_streaming_persistence_multi_replica() {
             replica_id
         );
         let key_prefix = format!("r{}k", replica_id);
-        assert!(deltas[0].as_ref().unwrap().key.starts_with(&key_prefix));
+        assert!(deltas[0]
+            .as_ref()
+            .unwrap()
+            .key
+            .starts_with(key_prefix.as_bytes()));
     }
 }
 
@@ -501,9 +505,15 @@ async fn test_streaming_persistence_deletes() {
         "Should have at least set and delete deltas"
     );
 
-    let keys: Vec<_> = deltas.iter().map(|d| d.key.as_str()).collect();
-    assert!(keys.contains(&"to_delete"), "Should have to_delete key");
-    assert!(keys.contains(&"to_keep"), "Should have to_keep key");
+    let keys: Vec<_> = deltas.iter().map(|d| d.key.as_bytes()).collect();
+    assert!(
+        keys.contains(&b"to_delete".as_slice()),
+        "Should have to_delete key"
+    );
+    assert!(
+        keys.contains(&b"to_keep".as_slice()),
+        "Should have to_keep key"
+    );
 }
 
 /// Test concurrent writes and flushes
@@ -541,7 +551,7 @@ async fn test_streaming_persistence_concurrent_writes() {
             replica_id,
         };
         let replicated = ReplicatedValue::with_value(SDS::from_str(value), clock);
-        ReplicationDelta::new(key.to_string(), replicated, replica_id)
+        ReplicationDelta::new(SDS::from(key), replicated, replica_id)
     }
 
     // Spawn concurrent writers
@@ -0,0 +1,81 @@
+//! Transaction Deterministic Simulation Tests
+//!
+//! VOPR-style tests for per-connection MULTI/EXEC/WATCH with multiple seeds.
+
+use redis_sim::production::{
+    run_transaction_batch, summarize_transaction_batch, TransactionDSTConfig, TransactionDSTHarness,
+};
+
+// =============================================================================
+// Standard Configuration Tests - 100+ Seeds
+// =============================================================================
+
+#[test]
+fn test_transaction_dst_100_seeds_standard() {
+    let results = run_transaction_batch(0, 100, 300, TransactionDSTConfig::new);
+    let summary = summarize_transaction_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(
+        passed, 100,
+        "All 100 seeds should pass with standard config"
+    );
+}
+
+#[test]
+fn test_transaction_dst_100_seeds_high_contention() {
+    let results = run_transaction_batch(1000, 100, 300, TransactionDSTConfig::high_contention);
+    let summary = summarize_transaction_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(
+        passed, 100,
+        "All 100 seeds should pass with high contention"
+    );
+
+    let aborts: u64 = results.iter().map(|r| r.aborts).sum();
+    assert!(
+        aborts > 0,
+        "Contended WATCHes should abort some transactions"
+    );
+}
+
+#[test]
+fn test_transaction_dst_100_seeds_single_shard() {
+    let results = run_transaction_batch(2000, 100, 300, TransactionDSTConfig::single_shard);
+    let summary = summarize_transaction_batch(&results);
+    println!("{}", summary);
+
+    let passed = results.iter().filter(|r| r.is_success()).count();
+    assert_eq!(passed, 100, "All 100 seeds should pass on a single shard");
+}
+
+// =============================================================================
+// Stress Tests
+// =============================================================================
+
+#[test]
+fn test_transaction_dst_stress_2000_ops() {
+    let mut harness = TransactionDSTHarness::with_seed(42);
+    harness.run(2000);
+    let result = harness.result();
+    println!("Stress 2000 ops: {}", result.summary());
+    assert!(result.is_success(), "2000 ops should maintain invariants");
+    assert!(
+        result.concurrent_execs > 0,
+        "Some EXECs should race an audit"
+    );
+}
+
+#[test]
+fn test_transaction_dst_reproducible() {
+    let run = || {
+        let mut harness = TransactionDSTHarness::with_seed(777);
+        harness.run(500);
+        let r = harness.result().clone();
+        (r.commits, r.aborts, r.discards, r.plain_writes, r.audits)
+    };
+    assert_eq!(run(), run(), "Same seed must produce the same run");
+}